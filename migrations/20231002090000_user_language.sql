@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE discord_users ADD COLUMN language TEXT;
@@ -0,0 +1,31 @@
+-- Add migration script here
+CREATE TABLE
+    public.bot_roles (
+        discord_id bigint NOT NULL,
+        guild_id bigint NOT NULL,
+        role TEXT NOT NULL,
+        granted_by bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (discord_id, guild_id)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.bot_roles FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE
+    public.audit_log (
+        id bigserial PRIMARY KEY,
+        guild_id bigint NOT NULL,
+        actor bigint NOT NULL,
+        action TEXT NOT NULL,
+        target TEXT,
+        details TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX audit_log_guild_id_created_at_idx ON public.audit_log (guild_id, created_at DESC);
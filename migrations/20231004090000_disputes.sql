@@ -0,0 +1,28 @@
+-- Add migration script here
+-- A dispute freezes the amount of a single tip (one recipient of a tip event) until a moderator resolves it.
+CREATE TABLE
+    public.disputes (
+        id bigserial PRIMARY KEY,
+        tip_uuid TEXT NOT NULL,
+        guild_id bigint NOT NULL,
+        opened_by bigint NOT NULL,
+        tipper bigint NOT NULL,
+        recipient bigint NOT NULL,
+        amount bigint NOT NULL,
+        reason TEXT NOT NULL,
+        thread_id bigint,
+        -- open / released / refunded
+        status TEXT NOT NULL,
+        resolved_by bigint,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        CONSTRAINT disputes_tip_uuid_recipient_key UNIQUE (tip_uuid, recipient),
+        CONSTRAINT non_negative_amount CHECK (amount >= 0)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.disputes FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- The outcome of a reactdrop, used for giveaway statistics. Reactdrops from before this migration have no results.
+ALTER TABLE reactdrops ADD COLUMN guild_id bigint;
+-- number of users that reacted and got a share of the pot
+ALTER TABLE reactdrops ADD COLUMN participants integer;
+-- number of members in the guild when the reactdrop finished
+ALTER TABLE reactdrops ADD COLUMN eligible integer;
+-- uuid of the tip event that paid out the reactdrop
+ALTER TABLE reactdrops ADD COLUMN tip_uuid TEXT;
@@ -0,0 +1,26 @@
+-- Add migration script here
+-- Every movement of funds in or out of the treasury of a guild. The balance of a treasury is the sum of its inflows
+-- minus the sum of its outflows.
+CREATE TABLE
+    public.treasury_ledger (
+        id bigserial PRIMARY KEY,
+        guild_id bigint NOT NULL,
+        -- inflows: fee / donation, outflows: award / match / scheduled_drop
+        kind TEXT NOT NULL,
+        amount bigint NOT NULL,
+        -- the user that funds came from or went to
+        counterparty bigint,
+        memo TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        CONSTRAINT non_negative_amount CHECK (amount >= 0)
+    ) TABLESPACE pg_default;
+
+CREATE INDEX treasury_ledger_guild_id_created_at_idx ON public.treasury_ledger (guild_id, created_at);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.treasury_ledger FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,52 @@
+-- Add migration script here
+CREATE TABLE
+    public.guild_settings (
+        guild_id bigint NOT NULL PRIMARY KEY,
+        announcement_channel_id bigint,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.guild_settings FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE
+    public.announcements (
+        id bigserial PRIMARY KEY,
+        author bigint NOT NULL,
+        message TEXT NOT NULL,
+        -- dm / channel
+        target TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.announcements FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE
+    public.announcement_deliveries (
+        announcement_id bigint NOT NULL REFERENCES public.announcements (id),
+        -- a user id for DMs, a channel id for channel announcements
+        recipient bigint NOT NULL,
+        delivered BOOLEAN NOT NULL,
+        error TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (announcement_id, recipient)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.announcement_deliveries FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
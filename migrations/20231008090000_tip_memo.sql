@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE public.tips_vrsc ADD COLUMN memo TEXT;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE public.guild_settings ADD COLUMN fallback_channel_id bigint;
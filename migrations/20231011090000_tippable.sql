@@ -0,0 +1,3 @@
+-- Add migration script here
+-- users that opted out of group tips (role tips and soaks)
+ALTER TABLE discord_users ADD COLUMN tippable BOOLEAN NOT NULL DEFAULT TRUE;
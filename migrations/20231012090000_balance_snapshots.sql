@@ -0,0 +1,18 @@
+-- Add migration script here
+-- A copy of every balance, taken once a day.
+CREATE TABLE
+    public.balance_snapshots (
+        discord_id bigint NOT NULL,
+        balance bigint NOT NULL,
+        snapshot_date DATE NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (discord_id, snapshot_date)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.balance_snapshots FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
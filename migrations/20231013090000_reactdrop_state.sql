@@ -0,0 +1,6 @@
+-- Add migration script here
+-- reactdrops are pending until they are processed (paid out) or cancelled. A reactdrop that was ended or cancelled
+-- before its finish time has the user that did so in ended_by.
+ALTER TABLE reactdrops ADD COLUMN ended_by bigint;
+ALTER TABLE reactdrops
+    ADD CONSTRAINT reactdrops_status_check CHECK (status IN ('pending', 'processed', 'cancelled'));
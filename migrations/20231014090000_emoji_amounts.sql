@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE
+    public.guild_emoji_amounts (
+        guild_id bigint NOT NULL,
+        -- the emoji as it is rendered by Discord: a unicode emoji or <:name:id>
+        emoji TEXT NOT NULL,
+        amount bigint NOT NULL CHECK (amount > 0),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (guild_id, emoji)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.guild_emoji_amounts FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
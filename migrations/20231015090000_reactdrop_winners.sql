@@ -0,0 +1,3 @@
+-- Add migration script here
+-- a reactdrop with winners is a raffle: that many random participants split the pot instead of all of them.
+ALTER TABLE reactdrops ADD COLUMN winners integer CHECK (winners > 0);
@@ -0,0 +1,21 @@
+-- Add migration script here
+CREATE TABLE
+    public.currency_snapshots (
+        id bigserial PRIMARY KEY,
+        -- the currency as it is configured in basket_currencies
+        currency TEXT NOT NULL,
+        supply bigint NOT NULL,
+        sampled_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX currency_snapshots_currency_sampled_at ON public.currency_snapshots (currency, sampled_at);
+
+CREATE TABLE
+    public.currency_snapshot_reserves (
+        snapshot_id bigint NOT NULL REFERENCES public.currency_snapshots (id) ON DELETE CASCADE,
+        -- the i-address of the reserve currency
+        reserve_currency_id TEXT NOT NULL,
+        reserves bigint NOT NULL,
+        price_in_reserve bigint NOT NULL,
+        PRIMARY KEY (snapshot_id, reserve_currency_id)
+    ) TABLESPACE pg_default;
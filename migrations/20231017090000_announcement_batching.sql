@@ -0,0 +1,4 @@
+-- Add migration script here
+-- NULL means the default of the bot is used.
+ALTER TABLE public.guild_settings ADD COLUMN batch_threshold integer CHECK (batch_threshold >= 0);
+ALTER TABLE public.guild_settings ADD COLUMN batch_window_seconds integer CHECK (batch_window_seconds > 0);
@@ -0,0 +1,20 @@
+-- Add migration script here
+-- A tip event (all tips with the same uuid) can be reversed once by an admin. Recipients that no longer have their
+-- share are skipped, the amounts that could not be recovered are kept in `unrecovered`.
+CREATE TABLE
+    public.tip_reversals (
+        tip_uuid TEXT NOT NULL PRIMARY KEY,
+        reversed_by bigint NOT NULL,
+        tipper bigint NOT NULL,
+        recovered bigint NOT NULL,
+        unrecovered bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.tip_reversals FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
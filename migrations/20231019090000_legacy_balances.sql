@@ -0,0 +1,19 @@
+-- Add migration script here
+-- Balances imported from a legacy tipbot. A balance is credited to the user once, when they claim it.
+CREATE TABLE
+    public.legacy_balances (
+        discord_id bigint NOT NULL PRIMARY KEY,
+        amount bigint NOT NULL CHECK (amount > 0),
+        -- the name of the tipbot the balance was imported from
+        source TEXT NOT NULL,
+        claimed_at TIMESTAMPTZ,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.legacy_balances FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- Guilds that receive the network digest, see /config announce-blocks.
+CREATE TABLE
+    public.network_subscriptions (
+        guild_id bigint NOT NULL PRIMARY KEY,
+        channel_id bigint NOT NULL,
+        -- transactions that move at least this amount (in sats) are listed in the digest
+        large_tx_threshold bigint NOT NULL CHECK (large_tx_threshold > 0),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.network_subscriptions FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
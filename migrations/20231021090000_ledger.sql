@@ -0,0 +1,44 @@
+-- Add migration script here
+-- The double-entry ledger: the entries of an event always sum to zero, and the balance of a user in balance_vrsc is the
+-- sum of the entries of their account (`user:<discord_id>`). Other accounts are `deposits`, `withdrawals`,
+-- `network_fees`, `escrow`, `treasury:<guild_id>`, `legacy` and `opening`.
+CREATE TABLE
+    public.ledger_entries (
+        id bigserial PRIMARY KEY,
+        event_id TEXT NOT NULL,
+        -- tip / deposit / withdrawal / dispute / reversal / donation / legacy_claim / opening
+        kind TEXT NOT NULL,
+        account TEXT NOT NULL,
+        amount bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX ledger_entries_event_id_idx ON public.ledger_entries (event_id);
+CREATE INDEX ledger_entries_account_idx ON public.ledger_entries (account);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.ledger_entries FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- everything that existed before the ledger is opened against the `opening` account
+INSERT INTO ledger_entries (event_id, kind, account, amount)
+SELECT 'opening', 'opening', 'user:' || discord_id, balance
+FROM balance_vrsc WHERE discord_id IS NOT NULL AND balance <> 0;
+
+INSERT INTO ledger_entries (event_id, kind, account, amount)
+SELECT 'opening', 'opening', 'escrow', SUM(amount)
+FROM disputes WHERE status = 'open' HAVING SUM(amount) <> 0;
+
+INSERT INTO ledger_entries (event_id, kind, account, amount)
+SELECT 'opening', 'opening', 'treasury:' || guild_id,
+    SUM(CASE WHEN kind IN ('fee', 'donation') THEN amount ELSE -amount END)
+FROM treasury_ledger GROUP BY guild_id
+HAVING SUM(CASE WHEN kind IN ('fee', 'donation') THEN amount ELSE -amount END) <> 0;
+
+INSERT INTO ledger_entries (event_id, kind, account, amount)
+SELECT 'opening', 'opening', 'opening', -SUM(amount)
+FROM ledger_entries WHERE event_id = 'opening' HAVING SUM(amount) <> 0;
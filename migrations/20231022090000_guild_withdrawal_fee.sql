@@ -0,0 +1,10 @@
+-- Add migration script here
+-- A guild can override the bot withdrawal fee of the settings with either a flat fee (in sats) or a percentage.
+-- When both are NULL the fee of the settings is used.
+ALTER TABLE public.guild_settings ADD COLUMN withdrawal_fee_sats bigint CHECK (withdrawal_fee_sats >= 0);
+ALTER TABLE public.guild_settings
+    ADD COLUMN withdrawal_fee_percentage double precision
+    CHECK (withdrawal_fee_percentage >= 0 AND withdrawal_fee_percentage <= 100);
+ALTER TABLE public.guild_settings
+    ADD CONSTRAINT guild_settings_one_withdrawal_fee_check
+    CHECK (withdrawal_fee_sats IS NULL OR withdrawal_fee_percentage IS NULL);
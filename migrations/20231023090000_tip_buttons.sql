@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Whether quick tip buttons are put under tip announcements in a guild.
+ALTER TABLE public.guild_settings ADD COLUMN tip_buttons boolean NOT NULL DEFAULT false;
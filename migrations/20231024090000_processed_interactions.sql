@@ -0,0 +1,10 @@
+-- Add migration script here
+-- Interactions that moved funds. A tip or withdrawal claims its interaction id in here before it moves anything, so an
+-- interaction that Discord delivers twice, or that is retried after a restart, can never debit a user twice.
+CREATE TABLE
+    public.processed_interactions (
+        interaction_id bigint PRIMARY KEY,
+        -- tip / withdrawal
+        operation TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
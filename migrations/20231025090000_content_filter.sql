@@ -0,0 +1,21 @@
+-- Add migration script here
+-- Words a guild blocked in the notes of tips, on top of the default list of the bot.
+CREATE TABLE
+    public.guild_filter_words (
+        guild_id bigint NOT NULL,
+        -- normalized, see `content_filter::normalize`
+        word TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (guild_id, word)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.guild_filter_words FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- Whether the default list of blocked words applies in a guild.
+ALTER TABLE public.guild_settings ADD COLUMN default_filter boolean NOT NULL DEFAULT true;
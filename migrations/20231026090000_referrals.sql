@@ -0,0 +1,58 @@
+-- Add migration script here
+-- The referral code of a user, made with /referral link.
+CREATE TABLE
+    public.referral_codes (
+        discord_id bigint PRIMARY KEY,
+        code TEXT NOT NULL UNIQUE,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.referral_codes FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- A user can be referred once. Both users are rewarded from the `referrals` ledger account after the first tip of the
+-- referee.
+CREATE TABLE
+    public.referrals (
+        referee bigint PRIMARY KEY,
+        referrer bigint NOT NULL CHECK (referrer <> referee),
+        -- the rewards that were paid, NULL until they are
+        referrer_reward bigint,
+        referee_reward bigint,
+        rewarded_at TIMESTAMPTZ,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX referrals_referrer_idx ON public.referrals (referrer);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.referrals FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- The rewards of a referral, set by the bot admins. A reward of 0 is not paid, so the program is off by default.
+CREATE TABLE
+    public.referral_settings (
+        id boolean PRIMARY KEY DEFAULT true CHECK (id),
+        referrer_reward bigint NOT NULL DEFAULT 0 CHECK (referrer_reward >= 0),
+        referee_reward bigint NOT NULL DEFAULT 0 CHECK (referee_reward >= 0),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.referral_settings FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+INSERT INTO referral_settings DEFAULT VALUES;
@@ -0,0 +1,22 @@
+-- Add migration script here
+-- The questions of the trivia game of a guild, managed with /trivia add and /trivia remove. `answer` can hold multiple
+-- accepted answers, separated by `|`.
+CREATE TABLE
+    public.trivia_questions (
+        id bigserial PRIMARY KEY,
+        guild_id bigint NOT NULL,
+        question TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        created_by bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX trivia_questions_guild_id_idx ON public.trivia_questions (guild_id);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.trivia_questions FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,31 @@
+-- Add migration script here
+-- Messages about balance changes that were not delivered yet. A message is deleted once it is delivered, and retried
+-- with backoff until then.
+CREATE TABLE
+    public.outbox (
+        id bigserial PRIMARY KEY,
+        -- 'channel' or 'dm'
+        kind TEXT NOT NULL,
+        channel_id bigint,
+        guild_id bigint,
+        -- the recipient of a DM, or the user that gets a channel message in DM when the channel can't be used
+        user_id bigint NOT NULL,
+        content TEXT NOT NULL,
+        -- the target of the quick tip buttons of the message, if it has them
+        button_user bigint,
+        button_event uuid,
+        attempts integer NOT NULL DEFAULT 0,
+        last_error TEXT,
+        next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX outbox_next_attempt_at_idx ON public.outbox (next_attempt_at);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.outbox FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
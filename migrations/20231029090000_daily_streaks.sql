@@ -0,0 +1,46 @@
+-- Add migration script here
+-- The reward of the first day of a daily streak in a guild, in sats. 0 means /daily is off.
+ALTER TABLE public.guild_settings ADD COLUMN daily_reward bigint NOT NULL DEFAULT 0 CHECK (daily_reward >= 0);
+
+-- The daily streak of a user in a guild. The time of the last claim is set by the database, so a user can't claim
+-- before their cooldown is over.
+CREATE TABLE
+    public.daily_streaks (
+        guild_id bigint NOT NULL,
+        discord_id bigint NOT NULL,
+        streak integer NOT NULL,
+        best_streak integer NOT NULL,
+        last_claim_at TIMESTAMPTZ NOT NULL,
+        total_claimed bigint NOT NULL DEFAULT 0,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (guild_id, discord_id)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.daily_streaks FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- Every claimed daily reward. The rewards are paid from the `daily:<guild_id>` ledger account.
+CREATE TABLE
+    public.daily_claims (
+        id bigserial PRIMARY KEY,
+        guild_id bigint NOT NULL,
+        discord_id bigint NOT NULL,
+        streak integer NOT NULL,
+        amount bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX daily_claims_guild_id_discord_id_idx ON public.daily_claims (guild_id, discord_id);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.daily_claims FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
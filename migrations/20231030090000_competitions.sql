@@ -0,0 +1,51 @@
+-- Add migration script here
+-- A tipping competition in a guild, won by the member with the best score at `ends_at`. The prize is paid from the
+-- treasury of the guild. A guild has at most one running competition.
+CREATE TABLE
+    public.competitions (
+        id bigserial PRIMARY KEY,
+        guild_id bigint NOT NULL,
+        channel_id bigint NOT NULL,
+        started_by bigint NOT NULL,
+        -- 'unique_recipients' or 'amount'
+        metric TEXT NOT NULL,
+        prize bigint NOT NULL CHECK (prize > 0),
+        -- tips with a smaller share per recipient don't count
+        min_tip bigint NOT NULL DEFAULT 0,
+        ends_at TIMESTAMPTZ NOT NULL,
+        -- 'running', 'finished' or 'cancelled'
+        status TEXT NOT NULL DEFAULT 'running',
+        winner bigint,
+        prize_paid boolean NOT NULL DEFAULT false,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE UNIQUE INDEX competitions_running_guild_id_idx ON public.competitions (guild_id) WHERE status = 'running';
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.competitions FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- The tips that count for a competition, one row per recipient.
+CREATE TABLE
+    public.competition_tips (
+        competition_id bigint NOT NULL REFERENCES public.competitions (id),
+        tip_uuid TEXT NOT NULL,
+        tipper bigint NOT NULL,
+        recipient bigint NOT NULL,
+        amount bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (competition_id, tip_uuid, recipient)
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.competition_tips FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
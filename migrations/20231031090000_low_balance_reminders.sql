@@ -0,0 +1,3 @@
+-- Add migration script here
+-- users that want a DM when their balance drops below this amount, in sats. NULL means no reminders.
+ALTER TABLE discord_users ADD COLUMN low_balance_threshold BIGINT;
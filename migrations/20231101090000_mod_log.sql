@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE public.guild_settings ADD COLUMN mod_log_channel_id bigint;
+-- tips of at least this amount, in sats, are posted in the mod-log. NULL means the default of the bot.
+ALTER TABLE public.guild_settings ADD COLUMN mod_log_tip_threshold bigint;
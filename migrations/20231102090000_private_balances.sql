@@ -0,0 +1,3 @@
+-- Add migration script here
+-- private users don't have the amounts of their tips shown in channels, and others can't look up their balance.
+ALTER TABLE discord_users ADD COLUMN private BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,27 @@
+-- Add migration script here
+-- Guilds that receive the news feeds, see /config news.
+CREATE TABLE
+    public.news_subscriptions (
+        guild_id bigint NOT NULL PRIMARY KEY,
+        channel_id bigint NOT NULL,
+        -- the topics of the feeds the guild wants, empty for all of them
+        topics TEXT[] NOT NULL DEFAULT '{}',
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.news_subscriptions FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- The items of the news feeds that were seen, so every item is relayed once.
+CREATE TABLE
+    public.news_items (
+        feed TEXT NOT NULL,
+        item_id TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (feed, item_id)
+    ) TABLESPACE pg_default;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- compact users get informational embeds without their details, e.g. only the USD and BTC price in /price.
+ALTER TABLE discord_users ADD COLUMN compact BOOLEAN NOT NULL DEFAULT false;
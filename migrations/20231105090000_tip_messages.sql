@@ -0,0 +1,10 @@
+-- Add migration script here
+-- The announcements of tips in a channel, so a tip can link back to its announcement.
+CREATE TABLE
+    public.tip_messages (
+        tip_event_id TEXT NOT NULL PRIMARY KEY,
+        guild_id bigint,
+        channel_id bigint NOT NULL,
+        message_id bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
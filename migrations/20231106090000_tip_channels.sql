@@ -0,0 +1,9 @@
+-- Add migration script here
+-- The channels a guild allows tipping in, see /admin channels. A guild without channels allows tipping everywhere.
+CREATE TABLE
+    public.tip_channels (
+        guild_id bigint NOT NULL,
+        channel_id bigint NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (guild_id, channel_id)
+    ) TABLESPACE pg_default;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- reactdrops that only pay out to the participants that have this role.
+ALTER TABLE reactdrops ADD COLUMN required_role bigint;
@@ -0,0 +1,19 @@
+-- Add migration script here
+-- The state of the hot wallet that owners control with /wallet. There is a single row.
+CREATE TABLE
+    public.hot_wallet_settings (
+        id boolean PRIMARY KEY DEFAULT true CHECK (id),
+        -- no withdrawals are sent while they are frozen, until an owner unfreezes them
+        withdrawals_frozen BOOLEAN NOT NULL DEFAULT false,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.hot_wallet_settings FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+INSERT INTO hot_wallet_settings DEFAULT VALUES;
@@ -0,0 +1,36 @@
+-- Add migration script here
+-- Messages the outbox gave up on, like DMs to users that do not accept DMs from the bot. They are retried now and then,
+-- in case the user opened their DMs, and kept for inspection after the last retry.
+CREATE TABLE
+    public.failed_notifications (
+        id bigserial PRIMARY KEY,
+        -- the columns of the outbox message, see `outbox`
+        kind TEXT NOT NULL,
+        channel_id bigint,
+        guild_id bigint,
+        user_id bigint NOT NULL,
+        content TEXT NOT NULL,
+        button_user bigint,
+        button_event uuid,
+        last_error TEXT,
+        retries integer NOT NULL DEFAULT 0,
+        next_retry_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX failed_notifications_next_retry_at_idx ON public.failed_notifications (next_retry_at);
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP 
+	BEFORE
+	UPDATE
+	    ON public.failed_notifications FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- the messages the outbox already gave up on, after `delivery::MAX_ATTEMPTS` attempts
+INSERT INTO failed_notifications (kind, channel_id, guild_id, user_id, content, button_user, button_event, last_error, created_at)
+SELECT kind, channel_id, guild_id, user_id, content, button_user, button_event, last_error, created_at
+FROM outbox WHERE attempts >= 10;
+
+DELETE FROM outbox WHERE attempts >= 10;
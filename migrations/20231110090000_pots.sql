@@ -0,0 +1,51 @@
+-- Add migration script here
+-- A crowdfunding pot in a guild. The contributions are held in the `pot:<id>` account of the ledger until the creator
+-- closes the pot, which pays them out to the recipient, or until it is cancelled, which refunds them.
+CREATE TABLE
+    public.pots (
+        id bigserial PRIMARY KEY,
+        guild_id bigint NOT NULL,
+        channel_id bigint NOT NULL,
+        -- the message with the progress of the pot, edited on every contribution
+        message_id bigint,
+        name TEXT NOT NULL,
+        created_by bigint NOT NULL,
+        goal bigint NOT NULL CHECK (goal > 0),
+        -- the pot is paid out to either a user or an address or VerusID
+        recipient_user bigint,
+        recipient_address TEXT,
+        -- a pot that is paid out to an address is withdrawing from just before the withdrawal is sent until it is
+        -- paid out, so it can't be withdrawn twice or cancelled in the meantime. A pot that is still closed with a
+        -- balance was never paid out, and can be cancelled to refund its contributors.
+        status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'closed', 'withdrawing', 'cancelled')),
+        -- what the withdrawal of the pot pays, stored before it is sent, and its operation, stored as soon as it was
+        -- sent. A pot that is still withdrawing when the bot starts is paid out when its operation went through, and
+        -- closed again when nothing was sent.
+        withdrawal_opid TEXT,
+        withdrawal_amount bigint,
+        withdrawal_tx_fee bigint,
+        withdrawal_bot_fee bigint,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        CHECK ((recipient_user IS NULL) <> (recipient_address IS NULL))
+    ) TABLESPACE pg_default;
+
+CREATE UNIQUE INDEX pots_open_name_idx ON public.pots (guild_id, lower(name)) WHERE status = 'open';
+
+CREATE TRIGGER SET_UPDATED_TIMESTAMP
+	BEFORE
+	UPDATE
+	    ON public.pots FOR EACH ROW
+	EXECUTE
+	    PROCEDURE trigger_set_timestamp();
+
+-- Every contribution to a pot, so a cancelled pot can refund each contributor.
+CREATE TABLE
+    public.pot_contributions (
+        pot_id bigint NOT NULL REFERENCES public.pots (id),
+        discord_id bigint NOT NULL,
+        amount bigint NOT NULL CHECK (amount > 0),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX pot_contributions_pot_id_idx ON public.pot_contributions (pot_id);
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- A reactdrop is paying from the moment it ends until its payout is stored. The bot resumes the payout of reactdrops
+-- that are still paying, e.g. after a restart or an error.
+ALTER TABLE reactdrops DROP CONSTRAINT reactdrops_status_check;
+ALTER TABLE reactdrops
+    ADD CONSTRAINT reactdrops_status_check CHECK (status IN ('pending', 'paying', 'processed', 'cancelled'));
@@ -0,0 +1,24 @@
+-- Add migration script here
+-- Events waiting to be posted in the mod-logs, see `mod_log`. They are stored in the transaction of the balance change
+-- they are about, and deleted when they are posted.
+CREATE TABLE
+    public.mod_log_queue (
+        id bigserial PRIMARY KEY,
+        -- without a guild, the event goes to the mod-logs of all guilds of the user
+        guild_id bigint,
+        kind TEXT NOT NULL CHECK (kind IN ('withdrawal', 'tip', 'blacklist', 'balance_mismatch')),
+        -- the user the event is about: the one that withdrew, tipped, was blacklisted or has the mismatching balance
+        user_id bigint NOT NULL,
+        -- withdrawals and tips, in sats
+        amount bigint,
+        fees bigint,
+        txid TEXT,
+        recipients integer,
+        tip_event_id uuid,
+        blacklisted boolean,
+        actor bigint,
+        -- balance mismatches, in sats
+        balance bigint,
+        ledger bigint,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
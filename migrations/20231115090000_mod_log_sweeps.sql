@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Sweeps of the hot wallet to cold storage are posted in the mod-logs with their operation and where they went.
+ALTER TABLE mod_log_queue
+    DROP CONSTRAINT mod_log_queue_kind_check,
+    ADD CONSTRAINT mod_log_queue_kind_check CHECK (kind IN ('withdrawal', 'tip', 'blacklist', 'balance_mismatch', 'sweep')),
+    ADD COLUMN opid TEXT,
+    ADD COLUMN destination TEXT;
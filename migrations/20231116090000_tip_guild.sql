@@ -0,0 +1,3 @@
+-- Add migration script here
+-- The guild a tip was made in, so a tip is only disputed in its own guild. Older tips don't have one.
+ALTER TABLE public.tips_vrsc ADD COLUMN guild_id bigint;
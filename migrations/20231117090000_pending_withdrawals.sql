@@ -0,0 +1,15 @@
+-- Add migration script here
+-- A withdrawal that is reserved but not settled or refunded yet, with what it reserved and the operation that sends
+-- it, once it was sent. A withdrawal that is still pending when the bot starts is settled when its operation went
+-- through, and refunded when it failed.
+CREATE TABLE
+    public.pending_withdrawals (
+        uuid TEXT NOT NULL PRIMARY KEY,
+        discord_id bigint NOT NULL,
+        guild_id bigint,
+        amount bigint NOT NULL,
+        tx_fee bigint NOT NULL,
+        bot_fee bigint NOT NULL,
+        opid TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
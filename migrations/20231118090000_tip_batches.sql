@@ -0,0 +1,24 @@
+-- Add migration script here
+-- Tips that are announced in a summary instead of on their own, see `/config batching`. Like the outbox, they are
+-- stored in the transaction that pays the tip. Once the batch is due, its entries are coalesced into a single summary
+-- in the outbox and deleted.
+CREATE TABLE
+    public.tip_batch_entries (
+        id bigserial PRIMARY KEY,
+        -- 'channel' for the announcements in a channel, 'dm' for the DMs to a recipient
+        kind TEXT NOT NULL CHECK (kind IN ('channel', 'dm')),
+        -- the channel of the announcements, or the recipient of the DMs
+        key bigint NOT NULL,
+        guild_id bigint,
+        -- the tipper, who gets the channel summary in DM when the channel can't be used, or the recipient of a DM
+        initiator bigint NOT NULL,
+        amount bigint NOT NULL,
+        -- the recipients of the tip that could not be DMed, which the channel summary mentions
+        unnotified integer NOT NULL DEFAULT 0,
+        window_seconds integer NOT NULL,
+        -- the end of the window of the first tip in the batch, when the summary is posted
+        flush_at TIMESTAMPTZ NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    ) TABLESPACE pg_default;
+
+CREATE INDEX tip_batch_entries_key_idx ON public.tip_batch_entries (kind, key);
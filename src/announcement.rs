@@ -0,0 +1,151 @@
+//! Announcements from the operators of the bot to its users.
+//!
+//! Discord bans bots that send too many DMs in a short time, so announcements are delivered in batches of
+//! [`BATCH_SIZE`] with a pause of [`BATCH_PAUSE`] in between. Every delivery (or failure to deliver) is stored in the
+//! `announcement_deliveries` table.
+
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{ChannelId, Http, UserId};
+use sqlx::PgPool;
+use tokio::time::sleep;
+use tracing::{debug, error, info};
+
+use crate::util::database;
+
+const BATCH_SIZE: usize = 10;
+const BATCH_PAUSE: Duration = Duration::from_secs(10);
+const MESSAGE_PAUSE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum AnnouncementTarget {
+    #[name = "DM all users"]
+    Dm,
+    #[name = "Announcement channels"]
+    Channel,
+}
+
+impl AnnouncementTarget {
+    /// How the target is stored with the announcement.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dm => "dm",
+            Self::Channel => "channel",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DeliveryReport {
+    pub delivered: usize,
+    pub failed: usize,
+}
+
+enum Recipient {
+    User(UserId),
+    Channel(ChannelId),
+}
+
+impl Recipient {
+    fn id(&self) -> u64 {
+        match self {
+            Self::User(user_id) => user_id.0,
+            Self::Channel(channel_id) => channel_id.0,
+        }
+    }
+}
+
+/// Delivers an announcement to all recipients of the given target. This takes a while for DMs, so it is meant to
+/// be run in the background.
+pub async fn deliver(
+    http: Arc<Http>,
+    pool: PgPool,
+    announcement_id: i64,
+    message: String,
+    target: AnnouncementTarget,
+) -> DeliveryReport {
+    let recipients = match target {
+        AnnouncementTarget::Dm => database::get_announcement_recipients(&pool)
+            .await
+            .map(|users| users.into_iter().map(Recipient::User).collect::<Vec<_>>()),
+        AnnouncementTarget::Channel => {
+            database::get_announcement_channels(&pool)
+                .await
+                .map(|channels| {
+                    channels
+                        .into_iter()
+                        .map(Recipient::Channel)
+                        .collect::<Vec<_>>()
+                })
+        }
+    };
+
+    let recipients = match recipients {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            error!("could not get the recipients of announcement {announcement_id}: {e:?}");
+            return DeliveryReport::default();
+        }
+    };
+
+    info!(
+        "delivering announcement {announcement_id} to {} recipient(s)",
+        recipients.len()
+    );
+
+    let mut report = DeliveryReport::default();
+
+    for (i, batch) in recipients.chunks(BATCH_SIZE).enumerate() {
+        if i > 0 {
+            sleep(BATCH_PAUSE).await;
+        }
+
+        for recipient in batch {
+            let result = match recipient {
+                Recipient::User(user_id) => match user_id.create_dm_channel(&http).await {
+                    Ok(channel) => channel
+                        .send_message(&http, |m| m.content(&message))
+                        .await
+                        .map(|_| ()),
+                    Err(e) => Err(e),
+                },
+                Recipient::Channel(channel_id) => channel_id
+                    .send_message(&http, |m| m.content(&message))
+                    .await
+                    .map(|_| ()),
+            };
+
+            let error = match result {
+                Ok(()) => {
+                    report.delivered += 1;
+                    None
+                }
+                Err(e) => {
+                    debug!(
+                        "could not deliver announcement to {}: {e:?}",
+                        recipient.id()
+                    );
+                    report.failed += 1;
+                    Some(e.to_string())
+                }
+            };
+
+            if let Err(e) = database::insert_announcement_delivery(
+                &pool,
+                announcement_id,
+                recipient.id(),
+                error,
+            )
+            .await
+            {
+                error!("could not store delivery of announcement {announcement_id}: {e:?}");
+            }
+
+            sleep(MESSAGE_PAUSE).await;
+        }
+    }
+
+    info!("announcement {announcement_id} delivered: {report:?}");
+
+    report
+}
@@ -0,0 +1,387 @@
+//! A read-only HTTP API with tipping statistics, for community sites that want to show them.
+//!
+//! When `api_bind_address` and `api_key` are set in the config, an HTTP server is started that serves JSON on
+//! `/api/v1/...`. Every request needs the key in the `x-api-key` header. Amounts are in sats, times are unix timestamps
+//! and Discord ids are strings, as they don't fit in a JavaScript number.
+//!
+//! The responses use the same queries as the commands that show the statistics in Discord: `/stats drops`, `/streaks`
+//! and `/competition scoreboard`.
+//!
+//! The OpenAPI spec of the API is served on `/api/docs`, without a key. It is generated from the handlers and the types
+//! they respond with, see [`ApiDoc`].
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use poise::serenity_prelude::GuildId;
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{error, info};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::{util::database, Error};
+
+const RECENT_REACTDROPS: i64 = 25;
+const LEADERBOARD_SIZE: i64 = 10;
+
+struct ApiState {
+    pool: PgPool,
+    api_key: Secret<String>,
+}
+
+/// An error of a request, which is logged and answered with a 500 without details.
+struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        error!("API request failed: {:?}", self.0);
+
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "verusbot API",
+        description = "Read-only tipping statistics. Amounts are in sats, times are unix timestamps and Discord ids are strings."
+    ),
+    paths(volume, guild_stats, leaderboard, reactdrops),
+    components(schemas(
+        Volume,
+        VolumeResponse,
+        GuildStats,
+        ReactdropResult,
+        StreakEntry,
+        ScoreEntry,
+        CompetitionStanding,
+        Leaderboard
+    )),
+    modifiers(&ApiKeyHeader),
+    security(("api_key" = []))
+)]
+pub struct ApiDoc;
+
+/// Documents the `x-api-key` header that every request needs, see [`require_api_key`].
+struct ApiKeyHeader;
+
+impl Modify for ApiKeyHeader {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Volume {
+    tips: i64,
+    amount: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct VolumeResponse {
+    day: Volume,
+    week: Volume,
+    month: Volume,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct GuildStats {
+    reactdrops: i64,
+    average_reactdrop_pot: u64,
+    treasury: u64,
+    daily_reward: u64,
+    daily_pool: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ReactdropResult {
+    started_at: i64,
+    pot: u64,
+    participants: Option<i32>,
+    eligible: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct StreakEntry {
+    user_id: String,
+    streak: i32,
+    best_streak: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ScoreEntry {
+    user_id: String,
+    score: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct CompetitionStanding {
+    metric: String,
+    prize: u64,
+    min_tip: u64,
+    ends_at: i64,
+    scores: Vec<ScoreEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Leaderboard {
+    streaks: Vec<StreakEntry>,
+    /// The running competition of the guild, if there is one
+    competition: Option<CompetitionStanding>,
+}
+
+pub async fn serve(address: SocketAddr, pool: PgPool, api_key: Secret<String>) {
+    let state = Arc::new(ApiState { pool, api_key });
+    let app = Router::new()
+        .route("/api/v1/volume", get(volume))
+        .route("/api/v1/guilds/:guild_id/stats", get(guild_stats))
+        .route("/api/v1/guilds/:guild_id/leaderboard", get(leaderboard))
+        .route("/api/v1/guilds/:guild_id/reactdrops", get(reactdrops))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ))
+        // added after the key check, so the docs can be read without a key
+        .route("/api/docs", get(docs))
+        .with_state(state);
+
+    info!("serving the API on {address}");
+
+    if let Err(e) = axum::Server::bind(&address)
+        .serve(app.into_make_service())
+        .await
+    {
+        error!("API server stopped: {e:?}");
+    }
+}
+
+async fn require_api_key<B>(
+    State(state): State<Arc<ApiState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let key = request
+        .headers()
+        .get("x-api-key")
+        .and_then(|key| key.to_str().ok())
+        .unwrap_or_default();
+
+    match key_matches(key, state.api_key.expose_secret()) {
+        true => next.run(request).await,
+        false => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+/// Compares keys in constant time, so the time a request takes does not tell how much of a key was right.
+fn key_matches(key: &str, expected: &str) -> bool {
+    key.len() == expected.len()
+        && key
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+async fn docs() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// The number and total amount of all tips in the last day, week and month
+#[utoipa::path(
+    get,
+    path = "/api/v1/volume",
+    responses(
+        (status = 200, body = VolumeResponse),
+        (status = 401, description = "The API key is missing or wrong")
+    )
+)]
+async fn volume(State(state): State<Arc<ApiState>>) -> ApiResult<VolumeResponse> {
+    let pool = &state.pool;
+    let volume = |hours| async move {
+        let (tips, amount) = database::get_tip_volume(pool, hours).await?;
+
+        Ok::<_, Error>(Volume {
+            tips,
+            amount: amount.as_sat(),
+        })
+    };
+
+    Ok(Json(VolumeResponse {
+        day: volume(24).await?,
+        week: volume(24 * 7).await?,
+        month: volume(24 * 30).await?,
+    }))
+}
+
+/// The reactdrops, treasury and daily pool of a guild
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds/{guild_id}/stats",
+    params(("guild_id" = u64, Path, description = "The id of the Discord server")),
+    responses(
+        (status = 200, body = GuildStats),
+        (status = 401, description = "The API key is missing or wrong")
+    )
+)]
+async fn guild_stats(
+    State(state): State<Arc<ApiState>>,
+    Path(guild_id): Path<u64>,
+) -> ApiResult<GuildStats> {
+    let pool = &state.pool;
+    let guild_id = GuildId(guild_id);
+
+    let (reactdrops, average_pot) = database::get_reactdrop_totals(pool, guild_id).await?;
+
+    Ok(Json(GuildStats {
+        reactdrops,
+        average_reactdrop_pot: average_pot.as_sat(),
+        treasury: database::get_treasury_balance(pool, guild_id)
+            .await?
+            .as_sat(),
+        daily_reward: database::get_daily_reward(pool, guild_id).await?.as_sat(),
+        daily_pool: database::get_daily_pool(pool, guild_id).await?.as_sat(),
+    }))
+}
+
+/// The best daily streaks of a guild and the standings of its running competition
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds/{guild_id}/leaderboard",
+    params(("guild_id" = u64, Path, description = "The id of the Discord server")),
+    responses(
+        (status = 200, body = Leaderboard),
+        (status = 401, description = "The API key is missing or wrong")
+    )
+)]
+async fn leaderboard(
+    State(state): State<Arc<ApiState>>,
+    Path(guild_id): Path<u64>,
+) -> ApiResult<Leaderboard> {
+    let pool = &state.pool;
+    let guild_id = GuildId(guild_id);
+
+    let streaks = database::get_daily_leaderboard(pool, guild_id, LEADERBOARD_SIZE)
+        .await?
+        .into_iter()
+        .map(|streak| StreakEntry {
+            user_id: streak.user_id.to_string(),
+            streak: streak.streak,
+            best_streak: streak.best_streak,
+        })
+        .collect();
+
+    let competition = match database::get_running_competition(pool, guild_id).await? {
+        Some(competition) => {
+            let scores = database::get_competition_scores(pool, &competition, LEADERBOARD_SIZE)
+                .await?
+                .into_iter()
+                .map(|(user_id, score)| ScoreEntry {
+                    user_id: user_id.to_string(),
+                    score,
+                })
+                .collect();
+
+            Some(CompetitionStanding {
+                metric: competition.metric.as_str().to_string(),
+                prize: competition.prize.as_sat(),
+                min_tip: competition.min_tip.as_sat(),
+                ends_at: competition.ends_at.timestamp(),
+                scores,
+            })
+        }
+        None => None,
+    };
+
+    Ok(Json(Leaderboard {
+        streaks,
+        competition,
+    }))
+}
+
+/// The results of the most recent reactdrops of a guild, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds/{guild_id}/reactdrops",
+    params(("guild_id" = u64, Path, description = "The id of the Discord server")),
+    responses(
+        (status = 200, body = [ReactdropResult]),
+        (status = 401, description = "The API key is missing or wrong")
+    )
+)]
+async fn reactdrops(
+    State(state): State<Arc<ApiState>>,
+    Path(guild_id): Path<u64>,
+) -> ApiResult<Vec<ReactdropResult>> {
+    let drops =
+        database::get_recent_reactdrop_results(&state.pool, GuildId(guild_id), RECENT_REACTDROPS)
+            .await?
+            .into_iter()
+            .map(|drop| ReactdropResult {
+                started_at: drop.started_at.timestamp(),
+                pot: drop.pot.as_sat(),
+                participants: drop.participants,
+                eligible: drop.eligible,
+            })
+            .collect();
+
+    Ok(Json(drops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_exact_key_matches() {
+        assert!(key_matches("s3cret", "s3cret"));
+        assert!(!key_matches("s3cres", "s3cret"));
+        assert!(!key_matches("s3cret2", "s3cret"));
+        assert!(!key_matches("", "s3cret"));
+    }
+
+    #[test]
+    fn docs_list_every_route() {
+        let docs = ApiDoc::openapi();
+        let mut paths = docs
+            .paths
+            .paths
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            vec![
+                "/api/v1/guilds/{guild_id}/leaderboard",
+                "/api/v1/guilds/{guild_id}/reactdrops",
+                "/api/v1/guilds/{guild_id}/stats",
+                "/api/v1/volume",
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt::Display;
+
+use poise::serenity_prelude::{GuildId, UserId};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use tracing::error;
+
+use crate::util::database;
+
+/// Actions that are recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Blacklist,
+    Unblacklist,
+    SetWithdrawFee,
+    WithdrawalsEnabled,
+    DepositsEnabled,
+    Maintenance,
+    Rescan,
+    CheckTxid,
+    ManualWithdraw,
+    GrantRole,
+    RevokeRole,
+    DisputeOpened,
+    DisputeResolved,
+    Announce,
+    SetAnnouncementChannel,
+    SetFallbackChannel,
+    ExportGuild,
+    ImportGuild,
+    SetEmojiAmount,
+    SetBatching,
+    ReverseTip,
+    ImportLegacy,
+    SetNetworkDigest,
+    ProofOfReserves,
+    SetGuildWithdrawalFee,
+    SetTipButtons,
+    SetFilterWord,
+    SetDefaultFilter,
+    SetReferralRewards,
+    FundReferralPool,
+    AddTriviaQuestion,
+    RemoveTriviaQuestion,
+    SetDailyReward,
+    StartCompetition,
+    CancelCompetition,
+    SetModLog,
+    SetNewsFeed,
+    PostNews,
+    AllowTipChannel,
+    DisallowTipChannel,
+    SweepHotWallet,
+    FreezeWithdrawals,
+    SettleWithdrawal,
+    RefundWithdrawal,
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blacklist => write!(f, "blacklist"),
+            Self::Unblacklist => write!(f, "unblacklist"),
+            Self::SetWithdrawFee => write!(f, "set_withdraw_fee"),
+            Self::WithdrawalsEnabled => write!(f, "withdrawals_enabled"),
+            Self::DepositsEnabled => write!(f, "deposits_enabled"),
+            Self::Maintenance => write!(f, "maintenance"),
+            Self::Rescan => write!(f, "rescan"),
+            Self::CheckTxid => write!(f, "check_txid"),
+            Self::ManualWithdraw => write!(f, "manual_withdraw"),
+            Self::GrantRole => write!(f, "grant_role"),
+            Self::RevokeRole => write!(f, "revoke_role"),
+            Self::DisputeOpened => write!(f, "dispute_opened"),
+            Self::DisputeResolved => write!(f, "dispute_resolved"),
+            Self::Announce => write!(f, "announce"),
+            Self::SetAnnouncementChannel => write!(f, "set_announcement_channel"),
+            Self::SetFallbackChannel => write!(f, "set_fallback_channel"),
+            Self::ExportGuild => write!(f, "export_guild"),
+            Self::ImportGuild => write!(f, "import_guild"),
+            Self::SetEmojiAmount => write!(f, "set_emoji_amount"),
+            Self::SetBatching => write!(f, "set_batching"),
+            Self::ReverseTip => write!(f, "reverse_tip"),
+            Self::ImportLegacy => write!(f, "import_legacy"),
+            Self::SetNetworkDigest => write!(f, "set_network_digest"),
+            Self::ProofOfReserves => write!(f, "proof_of_reserves"),
+            Self::SetGuildWithdrawalFee => write!(f, "set_guild_withdrawal_fee"),
+            Self::SetTipButtons => write!(f, "set_tip_buttons"),
+            Self::SetFilterWord => write!(f, "set_filter_word"),
+            Self::SetDefaultFilter => write!(f, "set_default_filter"),
+            Self::SetReferralRewards => write!(f, "set_referral_rewards"),
+            Self::FundReferralPool => write!(f, "fund_referral_pool"),
+            Self::AddTriviaQuestion => write!(f, "add_trivia_question"),
+            Self::RemoveTriviaQuestion => write!(f, "remove_trivia_question"),
+            Self::SetDailyReward => write!(f, "set_daily_reward"),
+            Self::StartCompetition => write!(f, "start_competition"),
+            Self::CancelCompetition => write!(f, "cancel_competition"),
+            Self::SetModLog => write!(f, "set_mod_log"),
+            Self::SetNewsFeed => write!(f, "set_news_feed"),
+            Self::PostNews => write!(f, "post_news"),
+            Self::AllowTipChannel => write!(f, "allow_tip_channel"),
+            Self::DisallowTipChannel => write!(f, "disallow_tip_channel"),
+            Self::SweepHotWallet => write!(f, "sweep_hot_wallet"),
+            Self::FreezeWithdrawals => write!(f, "freeze_withdrawals"),
+            Self::SettleWithdrawal => write!(f, "settle_withdrawal"),
+            Self::RefundWithdrawal => write!(f, "refund_withdrawal"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub actor: UserId,
+    pub action: String,
+    pub target: Option<String>,
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stores an entry in the audit log.
+///
+/// A failure to write the audit log should never undo the action that was taken, so errors are logged and ignored.
+pub async fn record(
+    pool: &PgPool,
+    guild_id: GuildId,
+    actor: UserId,
+    action: AuditAction,
+    target: Option<String>,
+    details: Option<String>,
+) {
+    if let Err(e) =
+        database::insert_audit_entry(pool, guild_id, actor, &action.to_string(), target, details)
+            .await
+    {
+        error!("could not write {action} by {actor} to the audit log: {e:?}");
+    }
+}
@@ -0,0 +1,228 @@
+//! Bot-level roles and the permissions that come with them.
+//!
+//! There are 4 roles, from least to most privileged:
+//! - **Support**: can look at the audit log to help users with their questions.
+//! - **Moderator**: can additionally (un)blacklist users, resolve disputes and see the status of the bot.
+//! - **Guild admin**: can additionally manage the settings of their guild and hand out the Support and Moderator roles.
+//! - **Operator**: can do everything, including managing the funds and the daemon. Operators are the `owners` in the
+//!   config file; this role can not be given out with a command.
+//!
+//! Roles other than Operator are stored per guild in the `bot_roles` table. Admin commands are mostly used in DMs, in
+//! which case the guild from the config (`discord_guild_id`) is used. The roles of other guilds are handed out with
+//! `role grant` in that guild.
+//!
+//! Commands declare the permission they need with a check, e.g. `#[poise::command(check = "can_view_audit")]`.
+
+use poise::serenity_prelude::{GuildId, UserId};
+use tracing::*;
+
+use crate::{util::database, Context, Data, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, poise::ChoiceParameter)]
+pub enum BotRole {
+    #[name = "Support"]
+    Support,
+    #[name = "Moderator"]
+    Moderator,
+    #[name = "Guild admin"]
+    GuildAdmin,
+    #[name = "Operator"]
+    Operator,
+}
+
+impl TryFrom<String> for BotRole {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "support" => Ok(Self::Support),
+            "moderator" => Ok(Self::Moderator),
+            "guild_admin" => Ok(Self::GuildAdmin),
+            "operator" => Ok(Self::Operator),
+            _ => Err(format!("unknown bot role {value}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Read the audit log
+    ViewAudit,
+    /// See the (financial) status of the bot
+    ViewStatus,
+    /// Blacklist or unblacklist a user
+    Blacklist,
+    /// Release or refund the funds of a disputed tip
+    ResolveDisputes,
+    /// Change settings of a guild
+    ManageGuild,
+    /// Give or take the Support and Moderator roles
+    ManageRoles,
+    /// Fees, deposits, withdrawals, rescans and manual transaction fixes
+    ManageFunds,
+    /// Maintenance mode and registering commands
+    ManageBot,
+}
+
+impl BotRole {
+    /// How the role is stored, the inverse of `TryFrom<String>`. `Display` shows the name of the choice instead.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Support => "support",
+            Self::Moderator => "moderator",
+            Self::GuildAdmin => "guild_admin",
+            Self::Operator => "operator",
+        }
+    }
+
+    /// The permissions matrix.
+    pub fn allows(&self, permission: Permission) -> bool {
+        use Permission::*;
+
+        match self {
+            BotRole::Operator => true,
+            BotRole::GuildAdmin => matches!(
+                permission,
+                ViewAudit | ViewStatus | Blacklist | ResolveDisputes | ManageGuild | ManageRoles
+            ),
+            BotRole::Moderator => matches!(
+                permission,
+                ViewAudit | ViewStatus | Blacklist | ResolveDisputes
+            ),
+            BotRole::Support => matches!(permission, ViewAudit),
+        }
+    }
+
+    /// Whether a user with this role can give `other` to someone else (or take it away).
+    pub fn can_manage(&self, other: BotRole) -> bool {
+        match self {
+            BotRole::Operator => other != BotRole::Operator,
+            BotRole::GuildAdmin => other < BotRole::GuildAdmin,
+            _ => false,
+        }
+    }
+}
+
+/// The guild a command applies to: the guild it was used in, or the configured guild when used in DMs.
+pub fn guild_of(ctx: Context<'_>) -> GuildId {
+    ctx.guild_id().unwrap_or_else(|| {
+        GuildId(
+            ctx.data()
+                .settings
+                .application
+                .discord_guild_id
+                .parse::<u64>()
+                .unwrap(),
+        )
+    })
+}
+
+pub fn is_operator(data: &Data, user_id: &UserId) -> bool {
+    data.owners.contains(user_id)
+}
+
+/// Returns the role of a user in a guild, if any.
+pub async fn role_of(
+    data: &Data,
+    user_id: &UserId,
+    guild_id: GuildId,
+) -> Result<Option<BotRole>, Error> {
+    if is_operator(data, user_id) {
+        return Ok(Some(BotRole::Operator));
+    }
+
+    database::get_bot_role(&data.database, user_id, guild_id).await
+}
+
+pub async fn has_permission(ctx: Context<'_>, permission: Permission) -> Result<bool, Error> {
+    let role = role_of(ctx.data(), &ctx.author().id, guild_of(ctx)).await?;
+    let allowed = role.is_some_and(|role| role.allows(permission));
+
+    if !allowed {
+        debug!(
+            "{} ({:?}) is not allowed to {permission:?}",
+            ctx.author().id,
+            role
+        );
+    }
+
+    Ok(allowed)
+}
+
+pub async fn can_view_audit(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ViewAudit).await
+}
+
+pub async fn can_view_status(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ViewStatus).await
+}
+
+pub async fn can_blacklist(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::Blacklist).await
+}
+
+pub async fn can_resolve_disputes(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ResolveDisputes).await
+}
+
+pub async fn can_manage_guild(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ManageGuild).await
+}
+
+pub async fn can_manage_roles(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ManageRoles).await
+}
+
+pub async fn can_manage_funds(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ManageFunds).await
+}
+
+pub async fn can_manage_bot(ctx: Context<'_>) -> Result<bool, Error> {
+    has_permission(ctx, Permission::ManageBot).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_matrix() {
+        assert!(BotRole::Support.allows(Permission::ViewAudit));
+        assert!(!BotRole::Support.allows(Permission::Blacklist));
+
+        assert!(BotRole::Moderator.allows(Permission::Blacklist));
+        assert!(BotRole::Moderator.allows(Permission::ResolveDisputes));
+        assert!(!BotRole::Support.allows(Permission::ResolveDisputes));
+        assert!(!BotRole::Moderator.allows(Permission::ManageGuild));
+
+        assert!(BotRole::GuildAdmin.allows(Permission::ManageRoles));
+        assert!(!BotRole::GuildAdmin.allows(Permission::ManageFunds));
+        assert!(!BotRole::GuildAdmin.allows(Permission::ManageBot));
+
+        assert!(BotRole::Operator.allows(Permission::ManageFunds));
+        assert!(BotRole::Operator.allows(Permission::ManageBot));
+    }
+
+    #[test]
+    fn unknown_roles_are_not_parsed() {
+        for role in [
+            BotRole::Support,
+            BotRole::Moderator,
+            BotRole::GuildAdmin,
+            BotRole::Operator,
+        ] {
+            assert_eq!(BotRole::try_from(role.as_str().to_string()), Ok(role));
+        }
+        assert!(BotRole::try_from("admin".to_string()).is_err());
+    }
+
+    #[test]
+    fn role_management() {
+        assert!(BotRole::GuildAdmin.can_manage(BotRole::Moderator));
+        assert!(BotRole::GuildAdmin.can_manage(BotRole::Support));
+        assert!(!BotRole::GuildAdmin.can_manage(BotRole::GuildAdmin));
+        assert!(!BotRole::Moderator.can_manage(BotRole::Support));
+        assert!(BotRole::Operator.can_manage(BotRole::GuildAdmin));
+        assert!(!BotRole::Operator.can_manage(BotRole::Operator));
+    }
+}
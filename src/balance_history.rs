@@ -0,0 +1,107 @@
+//! Daily snapshots of all balances, used to show how a balance changed over time.
+//!
+//! A snapshot is taken when the bot starts and every night at midnight (UTC). Snapshots are stored per day, so
+//! taking one twice on the same day does nothing.
+
+use std::path::Path;
+
+use chrono::{Days, NaiveDate, Utc};
+use plotters::prelude::*;
+use sqlx::PgPool;
+use tokio::time::sleep;
+use tracing::{error, info};
+use vrsc::Amount;
+
+use crate::{util::database, Error};
+
+const CHART_SIZE: (u32, u32) = (800, 400);
+const CHART_COLOR: RGBColor = RGBColor(49, 101, 212);
+
+/// Takes a snapshot now and then every night.
+pub async fn snapshot_nightly(pool: PgPool) {
+    loop {
+        match database::snapshot_balances(&pool).await {
+            Ok(count) => info!("stored {count} balance snapshots"),
+            Err(e) => error!("could not snapshot balances: {e:?}"),
+        }
+
+        let now = Utc::now();
+        let midnight = now
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0))
+            .map(|midnight| midnight.and_utc());
+
+        match midnight.and_then(|midnight| (midnight - now).to_std().ok()) {
+            Some(until_midnight) => sleep(until_midnight).await,
+            None => sleep(std::time::Duration::from_secs(60 * 60)).await,
+        }
+    }
+}
+
+/// The change of a balance over a period, in sats.
+pub fn net_flow(history: &[(NaiveDate, Amount)]) -> i64 {
+    match (history.first(), history.last()) {
+        (Some((_, first)), Some((_, last))) => last.as_sat() as i64 - first.as_sat() as i64,
+        _ => 0,
+    }
+}
+
+/// Draws the balance over time as a line chart and saves it as a PNG at `path`. The chart has no labels, the dates and
+/// amounts are shown in the message the chart is attached to.
+pub fn render_chart(history: &[(NaiveDate, Amount)], path: &Path) -> Result<(), Error> {
+    let (Some((first_day, _)), Some((last_day, _))) = (history.first(), history.last()) else {
+        return Err("no balance history to draw".into());
+    };
+
+    let max = history
+        .iter()
+        .map(|(_, balance)| balance.as_vrsc())
+        .fold(0.0, f64::max);
+    // a flat line at the bottom of the chart looks like an empty chart, so add some room when all balances are 0
+    let max = if max > 0.0 { max * 1.1 } else { 1.0 };
+
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .margin(20)
+        .build_cartesian_2d(*first_day..*last_day, 0.0..max)?;
+
+    chart.draw_series(
+        AreaSeries::new(
+            history
+                .iter()
+                .map(|(day, balance)| (*day, balance.as_vrsc())),
+            0.0,
+            CHART_COLOR.mix(0.2),
+        )
+        .border_style(CHART_COLOR.stroke_width(3)),
+    )?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 10, day).unwrap()
+    }
+
+    #[test]
+    fn net_flow_is_the_difference_between_first_and_last_day() {
+        let history = vec![
+            (day(1), Amount::from_sat(500)),
+            (day(2), Amount::from_sat(2_000)),
+            (day(3), Amount::from_sat(300)),
+        ];
+
+        assert_eq!(net_flow(&history), -200);
+        assert_eq!(net_flow(&history[..2]), 1_500);
+        assert_eq!(net_flow(&[]), 0);
+    }
+}
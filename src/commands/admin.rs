@@ -1,36 +1,86 @@
-use poise::serenity_prelude::UserId;
-use sqlx::PgPool;
-use std::{sync::Arc, time::Duration};
+use poise::serenity_prelude::{
+    Attachment, AttachmentType, CacheHttp, GuildChannel, GuildId, UserId,
+};
+use std::{borrow::Cow, str::FromStr, time::Duration};
 use tracing::{debug, error, instrument, trace};
 use uuid::Uuid;
 use vrsc::Amount;
 use vrsc_rpc::{bitcoin::Txid, RpcApi};
 
 use crate::{
-    util::database,
-    wallet_listener::{process_txid, TransactionProcessor},
+    announcement::{self, AnnouncementTarget},
+    audit::{self, AuditAction},
+    authorization::{self, *},
+    commands::{
+        begin_balance_change, confirm,
+        pot::{self, WithdrawingPot},
+        tipping::Reversal,
+        wallet::{self, PendingWithdrawal},
+    },
+    guild_export::GuildExport,
+    i18n::tr,
+    legacy_import, network_digest,
+    news::{self, NewsItem},
+    proof_of_reserves::{self, Report},
+    reactdrop, schema,
+    util::{database, format, rpc_guard},
+    wallet_listener::process_txid,
     Context, Error,
 };
 
+/// The admin commands with the permission they need, as listed by `adminhelp`.
+const ADMIN_COMMANDS: &[(Permission, &str)] = &[
+    (Permission::ViewStatus, "!status                         - (financial) status of the bot"),
+    (Permission::Blacklist, "!blacklist <user_id>            - blacklists a user (no more tipping, deposits & withdraws)"),
+    (Permission::ManageFunds, "!rescanfromheight <blockheight> - rescan blockchain from given height"),
+    (Permission::ManageFunds, "!checktxid <txid>               - manually check txid (in case user balance was not updated)"),
+    (Permission::ManageFunds, "!withdrawenabled <true/false>   - enable / disable withdraws"),
+    (Permission::ManageFunds, "!depositenabled <true/false>    - enable / disable deposits"),
+    (Permission::ManageFunds, "!setwithdrawfee <sats>          - sets the fee a user is charged when withdrawing funds"),
+    (Permission::ManageBot, "!maintenance <true/false>       - set maintenance mode (commands are not executed)"),
+    (Permission::ViewAudit, "!audit [limit]                  - show the most recent entries of the audit log"),
+    (Permission::ManageRoles, "!role grant <user_id> <role>    - give a user a bot role (Support, Moderator, Guild admin)"),
+    (Permission::ManageRoles, "!role revoke <user_id>          - take away the bot role of a user"),
+    (Permission::ManageRoles, "!role list                      - list all users with a bot role"),
+    (Permission::ManageBot, "/exportguild [guild_id]         - export the settings, treasury and reactdrops of a server as JSON"),
+    (Permission::ManageBot, "/importguild <file>             - import a server export from another deployment"),
+    (Permission::ManageFunds, "/admin reverse <event_id>       - reverse a tip event (role tip, reactdrop) of the last 24 hours"),
+    (Permission::ManageFunds, "/importlegacy <file> <source>   - load a CSV of legacy tipbot balances (discord_id,amount) to be claimed"),
+    (Permission::ManageFunds, "/proofofreserves [address]      - make a signed proof-of-reserves report to publish"),
+    (Permission::ManageFunds, "/wallet status                  - show the hot wallet balance against what the bot owes"),
+    (Permission::ManageFunds, "/wallet sweep [amount]          - send the excess of the hot wallet to the cold storage address"),
+    (Permission::ManageFunds, "/wallet rescan <blockheight>    - rescan the deposit addresses from given height"),
+    (Permission::ManageFunds, "/wallet freeze <true/false>     - freeze / unfreeze all withdrawals, also after a restart"),
+    (Permission::ViewStatus, "/admin sync                     - show the progress of the daemon while it is reindexing or syncing"),
+    (Permission::ManageFunds, "/admin fees [days]              - show the bot withdrawal fees that were collected"),
+    (Permission::ManageFunds, "/admin simulate-drop <message_link> - show what a reactdrop would pay out if it ended now, without paying out"),
+    (Permission::ManageGuild, "/admin channels allow|disallow|list - restrict tipping in a server to some channels"),
+    (Permission::ManageGuild, "/admin announce-blocks [channel] [threshold] - post an hourly digest of the Verus network in a channel of this server"),
+    (Permission::ManageBot, "/admin dbinfo                    - show the applied database migrations, table sizes and connection pool"),
+    (Permission::ManageFunds, "/admin withdrawal settle <uuid> <txid> - settle a reserved withdrawal that went through"),
+    (Permission::ManageFunds, "/admin withdrawal refund <uuid>  - refund a reserved withdrawal that was never sent or failed"),
+    (Permission::ManageFunds, "/admin pot settle <pot_id> <txid> - pay out a withdrawing pot whose withdrawal went through"),
+    (Permission::ManageFunds, "/admin pot refund <pot_id>      - refund the contributors of a withdrawing pot that was never sent or failed"),
+    (Permission::ManageFunds, "/referralrewards [..]           - show or set the referral rewards, or fund the referral pool from your balance"),
+    (Permission::ManageBot, "/postnews <topic> <title> [link] - relay news to the servers that subscribed to the topic"),
+];
+
+/// Lists the admin commands the author is allowed to use.
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_view_audit")]
 pub async fn adminhelp(ctx: Context<'_>) -> Result<(), Error> {
+    let role = authorization::role_of(ctx.data(), &ctx.author().id, guild_of(ctx)).await?;
+    let commands = ADMIN_COMMANDS
+        .iter()
+        .filter(|(permission, _)| role.is_some_and(|role| role.allows(*permission)))
+        .map(|(_, line)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+
     ctx.send(|builder| {
-        builder.ephemeral(true).content(
-            r#"
-```
-!status                         - (financial) status of the bot
-!blacklist <user_id>            - blacklists a user (no more tipping, deposits & withdraws)
-!rescanfromheight <blockheight> - rescan blockchain from given height
-!checktxid <txid>               - manually check txid (in case user balance was not updated)
-!withdrawenabled <true/false>   - enable / disable withdraws
-!depositenabled <true/false>    - enable / disable deposits
-!setwithdrawfee <sats>          - sets the fee a user is charged when withdrawing funds
-!maintenance <true/false>       - set maintenance mode (commands are not executed) 
-
-```
-    "#,
-        )
+        builder
+            .ephemeral(true)
+            .content(format!("```\n{commands}\n```"))
     })
     .await?;
 
@@ -38,7 +88,7 @@ pub async fn adminhelp(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_view_status")]
 pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
     let pool = &ctx.data().database;
 
@@ -53,7 +103,10 @@ pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
 
     let client = ctx.data().verus()?;
 
-    let daemon_balance = client.get_balance(None, None)?;
+    let daemon_balance = rpc_guard::call(&client, "getbalance", |client| {
+        client.get_balance(None, None)
+    })
+    .await?;
 
     debug!("total balance: {total_balance}");
     debug!("total_tipped: {total_tipped}");
@@ -69,28 +122,30 @@ pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
                 .field("bot in maintenance", maintenance, false)
                 .field("deposits enabled", deposits_enabled, false)
                 .field("withdrawals enabled", withdrawals_enabled, false)
-                .field("VRSC daemon balance", daemon_balance, false)
-                .field("Tipbot balance", total_balance, false)
-                .field("Total deposited", total_deposited, false)
-                .field("Total withdrawn", total_withdrawn, false)
+                .field("VRSC daemon balance", format::amount(daemon_balance), false)
+                .field("Tipbot balance", format::amount(total_balance), false)
+                .field("Total deposited", format::amount(total_deposited), false)
+                .field("Total withdrawn", format::amount(total_withdrawn), false)
                 .field(
                     "Database deposits - withdraws",
-                    total_deposited
-                        .checked_sub(total_withdrawn)
-                        .unwrap_or(Amount::ZERO),
+                    format::amount(
+                        total_deposited
+                            .checked_sub(total_withdrawn)
+                            .unwrap_or(Amount::ZERO),
+                    ),
                     false,
                 )
-                .field("Total tipped", total_tipped, false)
-                .field("Largest tip", largest_tip, false)
+                .field("Total tipped", format::amount(total_tipped), false)
+                .field("Largest tip", format::amount(largest_tip), false)
                 .field(
                     "Bot fees _(minus network fees)_",
-                    {
+                    format::amount({
                         if let Some(pos_amount) = daemon_balance.checked_sub(total_balance) {
                             pos_amount
                         } else {
                             Amount::ZERO
                         }
-                    },
+                    }),
                     false,
                 )
         })
@@ -106,17 +161,20 @@ async fn totaldeposited(ctx: Context<'_>) -> Result<Amount, Error> {
     let pool = &ctx.data().database;
     let client = ctx.data().verus()?;
 
-    let deposit_transactions = database::get_all_txids(&pool, "deposit").await?;
+    let deposit_transactions = database::get_all_txids(pool, "deposit").await?;
 
     let mut sum = Amount::ZERO;
 
     for txid in deposit_transactions {
-        let raw_tx = client.get_raw_transaction_verbose(&txid)?;
+        let raw_tx = rpc_guard::call(&client, "getrawtransaction", move |client| {
+            client.get_raw_transaction_verbose(&txid)
+        })
+        .await?;
 
         for vout in raw_tx.vout.iter() {
             if let Some(addresses) = &vout.script_pubkey.addresses {
                 for address in addresses {
-                    if let Some(user_id) = database::get_user_from_address(&pool, address).await? {
+                    if let Some(user_id) = database::get_user_from_address(pool, address).await? {
                         trace!("there is a user for this address: {user_id}",);
                         sum = sum.checked_add(vout.value_sat).unwrap();
                     }
@@ -136,12 +194,15 @@ pub async fn totalwithdrawn(ctx: Context<'_>) -> Result<Amount, Error> {
     let pool = &ctx.data().database;
     let client = ctx.data().verus()?;
 
-    let withdraw_transactions = database::get_all_txids(&pool, "withdraw").await?;
+    let withdraw_transactions = database::get_all_txids(pool, "withdraw").await?;
 
     let mut sum = Amount::ZERO;
 
     for txid in withdraw_transactions {
-        let raw_tx = client.get_raw_transaction_verbose(&txid)?;
+        let raw_tx = rpc_guard::call(&client, "getrawtransaction", move |client| {
+            client.get_raw_transaction_verbose(&txid)
+        })
+        .await?;
         let vout = raw_tx.vout.first().unwrap();
 
         sum = sum.checked_add(vout.value_sat).unwrap();
@@ -151,25 +212,43 @@ pub async fn totalwithdrawn(ctx: Context<'_>) -> Result<Amount, Error> {
 }
 
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_blacklist")]
 pub async fn blacklist(ctx: Context<'_>, user_id: UserId) -> Result<(), Error> {
     debug!("no more fun for {user_id}");
     let pool = &ctx.data().database;
 
-    if let Some(status) = database::get_blacklist_status(&pool, user_id).await? {
-        if status == true {
-            database::set_blacklist_status(&pool, user_id, false).await?;
+    if let Some(status) = database::get_blacklist_status(pool, user_id).await? {
+        if status {
+            database::set_blacklist_status(pool, user_id, false, ctx.author().id).await?;
             if let Ok(mut blacklist) = ctx.data().blacklist.lock() {
                 blacklist.remove(&user_id);
             }
+            audit::record(
+                pool,
+                guild_of(ctx),
+                ctx.author().id,
+                AuditAction::Unblacklist,
+                Some(user_id.to_string()),
+                None,
+            )
+            .await;
             ctx.send(|reply| reply.content(format!("user {user_id} removed from blacklist")))
                 .await?;
             trace!("{user_id} has been removed from blacklist");
         } else {
-            database::set_blacklist_status(&pool, user_id, true).await?;
+            database::set_blacklist_status(pool, user_id, true, ctx.author().id).await?;
             if let Ok(mut blacklist) = ctx.data().blacklist.lock() {
                 blacklist.insert(user_id);
             }
+            audit::record(
+                pool,
+                guild_of(ctx),
+                ctx.author().id,
+                AuditAction::Blacklist,
+                Some(user_id.to_string()),
+                None,
+            )
+            .await;
             ctx.send(|reply| reply.content(format!("user {user_id} blacklisted")))
                 .await?;
 
@@ -183,16 +262,26 @@ pub async fn blacklist(ctx: Context<'_>, user_id: UserId) -> Result<(), Error> {
 }
 
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_funds")]
 pub async fn setwithdrawfee(ctx: Context<'_>, amount: u64) -> Result<(), Error> {
     let withdrawal_fee = &ctx.data().withdrawal_fee;
 
     debug!("fee before changing: {:?}", withdrawal_fee);
 
     let mut write = withdrawal_fee.write().await;
+    let previous = *write;
     *write = Amount::from_sat(amount);
 
     debug!("fee after changing: {:?}", withdrawal_fee);
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::SetWithdrawFee,
+        None,
+        Some(format!("{} sats -> {amount} sats", previous.as_sat())),
+    )
+    .await;
     ctx.send(|reply| reply.content(format!("Withdraw fee set to {} sats", amount)))
         .await?;
 
@@ -200,27 +289,50 @@ pub async fn setwithdrawfee(ctx: Context<'_>, amount: u64) -> Result<(), Error>
 }
 
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_funds")]
 pub async fn rescanfromheight(ctx: Context<'_>, height: u64) -> Result<(), Error> {
+    if rescan(ctx, height).await? {
+        ctx.send(|reply| reply.content("Rescan done")).await?;
+    }
+
+    Ok(())
+}
+
+/// Rescans the chain from `height` and processes the deposits it found. Returns whether the rescan succeeded.
+pub(crate) async fn rescan(ctx: Context<'_>, height: u64) -> Result<bool, Error> {
     trace!("Initiating a rescan from height {height}");
 
     let client = &ctx.data().verus()?;
-    if let Ok(()) = client.rescan_from_height(height) {
-        trace!("rescan done");
+    if rpc_guard::call_once(client, "rescanfromheight", move |client| {
+        client.rescan_from_height(height)
+    })
+    .await
+    .is_err()
+    {
+        trace!("rescan did not succeed");
 
-        tokio::time::sleep(Duration::from_secs(1)).await;
-        ctx.data().tx_processor.process_long_queue().await?;
-        ctx.data().tx_processor.process_short_queue().await?;
-        ctx.send(|reply| reply.content("Rescan done")).await?;
-    } else {
-        trace!("rescan did not succeed")
+        return Ok(false);
     }
+    trace!("rescan done");
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    ctx.data().tx_processor.process_long_queue().await?;
+    ctx.data().tx_processor.process_short_queue().await?;
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::Rescan,
+        None,
+        Some(format!("from height {height}")),
+    )
+    .await;
 
-    Ok(())
+    Ok(true)
 }
 
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_funds")]
 pub async fn withdrawenabled(ctx: Context<'_>, value: bool) -> Result<(), Error> {
     trace!("set withdraws enabled to {value}");
 
@@ -230,6 +342,15 @@ pub async fn withdrawenabled(ctx: Context<'_>, value: bool) -> Result<(), Error>
         *write = value;
     }
 
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::WithdrawalsEnabled,
+        None,
+        Some(value.to_string()),
+    )
+    .await;
     ctx.send(|reply| reply.content(format!("Withdraws enabled: {value}")))
         .await?;
 
@@ -237,24 +358,33 @@ pub async fn withdrawenabled(ctx: Context<'_>, value: bool) -> Result<(), Error>
 }
 
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_funds")]
 pub async fn depositenabled(ctx: Context<'_>, value: bool) -> Result<(), Error> {
     trace!("set deposits enabled to {value}");
 
     {
         let deposits_enabled = &ctx.data().deposits_enabled;
         let mut write = deposits_enabled.write().await;
-        if *write == true && value == false {
+        if *write && !value {
             trace!("need to process possible unprocessed transactions");
 
-            let pool = &ctx.data().database;
-            let tx_proc = Arc::clone(&ctx.data().tx_processor);
-
-            process_stored_txids(pool, tx_proc).await?
+            ctx.data()
+                .tx_processor
+                .process_stored_transactions()
+                .await?
         }
         *write = value;
     }
 
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::DepositsEnabled,
+        None,
+        Some(value.to_string()),
+    )
+    .await;
     ctx.send(|reply| reply.content(format!("Deposits enabled: {value}")))
         .await?;
 
@@ -263,7 +393,7 @@ pub async fn depositenabled(ctx: Context<'_>, value: bool) -> Result<(), Error>
 
 /// Manually checks a tx if it was not caught with rescan
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_funds")]
 pub async fn checktxid(ctx: Context<'_>, txid: Txid) -> Result<(), Error> {
     trace!("manually check {txid}");
     let http = ctx.serenity_context().http.clone();
@@ -271,8 +401,21 @@ pub async fn checktxid(ctx: Context<'_>, txid: Txid) -> Result<(), Error> {
 
     let client = &ctx.data().verus()?;
 
-    if let Ok(raw_tx) = client.get_raw_transaction_verbose(&txid) {
-        process_txid(http, &pool, &raw_tx).await?;
+    if let Ok(raw_tx) = rpc_guard::call(client, "getrawtransaction", move |client| {
+        client.get_raw_transaction_verbose(&txid)
+    })
+    .await
+    {
+        process_txid(http, &pool, client, &ctx.data().translations, &raw_tx).await?;
+        audit::record(
+            &pool,
+            guild_of(ctx),
+            ctx.author().id,
+            AuditAction::CheckTxid,
+            Some(txid.to_string()),
+            None,
+        )
+        .await;
     }
 
     Ok(())
@@ -282,7 +425,7 @@ pub async fn checktxid(ctx: Context<'_>, txid: Txid) -> Result<(), Error> {
 ///
 /// Needs discord_user_id, txid, tx_fee (in sats)
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_funds")]
 pub async fn manuallyaddwithdraw(
     ctx: Context<'_>,
     user_id: UserId,
@@ -305,6 +448,15 @@ pub async fn manuallyaddwithdraw(
     )
     .await?;
 
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::ManualWithdraw,
+        Some(user_id.to_string()),
+        Some(format!("{txid} ({tx_fee} sats fee)")),
+    )
+    .await;
     ctx.send(|reply| reply.ephemeral(true).content(format!("{txid} stored.")))
         .await?;
 
@@ -313,19 +465,53 @@ pub async fn manuallyaddwithdraw(
 
 /// Set maintenance mode on or off
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_bot")]
 pub async fn maintenance(ctx: Context<'_>, value: bool) -> Result<(), Error> {
     trace!("setting maintenance mode to {value}");
 
     {
         let mut write = ctx.data().tx_processor.maintenance.write().await;
-        if *write == true && value == false {
+        if *write && !value {
             trace!("need to process possible unprocessed transactions");
 
-            let pool = &ctx.data().database;
-            let tx_proc = Arc::clone(&ctx.data().tx_processor);
+            ctx.data()
+                .tx_processor
+                .process_stored_transactions()
+                .await?
+        }
+        *write = value;
+    }
+
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::Maintenance,
+        None,
+        Some(value.to_string()),
+    )
+    .await;
+    ctx.send(|reply| reply.content(format!("Maintenance mode set to {value}")))
+        .await?;
+
+    Ok(())
+}
+
+/// Set maintenance mode on or off
+#[instrument(skip(ctx))]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_manage_bot")]
+pub async fn test_17000(ctx: Context<'_>, value: bool) -> Result<(), Error> {
+    trace!("setting maintenance mode to {value}");
 
-            process_stored_txids(pool, tx_proc).await?
+    {
+        let mut write = ctx.data().tx_processor.maintenance.write().await;
+        if *write && !value {
+            trace!("need to process possible unprocessed transactions");
+
+            ctx.data()
+                .tx_processor
+                .process_stored_transactions()
+                .await?
         }
         *write = value;
     }
@@ -336,48 +522,1479 @@ pub async fn maintenance(ctx: Context<'_>, value: bool) -> Result<(), Error> {
     Ok(())
 }
 
-async fn process_stored_txids(
-    pool: &PgPool,
-    tx_proc: Arc<TransactionProcessor>,
+/// Show the most recent entries of the audit log
+#[instrument(skip(ctx))]
+#[poise::command(dm_only, prefix_command, hide_in_help, check = "can_view_audit")]
+pub async fn audit(ctx: Context<'_>, limit: Option<i64>) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let limit = limit.unwrap_or(20).clamp(1, 50);
+
+    let entries = database::get_audit_entries(pool, guild_of(ctx), limit).await?;
+
+    if entries.is_empty() {
+        ctx.send(|reply| reply.content("The audit log is empty"))
+            .await?;
+
+        return Ok(());
+    }
+
+    let lines = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "`{}` <t:{}:f> <@{}> **{}**{}{}",
+                entry.id,
+                entry.created_at.timestamp(),
+                entry.actor,
+                entry.action,
+                entry
+                    .target
+                    .as_ref()
+                    .map(|t| format!(" on {t}"))
+                    .unwrap_or_default(),
+                entry
+                    .details
+                    .as_ref()
+                    .map(|d| format!(" ({d})"))
+                    .unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|reply| {
+        reply.embed(|embed| {
+            embed
+                .title(format!("Audit log (last {})", entries.len()))
+                .description(lines)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the bot roles of users in the server it is used in, or in the main server when used in DMs
+#[instrument(skip(_ctx))]
+#[poise::command(
+    prefix_command,
+    hide_in_help,
+    check = "can_manage_roles",
+    subcommands("grant", "revoke", "list")
+)]
+pub async fn role(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Give a user a bot role
+#[instrument(skip(ctx))]
+#[poise::command(prefix_command, hide_in_help, check = "can_manage_roles")]
+async fn grant(ctx: Context<'_>, user_id: UserId, role: BotRole) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let own_role = authorization::role_of(ctx.data(), &ctx.author().id, guild_id).await?;
+    let current_role = authorization::role_of(ctx.data(), &user_id, guild_id).await?;
+
+    let allowed = own_role.is_some_and(|own| {
+        own.can_manage(role) && current_role.is_none_or(|current| own.can_manage(current))
+    });
+
+    if !allowed {
+        ctx.send(|reply| reply.content(format!("You are not allowed to make {user_id} a {role}")))
+            .await?;
+
+        return Ok(());
+    }
+
+    database::set_bot_role(pool, &user_id, guild_id, role, &ctx.author().id).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::GrantRole,
+        Some(user_id.to_string()),
+        Some(role.as_str().to_string()),
+    )
+    .await;
+
+    ctx.send(|reply| reply.content(format!("{user_id} is now a {role}")))
+        .await?;
+
+    Ok(())
+}
+
+/// Take away the bot role of a user
+#[instrument(skip(ctx))]
+#[poise::command(prefix_command, hide_in_help, check = "can_manage_roles")]
+async fn revoke(ctx: Context<'_>, user_id: UserId) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let own_role = authorization::role_of(ctx.data(), &ctx.author().id, guild_id).await?;
+
+    match database::get_bot_role(pool, &user_id, guild_id).await? {
+        Some(current) if own_role.is_some_and(|own| own.can_manage(current)) => {
+            database::delete_bot_role(pool, &user_id, guild_id).await?;
+            audit::record(
+                pool,
+                guild_id,
+                ctx.author().id,
+                AuditAction::RevokeRole,
+                Some(user_id.to_string()),
+                Some(current.to_string()),
+            )
+            .await;
+
+            ctx.send(|reply| reply.content(format!("{user_id} is no longer a {current}")))
+                .await?;
+        }
+        Some(current) => {
+            ctx.send(|reply| {
+                reply.content(format!("You are not allowed to revoke the {current} role"))
+            })
+            .await?;
+        }
+        None => {
+            ctx.send(|reply| reply.content(format!("{user_id} does not have a bot role")))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// List all users with a bot role
+#[instrument(skip(ctx))]
+#[poise::command(prefix_command, hide_in_help, check = "can_manage_roles")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let roles = database::get_bot_roles(&ctx.data().database, guild_of(ctx)).await?;
+
+    let operators = ctx
+        .data()
+        .owners
+        .iter()
+        .map(|id| format!("<@{id}> ({})", BotRole::Operator));
+    let others = roles
+        .iter()
+        .map(|(user_id, role)| format!("<@{user_id}> ({role})"));
+
+    ctx.send(|reply| {
+        reply.embed(|embed| {
+            embed
+                .title("Bot roles")
+                .description(operators.chain(others).collect::<Vec<_>>().join("\n"))
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Send an announcement to all users or to the announcement channel of every server
+///
+/// DMs skip users that turned notifications off. Delivery happens in the background in small batches, a summary is
+/// posted in this channel when it is done.
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_bot")]
+pub async fn announce(
+    ctx: Context<'_>,
+    #[description = "The message to announce"] message: String,
+    #[description = "Where to send the announcement"] target: AnnouncementTarget,
 ) -> Result<(), Error> {
-    let stored_txids = database::get_stored_txids(&pool).await?;
+    let pool = &ctx.data().database;
 
-    for txid in stored_txids {
-        trace!("processing {txid}");
-        // checks tx and puts them in a queue
-        tx_proc.check_tx(txid).await?;
+    let announcement_id =
+        database::insert_announcement(pool, &ctx.author().id, &message, target).await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::Announce,
+        Some(announcement_id.to_string()),
+        Some(target.as_str().to_string()),
+    )
+    .await;
 
-        // process the queue immediately
-        tx_proc.process_long_queue().await?;
-        tx_proc.process_short_queue().await?;
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Announcement {announcement_id} is being delivered, you will get a summary in this channel when it is done"
+        ))
+    })
+    .await?;
 
-        database::set_stored_txid_to_processed(&pool, &txid).await?;
+    // delivering DMs can take much longer than the 15 minutes an interaction token is valid,
+    // so the summary is sent as a regular message.
+    let http = ctx.serenity_context().http.clone();
+    let pool = pool.clone();
+    let channel_id = ctx.channel_id();
+
+    tokio::spawn(async move {
+        let report =
+            announcement::deliver(http.clone(), pool, announcement_id, message, target).await;
+
+        if let Err(e) = channel_id
+            .send_message(&http, |m| {
+                m.content(format!(
+                    "Announcement {announcement_id}: delivered {}, failed {}",
+                    report.delivered, report.failed
+                ))
+            })
+            .await
+        {
+            error!("could not send the summary of announcement {announcement_id}: {e:?}");
+        }
+    });
+
+    Ok(())
+}
+
+/// Export the settings, treasury ledger and reactdrop history of a server as JSON
+///
+/// The export can be imported in another deployment of the bot with `/importguild`. Balances are not exported, and
+/// the funds of the treasury need to be sent to the wallet of the other deployment separately.
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_bot")]
+pub async fn exportguild(
+    ctx: Context<'_>,
+    #[description = "The id of the server, defaults to this server"] guild_id: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = match guild_id {
+        Some(guild_id) => GuildId(guild_id.parse()?),
+        None => guild_of(ctx),
+    };
+    let pool = &ctx.data().database;
+
+    ctx.defer_ephemeral().await?;
+
+    let export = database::export_guild(pool, guild_id).await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::ExportGuild,
+        Some(guild_id.to_string()),
+        None,
+    )
+    .await;
+
+    let json = export.to_json()?;
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!(
+                "Export of {guild_id}: {} treasury entries, {} reactdrops",
+                export.treasury.len(),
+                export.reactdrops.len()
+            ))
+            .attachment(AttachmentType::Bytes {
+                data: Cow::Owned(json),
+                filename: format!("guild-{guild_id}.json"),
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Import a server export made with `/exportguild` in another deployment of the bot
+///
+/// Refuses to import when this deployment already has a treasury or reactdrop history for the server.
+#[instrument(skip(ctx, file))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_bot")]
+pub async fn importguild(
+    ctx: Context<'_>,
+    #[description = "The JSON file made by /exportguild"] file: Attachment,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+
+    ctx.defer_ephemeral().await?;
+
+    let export = match GuildExport::from_json(&file.download().await?) {
+        Ok(export) => export,
+        Err(e) => {
+            ctx.send(|reply| {
+                reply
+                    .ephemeral(true)
+                    .content(format!("Invalid export: {e}"))
+            })
+            .await?;
+
+            return Ok(());
+        }
+    };
+    let guild_id = GuildId(export.guild_id);
+
+    if database::guild_has_history(pool, guild_id).await? {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(format!(
+                "{guild_id} already has a history in this deployment, not importing"
+            ))
+        })
+        .await?;
+
+        return Ok(());
     }
 
+    database::import_guild(pool, &export).await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::ImportGuild,
+        Some(guild_id.to_string()),
+        Some(format!(
+            "{} treasury entries, {} reactdrops",
+            export.treasury.len(),
+            export.reactdrops.len()
+        )),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Imported {guild_id}: {} treasury entries, {} reactdrops",
+            export.treasury.len(),
+            export.reactdrops.len()
+        ))
+    })
+    .await?;
+
     Ok(())
 }
 
-/// Set maintenance mode on or off
+/// Operate the bot, every subcommand has its own permission check
+#[instrument(skip(_ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    subcommands(
+        "reverse",
+        "sync",
+        "fees",
+        "simulate_drop",
+        "channels",
+        "dbinfo",
+        "withdrawal",
+        "pot",
+        "announce_blocks"
+    )
+)]
+pub async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Post an hourly digest of the Verus network in a channel
+///
+/// The digest shows block milestones, changes of the difficulty and the staking supply, and transactions that moved \
+/// more than the threshold. Leave the channel empty to stop the digest in this server.
 #[instrument(skip(ctx))]
-#[poise::command(dm_only, owners_only, prefix_command, hide_in_help)]
-pub async fn test_17000(ctx: Context<'_>, value: bool) -> Result<(), Error> {
-    trace!("setting maintenance mode to {value}");
+#[poise::command(
+    slash_command,
+    rename = "announce-blocks",
+    hide_in_help,
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn announce_blocks(
+    ctx: Context<'_>,
+    #[description = "The channel for the network digest"] channel: Option<GuildChannel>,
+    #[description = "List transactions of at least this amount, 10000 by default"]
+    #[min = 1]
+    threshold: Option<f64>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let threshold = match threshold {
+        Some(threshold) => Amount::from_vrsc(threshold)?,
+        None => Amount::from_sat(network_digest::DEFAULT_LARGE_TX_THRESHOLD),
+    };
+    let subscription = channel.map(|channel| (channel.id, threshold));
+
+    database::set_network_subscription(pool, guild_id, subscription).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetNetworkDigest,
+        subscription.map(|(channel_id, _)| channel_id.to_string()),
+        subscription.map(|(_, threshold)| threshold.to_string()),
+    )
+    .await;
+
+    let content = match subscription {
+        Some((channel_id, threshold)) => tr!(
+            ctx,
+            "config-digest-set",
+            channel: format!("<#{channel_id}>"),
+            threshold: format::amount(threshold)
+        ),
+        None => tr!(ctx, "config-digest-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Restrict tipping in this server to some channels
+///
+/// As long as no channel is allowed, tipping is allowed in every channel. Once a channel is allowed, the tipping \
+/// commands only work in the allowed channels.
+#[instrument(skip(_ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    guild_only,
+    check = "can_manage_guild",
+    subcommands("allow", "disallow", "list_channels")
+)]
+async fn channels(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Allow tipping in a channel
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, guild_only, check = "can_manage_guild")]
+async fn allow(
+    ctx: Context<'_>,
+    #[description = "The channel to allow tipping in"] channel: GuildChannel,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    if database::allow_tip_channel(pool, guild_id, channel.id).await? {
+        audit::record(
+            pool,
+            guild_id,
+            ctx.author().id,
+            AuditAction::AllowTipChannel,
+            Some(channel.id.to_string()),
+            None,
+        )
+        .await;
+    }
+    ctx.data()
+        .tip_channels
+        .write()
+        .unwrap()
+        .entry(guild_id)
+        .or_default()
+        .insert(channel.id);
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!("Tipping is allowed in <#{}>", channel.id))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stop allowing tipping in a channel
+///
+/// When no allowed channels are left, tipping is allowed in every channel again.
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, guild_only, check = "can_manage_guild")]
+async fn disallow(
+    ctx: Context<'_>,
+    #[description = "The channel to stop allowing tipping in"] channel: GuildChannel,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    if database::disallow_tip_channel(pool, guild_id, channel.id).await? {
+        audit::record(
+            pool,
+            guild_id,
+            ctx.author().id,
+            AuditAction::DisallowTipChannel,
+            Some(channel.id.to_string()),
+            None,
+        )
+        .await;
+    }
+    let restricted = {
+        let mut tip_channels = ctx.data().tip_channels.write().unwrap();
+        if let Some(channels) = tip_channels.get_mut(&guild_id) {
+            channels.remove(&channel.id);
+            if channels.is_empty() {
+                tip_channels.remove(&guild_id);
+            }
+        }
+
+        tip_channels.contains_key(&guild_id)
+    };
+
+    let content = match restricted {
+        true => format!("Tipping is no longer allowed in <#{}>", channel.id),
+        false => {
+            "No channels are allowed anymore, so tipping is allowed in every channel".to_string()
+        }
+    };
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Show the channels that allow tipping
+#[instrument(skip(ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    guild_only,
+    rename = "list",
+    check = "can_manage_guild"
+)]
+async fn list_channels(ctx: Context<'_>) -> Result<(), Error> {
+    let mut channels = ctx
+        .data()
+        .tip_channels
+        .read()
+        .unwrap()
+        .get(&guild_of(ctx))
+        .map(|channels| channels.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+    channels.sort();
+
+    let content = match channels.is_empty() {
+        true => "Tipping is allowed in every channel".to_string(),
+        false => format!(
+            "Tipping is allowed in {}",
+            channels
+                .iter()
+                .map(|channel_id| format!("<#{channel_id}>"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Reverse a tip event (a role tip, soak or reactdrop) of the last 24 hours
+///
+/// The tips are taken back from the recipients that still have them and returned to the tipper. Recipients that
+/// already spent (part of) their tip, or disputed it, are skipped and reported.
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+async fn reverse(
+    ctx: Context<'_>,
+    #[description = "The id of the tip event"] event_id: String,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let event_id = event_id.trim();
+
+    if !confirm(ctx, format!("Reverse all tips of event {event_id}?")).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let content = match database::reverse_tip_event(pool, event_id, &ctx.author().id).await? {
+        Reversal::NotFound => {
+            format!("There is no tip event {event_id} in the last 24 hours")
+        }
+        Reversal::AlreadyReversed => format!("Tip event {event_id} was already reversed"),
+        Reversal::Reversed {
+            tipper,
+            recovered,
+            unrecovered,
+        } => {
+            let total = |tips: &Vec<(UserId, Amount)>| {
+                tips.iter()
+                    .fold(Amount::ZERO, |total, (_, amount)| total + *amount)
+            };
+
+            audit::record(
+                pool,
+                guild_of(ctx),
+                ctx.author().id,
+                AuditAction::ReverseTip,
+                Some(event_id.to_string()),
+                Some(format!(
+                    "recovered {} from {} recipients, not recovered {} from {} recipients",
+                    total(&recovered),
+                    recovered.len(),
+                    total(&unrecovered),
+                    unrecovered.len()
+                )),
+            )
+            .await;
+
+            let mut content = format!(
+                "Reversed tip event {event_id}: returned {} from {} recipient(s) to <@{tipper}>",
+                format::amount(total(&recovered)),
+                recovered.len()
+            );
+            if !unrecovered.is_empty() {
+                content.push_str(&format!(
+                    "\nCould not recover {} from {} recipient(s) that spent or disputed their tip: {}",
+                    format::amount(total(&unrecovered)),
+                    unrecovered.len(),
+                    unrecovered
+                        .iter()
+                        .map(|(recipient, amount)| format!(
+                            "<@{recipient}> ({})",
+                            format::amount(*amount)
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+
+            content
+        }
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Settle or refund a withdrawal that the bot could not resolve
+///
+/// A withdrawal whose send timed out, or whose operation the daemon does not know anymore, stays reserved until an \
+/// operator checks on chain whether it went through.
+#[instrument(skip(_ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    check = "can_manage_funds",
+    subcommands("settle_withdrawal", "refund_withdrawal")
+)]
+async fn withdrawal(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
 
+/// Settle a reserved withdrawal that went through
+#[instrument(skip(ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    rename = "settle",
+    check = "can_manage_funds"
+)]
+async fn settle_withdrawal(
+    ctx: Context<'_>,
+    #[description = "The id of the withdrawal"] uuid: String,
+    #[description = "The transaction that paid it out"] txid: String,
+) -> Result<(), Error> {
+    let (Ok(uuid), Ok(txid)) = (Uuid::parse_str(uuid.trim()), Txid::from_str(txid.trim())) else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content("Invalid withdrawal id or txid")
+        })
+        .await?;
+
+        return Ok(());
+    };
+    let Some(withdrawal) = pending_withdrawal(ctx, uuid).await? else {
+        return Ok(());
+    };
+
+    if !confirm(
+        ctx,
+        format!(
+            "Settle withdrawal {uuid} of <@{}> ({} and {} in fees) with {txid}?",
+            withdrawal.user_id,
+            format::amount(withdrawal.amount),
+            format::amount(withdrawal.tx_fee + withdrawal.bot_fee)
+        ),
+    )
+    .await?
     {
-        let mut write = ctx.data().tx_processor.maintenance.write().await;
-        if *write == true && value == false {
-            trace!("need to process possible unprocessed transactions");
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let pool = &ctx.data().database;
+    wallet::settle_pending_withdrawal(pool, &withdrawal, &txid).await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::SettleWithdrawal,
+        Some(uuid.to_string()),
+        Some(format!(
+            "{} of {} with {txid}",
+            withdrawal.amount, withdrawal.user_id
+        )),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!("Withdrawal {uuid} is settled with {txid}"))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Refund a reserved withdrawal that was never sent or failed
+#[instrument(skip(ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    rename = "refund",
+    check = "can_manage_funds"
+)]
+async fn refund_withdrawal(
+    ctx: Context<'_>,
+    #[description = "The id of the withdrawal"] uuid: String,
+) -> Result<(), Error> {
+    let Ok(uuid) = Uuid::parse_str(uuid.trim()) else {
+        ctx.send(|reply| reply.ephemeral(true).content("Invalid withdrawal id"))
+            .await?;
+
+        return Ok(());
+    };
+    let Some(withdrawal) = pending_withdrawal(ctx, uuid).await? else {
+        return Ok(());
+    };
+
+    if !confirm(
+        ctx,
+        format!(
+            "Refund withdrawal {uuid} of <@{}> ({} and {} in fees)? Only do this when it is certain that it was not sent.",
+            withdrawal.user_id,
+            format::amount(withdrawal.amount),
+            format::amount(withdrawal.tx_fee + withdrawal.bot_fee)
+        ),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let pool = &ctx.data().database;
+    database::refund_withdrawal(
+        pool,
+        &withdrawal.user_id,
+        withdrawal.amount,
+        withdrawal.tx_fee,
+        withdrawal.bot_fee,
+        &uuid,
+    )
+    .await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::RefundWithdrawal,
+        Some(uuid.to_string()),
+        Some(format!("{} of {}", withdrawal.amount, withdrawal.user_id)),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Withdrawal {uuid} is refunded to <@{}>",
+            withdrawal.user_id
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The reserved withdrawal with this id, or `None` after telling the author there is none.
+async fn pending_withdrawal(
+    ctx: Context<'_>,
+    uuid: Uuid,
+) -> Result<Option<PendingWithdrawal>, Error> {
+    let withdrawal = database::get_pending_withdrawals(&ctx.data().database)
+        .await?
+        .into_iter()
+        .find(|withdrawal| withdrawal.uuid == uuid);
+    if withdrawal.is_none() {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(format!("Withdrawal {uuid} is not reserved"))
+        })
+        .await?;
+    }
+
+    Ok(withdrawal)
+}
+
+/// Settle or refund the withdrawal of a pot that the bot could not resolve
+///
+/// A pot whose send timed out, or whose operation the daemon does not know anymore, stays withdrawing until an \
+/// operator checks on chain whether it went through.
+#[instrument(skip(_ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    check = "can_manage_funds",
+    subcommands("settle_pot", "refund_pot")
+)]
+async fn pot(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Pay out a withdrawing pot whose withdrawal went through
+#[instrument(skip(ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    rename = "settle",
+    check = "can_manage_funds"
+)]
+async fn settle_pot(
+    ctx: Context<'_>,
+    #[description = "The id of the pot"] pot_id: i64,
+    #[description = "The transaction that paid it out"] txid: String,
+) -> Result<(), Error> {
+    let Ok(txid) = Txid::from_str(txid.trim()) else {
+        ctx.send(|reply| reply.ephemeral(true).content("Invalid txid"))
+            .await?;
+
+        return Ok(());
+    };
+    let Some(pot) = withdrawing_pot(ctx, pot_id).await? else {
+        return Ok(());
+    };
+    let Some((opid, payout)) = pot.sent else {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(format!(
+                "The withdrawal of pot {pot_id} was never sent, it can only be refunded"
+            ))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    if !confirm(
+        ctx,
+        format!("Pay out pot {pot_id} of <@{}> with {txid}?", pot.created_by),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let pool = &ctx.data().database;
+    pot::settle_withdrawal(
+        pool,
+        pot_id,
+        pot.created_by,
+        &payout,
+        opid.as_deref(),
+        &txid,
+    )
+    .await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::SettleWithdrawal,
+        Some(format!("pot {pot_id}")),
+        Some(format!("with {txid}")),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!("Pot {pot_id} is paid out with {txid}"))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Refund the contributors of a withdrawing pot whose withdrawal was never sent or failed
+#[instrument(skip(ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    rename = "refund",
+    check = "can_manage_funds"
+)]
+async fn refund_pot(
+    ctx: Context<'_>,
+    #[description = "The id of the pot"] pot_id: i64,
+) -> Result<(), Error> {
+    let Some(pot) = withdrawing_pot(ctx, pot_id).await? else {
+        return Ok(());
+    };
+
+    if !confirm(
+        ctx,
+        format!(
+            "Refund the contributors of pot {pot_id} of <@{}>? Only do this when it is certain that it was not sent.",
+            pot.created_by
+        ),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let pool = &ctx.data().database;
+    // the pot is closed again first, as only a closed pot can be cancelled
+    let refunds = match database::revert_pot_withdrawal(pool, pot_id).await? {
+        true => database::cancel_pot(pool, pot_id).await?,
+        false => None,
+    };
+    let Some(refunds) = refunds else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(format!("Pot {pot_id} is no longer withdrawing"))
+        })
+        .await?;
+
+        return Ok(());
+    };
+    let refunded = refunds
+        .iter()
+        .fold(Amount::ZERO, |total, (_, amount)| total + *amount);
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::RefundWithdrawal,
+        Some(format!("pot {pot_id}")),
+        Some(format!("{refunded} to {} contributor(s)", refunds.len())),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Pot {pot_id} is cancelled, {} is refunded to {} contributor(s)",
+            format::amount(refunded),
+            refunds.len()
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The withdrawing pot with this id, or `None` after telling the author there is none.
+async fn withdrawing_pot(ctx: Context<'_>, pot_id: i64) -> Result<Option<WithdrawingPot>, Error> {
+    let pot = database::get_withdrawing_pots(&ctx.data().database)
+        .await?
+        .into_iter()
+        .find(|pot| pot.id == pot_id);
+    if pot.is_none() {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(format!("Pot {pot_id} is not withdrawing"))
+        })
+        .await?;
+    }
+
+    Ok(pot)
+}
 
-            let pool = &ctx.data().database;
-            let tx_proc = Arc::clone(&ctx.data().tx_processor);
+/// Load balances of a legacy tipbot from a CSV, for users to claim with `/claim legacy`
+///
+/// The CSV has `discord_id,amount` lines, with amounts in VRSC. Users that already have an imported balance are skipped. Move the funds from the legacy
+/// tipbot to the wallet of this bot before users start claiming.
+#[instrument(skip(ctx, file))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+pub async fn importlegacy(
+    ctx: Context<'_>,
+    #[description = "The CSV file with discord_id,amount lines"] file: Attachment,
+    #[description = "The name of the legacy tipbot"] source: String,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
 
-            process_stored_txids(pool, tx_proc).await?
+    ctx.defer_ephemeral().await?;
+
+    let csv = String::from_utf8(file.download().await?)?;
+    let balances = match legacy_import::parse_csv(&csv) {
+        Ok(balances) => balances,
+        Err(e) => {
+            ctx.send(|reply| reply.ephemeral(true).content(format!("Invalid CSV, {e}")))
+                .await?;
+
+            return Ok(());
         }
-        *write = value;
+    };
+
+    let total = balances
+        .iter()
+        .fold(Amount::ZERO, |total, (_, amount)| total + *amount);
+    let imported = database::import_legacy_balances(pool, &source, &balances).await?;
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::ImportLegacy,
+        Some(source.clone()),
+        Some(format!(
+            "{imported} of {} balances, {total} in the file",
+            balances.len()
+        )),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Imported {imported} of {} balances from {source} ({} in the file). Users that already had an imported balance were skipped.",
+            balances.len(),
+            format::amount(total)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Make a proof-of-reserves report, signed by the daemon, to publish to the community
+///
+/// The report is attached as JSON. Its hash is signed with `address`, or with the address that holds the most funds.
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+pub async fn proofofreserves(
+    ctx: Context<'_>,
+    #[description = "The address or identity of the wallet to sign with"] address: Option<String>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let client = ctx.data().verus()?;
+
+    ctx.defer_ephemeral().await?;
+
+    let height = rpc_guard::call(&client, "getblockchaininfo", |client| {
+        client.get_blockchain_info()
+    })
+    .await?
+    .blocks;
+    let liabilities = database::get_liabilities(pool).await?;
+    let addresses = proof_of_reserves::address_balances(&client).await?;
+    let report = Report::new(height, liabilities, addresses);
+
+    let Some(address) = address.or_else(|| report.addresses.first().map(|a| a.address.clone()))
+    else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content("The wallet has no funds, there is nothing to prove")
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let json = report.to_json()?;
+    let hash = proof_of_reserves::hash(&json);
+    let signature = proof_of_reserves::sign(&client, &address, &hash).await?;
+
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::ProofOfReserves,
+        Some(hash.clone()),
+        Some(format!(
+            "liabilities {}, reserves {}",
+            Amount::from_sat(report.liabilities.total),
+            Amount::from_sat(report.total_reserves)
+        )),
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!(
+                "Proof of reserves at height {height}
+Liabilities: {}
+Reserves: {} ({:.2}%)
+Hash: `{hash}`
+Signed by: `{address}`
+Signature: `{signature}`
+Verify with: `verus verifymessage \"{address}\" \"{signature}\" \"{hash}\"`",
+                format::amount(Amount::from_sat(report.liabilities.total)),
+                format::amount(Amount::from_sat(report.total_reserves)),
+                report.reserve_ratio() * 100.0
+            ))
+            .attachment(AttachmentType::Bytes {
+                data: Cow::Owned(json),
+                filename: format!("proof-of-reserves-{height}.json"),
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Show the progress of the daemon while it is reindexing or syncing
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_view_status")]
+async fn sync(ctx: Context<'_>) -> Result<(), Error> {
+    let client = ctx.data().verus()?;
+    let info = rpc_guard::call(&client, "getblockchaininfo", |client| {
+        client.get_blockchain_info()
+    })
+    .await?;
+    let sync = ctx.data().sync.read().await.clone();
+
+    let content = match sync {
+        None => format!(
+            "The daemon is in sync at block {} ({} headers)",
+            info.blocks, info.headers
+        ),
+        Some(sync) => format!(
+            "The daemon is syncing, user commands that change balances are paused
+Verification progress: {:.2}%
+Blocks: {} of {} headers ({} behind)
+Estimated time remaining: {}",
+            info.verificationprogress * 100.0,
+            info.blocks,
+            info.headers,
+            info.headers.saturating_sub(info.blocks),
+            sync.remaining
+                .map(|remaining| {
+                    let minutes = remaining.as_secs() / 60;
+                    format!("{}h {}m", minutes / 60, minutes % 60)
+                })
+                .unwrap_or("unknown, check again in a minute".to_string())
+        ),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// The number of applied migrations that `/admin dbinfo` lists.
+const DBINFO_MIGRATIONS: usize = 5;
+
+/// Show the applied database migrations, the size of the main tables and the connection pool
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_bot")]
+async fn dbinfo(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let applied = schema::applied_versions(pool).await?;
+    let embedded = schema::embedded_versions();
+    let row_counts = database::get_table_row_counts(pool).await?;
+
+    let mut content = format!(
+        "Migrations: {} applied, {} in this version of the bot",
+        applied.len(),
+        embedded.len()
+    );
+    for version in applied.iter().rev().take(DBINFO_MIGRATIONS) {
+        content.push_str(&format!(
+            "\n- {version} {}",
+            schema::description(*version).unwrap_or_else(|| String::from("(unknown)"))
+        ));
     }
 
-    ctx.send(|reply| reply.content(format!("Maintenance mode set to {value}")))
+    content.push_str("\nRows:");
+    for (table, count) in row_counts {
+        content.push_str(&format!("\n- {table}: {count}"));
+    }
+
+    content.push_str(&format!(
+        "\nPool: {} connection(s), {} idle, {} at most",
+        pool.size(),
+        pool.num_idle(),
+        pool.options().get_max_connections()
+    ));
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Show the withdrawal fees the bot collected
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+async fn fees(
+    ctx: Context<'_>,
+    #[description = "Only count the fees of the last days"]
+    #[min = 1]
+    days: Option<u32>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+
+    let (total, total_withdrawals) = database::get_collected_bot_fees(pool, None).await?;
+    let mut content = format!(
+        "Bot fees collected: {} from {total_withdrawals} withdrawal(s)
+Current fee: {}",
+        format::amount(total),
+        ctx.data().settings.application.bot_withdrawal_fee
+    );
+
+    if let Some(days) = days {
+        let since = chrono::Utc::now() - chrono::Duration::days(days as i64);
+        let (collected, withdrawals) = database::get_collected_bot_fees(pool, Some(since)).await?;
+        content.push_str(&format!(
+            "\nIn the last {days} day(s): {} from {withdrawals} withdrawal(s)",
+            format::amount(collected)
+        ));
+    }
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
         .await?;
 
     Ok(())
 }
+
+/// Show what a reactdrop would pay out if it ended now
+///
+/// Runs the payout calculation on the current reactions without tipping anyone or changing the reactdrop. For a \
+/// raffle, the winners are one random draw.
+#[instrument(skip(ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    rename = "simulate-drop",
+    check = "can_manage_funds"
+)]
+async fn simulate_drop(
+    ctx: Context<'_>,
+    #[description = "The link to the message of the reactdrop"] message_link: String,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+
+    let reactdrop = match reactdrop::parse_message_link(&message_link) {
+        Some((channel_id, message_id)) => {
+            database::get_reactdrop(pool, channel_id, message_id).await?
+        }
+        None => None,
+    };
+    let Some(reactdrop) = reactdrop else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content("That link is not the message of a reactdrop")
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    ctx.defer_ephemeral().await?;
+
+    let message = reactdrop
+        .channel_id
+        .message(ctx.http(), reactdrop.message_id)
+        .await?;
+    let simulation =
+        reactdrop::simulate(ctx.serenity_context(), pool, &reactdrop, &message).await?;
+
+    let mut content = format!(
+        "Reactdrop of <@{}> for {} ({}, ends <t:{}:R>)
+Reactions: {}, of which {} from bots
+Participants: {}",
+        reactdrop.author,
+        format::amount(reactdrop.tip_amount),
+        reactdrop.status,
+        reactdrop.finish_time.timestamp(),
+        simulation.reactions,
+        simulation.bots,
+        simulation.participants,
+    );
+    if let Some(role_id) = reactdrop.required_role {
+        content.push_str(&format!(
+            ", not counting {} without the required role <@&{role_id}>",
+            simulation.without_role
+        ));
+    }
+    if let Some(winners) = reactdrop.winners {
+        content.push_str(&format!(
+            "\nRaffle for {winners} winner(s), drawn: {}",
+            simulation
+                .recipients
+                .iter()
+                .map(|user_id| format!("<@{user_id}>"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    match &simulation.split {
+        Some(split) => content.push_str(&format!(
+            "\nRecipients: {}, each getting {}
+Tipped: {}, dust returned to the author: {}",
+            simulation.recipients.len(),
+            format::amount(split.share),
+            format::amount(split.tipped(simulation.recipients.len())),
+            format::amount(split.dust)
+        )),
+        None => content.push_str("\nNobody would be tipped"),
+    }
+    content.push_str(&format!(
+        "\nBalance of the author: {}{}",
+        format::amount(simulation.author_balance),
+        match simulation.affordable() {
+            true => "",
+            false => ", which is not enough: the payout would fail",
+        }
+    ));
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Show or set the referral rewards, or fund the referral pool
+///
+/// Leave everything empty to show the rewards and what is left in the pool. Funding moves the amount from your own
+/// balance into the pool. Rewards are only paid while the pool can pay both of them.
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+pub async fn referralrewards(
+    ctx: Context<'_>,
+    #[description = "The reward of the user that referred someone, in VRSC"]
+    #[min = 0]
+    referrer_reward: Option<f64>,
+    #[description = "The reward of the user that was referred, in VRSC"]
+    #[min = 0]
+    referee_reward: Option<f64>,
+    #[description = "An amount to move from your balance into the referral pool, in VRSC"]
+    #[min = 0.00000001]
+    fund: Option<f64>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let (current_referrer_reward, current_referee_reward) =
+        database::get_referral_rewards(pool).await?;
+    let mut content = vec![];
+
+    if referrer_reward.is_some() || referee_reward.is_some() {
+        let referrer_reward = referrer_reward
+            .map(Amount::from_vrsc)
+            .transpose()?
+            .unwrap_or(current_referrer_reward);
+        let referee_reward = referee_reward
+            .map(Amount::from_vrsc)
+            .transpose()?
+            .unwrap_or(current_referee_reward);
+
+        database::set_referral_rewards(pool, referrer_reward, referee_reward).await?;
+        audit::record(
+            pool,
+            guild_of(ctx),
+            ctx.author().id,
+            AuditAction::SetReferralRewards,
+            None,
+            Some(format!("{referrer_reward} / {referee_reward}")),
+        )
+        .await;
+
+        content.push(format!(
+            "Referral rewards set to {} for the referrer and {} for the referee",
+            format::amount(referrer_reward),
+            format::amount(referee_reward)
+        ));
+    }
+
+    if let Some(fund) = fund {
+        let amount = Amount::from_vrsc(fund)?;
+        if database::fund_referral_pool(pool, &ctx.author().id, amount).await? {
+            audit::record(
+                pool,
+                guild_of(ctx),
+                ctx.author().id,
+                AuditAction::FundReferralPool,
+                None,
+                Some(amount.to_string()),
+            )
+            .await;
+
+            content.push(format!(
+                "Moved {} into the referral pool",
+                format::amount(amount)
+            ));
+        } else {
+            content.push(format!(
+                "Your balance is too low to fund the referral pool with {}",
+                format::amount(amount)
+            ));
+        }
+    }
+
+    if content.is_empty() {
+        content.push(format!(
+            "Referral rewards: {} for the referrer, {} for the referee",
+            format::amount(current_referrer_reward),
+            format::amount(current_referee_reward)
+        ));
+    }
+    content.push(format!(
+        "Referral pool: {}",
+        format::amount(database::get_referral_pool(pool).await?)
+    ));
+
+    ctx.send(|reply| reply.ephemeral(true).content(content.join("\n")))
+        .await?;
+
+    Ok(())
+}
+
+/// Relay news to the servers that subscribed to its topic with `/config news`
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_bot")]
+pub async fn postnews(
+    ctx: Context<'_>,
+    #[description = "The topic of the news, e.g. announcements"] topic: String,
+    #[description = "The news"]
+    #[max_length = 256]
+    title: String,
+    #[description = "A link to more about it"] link: Option<String>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let topic = topic.trim().to_lowercase();
+    let item = NewsItem {
+        id: ctx.id().to_string(),
+        title,
+        link,
+    };
+
+    audit::record(
+        pool,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::PostNews,
+        Some(topic.clone()),
+        Some(item.title.clone()),
+    )
+    .await;
+    ctx.defer_ephemeral().await?;
+
+    let posted = news::relay(ctx.http(), pool, &topic, &item).await?;
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!("The news was posted in {posted} channel(s)"))
+    })
+    .await?;
+
+    Ok(())
+}
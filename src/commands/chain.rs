@@ -1,46 +1,89 @@
-use std::collections::HashMap;
-
-use chrono::{DateTime, Utc};
-use poise::serenity_prelude::Colour;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{
+    ButtonStyle, CollectComponentInteraction, Colour, CreateComponents, CreateEmbed,
+    InteractionResponseType,
+};
 use serde::Deserialize;
-use tracing::{debug, instrument};
+use serde_json::json;
+use tracing::{debug, instrument, trace};
 use uuid::Uuid;
 use vrsc::Amount;
-use vrsc_rpc::RpcApi;
+use vrsc_rpc::{Client as VerusClient, RpcApi};
 
-use crate::{Context, Error};
+use crate::{
+    authorization::is_operator,
+    currency_history,
+    i18n::tr,
+    price::{self, Prices, Source},
+    util::{database, embed::InfoEmbed, format, rpc_guard},
+    Context, Error,
+};
 
 /// Show information about Verus blockchain.
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
 #[poise::command(track_edits, slash_command, category = "Miscellaneous")]
-pub async fn chaininfo(ctx: Context<'_>) -> Result<(), Error> {
-    let client = ctx.data().verus()?;
-    let blockchain_info = client.get_blockchain_info()?;
-    let mining_info = client.get_mining_info()?;
+pub async fn chaininfo(
+    ctx: Context<'_>,
+    #[description = "Skip the cache (owners only)"] fresh: Option<bool>,
+) -> Result<(), Error> {
+    let chain_info = match ctx.data().chain_info.get(&()) {
+        Some(chain_info) if !skip_cache(ctx, fresh) => chain_info,
+        _ => {
+            let client = ctx.data().verus()?;
+            let blockchain_info = rpc_guard::call(&client, "getblockchaininfo", |client| {
+                client.get_blockchain_info()
+            })
+            .await?;
+            let mining_info =
+                rpc_guard::call(&client, "getmininginfo", |client| client.get_mining_info())
+                    .await?;
+            let chain_info = ChainInfo {
+                blocks: blockchain_info.blocks,
+                difficulty: blockchain_info.difficulty,
+                staking_supply: mining_info.stakingsupply,
+                average_block_fees: mining_info.averageblockfees,
+            };
+            ctx.data().chain_info.insert((), chain_info.clone());
+
+            chain_info
+        }
+    };
 
-    let testnet_name = match ctx.data().settings.application.testnet {
-        true => "Verus (testnet)",
-        false => "Verus",
+    let title = match ctx.data().settings.application.testnet {
+        true => tr!(ctx, "chaininfo-title-testnet"),
+        false => tr!(ctx, "chaininfo-title"),
     };
 
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .field(tr!(ctx, "chaininfo-height"), chain_info.blocks, false)
+        .detail(
+            tr!(ctx, "chaininfo-difficulty"),
+            chain_info.difficulty,
+            false,
+        )
+        .field(
+            tr!(ctx, "chaininfo-staking"),
+            format::amount(Amount::from_vrsc(chain_info.staking_supply).unwrap()),
+            false,
+        )
+        .detail(
+            tr!(ctx, "chaininfo-block-fees"),
+            format::amount(Amount::from_vrsc(chain_info.average_block_fees).unwrap()),
+            false,
+        );
+
     ctx.send(|reply| {
         reply
-            .embed(|embed| {
-                embed
-                    .title(format!("{} info", testnet_name))
-                    .field("height", blockchain_info.blocks, false)
-                    .field("difficulty", blockchain_info.difficulty, false)
-                    .field(
-                        "amount staking",
-                        Amount::from_vrsc(mining_info.stakingsupply).unwrap(),
-                        false,
-                    )
-                    .field(
-                        "average block fees",
-                        Amount::from_vrsc(mining_info.averageblockfees).unwrap(),
-                        false,
-                    )
-            })
+            .embed(|embed| fields.fill(embed.title(title)))
             .ephemeral(true)
     })
     .await?;
@@ -54,16 +97,17 @@ pub async fn chaininfo(ctx: Context<'_>) -> Result<(), Error> {
 pub async fn peerinfo(ctx: Context<'_>) -> Result<(), Error> {
     let client = &ctx.data().verus()?;
 
-    let peer_info = client
-        .get_peer_info()?
+    let peer_info = rpc_guard::call(client, "getpeerinfo", |client| client.get_peer_info())
+        .await?
         .into_iter()
-        .filter(|peer| peer.inbound == false)
+        .filter(|peer| !peer.inbound)
         .collect::<Vec<_>>();
 
     ctx.send(|reply| {
-        reply.ephemeral(true).content(format!(
-            "Publicly available peers:```{}```",
-            peer_info
+        reply.ephemeral(true).content(tr!(
+            ctx,
+            "peerinfo-peers",
+            peers: peer_info
                 .into_iter()
                 .map(|peer| peer.addr)
                 .collect::<Vec<_>>()
@@ -78,68 +122,77 @@ pub async fn peerinfo(ctx: Context<'_>) -> Result<(), Error> {
 /// Show VRSC price information
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
 #[poise::command(slash_command, category = "Miscellaneous")]
-pub async fn price(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn price(
+    ctx: Context<'_>,
+    #[description = "Skip the cache (owners only)"] fresh: Option<bool>,
+) -> Result<(), Error> {
     ctx.defer().await?;
 
-    let resp: CoinPaprika =
-        reqwest::get("https://api.coinpaprika.com/v1/tickers/vrsc-verus-coin?quotes=USD,BTC")
-            .await?
-            .json()
-            .await?;
+    let prices = vrsc_prices(ctx, skip_cache(ctx, fresh)).await?;
+    let ticker = prices.ticker.as_ref();
+    let usd_quote = ticker.and_then(|ticker| ticker.quotes.get("USD"));
 
-    let btc_price = resp
-        .quotes
-        .get("BTC")
-        .and_then(|obj| Some(obj.price))
-        .unwrap_or(0.0);
-
-    let usd_price = resp
-        .quotes
-        .get("USD")
-        .and_then(|obj| Some(obj.price))
-        .unwrap_or(0.0);
+    let price_up = ticker
+        .and_then(|ticker| ticker.quotes.get("BTC"))
+        .map(|obj| obj.percent_change_24h)
+        .unwrap_or(0.0)
+        .is_sign_positive();
 
-    let usd_volume = resp
-        .quotes
-        .get("USD")
-        .and_then(|obj| Some(obj.volume_24h))
-        .unwrap_or(0.0);
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields.field(
+        tr!(ctx, "price-usd"),
+        format::usd(prices.usd().unwrap_or(0.0)),
+        true,
+    );
+    if let Some(btc_price) = prices.btc() {
+        fields.field(tr!(ctx, "price-btc"), format::btc(btc_price), true);
+    }
+    for quote in &prices.quotes {
+        let name = match quote.source {
+            Source::Bridge => tr!(ctx, "price-onchain", basket: price::BRIDGE),
+            source => source.name().to_string(),
+        };
+        fields.detail(name, format::usd(quote.usd), true);
+    }
+    if let (Some(ticker), Some(usd_quote)) = (ticker, usd_quote) {
+        fields
+            .detail(
+                tr!(ctx, "price-from-ath"),
+                usd_quote.percent_from_price_ath,
+                false,
+            )
+            .detail(
+                tr!(ctx, "price-volume"),
+                format::usd(usd_quote.volume_24h),
+                false,
+            )
+            .detail(
+                tr!(ctx, "price-circulating-supply"),
+                format::number(ticker.circulating_supply as f64, 0),
+                false,
+            );
+    }
 
-    let price_up = resp
+    let sources = prices
         .quotes
-        .get("BTC")
-        .and_then(|obj| Some(obj.percent_change_24h))
-        .unwrap_or(0.0)
-        .is_sign_positive();
+        .iter()
+        .map(|quote| quote.source.name())
+        .collect::<Vec<_>>()
+        .join(", ");
 
     ctx.send(|reply| {
         reply.embed(|embed| {
-            embed
-                .title("VRSC price information")
-                .field("USD price", format!("$ {:.4} ", &usd_price), true)
-                .field("BTC price", format!("₿ {:.8} ", &btc_price), true)
-                .field(
-                    "% from ATH (USD)",
-                    resp.quotes
-                        .get("USD")
-                        .and_then(|obj| Some(obj.percent_from_price_ath))
-                        .unwrap_or(0.0),
-                    false,
-                )
-                .field("Volume 24h (USD)", format!("{:.8}", &usd_volume), false)
-                .field(
-                    "Circulating supply (VRSC)",
-                    format!("{}", resp.circulating_supply),
-                    false,
-                )
-                .timestamp(resp.last_updated)
+            fields
+                .fill(embed)
+                .title(tr!(ctx, "price-title"))
+                .timestamp(ticker.map_or_else(Utc::now, |ticker| ticker.last_updated))
                 .color(match price_up {
                     true => Colour::DARK_GREEN,
                     false => Colour::RED,
                 })
                 .footer(|footer| {
                     footer
-                        .text("Data from CoinPaprika")
+                        .text(tr!(ctx, "price-data-source", source: sources.as_str()))
                         .icon_url("https://i.imgur.com/wwH60Uf.png")
                 })
         })
@@ -150,127 +203,772 @@ pub async fn price(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 /// Show currency information
+///
+/// For basket currencies the reserves are shown with the price of the basket in each reserve and the weight of the \
+/// reserve, together with the value of the supply and of the reserves (TVL) in USD.
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
 #[poise::command(slash_command, category = "Miscellaneous")]
-pub async fn currency(ctx: Context<'_>, currency: String) -> Result<(), Error> {
+pub async fn currency(
+    ctx: Context<'_>,
+    currency: String,
+    #[description = "Skip the cache (owners only)"] fresh: Option<bool>,
+) -> Result<(), Error> {
     let verus_client = ctx.data().verus()?;
-    let price: CoinPaprika =
-        reqwest::get("https://api.coinpaprika.com/v1/tickers/vrsc-verus-coin?quotes=USD,BTC")
-            .await?
-            .json()
-            .await?;
-
-    let usd_price = price
-        .quotes
-        .get("USD")
-        .and_then(|obj| Some(obj.price))
+    let usd_price = vrsc_prices(ctx, skip_cache(ctx, fresh))
+        .await?
+        .usd()
         .unwrap_or(0.0);
 
-    let mut fields = vec![];
+    let mut fields = InfoEmbed::new(ctx).await?;
 
-    if let Ok(currency) = verus_client.get_currency(&currency) {
-        if let Some(currency_state) = currency.bestcurrencystate {
-            fields.push((
-                "Supply",
-                format!("{}", currency_state.supply.as_vrsc()),
-                false,
-            ));
-
-            if let Some(reserve_currencies) = currency_state.reservecurrencies.as_ref() {
-                debug!("{:#?}", &reserve_currencies);
-                let mut baskets = reserve_currencies
-                    .iter()
-                    .filter_map(|rc| {
-                        let name = ctx.data().to_currency_name(&rc.currencyid).ok()?;
-                        Some((name, rc.reserves.as_vrsc()))
-                    })
-                    .collect::<Vec<(String, f64)>>();
-
-                let longest_name_len = baskets.iter().max_by_key(|x| x.0.len()).unwrap().0.len();
-                let longest_value_len = format!(
-                    "{}",
-                    baskets
-                        .iter()
-                        .map(|t| t.1 * 100_000_000.0)
-                        .reduce(|acc, amount| amount.max(acc))
-                        .unwrap()
-                )
-                .len();
-
-                debug!("{longest_value_len}");
-
-                baskets.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
-
-                let tvl_str = format!(
-                    "```{}```",
-                    baskets
-                        .iter()
-                        .map(|tvl| format!(
-                            "{name:<max_name_len$}: {value:>max$.*}",
-                            8,
-                            name = tvl.0,
-                            value = tvl.1,
-                            max_name_len = longest_name_len + 1,
-                            max = longest_value_len + 1
-                        ))
-                        .collect::<Vec<_>>()
-                        .join("\n")
+    let currency_name = currency.clone();
+    if let Ok(currency) = rpc_guard::call(&verus_client, "getcurrency", move |client| {
+        client.get_currency(&currency_name)
+    })
+    .await
+    {
+        let currency_state = currency.bestcurrencystate;
+        fields.field(
+            tr!(ctx, "currency-supply"),
+            format::amount_in(currency_state.supply, &currency.fullyqualifiedname),
+            false,
+        );
+
+        if let Some(reserve_currencies) = currency_state.reservecurrencies.as_ref() {
+            debug!("{:#?}", &reserve_currencies);
+            let (_, native_name) =
+                currency_history::native_currency(ctx.data().settings.application.testnet);
+
+            let mut reserves = vec![];
+            for rc in reserve_currencies {
+                let Ok(name) = ctx.data().to_currency_name(&rc.currencyid).await else {
+                    continue;
+                };
+                reserves.push(Reserve {
+                    name,
+                    reserves: rc.reserves,
+                    price_in_reserve: rc.priceinreserve,
+                    weight: rc.weight,
+                });
+            }
+            reserves.sort_by_key(|a| a.name.to_lowercase());
+
+            if !reserves.is_empty() {
+                fields.detail(
+                    tr!(ctx, "currency-baskets"),
+                    reserve_table(&reserves),
+                    false,
                 );
+            }
+
+            // the native currency is found by name, so this works on mainnet (VRSC) and testnet (VRSCTEST)
+            if let Some(native) = reserves.iter().find(|reserve| reserve.name == native_name) {
+                let vrsc_value_of_currency_supply =
+                    currency_state.supply.as_vrsc() * native.price_in_reserve.as_vrsc();
+
+                fields
+                    .field(
+                        tr!(ctx, "currency-value-usd"),
+                        format::usd(vrsc_value_of_currency_supply * usd_price),
+                        true,
+                    )
+                    .field(
+                        tr!(ctx, "currency-tvl-usd"),
+                        format::usd(basket_tvl(&reserves, native.price_in_reserve) * usd_price),
+                        true,
+                    );
+            }
+        }
+
+        ctx.send(|reply| {
+            reply.embed(|embed| {
+                fields
+                    .fill(embed)
+                    .title(currency.fullyqualifiedname.to_string())
+            })
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Whether the author asked to skip the caches and is allowed to.
+fn skip_cache(ctx: Context<'_>, fresh: Option<bool>) -> bool {
+    fresh.unwrap_or(false) && is_operator(ctx.data(), &ctx.author().id)
+}
+
+/// The price of VRSC from all sources, from the cache unless `fresh`.
+async fn vrsc_prices(ctx: Context<'_>, fresh: bool) -> Result<Prices, Error> {
+    if !fresh {
+        if let Some(prices) = ctx.data().prices.get(&()) {
+            return Ok(prices);
+        }
+    }
+
+    let prices = price::fetch(ctx.data()).await?;
+    ctx.data().prices.insert((), prices.clone());
+
+    Ok(prices)
+}
+
+/// The parts of `getblockchaininfo` and `getmininginfo` that `/chaininfo` shows.
+#[derive(Debug, Clone)]
+pub struct ChainInfo {
+    blocks: u64,
+    difficulty: f64,
+    staking_supply: f64,
+    average_block_fees: f64,
+}
+
+/// A reserve currency of a basket.
+struct Reserve {
+    name: String,
+    reserves: Amount,
+    /// The price of one unit of the basket in this reserve
+    price_in_reserve: Amount,
+    weight: f64,
+}
+
+/// The reserves of a basket as a table in a code block.
+fn reserve_table(reserves: &[Reserve]) -> String {
+    let rows = reserves
+        .iter()
+        .map(|reserve| {
+            [
+                reserve.name.clone(),
+                format::number(reserve.reserves.as_vrsc(), 8),
+                format::number(reserve.price_in_reserve.as_vrsc(), 8),
+                format!("{:.2}%", reserve.weight * 100.0),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let widths = (0..4)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    format!(
+        "```{}```",
+        rows.iter()
+            .map(|row| format!(
+                "{:<name$} {:>reserves$} {:>price$} {:>weight$}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                name = widths[0] + 1,
+                reserves = widths[1],
+                price = widths[2],
+                weight = widths[3]
+            ))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// The value of all reserves of a basket in VRSC. A reserve is worth `price_in_native / price_in_reserve` VRSC per
+/// unit, as the basket is worth `price_in_reserve` of that reserve and `price_in_native` VRSC.
+fn basket_tvl(reserves: &[Reserve], price_in_native: Amount) -> f64 {
+    reserves
+        .iter()
+        .filter(|reserve| reserve.price_in_reserve > Amount::ZERO)
+        .map(|reserve| {
+            reserve.reserves.as_vrsc() * price_in_native.as_vrsc()
+                / reserve.price_in_reserve.as_vrsc()
+        })
+        .sum()
+}
+
+/// The identity flag that marks the timelock of an identity as a delay (in blocks) instead of a block height.
+const IDENTITY_FLAG_LOCKED: u16 = 2;
+/// The most content map entries shown, to stay within the limits of an embed.
+const MAX_CONTENT_MAP_ENTRIES: usize = 10;
+
+#[derive(Debug, PartialEq)]
+enum Timelock {
+    Unlocked,
+    /// Locked until it is unlocked, after which spending is delayed by this many blocks
+    Delay(u64),
+    /// Locked until this block height
+    Until(u64),
+}
 
-                println!("{}", tvl_str);
+impl Timelock {
+    fn new(flags: u16, timelock: u64) -> Self {
+        if flags & IDENTITY_FLAG_LOCKED != 0 {
+            Timelock::Delay(timelock)
+        } else if timelock > 0 {
+            Timelock::Until(timelock)
+        } else {
+            Timelock::Unlocked
+        }
+    }
+}
+
+/// Look up a VerusID
+///
+/// Shows the addresses, authorities, content and timelock of an identity. Check the identity this way before you \
+/// withdraw to it.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+pub async fn identity(
+    ctx: Context<'_>,
+    #[description = "The identity, e.g. name@ or an i-address"] identity: String,
+) -> Result<(), Error> {
+    let client = ctx.data().verus()?;
+    let name = identity.trim();
+
+    let identity_name = name.to_owned();
+    let Ok(result) = rpc_guard::call(&client, "getidentity", move |client| {
+        client.get_identity(&identity_name)
+    })
+    .await
+    else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "identity-not-found", identity: name))
+        })
+        .await?;
 
-                fields.push(("Baskets", tvl_str, false));
+        return Ok(());
+    };
+    let identity = result.identity;
+    let revoked = result.status == "revoked";
+
+    let authority = |address: String| {
+        if address == identity.identityaddress.to_string() {
+            tr!(ctx, "identity-self")
+        } else {
+            address
+        }
+    };
 
-                // divide supply by the lastconversionprice of verus
-                if ctx.data().settings.application.testnet {
-                    let price = reserve_currencies
-                        .iter()
-                        .find(|c| c.currencyid.to_string() == "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq")
-                        .and_then(|c| Some(c.priceinreserve))
-                        .unwrap_or(Amount::ZERO);
+    let primary_addresses = format!(
+        "{}\n{}",
+        identity
+            .primaryaddresses
+            .iter()
+            .map(|address| format!("`{address}`"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        tr!(
+            ctx,
+            "identity-minimum-signatures",
+            count: identity.minimumsignatures
+        )
+    );
+
+    let content_map = match identity
+        .contentmap
+        .as_object()
+        .filter(|content_map| !content_map.is_empty())
+    {
+        None => tr!(ctx, "identity-none"),
+        Some(content_map) => format!(
+            "```{}```",
+            content_map
+                .iter()
+                .take(MAX_CONTENT_MAP_ENTRIES)
+                .map(|(key, value)| format!("{key}: {value}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    };
 
-                    let vrsc_value_of_currency_supply =
-                        currency_state.supply.as_vrsc() * price.as_vrsc(); //.unwrap_or(Amount::ZERO)
+    let timelock = match Timelock::new(identity.flags, identity.timelock) {
+        Timelock::Unlocked => tr!(ctx, "identity-unlocked"),
+        Timelock::Delay(blocks) => tr!(ctx, "identity-locked-delay", blocks: blocks),
+        Timelock::Until(height) => tr!(ctx, "identity-locked-until", height: height),
+    };
 
-                    let dollar_value_of_currency_supply =
-                        dbg!(vrsc_value_of_currency_supply * usd_price);
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .field(
+            tr!(ctx, "identity-address"),
+            format!("`{}`", identity.identityaddress),
+            false,
+        )
+        .field(
+            tr!(ctx, "identity-primary-addresses"),
+            primary_addresses,
+            false,
+        )
+        .detail(
+            tr!(ctx, "identity-revocation"),
+            authority(identity.revocationauthority.to_string()),
+            true,
+        )
+        .detail(
+            tr!(ctx, "identity-recovery"),
+            authority(identity.recoveryauthority.to_string()),
+            true,
+        )
+        .field(tr!(ctx, "identity-timelock"), timelock, false)
+        .detail(tr!(ctx, "identity-content-map"), content_map, false);
 
-                    fields.push((
-                        "est. currency value (USD)",
-                        format!("$ {dollar_value_of_currency_supply:.2}"),
-                        false,
-                    ));
-                }
+    ctx.send(|reply| {
+        reply.embed(|embed| {
+            if revoked {
+                embed
+                    .description(tr!(ctx, "identity-revoked"))
+                    .color(Colour::RED);
+            } else {
+                embed.color(Colour::DARK_GREEN);
             }
 
-            ctx.send(|reply| {
-                reply.embed(|embed| {
-                    embed
-                        .title(format!("{}", currency.fullyqualifiedname))
-                        .fields(fields)
+            fields.fill(embed).title(format!("{}@", identity.name))
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Timeframe {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Timeframe {
+    fn duration(self) -> chrono::Duration {
+        match self {
+            Self::Day => chrono::Duration::days(1),
+            Self::Week => chrono::Duration::weeks(1),
+            Self::Month => chrono::Duration::days(30),
+            Self::Year => chrono::Duration::days(365),
+        }
+    }
+}
+
+/// Show a chart of the supply and price of a basket currency over time
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+pub async fn currencychart(
+    ctx: Context<'_>,
+    #[description = "The basket currency"]
+    #[autocomplete = "autocomplete_basket_currency"]
+    currency: String,
+    #[description = "The period to show"] timeframe: Timeframe,
+) -> Result<(), Error> {
+    let settings = &ctx.data().settings.application;
+    let Some(currency) = settings
+        .basket_currencies
+        .iter()
+        .find(|basket| basket.eq_ignore_ascii_case(&currency))
+    else {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "currencychart-unknown",
+                currency: currency,
+                available: settings.basket_currencies.join(", ")
+            ))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let (native_id, native_name) = currency_history::native_currency(settings.testnet);
+    let since = Utc::now() - timeframe.duration();
+    let history =
+        database::get_currency_history(&ctx.data().database, currency, native_id, since).await?;
+
+    // a chart needs at least 2 snapshots
+    if history.len() < 2 {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "currencychart-empty", currency: currency.as_str()))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let filename = format!("currency-{}.png", ctx.id());
+    let out = PathBuf::from(format!("charts/{filename}"));
+    std::fs::create_dir_all("charts")?;
+    currency_history::render_chart(&history, &out)?;
+
+    let first = &history[0];
+    let last = &history[history.len() - 1];
+
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .detail(
+            tr!(ctx, "currencychart-period"),
+            format!(
+                "{} - {}",
+                first.sampled_at.format("%Y-%m-%d %H:%M"),
+                last.sampled_at.format("%Y-%m-%d %H:%M")
+            ),
+            false,
+        )
+        .field(
+            tr!(ctx, "currency-supply"),
+            format::amount_in(last.supply, currency),
+            true,
+        );
+    if let Some(price) = last.price {
+        fields.field(
+            tr!(ctx, "currencychart-price"),
+            format::amount_in(price, native_name),
+            true,
+        );
+    }
+
+    ctx.send(|reply| {
+        reply
+            .embed(|embed| {
+                fields
+                    .fill(embed)
+                    .title(currency)
+                    .image(format!("attachment://{filename}"))
+            })
+            .attachment(poise::serenity_prelude::AttachmentType::Path(&out))
+    })
+    .await?;
+
+    std::fs::remove_file(&out)?;
+
+    Ok(())
+}
+
+async fn autocomplete_basket_currency(
+    ctx: Context<'_>,
+    partial: &str,
+) -> impl Iterator<Item = String> {
+    ctx.data()
+        .settings
+        .application
+        .basket_currencies
+        .iter()
+        .filter(|basket| basket.to_lowercase().starts_with(&partial.to_lowercase()))
+        .cloned()
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// How long an estimate of `estimateconversion` is reused for the same conversion.
+const ESTIMATE_TTL: Duration = Duration::from_secs(60);
+/// How long the calculator stays interactive after its last use.
+const CALC_TIMEOUT: Duration = Duration::from_secs(300);
+/// The largest amount the calculator goes up to: the maximum supply of VRSC, in sats.
+const MAX_CALC_SATS: u64 = 83_540_184 * 100_000_000;
+
+/// Estimates by (from, to, amount in sats), with the moment they were estimated.
+type Estimates = HashMap<(String, String, u64), (Instant, Option<f64>)>;
+
+static ESTIMATES: Lazy<Mutex<Estimates>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Deserialize, Debug)]
+struct ConversionEstimate {
+    estimatedcurrencyout: f64,
+}
+
+/// The state of a `/calc` calculator.
+#[derive(Debug, Clone, PartialEq)]
+struct Calculation {
+    amount: Amount,
+    from: String,
+    to: String,
+}
+
+/// What a button of the calculator does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalcButton {
+    Divide,
+    Multiply,
+    Swap,
+}
+
+impl CalcButton {
+    const ALL: [CalcButton; 3] = [Self::Divide, Self::Multiply, Self::Swap];
+
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Divide => "divide",
+            Self::Multiply => "multiply",
+            Self::Swap => "swap",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Divide => "÷10",
+            Self::Multiply => "×10",
+            Self::Swap => "⇄",
+        }
+    }
+}
+
+impl Calculation {
+    /// Applies a button press. The amount stays between 1 sat and [`MAX_CALC_SATS`], so that it can be estimated.
+    fn apply(&mut self, button: CalcButton) {
+        match button {
+            CalcButton::Divide if self.amount.as_sat() >= 10 => {
+                self.amount = Amount::from_sat(self.amount.as_sat() / 10)
+            }
+            CalcButton::Multiply if self.amount.as_sat() <= MAX_CALC_SATS / 10 => {
+                self.amount = Amount::from_sat(self.amount.as_sat() * 10)
+            }
+            CalcButton::Swap => std::mem::swap(&mut self.from, &mut self.to),
+            _ => {}
+        }
+    }
+
+    /// The estimated output of the conversion, or None when the daemon can not convert between the currencies.
+    /// Estimates are cached for [`ESTIMATE_TTL`], so flipping back and forth does not hit the daemon every time.
+    async fn estimate(&self, client: &Arc<VerusClient>) -> Option<f64> {
+        if self.from == self.to {
+            return Some(self.amount.as_vrsc());
+        }
+
+        let key = (self.from.clone(), self.to.clone(), self.amount.as_sat());
+        if let Some((estimated_at, estimate)) = ESTIMATES.lock().unwrap().get(&key) {
+            if estimated_at.elapsed() < ESTIMATE_TTL {
+                return *estimate;
+            }
+        }
+
+        let params = [json!({
+            "currency": self.from,
+            "convertto": self.to,
+            "amount": self.amount.as_vrsc(),
+        })];
+        let estimate = rpc_guard::call(client, "estimateconversion", move |client| {
+            client.call::<ConversionEstimate>("estimateconversion", &params)
+        })
+        .await
+        .map(|estimate| estimate.estimatedcurrencyout)
+        .map_err(|e| debug!("could not estimate conversion: {e:?}"))
+        .ok();
+
+        let mut estimates = ESTIMATES.lock().unwrap();
+        estimates.retain(|_, (estimated_at, _)| estimated_at.elapsed() < ESTIMATE_TTL);
+        estimates.insert(key, (Instant::now(), estimate));
+
+        estimate
+    }
+}
+
+/// Estimate a conversion between Verus currencies
+///
+/// Opens a calculator in which the amount and the currencies can be changed with buttons and menus. The estimate \
+/// does not include conversion fees.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+pub async fn calc(
+    ctx: Context<'_>,
+    #[description = "The amount to convert"]
+    #[min = 0.00000001]
+    amount: Option<f64>,
+) -> Result<(), Error> {
+    let settings = &ctx.data().settings.application;
+    let (_, native_name) = currency_history::native_currency(settings.testnet);
+    let mut currencies = vec![native_name.to_string()];
+    currencies.extend(settings.basket_currencies.iter().cloned());
+    // a select menu has at most 25 options
+    currencies.truncate(25);
+
+    let mut calculation = Calculation {
+        amount: Amount::from_vrsc(amount.unwrap_or(1.0))?,
+        from: native_name.to_string(),
+        to: currencies.get(1).unwrap_or(&currencies[0]).clone(),
+    };
+
+    let client = ctx.data().verus()?;
+    let prefix = ctx.id().to_string();
+    let from_id = format!("{prefix}-from");
+    let to_id = format!("{prefix}-to");
+
+    let estimate = calculation.estimate(&client).await;
+    let handle = ctx
+        .send(|reply| {
+            reply
+                .ephemeral(true)
+                .embed(|embed| calc_embed(ctx, embed, &calculation, estimate))
+                .components(|components| {
+                    calc_components(components, &prefix, &currencies, &calculation)
                 })
+        })
+        .await?;
+
+    loop {
+        let filter_prefix = prefix.clone();
+        let interaction = CollectComponentInteraction::new(ctx)
+            .author_id(ctx.author().id)
+            .channel_id(ctx.channel_id())
+            .timeout(CALC_TIMEOUT)
+            .filter(move |interaction| interaction.data.custom_id.starts_with(&filter_prefix))
+            .await;
+
+        let Some(interaction) = interaction else {
+            trace!("calculator timed out");
+            break;
+        };
+
+        let custom_id = interaction.data.custom_id.as_str();
+        let selected = interaction.data.values.first().cloned();
+        if custom_id == from_id {
+            calculation.from = selected.unwrap_or(calculation.from);
+        } else if custom_id == to_id {
+            calculation.to = selected.unwrap_or(calculation.to);
+        } else if let Some(button) = CalcButton::ALL
+            .into_iter()
+            .find(|button| custom_id == format!("{prefix}-{}", button.id()))
+        {
+            calculation.apply(button);
+        }
+
+        let estimate = calculation.estimate(&client).await;
+        interaction
+            .create_interaction_response(ctx, |response| {
+                response
+                    .kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|data| {
+                        data.embed(|embed| calc_embed(ctx, embed, &calculation, estimate))
+                            .components(|components| {
+                                calc_components(components, &prefix, &currencies, &calculation)
+                            })
+                    })
             })
             .await?;
-        }
     }
 
+    // the buttons no longer work, so they are removed
+    handle
+        .edit(ctx, |reply| reply.components(|components| components))
+        .await?;
+
     Ok(())
 }
 
-#[derive(Deserialize, Debug)]
-pub struct CoinPaprika {
-    #[serde(rename = "id")]
-    pub guid: String,
-    pub symbol: String,
-    pub circulating_supply: u64,
-    pub last_updated: DateTime<Utc>,
-    pub quotes: HashMap<String, CoinPaprikaQuoteCoin>,
+fn calc_embed<'a>(
+    ctx: Context<'_>,
+    embed: &'a mut CreateEmbed,
+    calculation: &Calculation,
+    estimate: Option<f64>,
+) -> &'a mut CreateEmbed {
+    let description = match estimate {
+        Some(estimate) => format!(
+            "{} ≈ **{}**",
+            format::amount_in(calculation.amount, &calculation.from),
+            format::amount_in(
+                Amount::from_vrsc(estimate).unwrap_or(Amount::ZERO),
+                &calculation.to
+            ),
+        ),
+        None => tr!(
+            ctx,
+            "calc-unavailable",
+            from: calculation.from.as_str(),
+            to: calculation.to.as_str()
+        ),
+    };
+
+    embed
+        .title(tr!(ctx, "calc-title"))
+        .description(description)
+        .footer(|footer| footer.text(tr!(ctx, "calc-footer")))
 }
 
-#[derive(Deserialize, Debug)]
-pub struct CoinPaprikaQuoteCoin {
-    pub price: f64,
-    pub volume_24h: f64,
-    pub percent_change_24h: f64,
-    pub percent_from_price_ath: f64,
+fn calc_components<'a>(
+    components: &'a mut CreateComponents,
+    prefix: &str,
+    currencies: &[String],
+    calculation: &Calculation,
+) -> &'a mut CreateComponents {
+    for (suffix, selected) in [("from", &calculation.from), ("to", &calculation.to)] {
+        components.create_action_row(|row| {
+            row.create_select_menu(|menu| {
+                menu.custom_id(format!("{prefix}-{suffix}"))
+                    .options(|options| {
+                        for currency in currencies {
+                            options.create_option(|option| {
+                                option
+                                    .label(currency)
+                                    .value(currency)
+                                    .default_selection(currency == selected)
+                            });
+                        }
+                        options
+                    })
+            })
+        });
+    }
+
+    components.create_action_row(|row| {
+        for button in CalcButton::ALL {
+            row.create_button(|b| {
+                b.custom_id(format!("{prefix}-{}", button.id()))
+                    .label(button.label())
+                    .style(ButtonStyle::Secondary)
+            });
+        }
+        row
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reserve(name: &str, reserves: f64, price_in_reserve: f64) -> Reserve {
+        Reserve {
+            name: name.to_string(),
+            reserves: Amount::from_vrsc(reserves).unwrap(),
+            price_in_reserve: Amount::from_vrsc(price_in_reserve).unwrap(),
+            weight: 0.5,
+        }
+    }
+
+    #[test]
+    fn calc_buttons_keep_the_amount_in_range() {
+        let mut calculation = Calculation {
+            amount: Amount::from_sat(15),
+            from: "VRSC".to_string(),
+            to: "Bridge.vETH".to_string(),
+        };
+
+        calculation.apply(CalcButton::Divide);
+        assert_eq!(calculation.amount, Amount::from_sat(1));
+        calculation.apply(CalcButton::Divide);
+        assert_eq!(calculation.amount, Amount::from_sat(1));
+
+        calculation.amount = Amount::from_sat(MAX_CALC_SATS);
+        calculation.apply(CalcButton::Multiply);
+        assert_eq!(calculation.amount, Amount::from_sat(MAX_CALC_SATS));
+
+        calculation.apply(CalcButton::Swap);
+        assert_eq!(calculation.from, "Bridge.vETH");
+        assert_eq!(calculation.to, "VRSC");
+    }
+
+    #[test]
+    fn timelock_is_a_delay_when_the_identity_is_locked() {
+        assert_eq!(Timelock::new(0, 0), Timelock::Unlocked);
+        assert_eq!(
+            Timelock::new(IDENTITY_FLAG_LOCKED, 1440),
+            Timelock::Delay(1440)
+        );
+        assert_eq!(Timelock::new(0, 2_500_000), Timelock::Until(2_500_000));
+    }
+
+    #[test]
+    fn basket_tvl_values_every_reserve_in_vrsc() {
+        // 1 basket = 2 VRSC = 4 DAI, so 1 DAI = 0.5 VRSC
+        let reserves = vec![reserve("VRSC", 1_000.0, 2.0), reserve("DAI", 2_000.0, 4.0)];
+
+        assert_eq!(
+            basket_tvl(&reserves, Amount::from_vrsc(2.0).unwrap()),
+            2_000.0
+        );
+    }
 }
@@ -0,0 +1,423 @@
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{ChannelId, GuildId, Http, UserId};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use tokio::time::interval;
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    audit::{self, AuditAction},
+    authorization::{can_manage_guild, guild_of},
+    i18n::{tr, tr_locale, Translations},
+    shutdown::Shutdown,
+    util::{
+        database,
+        delivery::{self, Destination},
+        embed::InfoEmbed,
+        format,
+    },
+    Context, Error,
+};
+
+const FINISH_INTERVAL: Duration = Duration::from_secs(60);
+const SCOREBOARD_SIZE: i64 = 10;
+
+/// What the members of a competition compete on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum CompetitionMetric {
+    /// The number of different members a member tipped
+    #[name = "Unique recipients"]
+    UniqueRecipients,
+    /// The amount a member tipped. Tips between two members cancel each other out, so tipping back and forth does not
+    /// add to the score.
+    #[name = "Amount tipped"]
+    Amount,
+}
+
+impl From<String> for CompetitionMetric {
+    fn from(value: String) -> Self {
+        match value.as_ref() {
+            "unique_recipients" => Self::UniqueRecipients,
+            "amount" => Self::Amount,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl CompetitionMetric {
+    /// How the metric is stored, the inverse of `From<String>`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UniqueRecipients => "unique_recipients",
+            Self::Amount => "amount",
+        }
+    }
+
+    /// A score as it is shown on the scoreboard.
+    pub fn format_score(&self, score: i64) -> String {
+        match self {
+            Self::UniqueRecipients => score.to_string(),
+            Self::Amount => format::amount(Amount::from_sat(score.max(0) as u64)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Competition {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub started_by: UserId,
+    pub metric: CompetitionMetric,
+    pub prize: Amount,
+    pub min_tip: Amount,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// The outcome of a competition that ended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompetitionResult {
+    Won {
+        winner: UserId,
+        score: i64,
+    },
+    /// The treasury did not have enough for the prize when the competition ended
+    Unpaid {
+        winner: UserId,
+        score: i64,
+    },
+    NoWinner,
+}
+
+/// Compete in tipping, with a prize from the treasury for the winner
+///
+/// -------- :robot: **Competitions** --------
+/// Server admins start a competition with `/competition start`. The member with the best score when it ends wins a \
+/// prize from the treasury of the server. The score is either the number of different members someone tipped, or \
+/// the amount they tipped.
+///
+/// To keep it fair, tips to yourself, tips below the minimum of the competition and tips that were reversed don't \
+/// count, and for the amount, tips between the same two members cancel each other out. \
+/// See the scores with `/competition scoreboard`.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Competitions",
+    guild_only,
+    subcommands("start", "scoreboard", "cancel")
+)]
+pub async fn competition(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Start a tipping competition in this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Competitions",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn start(
+    ctx: Context<'_>,
+    #[description = "How long the competition lasts, in hours"]
+    #[min = 1]
+    #[max = 720]
+    duration: u32,
+    #[description = "The prize for the winner, paid from the treasury"]
+    #[min = 0.1]
+    prize: f64,
+    #[description = "What members compete on, the number of members they tipped by default"]
+    metric: Option<CompetitionMetric>,
+    #[description = "The smallest tip per recipient that counts, 0.1 VRSC by default"]
+    #[min = 0]
+    min_tip: Option<f64>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let prize = Amount::from_vrsc(prize)?;
+    let min_tip = Amount::from_vrsc(min_tip.unwrap_or(0.1))?;
+    let metric = metric.unwrap_or(CompetitionMetric::UniqueRecipients);
+
+    let treasury = database::get_treasury_balance(pool, guild_id).await?;
+    if treasury < prize {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "competition-treasury-short",
+                treasury: format::amount(treasury)
+            ))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let mut competition = Competition {
+        id: 0,
+        guild_id,
+        channel_id: ctx.channel_id(),
+        started_by: ctx.author().id,
+        metric,
+        prize,
+        min_tip,
+        ends_at: Utc::now() + chrono::Duration::hours(duration as i64),
+    };
+    let Some(id) = database::start_competition(pool, &competition).await? else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "competition-already-running"))
+        })
+        .await?;
+
+        return Ok(());
+    };
+    competition.id = id;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::StartCompetition,
+        Some(competition.id.to_string()),
+        Some(format!(
+            "{}, prize {prize}, min tip {min_tip}",
+            metric.as_str()
+        )),
+    )
+    .await;
+    debug!("competition {} started in {guild_id}", competition.id);
+
+    ctx.send(|reply| {
+        reply.content(tr!(
+            ctx,
+            "competition-started",
+            metric: metric_name(ctx, metric),
+            prize: format::amount(prize),
+            min_tip: format::amount(min_tip),
+            ends: format!("<t:{}:R>", competition.ends_at.timestamp())
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Show the scores of the competition in this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Competitions", guild_only)]
+async fn scoreboard(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+
+    let Some(competition) = database::get_running_competition(pool, guild_of(ctx)).await? else {
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "competition-none")))
+            .await?;
+
+        return Ok(());
+    };
+
+    let scores = database::get_competition_scores(pool, &competition, SCOREBOARD_SIZE).await?;
+    let scoreboard = match scores.is_empty() {
+        true => tr!(ctx, "competition-no-scores"),
+        false => scores
+            .iter()
+            .enumerate()
+            .map(|(rank, (user_id, score))| {
+                format!(
+                    "{}. <@{user_id}>: {}",
+                    rank + 1,
+                    competition.metric.format_score(*score)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .detail(
+            tr!(ctx, "competition-metric"),
+            metric_name(ctx, competition.metric),
+            true,
+        )
+        .field(
+            tr!(ctx, "competition-prize"),
+            format::amount(competition.prize),
+            true,
+        )
+        .detail(
+            tr!(ctx, "competition-min-tip"),
+            format::amount(competition.min_tip),
+            true,
+        )
+        .field(
+            tr!(ctx, "competition-ends"),
+            format!("<t:{}:R>", competition.ends_at.timestamp()),
+            true,
+        );
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).embed(|embed| {
+            fields
+                .fill(embed)
+                .title(tr!(ctx, "competition-scoreboard-title"))
+                .description(scoreboard)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Cancel the competition in this server, without a prize
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Competitions",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn cancel(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let content = match database::cancel_competition(pool, guild_id).await? {
+        Some(id) => {
+            audit::record(
+                pool,
+                guild_id,
+                ctx.author().id,
+                AuditAction::CancelCompetition,
+                Some(id.to_string()),
+                None,
+            )
+            .await;
+
+            tr!(ctx, "competition-cancelled")
+        }
+        None => tr!(ctx, "competition-none"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+fn metric_name(ctx: Context<'_>, metric: CompetitionMetric) -> String {
+    match metric {
+        CompetitionMetric::UniqueRecipients => tr!(ctx, "competition-metric-unique-recipients"),
+        CompetitionMetric::Amount => tr!(ctx, "competition-metric-amount"),
+    }
+}
+
+/// Counts a tip for the competition in `guild_id`, if one is running. The tip is already done, so failures are only
+/// logged.
+pub async fn record_tips(
+    pool: &PgPool,
+    guild_id: Option<GuildId>,
+    tip_event_id: Uuid,
+    tipper: UserId,
+    recipients: &[UserId],
+    share: Amount,
+) {
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+
+    if let Err(e) =
+        database::record_competition_tips(pool, guild_id, tip_event_id, &tipper, recipients, share)
+            .await
+    {
+        error!("could not count tip {tip_event_id} for the competition in {guild_id}: {e:?}");
+    }
+}
+
+/// Ends the competitions whose time is up and pays their prizes, every [`FINISH_INTERVAL`], until the bot shuts down.
+pub async fn finish_periodically(
+    http: Arc<Http>,
+    pool: PgPool,
+    translations: Arc<Translations>,
+    shutdown: Arc<Shutdown>,
+) {
+    let mut interval = interval(FINISH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.stopped() => break,
+        }
+        // prizes that are being paid are paid before the bot shuts down
+        let Some(_in_flight) = shutdown.begin() else {
+            break;
+        };
+
+        let due = match database::get_ended_competitions(&pool).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("could not get the competitions that ended: {e:?}");
+                continue;
+            }
+        };
+
+        for competition in due {
+            if let Err(e) = finish(&http, &pool, &translations, &competition).await {
+                error!("could not finish competition {}: {e:?}", competition.id);
+            }
+        }
+    }
+
+    info!("competition loop stopped");
+}
+
+async fn finish(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    competition: &Competition,
+) -> Result<(), Error> {
+    let Some(result) = database::finish_competition(pool, competition).await? else {
+        return Ok(());
+    };
+    info!("competition {} ended: {result:?}", competition.id);
+
+    let content = match result {
+        CompetitionResult::Won { winner, score } => tr_locale!(
+            translations,
+            None,
+            "competition-won",
+            winner: format!("<@{winner}>"),
+            score: competition.metric.format_score(score),
+            prize: format::amount(competition.prize)
+        ),
+        CompetitionResult::Unpaid { winner, score } => tr_locale!(
+            translations,
+            None,
+            "competition-won-unpaid",
+            winner: format!("<@{winner}>"),
+            score: competition.metric.format_score(score),
+            prize: format::amount(competition.prize)
+        ),
+        CompetitionResult::NoWinner => tr_locale!(translations, None, "competition-no-winner"),
+    };
+
+    delivery::deliver(
+        http,
+        pool,
+        translations,
+        Destination::Channel {
+            channel_id: competition.channel_id,
+            guild_id: Some(competition.guild_id),
+            initiator: competition.started_by,
+        },
+        &content,
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
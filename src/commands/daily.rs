@@ -0,0 +1,263 @@
+use chrono::{DateTime, Duration, Utc};
+use poise::serenity_prelude::UserId;
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    authorization::guild_of,
+    commands::{begin_balance_change, bot_degraded, user_blacklisted},
+    i18n::tr,
+    util::{database, embed::InfoEmbed, format},
+    Context, Error,
+};
+
+/// How long a user has to wait between two claims.
+const COOLDOWN_HOURS: i64 = 24;
+/// A streak ends when a user does not claim within this long after their last claim.
+pub const STREAK_HOURS: i64 = 48;
+/// Every day of a streak adds 10% to the reward, up to double the reward from this day on.
+const MAX_MULTIPLIER_STREAK: i32 = 11;
+const LEADERBOARD_SIZE: i64 = 10;
+
+/// The outcome of `/daily`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DailyClaim {
+    Claimed {
+        amount: Amount,
+        streak: i32,
+    },
+    /// The guild did not turn `/daily` on
+    Off,
+    /// The user already claimed, and can claim again at this time
+    Cooldown(DateTime<Utc>),
+    /// The pool of the guild does not have enough left for the reward
+    PoolEmpty,
+}
+
+/// The streak of a user on the leaderboard of a guild.
+#[derive(Debug)]
+pub struct DailyStreak {
+    pub user_id: UserId,
+    /// The current streak, 0 when it ended
+    pub streak: i32,
+    pub best_streak: i32,
+}
+
+/// Claim your daily reward in this server
+///
+/// -------- :robot: **Daily rewards** --------
+/// Servers that turned daily rewards on (see `/config daily`) pay a reward once every 24 hours, from a pool anyone \
+/// can fund with `/dailyfund`. Claim every day to build a streak: every day of a streak adds 10% to the reward, up \
+/// to double. A streak ends when you don't claim within 48 hours. See the best streaks with `/streaks`.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Daily rewards", guild_only)]
+pub async fn daily(ctx: Context<'_>) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let guild_id = guild_of(ctx);
+    let claim = database::claim_daily(&ctx.data().database, guild_id, &ctx.author().id).await?;
+
+    let content = match claim {
+        DailyClaim::Claimed { amount, streak } => {
+            debug!(
+                "{} claimed {amount} on day {streak} of their streak in {guild_id}",
+                ctx.author().id
+            );
+            tr!(
+                ctx,
+                "daily-claimed",
+                user: format!("<@{}>", ctx.author().id),
+                amount: format::amount(amount),
+                streak: streak
+            )
+        }
+        DailyClaim::Off => tr!(ctx, "daily-off"),
+        DailyClaim::Cooldown(next_claim_at) => tr!(
+            ctx,
+            "daily-cooldown",
+            time: format!("<t:{}:R>", next_claim_at.timestamp())
+        ),
+        DailyClaim::PoolEmpty => tr!(ctx, "daily-pool-empty"),
+    };
+    let claimed = matches!(claim, DailyClaim::Claimed { .. });
+
+    ctx.send(|reply| reply.ephemeral(!claimed).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Show the longest daily streaks in this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Daily rewards", guild_only)]
+pub async fn streaks(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let streaks = database::get_daily_leaderboard(pool, guild_id, LEADERBOARD_SIZE).await?;
+    let reward = database::get_daily_reward(pool, guild_id).await?;
+    let pool_balance = database::get_daily_pool(pool, guild_id).await?;
+
+    let leaderboard = match streaks.is_empty() {
+        true => tr!(ctx, "daily-no-streaks"),
+        false => streaks
+            .iter()
+            .enumerate()
+            .map(|(rank, streak)| {
+                tr!(
+                    ctx,
+                    "daily-leaderboard-line",
+                    rank: rank + 1,
+                    user: format!("<@{}>", streak.user_id),
+                    streak: streak.streak,
+                    best: streak.best_streak
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .detail(
+            tr!(ctx, "daily-reward"),
+            match reward == Amount::ZERO {
+                true => tr!(ctx, "daily-reward-off"),
+                false => format::amount(reward),
+            },
+            true,
+        )
+        .detail(tr!(ctx, "daily-pool"), format::amount(pool_balance), true);
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).embed(|embed| {
+            fields
+                .fill(embed)
+                .title(tr!(ctx, "daily-leaderboard-title"))
+                .description(leaderboard)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Add to the pool that the daily rewards of this server are paid from
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Daily rewards", guild_only)]
+pub async fn dailyfund(
+    ctx: Context<'_>,
+    #[description = "The amount you want to add to the pool"]
+    #[min = 0.1]
+    amount: f64,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let amount = Amount::from_vrsc(amount)?;
+    let guild_id = guild_of(ctx);
+
+    if !database::fund_daily_pool(&ctx.data().database, guild_id, &ctx.author().id, amount).await? {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "tip-insufficient-balance"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    debug!(
+        "{} added {amount} to the daily pool of {guild_id}",
+        ctx.author().id
+    );
+
+    ctx.send(|reply| {
+        reply.content(tr!(
+            ctx,
+            "daily-funded",
+            user: format!("<@{}>", ctx.author().id),
+            amount: format::amount(amount)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The streak of a user after claiming at `now`, given their previous streak and the time of their last claim. When
+/// they claimed too recently, returns the time they can claim again instead.
+pub fn next_streak(
+    last: Option<(i32, DateTime<Utc>)>,
+    now: DateTime<Utc>,
+) -> Result<i32, DateTime<Utc>> {
+    let Some((streak, last_claim_at)) = last else {
+        return Ok(1);
+    };
+
+    let next_claim_at = last_claim_at + Duration::hours(COOLDOWN_HOURS);
+    if now < next_claim_at {
+        return Err(next_claim_at);
+    }
+
+    match now < last_claim_at + Duration::hours(STREAK_HOURS) {
+        true => Ok(streak.saturating_add(1)),
+        false => Ok(1),
+    }
+}
+
+/// The reward for a day of a streak: the `base` reward plus 10% for every day after the first, up to double.
+pub fn reward(base: Amount, streak: i32) -> Amount {
+    let tenths = 9 + streak.clamp(1, MAX_MULTIPLIER_STREAK) as u128;
+
+    Amount::from_sat((base.as_sat() as u128 * tenths / 10) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::hours(hours)
+    }
+
+    #[test]
+    fn streaks_grow_daily_and_end_after_two_days() {
+        assert_eq!(next_streak(None, at(0)), Ok(1));
+        assert_eq!(next_streak(Some((1, at(0))), at(12)), Err(at(24)));
+        assert_eq!(next_streak(Some((1, at(0))), at(24)), Ok(2));
+        assert_eq!(next_streak(Some((5, at(0))), at(47)), Ok(6));
+        assert_eq!(next_streak(Some((5, at(0))), at(48)), Ok(1));
+    }
+
+    #[test]
+    fn rewards_grow_with_the_streak_up_to_double() {
+        let base = Amount::from_sat(1_000);
+
+        assert_eq!(reward(base, 1), base);
+        assert_eq!(reward(base, 2), Amount::from_sat(1_100));
+        assert_eq!(reward(base, MAX_MULTIPLIER_STREAK), Amount::from_sat(2_000));
+        assert_eq!(reward(base, 365), Amount::from_sat(2_000));
+    }
+}
@@ -0,0 +1,403 @@
+use poise::serenity_prelude::{CacheHttp, ChannelId, ChannelType, UserId};
+use sqlx::types::chrono::{DateTime, Utc};
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    audit::{self, AuditAction},
+    authorization::{can_resolve_disputes, guild_of, Permission},
+    commands::{begin_balance_change, bot_degraded, user_blacklisted},
+    i18n::tr,
+    util::{database, format},
+    Context, Error,
+};
+
+#[derive(Debug)]
+pub struct Dispute {
+    pub id: i64,
+    pub tip_uuid: String,
+    pub opened_by: UserId,
+    pub tipper: UserId,
+    pub recipient: UserId,
+    pub amount: Amount,
+    pub reason: String,
+    pub thread_id: Option<ChannelId>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The result of opening a dispute for a tip.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DisputeOpening {
+    /// The dispute with this id is opened and the tip is frozen
+    Opened(i64),
+    /// The tip event was reversed, so the tipper already got the tip back
+    Reversed,
+    /// The recipient no longer has the funds of the tip
+    InsufficientBalance,
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum DisputeOutcome {
+    /// The recipient keeps the tip
+    #[name = "Release to recipient"]
+    Release,
+    /// The tip goes back to the tipper
+    #[name = "Refund to tipper"]
+    Refund,
+}
+
+impl DisputeOutcome {
+    /// The status of a dispute that is resolved with this outcome.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Release => "released",
+            Self::Refund => "refunded",
+        }
+    }
+}
+
+/// Dispute a tip
+///
+/// -------- :robot: **Disputes** --------
+/// Open a dispute when something went wrong with a tip you sent or received. \
+/// The tipped amount is frozen and a private thread is opened with the other party and the moderators. \
+/// A moderator will either release the tip to the recipient or refund it to the tipper.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Tipping",
+    guild_only,
+    subcommands("open", "resolve", "list")
+)]
+pub async fn dispute(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Suggests the recent tips of the user in this guild. The value is `<tip uuid>:<recipient id>`, as a group tip has
+/// multiple recipients.
+async fn autocomplete_tip(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<poise::AutocompleteChoice<String>> {
+    let tips = match database::get_recent_tips(&ctx.data().database, &ctx.author().id, 25).await {
+        Ok(tips) => tips,
+        Err(e) => {
+            error!("could not get recent tips: {e:?}");
+            return vec![];
+        }
+    };
+
+    let guild_id = guild_of(ctx);
+
+    tips.into_iter()
+        .filter(|tip| tip.guild_id == Some(guild_id))
+        .map(|tip| {
+            let name = if tip.tipper == ctx.author().id {
+                format!(
+                    "{} {} tip sent to {} on {}",
+                    format::amount(tip.amount),
+                    tip.kind,
+                    tip.recipient,
+                    tip.created_at.format("%Y-%m-%d %H:%M")
+                )
+            } else {
+                format!(
+                    "{} {} tip received from {} on {}",
+                    format::amount(tip.amount),
+                    tip.kind,
+                    tip.tipper,
+                    tip.created_at.format("%Y-%m-%d %H:%M")
+                )
+            };
+
+            poise::AutocompleteChoice {
+                name,
+                value: format!("{}:{}", tip.uuid, tip.recipient),
+            }
+        })
+        .filter(|choice| choice.name.contains(partial))
+        .collect()
+}
+
+/// Open a dispute for a tip you sent or received
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Tipping", guild_only)]
+async fn open(
+    ctx: Context<'_>,
+    #[description = "Select the tip you want to dispute"]
+    #[autocomplete = "autocomplete_tip"]
+    tip_id: String,
+    #[description = "What went wrong?"] reason: String,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let tip = match tip_id.split_once(':') {
+        Some((uuid, recipient)) => match recipient.parse::<u64>() {
+            Ok(recipient) => database::get_tip(pool, uuid, &UserId(recipient)).await?,
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    // a tip is disputed in the guild it was made in, where its moderators resolve it
+    let Some(tip) = tip.filter(|tip| {
+        (tip.tipper == ctx.author().id || tip.recipient == ctx.author().id)
+            && tip.tipper != tip.recipient
+            && tip.guild_id == Some(guild_id)
+    }) else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "dispute-unknown-tip"))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let window_hours = ctx.data().settings.application.dispute_window_hours;
+    if Utc::now() - tip.created_at > chrono::Duration::hours(window_hours as i64) {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "dispute-too-old", hours: window_hours))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    if database::dispute_exists(pool, &tip.uuid, &tip.recipient).await? {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "dispute-already-open"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let dispute_id =
+        match database::open_dispute(pool, &tip, guild_id, &ctx.author().id, &reason).await? {
+            DisputeOpening::Opened(dispute_id) => dispute_id,
+            DisputeOpening::Reversed => {
+                ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "dispute-reversed")))
+                    .await?;
+
+                return Ok(());
+            }
+            DisputeOpening::InsufficientBalance => {
+                ctx.send(|reply| {
+                    reply
+                        .ephemeral(true)
+                        .content(tr!(ctx, "dispute-insufficient-balance"))
+                })
+                .await?;
+
+                return Ok(());
+            }
+        };
+
+    debug!("dispute {dispute_id} opened, {} frozen", tip.amount);
+
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::DisputeOpened,
+        Some(dispute_id.to_string()),
+        Some(format!("tip {}: {}", tip.uuid, reason)),
+    )
+    .await;
+
+    let thread = ctx
+        .channel_id()
+        .create_private_thread(ctx.http(), |thread| {
+            thread
+                .name(format!("Dispute #{dispute_id}"))
+                .kind(ChannelType::PrivateThread)
+        })
+        .await?;
+
+    database::set_dispute_thread(pool, dispute_id, thread.id).await?;
+
+    // everyone that can resolve disputes in this guild is added to the thread, alongside both parties.
+    let moderators = database::get_bot_roles(pool, guild_id)
+        .await?
+        .into_iter()
+        .filter(|(_, role)| role.allows(Permission::ResolveDisputes))
+        .map(|(user_id, _)| user_id);
+
+    for user_id in [tip.tipper, tip.recipient].into_iter().chain(moderators) {
+        if let Err(e) = thread.id.add_thread_member(ctx.http(), user_id).await {
+            warn!(
+                "could not add {user_id} to dispute thread {}: {e:?}",
+                thread.id
+            );
+        }
+    }
+
+    thread
+        .id
+        .send_message(ctx.http(), |message| {
+            message.content(tr!(
+                ctx,
+                "dispute-thread-opened",
+                id: dispute_id,
+                opened_by: format!("<@{}>", ctx.author().id),
+                tipper: format!("<@{}>", tip.tipper),
+                recipient: format!("<@{}>", tip.recipient),
+                amount: format::amount(tip.amount),
+                reason: reason.as_str()
+            ))
+        })
+        .await?;
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(tr!(
+            ctx,
+            "dispute-opened",
+            id: dispute_id,
+            thread: format!("<#{}>", thread.id)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Resolve a dispute by releasing or refunding the frozen tip
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Tipping",
+    guild_only,
+    check = "can_resolve_disputes"
+)]
+async fn resolve(
+    ctx: Context<'_>,
+    #[description = "The number of the dispute"] dispute_id: i64,
+    #[description = "Who gets the frozen funds"] outcome: DisputeOutcome,
+) -> Result<(), Error> {
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let Some(dispute) =
+        database::resolve_dispute(pool, dispute_id, guild_id, outcome, &ctx.author().id).await?
+    else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "dispute-not-open", id: dispute_id))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    debug!("dispute {dispute_id} {}", outcome.as_str());
+
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::DisputeResolved,
+        Some(dispute_id.to_string()),
+        Some(outcome.as_str().to_string()),
+    )
+    .await;
+
+    let message_id = match outcome {
+        DisputeOutcome::Release => "dispute-released",
+        DisputeOutcome::Refund => "dispute-refunded",
+    };
+    let message = tr!(
+        ctx,
+        message_id,
+        id: dispute_id,
+        amount: format::amount(dispute.amount),
+        tipper: format!("<@{}>", dispute.tipper),
+        recipient: format!("<@{}>", dispute.recipient),
+        moderator: format!("<@{}>", ctx.author().id)
+    );
+
+    if let Some(thread_id) = dispute.thread_id {
+        thread_id
+            .send_message(ctx.http(), |m| m.content(&message))
+            .await?;
+        if let Err(e) = thread_id
+            .edit_thread(ctx.http(), |thread| thread.locked(true).archived(true))
+            .await
+        {
+            warn!("could not close dispute thread {thread_id}: {e:?}");
+        }
+    }
+
+    ctx.send(|reply| reply.ephemeral(true).content(message))
+        .await?;
+
+    Ok(())
+}
+
+/// List the open disputes of this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Tipping",
+    guild_only,
+    check = "can_resolve_disputes"
+)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let disputes = database::get_open_disputes(&ctx.data().database, guild_of(ctx)).await?;
+
+    let content = if disputes.is_empty() {
+        tr!(ctx, "dispute-none-open")
+    } else {
+        disputes
+            .iter()
+            .map(|d| {
+                format!(
+                    "#{} {} - <@{}> to <@{}>, {} (tip `{}`, opened by <@{}>){}\n> {}",
+                    d.id,
+                    d.created_at.format("%Y-%m-%d %H:%M"),
+                    d.tipper,
+                    d.recipient,
+                    format::amount(d.amount),
+                    d.tip_uuid,
+                    d.opened_by,
+                    d.thread_id
+                        .map(|thread_id| format!(" <#{thread_id}>"))
+                        .unwrap_or_default(),
+                    d.reason
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,695 @@
+use poise::serenity_prelude::{CacheHttp, GuildChannel, ReactionType};
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    audit::{self, AuditAction},
+    authorization::{can_manage_guild, guild_of},
+    configuration::BotFee,
+    content_filter::{self, MAX_WORD_LENGTH},
+    i18n::tr,
+    mod_log,
+    util::{database, delivery, format},
+    Context, Error,
+};
+
+/// Change the settings of the bot in this server
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild",
+    subcommands(
+        "announcements",
+        "fallback",
+        "emojiamounts",
+        "batching",
+        "withdrawalfee",
+        "tipbuttons",
+        "filter",
+        "daily",
+        "modlog",
+        "news"
+    )
+)]
+pub async fn config(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set the channel where announcements of the bot are posted
+///
+/// Leave the channel empty to stop receiving announcements in this server.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn announcements(
+    ctx: Context<'_>,
+    #[description = "The channel for announcements"] channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let channel_id = channel.map(|channel| channel.id);
+
+    database::set_announcement_channel(pool, guild_id, channel_id).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetAnnouncementChannel,
+        channel_id.map(|id| id.to_string()),
+        None,
+    )
+    .await;
+
+    let content = match channel_id {
+        Some(channel_id) => tr!(
+            ctx,
+            "config-announcements-set",
+            channel: format!("<#{channel_id}>")
+        ),
+        None => tr!(ctx, "config-announcements-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Set the channel where tip announcements go when the original channel can not be used
+///
+/// Leave the channel empty to send these announcements to the tipper in DM instead.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn fallback(
+    ctx: Context<'_>,
+    #[description = "The fallback channel"] channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let channel_id = channel.map(|channel| channel.id);
+
+    database::set_fallback_channel(pool, guild_id, channel_id).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetFallbackChannel,
+        channel_id.map(|id| id.to_string()),
+        None,
+    )
+    .await;
+
+    let content = match channel_id {
+        Some(channel_id) => tr!(
+            ctx,
+            "config-fallback-set",
+            channel: format!("<#{channel_id}>")
+        ),
+        None => tr!(ctx, "config-fallback-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Set when tip announcements in a channel are combined into a single summary
+///
+/// When a channel gets more tip announcements than the threshold within the window, or has slow mode, the next tips \
+/// are summarized in one message at the end of the window. Leave a value empty to use the default, or set the \
+/// threshold to 0 to never combine announcements.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn batching(
+    ctx: Context<'_>,
+    #[description = "The number of announcements in the window before they are combined"]
+    #[max = 100]
+    threshold: Option<u32>,
+    #[description = "The window in seconds"]
+    #[min = 10]
+    #[max = 3600]
+    window: Option<u32>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    database::set_batch_settings(pool, guild_id, threshold, window).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetBatching,
+        None,
+        Some(format!("threshold={threshold:?} window={window:?}")),
+    )
+    .await;
+
+    let threshold = threshold.unwrap_or(delivery::DEFAULT_BATCH_THRESHOLD);
+    let window = window.unwrap_or(delivery::DEFAULT_BATCH_WINDOW_SECONDS);
+    let content = match threshold {
+        0 => tr!(ctx, "config-batching-off"),
+        _ => tr!(
+            ctx,
+            "config-batching-set",
+            threshold: threshold,
+            window: window
+        ),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum FeeKind {
+    Flat,
+    Percentage,
+}
+
+/// Set the fee the bot charges for withdrawals from this server, on top of the network fee
+///
+/// A flat fee is in VRSC, a percentage is of the withdrawn amount. Leave both empty to use the default fee of the bot.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn withdrawalfee(
+    ctx: Context<'_>,
+    #[description = "Whether the fee is a flat amount or a percentage"] kind: Option<FeeKind>,
+    #[description = "The fee in VRSC, or the percentage"]
+    #[min = 0]
+    #[max = 100]
+    value: Option<f64>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let fee = match kind {
+        Some(FeeKind::Flat) => Some(BotFee::Flat(Amount::from_vrsc(value.unwrap_or(0.0))?)),
+        Some(FeeKind::Percentage) => Some(BotFee::Percentage(value.unwrap_or(0.0))),
+        None => None,
+    };
+
+    database::set_guild_withdrawal_fee(pool, guild_id, fee).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetGuildWithdrawalFee,
+        None,
+        fee.map(|fee| fee.to_string()),
+    )
+    .await;
+
+    let content = match fee {
+        Some(fee) => tr!(ctx, "config-withdrawal-fee-set", fee: fee.to_string()),
+        None => tr!(
+            ctx,
+            "config-withdrawal-fee-default",
+            fee: ctx.data().settings.application.bot_withdrawal_fee.to_string()
+        ),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Put buttons under tip announcements that let others tip the same recipients
+///
+/// The buttons tip 0.1 or 1 VRSC, or an amount entered with "Custom". Tips of busy channels that are combined into a \
+/// summary get no buttons.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn tipbuttons(
+    ctx: Context<'_>,
+    #[description = "Whether tip announcements get tip buttons"] enabled: bool,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    database::set_tip_buttons(pool, guild_id, enabled).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetTipButtons,
+        None,
+        Some(enabled.to_string()),
+    )
+    .await;
+
+    let content = match enabled {
+        true => tr!(ctx, "config-tip-buttons-on"),
+        false => tr!(ctx, "config-tip-buttons-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Turn daily rewards on in this server, and set the reward
+///
+/// Members claim the reward once every 24 hours with `/daily`, plus 10% for every day of their streak up to double. \
+/// The rewards are paid from a pool that anyone can fund with `/dailyfund`. A reward of 0 turns daily rewards off.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn daily(
+    ctx: Context<'_>,
+    #[description = "The reward of the first day of a streak in VRSC, 0 to turn daily rewards off"]
+    #[min = 0]
+    reward: f64,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let reward = Amount::from_vrsc(reward)?;
+
+    database::set_daily_reward(pool, guild_id, reward).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetDailyReward,
+        None,
+        Some(reward.to_string()),
+    )
+    .await;
+
+    let content = match reward == Amount::ZERO {
+        true => tr!(ctx, "config-daily-off"),
+        false => tr!(
+            ctx,
+            "config-daily-on",
+            reward: format::amount(reward),
+            pool: format::amount(database::get_daily_pool(pool, guild_id).await?)
+        ),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Set the channel where withdrawals, large tips and other balance events are posted for moderators
+///
+/// Tips of at least the tip threshold are posted, 100 VRSC by default. Leave the channel empty to turn the mod-log \
+/// off.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn modlog(
+    ctx: Context<'_>,
+    #[description = "The channel for the mod-log"] channel: Option<GuildChannel>,
+    #[description = "The smallest tip in VRSC that is posted, 100 by default"]
+    #[min = 0]
+    tip_threshold: Option<f64>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let channel_id = channel.map(|channel| channel.id);
+    let tip_threshold = tip_threshold.map(Amount::from_vrsc).transpose()?;
+
+    database::set_mod_log(pool, guild_id, channel_id, tip_threshold).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetModLog,
+        channel_id.map(|id| id.to_string()),
+        tip_threshold.map(|threshold| threshold.to_string()),
+    )
+    .await;
+
+    let threshold = tip_threshold.unwrap_or(Amount::from_sat(mod_log::DEFAULT_TIP_THRESHOLD));
+    let content = match channel_id {
+        Some(channel_id) => tr!(
+            ctx,
+            "config-mod-log-set",
+            channel: format!("<#{channel_id}>"),
+            threshold: format::amount(threshold)
+        ),
+        None => tr!(ctx, "config-mod-log-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Post Verus news in a channel
+///
+/// Pick the topics you want as a comma separated list, or leave them empty for all news. Leave the channel empty to \
+/// stop the news in this server.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn news(
+    ctx: Context<'_>,
+    #[description = "The channel for the news"] channel: Option<GuildChannel>,
+    #[description = "The topics you want, e.g. releases, all topics by default"] topics: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let mut available = ctx
+        .data()
+        .settings
+        .application
+        .news_feeds
+        .iter()
+        .map(|feed| feed.topic.clone())
+        .collect::<Vec<_>>();
+    available.sort();
+    available.dedup();
+
+    let topics = topics
+        .iter()
+        .flat_map(|topics| topics.split(','))
+        .map(|topic| topic.trim().to_lowercase())
+        .filter(|topic| !topic.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(unknown) = topics.iter().find(|topic| !available.contains(topic)) {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "config-news-unknown-topic",
+                topic: unknown.as_str(),
+                available: available.join(", ")
+            ))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let channel_id = channel.map(|channel| channel.id);
+    database::set_news_subscription(pool, guild_id, channel_id.map(|id| (id, topics.as_slice())))
+        .await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetNewsFeed,
+        channel_id.map(|id| id.to_string()),
+        Some(topics.join(",")),
+    )
+    .await;
+
+    let content = match channel_id {
+        Some(channel_id) => tr!(
+            ctx,
+            "config-news-set",
+            channel: format!("<#{channel_id}>"),
+            topics: match topics.is_empty() {
+                true => available.join(", "),
+                false => topics.join(", "),
+            }
+        ),
+        None => tr!(ctx, "config-news-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Block words in the notes of tips in this server
+///
+/// Leave everything empty to list the blocked words of this server. Words are also blocked when their letters are \
+/// spaced out or replaced by look-alikes, like $h1t. The bot blocks a list of common profanity too, unless it is \
+/// turned off with `default_list`.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn filter(
+    ctx: Context<'_>,
+    #[description = "A word to block"] add: Option<String>,
+    #[description = "A word to no longer block"] remove: Option<String>,
+    #[description = "Whether the default list of the bot is blocked too"] default_list: Option<
+        bool,
+    >,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    if add.is_none() && remove.is_none() && default_list.is_none() {
+        let words = database::get_filter_words(pool, guild_id).await?;
+        let mut content = match words.is_empty() {
+            true => tr!(ctx, "config-filter-none"),
+            false => words
+                .iter()
+                .map(|word| format::user_text(word, MAX_WORD_LENGTH))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        if database::get_default_filter(pool, guild_id).await? {
+            content.push('\n');
+            content.push_str(&tr!(ctx, "config-filter-default-on"));
+        }
+
+        ctx.send(|reply| reply.ephemeral(true).content(content))
+            .await?;
+
+        return Ok(());
+    }
+
+    let mut responses = vec![];
+
+    for (word, blocked) in [(add, true), (remove, false)] {
+        let Some(word) = word else {
+            continue;
+        };
+        let word = content_filter::normalize(word.trim());
+        if word.is_empty()
+            || word.chars().count() > MAX_WORD_LENGTH
+            || word.contains(char::is_whitespace)
+        {
+            responses.push(tr!(
+                ctx,
+                "config-filter-invalid",
+                max: MAX_WORD_LENGTH
+            ));
+            continue;
+        }
+
+        database::set_filter_word(pool, guild_id, &word, blocked).await?;
+        audit::record(
+            pool,
+            guild_id,
+            ctx.author().id,
+            AuditAction::SetFilterWord,
+            Some(word.clone()),
+            Some(blocked.to_string()),
+        )
+        .await;
+
+        let word = format::user_text(&word, MAX_WORD_LENGTH);
+        responses.push(match blocked {
+            true => tr!(ctx, "config-filter-added", word: word),
+            false => tr!(ctx, "config-filter-removed", word: word),
+        });
+    }
+
+    if let Some(enabled) = default_list {
+        database::set_default_filter(pool, guild_id, enabled).await?;
+        audit::record(
+            pool,
+            guild_id,
+            ctx.author().id,
+            AuditAction::SetDefaultFilter,
+            None,
+            Some(enabled.to_string()),
+        )
+        .await;
+
+        responses.push(match enabled {
+            true => tr!(ctx, "config-filter-default-on"),
+            false => tr!(ctx, "config-filter-default-off"),
+        });
+    }
+
+    ctx.send(|reply| reply.ephemeral(true).content(responses.join("\n")))
+        .await?;
+
+    Ok(())
+}
+
+/// The most emoji amounts a server can have, which is the most buttons Discord shows below a message.
+const MAX_EMOJI_AMOUNTS: usize = 25;
+
+/// Map an emoji to an amount, for the react-to-tip feature and the quick-tip buttons
+///
+/// Leave the amount empty to remove the emoji, or leave both empty to list the emoji amounts of this server. \
+/// The emoji can be a standard emoji or a custom emoji of this server.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn emojiamounts(
+    ctx: Context<'_>,
+    #[description = "The emoji, e.g. :gem:"] emoji: Option<String>,
+    #[min = 0.00000001]
+    #[description = "The amount the emoji stands for"]
+    amount: Option<f64>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let Some(emoji) = emoji else {
+        let emoji_amounts = database::get_emoji_amounts(pool, guild_id).await?;
+        let content = if emoji_amounts.is_empty() {
+            tr!(ctx, "config-emoji-amounts-none")
+        } else {
+            emoji_amounts
+                .iter()
+                .map(|(emoji, amount)| format!("{emoji} = {}", format::amount(*amount)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        ctx.send(|reply| reply.ephemeral(true).content(content))
+            .await?;
+
+        return Ok(());
+    };
+
+    let Some(emoji) = guild_emoji(ctx, &emoji).await? else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "config-emoji-invalid", emoji: emoji))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let amount = amount.map(Amount::from_vrsc).transpose()?;
+
+    if amount.is_some() {
+        let emoji_amounts = database::get_emoji_amounts(pool, guild_id).await?;
+        if emoji_amounts.len() >= MAX_EMOJI_AMOUNTS
+            && !emoji_amounts.iter().any(|(existing, _)| existing == &emoji)
+        {
+            ctx.send(|reply| {
+                reply.ephemeral(true).content(tr!(
+                    ctx,
+                    "config-emoji-amounts-full",
+                    max: MAX_EMOJI_AMOUNTS
+                ))
+            })
+            .await?;
+
+            return Ok(());
+        }
+    }
+
+    database::set_emoji_amount(pool, guild_id, &emoji, amount).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::SetEmojiAmount,
+        Some(emoji.clone()),
+        amount.map(|amount| amount.to_string()),
+    )
+    .await;
+
+    let content = match amount {
+        Some(amount) => tr!(
+            ctx,
+            "config-emoji-amount-set",
+            emoji: emoji,
+            amount: format::amount(amount)
+        ),
+        None => tr!(ctx, "config-emoji-amount-removed", emoji: emoji),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the emoji as Discord renders it, if it is a standard emoji (or its :shortcode:) or a custom emoji of this
+/// server.
+async fn guild_emoji(ctx: Context<'_>, emoji: &str) -> Result<Option<String>, Error> {
+    let emoji = emoji.trim();
+    if let Some(unicode) = emoji
+        .strip_prefix(':')
+        .and_then(|emoji| emoji.strip_suffix(':'))
+        .and_then(emojis::get_by_shortcode)
+    {
+        return Ok(Some(unicode.as_str().to_string()));
+    }
+
+    match ReactionType::try_from(emoji) {
+        Ok(ReactionType::Custom { id, .. }) => {
+            let emojis = guild_of(ctx).emojis(ctx.http()).await?;
+            if emojis.iter().any(|e| e.id == id) {
+                Ok(Some(ReactionType::try_from(emoji)?.to_string()))
+            } else {
+                debug!("emoji not in guild: {emoji}");
+                Ok(None)
+            }
+        }
+        Ok(ReactionType::Unicode(unicode)) => {
+            Ok(emojis::get(&unicode).map(|emoji| emoji.as_str().to_string()))
+        }
+        _ => Ok(None),
+    }
+}
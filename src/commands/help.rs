@@ -0,0 +1,569 @@
+//! `/help`: the commands of the bot by category, with a menu to switch between the categories and buttons to page
+//! through the long ones.
+//!
+//! The texts come from the doc comments of the commands: the first paragraph is the description that is listed, the
+//! rest is the help text that `/help <command>` shows. Commands that are hidden in help are listed in the Admin
+//! category, which is only shown to users with a bot role.
+
+use std::time::Duration;
+
+use poise::serenity_prelude::{
+    ButtonStyle, CollectComponentInteraction, Colour, CreateComponents, CreateEmbed,
+    InteractionResponseType,
+};
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use crate::{
+    authorization::{guild_of, role_of},
+    i18n::tr,
+    Context, Data, Error,
+};
+
+/// The categories that come first in the menu, in this order. Other categories follow in the order of their first
+/// command, and [`ADMIN`] comes last.
+const CATEGORIES: [&str; 3] = ["Tipping", "Wallet", "Miscellaneous"];
+/// The category of the commands that are hidden in help.
+const ADMIN: &str = "Admin";
+/// The number of commands on a page of a category.
+const PAGE_SIZE: usize = 8;
+const HELP_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A command as it is listed in `/help`. Groups are listed as their subcommands.
+#[derive(Debug, Clone, PartialEq)]
+struct HelpEntry {
+    category: String,
+    /// With the prefix it is used with, e.g. `/tip user` or `!status`
+    name: String,
+    parameters: Vec<HelpParameter>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct HelpParameter {
+    name: String,
+    description: Option<String>,
+    required: bool,
+}
+
+impl HelpEntry {
+    fn new(command: &poise::Command<Data, Error>, category: String) -> Self {
+        let prefix = match command.slash_action {
+            Some(_) => "/",
+            None => "!",
+        };
+
+        Self {
+            category,
+            name: format!("{prefix}{}", command.qualified_name),
+            parameters: command
+                .parameters
+                .iter()
+                .map(|parameter| HelpParameter {
+                    name: parameter.name.clone(),
+                    description: parameter.description.clone(),
+                    required: parameter.required,
+                })
+                .collect(),
+            description: command.description.clone(),
+        }
+    }
+
+    /// The name with the parameters, e.g. `/tip user <user> <tip_amount> [message]`.
+    fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for parameter in &self.parameters {
+            match parameter.required {
+                true => usage.push_str(&format!(" <{}>", parameter.name)),
+                false => usage.push_str(&format!(" [{}]", parameter.name)),
+            }
+        }
+
+        usage
+    }
+
+    /// The command with an example value for each required parameter, written the way Discord shows slash commands
+    /// or the way prefix commands are typed.
+    fn example(&self) -> String {
+        let mut example = self.name.clone();
+        for parameter in self
+            .parameters
+            .iter()
+            .filter(|parameter| parameter.required)
+        {
+            let value = example_value(&parameter.name);
+            match self.name.starts_with('/') {
+                true => example.push_str(&format!(" {}:{value}", parameter.name)),
+                false => example.push_str(&format!(" {value}")),
+            }
+        }
+
+        example
+    }
+}
+
+/// An example value for a parameter, based on its name.
+fn example_value(name: &str) -> String {
+    let examples = [
+        ("amount", "10"),
+        ("user", "@user"),
+        ("role", "@role"),
+        ("channel", "#channel"),
+        ("emoji", "🎉"),
+        ("currency", "VRSC"),
+        ("destination", "RTipBotAddress"),
+        ("address", "RTipBotAddress"),
+        ("identity", "name@"),
+        ("height", "2500000"),
+        ("days", "7"),
+        ("winners", "3"),
+    ];
+
+    examples
+        .iter()
+        .find(|(part, _)| name.contains(part))
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_else(|| format!("<{name}>"))
+}
+
+/// The entries of `commands`, leaving out the hidden commands unless `admin`.
+fn entries(commands: &[poise::Command<Data, Error>], admin: bool) -> Vec<HelpEntry> {
+    let mut entries = vec![];
+    collect_entries(commands, None, false, &mut entries);
+    if !admin {
+        entries.retain(|entry| entry.category != ADMIN);
+    }
+
+    entries
+}
+
+fn collect_entries(
+    commands: &[poise::Command<Data, Error>],
+    parent_category: Option<&str>,
+    hidden: bool,
+    entries: &mut Vec<HelpEntry>,
+) {
+    for command in commands {
+        let hidden = hidden || command.hide_in_help;
+        let category = command.category.or(parent_category);
+
+        if command.subcommands.is_empty() {
+            let category = match hidden {
+                true => ADMIN,
+                false => category.unwrap_or("Miscellaneous"),
+            };
+            entries.push(HelpEntry::new(command, category.to_string()));
+        } else {
+            collect_entries(&command.subcommands, category, hidden, entries);
+        }
+    }
+}
+
+/// The categories of `entries`, in the order of the menu.
+fn categories(entries: &[HelpEntry]) -> Vec<String> {
+    let mut categories = CATEGORIES
+        .iter()
+        .filter(|category| entries.iter().any(|entry| &entry.category == *category))
+        .map(|category| category.to_string())
+        .collect::<Vec<_>>();
+
+    for entry in entries {
+        if entry.category != ADMIN && !categories.contains(&entry.category) {
+            categories.push(entry.category.clone());
+        }
+    }
+    if entries.iter().any(|entry| entry.category == ADMIN) {
+        categories.push(ADMIN.to_string());
+    }
+
+    categories
+}
+
+/// Finds a command by its full name, e.g. `tip user`.
+fn find_command<'a>(
+    commands: &'a [poise::Command<Data, Error>],
+    name: &str,
+) -> Option<&'a poise::Command<Data, Error>> {
+    let name = name.trim().trim_start_matches(['/', '!']);
+
+    commands.iter().find_map(|command| {
+        if command.qualified_name == name {
+            return Some(command);
+        }
+
+        find_command(&command.subcommands, name)
+    })
+}
+
+/// Show the commands of the bot
+///
+/// Pick a category in the menu to see its commands. Enter a command to see everything about it, including its \
+/// parameters and an example.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(track_edits, slash_command, category = "Miscellaneous")]
+pub async fn help(
+    ctx: Context<'_>,
+    #[description = "Specific command to show help about"]
+    #[autocomplete = "poise::builtins::autocomplete_command"]
+    command: Option<String>,
+) -> Result<(), Error> {
+    let admin = role_of(ctx.data(), &ctx.author().id, guild_of(ctx))
+        .await?
+        .is_some();
+    let commands = &ctx.framework().options().commands;
+
+    if let Some(name) = command {
+        return command_help(ctx, commands, &name, admin).await;
+    }
+
+    let entries = entries(commands, admin);
+    let categories = categories(&entries);
+    let Some(mut category) = categories.first().cloned() else {
+        return Ok(());
+    };
+    let mut page = 0;
+
+    let prefix = ctx.id().to_string();
+    let category_id = format!("{prefix}-category");
+    let previous_id = format!("{prefix}-previous");
+    let next_id = format!("{prefix}-next");
+
+    let handle = ctx
+        .send(|reply| {
+            reply
+                .ephemeral(true)
+                .embed(|embed| category_embed(ctx, embed, &entries, &category, page))
+                .components(|components| {
+                    help_components(
+                        ctx,
+                        components,
+                        &prefix,
+                        &entries,
+                        &categories,
+                        &category,
+                        page,
+                    )
+                })
+        })
+        .await?;
+
+    loop {
+        let filter_prefix = prefix.clone();
+        let interaction = CollectComponentInteraction::new(ctx)
+            .author_id(ctx.author().id)
+            .channel_id(ctx.channel_id())
+            .timeout(HELP_TIMEOUT)
+            .filter(move |interaction| interaction.data.custom_id.starts_with(&filter_prefix))
+            .await;
+
+        let Some(interaction) = interaction else {
+            trace!("help timed out");
+            break;
+        };
+
+        let custom_id = interaction.data.custom_id.as_str();
+        if custom_id == category_id {
+            if let Some(selected) = interaction.data.values.first() {
+                category = selected.clone();
+                page = 0;
+            }
+        } else if custom_id == previous_id {
+            page = page.saturating_sub(1);
+        } else if custom_id == next_id {
+            page = (page + 1).min(page_count(&entries, &category) - 1);
+        }
+
+        interaction
+            .create_interaction_response(ctx, |response| {
+                response
+                    .kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|data| {
+                        data.embed(|embed| category_embed(ctx, embed, &entries, &category, page))
+                            .components(|components| {
+                                help_components(
+                                    ctx,
+                                    components,
+                                    &prefix,
+                                    &entries,
+                                    &categories,
+                                    &category,
+                                    page,
+                                )
+                            })
+                    })
+            })
+            .await?;
+    }
+
+    // the menu and buttons no longer work, so they are removed
+    handle
+        .edit(ctx, |reply| reply.components(|components| components))
+        .await?;
+
+    Ok(())
+}
+
+/// Shows everything about a single command: its help text, parameters and an example.
+async fn command_help(
+    ctx: Context<'_>,
+    commands: &[poise::Command<Data, Error>],
+    name: &str,
+    admin: bool,
+) -> Result<(), Error> {
+    // the entries of the command, or of its subcommands, that the author may see
+    let command = find_command(commands, name);
+    let entries = entries(commands, admin)
+        .into_iter()
+        .filter(|entry| {
+            command.is_some_and(|command| {
+                let name = &entry.name[1..];
+                name == command.qualified_name
+                    || name.starts_with(&format!("{} ", command.qualified_name))
+            })
+        })
+        .collect::<Vec<_>>();
+    let Some(command) = command.filter(|_| !entries.is_empty()) else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "help-unknown-command", command: name))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let title = match entries.as_slice() {
+        [entry] => entry.usage(),
+        _ => format!("/{}", command.qualified_name),
+    };
+    let mut description = command.description.clone().unwrap_or_default();
+    if let Some(help_text) = command.help_text {
+        description.push_str(&format!("\n\n{}", help_text()));
+    }
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).embed(|embed| {
+            embed
+                .title(truncate(&title, 256))
+                .colour(Colour::BLUE)
+                .description(truncate(&description, 4096));
+
+            match entries.as_slice() {
+                [entry] => {
+                    for parameter in &entry.parameters {
+                        let requirement = match parameter.required {
+                            true => tr!(ctx, "help-required"),
+                            false => tr!(ctx, "help-optional"),
+                        };
+                        embed.field(
+                            &parameter.name,
+                            format!(
+                                "{} ({requirement})",
+                                parameter.description.as_deref().unwrap_or("-")
+                            ),
+                            false,
+                        );
+                    }
+                    embed.field(
+                        tr!(ctx, "help-example"),
+                        format!("`{}`", entry.example()),
+                        false,
+                    );
+                }
+                entries => {
+                    for entry in entries.iter().take(25) {
+                        embed.field(
+                            truncate(&entry.usage(), 256),
+                            entry_value(ctx, entry),
+                            false,
+                        );
+                    }
+                }
+            }
+
+            embed
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn page_count(entries: &[HelpEntry], category: &str) -> usize {
+    let count = entries
+        .iter()
+        .filter(|entry| entry.category == category)
+        .count();
+
+    count.div_ceil(PAGE_SIZE).max(1)
+}
+
+fn entry_value(ctx: Context<'_>, entry: &HelpEntry) -> String {
+    let value = format!(
+        "{}\n{}: `{}`",
+        entry.description.as_deref().unwrap_or("-"),
+        tr!(ctx, "help-example"),
+        entry.example()
+    );
+
+    truncate(&value, 1024)
+}
+
+fn category_embed<'a>(
+    ctx: Context<'_>,
+    embed: &'a mut CreateEmbed,
+    entries: &[HelpEntry],
+    category: &str,
+    page: usize,
+) -> &'a mut CreateEmbed {
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.category == category)
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        embed.field(
+            truncate(&entry.usage(), 256),
+            entry_value(ctx, entry),
+            false,
+        );
+    }
+
+    embed
+        .title(tr!(ctx, "help-title", category: category))
+        .colour(Colour::BLUE)
+        .footer(|footer| {
+            footer.text(tr!(
+                ctx,
+                "help-footer",
+                page: page + 1,
+                pages: page_count(entries, category)
+            ))
+        })
+}
+
+fn help_components<'a>(
+    ctx: Context<'_>,
+    components: &'a mut CreateComponents,
+    prefix: &str,
+    entries: &[HelpEntry],
+    categories: &[String],
+    category: &str,
+    page: usize,
+) -> &'a mut CreateComponents {
+    components.create_action_row(|row| {
+        row.create_select_menu(|menu| {
+            menu.custom_id(format!("{prefix}-category"))
+                .options(|options| {
+                    // a select menu has at most 25 options
+                    for option_category in categories.iter().take(25) {
+                        options.create_option(|option| {
+                            option
+                                .label(option_category)
+                                .value(option_category)
+                                .default_selection(option_category == category)
+                        });
+                    }
+                    options
+                })
+        })
+    });
+
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .custom_id(format!("{prefix}-previous"))
+                .label(tr!(ctx, "help-previous"))
+                .style(ButtonStyle::Secondary)
+                .disabled(page == 0)
+        })
+        .create_button(|button| {
+            button
+                .custom_id(format!("{prefix}-next"))
+                .label(tr!(ctx, "help-next"))
+                .style(ButtonStyle::Secondary)
+                .disabled(page + 1 >= page_count(entries, category))
+        })
+    })
+}
+
+/// Cuts `text` to at most `max` characters, the limits of the parts of an embed.
+fn truncate(text: &str, max: usize) -> String {
+    match text.chars().count() > max {
+        true => format!("{}…", text.chars().take(max - 1).collect::<String>()),
+        false => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(category: &str, name: &str) -> HelpEntry {
+        HelpEntry {
+            category: category.to_string(),
+            name: name.to_string(),
+            parameters: vec![],
+            description: None,
+        }
+    }
+
+    fn parameter(name: &str, required: bool) -> HelpParameter {
+        HelpParameter {
+            name: name.to_string(),
+            description: None,
+            required,
+        }
+    }
+
+    #[test]
+    fn usage_and_example_show_the_parameters() {
+        let mut tip = entry("Tipping", "/tip user");
+        tip.parameters = vec![
+            parameter("user", true),
+            parameter("tip_amount", true),
+            parameter("message", false),
+        ];
+        assert_eq!(tip.usage(), "/tip user <user> <tip_amount> [message]");
+        assert_eq!(tip.example(), "/tip user user:@user tip_amount:10");
+
+        let mut rescan = entry(ADMIN, "!rescanfromheight");
+        rescan.parameters = vec![parameter("height", true), parameter("note", true)];
+        assert_eq!(rescan.example(), "!rescanfromheight 2500000 <note>");
+    }
+
+    #[test]
+    fn categories_follow_the_menu_order_with_admin_last() {
+        let entries = vec![
+            entry(ADMIN, "!status"),
+            entry("Treasury", "/treasury show"),
+            entry("Miscellaneous", "/help"),
+            entry("Tipping", "/tip user"),
+            entry("Trivia", "/trivia start"),
+        ];
+
+        assert_eq!(
+            categories(&entries),
+            ["Tipping", "Miscellaneous", "Treasury", "Trivia", ADMIN]
+        );
+        assert_eq!(categories(&entries[1..]).last().unwrap(), "Trivia");
+    }
+
+    #[test]
+    fn categories_are_split_into_pages() {
+        let entries = (0..PAGE_SIZE + 1)
+            .map(|i| entry("Wallet", &format!("/command{i}")))
+            .collect::<Vec<_>>();
+
+        assert_eq!(page_count(&entries, "Wallet"), 2);
+        assert_eq!(page_count(&entries[..PAGE_SIZE], "Wallet"), 1);
+        assert_eq!(page_count(&entries, "Tipping"), 1);
+    }
+
+    #[test]
+    fn long_texts_are_truncated() {
+        assert_eq!(truncate("tip", 3), "tip");
+        assert_eq!(truncate("tipping", 4), "tip…");
+    }
+}
@@ -0,0 +1,271 @@
+//! `/wallet`: the owners' view of the hot wallet, the wallet of the daemon that withdrawals are paid from.
+//!
+//! The hot wallet only needs to hold part of what the bot owes its users, see `reserve_ratio` in the `hot_wallet`
+//! settings. The excess can be swept to the configured cold storage address. During an incident the owners can
+//! freeze all withdrawals, which stays in effect after a restart until it is lifted.
+
+use tracing::{debug, error, instrument, trace};
+use vrsc::Amount;
+use vrsc_rpc::RpcApi;
+
+use crate::{
+    audit::{self, AuditAction},
+    authorization::{can_manage_funds, guild_of},
+    commands::{admin, begin_balance_change, confirm},
+    mod_log::{self, ModLogEvent},
+    util::{database, format, rpc::VerusRpc, rpc_guard},
+    Context, Error,
+};
+
+/// Manage the hot wallet
+#[instrument(skip(_ctx))]
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    check = "can_manage_funds",
+    subcommands("status", "sweep", "rescan", "freeze")
+)]
+pub async fn wallet(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the balance of the hot wallet against what the bot owes
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let settings = &ctx.data().settings.application.hot_wallet;
+    let client = ctx.data().verus()?;
+
+    let balance = rpc_guard::call(&client, "getbalance", |client| {
+        client.get_balance(None, None)
+    })
+    .await?;
+    let liabilities =
+        Amount::from_sat(database::get_liabilities(&ctx.data().database).await?.total);
+    let reserve = reserve(liabilities, settings.reserve_ratio);
+    let frozen = *ctx.data().withdrawals_frozen.read().await;
+
+    let coverage = match liabilities.as_sat() {
+        0 => String::from("-"),
+        total => format!("{:.2}%", balance.as_sat() as f64 / total as f64 * 100.0),
+    };
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Hot wallet: {} ({coverage} of the liabilities)
+Liabilities: {}
+Reserve: {} ({:.0}% of the liabilities)
+Excess: {}
+Cold storage: {}
+Withdrawals frozen: {frozen}",
+            format::amount(balance),
+            format::amount(liabilities),
+            format::amount(reserve),
+            settings.reserve_ratio * 100.0,
+            format::amount(excess(balance, liabilities, settings.reserve_ratio)),
+            settings
+                .cold_storage_address
+                .as_deref()
+                .unwrap_or("not configured"),
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Send the excess of the hot wallet to the cold storage address
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+async fn sweep(
+    ctx: Context<'_>,
+    #[description = "The amount to sweep, at most the excess. Sweeps all of the excess when empty"]
+    amount: Option<f64>,
+) -> Result<(), Error> {
+    let settings = &ctx.data().settings.application.hot_wallet;
+
+    if *ctx.data().withdrawals_frozen.read().await {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(
+                "Withdrawals are frozen, unfreeze them with `/wallet freeze false` to sweep",
+            )
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let Some(address) = settings.cold_storage_address.clone() else {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(
+                "There is no cold storage address, set `hot_wallet.cold_storage_address` in the configuration",
+            )
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let client = ctx.data().verus()?;
+    let balance = rpc_guard::call(&client, "getbalance", |client| {
+        client.get_balance(None, None)
+    })
+    .await?;
+    let liabilities =
+        Amount::from_sat(database::get_liabilities(&ctx.data().database).await?.total);
+    // the network fee of the sweep is paid from the hot wallet as well
+    let tx_fee = *ctx.data().withdrawal_fee.read().await;
+    let sweepable = excess(balance, liabilities, settings.reserve_ratio)
+        .checked_sub(tx_fee)
+        .unwrap_or(Amount::ZERO);
+
+    let amount = match amount {
+        Some(amount) => Amount::from_vrsc(amount)?,
+        None => sweepable,
+    };
+    if amount == Amount::ZERO || amount > sweepable {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(format!(
+                "Can not sweep {}, the hot wallet has {} above its reserve",
+                format::amount(amount),
+                format::amount(sweepable)
+            ))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    if !confirm(
+        ctx,
+        format!("Sweep {} to `{address}`?", format::amount(amount)),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    // until PBaaS releases on mainnet, we should not use a value for currency for "VRSC" withdrawals as there will be a daemon error
+    let currency = match ctx.data().settings.application.testnet {
+        true => Some("vrsctest"),
+        false => None,
+    };
+    let opid = client.send_to(currency, amount, &address).await?;
+    debug!("sweep opid: {opid}");
+
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::SweepHotWallet,
+        Some(opid.clone()),
+        Some(format!("{} to {address}", format::amount(amount))),
+    )
+    .await;
+    // the sweep has no ledger entry, as the funds are not owed to anyone, so it is queued for the mod-log here
+    let event = ModLogEvent::Sweep {
+        user_id: ctx.author().id,
+        amount,
+        fees: tx_fee,
+        opid: opid.clone(),
+        destination: address.clone(),
+    };
+    let queued = match ctx.data().database.acquire().await {
+        Ok(mut conn) => mod_log::dispatch(&mut conn, ctx.guild_id(), event).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = queued {
+        error!("could not queue sweep {opid} for the mod-log: {e:?}");
+    }
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(format!(
+            "Sweeping {} to `{address}`, operation `{opid}`",
+            format::amount(amount)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Rescan the deposit addresses from a block height and process the deposits that were missed
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+async fn rescan(
+    ctx: Context<'_>,
+    #[description = "The block height to rescan from"] height: u64,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let content = match admin::rescan(ctx, height).await? {
+        true => format!("Rescanned from height {height}, the deposits were processed"),
+        false => format!("The rescan from height {height} did not succeed"),
+    };
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Freeze or unfreeze all withdrawals
+#[instrument(skip(ctx))]
+#[poise::command(slash_command, hide_in_help, check = "can_manage_funds")]
+async fn freeze(
+    ctx: Context<'_>,
+    #[description = "Whether withdrawals are frozen"] frozen: bool,
+) -> Result<(), Error> {
+    trace!("set withdrawals frozen to {frozen}");
+
+    database::set_withdrawals_frozen(&ctx.data().database, frozen).await?;
+    *ctx.data().withdrawals_frozen.write().await = frozen;
+
+    audit::record(
+        &ctx.data().database,
+        guild_of(ctx),
+        ctx.author().id,
+        AuditAction::FreezeWithdrawals,
+        None,
+        Some(frozen.to_string()),
+    )
+    .await;
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(format!("Withdrawals frozen: {frozen}"))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The part of the liabilities that stays in the hot wallet.
+fn reserve(liabilities: Amount, reserve_ratio: f64) -> Amount {
+    Amount::from_sat((liabilities.as_sat() as f64 * reserve_ratio.max(0.0)).ceil() as u64)
+}
+
+/// What the hot wallet holds above its reserve.
+fn excess(balance: Amount, liabilities: Amount, reserve_ratio: f64) -> Amount {
+    balance
+        .checked_sub(reserve(liabilities, reserve_ratio))
+        .unwrap_or(Amount::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_funds_above_the_reserve_are_excess() {
+        let vrsc = |coins: u64| Amount::from_sat(coins * 100_000_000);
+
+        assert_eq!(excess(vrsc(100), vrsc(200), 0.3), vrsc(40));
+        assert_eq!(excess(vrsc(100), vrsc(100), 1.0), Amount::ZERO);
+        assert_eq!(excess(vrsc(50), vrsc(100), 1.0), Amount::ZERO);
+        assert_eq!(excess(vrsc(100), Amount::ZERO, 0.3), vrsc(100));
+        // the reserve is rounded up, so a sweep never leaves less than the ratio
+        assert_eq!(reserve(Amount::from_sat(10), 0.25), Amount::from_sat(3));
+    }
+}
@@ -1,10 +1,16 @@
 use std::time::Instant;
 
-use poise::ChoiceParameter;
+use poise::{serenity_prelude::Colour, ChoiceParameter};
 use tracing::{instrument, trace};
 use uuid::Uuid;
+use vrsc::Amount;
 
-use crate::{util::database, Context, Error};
+use crate::{
+    health::{self, WalletLock},
+    i18n::tr,
+    util::{database, format},
+    Context, Error,
+};
 
 /// Show information about this bot.
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
@@ -36,31 +42,6 @@ pub async fn info(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
-/// Show help
-#[poise::command(track_edits, slash_command, category = "Miscellaneous")]
-#[instrument]
-pub async fn help(
-    ctx: Context<'_>,
-    #[description = "Specific command to show help about"]
-    #[autocomplete = "poise::builtins::autocomplete_command"]
-    command: Option<String>,
-) -> Result<(), Error> {
-    let extra_text_at_bottom = "\
-Type `/help <command>` for more info on a command.";
-
-    poise::builtins::help(
-        ctx,
-        command.as_deref(),
-        poise::builtins::HelpConfiguration {
-            extra_text_at_bottom,
-            ephemeral: true,
-            ..Default::default()
-        },
-    )
-    .await?;
-    Ok(())
-}
-
 /// Links to the bot GitHub repo
 #[poise::command(discard_spare_arguments, slash_command, category = "Miscellaneous")]
 pub async fn source(ctx: Context<'_>) -> Result<(), Error> {
@@ -72,7 +53,12 @@ pub async fn source(ctx: Context<'_>) -> Result<(), Error> {
 /// Register slash commands in this guild or globally
 ///
 /// Run with no arguments to register in guild, run with argument "global" to register globally.
-#[poise::command(owners_only, prefix_command, hide_in_help, category = "Miscellaneous")]
+#[poise::command(
+    prefix_command,
+    hide_in_help,
+    category = "Miscellaneous",
+    check = "crate::authorization::can_manage_bot"
+)]
 pub async fn register(ctx: Context<'_>, #[flag] global: bool) -> Result<(), Error> {
     poise::builtins::register_application_commands(ctx, global).await?;
 
@@ -91,7 +77,7 @@ pub async fn register(ctx: Context<'_>, #[flag] global: bool) -> Result<(), Erro
 #[poise::command(track_edits, slash_command, category = "Miscellaneous")]
 pub async fn notifications(ctx: Context<'_>, notifications: Notification) -> Result<(), Error> {
     let pool = &ctx.data().database;
-    database::update_notifications(&pool, &ctx.author().id, &notifications.to_string()).await?;
+    database::update_notifications(pool, &ctx.author().id, &notifications.to_string()).await?;
 
     ctx.send(|reply| {
         reply.ephemeral(true).content(format!(
@@ -104,6 +90,324 @@ pub async fn notifications(ctx: Context<'_>, notifications: Notification) -> Res
     Ok(())
 }
 
+/// Change your personal settings
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    subcommands("tippable", "lowbalance", "private", "output")
+)]
+pub async fn settings(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Choose whether you receive role tips and soaks
+///
+/// -------- :robot: **Tippable** --------
+///
+/// Set to **False** to be left out of role tips and soaks. You can still be tipped directly.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+async fn tippable(
+    ctx: Context<'_>,
+    #[description = "Whether you want to receive role tips and soaks"] tippable: bool,
+) -> Result<(), Error> {
+    database::update_tippable(&ctx.data().database, &ctx.author().id, tippable).await?;
+
+    let content = match tippable {
+        true => tr!(ctx, "settings-tippable-on"),
+        false => tr!(ctx, "settings-tippable-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Keep your balance and the amounts you are tipped private
+///
+/// -------- :robot: **Private** --------
+///
+/// Set to **True** to leave the amounts out of the announcements of tips to you, and to keep others from looking up \
+/// your balance. You still see the amounts in your DMs.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+async fn private(
+    ctx: Context<'_>,
+    #[description = "Whether you want to keep your balance and tips private"] private: bool,
+) -> Result<(), Error> {
+    database::update_private(&ctx.data().database, &ctx.author().id, private).await?;
+
+    let content = match private {
+        true => tr!(ctx, "settings-private-on"),
+        false => tr!(ctx, "settings-private-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Get a DM when your balance drops below an amount
+///
+/// -------- :robot: **Low balance** --------
+///
+/// When a tip, reactdrop or withdrawal makes your balance drop below the amount, you get a DM with your deposit \
+/// address to top up. Leave the amount out to stop the reminders.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+async fn lowbalance(
+    ctx: Context<'_>,
+    #[description = "The balance below which you want a reminder, leave out to turn reminders off"]
+    #[min = 0.1]
+    threshold: Option<f64>,
+) -> Result<(), Error> {
+    let threshold = threshold.map(Amount::from_vrsc).transpose()?;
+    database::update_low_balance_threshold(&ctx.data().database, &ctx.author().id, threshold)
+        .await?;
+
+    let content = match threshold {
+        Some(threshold) => tr!(
+            ctx,
+            "settings-low-balance-on",
+            threshold: format::amount(threshold)
+        ),
+        None => tr!(ctx, "settings-low-balance-off"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Choose how much informational commands show
+///
+/// -------- :robot: **Output** --------
+///
+/// - **Compact**: Only the essentials, e.g. the USD and BTC price in `/price`.
+/// - **Detailed**: Everything, which is the default.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+async fn output(
+    ctx: Context<'_>,
+    #[description = "How much informational commands show"] output: Output,
+) -> Result<(), Error> {
+    let compact = matches!(output, Output::Compact);
+    database::update_compact(&ctx.data().database, &ctx.author().id, compact).await?;
+
+    let content = match compact {
+        true => tr!(ctx, "settings-output-compact"),
+        false => tr!(ctx, "settings-output-detailed"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Change the language the bot responds in
+///
+/// -------- :robot: **Language** --------
+///
+/// Pick one of the available languages. Pick **auto** to use the language of your Discord client.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous")]
+pub async fn language(
+    ctx: Context<'_>,
+    #[description = "The language you want the bot to respond in"]
+    #[autocomplete = "autocomplete_language"]
+    language: String,
+) -> Result<(), Error> {
+    let translations = &ctx.data().translations;
+
+    let language = match language.as_str() {
+        "auto" => None,
+        l if translations.has_locale(l) => Some(l),
+        _ => {
+            ctx.send(|reply| {
+                reply.ephemeral(true).content(tr!(
+                    ctx,
+                    "language-unknown",
+                    language: language.as_str(),
+                    available: translations.locales().join(", ")
+                ))
+            })
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let pool = &ctx.data().database;
+    database::update_language(pool, &ctx.author().id, language).await?;
+
+    {
+        let mut user_languages = ctx.data().user_languages.write().unwrap();
+        match language {
+            Some(language) => user_languages.insert(ctx.author().id, language.to_owned()),
+            None => user_languages.remove(&ctx.author().id),
+        };
+    }
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(tr!(
+            ctx,
+            "language-set",
+            language: language.unwrap_or("auto")
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Change the language of the DMs the bot sends you
+///
+/// -------- :robot: **DM language** --------
+///
+/// Notifications in DM (tips, deposits) are sent in this language, regardless of the server you are in. \
+/// Pick **auto** to use the language you picked with `/language`.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Miscellaneous", rename = "dm-language")]
+pub async fn dm_language(
+    ctx: Context<'_>,
+    #[description = "The language you want to receive DMs in"]
+    #[autocomplete = "autocomplete_language"]
+    language: String,
+) -> Result<(), Error> {
+    let translations = &ctx.data().translations;
+
+    let language = match language.as_str() {
+        "auto" => None,
+        l if translations.has_locale(l) => Some(l),
+        _ => {
+            ctx.send(|reply| {
+                reply.ephemeral(true).content(tr!(
+                    ctx,
+                    "language-unknown",
+                    language: language.as_str(),
+                    available: translations.locales().join(", ")
+                ))
+            })
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    database::update_dm_language(&ctx.data().database, &ctx.author().id, language).await?;
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).content(tr!(
+            ctx,
+            "language-dm-set",
+            language: language.unwrap_or("auto")
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Show whether the bot and the services it depends on are healthy
+///
+/// -------- :robot: **Status** --------
+/// Shows whether the Verus daemon and the database can be reached, how far the daemon is synced compared to the \
+/// explorer and whether the wallet is locked.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, rename = "status", category = "Miscellaneous")]
+pub async fn health(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let client = ctx.data().verus()?;
+    let report = health::check(
+        &client,
+        &ctx.data().database,
+        ctx.data().settings.application.testnet,
+    )
+    .await;
+    let degraded = *ctx.data().degraded.read().await;
+
+    let yes_no = |ok: bool| match ok {
+        true => tr!(ctx, "status-ok"),
+        false => tr!(ctx, "status-unreachable"),
+    };
+    let unknown = || tr!(ctx, "status-unknown");
+
+    let daemon_height = report
+        .daemon_height
+        .map(|(blocks, headers)| tr!(ctx, "status-blocks", blocks: blocks, headers: headers))
+        .unwrap_or_else(unknown);
+    let explorer_height = report
+        .explorer_height
+        .map(|height| height.to_string())
+        .unwrap_or_else(unknown);
+    let wallet_lock = match report.wallet_lock {
+        Some(WalletLock::Unencrypted) => tr!(ctx, "status-wallet-unencrypted"),
+        Some(WalletLock::Locked) => tr!(ctx, "status-wallet-locked"),
+        Some(WalletLock::Unlocked) => tr!(ctx, "status-wallet-unlocked"),
+        None => unknown(),
+    };
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).embed(|embed| {
+            embed
+                .title(tr!(ctx, "status-title"))
+                .colour(
+                    match degraded || !report.daemon_reachable || !report.database_reachable {
+                        true => Colour::RED,
+                        false => Colour::DARK_GREEN,
+                    },
+                )
+                .field(
+                    tr!(ctx, "status-daemon"),
+                    yes_no(report.daemon_reachable),
+                    true,
+                )
+                .field(
+                    tr!(ctx, "status-database"),
+                    yes_no(report.database_reachable),
+                    true,
+                )
+                .field(tr!(ctx, "status-wallet"), wallet_lock, true)
+                .field(tr!(ctx, "status-daemon-height"), daemon_height, true)
+                .field(tr!(ctx, "status-explorer-height"), explorer_height, true)
+                .field(
+                    tr!(ctx, "status-degraded"),
+                    match degraded {
+                        true => tr!(ctx, "status-degraded-yes"),
+                        false => tr!(ctx, "status-degraded-no"),
+                    },
+                    false,
+                )
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn autocomplete_language(ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let mut languages = vec![String::from("auto")];
+    languages.extend(
+        ctx.data()
+            .translations
+            .locales()
+            .into_iter()
+            .map(|l| l.to_owned()),
+    );
+
+    languages
+        .into_iter()
+        .filter(|l| l.to_lowercase().starts_with(&partial.to_lowercase()))
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
 #[derive(Debug, ChoiceParameter)]
 pub enum Notification {
     #[name = "All"]
@@ -127,3 +431,11 @@ impl From<String> for Notification {
         }
     }
 }
+
+#[derive(Debug, ChoiceParameter)]
+pub enum Output {
+    #[name = "Compact"]
+    Compact,
+    #[name = "Detailed"]
+    Detailed,
+}
@@ -1,28 +1,188 @@
-use poise::serenity_prelude::UserId;
+use std::time::Duration;
+
+use poise::serenity_prelude::{
+    ButtonStyle, CollectComponentInteraction, InteractionResponseType, UserId,
+};
 use tracing::trace;
 
-use crate::{Context, Error};
+use crate::{content_filter, i18n::tr, shutdown::InFlight, Context, Error};
 
 pub mod admin;
 pub mod chain;
+pub mod competition;
+pub mod daily;
+pub mod dispute;
+pub mod guild_settings;
+pub mod help;
+pub mod hot_wallet;
 pub mod misc;
+pub mod pot;
+pub mod referral;
+pub mod stats;
 pub mod tipping;
+pub mod treasury;
+pub mod trivia;
 pub mod wallet;
 
-async fn user_blacklisted(ctx: Context<'_>, user_id: UserId) -> Result<bool, Error> {
-    let blacklist = &ctx.data().blacklist;
+/// Commands that change balances should not run while the Verus daemon is unreachable or syncing.
+async fn bot_degraded(ctx: Context<'_>) -> Result<bool, Error> {
+    if *ctx.data().degraded.read().await {
+        trace!("bot is degraded");
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "error-degraded")))
+            .await?;
 
-    if blacklist.lock().unwrap().contains(&user_id) {
-        trace!("user is blacklisted");
+        return Ok(true);
+    }
+
+    let sync = ctx.data().sync.read().await.clone();
+    if let Some(sync) = sync {
+        trace!("daemon is syncing");
+        let progress = format!("{:.0}", sync.verification_progress * 100.0);
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "error-syncing", progress: progress))
+        })
+        .await?;
+
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// Withdrawals are refused while they are turned off with `!withdrawenabled` or frozen with `/wallet freeze`. Every
+/// command that sends funds out of the wallet checks this first.
+async fn withdrawals_blocked(ctx: Context<'_>) -> Result<bool, Error> {
+    let enabled = *ctx.data().withdrawals_enabled.read().await;
+    let frozen = *ctx.data().withdrawals_frozen.read().await;
+    if !enabled || frozen {
+        trace!("withdrawals are disabled or frozen");
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "withdraw-disabled")))
+            .await?;
+
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// Registers a command that changes balances, so a shutdown waits until it is done. Returns `None` when the bot is
+/// shutting down, after telling the author to try again.
+///
+/// Commands with a confirmation prompt call this after the prompt is answered, so a prompt that is left open doesn't
+/// hold up a shutdown.
+async fn begin_balance_change(ctx: Context<'_>) -> Result<Option<InFlight>, Error> {
+    let in_flight = ctx.data().shutdown.begin();
+    if in_flight.is_none() {
+        trace!("bot is shutting down");
         ctx.send(|reply| {
             reply
                 .ephemeral(true)
-                .content(format!("You have been temporarily suspended"))
+                .content(tr!(ctx, "error-shutting-down"))
         })
         .await?;
+    }
+
+    Ok(in_flight)
+}
+
+async fn user_blacklisted(ctx: Context<'_>, user_id: UserId) -> Result<bool, Error> {
+    let blacklist = &ctx.data().blacklist;
+
+    if blacklist.lock().unwrap().contains(&user_id) {
+        trace!("user is blacklisted");
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "user-suspended")))
+            .await?;
 
         return Ok(true);
     }
 
     Ok(false)
 }
+
+/// Refuses text a user wants the bot to post, like the note of a tip, when it has a word that is blocked in this guild.
+/// See [`content_filter`].
+async fn text_blocked(ctx: Context<'_>, text: Option<&str>) -> Result<bool, Error> {
+    let Some(text) = text else {
+        return Ok(false);
+    };
+    if content_filter::check(&ctx.data().database, ctx.guild_id(), text)
+        .await?
+        .is_none()
+    {
+        return Ok(false);
+    }
+
+    trace!("text has a blocked word");
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(tr!(ctx, "error-text-blocked"))
+    })
+    .await?;
+
+    Ok(true)
+}
+
+/// How long a user has to answer a confirmation prompt.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Asks the author to confirm `question` with a button. Returns false when the author cancels or does not answer in
+/// time. The prompt is ephemeral and is updated with the answer, so the command should send its own reply afterwards.
+async fn confirm(ctx: Context<'_>, question: String) -> Result<bool, Error> {
+    let confirm_id = format!("{}-confirm", ctx.id());
+    let cancel_id = format!("{}-cancel", ctx.id());
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(question)
+            .components(|components| {
+                components.create_action_row(|row| {
+                    row.create_button(|button| {
+                        button
+                            .custom_id(&confirm_id)
+                            .label(tr!(ctx, "confirm-yes"))
+                            .style(ButtonStyle::Success)
+                    })
+                    .create_button(|button| {
+                        button
+                            .custom_id(&cancel_id)
+                            .label(tr!(ctx, "confirm-no"))
+                            .style(ButtonStyle::Secondary)
+                    })
+                })
+            })
+    })
+    .await?;
+
+    let prefix = ctx.id().to_string();
+    let interaction = CollectComponentInteraction::new(ctx)
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .timeout(CONFIRM_TIMEOUT)
+        .filter(move |interaction| interaction.data.custom_id.starts_with(&prefix))
+        .await;
+
+    let Some(interaction) = interaction else {
+        trace!("confirmation timed out");
+        return Ok(false);
+    };
+
+    let confirmed = interaction.data.custom_id == confirm_id;
+    let answer = match confirmed {
+        true => tr!(ctx, "confirm-confirmed"),
+        false => tr!(ctx, "confirm-cancelled"),
+    };
+
+    interaction
+        .create_interaction_response(ctx, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|data| data.content(answer).components(|c| c))
+        })
+        .await?;
+
+    Ok(confirmed)
+}
@@ -0,0 +1,880 @@
+//! `/pot`: crowdfunding pots, for members that raise funds together for a goal.
+//!
+//! The contributions are held in the ledger account of the pot, see [`crate::ledger::Account::Pot`], until the
+//! creator closes the pot. That pays everything to the recipient of the pot: a member, or an address or VerusID it is
+//! withdrawn to, minus the network fee and the bot fee. A cancelled pot refunds every contributor.
+//!
+//! A withdrawal that was interrupted, like when the bot stopped while sending it, is resolved when the bot starts, see
+//! [`resolve_withdrawals`]: the pot is paid out when its withdrawal went through, and closed again when nothing was
+//! sent. A withdrawal whose send timed out might still have gone through, so it is left for an operator to check. A
+//! pot that was closed but never paid out can still be cancelled.
+
+use std::time::Duration;
+
+use poise::serenity_prelude::{
+    self, CacheHttp, ChannelId, CreateEmbed, GuildId, MessageId, UserId,
+};
+use sqlx::PgPool;
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+use vrsc_rpc::bitcoin::Txid;
+
+use crate::{
+    authorization::{can_manage_guild, guild_of},
+    commands::{
+        begin_balance_change, bot_degraded, confirm, user_blacklisted,
+        wallet::{bot_fee_of, destination_is_valid, max_withdrawal, wait_for_sendcurrency_finish},
+        withdrawals_blocked,
+    },
+    configuration::BotFee,
+    i18n::tr,
+    metrics,
+    util::{database, format, rpc::VerusRpc, rpc_guard},
+    Context, Error,
+};
+
+/// The number of blocks in the progress bar of a pot.
+const PROGRESS_BAR_SIZE: u64 = 10;
+
+/// Who a pot is paid out to when it is closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PotRecipient {
+    User(UserId),
+    /// An address or VerusID that the pot is withdrawn to
+    Address(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Pot {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    /// The message with the progress of the pot
+    pub message_id: Option<MessageId>,
+    pub name: String,
+    pub created_by: UserId,
+    pub goal: Amount,
+    pub recipient: PotRecipient,
+}
+
+/// The outcome of a contribution to a pot.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PotContribution {
+    /// The contribution was added, and the pot now holds `raised`
+    Added {
+        raised: Amount,
+    },
+    /// The pot was closed or cancelled in the meantime
+    NotOpen,
+    InsufficientBalance,
+}
+
+/// Where the funds of a closed pot go.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PotPayout {
+    User(UserId),
+    /// A withdrawal of `amount`, where `amount` and the fees together are everything the pot holds
+    Withdrawal {
+        amount: Amount,
+        tx_fee: Amount,
+        bot_fee: Amount,
+    },
+}
+
+/// A pot whose withdrawal was started but never paid out.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WithdrawingPot {
+    pub id: i64,
+    pub created_by: UserId,
+    /// The operation of the withdrawal and what it pays, which are stored before it is sent. The operation is unknown
+    /// until it was sent, or when sending it timed out.
+    pub sent: Option<(Option<String>, PotPayout)>,
+}
+
+/// Raise funds together in a crowdfunding pot
+///
+/// -------- :robot: **Pots** --------
+/// Open a pot for a goal with `/pot create`, and members add to it with `/pot contribute`. The message of the pot \
+/// shows how far it got. The bot holds the contributions until the creator closes the pot with `/pot close`, which \
+/// pays everything to the recipient of the pot: a member, or an address or VerusID. A pot that is cancelled with \
+/// `/pot cancel` refunds every contributor.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Pots",
+    guild_only,
+    subcommands("create", "contribute", "close", "cancel")
+)]
+pub async fn pot(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Open a crowdfunding pot in this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Pots", guild_only)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "The name of the pot"]
+    #[max_length = 50]
+    name: String,
+    #[description = "The amount the pot raises"]
+    #[min = 0.1]
+    goal_amount: f64,
+    #[description = "The member the pot is paid to, you by default"] recipient: Option<
+        serenity_prelude::User,
+    >,
+    #[description = "An address starting with R* or i*, or a VerusID (ends with @), to withdraw the pot to instead"]
+    address: Option<String>,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    let recipient = match (recipient, address) {
+        (Some(_), Some(_)) => {
+            ctx.send(|reply| {
+                reply
+                    .ephemeral(true)
+                    .content(tr!(ctx, "pot-two-recipients"))
+            })
+            .await?;
+
+            return Ok(());
+        }
+        (Some(user), None) => PotRecipient::User(user.id),
+        (None, Some(address)) => {
+            let address = address.trim().to_string();
+            if !destination_is_valid(&address, &ctx.data().verus()?).await? {
+                ctx.send(|reply| {
+                    reply.ephemeral(true).content(tr!(
+                        ctx,
+                        "withdraw-invalid-destination",
+                        destination: address.as_str()
+                    ))
+                })
+                .await?;
+
+                return Ok(());
+            }
+
+            PotRecipient::Address(address)
+        }
+        (None, None) => PotRecipient::User(ctx.author().id),
+    };
+
+    let pool = &ctx.data().database;
+    let mut pot = Pot {
+        id: 0,
+        guild_id: guild_of(ctx),
+        channel_id: ctx.channel_id(),
+        message_id: None,
+        name: name.trim().to_string(),
+        created_by: ctx.author().id,
+        goal: Amount::from_vrsc(goal_amount)?,
+        recipient,
+    };
+    let Some(id) = database::create_pot(pool, &pot).await? else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "pot-name-taken", name: pot.name.as_str()))
+        })
+        .await?;
+
+        return Ok(());
+    };
+    pot.id = id;
+    debug!("pot {} opened in {}: {pot:?}", pot.id, pot.guild_id);
+
+    let reply_handle = ctx
+        .send(|reply| {
+            reply
+                .content(tr!(
+                    ctx,
+                    "pot-created",
+                    user: format!("<@{}>", ctx.author().id),
+                    name: pot.name.as_str(),
+                    goal: format::amount(pot.goal)
+                ))
+                .embed(|embed| progress_embed(ctx, embed, &pot, Amount::ZERO))
+        })
+        .await?;
+    let message = reply_handle.message().await?;
+    database::set_pot_message(pool, pot.id, message.id).await?;
+
+    Ok(())
+}
+
+/// Contribute to a crowdfunding pot
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Pots", guild_only)]
+async fn contribute(
+    ctx: Context<'_>,
+    #[description = "The name of the pot"]
+    #[autocomplete = "autocomplete_pot"]
+    name: String,
+    #[description = "The amount you want to contribute"]
+    #[min = 0.1]
+    amount: f64,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let Some(pot) = open_pot(ctx, &name).await? else {
+        return Ok(());
+    };
+    let amount = Amount::from_vrsc(amount)?;
+
+    let raised =
+        match database::contribute_to_pot(&ctx.data().database, pot.id, &ctx.author().id, amount)
+            .await?
+        {
+            PotContribution::Added { raised } => raised,
+            PotContribution::NotOpen => {
+                ctx.send(|reply| {
+                    reply
+                        .ephemeral(true)
+                        .content(tr!(ctx, "pot-not-found", name: pot.name.as_str()))
+                })
+                .await?;
+
+                return Ok(());
+            }
+            PotContribution::InsufficientBalance => {
+                ctx.send(|reply| {
+                    reply
+                        .ephemeral(true)
+                        .content(tr!(ctx, "tip-insufficient-balance"))
+                })
+                .await?;
+
+                return Ok(());
+            }
+        };
+    debug!(
+        "{} contributed {amount} to pot {}, which raised {raised}",
+        ctx.author().id,
+        pot.id
+    );
+
+    update_progress(ctx, &pot, raised, None).await;
+    ctx.send(|reply| {
+        reply.content(tr!(
+            ctx,
+            "pot-contributed",
+            user: format!("<@{}>", ctx.author().id),
+            amount: format::amount(amount),
+            name: pot.name.as_str(),
+            raised: format::amount(raised),
+            goal: format::amount(pot.goal)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Close your crowdfunding pot and pay it out to its recipient
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Pots", guild_only)]
+async fn close(
+    ctx: Context<'_>,
+    #[description = "The name of the pot"]
+    #[autocomplete = "autocomplete_pot"]
+    name: String,
+) -> Result<(), Error> {
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+
+    let Some(pot) = open_pot(ctx, &name).await? else {
+        return Ok(());
+    };
+    if pot.created_by != ctx.author().id {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "pot-close-not-allowed"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+    if matches!(pot.recipient, PotRecipient::Address(_)) && withdrawals_blocked(ctx).await? {
+        return Ok(());
+    }
+
+    let pool = &ctx.data().database;
+    let raised = database::get_pot_raised(pool, pot.id).await?;
+    let mut prompt = tr!(
+        ctx,
+        "pot-confirm-close",
+        name: pot.name.as_str(),
+        raised: format::amount(raised),
+        recipient: recipient_name(&pot.recipient)
+    );
+    if let PotRecipient::Address(_) = pot.recipient {
+        let tx_fee = *ctx.data().withdrawal_fee.read().await;
+        let (amount, bot_fee) = withdrawal_of(raised, tx_fee, &bot_fee_of(ctx).await?);
+        prompt.push('\n');
+        prompt.push_str(&tr!(
+            ctx,
+            "pot-confirm-close-fees",
+            amount: format::amount(amount),
+            tx_fee: format::amount(tx_fee),
+            bot_fee: format::amount(bot_fee)
+        ));
+    }
+    if !confirm(ctx, prompt).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    // a pot for a member is paid out as it is closed, a pot for an address when its withdrawal was sent
+    let pay_to = match pot.recipient {
+        PotRecipient::User(user_id) => Some(user_id),
+        PotRecipient::Address(_) => None,
+    };
+    let Some(raised) = database::close_pot(pool, pot.id, pay_to).await? else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "pot-not-found", name: pot.name.as_str()))
+        })
+        .await?;
+
+        return Ok(());
+    };
+    info!(
+        "pot {} closed by {}, it raised {raised}",
+        pot.id,
+        ctx.author().id
+    );
+
+    let content = match &pot.recipient {
+        PotRecipient::User(user_id) => tr!(
+            ctx,
+            "pot-paid",
+            name: pot.name.as_str(),
+            recipient: format!("<@{user_id}>"),
+            amount: format::amount(raised)
+        ),
+        PotRecipient::Address(address) => withdraw(ctx, &pot, address).await?,
+    };
+
+    update_progress(ctx, &pot, raised, Some(tr!(ctx, "pot-status-closed"))).await;
+    ctx.send(|reply| reply.content(content)).await?;
+
+    Ok(())
+}
+
+/// Cancel a crowdfunding pot and refund its contributors
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Pots", guild_only)]
+async fn cancel(
+    ctx: Context<'_>,
+    #[description = "The name of the pot"]
+    #[autocomplete = "autocomplete_cancellable_pot"]
+    name: String,
+) -> Result<(), Error> {
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+
+    let Some(pot) = cancellable_pot(ctx, &name).await? else {
+        return Ok(());
+    };
+    if pot.created_by != ctx.author().id && !can_manage_guild(ctx).await? {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "pot-cancel-not-allowed"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let pool = &ctx.data().database;
+    let raised = database::get_pot_raised(pool, pot.id).await?;
+    if !confirm(
+        ctx,
+        tr!(
+            ctx,
+            "pot-confirm-cancel",
+            name: pot.name.as_str(),
+            raised: format::amount(raised)
+        ),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let Some(refunds) = database::cancel_pot(pool, pot.id).await? else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "pot-not-found", name: pot.name.as_str()))
+        })
+        .await?;
+
+        return Ok(());
+    };
+    let refunded = refunds
+        .iter()
+        .fold(Amount::ZERO, |total, (_, amount)| total + *amount);
+    info!(
+        "pot {} cancelled by {}, refunded {refunded} to {} contributor(s)",
+        pot.id,
+        ctx.author().id,
+        refunds.len()
+    );
+
+    update_progress(
+        ctx,
+        &pot,
+        Amount::ZERO,
+        Some(tr!(ctx, "pot-status-cancelled")),
+    )
+    .await;
+    ctx.send(|reply| {
+        reply.content(tr!(
+            ctx,
+            "pot-cancelled",
+            name: pot.name.as_str(),
+            amount: format::amount(refunded),
+            contributors: refunds.len()
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Withdraws a closed pot to its address, minus the network fee and the bot fee. The pot is paid out once the
+/// withdrawal went through. When the pot does not hold enough for the fees or the withdrawal failed, the pot is closed
+/// again, so its creator can cancel it. When the outcome is not known, the pot stays withdrawing until it is resolved,
+/// see [`resolve_withdrawals`].
+async fn withdraw(ctx: Context<'_>, pot: &Pot, address: &str) -> Result<String, Error> {
+    let pool = &ctx.data().database;
+    let tx_fee = *ctx.data().withdrawal_fee.read().await;
+    let bot_fee = bot_fee_of(ctx).await?;
+
+    // a send can't be undone, so the withdrawal is started before anything is sent. That keeps it from being sent
+    // twice or being cancelled while it is sent.
+    let Some(raised) = database::start_pot_withdrawal(pool, pot.id).await? else {
+        return Ok(tr!(ctx, "pot-not-found", name: pot.name.as_str()));
+    };
+    let (amount, bot_fee) = withdrawal_of(raised, tx_fee, &bot_fee);
+    if amount == Amount::ZERO {
+        return revert_withdrawal(ctx, pot, raised, address).await;
+    }
+
+    // what is sent is stored before it is sent, so a pot that is withdrawing without it was never sent
+    if let Err(e) =
+        database::store_pot_withdrawal(pool, pot.id, None, amount, tx_fee, bot_fee).await
+    {
+        database::revert_pot_withdrawal(pool, pot.id).await?;
+
+        return Err(e);
+    }
+
+    let client = ctx.data().verus()?;
+    // until PBaaS releases on mainnet, we should not use a value for currency for "VRSC" withdrawals as there will be a daemon error
+    let currency = match ctx.data().settings.application.testnet {
+        true => Some("vrsctest"),
+        false => None,
+    };
+    let opid = match client.send_to(currency, amount, address).await {
+        Ok(opid) => opid,
+        Err(e) if rpc_guard::not_done(&e) => {
+            error!("could not withdraw pot {} to {address}: {e:?}", pot.id);
+
+            return revert_withdrawal(ctx, pot, raised, address).await;
+        }
+        Err(e) => {
+            // the send timed out or lost its connection, so it might still go through. The pot stays withdrawing for
+            // an operator to check, see `resolve_withdrawals`.
+            error!(
+                "could not tell whether pot {} was withdrawn to {address}: {e:?}",
+                pot.id
+            );
+
+            return Ok(tr!(ctx, "pot-withdraw-unknown", name: pot.name.as_str()));
+        }
+    };
+    debug!("pot {} withdrawal opid: {opid}", pot.id);
+    // without its operation, the withdrawal can only be resolved by hand when the bot stops before it is paid out
+    store_opid(pool, pot.id, &opid, amount, tx_fee, bot_fee).await;
+
+    // the pot is only paid out once the daemon reports how the withdrawal ended
+    let txid = match wait_for_sendcurrency_finish(pool, &client, &opid).await {
+        Ok(Some(txid)) => txid,
+        Ok(None) => return revert_withdrawal(ctx, pot, raised, address).await,
+        Err(e) => {
+            error!(
+                "could not get the outcome of {opid}, the withdrawal of pot {}: {e:?}",
+                pot.id
+            );
+
+            return Ok(tr!(ctx, "pot-withdraw-unknown", name: pot.name.as_str()));
+        }
+    };
+
+    let payout = PotPayout::Withdrawal {
+        amount,
+        tx_fee,
+        bot_fee,
+    };
+    settle_withdrawal(pool, pot.id, pot.created_by, &payout, Some(&opid), &txid).await?;
+
+    Ok(tr!(
+        ctx,
+        "pot-withdrawn",
+        name: pot.name.as_str(),
+        amount: format::amount(amount),
+        address: address
+    ))
+}
+
+/// The number of times the operation of a pot withdrawal is stored before it is left for an operator.
+const STORE_OPID_ATTEMPTS: u32 = 5;
+const STORE_OPID_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Stores the operation of a pot withdrawal that was sent, retrying when that fails. A pot whose operation could not be
+/// stored stays withdrawing, and is never closed again when the bot stops, as it might have been sent.
+async fn store_opid(
+    pool: &PgPool,
+    pot_id: i64,
+    opid: &str,
+    amount: Amount,
+    tx_fee: Amount,
+    bot_fee: Amount,
+) {
+    for attempt in 1..=STORE_OPID_ATTEMPTS {
+        match database::store_pot_withdrawal(pool, pot_id, Some(opid), amount, tx_fee, bot_fee)
+            .await
+        {
+            Ok(()) => return,
+            Err(e) => error!(
+                "could not store the withdrawal {opid} of pot {pot_id} (attempt {attempt}): {e:?}"
+            ),
+        }
+        if attempt < STORE_OPID_ATTEMPTS {
+            tokio::time::sleep(STORE_OPID_RETRY_DELAY).await;
+        }
+    }
+    error!("gave up storing the withdrawal {opid} of pot {pot_id}, it has to be checked with `/admin pot` if the bot stops");
+}
+
+/// Closes a withdrawing pot again when nothing was sent, so its creator can cancel it to refund its contributors.
+async fn revert_withdrawal(
+    ctx: Context<'_>,
+    pot: &Pot,
+    raised: Amount,
+    address: &str,
+) -> Result<String, Error> {
+    database::revert_pot_withdrawal(&ctx.data().database, pot.id).await?;
+
+    Ok(tr!(
+        ctx,
+        "pot-withdraw-failed",
+        name: pot.name.as_str(),
+        amount: format::amount(raised),
+        address: address,
+        user: format!("<@{}>", pot.created_by)
+    ))
+}
+
+/// Resolves the withdrawals of pots that are still withdrawing, like when the bot stopped while sending them. A pot
+/// whose withdrawal went through is paid out, and a pot whose withdrawal was never sent or failed is closed again, so
+/// its creator can cancel it. A withdrawal whose send timed out, or that the daemon does not know anymore, is left for
+/// an operator to check.
+pub async fn resolve_withdrawals(pool: &PgPool, client: &impl VerusRpc) -> Result<(), Error> {
+    for pot in database::get_withdrawing_pots(pool).await? {
+        if let Err(e) = resolve_withdrawal(pool, client, pot).await {
+            error!("could not resolve a pot withdrawal: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn resolve_withdrawal(
+    pool: &PgPool,
+    client: &impl VerusRpc,
+    pot: WithdrawingPot,
+) -> Result<(), Error> {
+    let Some((opid, payout)) = pot.sent else {
+        warn!(
+            "the withdrawal of pot {} was never sent, closing it again",
+            pot.id
+        );
+        database::revert_pot_withdrawal(pool, pot.id).await?;
+
+        return Ok(());
+    };
+
+    let Some(opid) = opid else {
+        error!(
+            "the withdrawal of pot {} might have been sent, check it and use `/admin pot`",
+            pot.id
+        );
+
+        return Ok(());
+    };
+    if client.operation_status(&opid).await?.is_none() {
+        error!(
+            "the daemon does not know {opid}, the withdrawal of pot {}, check it and use `/admin pot`",
+            pot.id
+        );
+
+        return Ok(());
+    }
+    let Some(txid) = wait_for_sendcurrency_finish(pool, client, &opid).await? else {
+        warn!(
+            "the withdrawal {opid} of pot {} failed, closing it again",
+            pot.id
+        );
+        database::revert_pot_withdrawal(pool, pot.id).await?;
+
+        return Ok(());
+    };
+
+    settle_withdrawal(pool, pot.id, pot.created_by, &payout, Some(&opid), &txid).await?;
+    info!(
+        "paid out pot {} after its withdrawal {opid} went through",
+        pot.id
+    );
+
+    Ok(())
+}
+
+/// Pays out a withdrawing pot whose withdrawal went through with `txid`. The withdrawal is stored on behalf of the
+/// creator, so it can be looked up like any other.
+pub async fn settle_withdrawal(
+    pool: &PgPool,
+    pot_id: i64,
+    created_by: UserId,
+    payout: &PotPayout,
+    opid: Option<&str>,
+    txid: &Txid,
+) -> Result<(), Error> {
+    let uuid = Uuid::new_v4();
+    database::pay_out_pot(pool, pot_id, payout, &uuid, Some(&txid.to_string())).await?;
+    let PotPayout::Withdrawal { amount, tx_fee, .. } = payout else {
+        return Ok(());
+    };
+    metrics::record_withdrawal(*amount);
+
+    // the pot is paid out, so it is only logged when the withdrawal can't be looked up later
+    let opid = opid
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("opid-{uuid}"));
+    if let Err(e) =
+        database::store_withdraw_transaction(pool, &uuid, &created_by, Some(txid), &opid, tx_fee)
+            .await
+    {
+        error!("could not store {opid}, the withdrawal of pot {pot_id}: {e:?}");
+    }
+
+    Ok(())
+}
+
+/// What is sent when a pot that holds `raised` is withdrawn, and the bot fee that is taken from it besides the network
+/// fee. What is left over from rounding the fee goes with the bot fee, as the withdrawal empties the pot.
+fn withdrawal_of(raised: Amount, tx_fee: Amount, bot_fee: &BotFee) -> (Amount, Amount) {
+    let amount = max_withdrawal(&raised, &tx_fee, bot_fee);
+    let fees = raised.checked_sub(amount).unwrap_or(Amount::ZERO);
+
+    (amount, fees.checked_sub(tx_fee).unwrap_or(Amount::ZERO))
+}
+
+/// The open pot with this name in the guild of `ctx`, or `None` after telling the author there is none.
+async fn open_pot(ctx: Context<'_>, name: &str) -> Result<Option<Pot>, Error> {
+    let pot = database::get_open_pot(&ctx.data().database, guild_of(ctx), name).await?;
+
+    tell_if_not_found(ctx, name, pot).await
+}
+
+/// The pot with this name in the guild of `ctx` that can be cancelled, or `None` after telling the author there is
+/// none.
+async fn cancellable_pot(ctx: Context<'_>, name: &str) -> Result<Option<Pot>, Error> {
+    let pot = database::get_cancellable_pot(&ctx.data().database, guild_of(ctx), name).await?;
+
+    tell_if_not_found(ctx, name, pot).await
+}
+
+async fn tell_if_not_found(
+    ctx: Context<'_>,
+    name: &str,
+    pot: Option<Pot>,
+) -> Result<Option<Pot>, Error> {
+    if pot.is_none() {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "pot-not-found", name: name.trim()))
+        })
+        .await?;
+    }
+
+    Ok(pot)
+}
+
+/// Shows the progress of a pot in its message. The contribution is already done, so failures are only logged.
+async fn update_progress(ctx: Context<'_>, pot: &Pot, raised: Amount, status: Option<String>) {
+    let Some(message_id) = pot.message_id else {
+        return;
+    };
+
+    if let Err(e) = pot
+        .channel_id
+        .edit_message(ctx.http(), message_id, |edit| {
+            edit.embed(|embed| {
+                let embed = progress_embed(ctx, embed, pot, raised);
+                match &status {
+                    Some(status) => embed.footer(|footer| footer.text(status)),
+                    None => embed,
+                }
+            })
+        })
+        .await
+    {
+        warn!("could not update the message of pot {}: {e:?}", pot.id);
+    }
+}
+
+fn progress_embed<'a>(
+    ctx: Context<'_>,
+    embed: &'a mut CreateEmbed,
+    pot: &Pot,
+    raised: Amount,
+) -> &'a mut CreateEmbed {
+    embed
+        .title(tr!(ctx, "pot-title", name: pot.name.as_str()))
+        .description(progress_bar(raised, pot.goal))
+        .field(tr!(ctx, "pot-raised"), format::amount(raised), true)
+        .field(tr!(ctx, "pot-goal"), format::amount(pot.goal), true)
+        .field(
+            tr!(ctx, "pot-recipient"),
+            recipient_name(&pot.recipient),
+            true,
+        )
+}
+
+fn recipient_name(recipient: &PotRecipient) -> String {
+    match recipient {
+        PotRecipient::User(user_id) => format!("<@{user_id}>"),
+        PotRecipient::Address(address) => format!("`{address}`"),
+    }
+}
+
+/// A bar of [`PROGRESS_BAR_SIZE`] blocks with the percentage of the goal that was raised. The bar is full from the
+/// goal on, the percentage goes on above 100%.
+fn progress_bar(raised: Amount, goal: Amount) -> String {
+    let percentage = match goal.as_sat() {
+        0 => 100,
+        goal => (raised.as_sat() as u128 * 100 / goal as u128) as u64,
+    };
+    let filled = (percentage * PROGRESS_BAR_SIZE / 100).min(PROGRESS_BAR_SIZE);
+
+    format!(
+        "{}{} {percentage}%",
+        "▰".repeat(filled as usize),
+        "▱".repeat((PROGRESS_BAR_SIZE - filled) as usize)
+    )
+}
+
+async fn autocomplete_pot(ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let pots = match database::get_open_pots(&ctx.data().database, guild_of(ctx)).await {
+        Ok(pots) => pots,
+        Err(e) => {
+            error!("could not get the open pots: {e:?}");
+            vec![]
+        }
+    };
+
+    pot_names(pots, partial)
+}
+
+async fn autocomplete_cancellable_pot(
+    ctx: Context<'_>,
+    partial: &str,
+) -> impl Iterator<Item = String> {
+    let pots = match database::get_cancellable_pots(&ctx.data().database, guild_of(ctx)).await {
+        Ok(pots) => pots,
+        Err(e) => {
+            error!("could not get the cancellable pots: {e:?}");
+            vec![]
+        }
+    };
+
+    pot_names(pots, partial)
+}
+
+fn pot_names(pots: Vec<Pot>, partial: &str) -> impl Iterator<Item = String> {
+    pots.into_iter()
+        .map(|pot| pot.name)
+        .filter(|name| name.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_bar_fills_up_to_the_goal() {
+        let vrsc = |coins: u64| Amount::from_sat(coins * 100_000_000);
+
+        assert_eq!(progress_bar(Amount::ZERO, vrsc(10)), "▱▱▱▱▱▱▱▱▱▱ 0%");
+        assert_eq!(progress_bar(vrsc(3), vrsc(10)), "▰▰▰▱▱▱▱▱▱▱ 30%");
+        // the percentage is rounded down, so the bar is only full at the goal
+        assert_eq!(
+            progress_bar(Amount::from_sat(99_999_999), vrsc(1)),
+            "▰▰▰▰▰▰▰▰▰▱ 99%"
+        );
+        assert_eq!(progress_bar(vrsc(25), vrsc(10)), "▰▰▰▰▰▰▰▰▰▰ 250%");
+    }
+
+    #[test]
+    fn a_withdrawal_empties_the_pot_and_pays_both_fees() {
+        let tx_fee = Amount::from_sat(100);
+
+        let fee = BotFee::Percentage(0.5);
+        let (amount, bot_fee) = withdrawal_of(Amount::from_sat(10_151), tx_fee, &fee);
+        assert_eq!(amount, Amount::from_sat(10_000));
+        // 1 sat is left over from rounding the fee
+        assert_eq!(bot_fee, Amount::from_sat(51));
+        assert!(bot_fee >= fee.fee_for(amount));
+
+        let flat = BotFee::Flat(Amount::from_sat(50));
+        assert_eq!(
+            withdrawal_of(Amount::from_sat(1_000), tx_fee, &flat),
+            (Amount::from_sat(850), Amount::from_sat(50))
+        );
+        // a pot that can't pay the fees is not withdrawn
+        assert_eq!(
+            withdrawal_of(Amount::from_sat(120), tx_fee, &flat),
+            (Amount::ZERO, Amount::from_sat(20))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use poise::serenity_prelude::{Http, UserId};
+use rand::Rng;
+use sqlx::PgPool;
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    commands::user_blacklisted,
+    i18n::{tr, tr_locale, Translations},
+    util::{
+        database,
+        delivery::{self, Destination},
+        format,
+    },
+    Context, Error,
+};
+
+/// The characters of referral codes, without the ones that are easily mixed up (0 and O, 1 and I).
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 8;
+
+/// The outcome of `/referral claim`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReferralClaim {
+    /// The user is now a referral of this user
+    Claimed(UserId),
+    UnknownCode,
+    OwnCode,
+    AlreadyReferred,
+    /// The owner of the code was referred by the user
+    Mutual,
+    /// The user already sent a tip, so they are not a new user
+    NotNew,
+}
+
+/// Invite new users and get rewarded when they send their first tip
+///
+/// -------- :robot: **Referrals** --------
+/// Share your code from `/referral link` with someone new to the bot. \
+/// When they claim it with `/referral claim` and send their first tip, you both get a reward.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Referrals", subcommands("link", "claim"))]
+pub async fn referral(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Get your referral code to share with new users
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Referrals")]
+async fn link(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+
+    let code = database::get_or_store_referral_code(pool, &ctx.author().id, &new_code()).await?;
+    let (referred, rewarded) = database::get_referral_counts(pool, &ctx.author().id).await?;
+    let (referrer_reward, referee_reward) = database::get_referral_rewards(pool).await?;
+
+    let mut content = tr!(ctx, "referral-link", code: code.as_str());
+    if referrer_reward > Amount::ZERO || referee_reward > Amount::ZERO {
+        content.push('\n');
+        content.push_str(&tr!(
+            ctx,
+            "referral-rewards",
+            referrer_reward: format::amount(referrer_reward),
+            referee_reward: format::amount(referee_reward)
+        ));
+    }
+    if referred > 0 {
+        content.push('\n');
+        content.push_str(&tr!(
+            ctx,
+            "referral-counts",
+            referred: referred,
+            rewarded: rewarded
+        ));
+    }
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Claim the referral code of the user that invited you
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Referrals")]
+async fn claim(
+    ctx: Context<'_>,
+    #[description = "The referral code you got"]
+    #[max_length = 20]
+    code: String,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    let pool = &ctx.data().database;
+    let code = code.trim().to_uppercase();
+
+    let content = match database::claim_referral(pool, &ctx.author().id, &code).await? {
+        ReferralClaim::Claimed(referrer) => {
+            debug!("{} was referred by {referrer}", ctx.author().id);
+            tr!(ctx, "referral-claimed", referrer: format!("<@{referrer}>"))
+        }
+        ReferralClaim::UnknownCode => tr!(
+            ctx,
+            "referral-unknown-code",
+            code: format::user_text(&code, CODE_LENGTH * 2)
+        ),
+        ReferralClaim::OwnCode => tr!(ctx, "referral-own-code"),
+        ReferralClaim::AlreadyReferred => tr!(ctx, "referral-already-referred"),
+        ReferralClaim::Mutual => tr!(ctx, "referral-mutual"),
+        ReferralClaim::NotNew => tr!(ctx, "referral-not-new"),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+fn new_code() -> String {
+    let mut rng = rand::thread_rng();
+
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Pays the referral rewards when `tipper` was referred and this was their first tip, and lets both users know in DM.
+/// The tip is already done, so failures are only logged.
+pub async fn reward_first_tip(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    tipper: UserId,
+) {
+    let (referrer, referrer_reward, referee_reward) =
+        match database::pay_referral_rewards(pool, &tipper).await {
+            Ok(Some(rewards)) => rewards,
+            Ok(None) => return,
+            Err(e) => {
+                error!("could not pay the referral rewards of {tipper}: {e:?}");
+                return;
+            }
+        };
+    info!(
+        "paid referral rewards to {referrer} ({referrer_reward}) and {tipper} ({referee_reward})"
+    );
+
+    let messages = [
+        (
+            referrer,
+            referrer_reward,
+            "referral-rewarded-referrer",
+            format!("<@{tipper}>"),
+        ),
+        (
+            tipper,
+            referee_reward,
+            "referral-rewarded-referee",
+            format!("<@{referrer}>"),
+        ),
+    ];
+
+    for (user_id, reward, message, other) in messages {
+        if reward == Amount::ZERO {
+            continue;
+        }
+
+        let locale = match database::get_dm_language(pool, &user_id).await {
+            Ok(locale) => locale,
+            Err(e) => {
+                warn!("could not get the DM language of {user_id}: {e:?}");
+                None
+            }
+        };
+        let content = tr_locale!(
+            translations,
+            locale.as_deref(),
+            message,
+            amount: format::amount(reward),
+            user: other
+        );
+
+        if let Err(e) = delivery::deliver(
+            http,
+            pool,
+            translations,
+            Destination::Dm(user_id),
+            &content,
+            None,
+        )
+        .await
+        {
+            warn!("could not send the referral reward DM to {user_id}: {e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_use_the_alphabet() {
+        let code = new_code();
+
+        assert_eq!(code.len(), CODE_LENGTH);
+        assert!(code.bytes().all(|c| CODE_ALPHABET.contains(&c)));
+    }
+}
@@ -0,0 +1,155 @@
+use sqlx::types::chrono::{DateTime, Utc};
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    authorization::{can_manage_guild, guild_of},
+    util::{database, embed::InfoEmbed, format},
+    Context, Error,
+};
+
+/// The number of winners that is used to measure how concentrated the reactdrop winnings are.
+const TOP_WINNERS: usize = 5;
+
+#[derive(Debug)]
+pub struct DropResult {
+    pub started_at: DateTime<Utc>,
+    pub pot: Amount,
+    pub participants: Option<i32>,
+    pub eligible: Option<i32>,
+}
+
+#[derive(Debug)]
+pub struct HourStats {
+    pub hour: i32,
+    pub drops: i64,
+    pub average_participants: f64,
+    pub participation_rate: Option<f64>,
+}
+
+/// Statistics for server admins
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild",
+    subcommands("drops")
+)]
+pub async fn stats(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// How the reactdrops in this server perform
+///
+/// -------- :robot: **Reactdrop statistics** --------
+/// Shows the participation of recent reactdrops, the average pot size, how often the same users win, \
+/// and at which time of day (UTC) reactdrops get the most participants.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Miscellaneous",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn drops(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let (number_of_drops, average_pot) = database::get_reactdrop_totals(pool, guild_id).await?;
+    let recent_drops = database::get_recent_reactdrop_results(pool, guild_id, 10).await?;
+    let winners = database::get_reactdrop_winners(pool, guild_id).await?;
+    let hours = database::get_reactdrop_hours(pool, guild_id).await?;
+
+    debug!("{number_of_drops} reactdrops, {} winners", winners.len());
+
+    let recent = recent_drops
+        .iter()
+        .map(|drop| {
+            format!(
+                "{} - {}: {}",
+                drop.started_at.format("%Y-%m-%d %H:%M"),
+                format::amount(drop.pot),
+                participation(drop.participants, drop.eligible)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let total_wins = winners.iter().map(|(_, wins)| wins).sum::<i64>();
+    let top_wins = winners
+        .iter()
+        .take(TOP_WINNERS)
+        .map(|(_, wins)| wins)
+        .sum::<i64>();
+    let concentration = if total_wins == 0 {
+        String::from("No winners yet")
+    } else {
+        format!(
+            "{} wins by {} different users. The top {TOP_WINNERS} won {:.1}% of the time:\n{}",
+            total_wins,
+            winners.len(),
+            top_wins as f64 / total_wins as f64 * 100.0,
+            winners
+                .iter()
+                .take(TOP_WINNERS)
+                .map(|(user_id, wins)| format!("<@{user_id}>: {wins}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    };
+
+    // one line per hour, kept short to stay within the embed field limit of 1024 characters.
+    let time_of_day = hours
+        .iter()
+        .map(|hour| {
+            format!(
+                "`{:02}:00` {} drop(s), avg. {:.1} users{}",
+                hour.hour,
+                hour.drops,
+                hour.average_participants,
+                hour.participation_rate
+                    .map(|rate| format!(" ({:.1}%)", rate * 100.0))
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .field("Reactdrops", number_of_drops, true)
+        .field("Average pot", format::amount(average_pot), true)
+        .detail("Recent reactdrops", or_none(recent), false)
+        .detail("Repeat winners", concentration, false)
+        .detail("Time of day (UTC)", or_none(time_of_day), false);
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .embed(|embed| fields.fill(embed.title("Reactdrop statistics")))
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn participation(participants: Option<i32>, eligible: Option<i32>) -> String {
+    match (participants, eligible) {
+        (Some(participants), Some(eligible)) if eligible > 0 => format!(
+            "{participants} of {eligible} members ({:.1}%)",
+            participants as f64 / eligible as f64 * 100.0
+        ),
+        (Some(participants), _) => format!("{participants} participants"),
+        (None, _) => String::from("unknown"),
+    }
+}
+
+fn or_none(lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        String::from("-")
+    } else {
+        lines.join("\n")
+    }
+}
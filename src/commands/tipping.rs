@@ -1,5 +1,8 @@
 use ::chrono::Duration;
-use poise::serenity_prelude::{self, CacheHttp, ChannelId, ReactionType, RoleId, UserId};
+use poise::serenity_prelude::{
+    self, CacheHttp, ChannelId, GuildId, MessageId, OnlineStatus, ReactionType, Role, RoleId,
+    UserId,
+};
 
 use sqlx::{types::chrono, PgPool};
 use tracing::*;
@@ -7,12 +10,84 @@ use uuid::Uuid;
 use vrsc::Amount;
 
 use crate::{
-    commands::{misc::Notification, user_blacklisted},
-    util::database::{self},
+    authorization::can_manage_guild,
+    commands::{
+        begin_balance_change, bot_degraded, competition, confirm, misc::Notification, referral,
+        text_blocked, user_blacklisted,
+    },
+    i18n::{tr, tr_locale, Translations},
+    low_balance, metrics, quick_tip,
+    reactdrop::{self, Reactdrop, ReactdropState},
+    util::{
+        balance_store::BalanceStore,
+        database::{self},
+        delivery::{self, Delivery, Destination},
+        format,
+    },
     wallet::get_and_check_balance,
     Context, Error,
 };
 
+/// A single tip to a single recipient, as stored in the `tips_vrsc` table. Group tips share the same uuid.
+#[derive(Debug)]
+pub struct Tip {
+    pub uuid: String,
+    pub recipient: UserId,
+    pub tipper: UserId,
+    pub kind: String,
+    pub amount: Amount,
+    pub memo: Option<String>,
+    /// The guild the tip was made in, unknown for tips made before it was stored
+    pub guild_id: Option<GuildId>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The result of reversing a tip event with `/admin reverse`.
+#[derive(Debug)]
+pub enum Reversal {
+    /// There is no tip event with this id in the last 24 hours
+    NotFound,
+    AlreadyReversed,
+    Reversed {
+        tipper: UserId,
+        /// The recipients the tip was taken back from
+        recovered: Vec<(UserId, Amount)>,
+        /// The recipients that no longer have (all of) their tip, or have it frozen in a dispute
+        unrecovered: Vec<(UserId, Amount)>,
+    },
+}
+
+/// Refuses the tipping commands outside the channels that the guild allows tipping in, and points the author to those
+/// channels. A guild that did not allow any channels with `/admin channels allow` allows tipping everywhere.
+async fn tipping_channel_allowed(ctx: Context<'_>) -> Result<bool, Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+
+    let mut channels = match ctx.data().tip_channels.read().unwrap().get(&guild_id) {
+        Some(channels) if !channels.contains(&ctx.channel_id()) => {
+            channels.iter().copied().collect::<Vec<_>>()
+        }
+        _ => return Ok(true),
+    };
+    channels.sort();
+
+    trace!("tipping is not allowed in {}", ctx.channel_id());
+    let channels = channels
+        .iter()
+        .map(|channel_id| format!("<#{channel_id}>"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(tr!(ctx, "error-tipping-channel", channels: channels))
+    })
+    .await?;
+
+    Ok(false)
+}
+
 /// Tip a user or a role
 ///
 /// -------- :robot: **Tipping a user** --------
@@ -29,20 +104,31 @@ pub async fn tip(_ctx: Context<'_>) -> Result<(), Error> {
 
 /// Tip a role by entering and selecting the role name.
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
-#[poise::command(slash_command, category = "Tipping")]
+#[poise::command(slash_command, category = "Tipping", check = "tipping_channel_allowed")]
 async fn role(
     ctx: Context<'_>,
     #[description = "Enter and select the role you want to tip"] role: serenity_prelude::Role,
     #[description = "The amount you want to tip"]
     #[min = 0.5]
     tip_amount: f64,
+    #[description = "An optional message to go with your tip"]
+    #[max_length = 200]
+    message: Option<String>,
 ) -> Result<(), Error> {
     if user_blacklisted(ctx, ctx.author().id).await? {
         return Ok(());
     }
 
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+
     debug!("role: {:?}", role.id);
     let tip_amount = Amount::from_vrsc(tip_amount)?;
+    let note = sanitize_note(message);
+    if text_blocked(ctx, note.as_deref()).await? {
+        return Ok(());
+    }
 
     if get_and_check_balance(&ctx, tip_amount, Amount::ZERO)
         .await?
@@ -55,19 +141,31 @@ async fn role(
             let guild_members = guild.members.values();
             let role_members = guild_members
                 .filter(
-                    |m| m.roles.contains(&role.id) || &role.id == &RoleId(guild.id.0), // @everyone role_id (same as guild_id) does never get tips
+                    |m| m.roles.contains(&role.id) || role.id == RoleId(guild.id.0), // @everyone role_id (same as guild_id) does never get tips
                 )
                 .map(|m| m.user.id)
                 .collect::<Vec<_>>();
+            let role_members = without_opted_out(ctx, role_members).await?;
+
+            if !confirm_group_tip(ctx, tip_amount, role_members.len()).await? {
+                return Ok(());
+            }
+            let Some(_in_flight) = begin_balance_change(ctx).await? else {
+                return Ok(());
+            };
 
             tip_multiple_users(
                 &ctx.data().database,
+                &ctx.data().translations,
                 ctx.author().id,
                 ctx.http(),
                 &ctx.channel_id(),
+                ctx.guild_id(),
                 &role_members,
                 &tip_amount,
                 "role",
+                note.as_deref(),
+                Some(ctx.id()),
             )
             .await?;
 
@@ -75,12 +173,8 @@ async fn role(
         } else {
             trace!("not in a guild, send error");
 
-            ctx.send(|reply| {
-                reply.ephemeral(true).content(format!(
-                    "You need to be in a Discord server to use this command."
-                ))
-            })
-            .await?;
+            ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "error-guild-only")))
+                .await?;
 
             return Ok(());
         }
@@ -91,17 +185,32 @@ async fn role(
 
 /// Tip a user by entering and selecting the user's name.
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
-#[poise::command(slash_command, category = "Tipping")]
+#[poise::command(slash_command, category = "Tipping", check = "tipping_channel_allowed")]
 async fn user(
     ctx: Context<'_>,
     #[description = "Enter and select the user you want to tip"] user: serenity_prelude::User,
     #[description = "The amount you want to tip"] tip_amount: f64,
+    #[description = "An optional message to go with your tip"]
+    #[max_length = 200]
+    message: Option<String>,
 ) -> Result<(), Error> {
     if user_blacklisted(ctx, ctx.author().id).await? {
         return Ok(());
     }
 
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
     let tip_amount = Amount::from_vrsc(tip_amount)?;
+    let note = sanitize_note(message);
+    if text_blocked(ctx, note.as_deref()).await? {
+        return Ok(());
+    }
+    let channel_note = note.as_deref().map(|note| tr!(ctx, "tip-note", note: note));
 
     debug!(
         "user {} ({}) wants to tip {} with {tip_amount}",
@@ -120,106 +229,261 @@ async fn user(
         .is_some()
     {
         trace!("tipper has enough balance");
+        let translations = &ctx.data().translations;
+        let notification = database::get_notification_settings(pool, &[user.id])
+            .await?
+            .into_iter()
+            .next()
+            .map(|(_, notification)| notification);
+
+        // everything that is sent about the tip is decided before it is paid, so the transaction that pays it does
+        // not wait on other queries or on Discord
+        let dm_only = matches!(notification, Some(Notification::DMOnly));
+        let dm_batched = dm_only && delivery::dm_batched(pool, user.id).await?;
+        let dm_content = match dm_only && !dm_batched {
+            true => Some(
+                tip_dm(
+                    pool,
+                    translations,
+                    ctx.author().id,
+                    user.id,
+                    tip_amount,
+                    note.as_deref(),
+                )
+                .await?,
+            ),
+            false => None,
+        };
 
-        database::process_a_tip(pool, &ctx.author().id, &vec![user.id], &tip_amount).await?;
+        // in a busy channel the tip is not announced on its own, the channel gets a summary later.
+        let batching =
+            delivery::batching(ctx.http(), pool, ctx.channel_id(), ctx.guild_id()).await?;
+        let tip_buttons = (!batching.batched() && quick_tip::enabled(pool, ctx.guild_id()).await?)
+            .then_some(quick_tip::Target::User(user.id));
+        let private = database::get_private_users(pool, &[user.id])
+            .await?
+            .contains(&user.id);
+        // recipients that only want DMs, or no notifications at all, are not pinged
+        let recipient = match notification {
+            Some(Notification::DMOnly | Notification::Off) => format!("`{}`", user.tag()),
+            _ => {
+                trace!("user has not set notification settings or wants them in the channel");
+                format!("<@{}>", user.id)
+            }
+        };
+        let announcement = match private {
+            true => tr!(
+                ctx,
+                "tip-user-announcement-private",
+                tipper: format!("<@{}>", &ctx.author().id),
+                recipient: recipient
+            ),
+            false => tr!(
+                ctx,
+                "tip-user-announcement",
+                tipper: format!("<@{}>", &ctx.author().id),
+                recipient: recipient,
+                amount: format::amount(tip_amount)
+            ),
+        };
 
-        // tips are only stored one way: counterparty is the sender of the tip.
-        let tip_event_id = Uuid::new_v4();
-        database::store_tip_transactions(
-            pool,
-            &tip_event_id,
-            &vec![user.id],
-            "direct",
+        // the DM and the announcement are stored in the outbox with the tip, so a tip is never paid without them
+        let mut tx = pool.begin().await?;
+        let tip_event_id = database::tip_transactionally(
+            &mut tx,
+            &ctx.author().id,
+            &[user.id],
             &tip_amount,
-            ctx.author().id,
+            "direct",
+            ctx.guild_id(),
+            note.as_deref(),
+            Some(ctx.id()),
         )
         .await?;
+        let dm = match &dm_content {
+            Some(content) => {
+                Some(delivery::enqueue(&mut tx, Destination::Dm(user.id), content, None).await?)
+            }
+            None => None,
+        };
+        if dm_batched {
+            delivery::batch_dm(&mut tx, user.id, tip_amount).await?;
+        }
+        delivery::batch_tip(&mut tx, &batching, ctx.author().id, tip_amount).await?;
+        let announced = match batching.batched() {
+            true => None,
+            false => Some(
+                delivery::enqueue(
+                    &mut tx,
+                    Destination::Channel {
+                        channel_id: ctx.channel_id(),
+                        guild_id: ctx.guild_id(),
+                        initiator: ctx.author().id,
+                    },
+                    &with_note(announcement, channel_note.as_deref()),
+                    tip_buttons,
+                )
+                .await?,
+            ),
+        };
+        tx.commit().await?;
 
-        match database::get_notification_settings(&pool, &vec![user.id])
-            .await?
-            .first()
-        {
-            Some((_, notification)) => {
-                match notification {
-                    Notification::All | Notification::ChannelOnly => {
-                        // send a message in the same channel:
-                        ctx.send(|reply| {
-                            reply.ephemeral(false).content(format!(
-                                "<@{}> just tipped <@{}> {tip_amount}!",
-                                &ctx.author().id,
-                                user.id
-                            ))
-                        })
-                        .await?;
-                    }
-                    Notification::DMOnly => {
-                        // send a non-pinging message in the channel:
-                        ctx.send(|reply| {
-                            reply.ephemeral(false).content(format!(
-                                "<@{}> just tipped `{}` {tip_amount}!",
-                                &ctx.author().id,
-                                user.tag()
-                            ))
-                        })
-                        .await?;
-                        // send a notification in dm:
-                        user.dm(&ctx.http(), |message| {
-                            message.content(format!(
-                                "You just got tipped {tip_amount} from <@{}>!",
-                                &ctx.author().id,
-                            ))
-                        })
-                        .await?;
+        delivery::record_tip(&batching);
+        if dm_content.is_some() {
+            delivery::record_dm(user.id);
+        }
+        metrics::record_tip("direct", 1, tip_amount);
+        referral::reward_first_tip(ctx.http(), pool, &ctx.data().translations, ctx.author().id)
+            .await;
+        competition::record_tips(
+            pool,
+            ctx.guild_id(),
+            tip_event_id,
+            ctx.author().id,
+            &[user.id],
+            tip_amount,
+        )
+        .await;
+        low_balance::remind(
+            ctx.http(),
+            pool,
+            &ctx.data().translations,
+            ctx.author().id,
+            tip_amount,
+        )
+        .await;
+
+        // the tip is done, so what fails from here on is logged instead of reported to the tipper as a failed tip.
+        // Announcements that could not be posted yet are retried by the outbox.
+        let mut link = None;
+        if let Some(announced) = &announced {
+            match delivery::attempt_returning(ctx.http(), pool, translations, announced).await {
+                Ok((_, Some(message))) => {
+                    if let Err(e) = message
+                        .react(ctx.http(), ReactionType::Unicode(String::from("✅")))
+                        .await
+                    {
+                        warn!("could not react to the announcement of tip {tip_event_id}: {e:?}");
                     }
-                    Notification::Off => {
-                        // send a non-pinging message in the channel:
-                        ctx.send(|reply| {
-                            reply.ephemeral(false).content(format!(
-                                "<@{}> just tipped `{}` {tip_amount}!",
-                                &ctx.author().id,
-                                user.tag()
-                            ))
-                        })
-                        .await?;
+                    if let Err(e) = database::store_tip_message(
+                        pool,
+                        &tip_event_id,
+                        ctx.guild_id(),
+                        message.channel_id,
+                        message.id,
+                    )
+                    .await
+                    {
+                        error!("could not store the announcement of tip {tip_event_id}: {e:?}");
                     }
+                    link = Some(message.link());
                 }
+                Ok((_, None)) => {}
+                Err(e) => warn!("could not announce tip {tip_event_id}: {e:?}"),
             }
-            None => {
-                trace!("User has not set notification settings, defaulting to Channel");
-
-                ctx.send(|reply| {
-                    reply.ephemeral(false).content(format!(
-                        "<@{}> just tipped <@{}> {tip_amount}!",
-                        &ctx.author().id,
-                        user.id
-                    ))
-                })
-                .await?;
+        }
+
+        // send the notification in dm, in the language of the recipient:
+        if let Some(dm) = &dm {
+            if let Err(e) = delivery::attempt(ctx.http(), pool, translations, dm).await {
+                warn!("could not notify {} of their tip: {e:?}", user.id);
             }
         }
 
+        if let Err(e) = send_receipt(ctx, tip_event_id, &user, tip_amount, link).await {
+            warn!("could not send the receipt of tip {tip_event_id}: {e:?}");
+        }
+
         return Ok(());
     }
 
     Ok(())
 }
 
+/// Shows the tipper the id of their tip, so they can refer to it later, e.g. in a dispute.
+async fn send_receipt(
+    ctx: Context<'_>,
+    tip_event_id: Uuid,
+    recipient: &serenity_prelude::User,
+    amount: Amount,
+    announcement_link: Option<String>,
+) -> Result<(), Error> {
+    let balance = database::get_balance_for_user(&ctx.data().database, &ctx.author().id)
+        .await?
+        .unwrap_or(0);
+
+    ctx.send(|reply| {
+        reply.ephemeral(true).embed(|embed| {
+            embed
+                .title(tr!(ctx, "tip-receipt-title"))
+                .field(
+                    tr!(ctx, "tip-receipt-recipient"),
+                    format!("<@{}>", recipient.id),
+                    true,
+                )
+                .field(tr!(ctx, "tip-receipt-amount"), format::amount(amount), true)
+                .field(
+                    tr!(ctx, "tip-receipt-balance"),
+                    format::amount(Amount::from_sat(balance)),
+                    true,
+                )
+                .field(
+                    tr!(ctx, "tip-receipt-id"),
+                    format!("`{tip_event_id}`"),
+                    false,
+                );
+
+            if let Some(link) = &announcement_link {
+                embed.url(link);
+            }
+
+            embed
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
 #[derive(Debug, poise::ChoiceParameter)]
 pub enum Hms {
     Hours,
     Minutes,
 }
 
-/// Start a giveaway where users need to react to a message to participate
+/// Start, end or cancel a giveaway where users need to react to a message to participate
 ///
 /// -------- :robot: **Reactdrop** --------
 /// When initiating a reactdrop, find a suitable emoji in the first parameter. \
 /// It can be any Emoji, as long as the emoji is in the current server.
 ///
 /// The amount is entered in the second parameter. This amount will be split among the participants of the reactdrop when it ends.
+/// Reactions with the same emoji in another skin tone count as well.
+///
+/// -------- :robot: **Required role** --------
+/// Enter a role to only let the members with that role participate. Reactions of other members are ignored.
+///
+/// -------- :robot: **Raffle** --------
+/// Enter a number of winners to turn the reactdrop into a raffle: when it ends, that many participants are picked at random \
+/// and split the amount among them.
+///
+/// -------- :robot: **Ending or cancelling a reactdrop** --------
+/// The creator of a reactdrop (or a server admin) can end it early, which pays out to everyone that reacted so far, \
+/// or cancel it, in which case nobody gets paid and the creator keeps the amount.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Tipping",
+    subcommands("start", "end", "cancel")
+)]
+pub async fn reactdrop(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Start a giveaway where users need to react to a message to participate
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
-#[poise::command(slash_command, category = "Tipping")]
-pub async fn reactdrop(
+#[poise::command(slash_command, category = "Tipping", check = "tipping_channel_allowed")]
+async fn start(
     ctx: Context<'_>,
     #[description = "The emoji users need to react with"] emoji: String,
     #[min = 0.1]
@@ -227,11 +491,23 @@ pub async fn reactdrop(
     amount: f64,
     #[min = 1] time: i64,
     #[description = "The time in hours, minutes or seconds"] hms: Hms,
+    #[min = 1]
+    #[max = 100]
+    #[description = "Pick this many random winners to split the amount, instead of everyone that reacted"]
+    winners: Option<u32>,
+    #[description = "Only members with this role can participate"] required_role: Option<Role>,
 ) -> Result<(), Error> {
     if user_blacklisted(ctx, ctx.author().id).await? {
         return Ok(());
     }
 
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
     let tip_amount = Amount::from_vrsc(amount)?;
 
     if get_and_check_balance(&ctx, tip_amount, Amount::ZERO)
@@ -247,8 +523,11 @@ pub async fn reactdrop(
                     if !emojis.iter().any(|e| e.id == id.0) {
                         trace!("emoji not in guild");
                         ctx.send(|reply| {
-                            reply.ephemeral(true).content("This emoji is not found in this Discord server, so it can't be used. Please pick another one")
-                        }).await?;
+                            reply
+                                .ephemeral(true)
+                                .content(tr!(ctx, "reactdrop-emoji-not-in-guild"))
+                        })
+                        .await?;
 
                         return Ok(());
                     } else {
@@ -256,13 +535,13 @@ pub async fn reactdrop(
                     }
                 }
                 ReactionType::Unicode(unicode) => {
-                    let emoji = emojis::get(&unicode);
+                    let emoji = emojis::get(unicode);
 
                     if emoji.is_none() {
                         ctx.send(|reply| {
-                            reply.ephemeral(true).content(
-                                "This is not a valid emoji. Please pick an emoji to start a Reactdrop",
-                            )
+                            reply
+                                .ephemeral(true)
+                                .content(tr!(ctx, "reactdrop-emoji-invalid"))
                         })
                         .await?;
 
@@ -287,11 +566,29 @@ pub async fn reactdrop(
             let finish_time = now.checked_add_signed(time_in_seconds).unwrap(); // sane values are guaranteed by command argument limits
             debug!("finish_time: {finish_time:?}");
 
+            // the remaining time is updated by the reactdrop loop, which looks for the "Time remaining: " marker.
+            // The marker is therefore always in English.
+            let mut started = tr!(
+                ctx,
+                "reactdrop-started",
+                amount: format::amount(tip_amount),
+                emoji: reaction_type.to_string()
+            );
+            if let Some(winners) = winners {
+                started.push('\n');
+                started.push_str(&tr!(ctx, "reactdrop-started-raffle", winners: winners));
+            }
+            if let Some(role) = &required_role {
+                started.push('\n');
+                started.push_str(&tr!(
+                    ctx,
+                    "reactdrop-started-role",
+                    role: format!("<@&{}>", role.id)
+                ));
+            }
             let reply_handle = ctx
                 .say(format!(
-                    ">>> **A reactdrop of {tip_amount} was started!**\n\n \
-React with the {} emoji to participate\n\nTime remaining: {} hour(s) and {} minute(s)",
-                    reaction_type.clone(),
+                    "{started}\n\nTime remaining: {} hour(s) and {} minute(s)",
                     time_in_seconds.num_seconds() / (60 * 60),
                     (time_in_seconds.num_seconds() / 60) % 60
                 ))
@@ -306,12 +603,15 @@ React with the {} emoji to participate\n\nTime remaining: {} hour(s) and {} minu
 
             database::insert_reactdrop(
                 &ctx.data().database,
-                ctx.author().id.try_into()?,
+                ctx.author().id.into(),
                 reaction_type.to_string(),
                 Amount::from_vrsc(amount).unwrap().as_sat() as i64,
-                channel_id.try_into()?,
-                message_id.try_into()?,
+                ctx.guild_id(),
+                channel_id.into(),
+                message_id.into(),
                 finish_time,
+                winners,
+                required_role.map(|role| role.id),
             )
             .await?;
         }
@@ -320,19 +620,479 @@ React with the {} emoji to participate\n\nTime remaining: {} hour(s) and {} minu
     Ok(())
 }
 
-// Divides the amount over the `users` vec, increases the balance for all `users` and stores the tip transaction
-// This function gets called in `tip role` and `reactdrop`
+/// Tip everyone who is online
+///
+/// -------- :robot: **Soaking** --------
+/// Splits the amount among all members of this server that are currently online, idle or on do not disturb. \
+/// Enter a role in the second parameter to only soak the online members of that role.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Tipping",
+    guild_only,
+    check = "tipping_channel_allowed"
+)]
+pub async fn soak(
+    ctx: Context<'_>,
+    #[description = "The amount you want to split among the online members"]
+    #[min = 0.5]
+    amount: f64,
+    #[description = "Only soak members of this role"] role: Option<serenity_prelude::Role>,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+
+    let amount = Amount::from_vrsc(amount)?;
+
+    let Some(guild) = ctx.guild() else {
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "error-guild-only")))
+            .await?;
+
+        return Ok(());
+    };
+
+    let online_members = {
+        let blacklist = ctx.data().blacklist.lock().unwrap();
+
+        guild
+            .presences
+            .values()
+            .filter(|presence| {
+                matches!(
+                    presence.status,
+                    OnlineStatus::Online | OnlineStatus::Idle | OnlineStatus::DoNotDisturb
+                )
+            })
+            .filter_map(|presence| guild.members.get(&presence.user.id))
+            .filter(|member| !member.user.bot && member.user.id != ctx.author().id)
+            .filter(|member| !blacklist.contains(&member.user.id))
+            .filter(|member| {
+                role.as_ref()
+                    .is_none_or(|role| member.roles.contains(&role.id))
+            })
+            .map(|member| member.user.id)
+            .collect::<Vec<_>>()
+    };
+    let recipients = without_opted_out(ctx, online_members)
+        .await?
+        .into_iter()
+        .take(MAX_SOAK_RECIPIENTS)
+        .collect::<Vec<_>>();
+
+    debug!("{} online members to soak", recipients.len());
+
+    if recipients.is_empty() {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "soak-no-recipients"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    if TipSplit::new(amount, recipients.len())
+        .is_none_or(|split| split.share.as_sat() < MIN_SOAK_SHARE)
+    {
+        ctx.send(|reply| {
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "soak-share-too-small",
+                count: recipients.len(),
+                minimum: format::amount(Amount::from_sat(MIN_SOAK_SHARE))
+            ))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    if !confirm_group_tip(ctx, amount, recipients.len()).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    if get_and_check_balance(&ctx, amount, Amount::ZERO)
+        .await?
+        .is_some()
+    {
+        tip_multiple_users(
+            &ctx.data().database,
+            &ctx.data().translations,
+            ctx.author().id,
+            ctx.http(),
+            &ctx.channel_id(),
+            ctx.guild_id(),
+            &recipients,
+            &amount,
+            "soak",
+            None,
+            Some(ctx.id()),
+        )
+        .await?;
+
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "soak-done")))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// End a reactdrop now and pay out to everyone that reacted so far
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Tipping", guild_only)]
+async fn end(
+    ctx: Context<'_>,
+    #[description = "The reactdrop to end"]
+    #[autocomplete = "autocomplete_reactdrop"]
+    reactdrop: String,
+) -> Result<(), Error> {
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let Some(reactdrop) = controllable_reactdrop(ctx, &reactdrop).await? else {
+        return Ok(());
+    };
+    let pool = &ctx.data().database;
+    let translations = &ctx.data().translations;
+
+    ctx.defer_ephemeral().await?;
+
+    if !database::update_reactdrop(
+        pool,
+        reactdrop.channel_id.0 as i64,
+        reactdrop.message_id.0 as i64,
+        ReactdropState::Paying,
+        Some(ctx.author().id),
+    )
+    .await?
+    {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "reactdrop-not-pending"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let mut message = reactdrop
+        .channel_id
+        .message(ctx.http(), reactdrop.message_id)
+        .await?;
+    let content = reactdrop::with_status(
+        &message.content,
+        &tr_locale!(translations, None, "reactdrop-status-ended"),
+    );
+    message
+        .edit(ctx.http(), |edit| edit.content(content))
+        .await?;
+
+    reactdrop::pay_out(
+        ctx.serenity_context(),
+        pool,
+        translations,
+        &reactdrop,
+        message,
+    )
+    .await?;
+    info!(
+        "reactdrop ended early by {}: {reactdrop:?}",
+        ctx.author().id
+    );
+
+    ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "reactdrop-ended")))
+        .await?;
+
+    Ok(())
+}
+
+/// Cancel a reactdrop, nobody gets paid
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Tipping", guild_only)]
+async fn cancel(
+    ctx: Context<'_>,
+    #[description = "The reactdrop to cancel"]
+    #[autocomplete = "autocomplete_reactdrop"]
+    reactdrop: String,
+) -> Result<(), Error> {
+    let Some(reactdrop) = controllable_reactdrop(ctx, &reactdrop).await? else {
+        return Ok(());
+    };
+    let pool = &ctx.data().database;
+
+    // the amount of a reactdrop is only taken from the creator when it is paid out, so there is nothing to refund.
+    if !database::update_reactdrop(
+        pool,
+        reactdrop.channel_id.0 as i64,
+        reactdrop.message_id.0 as i64,
+        ReactdropState::Cancelled,
+        Some(ctx.author().id),
+    )
+    .await?
+    {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "reactdrop-not-pending"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let mut message = reactdrop
+        .channel_id
+        .message(ctx.http(), reactdrop.message_id)
+        .await?;
+    let content = reactdrop::with_status(
+        &message.content,
+        &tr_locale!(ctx.data().translations, None, "reactdrop-status-cancelled"),
+    );
+    message
+        .edit(ctx.http(), |edit| edit.content(content))
+        .await?;
+    message.delete_reactions(ctx.http()).await?;
+    info!("reactdrop cancelled by {}: {reactdrop:?}", ctx.author().id);
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(tr!(ctx, "reactdrop-cancelled"))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the pending reactdrop with the `channel_id:message_id` given by [`autocomplete_reactdrop`], if the author
+/// is allowed to end or cancel it: its creator and server admins are.
+async fn controllable_reactdrop(
+    ctx: Context<'_>,
+    reactdrop: &str,
+) -> Result<Option<Reactdrop>, Error> {
+    let reactdrop = match reactdrop.split_once(':') {
+        Some((channel_id, message_id)) => match (channel_id.parse(), message_id.parse()) {
+            (Ok(channel_id), Ok(message_id)) => {
+                database::get_reactdrop(
+                    &ctx.data().database,
+                    ChannelId(channel_id),
+                    MessageId(message_id),
+                )
+                .await?
+            }
+            _ => None,
+        },
+        None => None,
+    };
+
+    let Some(reactdrop) = reactdrop.filter(|reactdrop| {
+        matches!(reactdrop.status, ReactdropState::Pending) && reactdrop.guild_id == ctx.guild_id()
+    }) else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "reactdrop-not-found"))
+        })
+        .await?;
+
+        return Ok(None);
+    };
+
+    if reactdrop.author != ctx.author().id && !can_manage_guild(ctx).await? {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "reactdrop-not-allowed"))
+        })
+        .await?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(reactdrop))
+}
+
+async fn autocomplete_reactdrop(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<poise::AutocompleteChoice<String>> {
+    let reactdrops = match database::get_pending_reactdrops(&ctx.data().database).await {
+        Ok(reactdrops) => reactdrops,
+        Err(e) => {
+            error!("could not get pending reactdrops: {e:?}");
+            return vec![];
+        }
+    };
+    let is_admin = can_manage_guild(ctx).await.unwrap_or(false);
+
+    reactdrops
+        .into_iter()
+        .filter(|reactdrop| reactdrop.guild_id == ctx.guild_id())
+        .filter(|reactdrop| is_admin || reactdrop.author == ctx.author().id)
+        .map(|reactdrop| poise::AutocompleteChoice {
+            name: format!(
+                "{} {} reactdrop ending {}",
+                format::amount(reactdrop.tip_amount),
+                reactdrop.emoji,
+                reactdrop.finish_time.format("%Y-%m-%d %H:%M")
+            ),
+            value: format!("{}:{}", reactdrop.channel_id, reactdrop.message_id),
+        })
+        .filter(|choice| choice.name.contains(partial))
+        .take(25)
+        .collect()
+}
+
+/// Removes the users that opted out of group tips with `/settings tippable`.
+async fn without_opted_out(ctx: Context<'_>, users: Vec<UserId>) -> Result<Vec<UserId>, Error> {
+    let opted_out = database::get_untippable_users(&ctx.data().database, &users).await?;
+
+    Ok(users
+        .into_iter()
+        .filter(|user| !opted_out.contains(user))
+        .collect())
+}
+
+/// Group tips to more users than configured in `max_group_tip_recipients` need to be confirmed by the tipper, after
+/// seeing how much every recipient gets. Returns false when the tipper did not confirm.
+async fn confirm_group_tip(
+    ctx: Context<'_>,
+    amount: Amount,
+    recipients: usize,
+) -> Result<bool, Error> {
+    if recipients <= ctx.data().settings.application.max_group_tip_recipients {
+        return Ok(true);
+    }
+
+    let Some(split) = TipSplit::new(amount, recipients) else {
+        return Ok(true);
+    };
+
+    confirm(
+        ctx,
+        tr!(
+            ctx,
+            "tip-confirm-group",
+            count: recipients,
+            amount: format::amount(amount),
+            share: format::amount(split.share)
+        ),
+    )
+    .await
+}
+
+/// A soak tips at most this many members.
+const MAX_SOAK_RECIPIENTS: usize = 100;
+/// Shares smaller than this (in satoshis) are not worth the notification.
+const MIN_SOAK_SHARE: u64 = 100_000;
+
+/// The longest note that can be sent with a tip.
+const MAX_NOTE_LENGTH: usize = 200;
+
+/// Makes the note of a tip safe to show in a message. Empty notes are dropped.
+fn sanitize_note(note: Option<String>) -> Option<String> {
+    note.map(|note| format::user_text(&note, MAX_NOTE_LENGTH))
+        .filter(|note| !note.is_empty())
+}
+
+fn with_note(message: String, note: Option<&str>) -> String {
+    match note {
+        Some(note) => format!("{message}\n{note}"),
+        None => message,
+    }
+}
+
+/// An amount divided evenly among a number of recipients. The dust is what could not be divided, which is returned
+/// to the tipper.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TipSplit {
+    pub share: Amount,
+    pub dust: Amount,
+}
+
+impl TipSplit {
+    pub fn new(amount: Amount, recipients: usize) -> Option<Self> {
+        let share = amount.checked_div(recipients as u64)?;
+        let dust = amount.checked_sub(share.checked_mul(recipients as u64)?)?;
+
+        Some(Self { share, dust })
+    }
+
+    /// The amount that actually went to the recipients.
+    pub fn tipped(&self, recipients: usize) -> Amount {
+        self.share
+            .checked_mul(recipients as u64)
+            .unwrap_or(Amount::ZERO)
+    }
+}
+
+/// Divides `amount` evenly among `users` and tips every user their share. What could not be divided stays with the
+/// tipper. Returns the id of the tip event and the split, or `None` when there is nobody to tip.
+pub async fn split_tip(
+    store: &mut impl BalanceStore,
+    author: UserId,
+    users: &[UserId],
+    amount: Amount,
+    kind: &str,
+    guild_id: Option<GuildId>,
+    note: Option<&str>,
+    interaction_id: Option<u64>,
+) -> Result<Option<(Uuid, TipSplit)>, Error> {
+    let Some(split) = TipSplit::new(amount, users.len()) else {
+        return Ok(None);
+    };
+    debug!(
+        "after division every member gets {}, {} is refunded",
+        split.share, split.dust
+    );
+
+    let tip_event_id = store
+        .tip(
+            author,
+            users,
+            split.share,
+            kind,
+            guild_id,
+            note,
+            interaction_id,
+        )
+        .await?;
+    metrics::record_tip(kind, users.len(), split.tipped(users.len()));
+
+    Ok(Some((tip_event_id, split)))
+}
+
+// Divides the amount over the `users` vec, refunds what could not be divided, increases the balance for all `users` and stores the tip transaction
+// This function gets called in `tip role` and `reactdrop`. Returns the id of the tip event, if there was one.
 // We need the ChannelId here because ReactDrops tend to last longer than 15 minutes, which is the time Discord drops the context, giving
 // us an invalid webhook token when trying to send a message using that context.
 pub async fn tip_multiple_users(
     pool: &PgPool,
+    translations: &Translations,
     author: UserId,
     http: impl CacheHttp + std::convert::AsRef<poise::serenity_prelude::Http>,
     channel_id: &ChannelId,
+    guild_id: Option<GuildId>,
     users: &Vec<UserId>,
     amount: &Amount,
     kind: &str,
-) -> Result<(), Error> {
+    note: Option<&str>,
+    interaction_id: Option<u64>,
+) -> Result<Option<Uuid>, Error> {
     // TODO optimize this query (select all that don't exist, insert them in 1 go)
     // check if all the tippees have an entry in the db
     // let pool = &ctx.data().database;
@@ -341,54 +1101,333 @@ pub async fn tip_multiple_users(
 
     debug!("users in tip_users: {:?}", users);
 
-    // need to divide tipping amount over number of users
-    if let Some(div_tip_amount) = amount.checked_div(users.len() as u64) {
-        let amount = div_tip_amount
-            .checked_mul(users.len() as u64)
-            .unwrap_or(*amount);
-        debug!("after division every member gets {div_tip_amount}");
-        debug!("members: {:#?}", &users);
-
-        let tip_event_id = Uuid::new_v4();
-
-        database::process_a_tip(pool, &author, &users, &div_tip_amount).await?;
+    let Some(split) = TipSplit::new(*amount, users.len()) else {
+        error!("could not send tip to role");
 
-        database::store_tip_transactions(pool, &tip_event_id, users, kind, &div_tip_amount, author)
-            .await?;
+        return Ok(None);
+    };
+    let div_tip_amount = split.share;
+    let tipped = split.tipped(users.len());
 
-        let notification_settings = database::get_notification_settings(pool, &users).await?;
-
-        for (user_id, notification) in notification_settings {
-            match (user_id, notification) {
-                (_, Notification::All) | (_, Notification::DMOnly) => {
-                    let user = UserId(user_id as u64).to_user(&http).await?;
-                    user.dm(&http, |message| {
-                        message.content(format!(
-                            "You just got tipped {div_tip_amount} from <@{}>!",
-                            &author,
-                        ))
-                    })
-                    .await?;
-                }
-                _ => {
-                    // don't ping when ChannelOnly or Off
+    // everything that is sent about the tip is decided before it is paid, so the transaction that pays it does not
+    // wait on other queries or on Discord
+    let mut unnotified = 0;
+    let mut dm_contents = vec![];
+    let mut batched_dms = vec![];
+    for (user_id, notification) in database::get_notification_settings(pool, users).await? {
+        match notification {
+            Notification::All | Notification::DMOnly => {
+                let user_id = UserId(user_id as u64);
+                // users that get many tips in a short time get a summary later. That summary fails like their last
+                // DM did when they don't accept DMs.
+                if delivery::dm_batched(pool, user_id).await? {
+                    if database::has_failed_dm(pool, user_id).await? {
+                        unnotified += 1;
+                    }
+                    batched_dms.push(user_id);
+                    continue;
                 }
+
+                let content =
+                    tip_dm(pool, translations, author, user_id, div_tip_amount, note).await?;
+                dm_contents.push((user_id, content));
+            }
+            _ => {
+                // don't ping when ChannelOnly or Off
             }
         }
+    }
 
-        channel_id
-            .send_message(http, |message| {
-                message.content(format!(
-                    "<@{}> just tipped {} to {} users!",
-                    &author,
-                    amount,
-                    &users.len()
-                ))
-            })
-            .await?;
-    } else {
+    // the amounts are left out when a recipient keeps them private, as the share is the same for everyone
+    let private = !database::get_private_users(pool, users).await?.is_empty();
+    let mut announcement = match (users.as_slice(), private) {
+        ([recipient], true) => tr_locale!(
+            translations,
+            None,
+            "tip-user-announcement-private",
+            tipper: format!("<@{}>", &author),
+            recipient: format!("<@{}>", recipient)
+        ),
+        ([recipient], false) => tr_locale!(
+            translations,
+            None,
+            "tip-user-announcement",
+            tipper: format!("<@{}>", &author),
+            recipient: format!("<@{}>", recipient),
+            amount: format::amount(tipped)
+        ),
+        (_, true) => tr_locale!(
+            translations,
+            None,
+            "tip-multiple-announcement-private",
+            tipper: format!("<@{}>", &author),
+            count: users.len()
+        ),
+        (_, false) => tr_locale!(
+            translations,
+            None,
+            "tip-multiple-announcement",
+            tipper: format!("<@{}>", &author),
+            amount: format::amount(tipped),
+            count: users.len(),
+            share: format::amount(div_tip_amount)
+        ),
+    };
+    if split.dust > Amount::ZERO {
+        announcement.push('\n');
+        announcement.push_str(&tr_locale!(
+            translations,
+            None,
+            "tip-dust-refunded",
+            dust: format::amount(split.dust),
+            tipper: format!("<@{}>", &author)
+        ));
+    }
+    let channel_note = note.map(|note| tr_locale!(translations, None, "tip-note", note: note));
+    let batching = delivery::batching(http.as_ref(), pool, *channel_id, guild_id).await?;
+    let tip_buttons = !batching.batched() && quick_tip::enabled(pool, guild_id).await?;
+
+    // the DMs and the announcement are stored in the outbox in the same transaction as the tip, so a tip is never paid
+    // without them
+    let mut tx = pool.begin().await?;
+    let Some((tip_event_id, _)) = split_tip(
+        &mut *tx,
+        author,
+        users,
+        *amount,
+        kind,
+        guild_id,
+        note,
+        interaction_id,
+    )
+    .await?
+    else {
         error!("could not send tip to role");
+
+        return Ok(None);
+    };
+
+    let mut dms = vec![];
+    for (user_id, content) in &dm_contents {
+        dms.push(delivery::enqueue(&mut tx, Destination::Dm(*user_id), content, None).await?);
     }
+    for user_id in &batched_dms {
+        delivery::batch_dm(&mut tx, *user_id, div_tip_amount).await?;
+    }
+    let batch_entry = delivery::batch_tip(&mut tx, &batching, author, tipped).await?;
 
-    Ok(())
+    let announced = match batching.batched() {
+        true => None,
+        false => {
+            let target = match users.as_slice() {
+                [recipient] => quick_tip::Target::User(*recipient),
+                _ => quick_tip::Target::Event(tip_event_id),
+            };
+
+            Some(
+                delivery::enqueue(
+                    &mut tx,
+                    Destination::Channel {
+                        channel_id: *channel_id,
+                        guild_id,
+                        initiator: author,
+                    },
+                    &with_note(announcement.clone(), channel_note.as_deref()),
+                    tip_buttons.then_some(target),
+                )
+                .await?,
+            )
+        }
+    };
+    tx.commit().await?;
+
+    delivery::record_tip(&batching);
+    for (user_id, _) in &dm_contents {
+        delivery::record_dm(*user_id);
+    }
+
+    referral::reward_first_tip(http.as_ref(), pool, translations, author).await;
+    competition::record_tips(pool, guild_id, tip_event_id, author, users, div_tip_amount).await;
+    low_balance::remind(http.as_ref(), pool, translations, author, tipped).await;
+
+    // a recipient that can not be notified must not keep the others from being notified
+    for dm in &dms {
+        match delivery::attempt(http.as_ref(), pool, translations, dm).await {
+            Ok(Delivery::Failed) => unnotified += 1,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("could not notify {:?} of their tip: {e:?}", dm.destination);
+                unnotified += 1;
+            }
+        }
+    }
+
+    if let Some(mut message) = announced {
+        if unnotified > 0 {
+            announcement.push('\n');
+            announcement.push_str(&tr_locale!(
+                translations,
+                None,
+                "tip-dm-failed",
+                count: unnotified
+            ));
+            message.content = with_note(announcement, channel_note.as_deref());
+            database::set_outbox_content(pool, message.id, &message.content).await?;
+        }
+
+        delivery::attempt(http.as_ref(), pool, translations, &message).await?;
+    } else if let Some(entry) = batch_entry.filter(|_| unnotified > 0) {
+        database::add_batch_unnotified(pool, entry, unnotified).await?;
+    }
+
+    Ok(Some(tip_event_id))
+}
+
+/// The DM to the recipient of a tip, in their language.
+async fn tip_dm(
+    pool: &PgPool,
+    translations: &Translations,
+    tipper: UserId,
+    recipient: UserId,
+    amount: Amount,
+    note: Option<&str>,
+) -> Result<String, Error> {
+    let locale = database::get_dm_language(pool, &recipient).await?;
+    let dm_note =
+        note.map(|note| tr_locale!(translations, locale.as_deref(), "tip-note", note: note));
+
+    Ok(with_note(
+        tr_locale!(
+            translations,
+            locale.as_deref(),
+            "tip-received-dm",
+            amount: format::amount(amount),
+            tipper: format!("<@{}>", &tipper)
+        ),
+        dm_note.as_deref(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::balance_store::mock::MockStore;
+
+    #[test]
+    fn split_returns_the_dust() {
+        let split = TipSplit::new(Amount::from_sat(100_000_001), 3).unwrap();
+
+        assert_eq!(split.share, Amount::from_sat(33_333_333));
+        assert_eq!(split.dust, Amount::from_sat(2));
+        assert_eq!(split.tipped(3), Amount::from_sat(99_999_999));
+    }
+
+    #[test]
+    fn split_without_dust() {
+        let split = TipSplit::new(Amount::from_sat(90), 3).unwrap();
+
+        assert_eq!(split.share, Amount::from_sat(30));
+        assert_eq!(split.dust, Amount::ZERO);
+    }
+
+    #[test]
+    fn no_split_without_recipients() {
+        assert!(TipSplit::new(Amount::from_sat(90), 0).is_none());
+    }
+
+    #[tokio::test]
+    async fn split_tip_keeps_the_dust_with_the_tipper() {
+        let mut store = MockStore::with_balances([(1, 1_000)]);
+
+        let (_, split) = split_tip(
+            &mut store,
+            UserId(1),
+            &[UserId(2), UserId(3), UserId(4)],
+            Amount::from_sat(100),
+            "role",
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(split.share, Amount::from_sat(33));
+        assert_eq!(store.balance_of(1), Some(Amount::from_sat(901)));
+        for recipient in 2..=4 {
+            assert_eq!(store.balance_of(recipient), Some(Amount::from_sat(33)));
+        }
+    }
+
+    #[tokio::test]
+    async fn split_tip_without_recipients_tips_nobody() {
+        let mut store = MockStore::with_balances([(1, 1_000)]);
+
+        let tip = split_tip(
+            &mut store,
+            UserId(1),
+            &[],
+            Amount::from_sat(100),
+            "soak",
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(tip.is_none());
+        assert!(store.tips.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unaffordable_split_tip_changes_nothing() {
+        let mut store = MockStore::with_balances([(1, 50)]);
+
+        let result = split_tip(
+            &mut store,
+            UserId(1),
+            &[UserId(2), UserId(3)],
+            Amount::from_sat(100),
+            "reactdrop",
+            None,
+            None,
+            None,
+        )
+        .await;
+
+        assert!(result.is_err_and(|e| database::is_insufficient_balance(&e)));
+        assert_eq!(store.balance_of(1), Some(Amount::from_sat(50)));
+        assert_eq!(store.balance_of(2), None);
+    }
+
+    #[tokio::test]
+    async fn redelivered_interaction_does_not_tip_twice() {
+        let mut store = MockStore::with_balances([(1, 1_000)]);
+        let recipients = [UserId(2), UserId(3)];
+        let mut results = vec![];
+        for _ in 0..2 {
+            results.push(
+                split_tip(
+                    &mut store,
+                    UserId(1),
+                    &recipients,
+                    Amount::from_sat(100),
+                    "role",
+                    None,
+                    None,
+                    Some(42),
+                )
+                .await,
+            );
+        }
+
+        let again = results.pop().unwrap().unwrap_err();
+        results.pop().unwrap().unwrap();
+
+        assert!(database::is_already_processed(&again));
+        assert_eq!(store.tips.lock().unwrap().len(), 1);
+        assert_eq!(store.balance_of(1), Some(Amount::from_sat(900)));
+    }
 }
@@ -0,0 +1,274 @@
+use std::{borrow::Cow, fmt::Display};
+
+use chrono::{Months, NaiveDate, TimeZone};
+use poise::serenity_prelude::{AttachmentType, UserId};
+use sqlx::types::chrono::{DateTime, Utc};
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    authorization::{can_manage_guild, guild_of},
+    commands::{begin_balance_change, bot_degraded, text_blocked, user_blacklisted},
+    i18n::tr,
+    util::{database, format},
+    Context, Error,
+};
+
+/// The kinds of movements in the treasury of a guild.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TreasuryKind {
+    Fee,
+    Donation,
+    Award,
+    Match,
+    ScheduledDrop,
+}
+
+impl TreasuryKind {
+    pub fn is_inflow(&self) -> bool {
+        matches!(self, Self::Fee | Self::Donation)
+    }
+}
+
+impl Display for TreasuryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fee => write!(f, "fee"),
+            Self::Donation => write!(f, "donation"),
+            Self::Award => write!(f, "award"),
+            Self::Match => write!(f, "match"),
+            Self::ScheduledDrop => write!(f, "scheduled_drop"),
+        }
+    }
+}
+
+impl From<String> for TreasuryKind {
+    fn from(value: String) -> Self {
+        match value.as_ref() {
+            "fee" => Self::Fee,
+            "donation" => Self::Donation,
+            "award" => Self::Award,
+            "match" => Self::Match,
+            "scheduled_drop" => Self::ScheduledDrop,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TreasuryEntry {
+    pub id: i64,
+    pub kind: TreasuryKind,
+    pub amount: Amount,
+    pub counterparty: Option<UserId>,
+    pub memo: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The community fund of this server
+///
+/// -------- :robot: **Treasury** --------
+/// Every server has a treasury that can receive donations. \
+/// Server admins can download a monthly statement of everything that went in and out of the treasury.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Treasury",
+    guild_only,
+    subcommands("donate", "report")
+)]
+pub async fn treasury(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Donate to the treasury of this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Treasury", guild_only)]
+async fn donate(
+    ctx: Context<'_>,
+    #[description = "The amount you want to donate"]
+    #[min = 0.1]
+    amount: f64,
+    #[description = "A message for the treasury report"] memo: Option<String>,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    if text_blocked(ctx, memo.as_deref()).await? {
+        return Ok(());
+    }
+
+    let amount = Amount::from_vrsc(amount)?;
+    let guild_id = guild_of(ctx);
+
+    if !database::donate_to_treasury(
+        &ctx.data().database,
+        guild_id,
+        &ctx.author().id,
+        amount,
+        memo.as_deref(),
+    )
+    .await?
+    {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "tip-insufficient-balance"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    debug!(
+        "{} donated {amount} to the treasury of {guild_id}",
+        ctx.author().id
+    );
+
+    ctx.send(|reply| {
+        reply.content(tr!(
+            ctx,
+            "treasury-donated",
+            donor: format!("<@{}>", ctx.author().id),
+            amount: format::amount(amount)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Download the treasury statement of a month
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Treasury",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn report(
+    ctx: Context<'_>,
+    #[description = "The month of the report, e.g. 2023-09"] month: String,
+) -> Result<(), Error> {
+    let Some((from, until)) = month_bounds(&month) else {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "treasury-invalid-month", month: month.as_str()))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    ctx.defer_ephemeral().await?;
+
+    let guild_id = guild_of(ctx);
+    let entries =
+        database::get_treasury_entries(&ctx.data().database, guild_id, from, until).await?;
+    let balance = database::get_treasury_balance(&ctx.data().database, guild_id).await?;
+
+    let total = |inflow: bool| {
+        entries
+            .iter()
+            .filter(|entry| entry.kind.is_inflow() == inflow)
+            .fold(Amount::ZERO, |sum, entry| {
+                sum.checked_add(entry.amount).unwrap_or(sum)
+            })
+    };
+    let inflows = total(true);
+    let outflows = total(false);
+
+    let filename = format!("treasury-{guild_id}-{month}.csv");
+    let statement = to_csv(&entries);
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(tr!(
+                ctx,
+                "treasury-report",
+                month: month.as_str(),
+                entries: entries.len(),
+                inflows: format::amount(inflows),
+                outflows: format::amount(outflows),
+                balance: format::amount(balance)
+            ))
+            .attachment(AttachmentType::Bytes {
+                data: Cow::Owned(statement.into_bytes()),
+                filename,
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Parses `YYYY-MM` into the start of that month and the start of the next month.
+fn month_bounds(month: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let first_day = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()?;
+    let next_month = first_day.checked_add_months(Months::new(1))?;
+
+    Some((
+        Utc.from_utc_datetime(&first_day.and_hms_opt(0, 0, 0)?),
+        Utc.from_utc_datetime(&next_month.and_hms_opt(0, 0, 0)?),
+    ))
+}
+
+fn to_csv(entries: &[TreasuryEntry]) -> String {
+    let mut csv = String::from("id,date,direction,kind,amount,counterparty,memo\n");
+
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},\"{}\"\n",
+            entry.id,
+            entry.created_at.format("%Y-%m-%d %H:%M:%S"),
+            match entry.kind.is_inflow() {
+                true => "in",
+                false => "out",
+            },
+            entry.kind,
+            entry.amount.as_vrsc(),
+            entry
+                .counterparty
+                .map(|user_id| user_id.to_string())
+                .unwrap_or_default(),
+            entry
+                .memo
+                .as_deref()
+                .unwrap_or_default()
+                .replace('"', "\"\"")
+        ));
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_bounds_span_one_month() {
+        let (from, until) = month_bounds("2023-12").unwrap();
+
+        assert_eq!(from.to_rfc3339(), "2023-12-01T00:00:00+00:00");
+        assert_eq!(until.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn invalid_months_are_rejected() {
+        assert!(month_bounds("2023-13").is_none());
+        assert!(month_bounds("september").is_none());
+        assert!(month_bounds("2023-09-01").is_none());
+    }
+}
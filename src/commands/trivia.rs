@@ -0,0 +1,487 @@
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{CacheHttp, ChannelId, CollectReply, UserId};
+use tracing::*;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    audit::{self, AuditAction},
+    authorization::{can_manage_guild, guild_of},
+    commands::{begin_balance_change, bot_degraded, tipping, user_blacklisted},
+    i18n::tr,
+    util::{database, format},
+    wallet::get_and_check_balance,
+    Context, Error,
+};
+
+/// How long players have to answer a question.
+const ROUND_TIMEOUT: Duration = Duration::from_secs(45);
+/// The pause between the answer of a round and the next question.
+const ROUND_PAUSE: Duration = Duration::from_secs(5);
+const MAX_QUESTION_LENGTH: usize = 300;
+const MAX_ANSWER_LENGTH: usize = 100;
+/// Discord refuses messages longer than 2000 characters.
+const MAX_LIST_LENGTH: usize = 1900;
+
+/// The channels with a game going on. A channel has one game at a time, or the answers would count for both.
+static RUNNING: Lazy<Mutex<HashSet<ChannelId>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// A question of the trivia game of a guild.
+#[derive(Debug)]
+pub struct TriviaQuestion {
+    pub id: i64,
+    pub question: String,
+    /// The accepted answers, separated by `|`
+    pub answer: String,
+}
+
+/// Play trivia, with a prize for the first correct answer of every round
+///
+/// -------- :robot: **Trivia** --------
+/// Server admins add questions with `/trivia add` and start a game with `/trivia start`. \
+/// Every round a question is posted in the channel, and the first correct answer wins the prize. \
+/// The prizes are paid from the balance of whoever started the game.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Trivia",
+    guild_only,
+    check = "can_manage_guild",
+    subcommands("start", "add", "list", "remove")
+)]
+pub async fn trivia(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Start a game of trivia in this channel, paid from your balance
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Trivia",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn start(
+    ctx: Context<'_>,
+    #[description = "The prize of every round"]
+    #[min = 0.01]
+    prize: f64,
+    #[description = "The number of questions"]
+    #[min = 1]
+    #[max = 20]
+    rounds: u32,
+) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    // a game takes minutes, so a shutdown only waits for the payout of a prize, not for the whole game
+    if begin_balance_change(ctx).await?.is_none() {
+        return Ok(());
+    }
+
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let channel_id = ctx.channel_id();
+    let prize = Amount::from_vrsc(prize)?;
+
+    let questions = database::get_random_trivia_questions(pool, guild_id, rounds as i64).await?;
+    if questions.is_empty() {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "trivia-no-questions"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let total = prize
+        .checked_mul(questions.len() as u64)
+        .ok_or("trivia prizes overflow")?;
+    if get_and_check_balance(&ctx, total, Amount::ZERO)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    if !RUNNING.lock().unwrap().insert(channel_id) {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "trivia-already-running"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let result = play(ctx, &questions, prize).await;
+    RUNNING.lock().unwrap().remove(&channel_id);
+
+    result
+}
+
+/// Plays the rounds of a game. The prize of a round is paid as soon as it is won, so the game stops when the starter
+/// can no longer pay it.
+async fn play(ctx: Context<'_>, questions: &[TriviaQuestion], prize: Amount) -> Result<(), Error> {
+    let channel_id = ctx.channel_id();
+    let starter = ctx.author().id;
+    let rounds = questions.len();
+
+    debug!("{starter} starts a trivia game of {rounds} round(s) in {channel_id}");
+    ctx.send(|reply| {
+        reply.content(tr!(
+            ctx,
+            "trivia-started",
+            starter: format!("<@{starter}>"),
+            rounds: rounds,
+            prize: format::amount(prize)
+        ))
+    })
+    .await?;
+
+    let mut winners: Vec<UserId> = vec![];
+
+    for (round, question) in questions.iter().enumerate() {
+        tokio::time::sleep(ROUND_PAUSE).await;
+        if ctx.data().shutdown.is_stopping() {
+            channel_id
+                .say(ctx.http(), tr!(ctx, "error-shutting-down"))
+                .await?;
+            break;
+        }
+
+        channel_id
+            .say(
+                ctx.http(),
+                tr!(
+                    ctx,
+                    "trivia-question",
+                    round: round + 1,
+                    rounds: rounds,
+                    question: format::user_text(&question.question, MAX_QUESTION_LENGTH),
+                    seconds: ROUND_TIMEOUT.as_secs()
+                ),
+            )
+            .await?;
+
+        let Some(winner) = first_correct_answer(ctx, &question.answer).await? else {
+            channel_id
+                .say(
+                    ctx.http(),
+                    tr!(
+                        ctx,
+                        "trivia-no-answer",
+                        answer: first_answer(&question.answer)
+                    ),
+                )
+                .await?;
+            continue;
+        };
+
+        channel_id
+            .say(
+                ctx.http(),
+                tr!(
+                    ctx,
+                    "trivia-correct",
+                    winner: format!("<@{winner}>"),
+                    answer: first_answer(&question.answer)
+                ),
+            )
+            .await?;
+
+        // a shutdown waits for the prize to be paid
+        let Some(_in_flight) = ctx.data().shutdown.begin() else {
+            channel_id
+                .say(ctx.http(), tr!(ctx, "error-shutting-down"))
+                .await?;
+            break;
+        };
+        let paid = tipping::tip_multiple_users(
+            &ctx.data().database,
+            &ctx.data().translations,
+            starter,
+            ctx.http(),
+            &channel_id,
+            ctx.guild_id(),
+            &vec![winner],
+            &prize,
+            "trivia",
+            None,
+            None,
+        )
+        .await;
+
+        match paid {
+            Ok(Some(_)) => winners.push(winner),
+            Ok(None) | Err(_) => {
+                warn!("could not pay the trivia prize of {winner}: {paid:?}");
+                channel_id
+                    .say(
+                        ctx.http(),
+                        tr!(ctx, "trivia-prize-failed", starter: format!("<@{starter}>")),
+                    )
+                    .await?;
+                break;
+            }
+        }
+    }
+
+    let content = match winners.is_empty() {
+        true => tr!(ctx, "trivia-over-no-winners"),
+        false => tr!(ctx, "trivia-over", winners: scoreboard(&winners)),
+    };
+    channel_id.say(ctx.http(), content).await?;
+
+    Ok(())
+}
+
+/// Waits for the first correct answer in the channel, from a user that can be tipped. The starter of the game can't
+/// win, as they pay the prize.
+async fn first_correct_answer(ctx: Context<'_>, answer: &str) -> Result<Option<UserId>, Error> {
+    let deadline = Instant::now() + ROUND_TIMEOUT;
+    let starter = ctx.author().id;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+
+        let answer = answer.to_owned();
+        let Some(message) = CollectReply::new(ctx)
+            .channel_id(ctx.channel_id())
+            .timeout(remaining)
+            .filter(move |message| {
+                !message.author.bot
+                    && message.author.id != starter
+                    && is_correct(&message.content, &answer)
+            })
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let player = message.author.id;
+        let blacklisted = ctx.data().blacklist.lock().unwrap().contains(&player);
+        let untippable = database::get_untippable_users(&ctx.data().database, &[player])
+            .await?
+            .contains(&player);
+        if !blacklisted && !untippable {
+            return Ok(Some(player));
+        }
+        debug!("{player} answered correctly but can't be tipped");
+    }
+}
+
+/// Lowercases an answer and removes punctuation and extra whitespace, so `The  Moon!` is the same as `the moon`.
+fn normalize(answer: &str) -> String {
+    answer
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `guess` is one of the accepted answers, which are separated by `|`.
+fn is_correct(guess: &str, answers: &str) -> bool {
+    let guess = normalize(guess);
+
+    !guess.is_empty() && answers.split('|').any(|answer| normalize(answer) == guess)
+}
+
+/// The first of the accepted answers, to reveal at the end of a round.
+fn first_answer(answers: &str) -> String {
+    let answer = answers.split('|').next().unwrap_or_default().trim();
+
+    format::user_text(answer, MAX_ANSWER_LENGTH)
+}
+
+/// The winners of a game with the number of rounds they won, the best first.
+fn scoreboard(winners: &[UserId]) -> String {
+    let mut wins: Vec<(UserId, usize)> = vec![];
+    for winner in winners {
+        match wins.iter_mut().find(|(user_id, _)| user_id == winner) {
+            Some((_, count)) => *count += 1,
+            None => wins.push((*winner, 1)),
+        }
+    }
+    wins.sort_by_key(|(_, count)| Reverse(*count));
+
+    wins.iter()
+        .map(|(user_id, count)| format!("<@{user_id}> ({count})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Add a question to the trivia game of this server
+///
+/// Separate multiple accepted answers with `|`, e.g. `Satoshi|Satoshi Nakamoto`. Answers are compared without \
+/// capitals and punctuation.
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Trivia",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "The question"]
+    #[max_length = 300]
+    question: String,
+    #[description = "The answer, or multiple accepted answers separated by |"]
+    #[max_length = 100]
+    answer: String,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+    let question = question.trim();
+    let answer = answer.trim();
+
+    if question.is_empty()
+        || question.chars().count() > MAX_QUESTION_LENGTH
+        || answer.chars().count() > MAX_ANSWER_LENGTH
+        || answer.split('|').any(|answer| normalize(answer).is_empty())
+    {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "trivia-invalid-question"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let id =
+        database::add_trivia_question(pool, guild_id, question, answer, &ctx.author().id).await?;
+    audit::record(
+        pool,
+        guild_id,
+        ctx.author().id,
+        AuditAction::AddTriviaQuestion,
+        Some(id.to_string()),
+        None,
+    )
+    .await;
+
+    ctx.send(|reply| {
+        reply
+            .ephemeral(true)
+            .content(tr!(ctx, "trivia-question-added", id: id))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// List the trivia questions of this server, with their answers
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Trivia",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let questions = database::get_trivia_questions(&ctx.data().database, guild_of(ctx)).await?;
+
+    let mut content = String::new();
+    for question in &questions {
+        let line = format!(
+            "`{}` {} → {}\n",
+            question.id,
+            format::user_text(&question.question, 100),
+            format::user_text(&question.answer, MAX_ANSWER_LENGTH)
+        );
+        if content.len() + line.len() > MAX_LIST_LENGTH {
+            content.push('…');
+            break;
+        }
+        content.push_str(&line);
+    }
+    if questions.is_empty() {
+        content = tr!(ctx, "trivia-no-questions");
+    }
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+/// Remove a question from the trivia game of this server
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(
+    slash_command,
+    category = "Trivia",
+    guild_only,
+    check = "can_manage_guild"
+)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The number of the question, see /trivia list"] id: i64,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
+    let guild_id = guild_of(ctx);
+
+    let content = match database::remove_trivia_question(pool, guild_id, id).await? {
+        true => {
+            audit::record(
+                pool,
+                guild_id,
+                ctx.author().id,
+                AuditAction::RemoveTriviaQuestion,
+                Some(id.to_string()),
+                None,
+            )
+            .await;
+
+            tr!(ctx, "trivia-question-removed", id: id)
+        }
+        false => tr!(ctx, "trivia-question-not-found", id: id),
+    };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_are_compared_without_capitals_and_punctuation() {
+        assert!(is_correct("The  Moon!", "the moon"));
+        assert!(is_correct("satoshi", "Satoshi Nakamoto|Satoshi"));
+        assert!(!is_correct("the", "the moon"));
+        assert!(!is_correct("?!", "the moon|"));
+    }
+
+    #[test]
+    fn scoreboard_starts_with_the_most_wins() {
+        let winners = [UserId(1), UserId(2), UserId(2)];
+
+        assert_eq!(scoreboard(&winners), "<@2> (2), <@1> (1)");
+    }
+}
@@ -1,16 +1,42 @@
 use std::path::PathBuf;
-use std::{cmp::Ordering, fmt, ops::Sub, str::FromStr, time::Duration};
+use std::{cmp::Ordering, str::FromStr, time::Duration};
 
 use fast_qr::convert::{image::ImageBuilder, Builder, Shape};
 use fast_qr::qr::QRBuilder;
+use poise::serenity_prelude::{CacheHttp, GuildId, UserId};
 use sqlx::PgPool;
 use tracing::*;
 use uuid::Uuid;
 use vrsc::{Address, Amount};
-use vrsc_rpc::{bitcoin::Txid, Client, RpcApi, SendCurrencyOutput};
-
-use crate::commands::user_blacklisted;
-use crate::{util::database, Context, Error};
+use vrsc_rpc::{bitcoin::Txid, RpcApi};
+
+use crate::commands::{begin_balance_change, bot_degraded, user_blacklisted, withdrawals_blocked};
+use crate::i18n::tr;
+use crate::{
+    balance_history,
+    configuration::BotFee,
+    low_balance, metrics,
+    util::{
+        balance_store::BalanceStore, database, embed::InfoEmbed, format, rpc::VerusRpc, rpc_guard,
+    },
+    Context, Error,
+};
+
+/// The number of tips `/balancehistory` lists.
+const RECENT_TIPS: i64 = 5;
+
+/// A withdrawal that was reserved but never settled or refunded, see [`database::reserve_withdrawal`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct PendingWithdrawal {
+    pub uuid: Uuid,
+    pub user_id: UserId,
+    pub guild_id: Option<GuildId>,
+    pub amount: Amount,
+    pub tx_fee: Amount,
+    pub bot_fee: Amount,
+    /// The operation that sends the withdrawal, once it was sent
+    pub opid: Option<String>,
+}
 
 /// Withdraw funds from the tipbot wallet.
 ///
@@ -19,15 +45,16 @@ use crate::{util::database, Context, Error};
 /// - an address that starts with R* or i*
 /// - an existing VerusID (ends with an `@`)
 ///
-/// A withdrawal fee will be subtracted from your remaining balance.
-/// You will encounter an error when the amount you want to withdraw is more than (your balance - withdrawal fee).
+/// A withdrawal fee (and a fee for the bot, if the bot or this server charges one) will be subtracted from your \
+/// remaining balance.
+/// You will encounter an error when the amount you want to withdraw is more than (your balance - withdrawal fees).
 ///
 /// -------- :robot: **Withdraw all** --------
 /// Zero out your balance by withdrawing everything to an address or VerusID that you specify. Valid withdrawal addresses are:
 /// - an address that starts with R* or i*
 /// - an existing VerusID (ends with an `@`)
 ///
-/// A withdrawal fee will be subtracted from the total balance before withdrawal.
+/// The withdrawal fees will be subtracted from the total balance before withdrawal.
 #[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
 #[poise::command(slash_command, category = "Wallet", subcommands("amount", "all"))]
 pub async fn withdraw(
@@ -47,14 +74,7 @@ pub async fn all(
     #[description = "You can use any address starting with R* or i*, or use an existing VerusID (ends with @)."]
     destination: String,
 ) -> Result<(), Error> {
-    if *ctx.data().withdrawals_enabled.read().await == false {
-        ctx.send(|reply| {
-            reply
-                .ephemeral(true)
-                .content(format!("Withdrawals are temporarily disabled."))
-        })
-        .await?;
-
+    if withdrawals_blocked(ctx).await? {
         return Ok(());
     }
 
@@ -62,6 +82,13 @@ pub async fn all(
         return Ok(());
     }
 
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
     debug!(
         "user {} ({}) demands a withdrawal of his whole balance",
         ctx.author().name,
@@ -69,10 +96,12 @@ pub async fn all(
     );
 
     let client = &ctx.data().verus()?;
-    if !destination_is_valid(&destination, &client) {
+    if !destination_is_valid(&destination, client).await? {
         ctx.send(|reply| {
-            reply.ephemeral(true).content(format!(
-                "Error: The destination you entered cannot be used: {destination}"
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "withdraw-invalid-destination",
+                destination: destination.as_str()
             ))
         })
         .await?;
@@ -84,85 +113,177 @@ pub async fn all(
     let uuid = Uuid::new_v4();
     let tx_fee = &ctx.data().withdrawal_fee.read().await.clone();
 
-    if let Some(balance) = database::get_balance_for_user(&pool, &ctx.author().id).await? {
+    if let Some(balance) = database::get_balance_for_user(pool, &ctx.author().id).await? {
         let balance_amount = Amount::from_sat(balance);
-        let withdrawal_amount = balance_amount.sub(*tx_fee); // no need to check for underflow, tx_fee is always low.
-
-        if withdrawal_amount > Amount::ZERO {
-            debug!("withdrawal_amount: {withdrawal_amount}, tx_fee: {tx_fee} must together be balance_amount: {balance_amount}");
+        let bot_fee_setting = bot_fee_of(ctx).await?;
+        let withdrawal_amount = max_withdrawal(&balance_amount, tx_fee, &bot_fee_setting);
+        let bot_fee = bot_fee_setting.fee_for(withdrawal_amount);
+
+        // a send can't be undone, so the interaction is claimed and the withdrawal is reserved before anything is
+        // sent. It is settled or refunded once the outcome of the send is known.
+        if withdrawal_amount > Amount::ZERO
+            && database::reserve_withdrawal(
+                pool,
+                ctx.id(),
+                &ctx.author().id,
+                withdrawal_amount,
+                *tx_fee,
+                bot_fee,
+                &uuid,
+                ctx.guild_id(),
+            )
+            .await?
+        {
+            debug!("withdrawal_amount: {withdrawal_amount}, tx_fee: {tx_fee}, bot_fee: {bot_fee} must together be at most balance_amount: {balance_amount}");
 
             let currency = match ctx.data().settings.application.testnet {
                 true => Some("vrsctest"),
                 false => None,
             };
-            let sco = SendCurrencyOutput::new(currency, &withdrawal_amount, &destination);
-            let opid = client.send_currency("*", vec![sco], None, None)?;
-            debug!("sendcurrency opid: {:?}", &opid);
-
-            if let Some(txid) = wait_for_sendcurrency_finish(&pool, &client, &opid).await? {
-                // at this point the txid is known. Now blockchain shenanigans could be happening, so we should store everything in the transactions_db table
-                database::store_withdraw_transaction(
-                    &pool,
-                    &uuid,
-                    &ctx.author().id,
-                    Some(&txid),
-                    &opid,
-                    &tx_fee,
-                )
-                .await?;
+            let opid = match client
+                .send_to(currency, withdrawal_amount, &destination)
+                .await
+            {
+                Ok(opid) => opid,
+                // only a send that certainly wasn't done is refunded
+                Err(e) if rpc_guard::not_done(&e) => {
+                    database::refund_withdrawal(
+                        pool,
+                        &ctx.author().id,
+                        withdrawal_amount,
+                        *tx_fee,
+                        bot_fee,
+                        &uuid,
+                    )
+                    .await?;
 
-                trace!("transaction {txid} stored in db, now decrease balance with ({withdrawal_amount} + {tx_fee})");
-                database::decrease_balance(&pool, &ctx.author().id, &withdrawal_amount, &tx_fee)
+                    return Err(e);
+                }
+                Err(e) => {
+                    // the send timed out or lost its connection, so it might still go through. Its operation is
+                    // not known, so the withdrawal stays reserved for an operator to check.
+                    error!("could not tell whether withdrawal {uuid} was sent: {e:?}");
+                    ctx.send(|reply| {
+                        reply.ephemeral(true).content(tr!(
+                            ctx,
+                            "withdraw-unknown-status",
+                            withdrawal_id: uuid.to_string()
+                        ))
+                    })
                     .await?;
 
-                let new_balance = database::get_balance_for_user(&pool, &ctx.author().id).await?;
+                    return Ok(());
+                }
+            };
+            debug!("sendcurrency opid: {:?}", &opid);
+            if let Err(e) = database::store_withdrawal_opid(pool, &uuid, &opid).await {
+                error!("could not store {opid}, the operation of withdrawal {uuid}: {e:?}");
+            }
+
+            match wait_for_sendcurrency_finish(pool, client, &opid).await {
+                Ok(Some(txid)) => {
+                    // at this point the txid is known. Now blockchain shenanigans could be happening, so we should store everything in the transactions_db table
+                    database::store_withdraw_transaction(
+                        pool,
+                        &uuid,
+                        &ctx.author().id,
+                        Some(&txid),
+                        &opid,
+                        tx_fee,
+                    )
+                    .await?;
 
-                ctx.send(|reply| {
-                    reply.ephemeral(true).embed(|embed| {
-                        let embed = embed
-                            .title("Withdraw")
-                            .field("Amount", withdrawal_amount, false)
-                            .field("Fees", tx_fee, false)
-                            .field(
-                                "Explorer",
-                                format!("[link](https://insight.verus.io/tx/{})", txid.to_string()),
+                    trace!("transaction {txid} stored in db, now settle the withdrawal of ({withdrawal_amount} + {tx_fee})");
+                    database::settle_withdrawal(
+                        pool,
+                        &ctx.author().id,
+                        withdrawal_amount,
+                        *tx_fee,
+                        bot_fee,
+                        &uuid,
+                        ctx.guild_id(),
+                        Some(&txid),
+                    )
+                    .await?;
+                    metrics::record_withdrawal(withdrawal_amount);
+
+                    let new_balance =
+                        database::get_balance_for_user(pool, &ctx.author().id).await?;
+
+                    ctx.send(|reply| {
+                        reply.ephemeral(true).embed(|embed| {
+                            let embed = embed
+                                .title(tr!(ctx, "withdraw-title"))
+                                .field(
+                                    tr!(ctx, "withdraw-amount"),
+                                    format::amount(withdrawal_amount),
+                                    false,
+                                )
+                                .field(tr!(ctx, "withdraw-fees"), format::amount(*tx_fee), false);
+
+                            if bot_fee > Amount::ZERO {
+                                embed.field(
+                                    tr!(ctx, "withdraw-bot-fee"),
+                                    format::amount(bot_fee),
+                                    false,
+                                );
+                            }
+
+                            embed.field(
+                                tr!(ctx, "withdraw-explorer"),
+                                format!("[link](https://insight.verus.io/tx/{})", txid),
                                 false,
                             );
 
-                        if let Some(new_balance) = new_balance {
-                            embed.field("New balance", Amount::from_sat(new_balance), false);
-                        }
+                            if let Some(new_balance) = new_balance {
+                                embed.field(
+                                    tr!(ctx, "withdraw-new-balance"),
+                                    format::amount(Amount::from_sat(new_balance)),
+                                    false,
+                                );
+                            }
 
-                        embed
+                            embed
+                        })
                     })
-                })
-                .await?;
-            } else {
-                // at this point, the sendcurrency didn't finish. Maybe it went through, but we don't know.
-                // We should check this manually, so we'll let the user know to contact support and we'll store the op-id in the database.
-                let response = format!("Something went wrong trying to process your withdrawal. Please contact support with withdrawal ID: {}",
-                uuid.to_string());
-
-                database::store_withdraw_transaction(
-                    &pool,
-                    &uuid,
-                    &ctx.author().id,
-                    None,
-                    &opid,
-                    &tx_fee,
-                )
-                .await?;
-
-                ctx.send(|reply| reply.ephemeral(true).content(&response))
                     .await?;
+                }
+                Ok(None) => {
+                    // the operation failed, so nothing was sent
+                    database::refund_withdrawal(
+                        pool,
+                        &ctx.author().id,
+                        withdrawal_amount,
+                        *tx_fee,
+                        bot_fee,
+                        &uuid,
+                    )
+                    .await?;
+
+                    ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "withdraw-failed")))
+                        .await?;
+                }
+                Err(e) => {
+                    // at this point, the sendcurrency didn't finish. Maybe it went through, but we don't know.
+                    // The withdrawal stays reserved with its operation and is settled or refunded when the bot
+                    // starts, see `resolve_withdrawals`. Until then we'll let the user know to contact support.
+                    error!("could not get the outcome of withdrawal {uuid} ({opid}): {e:?}");
+                    let response =
+                        tr!(ctx, "withdraw-unknown-status", withdrawal_id: uuid.to_string());
+
+                    ctx.send(|reply| reply.ephemeral(true).content(&response))
+                        .await?;
+                }
             }
 
             return Ok(());
         }
 
         ctx.send(|reply| {
-            reply.ephemeral(true).content(format!(
-                "Your balance is insufficient to withdraw everything.\nMax available balance for withdraw: {}", withdrawal_amount.checked_sub(*tx_fee).unwrap_or(Amount::ZERO)
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "withdraw-all-insufficient-balance",
+                max_amount: format::amount(withdrawal_amount)
             ))
         })
         .await?;
@@ -171,7 +292,7 @@ pub async fn all(
         ctx.send(|reply| {
             reply
                 .ephemeral(true)
-                .content(format!("Your balance is insufficient to withdraw"))
+                .content(tr!(ctx, "withdraw-no-balance"))
         })
         .await?;
     }
@@ -188,14 +309,7 @@ pub async fn amount(
     #[description = "You can use any address starting with R* or i*, or use an existing identity (ends with @)."]
     destination: String,
 ) -> Result<(), Error> {
-    if *ctx.data().withdrawals_enabled.read().await == false {
-        ctx.send(|reply| {
-            reply
-                .ephemeral(true)
-                .content(format!("Withdrawals are temporarily disabled."))
-        })
-        .await?;
-
+    if withdrawals_blocked(ctx).await? {
         return Ok(());
     }
 
@@ -203,6 +317,13 @@ pub async fn amount(
         return Ok(());
     }
 
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
     debug!(
         "user {} ({}) demands a withdrawal of {withdrawal_amount}",
         ctx.author().name,
@@ -210,10 +331,12 @@ pub async fn amount(
     );
 
     let client = &ctx.data().verus()?;
-    if !destination_is_valid(&destination, &client) {
+    if !destination_is_valid(&destination, client).await? {
         ctx.send(|reply| {
-            reply.ephemeral(true).content(format!(
-                "Error: The destination you entered cannot be used: {destination}"
+            reply.ephemeral(true).content(tr!(
+                ctx,
+                "withdraw-invalid-destination",
+                destination: destination.as_str()
             ))
         })
         .await?;
@@ -229,7 +352,7 @@ pub async fn amount(
         ctx.send(|reply| {
             reply
                 .ephemeral(true)
-                .content("Error: Withdrawal amount should be more than 0.0")
+                .content(tr!(ctx, "withdraw-amount-not-positive"))
         })
         .await?;
 
@@ -238,11 +361,28 @@ pub async fn amount(
 
     let pool = &ctx.data().database;
     let uuid = Uuid::new_v4();
-    let tx_fee = ctx.data().withdrawal_fee.read().await.clone();
-
-    if get_and_check_balance(&ctx, withdrawal_amount, tx_fee)
+    let tx_fee = *ctx.data().withdrawal_fee.read().await;
+    let bot_fee = bot_fee_of(ctx).await?.fee_for(withdrawal_amount);
+    let fees = tx_fee
+        .checked_add(bot_fee)
+        .ok_or("withdrawal fees overflow")?;
+
+    // a send can't be undone, so the interaction is claimed and the withdrawal is reserved before anything is sent.
+    // It is settled or refunded once the outcome of the send is known.
+    if get_and_check_balance(&ctx, withdrawal_amount, fees)
         .await?
         .is_some()
+        && database::reserve_withdrawal(
+            pool,
+            ctx.id(),
+            &ctx.author().id,
+            withdrawal_amount,
+            tx_fee,
+            bot_fee,
+            &uuid,
+            ctx.guild_id(),
+        )
+        .await?
     {
         trace!("balance is sufficient, withdrawal address is valid; starting sendcurrency");
 
@@ -251,74 +391,164 @@ pub async fn amount(
             true => Some("vrsctest"),
             false => None,
         };
-        let sco = SendCurrencyOutput::new(currency, &withdrawal_amount, &destination);
-        let opid = client.send_currency("*", vec![sco], None, None)?;
+        let opid = match client
+            .send_to(currency, withdrawal_amount, &destination)
+            .await
+        {
+            Ok(opid) => opid,
+            // only a send that certainly wasn't done is refunded
+            Err(e) if rpc_guard::not_done(&e) => {
+                database::refund_withdrawal(
+                    pool,
+                    &ctx.author().id,
+                    withdrawal_amount,
+                    tx_fee,
+                    bot_fee,
+                    &uuid,
+                )
+                .await?;
+
+                return Err(e);
+            }
+            Err(e) => {
+                // the send timed out or lost its connection, so it might still go through. Its operation is
+                // not known, so the withdrawal stays reserved for an operator to check.
+                error!("could not tell whether withdrawal {uuid} was sent: {e:?}");
+                ctx.send(|reply| {
+                    reply.ephemeral(true).content(tr!(
+                        ctx,
+                        "withdraw-unknown-status",
+                        withdrawal_id: uuid.to_string()
+                    ))
+                })
+                .await?;
+
+                return Ok(());
+            }
+        };
         debug!("sendcurrency opid: {:?}", &opid);
+        if let Err(e) = database::store_withdrawal_opid(pool, &uuid, &opid).await {
+            error!("could not store {opid}, the operation of withdrawal {uuid}: {e:?}");
+        }
 
-        if let Some(txid) = wait_for_sendcurrency_finish(&pool, &client, &opid).await? {
-            // at this point the txid is known. Now blockchain shenanigans could be happening, so we should store everything in the transactions_db table
-            database::store_withdraw_transaction(
-                &pool,
-                &uuid,
-                &ctx.author().id,
-                Some(&txid),
-                &opid,
-                &tx_fee,
-            )
-            .await?;
+        match wait_for_sendcurrency_finish(pool, client, &opid).await {
+            Ok(Some(txid)) => {
+                // at this point the txid is known. Now blockchain shenanigans could be happening, so we should store everything in the transactions_db table
+                database::store_withdraw_transaction(
+                    pool,
+                    &uuid,
+                    &ctx.author().id,
+                    Some(&txid),
+                    &opid,
+                    &tx_fee,
+                )
+                .await?;
 
-            trace!("transaction stored, now decrease balance");
-            database::decrease_balance(&pool, &ctx.author().id, &withdrawal_amount, &tx_fee)
+                trace!("transaction stored, now settle the withdrawal");
+                database::settle_withdrawal(
+                    pool,
+                    &ctx.author().id,
+                    withdrawal_amount,
+                    tx_fee,
+                    bot_fee,
+                    &uuid,
+                    ctx.guild_id(),
+                    Some(&txid),
+                )
                 .await?;
+                metrics::record_withdrawal(withdrawal_amount);
+                low_balance::remind(
+                    ctx.http(),
+                    pool,
+                    &ctx.data().translations,
+                    ctx.author().id,
+                    withdrawal_amount
+                        .checked_add(fees)
+                        .unwrap_or(withdrawal_amount),
+                )
+                .await;
 
-            let new_balance = database::get_balance_for_user(&pool, &ctx.author().id).await?;
+                let new_balance = database::get_balance_for_user(pool, &ctx.author().id).await?;
 
-            ctx.send(|reply| {
-                reply.ephemeral(true).embed(|embed| {
-                    let embed = embed
-                        .title("Withdraw")
-                        .field("Amount", withdrawal_amount, false)
-                        .field("Fees", tx_fee, false)
-                        .field(
-                            "Explorer",
-                            format!("[link](https://insight.verus.io/tx/{})", txid.to_string()),
+                ctx.send(|reply| {
+                    reply.ephemeral(true).embed(|embed| {
+                        let embed = embed
+                            .title(tr!(ctx, "withdraw-title"))
+                            .field(
+                                tr!(ctx, "withdraw-amount"),
+                                format::amount(withdrawal_amount),
+                                false,
+                            )
+                            .field(tr!(ctx, "withdraw-fees"), format::amount(tx_fee), false);
+
+                        if bot_fee > Amount::ZERO {
+                            embed.field(
+                                tr!(ctx, "withdraw-bot-fee"),
+                                format::amount(bot_fee),
+                                false,
+                            );
+                        }
+
+                        embed.field(
+                            tr!(ctx, "withdraw-explorer"),
+                            format!("[link](https://insight.verus.io/tx/{})", txid),
                             false,
                         );
 
-                    if let Some(new_balance) = new_balance {
-                        embed.field("New balance", Amount::from_sat(new_balance), false);
-                    }
+                        if let Some(new_balance) = new_balance {
+                            embed.field(
+                                tr!(ctx, "withdraw-new-balance"),
+                                format::amount(Amount::from_sat(new_balance)),
+                                false,
+                            );
+                        }
 
-                    embed
+                        embed
+                    })
                 })
-            })
-            .await?;
-        } else {
-            // at this point, the sendcurrency didn't finish. Maybe it went through, but we don't know.
-            // We should check this manually, so we'll let the user know to contact support and we'll store the op-id in the database.
-            let response = format!("Something went wrong trying to process your withdrawal. Please contact support with withdrawal ID: {}",
-                uuid.to_string());
-
-            database::store_withdraw_transaction(
-                &pool,
-                &uuid,
-                &ctx.author().id,
-                None,
-                &opid,
-                &tx_fee,
-            )
-            .await?;
-
-            ctx.send(|reply| reply.ephemeral(true).content(&response))
                 .await?;
+            }
+            Ok(None) => {
+                // the operation failed, so nothing was sent
+                database::refund_withdrawal(
+                    pool,
+                    &ctx.author().id,
+                    withdrawal_amount,
+                    tx_fee,
+                    bot_fee,
+                    &uuid,
+                )
+                .await?;
+
+                ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "withdraw-failed")))
+                    .await?;
+            }
+            Err(e) => {
+                // at this point, the sendcurrency didn't finish. Maybe it went through, but we don't know.
+                // The withdrawal stays reserved with its operation and is settled or refunded when the bot starts, see
+                // `resolve_withdrawals`. Until then we'll let the user know to contact support.
+                error!("could not get the outcome of withdrawal {uuid} ({opid}): {e:?}");
+                let response = tr!(ctx, "withdraw-unknown-status", withdrawal_id: uuid.to_string());
+
+                ctx.send(|reply| reply.ephemeral(true).content(&response))
+                    .await?;
+            }
         }
 
         return Ok(());
     }
 
+    let balance = database::get_balance_for_user(pool, &ctx.author().id)
+        .await?
+        .map(Amount::from_sat)
+        .unwrap_or(Amount::ZERO);
+    let max_amount = max_withdrawal(&balance, &tx_fee, &bot_fee_of(ctx).await?);
     ctx.send(|reply| {
-        reply.ephemeral(true).content(format!(
-            "Your balance is insufficient to withdraw {withdrawal_amount}.\nMax available balance for withdraw: {}", withdrawal_amount.checked_sub(tx_fee).unwrap_or(Amount::ZERO)
+        reply.ephemeral(true).content(tr!(
+            ctx,
+            "withdraw-insufficient-balance",
+            amount: format::amount(withdrawal_amount),
+            max_amount: format::amount(max_amount)
         ))
     })
     .await?;
@@ -327,28 +557,214 @@ pub async fn amount(
 }
 
 /// Show your balance
+///
+/// Only you see your balance, unless you set `public`. Users that made their balance private with \
+/// `/settings private` can't be looked up by others.
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4()))]
 #[poise::command(slash_command, category = "Wallet")]
-pub async fn balance(ctx: Context<'_>, target_user: Option<UserId>) -> Result<(), Error> {
+pub async fn balance(
+    ctx: Context<'_>,
+    target_user: Option<UserId>,
+    #[description = "Post the balance in the channel for everyone to see"] public: Option<bool>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().database;
     let user_id = match target_user {
         Some(user_id) => user_id,
         None => ctx.author().id,
     };
 
-    let balance = Amount::from_sat(database::get_balance_for_user(&ctx.data().database, &user_id)
-        .await?
-        .unwrap_or(0));
+    if user_id != ctx.author().id
+        && database::get_private_users(pool, &[user_id])
+            .await?
+            .contains(&user_id)
+    {
+        ctx.send(|reply| reply.ephemeral(true).content(tr!(ctx, "balance-private")))
+            .await?;
+
+        return Ok(());
+    }
+
+    let balance = Amount::from_sat(
+        database::get_balance_for_user(pool, &user_id)
+            .await?
+            .unwrap_or(0),
+    );
+
+    ctx.send(|reply| {
+        reply.ephemeral(!public.unwrap_or(false)).content(tr!(
+            ctx,
+            "balance-of-user",
+            user: user_id.to_string(),
+            balance: format::amount(balance)
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Show a chart of your balance over time
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4()))]
+#[poise::command(slash_command, category = "Wallet")]
+pub async fn balancehistory(
+    ctx: Context<'_>,
+    #[description = "The number of days to show, 30 by default"]
+    #[min = 2]
+    #[max = 365]
+    days: Option<u64>,
+) -> Result<(), Error> {
+    let days = days.unwrap_or(30);
+    let since = chrono::Utc::now().date_naive() - chrono::Days::new(days);
+    let history =
+        database::get_balance_history(&ctx.data().database, &ctx.author().id, since).await?;
+
+    // a chart needs at least 2 days
+    if history.len() < 2 {
+        ctx.send(|reply| {
+            reply
+                .ephemeral(true)
+                .content(tr!(ctx, "balance-history-empty"))
+        })
+        .await?;
+
+        return Ok(());
+    }
+
+    let filename = format!("balance-{}.png", ctx.author().id);
+    let out = PathBuf::from_str(&format!("charts/{filename}")).unwrap();
+    std::fs::create_dir_all("charts")?;
+    balance_history::render_chart(&history, &out)?;
+
+    let net_flow = balance_history::net_flow(&history);
+    let net_flow = format!(
+        "{}{}",
+        if net_flow < 0 { "-" } else { "+" },
+        format::amount(Amount::from_sat(net_flow.unsigned_abs()))
+    );
+    let (first_day, _) = history[0];
+    let (last_day, balance) = history[history.len() - 1];
+
+    let mut fields = InfoEmbed::new(ctx).await?;
+    fields
+        .detail(
+            tr!(ctx, "balance-history-period"),
+            format!("{first_day} - {last_day}"),
+            false,
+        )
+        .field(
+            tr!(ctx, "balance-history-balance"),
+            format::amount(balance),
+            true,
+        )
+        .detail(tr!(ctx, "balance-history-net-flow"), net_flow, true);
+
+    let recent_tips = recent_tips(ctx).await?;
+    if !recent_tips.is_empty() {
+        fields.detail(tr!(ctx, "balance-history-recent-tips"), recent_tips, false);
+    }
 
     ctx.send(|reply| {
         reply
+            .embed(|embed| {
+                fields
+                    .fill(embed)
+                    .title(tr!(ctx, "balance-history-title"))
+                    .image(format!("attachment://{filename}"))
+            })
+            .attachment(poise::serenity_prelude::AttachmentType::Path(&out))
             .ephemeral(true)
-            .content(format!("{}'s balance is: {}", user_id, balance))
     })
     .await?;
 
     Ok(())
 }
 
+/// The last tips the user sent or received, linking to their announcements.
+async fn recent_tips(ctx: Context<'_>) -> Result<String, Error> {
+    let pool = &ctx.data().database;
+    let tips = database::get_recent_tips(pool, &ctx.author().id, RECENT_TIPS).await?;
+    let links = database::get_tip_message_links(
+        pool,
+        &tips.iter().map(|tip| tip.uuid.clone()).collect::<Vec<_>>(),
+    )
+    .await?;
+
+    Ok(tips
+        .iter()
+        .map(|tip| {
+            let date = tip.created_at.format("%Y-%m-%d").to_string();
+            let line = if tip.tipper == ctx.author().id {
+                tr!(
+                    ctx,
+                    "balance-history-tip-sent",
+                    amount: format::amount(tip.amount),
+                    user: format!("<@{}>", tip.recipient),
+                    date: date
+                )
+            } else {
+                tr!(
+                    ctx,
+                    "balance-history-tip-received",
+                    amount: format::amount(tip.amount),
+                    user: format!("<@{}>", tip.tipper),
+                    date: date
+                )
+            };
+
+            match links.get(&tip.uuid) {
+                Some(link) => format!("- [{line}]({link})"),
+                None => format!("- {line}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Claim a balance
+///
+/// -------- :robot: **Legacy balances** --------
+/// If this server moved from another tipbot, your balance from that tipbot can be claimed once with `/claim legacy`.
+#[instrument(skip(_ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Wallet", subcommands("legacy"))]
+pub async fn claim(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Claim your balance from the tipbot this server used before
+#[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
+#[poise::command(slash_command, category = "Wallet")]
+async fn legacy(ctx: Context<'_>) -> Result<(), Error> {
+    if user_blacklisted(ctx, ctx.author().id).await? {
+        return Ok(());
+    }
+
+    if bot_degraded(ctx).await? {
+        return Ok(());
+    }
+    let Some(_in_flight) = begin_balance_change(ctx).await? else {
+        return Ok(());
+    };
+
+    let content =
+        match database::claim_legacy_balance(&ctx.data().database, &ctx.author().id).await? {
+            Some((amount, source)) => {
+                info!("{} claimed a legacy balance of {amount}", ctx.author().id);
+                tr!(
+                    ctx,
+                    "claim-legacy-done",
+                    amount: format::amount(amount),
+                    source: source
+                )
+            }
+            None => tr!(ctx, "claim-legacy-nothing"),
+        };
+
+    ctx.send(|reply| reply.ephemeral(true).content(content))
+        .await?;
+
+    Ok(())
+}
+
 /// Get an address to deposit funds to the tipbot wallet
 #[instrument(skip(ctx), fields(request_id = %Uuid::new_v4() ))]
 #[poise::command(slash_command, category = "Wallet")]
@@ -360,15 +776,14 @@ pub async fn deposit(ctx: Context<'_>) -> Result<(), Error> {
     );
     let pool = &ctx.data().database;
 
-    if let Some(address) = database::get_address_from_user(&pool, &ctx.author().id).await? {
+    if let Some(address) = database::get_address_from_user(pool, &ctx.author().id).await? {
         send_deposit_address_msg(ctx, &address).await?;
     } else {
         // the database doesn't have an address, let's create one:
-        let client = &ctx.data().verus().unwrap();
-        let address = client.get_new_address().unwrap();
-        crate::util::database::store_new_address_for_user(&pool, &ctx.author().id, &address)
-            .await
-            .expect("an address from the verus daemon");
+        let client = &ctx.data().verus()?;
+        let address =
+            rpc_guard::call(client, "getnewaddress", |client| client.get_new_address()).await?;
+        crate::util::database::store_new_address_for_user(pool, &ctx.author().id, &address).await?;
 
         send_deposit_address_msg(ctx, &address).await?;
     }
@@ -377,24 +792,14 @@ pub async fn deposit(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 async fn send_deposit_address_msg(ctx: Context<'_>, address: &Address) -> Result<(), Error> {
-    let filename = format!("{address}.png");
-    let out = PathBuf::from_str(&format!("qr_address/{}", &filename)).unwrap();
+    let (filename, out) = deposit_qr(address);
 
     ctx.send(|reply| {
-        let qr = QRBuilder::new(address.to_string()).build().unwrap();
-
-        let _img = ImageBuilder::default()
-            .shape(Shape::Circle)
-            .fit_width(400)
-            .module_color([49, 101, 212, 255])
-            .background_color([255, 255, 255, 0])
-            .to_file(&qr, out.as_os_str().to_str().unwrap());
-
         reply
             .embed(|embed| {
                 embed.image(format!("attachment://{filename}")).field(
-                    "Address",
-                    format!("{}", address.to_string()),
+                    tr!(ctx, "deposit-address"),
+                    format!("{}", address),
                     false,
                 )
             })
@@ -405,12 +810,29 @@ async fn send_deposit_address_msg(ctx: Context<'_>, address: &Address) -> Result
 
     Ok(())
 }
+
+/// Writes a QR code of a deposit address to a file, to attach to a message. Returns the name and path of the file.
+pub fn deposit_qr(address: &Address) -> (String, PathBuf) {
+    let filename = format!("{address}.png");
+    let out = PathBuf::from_str(&format!("qr_address/{}", &filename)).unwrap();
+    let qr = QRBuilder::new(address.to_string()).build().unwrap();
+
+    let _img = ImageBuilder::default()
+        .shape(Shape::Circle)
+        .fit_width(400)
+        .module_color([49, 101, 212, 255])
+        .background_color([255, 255, 255, 0])
+        .to_file(&qr, out.as_os_str().to_str().unwrap());
+
+    (filename, out)
+}
+
 // Sendcurrency works with op-ids because it can work with zk-transactions. Therefore the txid of a transactions is not always known directly after sending.
 // This function waits a bit and gets the txid once the operation_status RPC gives one.
 // if it doesn't give one, the user is notified and the op-id is stored in the database.
-async fn wait_for_sendcurrency_finish(
+pub(crate) async fn wait_for_sendcurrency_finish(
     pool: &PgPool,
-    client: &Client,
+    client: &impl VerusRpc,
     opid: &str,
 ) -> Result<Option<Txid>, Error> {
     // from https://buildmedia.readthedocs.org/media/pdf/zcash/english-docs/zcash.pdf
@@ -419,56 +841,139 @@ async fn wait_for_sendcurrency_finish(
     // we should return when status is one of failed or success.
     loop {
         trace!("getting operation status: {}", &opid);
-        let operation_status = client.z_get_operation_status(vec![&opid])?;
-        trace!("got operation status: {:?}", &operation_status);
-
-        if let Some(Some(opstatus)) = operation_status.first() {
-            if ["queued", "executing"].contains(&opstatus.status.as_ref()) {
-                tokio::time::sleep(Duration::from_millis(100)).await;
-                trace!("opid still executing");
-                continue;
-            }
+        let operation = client.operation_status(opid).await?;
+        trace!("got operation status: {:?}", &operation);
 
-            let params = opstatus.params.first().as_ref().unwrap().as_ref().unwrap();
+        let Some(operation) = operation else {
+            trace!("there was NO operation_status");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            continue;
+        };
 
-            if let Some(txid) = &opstatus.result {
-                trace!(
-                    "there was an operation_status, operation was executed with status: {}",
-                    opstatus.status
-                );
+        if operation.is_pending() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            trace!("opid still executing");
+            continue;
+        }
 
-                database::store_opid(
-                    &pool,
-                    &opid,
-                    &opstatus.status,
-                    opstatus.creation_time as i64,
-                    opstatus.result.as_ref().map(|txid| txid.txid),
-                    &params.address,
-                    params.amount,
-                    &params.currency.as_ref().unwrap_or(&String::from("VRSC")),
-                )
-                .await?;
-                return Ok(Some(txid.txid));
-            } else {
-                error!("execution failed with status: {}", opstatus.status);
+        database::store_opid(
+            pool,
+            opid,
+            &operation.status,
+            operation.creation_time,
+            operation.txid,
+            &operation.address,
+            operation.amount,
+            operation.currency.as_deref().unwrap_or("VRSC"),
+        )
+        .await?;
 
-                database::store_opid(
-                    &pool,
-                    &opid,
-                    &opstatus.status,
-                    opstatus.creation_time as i64,
-                    opstatus.result.as_ref().map(|txid| txid.txid),
-                    &params.address,
-                    params.amount,
-                    &params.currency.as_ref().unwrap(),
-                )
-                .await?;
-            }
-        } else {
-            trace!("there was NO operation_status");
-            tokio::time::sleep(Duration::from_millis(100)).await;
+        match operation.txid {
+            Some(_) => trace!(
+                "there was an operation_status, operation was executed with status: {}",
+                operation.status
+            ),
+            None => error!("execution failed with status: {}", operation.status),
         }
+
+        return Ok(operation.txid);
+    }
+}
+
+/// Resolves the withdrawals that are still reserved, like when the bot stopped while sending them or their outcome
+/// could not be checked. A withdrawal whose operation went through is settled, and one whose operation failed is
+/// refunded. A withdrawal without an operation, or whose operation the daemon does not know anymore, might have been
+/// sent and is left for an operator to check.
+pub async fn resolve_withdrawals(pool: &PgPool, client: &impl VerusRpc) -> Result<(), Error> {
+    for withdrawal in database::get_pending_withdrawals(pool).await? {
+        if let Err(e) = resolve_withdrawal(pool, client, withdrawal).await {
+            error!("could not resolve a withdrawal: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn resolve_withdrawal(
+    pool: &PgPool,
+    client: &impl VerusRpc,
+    withdrawal: PendingWithdrawal,
+) -> Result<(), Error> {
+    let PendingWithdrawal {
+        uuid,
+        user_id,
+        amount,
+        tx_fee,
+        bot_fee,
+        ..
+    } = withdrawal;
+
+    let Some(opid) = withdrawal.opid.as_deref() else {
+        error!(
+            "withdrawal {uuid} of {user_id} has no operation, check it and use `/admin withdrawal`"
+        );
+
+        return Ok(());
+    };
+    if client.operation_status(opid).await?.is_none() {
+        error!("the daemon does not know {opid}, withdrawal {uuid} of {user_id}, check it and use `/admin withdrawal`");
+
+        return Ok(());
+    }
+    let Some(txid) = wait_for_sendcurrency_finish(pool, client, opid).await? else {
+        warn!("withdrawal {uuid} ({opid}) of {user_id} failed, refunding it");
+        database::refund_withdrawal(pool, &user_id, amount, tx_fee, bot_fee, &uuid).await?;
+
+        return Ok(());
+    };
+
+    settle_pending_withdrawal(pool, &withdrawal, &txid).await?;
+    info!("settled withdrawal {uuid} of {user_id} after its operation {opid} went through");
+
+    Ok(())
+}
+
+/// Settles a pending withdrawal that went through with `txid`, and stores it like any other withdrawal.
+pub async fn settle_pending_withdrawal(
+    pool: &PgPool,
+    withdrawal: &PendingWithdrawal,
+    txid: &Txid,
+) -> Result<(), Error> {
+    database::settle_withdrawal(
+        pool,
+        &withdrawal.user_id,
+        withdrawal.amount,
+        withdrawal.tx_fee,
+        withdrawal.bot_fee,
+        &withdrawal.uuid,
+        withdrawal.guild_id,
+        Some(txid),
+    )
+    .await?;
+    metrics::record_withdrawal(withdrawal.amount);
+
+    // the withdrawal is settled, so it is only logged when it can't be looked up later
+    let opid = withdrawal
+        .opid
+        .clone()
+        .unwrap_or_else(|| format!("opid-{}", withdrawal.uuid));
+    if let Err(e) = database::store_withdraw_transaction(
+        pool,
+        &withdrawal.uuid,
+        &withdrawal.user_id,
+        Some(txid),
+        &opid,
+        &withdrawal.tx_fee,
+    )
+    .await
+    {
+        error!(
+            "could not store {opid}, the withdrawal {} of {}: {e:?}",
+            withdrawal.uuid, withdrawal.user_id
+        );
     }
+
+    Ok(())
 }
 
 // Let's do some address parsing
@@ -476,22 +981,25 @@ async fn wait_for_sendcurrency_finish(
 // (- is the withdrawal address a z_address?)
 // - is the withdrawal address an identity?
 // - is the withdrawal address a i-address?
-fn destination_is_valid(dest: &str, client: &Client) -> bool {
+pub(crate) async fn destination_is_valid(
+    dest: &str,
+    client: &impl VerusRpc,
+) -> Result<bool, Error> {
     if Address::from_str(dest).is_ok() {
         // this parses both R* addresses and i* addresses
         // (maybe z-addresses?)
-        return true;
+        return Ok(true);
     } else {
         debug!("dest: {}", dest);
         // it could be an identity
-        if client.get_identity(dest).is_ok() {
+        if client.identity_exists(dest).await? {
             // this is a valid identity, let's use it.
-            return true;
+            return Ok(true);
         }
     }
 
     // in all other cases it's invalid.
-    false
+    Ok(false)
 }
 
 // This function checks if the user has sufficient balance to withdraw and to pay the fees.
@@ -508,6 +1016,41 @@ pub fn balance_is_enough(balance: &Amount, amount_to_withdraw: &Amount, tx_fee:
     false
 }
 
+/// The largest amount that can be withdrawn from `balance` once the network fee and the bot fee are paid.
+pub fn max_withdrawal(balance: &Amount, tx_fee: &Amount, bot_fee: &BotFee) -> Amount {
+    bot_fee.max_withdrawal(balance.checked_sub(*tx_fee).unwrap_or(Amount::ZERO))
+}
+
+/// The fee the bot charges for withdrawals: the fee of the guild the command is used in, or else the fee of the
+/// settings.
+pub async fn bot_fee_of(ctx: Context<'_>) -> Result<BotFee, Error> {
+    if let Some(guild_id) = ctx.guild_id() {
+        if let Some(fee) =
+            database::get_guild_withdrawal_fee(&ctx.data().database, guild_id).await?
+        {
+            return Ok(fee);
+        }
+    }
+
+    Ok(ctx.data().settings.application.bot_withdrawal_fee)
+}
+
+/// The balance of a user, if it is enough to pay `amount` and `tx_fee`.
+pub async fn check_balance(
+    store: &mut impl BalanceStore,
+    user_id: UserId,
+    amount: Amount,
+    tx_fee: Amount,
+) -> Result<Option<Amount>, Error> {
+    let Some(balance) = store.balance(user_id).await? else {
+        warn!("user {user_id} should have a balance!");
+
+        return Ok(None);
+    };
+
+    Ok(balance_is_enough(&balance, &amount, &tx_fee).then_some(balance))
+}
+
 // In this context, get the balance of the sending user, check if it is sufficient, and return it.
 pub async fn get_and_check_balance(
     ctx: &Context<'_>,
@@ -516,45 +1059,41 @@ pub async fn get_and_check_balance(
 ) -> Result<Option<Amount>, Error> {
     let pool = &ctx.data().database;
 
-    if let Some(balance) = database::get_balance_for_user(&pool, &ctx.author().id).await? {
-        trace!("tipper has balance");
-
-        if balance_is_enough(
-            &Amount::from_sat(balance),
-            &amount_to_check,
-            &tx_fee, // no fees for tipping
-        ) {
+    match check_balance(
+        &mut *pool.acquire().await?,
+        ctx.author().id,
+        amount_to_check,
+        tx_fee,
+    )
+    .await?
+    {
+        Some(balance) => {
             trace!("tipper has sufficient balance");
-            return Ok(Some(Amount::from_sat(balance)));
-        } else {
+
+            Ok(Some(balance))
+        }
+        None => {
             trace!("balance is insufficient");
             ctx.send(|reply| {
                 reply
                     .ephemeral(true)
-                    .content(format!("Your balance is insufficient to tip that amount!"))
+                    .content(tr!(*ctx, "tip-insufficient-balance"))
             })
             .await?;
 
-            return Ok(None);
+            Ok(None)
         }
-    } else {
-        trace!("tipper has no balance");
-        warn!("user {} should have a balance!", ctx.author());
-
-        ctx.send(|reply| {
-            reply
-                .ephemeral(true)
-                .content(format!("Your balance is insufficient to tip that amount!"))
-        })
-        .await?;
-
-        return Ok(None);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::{
+        balance_store::mock::MockStore,
+        rpc::{mock::MockRpc, Operation},
+    };
+
     #[test]
     fn sufficient_balance() {
         let balance = Amount::from_sat(51000);
@@ -595,15 +1134,205 @@ mod tests {
 
         assert!(balance_is_enough(&balance, &to_withdraw, &tx_fee));
     }
-}
 
-#[derive(Debug)]
-struct MyError(String);
+    #[test]
+    fn max_withdrawal_leaves_room_for_both_fees() {
+        let balance = Amount::from_sat(51_005);
+        let tx_fee = Amount::from_sat(50_000);
+
+        let percentage = BotFee::Percentage(0.5);
+        let max = max_withdrawal(&balance, &tx_fee, &percentage);
+        assert_eq!(max, Amount::from_sat(1_000));
+        assert!(balance_is_enough(
+            &balance,
+            &max,
+            &(tx_fee + percentage.fee_for(max))
+        ));
+
+        let flat = BotFee::Flat(Amount::from_sat(5));
+        assert_eq!(
+            max_withdrawal(&balance, &tx_fee, &flat),
+            Amount::from_sat(1_000)
+        );
+        assert_eq!(
+            max_withdrawal(&Amount::from_sat(40_000), &tx_fee, &flat),
+            Amount::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_must_cover_the_fee() {
+        let mut store = MockStore::with_balances([(1, 51_000)]);
 
-impl fmt::Display for MyError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "There is an error: {}", self.0)
+        assert_eq!(
+            check_balance(
+                &mut store,
+                UserId(1),
+                Amount::from_sat(1_000),
+                Amount::from_sat(50_000)
+            )
+            .await
+            .unwrap(),
+            Some(Amount::from_sat(51_000))
+        );
+        assert_eq!(
+            check_balance(
+                &mut store,
+                UserId(1),
+                Amount::from_sat(1_001),
+                Amount::from_sat(50_000)
+            )
+            .await
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            check_balance(&mut store, UserId(2), Amount::ZERO, Amount::ZERO)
+                .await
+                .unwrap(),
+            None
+        );
     }
-}
 
-impl std::error::Error for MyError {}
+    #[tokio::test]
+    async fn identities_must_exist() {
+        let rpc = MockRpc {
+            identities: vec!["alice@".to_string()],
+            ..Default::default()
+        };
+
+        assert!(destination_is_valid("alice@", &rpc).await.unwrap());
+        assert!(!destination_is_valid("bob@", &rpc).await.unwrap());
+        assert!(!destination_is_valid("", &rpc).await.unwrap());
+    }
+
+    fn operation(status: &str, txid: Option<Txid>) -> Option<Operation> {
+        Some(Operation {
+            status: status.to_string(),
+            creation_time: 1_700_000_000,
+            txid,
+            address: "alice@".to_string(),
+            amount: 1.5,
+            currency: None,
+        })
+    }
+
+    #[sqlx::test]
+    async fn waits_until_the_operation_finishes(pool: PgPool) {
+        let txid = Txid::from_str(&"ab".repeat(32)).unwrap();
+        let rpc = MockRpc::with_statuses([
+            None,
+            operation("queued", None),
+            operation("executing", None),
+            operation("success", Some(txid)),
+        ]);
+
+        let result = wait_for_sendcurrency_finish(&pool, &rpc, "opid-1")
+            .await
+            .unwrap();
+
+        assert_eq!(result, Some(txid));
+        let stored =
+            sqlx::query!("SELECT status, result, currency FROM opids WHERE opid = 'opid-1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(stored.status, "success");
+        assert_eq!(stored.result, Some(txid.to_string()));
+        assert_eq!(stored.currency, "VRSC");
+    }
+
+    #[sqlx::test]
+    async fn failed_operation_has_no_txid(pool: PgPool) {
+        let rpc = MockRpc::with_statuses([operation("failed", None)]);
+
+        let result = wait_for_sendcurrency_finish(&pool, &rpc, "opid-1")
+            .await
+            .unwrap();
+
+        assert_eq!(result, None);
+        let stored = sqlx::query!("SELECT status FROM opids WHERE opid = 'opid-1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored.status, "failed");
+    }
+
+    /// Reserves a withdrawal of `sats` for user 1, sent with `opid` when it is given.
+    async fn reserve(pool: &PgPool, interaction_id: u64, sats: u64, opid: Option<&str>) -> Uuid {
+        let uuid = Uuid::new_v4();
+        assert!(database::reserve_withdrawal(
+            pool,
+            interaction_id,
+            &UserId(1),
+            Amount::from_sat(sats),
+            Amount::from_sat(10),
+            Amount::ZERO,
+            &uuid,
+            None,
+        )
+        .await
+        .unwrap());
+        if let Some(opid) = opid {
+            database::store_withdrawal_opid(pool, &uuid, opid)
+                .await
+                .unwrap();
+        }
+
+        uuid
+    }
+
+    #[sqlx::test]
+    async fn pending_withdrawals_are_resolved_by_their_operation(pool: PgPool) {
+        let txid = Txid::from_str(&"ab".repeat(32)).unwrap();
+        database::insert_discord_user(&pool, &UserId(1))
+            .await
+            .unwrap();
+        database::increase_balance(
+            &pool,
+            &UserId(1),
+            Amount::from_sat(1_000),
+            &Uuid::new_v4(),
+            &Txid::from_str(&"00".repeat(32)).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let sent = reserve(&pool, 1, 300, Some("opid-1")).await;
+        reserve(&pool, 2, 200, Some("opid-2")).await;
+        let unknown = reserve(&pool, 3, 100, None).await;
+
+        // every known operation is looked up once before it is waited for
+        let rpc = MockRpc::with_statuses([
+            operation("success", Some(txid)),
+            operation("success", Some(txid)),
+            operation("failed", None),
+            operation("failed", None),
+        ]);
+        resolve_withdrawals(&pool, &rpc).await.unwrap();
+
+        // the failed withdrawal is refunded, the one without an operation is left for an operator
+        assert_eq!(
+            database::get_balance_for_user(&pool, &UserId(1))
+                .await
+                .unwrap(),
+            Some(580)
+        );
+        let pending = database::get_pending_withdrawals(&pool).await.unwrap();
+        assert_eq!(
+            pending
+                .iter()
+                .map(|withdrawal| withdrawal.uuid)
+                .collect::<Vec<_>>(),
+            vec![unknown]
+        );
+        let stored = sqlx::query!(
+            "SELECT transaction_id FROM transactions_vrsc WHERE uuid = $1",
+            sent.to_string()
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored.transaction_id, txid.to_string());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{collections::HashSet, net::SocketAddr, path::PathBuf};
 
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
@@ -57,6 +57,9 @@ pub struct ApplicationSettings {
     pub discord_admin_thread_id: String,
     #[serde(with = "vrsc::util::amount::serde::as_sat")]
     pub global_withdrawal_fee: Amount,
+    /// Charged by the bot on top of `global_withdrawal_fee`, unless a guild overrides it.
+    #[serde(default)]
+    pub bot_withdrawal_fee: BotFee,
     #[serde(with = "vrsc::util::amount::serde::as_sat")]
     pub min_deposit_threshold: Amount,
     pub min_deposit_confirmations_small: u32,
@@ -64,6 +67,179 @@ pub struct ApplicationSettings {
     pub vrsc_block_notify_socket_path: PathBuf,
     pub vrsc_wallet_notify_socket_path: PathBuf,
     pub owners: HashSet<String>,
+    /// Role tips and soaks to more users than this need to be confirmed by the tipper.
+    #[serde(default = "default_max_group_tip_recipients")]
+    pub max_group_tip_recipients: usize,
+    /// How long after a tip it can still be disputed with `/dispute open`, in hours. The default is the 24 hours in
+    /// which `/admin reverse` can undo a tip event.
+    #[serde(default = "default_dispute_window_hours")]
+    pub dispute_window_hours: u64,
+    /// Basket currencies to sample for `/currencychart` and to offer in `/calc`, e.g. `Bridge.vETH`.
+    #[serde(default)]
+    pub basket_currencies: Vec<String>,
+    /// Address to serve Prometheus metrics on, e.g. `127.0.0.1:9100`. Metrics are not served when unset.
+    pub metrics_bind_address: Option<SocketAddr>,
+    /// Address to serve the read-only JSON API on, e.g. `127.0.0.1:9200`. The API is only served when `api_key` is set
+    /// too.
+    pub api_bind_address: Option<SocketAddr>,
+    /// The key clients of the API send in the `x-api-key` header.
+    pub api_key: Option<Secret<String>>,
+    /// Feeds of Verus news that guilds can subscribe to with `/config news`.
+    #[serde(default)]
+    pub news_feeds: Vec<NewsFeed>,
+    /// How long the prices of all sources are cached for `/price` and `/currency`, in seconds.
+    #[serde(default = "default_price_cache_secs")]
+    pub price_cache_secs: u64,
+    /// How long the blockchain and mining info is cached for `/chaininfo`, in seconds.
+    #[serde(default = "default_chaininfo_cache_secs")]
+    pub chaininfo_cache_secs: u64,
+    /// How long the names of currencies are cached, in seconds.
+    #[serde(default = "default_currency_name_cache_secs")]
+    pub currency_name_cache_secs: u64,
+    /// When to alert the operators about the liabilities of the bot. No alerts are sent when unset.
+    #[serde(default)]
+    pub liability_alerts: LiabilityAlerts,
+    /// Where `/wallet sweep` moves the funds the hot wallet does not need.
+    #[serde(default)]
+    pub hot_wallet: HotWallet,
+}
+
+fn default_max_group_tip_recipients() -> usize {
+    50
+}
+
+fn default_dispute_window_hours() -> u64 {
+    24
+}
+
+fn default_price_cache_secs() -> u64 {
+    60
+}
+
+fn default_chaininfo_cache_secs() -> u64 {
+    30
+}
+
+fn default_currency_name_cache_secs() -> u64 {
+    60 * 60
+}
+
+/// A feed of news that is relayed to the guilds that subscribed to its topic.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct NewsFeed {
+    /// What the feed is about, e.g. `releases`. Guilds pick the topics they want, several feeds can share one.
+    pub topic: String,
+    pub url: String,
+    #[serde(default)]
+    pub format: NewsFormat,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NewsFormat {
+    /// An RSS or Atom feed
+    #[default]
+    Rss,
+    /// The releases of a repository from the GitHub API, e.g. `https://api.github.com/repos/<owner>/<repo>/releases`
+    GithubReleases,
+}
+
+/// Limits on the total liabilities of the bot, see `liability_alert.rs`.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+pub struct LiabilityAlerts {
+    /// Alert when the liabilities exceed this, in sats.
+    pub max_total: Option<u64>,
+    /// Alert when the liabilities grow more than this within `growth_window_hours`, in sats.
+    pub max_growth: Option<u64>,
+    #[serde(default = "default_growth_window_hours")]
+    pub growth_window_hours: u64,
+    /// Alerts are posted to this webhook as `{"content": ...}`, besides being sent to the owners by DM.
+    pub webhook_url: Option<String>,
+}
+
+fn default_growth_window_hours() -> u64 {
+    24
+}
+
+/// The funds the hot wallet keeps, see `commands/hot_wallet.rs`.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct HotWallet {
+    /// The address `/wallet sweep` sends the excess funds to. Sweeping is not possible when unset.
+    pub cold_storage_address: Option<String>,
+    /// The part of the liabilities that stays in the hot wallet, e.g. 0.3 to keep 30% of what the bot owes its users.
+    /// Everything above that can be swept.
+    #[serde(default = "default_hot_reserve_ratio")]
+    pub reserve_ratio: f64,
+}
+
+impl Default for HotWallet {
+    fn default() -> Self {
+        Self {
+            cold_storage_address: None,
+            reserve_ratio: default_hot_reserve_ratio(),
+        }
+    }
+}
+
+fn default_hot_reserve_ratio() -> f64 {
+    1.0
+}
+
+/// A fee the bot charges for a withdrawal, on top of the network fee. Configured as `{ flat = 10000 }` (in sats) or
+/// `{ percentage = 0.5 }` (of the withdrawn amount).
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BotFee {
+    Flat(#[serde(with = "vrsc::util::amount::serde::as_sat")] Amount),
+    Percentage(f64),
+}
+
+impl Default for BotFee {
+    fn default() -> Self {
+        Self::Flat(Amount::ZERO)
+    }
+}
+
+impl BotFee {
+    /// The fee for withdrawing `amount`. Percentages are rounded up to the next sat.
+    pub fn fee_for(&self, amount: Amount) -> Amount {
+        match self {
+            Self::Flat(fee) => *fee,
+            Self::Percentage(percentage) => {
+                Amount::from_sat((amount.as_sat() as f64 * percentage / 100.0).ceil() as u64)
+            }
+        }
+    }
+
+    /// The largest amount that can be withdrawn from `available` when this fee is charged on top of it.
+    pub fn max_withdrawal(&self, available: Amount) -> Amount {
+        let mut withdrawal = match self {
+            Self::Flat(fee) => available.checked_sub(*fee).unwrap_or(Amount::ZERO),
+            Self::Percentage(percentage) => Amount::from_sat(
+                (available.as_sat() as f64 / (1.0 + percentage / 100.0)).floor() as u64,
+            ),
+        };
+
+        // rounding the percentage up can make the fee 1 sat too much
+        while withdrawal > Amount::ZERO
+            && withdrawal
+                .checked_add(self.fee_for(withdrawal))
+                .is_none_or(|total| total > available)
+        {
+            withdrawal = Amount::from_sat(withdrawal.as_sat() - 1);
+        }
+
+        withdrawal
+    }
+}
+
+impl std::fmt::Display for BotFee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Flat(fee) => write!(f, "{}", crate::util::format::amount(*fee)),
+            Self::Percentage(percentage) => write!(f, "{percentage}%"),
+        }
+    }
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -123,3 +299,38 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentage_fees_are_rounded_up() {
+        let fee = BotFee::Percentage(0.5);
+
+        assert_eq!(fee.fee_for(Amount::from_sat(1_000)), Amount::from_sat(5));
+        assert_eq!(fee.fee_for(Amount::from_sat(1_001)), Amount::from_sat(6));
+        assert_eq!(
+            BotFee::Flat(Amount::from_sat(10_000)).fee_for(Amount::from_sat(1)),
+            Amount::from_sat(10_000)
+        );
+    }
+
+    #[test]
+    fn max_withdrawal_leaves_room_for_the_fee() {
+        let fee = BotFee::Percentage(0.5);
+        let max = fee.max_withdrawal(Amount::from_sat(1_005));
+        assert_eq!(max, Amount::from_sat(1_000));
+        assert!(max + fee.fee_for(max) <= Amount::from_sat(1_005));
+
+        let max = fee.max_withdrawal(Amount::from_sat(1_006));
+        assert!(max + fee.fee_for(max) <= Amount::from_sat(1_006));
+
+        let flat = BotFee::Flat(Amount::from_sat(100));
+        assert_eq!(
+            flat.max_withdrawal(Amount::from_sat(150)),
+            Amount::from_sat(50)
+        );
+        assert_eq!(flat.max_withdrawal(Amount::from_sat(50)), Amount::ZERO);
+    }
+}
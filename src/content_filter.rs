@@ -0,0 +1,152 @@
+//! Filtering of the text users attach to tips and donations.
+//!
+//! Notes on tips and memos of treasury donations are posted by the bot, so they are not caught by the moderation of
+//! the server they are posted in. Every note is checked against a default list of blocked words and the words a guild
+//! blocked itself (see `/config filter`), and a tip with a blocked note is refused before any balance changes.
+//!
+//! Words are matched after undoing common character substitutions (`$h1t`) and letter spacing (`s h i t`). A word
+//! also matches longer words it is the start of (`shitty`), unless it is shorter than [`MIN_PREFIX_LENGTH`], so short
+//! words don't block innocent words that start with them.
+
+use poise::serenity_prelude::GuildId;
+use sqlx::PgPool;
+
+use crate::{util::database, Error};
+
+/// The words blocked in every guild that did not turn the default list off, and in DMs.
+pub const DEFAULT_WORDS: &[&str] = &[
+    "asshole", "bastard", "bitch", "cunt", "dick", "faggot", "fuck", "nigger", "pussy", "retard",
+    "shit", "slut", "whore",
+];
+
+/// The shortest word that also blocks the longer words it is the start of.
+const MIN_PREFIX_LENGTH: usize = 4;
+
+/// The longest word a guild can block.
+pub const MAX_WORD_LENGTH: usize = 50;
+
+/// Lowercases `text` and undoes common character substitutions, e.g. `$h1t` becomes `shit`.
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+/// The normalized words of `text`. Runs of single letters are joined into a word as well, so spacing out the letters
+/// of a word does not hide it.
+fn words(text: &str) -> Vec<String> {
+    let normalized = normalize(text);
+    let tokens = normalized
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut words = tokens
+        .iter()
+        .map(|token| token.to_string())
+        .collect::<Vec<_>>();
+    for run in tokens.split(|token| token.chars().count() > 1) {
+        if run.len() > 1 {
+            words.push(run.concat());
+        }
+    }
+
+    words
+}
+
+/// Returns the first of the `blocked` words that `text` contains, if any.
+pub fn find_blocked<'a>(text: &str, blocked: &'a [String]) -> Option<&'a str> {
+    let words = words(text);
+
+    blocked
+        .iter()
+        .find(|blocked| {
+            let blocked = normalize(blocked);
+            words.iter().any(|word| {
+                *word == blocked
+                    || (blocked.chars().count() >= MIN_PREFIX_LENGTH && word.starts_with(&blocked))
+            })
+        })
+        .map(String::as_str)
+}
+
+/// The words that are blocked in `guild_id`, or in DMs when it is `None`.
+pub async fn blocked_words(pool: &PgPool, guild_id: Option<GuildId>) -> Result<Vec<String>, Error> {
+    let (default_list, mut words) = match guild_id {
+        Some(guild_id) => (
+            database::get_default_filter(pool, guild_id).await?,
+            database::get_filter_words(pool, guild_id).await?,
+        ),
+        None => (true, vec![]),
+    };
+
+    if default_list {
+        words.extend(DEFAULT_WORDS.iter().map(|word| word.to_string()));
+    }
+
+    Ok(words)
+}
+
+/// Returns the blocked word in `text`, if it has one, for text that is posted in `guild_id`.
+pub async fn check(
+    pool: &PgPool,
+    guild_id: Option<GuildId>,
+    text: &str,
+) -> Result<Option<String>, Error> {
+    let blocked = blocked_words(pool, guild_id).await?;
+
+    Ok(find_blocked(text, &blocked).map(str::to_owned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_whole_words() {
+        let list = blocked(&["shit", "scam"]);
+
+        assert_eq!(find_blocked("what a SHIT tip", &list), Some("shit"));
+        assert_eq!(find_blocked("not a scam, promise", &list), Some("scam"));
+        assert_eq!(find_blocked("thanks for the help!", &list), None);
+    }
+
+    #[test]
+    fn undoes_substitutions_and_spacing() {
+        let list = blocked(&["shit"]);
+
+        assert_eq!(find_blocked("$h1t", &list), Some("shit"));
+        assert_eq!(find_blocked("s h i t", &list), Some("shit"));
+        assert_eq!(find_blocked("s.h.i.t happens", &list), Some("shit"));
+    }
+
+    #[test]
+    fn only_longer_words_block_their_prefixes() {
+        assert_eq!(find_blocked("shitty", &blocked(&["shit"])), Some("shit"));
+        assert_eq!(
+            find_blocked("a classic assessment", &blocked(&["ass"])),
+            None
+        );
+        assert_eq!(find_blocked("you ass", &blocked(&["ass"])), Some("ass"));
+    }
+
+    #[test]
+    fn default_words_are_normalized() {
+        for word in DEFAULT_WORDS {
+            assert_eq!(normalize(word), *word);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Snapshots of basket currencies, used to chart their supply and price over time.
+//!
+//! The currencies in `basket_currencies` are sampled every [`SAMPLE_INTERVAL`]. A snapshot holds the supply of the
+//! basket and, for every reserve currency, the reserves and the price of the basket in that reserve.
+
+use std::{ops::Range, path::Path, time::Duration};
+
+use chrono::{DateTime, Utc};
+use plotters::prelude::*;
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{debug, warn};
+use vrsc::Amount;
+use vrsc_rpc::{Client as VerusClient, RpcApi};
+
+use crate::{metrics, util::database, Error};
+
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The i-addresses of VRSC and VRSCTEST, the reserve currency prices are charted in.
+const VRSC_ID: &str = "i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV";
+const VRSCTEST_ID: &str = "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq";
+
+const CHART_SIZE: (u32, u32) = (800, 600);
+const SUPPLY_COLOR: RGBColor = RGBColor(49, 101, 212);
+const PRICE_COLOR: RGBColor = RGBColor(46, 160, 67);
+
+#[derive(Debug)]
+pub struct CurrencySnapshot {
+    pub sampled_at: DateTime<Utc>,
+    pub supply: Amount,
+    /// The price in the native currency of the chain, `None` when that is not a reserve of the basket.
+    pub price: Option<Amount>,
+}
+
+/// The native currency of the chain, as (i-address, name).
+pub fn native_currency(testnet: bool) -> (&'static str, &'static str) {
+    match testnet {
+        true => (VRSCTEST_ID, "VRSCTEST"),
+        false => (VRSC_ID, "VRSC"),
+    }
+}
+
+/// Samples all `currencies` now and then every [`SAMPLE_INTERVAL`]. Does nothing when no currencies are configured.
+pub async fn sample_periodically(client: VerusClient, pool: PgPool, currencies: Vec<String>) {
+    if currencies.is_empty() {
+        debug!("no basket currencies configured, not sampling");
+        return;
+    }
+
+    let mut interval = interval(SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        for currency in &currencies {
+            if let Err(e) = sample(&client, &pool, currency).await {
+                warn!("could not sample currency {currency}: {e:?}");
+            }
+        }
+    }
+}
+
+async fn sample(client: &VerusClient, pool: &PgPool, currency: &str) -> Result<(), Error> {
+    let definition = metrics::observe_rpc("getcurrency", || client.get_currency(currency))?;
+    let state = definition.bestcurrencystate;
+
+    let reserves = state
+        .reservecurrencies
+        .unwrap_or_default()
+        .iter()
+        .map(|rc| (rc.currencyid.to_string(), rc.reserves, rc.priceinreserve))
+        .collect::<Vec<_>>();
+
+    database::store_currency_snapshot(pool, currency, state.supply, &reserves).await
+}
+
+/// The range of the y axis for `values`, with some room above and below so the line does not touch the edges.
+fn y_range(values: impl Iterator<Item = f64>) -> Range<f64> {
+    let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), value| {
+        (min.min(value), max.max(value))
+    });
+
+    if min > max {
+        return 0.0..1.0;
+    }
+
+    let padding = if max > min {
+        (max - min) * 0.1
+    } else {
+        max.abs().max(1.0) * 0.1
+    };
+
+    (min - padding).max(0.0)..max + padding
+}
+
+/// Draws the supply (top) and the price (bottom) over time and saves it as a PNG at `path`. The price is left out
+/// when there is no price history. Like the balance chart, the chart has no labels.
+pub fn render_chart(history: &[CurrencySnapshot], path: &Path) -> Result<(), Error> {
+    let (Some(first), Some(last)) = (history.first(), history.last()) else {
+        return Err("no currency history to draw".into());
+    };
+    let time_range = first.sampled_at..last.sampled_at;
+
+    let prices = history
+        .iter()
+        .filter_map(|snapshot| {
+            snapshot
+                .price
+                .map(|price| (snapshot.sampled_at, price.as_vrsc()))
+        })
+        .collect::<Vec<_>>();
+
+    let root = BitMapBackend::new(path, CHART_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let (supply_area, price_area) = if prices.is_empty() {
+        (root.clone(), None)
+    } else {
+        let (top, bottom) = root.split_vertically(CHART_SIZE.1 / 2);
+        (top, Some(bottom))
+    };
+
+    let supply = history
+        .iter()
+        .map(|snapshot| (snapshot.sampled_at, snapshot.supply.as_vrsc()))
+        .collect::<Vec<_>>();
+    let mut chart = ChartBuilder::on(&supply_area)
+        .margin(20)
+        .build_cartesian_2d(
+            time_range.clone(),
+            y_range(supply.iter().map(|(_, supply)| *supply)),
+        )?;
+    let baseline = chart.y_range().start;
+    chart.draw_series(
+        AreaSeries::new(supply, baseline, SUPPLY_COLOR.mix(0.2))
+            .border_style(SUPPLY_COLOR.stroke_width(3)),
+    )?;
+
+    if let Some(price_area) = price_area {
+        let mut chart = ChartBuilder::on(&price_area)
+            .margin(20)
+            .build_cartesian_2d(time_range, y_range(prices.iter().map(|(_, price)| *price)))?;
+        let baseline = chart.y_range().start;
+        chart.draw_series(
+            AreaSeries::new(prices, baseline, PRICE_COLOR.mix(0.2))
+                .border_style(PRICE_COLOR.stroke_width(3)),
+        )?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn y_range_leaves_room_around_the_values() {
+        let range = y_range([10.0, 20.0, 15.0].into_iter());
+        assert_eq!(range, 9.0..21.0);
+    }
+
+    #[test]
+    fn y_range_of_a_flat_line_is_not_empty() {
+        let range = y_range([5.0, 5.0].into_iter());
+        assert!(range.start < 5.0 && range.end > 5.0);
+
+        assert_eq!(y_range(std::iter::empty()), 0.0..1.0);
+    }
+}
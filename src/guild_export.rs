@@ -0,0 +1,138 @@
+//! Export and import of the data of one guild, to move a community to another deployment of the bot.
+//!
+//! An export is a JSON file with the settings, the treasury ledger and the finished reactdrops of a guild. Balances
+//! belong to users and not to guilds, so they are not part of an export. Pending reactdrops are left out as well, as
+//! they would otherwise be paid out by both deployments.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{commands::treasury::TreasuryKind, Error};
+
+/// Bumped whenever the format of [`GuildExport`] changes in a way older deployments can not read.
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GuildExport {
+    pub version: u32,
+    pub guild_id: u64,
+    pub settings: Option<GuildSettingsExport>,
+    pub treasury: Vec<TreasuryEntryExport>,
+    pub reactdrops: Vec<ReactdropExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GuildSettingsExport {
+    pub announcement_channel_id: Option<u64>,
+    pub fallback_channel_id: Option<u64>,
+    /// Missing in exports made before announcement batching existed.
+    #[serde(default)]
+    pub batch_threshold: Option<u32>,
+    #[serde(default)]
+    pub batch_window_seconds: Option<u32>,
+}
+
+/// Timestamps are stored as RFC 3339 strings.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TreasuryEntryExport {
+    pub kind: String,
+    pub amount: u64,
+    pub counterparty: Option<u64>,
+    pub memo: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReactdropExport {
+    pub author: u64,
+    pub emoji: String,
+    pub amount: u64,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub status: String,
+    pub finish_time: String,
+    pub participants: Option<i32>,
+    pub eligible: Option<i32>,
+    pub tip_uuid: Option<String>,
+    /// Missing in exports made before raffles existed.
+    #[serde(default)]
+    pub winners: Option<u32>,
+}
+
+impl GuildExport {
+    pub fn to_json(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn from_json(json: &[u8]) -> Result<Self, Error> {
+        let export: Self = serde_json::from_slice(json)?;
+
+        if export.version > EXPORT_VERSION {
+            return Err(format!(
+                "export version {} is newer than the supported version {EXPORT_VERSION}",
+                export.version
+            )
+            .into());
+        }
+
+        let kinds = [
+            TreasuryKind::Fee,
+            TreasuryKind::Donation,
+            TreasuryKind::Award,
+            TreasuryKind::Match,
+            TreasuryKind::ScheduledDrop,
+        ]
+        .map(|kind| kind.to_string());
+        if let Some(entry) = export
+            .treasury
+            .iter()
+            .find(|entry| !kinds.contains(&entry.kind))
+        {
+            return Err(format!("unknown treasury entry kind `{}`", entry.kind).into());
+        }
+
+        Ok(export)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_roundtrips_through_json() {
+        let export = GuildExport {
+            version: EXPORT_VERSION,
+            guild_id: 42,
+            settings: Some(GuildSettingsExport {
+                announcement_channel_id: Some(1),
+                fallback_channel_id: None,
+                batch_threshold: Some(10),
+                batch_window_seconds: None,
+            }),
+            treasury: vec![TreasuryEntryExport {
+                kind: "donation".into(),
+                amount: 100_000_000,
+                counterparty: Some(7),
+                memo: Some("thanks".into()),
+                created_at: "2023-10-01T12:00:00+00:00".into(),
+            }],
+            reactdrops: vec![],
+        };
+
+        let imported = GuildExport::from_json(&export.to_json().unwrap()).unwrap();
+
+        assert_eq!(imported.guild_id, 42);
+        assert_eq!(imported.treasury[0].memo.as_deref(), Some("thanks"));
+        assert!(imported.reactdrops.is_empty());
+    }
+
+    #[test]
+    fn newer_exports_are_rejected() {
+        let json = format!(
+            r#"{{"version":{},"guild_id":1,"settings":null,"treasury":[],"reactdrops":[]}}"#,
+            EXPORT_VERSION + 1
+        );
+
+        assert!(GuildExport::from_json(json.as_bytes()).is_err());
+    }
+}
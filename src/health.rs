@@ -0,0 +1,234 @@
+//! Health of the services the bot depends on.
+//!
+//! A background task pings the Verus daemon every [`CHECK_INTERVAL`]. When the daemon can not be reached, the bot is
+//! marked as degraded: commands that change balances refuse to run until the daemon is back, instead of timing out
+//! halfway through. The same goes for when the daemon is reindexing or catching up with the chain, as balances would
+//! be based on an old state of the chain.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::{sync::RwLock, time::interval};
+use tracing::{debug, error, info, warn};
+use vrsc_rpc::{Client as VerusClient, RpcApi};
+
+use crate::{metrics, util::database, Error};
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+const EXPLORER_STATUS_URL: &str = "https://insight.verus.io/api/status?q=getInfo";
+
+/// How many headers the daemon may be ahead of its blocks before it is considered syncing. A new block is verified
+/// shortly after its header arrives, which should not pause the bot.
+const SYNC_TOLERANCE: u64 = 2;
+
+#[derive(Debug)]
+pub enum WalletLock {
+    /// The wallet is not encrypted
+    Unencrypted,
+    Locked,
+    Unlocked,
+}
+
+#[derive(Debug)]
+pub struct HealthReport {
+    pub daemon_reachable: bool,
+    /// Blocks and headers the daemon knows about
+    pub daemon_height: Option<(u64, u64)>,
+    pub explorer_height: Option<u64>,
+    pub database_reachable: bool,
+    pub wallet_lock: Option<WalletLock>,
+}
+
+/// How far the daemon is with verifying the chain while it is reindexing or syncing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncProgress {
+    pub blocks: u64,
+    pub headers: u64,
+    /// Between 0 and 1
+    pub verification_progress: f64,
+    /// Estimated from the progress made since syncing started
+    pub remaining: Option<Duration>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InsightStatus {
+    info: InsightInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct InsightInfo {
+    blocks: u64,
+}
+
+pub fn daemon_reachable(client: &VerusClient) -> bool {
+    metrics::observe_rpc("ping", || client.ping()).is_ok()
+}
+
+/// Checks all dependencies. The explorer is only checked on mainnet, as there is no public testnet explorer.
+pub async fn check(client: &VerusClient, pool: &PgPool, testnet: bool) -> HealthReport {
+    let daemon_reachable = daemon_reachable(client);
+
+    let daemon_height = if daemon_reachable {
+        metrics::observe_rpc("getblockchaininfo", || client.get_blockchain_info())
+            .ok()
+            .map(|info| (info.blocks, info.headers))
+    } else {
+        None
+    };
+
+    let wallet_lock = if daemon_reachable {
+        metrics::observe_rpc("getwalletinfo", || client.get_wallet_info())
+            .ok()
+            .map(|info| match info.unlocked_until {
+                None => WalletLock::Unencrypted,
+                Some(0) => WalletLock::Locked,
+                Some(_) => WalletLock::Unlocked,
+            })
+    } else {
+        None
+    };
+
+    let explorer_height = if testnet {
+        None
+    } else {
+        match explorer_height().await {
+            Ok(height) => Some(height),
+            Err(e) => {
+                warn!("could not get the explorer height: {e:?}");
+                None
+            }
+        }
+    };
+
+    let database_reachable = database::ping(pool).await.is_ok();
+
+    HealthReport {
+        daemon_reachable,
+        daemon_height,
+        explorer_height,
+        database_reachable,
+        wallet_lock,
+    }
+}
+
+async fn explorer_height() -> Result<u64, Error> {
+    let status: InsightStatus = reqwest::Client::new()
+        .get(EXPLORER_STATUS_URL)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    Ok(status.info.blocks)
+}
+
+pub fn is_syncing(blocks: u64, headers: u64) -> bool {
+    headers > blocks + SYNC_TOLERANCE
+}
+
+/// Extrapolates the verification progress made between `started` and `now` to 100%. Returns None when no progress
+/// was made yet.
+pub fn estimate_remaining(started: (Instant, f64), now: (Instant, f64)) -> Option<Duration> {
+    let elapsed = now.0.checked_duration_since(started.0)?.as_secs_f64();
+    let progressed = now.1 - started.1;
+    if elapsed <= 0.0 || progressed <= 0.0 {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(
+        (1.0 - now.1).max(0.0) * elapsed / progressed,
+    ))
+}
+
+/// Periodically checks whether the daemon can be reached and is in sync with the chain, and updates the `degraded`
+/// flag and the `sync` progress accordingly.
+pub async fn monitor(
+    client: VerusClient,
+    degraded: Arc<RwLock<bool>>,
+    sync: Arc<RwLock<Option<SyncProgress>>>,
+) {
+    let mut interval = interval(CHECK_INTERVAL);
+    // the moment and progress of the first check that found the daemon syncing
+    let mut sync_started: Option<(Instant, f64)> = None;
+
+    loop {
+        interval.tick().await;
+
+        let reachable = daemon_reachable(&client);
+
+        let info = match reachable {
+            true => metrics::observe_rpc("getblockchaininfo", || client.get_blockchain_info()).ok(),
+            false => None,
+        };
+        let progress = info
+            .filter(|info| is_syncing(info.blocks, info.headers))
+            .map(|info| {
+                let now = (Instant::now(), info.verificationprogress);
+                let started = *sync_started.get_or_insert(now);
+
+                SyncProgress {
+                    blocks: info.blocks,
+                    headers: info.headers,
+                    verification_progress: info.verificationprogress,
+                    remaining: estimate_remaining(started, now),
+                }
+            });
+
+        let mut sync = sync.write().await;
+        match (&*sync, &progress) {
+            (None, Some(progress)) => warn!(
+                "Verus daemon is syncing ({:.2}%), bot is degraded",
+                progress.verification_progress * 100.0
+            ),
+            (Some(_), None) if reachable => info!("Verus daemon is in sync again"),
+            _ => {}
+        }
+        if progress.is_none() {
+            sync_started = None;
+        }
+        *sync = progress;
+        drop(sync);
+
+        let mut degraded = degraded.write().await;
+
+        match (*degraded, reachable) {
+            (false, false) => error!("Verus daemon is unreachable, bot is degraded"),
+            (true, true) => info!("Verus daemon is reachable again"),
+            _ => debug!("daemon reachable: {reachable}"),
+        }
+
+        *degraded = !reachable;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daemon_is_syncing_when_it_is_behind_its_headers() {
+        assert!(!is_syncing(1_000, 1_000));
+        assert!(!is_syncing(1_000, 1_000 + SYNC_TOLERANCE));
+        assert!(is_syncing(1_000, 2_000));
+    }
+
+    #[test]
+    fn remaining_time_is_extrapolated_from_the_progress_so_far() {
+        let started = Instant::now();
+        let now = started + Duration::from_secs(600);
+
+        // 10% in 10 minutes, so the remaining 40% takes 40 minutes
+        assert_eq!(
+            estimate_remaining((started, 0.5), (now, 0.6)),
+            Some(Duration::from_secs(2_400))
+        );
+        assert_eq!(estimate_remaining((started, 0.5), (now, 0.5)), None);
+        assert_eq!(estimate_remaining((started, 0.5), (started, 0.5)), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! Translations of user-facing bot responses.
+//!
+//! Every `.ftl` file in `<project root>/translations/` is loaded once at startup. The filename (minus extension)
+//! is the locale it applies to, e.g. `nl.ftl` or `en-US.ftl`. `en-US.ftl` is the main translation file and must
+//! contain every message; other files may be incomplete, in which case the English message is used.
+//!
+//! The language used for a response is, in order of preference:
+//! - the language a user has set with `/language`
+//! - the locale of the user's Discord client
+//! - en-US
+//!
+//! DMs are sent in the language a user has set with `/dm-language`, or else the one set with `/language`.
+
+use std::collections::HashMap;
+
+use crate::{Context, Error};
+
+type FluentBundle = fluent::bundle::FluentBundle<
+    fluent::FluentResource,
+    intl_memoizer::concurrent::IntlLangMemoizer,
+>;
+
+pub const MAIN_LOCALE: &str = "en-US";
+
+pub struct Translations {
+    main: FluentBundle,
+    other: HashMap<String, FluentBundle>,
+}
+
+impl std::fmt::Debug for Translations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Translations")
+            .field("locales", &self.locales())
+            .finish()
+    }
+}
+
+/// Retrieves a translation in the language of the user that invoked the command, optionally with arguments:
+/// - `tr!(ctx, "identifier")` (no arguments)
+/// - `tr!(ctx, "identifier", arg1: VALUE1, arg2: VALUE2)` (with arguments)
+macro_rules! tr {
+    ( $ctx:expr, $id:expr $(, $argname:ident: $argvalue:expr )* $(,)? ) => {{
+        #[allow(unused_mut)]
+        let mut args = fluent::FluentArgs::new();
+        $( args.set(stringify!($argname), $argvalue); )*
+
+        $crate::i18n::get($ctx, $id, None, Some(&args))
+    }};
+}
+pub(crate) use tr;
+
+/// Like [`tr!`], but for places where there is no command context (background tasks). Takes the [`Translations`]
+/// and an optional locale instead of a context.
+macro_rules! tr_locale {
+    ( $translations:expr, $locale:expr, $id:expr $(, $argname:ident: $argvalue:expr )* $(,)? ) => {{
+        #[allow(unused_mut)]
+        let mut args = fluent::FluentArgs::new();
+        $( args.set(stringify!($argname), $argvalue); )*
+
+        $translations.format_for($locale, $id, None, Some(&args))
+    }};
+}
+pub(crate) use tr_locale;
+
+impl Translations {
+    /// The locales for which a translation file was loaded, sorted.
+    pub fn locales(&self) -> Vec<&str> {
+        let mut locales = self.other.keys().map(|l| l.as_str()).collect::<Vec<_>>();
+        locales.sort();
+
+        locales
+    }
+
+    pub fn has_locale(&self, locale: &str) -> bool {
+        self.other.contains_key(locale)
+    }
+
+    /// Formats the message in the given locale, falling back on en-US if the locale is unknown or the message
+    /// is missing from that locale's file.
+    pub fn format_for(
+        &self,
+        locale: Option<&str>,
+        id: &str,
+        attr: Option<&str>,
+        args: Option<&fluent::FluentArgs<'_>>,
+    ) -> String {
+        locale
+            .and_then(|locale| format(self.other.get(locale)?, id, attr, args))
+            .or_else(|| format(&self.main, id, attr, args))
+            .unwrap_or_else(|| {
+                tracing::warn!("unknown fluent message identifier `{}`", id);
+                id.to_string()
+            })
+    }
+}
+
+/// Given a language file and message identifier, returns the translation
+pub fn format(
+    bundle: &FluentBundle,
+    id: &str,
+    attr: Option<&str>,
+    args: Option<&fluent::FluentArgs<'_>>,
+) -> Option<String> {
+    let message = bundle.get_message(id)?;
+    let pattern = match attr {
+        Some(attribute) => message.get_attribute(attribute)?.value(),
+        None => message.value()?,
+    };
+    let formatted = bundle.format_pattern(pattern, args, &mut vec![]);
+
+    Some(formatted.into_owned())
+}
+
+/// The locale to use for responses to the author of this context.
+pub fn locale_of(ctx: Context<'_>) -> Option<String> {
+    let user_language = ctx
+        .data()
+        .user_languages
+        .read()
+        .unwrap()
+        .get(&ctx.author().id)
+        .cloned();
+
+    user_language.or_else(|| ctx.locale().map(|l| l.to_owned()))
+}
+
+/// Retrieves the translation in the language of the author of this context and calls [`format`]
+pub fn get(
+    ctx: Context<'_>,
+    id: &str,
+    attr: Option<&str>,
+    args: Option<&fluent::FluentArgs<'_>>,
+) -> String {
+    let locale = locale_of(ctx);
+
+    ctx.data()
+        .translations
+        .format_for(locale.as_deref(), id, attr, args)
+}
+
+/// Parses the `translations/` folder into a set of language files
+pub fn read_ftl() -> Result<Translations, Error> {
+    fn read_single_ftl(path: &std::path::Path) -> Result<(String, FluentBundle), Error> {
+        let locale = path.file_stem().ok_or("invalid .ftl filename")?;
+        let locale = locale.to_str().ok_or("invalid filename UTF-8")?;
+
+        let file_contents = std::fs::read_to_string(path)?;
+        let resource = fluent::FluentResource::try_new(file_contents)
+            .map_err(|(_, e)| format!("failed to parse {:?}: {:?}", path, e))?;
+
+        let mut bundle = FluentBundle::new_concurrent(vec![locale
+            .parse()
+            .map_err(|e| format!("invalid locale `{}`: {}", locale, e))?]);
+        // Discord mentions (<@123>) break when fluent wraps them in unicode isolation marks.
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(resource)
+            .map_err(|e| format!("failed to add resource to bundle: {:?}", e))?;
+
+        Ok((locale.to_string(), bundle))
+    }
+
+    Ok(Translations {
+        main: read_single_ftl(format!("translations/{MAIN_LOCALE}.ftl").as_ref())?.1,
+        other: std::fs::read_dir("translations")?
+            .map(|file| read_single_ftl(&file?.path()))
+            .collect::<Result<_, _>>()?,
+    })
+}
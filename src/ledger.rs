@@ -0,0 +1,262 @@
+//! The double-entry ledger of all funds the bot holds.
+//!
+//! Every change of a balance is an event of ledger entries that sum to zero: a tip takes an amount from the tipper and
+//! gives it to the recipients, a deposit takes it from the `deposits` account (the chain) and gives it to the user, and
+//! so on. The balances in `balance_vrsc` are the sum of the entries of each user, kept up to date in the same database
+//! transaction that stores the entries. They are checked against the ledger periodically, so a balance that was
+//! changed outside the ledger is noticed.
+
+use std::{collections::BTreeMap, fmt::Display, time::Duration};
+
+use poise::serenity_prelude::{GuildId, UserId};
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{error, info};
+
+use crate::{
+    metrics,
+    mod_log::{self, ModLogEvent},
+    util::database,
+    Error,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// An account in the ledger. Only user accounts have a balance in `balance_vrsc`, the balances of the other accounts
+/// are the sums of their entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Account {
+    User(UserId),
+    /// Funds that came in from the chain. Its balance is minus the total of all deposits.
+    Deposits,
+    /// Funds that were sent out to the chain.
+    Withdrawals,
+    /// Transaction fees paid to the network for withdrawals.
+    NetworkFees,
+    /// Fees the bot charged for withdrawals, see `bot_withdrawal_fee` in the settings.
+    BotFees,
+    /// Funds that are frozen in disputes.
+    Escrow,
+    /// Withdrawals and their fees that were reserved from a user before they were sent, until they are settled or
+    /// refunded.
+    PendingWithdrawals,
+    Treasury(GuildId),
+    /// Balances imported from a legacy tipbot, which enter the ledger when they are claimed.
+    Legacy,
+    /// The pool that referral rewards are paid from, funded by the bot admins.
+    Referrals,
+    /// The pool that the `/daily` rewards of a guild are paid from, funded by its members.
+    Daily(GuildId),
+    /// The contributions to a crowdfunding pot, until it is paid out or cancelled.
+    Pot(i64),
+    /// The balances that existed before the ledger did.
+    Opening,
+}
+
+impl Display for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(user_id) => write!(f, "user:{}", user_id.0),
+            Self::Deposits => write!(f, "deposits"),
+            Self::Withdrawals => write!(f, "withdrawals"),
+            Self::NetworkFees => write!(f, "network_fees"),
+            Self::BotFees => write!(f, "bot_fees"),
+            Self::Escrow => write!(f, "escrow"),
+            Self::PendingWithdrawals => write!(f, "pending_withdrawals"),
+            Self::Treasury(guild_id) => write!(f, "treasury:{}", guild_id.0),
+            Self::Legacy => write!(f, "legacy"),
+            Self::Referrals => write!(f, "referrals"),
+            Self::Daily(guild_id) => write!(f, "daily:{}", guild_id.0),
+            Self::Pot(pot_id) => write!(f, "pot:{pot_id}"),
+            Self::Opening => write!(f, "opening"),
+        }
+    }
+}
+
+/// What caused the entries of an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Tip,
+    Deposit,
+    /// A withdrawal that is reserved before it is sent
+    WithdrawalReserve,
+    Withdrawal,
+    /// A reserved withdrawal that was not sent
+    WithdrawalRefund,
+    Dispute,
+    Reversal,
+    Donation,
+    LegacyClaim,
+    ReferralFunding,
+    ReferralReward,
+    DailyFunding,
+    DailyReward,
+    Award,
+    PotContribution,
+    PotPayout,
+    PotRefund,
+}
+
+impl Display for EntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tip => write!(f, "tip"),
+            Self::Deposit => write!(f, "deposit"),
+            Self::WithdrawalReserve => write!(f, "withdrawal_reserve"),
+            Self::Withdrawal => write!(f, "withdrawal"),
+            Self::WithdrawalRefund => write!(f, "withdrawal_refund"),
+            Self::Dispute => write!(f, "dispute"),
+            Self::Reversal => write!(f, "reversal"),
+            Self::Donation => write!(f, "donation"),
+            Self::LegacyClaim => write!(f, "legacy_claim"),
+            Self::ReferralFunding => write!(f, "referral_funding"),
+            Self::ReferralReward => write!(f, "referral_reward"),
+            Self::DailyFunding => write!(f, "daily_funding"),
+            Self::DailyReward => write!(f, "daily_reward"),
+            Self::Award => write!(f, "award"),
+            Self::PotContribution => write!(f, "pot_contribution"),
+            Self::PotPayout => write!(f, "pot_payout"),
+            Self::PotRefund => write!(f, "pot_refund"),
+        }
+    }
+}
+
+/// A user whose balance does not match the sum of their ledger entries.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub user_id: UserId,
+    pub balance: i64,
+    pub ledger: i64,
+}
+
+/// Whether the entries of an event sum to zero. Entries that would overflow never balance.
+pub fn is_balanced(entries: &[(Account, i64)]) -> bool {
+    entries
+        .iter()
+        .try_fold(0i64, |sum, (_, amount)| sum.checked_add(*amount))
+        == Some(0)
+}
+
+/// The net change of the balance of every user in the entries, debits first. A user that is both debited and credited
+/// in the same event (e.g. someone that tips themselves) only has their net change applied, so they don't need the
+/// full debit on their balance.
+pub fn balance_changes(entries: &[(Account, i64)]) -> Vec<(UserId, i64)> {
+    let mut changes: BTreeMap<UserId, i64> = BTreeMap::new();
+    for (account, amount) in entries {
+        if let Account::User(user_id) = account {
+            *changes.entry(*user_id).or_default() += amount;
+        }
+    }
+
+    let mut changes = changes
+        .into_iter()
+        .filter(|(_, change)| *change != 0)
+        .collect::<Vec<_>>();
+    changes.sort_by_key(|(_, change)| *change);
+
+    changes
+}
+
+/// Checks the ledger against the balances every hour. Mismatches are logged, posted in the mod-logs of the guilds of
+/// the user and counted in the `verusbot_ledger_mismatches` metric, they are never corrected automatically.
+pub async fn check_periodically(pool: PgPool) {
+    let mut interval = interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let mismatches = match database::get_ledger_mismatches(&pool).await {
+            Ok(mismatches) => mismatches,
+            Err(e) => {
+                error!("could not check the ledger: {e:?}");
+                continue;
+            }
+        };
+        for mismatch in &mismatches {
+            error!(
+                "balance of {} is {} but the ledger says {}",
+                mismatch.user_id, mismatch.balance, mismatch.ledger
+            );
+            if let Err(e) = queue_mismatch(&pool, mismatch).await {
+                error!(
+                    "could not queue the mismatch of {} for the mod-log: {e:?}",
+                    mismatch.user_id
+                );
+            }
+        }
+
+        let unbalanced = match database::get_unbalanced_ledger_events(&pool).await {
+            Ok(unbalanced) => unbalanced,
+            Err(e) => {
+                error!("could not check the ledger: {e:?}");
+                continue;
+            }
+        };
+        for event_id in &unbalanced {
+            error!("ledger event {event_id} does not sum to zero");
+        }
+
+        metrics::LEDGER_MISMATCHES.set((mismatches.len() + unbalanced.len()) as i64);
+        if mismatches.is_empty() && unbalanced.is_empty() {
+            info!("ledger is consistent with all balances");
+        }
+    }
+}
+
+async fn queue_mismatch(pool: &PgPool, mismatch: &Mismatch) -> Result<(), Error> {
+    mod_log::dispatch(
+        &mut *pool.acquire().await?,
+        None,
+        ModLogEvent::BalanceMismatch {
+            user_id: mismatch.user_id,
+            balance: mismatch.balance,
+            ledger: mismatch.ledger,
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_must_sum_to_zero() {
+        assert!(is_balanced(&[
+            (Account::User(UserId(1)), -300),
+            (Account::User(UserId(2)), 150),
+            (Account::User(UserId(3)), 150),
+        ]));
+        assert!(!is_balanced(&[
+            (Account::User(UserId(1)), -300),
+            (Account::User(UserId(2)), 150),
+        ]));
+        assert!(!is_balanced(&[
+            (Account::Deposits, i64::MAX),
+            (Account::User(UserId(1)), i64::MAX),
+        ]));
+    }
+
+    #[test]
+    fn balance_changes_are_netted_per_user_with_debits_first() {
+        let changes = balance_changes(&[
+            (Account::User(UserId(1)), -300),
+            (Account::User(UserId(2)), 100),
+            (Account::User(UserId(1)), 100),
+            (Account::User(UserId(3)), 100),
+            (Account::Escrow, 0),
+        ]);
+
+        assert_eq!(
+            changes,
+            vec![(UserId(1), -200), (UserId(2), 100), (UserId(3), 100)]
+        );
+    }
+
+    #[test]
+    fn accounts_are_stored_by_name() {
+        assert_eq!(Account::User(UserId(42)).to_string(), "user:42");
+        assert_eq!(Account::Treasury(GuildId(7)).to_string(), "treasury:7");
+        assert_eq!(Account::NetworkFees.to_string(), "network_fees");
+    }
+}
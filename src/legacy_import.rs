@@ -0,0 +1,88 @@
+//! Import of balances from a legacy tipbot, for communities that move to this bot.
+//!
+//! The operator loads a CSV with a Discord user id and a balance (in VRSC) per line with `/importlegacy`. Users then
+//! claim their balance with `/claim legacy`, which credits it once. The funds themselves need to be moved from the
+//! wallet of the legacy tipbot to the wallet of this bot separately.
+
+use std::collections::HashSet;
+
+use poise::serenity_prelude::UserId;
+use vrsc::Amount;
+
+/// Parses lines of `discord_id,amount`. Empty lines are skipped, and so is a first line that is a header.
+pub fn parse_csv(csv: &str) -> Result<Vec<(UserId, Amount)>, String> {
+    let mut balances = vec![];
+    let mut seen = HashSet::new();
+
+    for (index, line) in csv.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some((discord_id, amount)) = line.split_once(',') else {
+            return Err(format!("line {line_number}: expected `discord_id,amount`"));
+        };
+
+        let Ok(discord_id) = discord_id.trim().parse::<u64>() else {
+            if index == 0 {
+                // a header
+                continue;
+            }
+
+            return Err(format!(
+                "line {line_number}: invalid Discord id `{discord_id}`"
+            ));
+        };
+
+        let amount = amount
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(|amount| Amount::from_vrsc(amount).ok())
+            .filter(|amount| *amount > Amount::ZERO)
+            .ok_or_else(|| format!("line {line_number}: invalid amount `{}`", amount.trim()))?;
+
+        if !seen.insert(discord_id) {
+            return Err(format!(
+                "line {line_number}: Discord id {discord_id} is listed twice"
+            ));
+        }
+
+        balances.push((UserId(discord_id), amount));
+    }
+
+    Ok(balances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_balances_and_skips_the_header() {
+        let csv = "discord_id,balance\n123,1.5\n\n456, 0.00000001\n";
+
+        assert_eq!(
+            parse_csv(csv),
+            Ok(vec![
+                (UserId(123), Amount::from_sat(150_000_000)),
+                (UserId(456), Amount::from_sat(1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(parse_csv("123,abc").is_err());
+        assert!(parse_csv("123,0").is_err());
+        assert!(parse_csv("123,-1").is_err());
+        assert!(parse_csv("123,1\nabc,1").is_err());
+        assert!(parse_csv("123").is_err());
+        assert_eq!(
+            parse_csv("123,1\n123,2"),
+            Err("line 2: Discord id 123 is listed twice".to_string())
+        );
+    }
+}
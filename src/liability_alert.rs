@@ -0,0 +1,248 @@
+//! Alerts for the operators when the liabilities of the bot (everything it owes, see `/proofofreserves`) grow too
+//! large or too fast.
+//!
+//! Every [`CHECK_INTERVAL`] the total liabilities are compared to the `liability_alerts` settings: an absolute cap,
+//! and a maximum growth within a window of hours. Either can mean the hot wallet needs more funds, or that something
+//! is crediting balances that shouldn't. A breach is sent once to the operators by DM, and to the configured webhook
+//! as `{"content": ...}`, which Discord webhooks accept as is. It is sent again after the liabilities got back within
+//! the limit and breached it once more.
+//!
+//! The samples for the growth are kept in memory, so after a restart the growth is measured from the restart.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use poise::serenity_prelude::{Http, UserId};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{debug, error, warn};
+use vrsc::Amount;
+
+use crate::{
+    configuration::LiabilityAlerts,
+    util::{database, format},
+    Error,
+};
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breach {
+    /// The liabilities exceed the cap.
+    Cap { total: Amount, cap: Amount },
+    /// The liabilities grew more than allowed within the window.
+    Growth {
+        growth: Amount,
+        max_growth: Amount,
+        window: Duration,
+    },
+}
+
+impl std::fmt::Display for Breach {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cap { total, cap } => write!(
+                f,
+                ":rotating_light: The liabilities of the bot are {}, above the cap of {}. Move more funds into the \
+                hot wallet or check for anomalies.",
+                format::amount(*total),
+                format::amount(*cap)
+            ),
+            Self::Growth {
+                growth,
+                max_growth,
+                window,
+            } => write!(
+                f,
+                ":rotating_light: The liabilities of the bot grew by {} in the last {} hours, more than the {} that \
+                is allowed. Check for anomalies.",
+                format::amount(*growth),
+                window.num_hours(),
+                format::amount(*max_growth)
+            ),
+        }
+    }
+}
+
+/// Keeps the liabilities of the last window and which limits are breached.
+#[derive(Debug)]
+pub struct Monitor {
+    config: LiabilityAlerts,
+    samples: VecDeque<(DateTime<Utc>, Amount)>,
+    cap_breached: bool,
+    growth_breached: bool,
+}
+
+impl Monitor {
+    pub fn new(config: LiabilityAlerts) -> Self {
+        Self {
+            config,
+            samples: VecDeque::new(),
+            cap_breached: false,
+            growth_breached: false,
+        }
+    }
+
+    /// Records the liabilities at `now`, and returns the limits they breach that they did not breach before.
+    pub fn check(&mut self, now: DateTime<Utc>, total: Amount) -> Vec<Breach> {
+        let window = Duration::hours(self.config.growth_window_hours as i64);
+        self.samples
+            .retain(|(sampled_at, _)| now - *sampled_at <= window);
+        self.samples.push_back((now, total));
+
+        let mut breaches = vec![];
+
+        let cap = self.config.max_total.map(Amount::from_sat);
+        let over_cap = cap.is_some_and(|cap| total > cap);
+        if let (Some(cap), true, false) = (cap, over_cap, self.cap_breached) {
+            breaches.push(Breach::Cap { total, cap });
+        }
+        self.cap_breached = over_cap;
+
+        let (_, oldest) = self.samples[0];
+        let growth = total.checked_sub(oldest).unwrap_or(Amount::ZERO);
+        let max_growth = self.config.max_growth.map(Amount::from_sat);
+        let grew_too_fast = max_growth.is_some_and(|max_growth| growth > max_growth);
+        if let (Some(max_growth), true, false) = (max_growth, grew_too_fast, self.growth_breached) {
+            breaches.push(Breach::Growth {
+                growth,
+                max_growth,
+                window,
+            });
+        }
+        self.growth_breached = grew_too_fast;
+
+        breaches
+    }
+}
+
+/// Checks the liabilities every [`CHECK_INTERVAL`] and alerts `owners` of new breaches.
+pub async fn check_periodically(
+    http: Arc<Http>,
+    pool: PgPool,
+    config: LiabilityAlerts,
+    owners: Vec<UserId>,
+) {
+    if config.max_total.is_none() && config.max_growth.is_none() {
+        debug!("no liability alerts configured");
+        return;
+    }
+
+    let client = reqwest::Client::new();
+    let mut monitor = Monitor::new(config.clone());
+    let mut interval = interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let liabilities = match database::get_liabilities(&pool).await {
+            Ok(liabilities) => liabilities,
+            Err(e) => {
+                error!("could not check the liabilities: {e:?}");
+                continue;
+            }
+        };
+
+        for breach in monitor.check(Utc::now(), Amount::from_sat(liabilities.total)) {
+            warn!("{breach}");
+            alert(&http, &client, &config, &owners, &breach).await;
+        }
+    }
+}
+
+async fn alert(
+    http: &Http,
+    client: &reqwest::Client,
+    config: &LiabilityAlerts,
+    owners: &[UserId],
+    breach: &Breach,
+) {
+    let content = breach.to_string();
+
+    for owner in owners {
+        if let Err(e) = dm(http, *owner, &content).await {
+            error!("could not send the liability alert to {owner}: {e:?}");
+        }
+    }
+
+    if let Some(url) = &config.webhook_url {
+        if let Err(e) = client
+            .post(url)
+            .json(&json!({ "content": content }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            error!("could not send the liability alert to the webhook: {e:?}");
+        }
+    }
+}
+
+async fn dm(http: &Http, user_id: UserId, content: &str) -> Result<(), Error> {
+    user_id
+        .create_dm_channel(http)
+        .await?
+        .say(http, content)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(max_total: Option<u64>, max_growth: Option<u64>) -> Monitor {
+        Monitor::new(LiabilityAlerts {
+            max_total,
+            max_growth,
+            growth_window_hours: 24,
+            webhook_url: None,
+        })
+    }
+
+    #[test]
+    fn cap_is_reported_once_until_it_clears() {
+        let mut monitor = monitor(Some(1_000), None);
+        let now = Utc::now();
+
+        assert_eq!(monitor.check(now, Amount::from_sat(900)), vec![]);
+        assert_eq!(
+            monitor.check(now, Amount::from_sat(1_100)),
+            vec![Breach::Cap {
+                total: Amount::from_sat(1_100),
+                cap: Amount::from_sat(1_000)
+            }]
+        );
+        assert_eq!(monitor.check(now, Amount::from_sat(1_200)), vec![]);
+        assert_eq!(monitor.check(now, Amount::from_sat(1_000)), vec![]);
+        assert_eq!(monitor.check(now, Amount::from_sat(1_001)).len(), 1);
+    }
+
+    #[test]
+    fn growth_is_measured_within_the_window() {
+        let mut monitor = monitor(None, Some(500));
+        let start = Utc::now();
+
+        assert_eq!(monitor.check(start, Amount::from_sat(1_000)), vec![]);
+        assert_eq!(
+            monitor.check(start + Duration::hours(12), Amount::from_sat(1_400)),
+            vec![]
+        );
+        assert_eq!(
+            monitor.check(start + Duration::hours(20), Amount::from_sat(1_600)),
+            vec![Breach::Growth {
+                growth: Amount::from_sat(600),
+                max_growth: Amount::from_sat(500),
+                window: Duration::hours(24),
+            }]
+        );
+
+        // the first sample left the window, so the growth is counted from 1,400
+        assert_eq!(
+            monitor.check(start + Duration::hours(30), Amount::from_sat(1_700)),
+            vec![]
+        );
+        assert!(!monitor.growth_breached);
+    }
+}
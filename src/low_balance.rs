@@ -0,0 +1,136 @@
+//! Reminders to top up, for users whose balance dropped below a threshold they picked with `/settings lowbalance`.
+//!
+//! Commands that spend a balance call [`remind`] afterwards with the amount that was spent. A user is only reminded
+//! when the balance crossed the threshold, so spending more while the balance is already low does not send another
+//! DM. The reminder has the deposit address of the user and a QR code, which the outbox can't carry, so it is sent
+//! directly and not retried.
+
+use poise::serenity_prelude::{AttachmentType, Http, UserId};
+use sqlx::PgPool;
+use tracing::{debug, warn};
+use vrsc::Amount;
+
+use crate::{
+    commands::wallet,
+    i18n::{tr_locale, Translations},
+    util::{database, format},
+    Error,
+};
+
+/// Reminds `user_id` to top up when spending `spent` made their balance drop below their threshold. The balance
+/// change is already done, so failures are only logged.
+pub async fn remind(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    user_id: UserId,
+    spent: Amount,
+) {
+    if let Err(e) = try_remind(http, pool, translations, user_id, spent).await {
+        warn!("could not send the low balance reminder to {user_id}: {e:?}");
+    }
+}
+
+async fn try_remind(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    user_id: UserId,
+    spent: Amount,
+) -> Result<(), Error> {
+    let Some((balance, threshold)) = database::get_low_balance_reminder(pool, &user_id).await?
+    else {
+        return Ok(());
+    };
+
+    if !dropped_below(balance, spent, threshold) {
+        return Ok(());
+    }
+    debug!("balance of {user_id} dropped to {balance}, below {threshold}");
+
+    let locale = database::get_dm_language(pool, &user_id).await?;
+    let address = database::get_address_from_user(pool, &user_id).await?;
+    let channel = user_id.create_dm_channel(http).await?;
+
+    match address {
+        Some(address) => {
+            let content = tr_locale!(
+                translations,
+                locale.as_deref(),
+                "low-balance-reminder",
+                balance: format::amount(balance),
+                threshold: format::amount(threshold)
+            );
+            let (filename, qr) = wallet::deposit_qr(&address);
+            let address_title = tr_locale!(translations, locale.as_deref(), "deposit-address");
+
+            channel
+                .send_message(http, |message| {
+                    message
+                        .content(content)
+                        .embed(|embed| {
+                            embed.image(format!("attachment://{filename}")).field(
+                                address_title,
+                                address.to_string(),
+                                false,
+                            )
+                        })
+                        .add_file(AttachmentType::Path(&qr))
+                })
+                .await?;
+        }
+        None => {
+            let content = tr_locale!(
+                translations,
+                locale.as_deref(),
+                "low-balance-reminder-no-address",
+                balance: format::amount(balance),
+                threshold: format::amount(threshold)
+            );
+
+            channel
+                .send_message(http, |message| message.content(content))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether spending `spent` made a balance cross `threshold`, given the balance after spending it.
+fn dropped_below(balance: Amount, spent: Amount, threshold: Amount) -> bool {
+    let before = balance.checked_add(spent).unwrap_or(balance);
+
+    balance < threshold && before >= threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_crossing_the_threshold_reminds() {
+        let threshold = Amount::from_sat(1_000);
+
+        assert!(dropped_below(
+            Amount::from_sat(900),
+            Amount::from_sat(100),
+            threshold
+        ));
+        assert!(dropped_below(
+            Amount::from_sat(0),
+            Amount::from_sat(5_000),
+            threshold
+        ));
+        assert!(!dropped_below(
+            Amount::from_sat(800),
+            Amount::from_sat(100),
+            threshold
+        ));
+        assert!(!dropped_below(
+            Amount::from_sat(1_000),
+            Amount::from_sat(100),
+            threshold
+        ));
+    }
+}
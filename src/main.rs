@@ -1,17 +1,42 @@
+pub mod announcement;
+pub mod api;
+pub mod audit;
+pub mod authorization;
+pub mod balance_history;
 pub mod commands;
 pub mod configuration;
+pub mod content_filter;
+pub mod currency_history;
+pub mod guild_export;
+pub mod health;
+pub mod i18n;
+pub mod ledger;
+pub mod legacy_import;
+pub mod liability_alert;
+pub mod low_balance;
+pub mod metrics;
+pub mod mod_log;
+pub mod network_digest;
+pub mod news;
+pub mod price;
+pub mod proof_of_reserves;
+pub mod quick_tip;
 pub mod reactdrop;
+pub mod schema;
+pub mod shutdown;
 pub mod util;
 pub mod wallet_listener;
 
 use crate::{
     configuration::{get_configuration, Settings},
-    util::database,
+    i18n::{tr, Translations},
+    shutdown::Shutdown,
+    util::{cache::TtlCache, database, delivery, rpc_guard},
     wallet_listener::TransactionProcessor,
 };
 use commands::*;
 // use opentelemetry::global;
-use poise::serenity_prelude::{self as serenity, CacheHttp, ChannelId, UserId};
+use poise::serenity_prelude::{self as serenity, CacheHttp, ChannelId, GuildId, UserId};
 use secrecy::ExposeSecret;
 use sqlx::PgPool;
 use std::{
@@ -37,7 +62,27 @@ async fn app() -> Result<(), Error> {
     let config = get_configuration()?;
     let pg_url = &config.database.connection_string();
     let database = PgPool::connect_lazy(pg_url)?;
-    sqlx::migrate!("./migrations").run(&database).await?;
+    schema::migrate(&database).await?;
+
+    metrics::register()?;
+    if let Some(address) = config.application.metrics_bind_address {
+        tokio::spawn(metrics::serve(address));
+    }
+    match (
+        config.application.api_bind_address,
+        config.application.api_key.clone(),
+    ) {
+        (Some(address), Some(api_key)) => {
+            tokio::spawn(api::serve(address, database.clone(), api_key));
+        }
+        (Some(_), None) => {
+            error!("api_bind_address is set without an api_key, not serving the API")
+        }
+        _ => {}
+    }
+
+    let translations = Arc::new(i18n::read_ftl()?);
+    debug!("loaded translations: {:?}", translations.locales());
 
     let owners = config
         .application
@@ -61,29 +106,61 @@ async fn app() -> Result<(), Error> {
             admin::maintenance(),
             admin::manuallyaddwithdraw(),
             admin::status(),
-            misc::help(),
+            admin::audit(),
+            admin::role(),
+            admin::announce(),
+            admin::exportguild(),
+            admin::importguild(),
+            admin::admin(),
+            admin::importlegacy(),
+            admin::proofofreserves(),
+            admin::referralrewards(),
+            admin::postnews(),
+            hot_wallet::wallet(),
+            // after `admin::status`, so that `!status` still finds the admin command
+            misc::health(),
+            dispute::dispute(),
+            help::help(),
             misc::info(),
             misc::source(),
             misc::register(),
             misc::notifications(),
+            misc::language(),
+            misc::dm_language(),
+            misc::settings(),
+            guild_settings::config(),
+            stats::stats(),
             chain::chaininfo(),
             chain::peerinfo(),
             chain::price(),
             chain::currency(),
+            chain::currencychart(),
+            chain::identity(),
+            chain::calc(),
             wallet::deposit(),
             wallet::balance(),
+            wallet::balancehistory(),
             wallet::withdraw(),
+            wallet::claim(),
             tipping::tip(),
             tipping::reactdrop(),
+            tipping::soak(),
+            treasury::treasury(),
+            referral::referral(),
+            trivia::trivia(),
+            daily::daily(),
+            daily::streaks(),
+            daily::dailyfund(),
+            competition::competition(),
+            pot::pot(),
         ],
         command_check: Some(|ctx| {
             let author = &ctx.author().id;
-            let owners = &ctx.data().owners;
 
             Box::pin(async move {
                 let maintenance_mode = { *ctx.data().tx_processor.maintenance.read().await };
 
-                if maintenance_mode && !owners.contains(&author) {
+                if maintenance_mode && !authorization::is_operator(ctx.data(), author) {
                     ctx.send(|reply| {
                         reply.content(
                             ":tools: The bot is in maintenance mode, we'll be right back :tools:",
@@ -111,6 +188,10 @@ async fn app() -> Result<(), Error> {
                     .await
                     .expect("a discord_user to be added to the database");
 
+                metrics::COMMANDS_EXECUTED
+                    .with_label_values(&[&ctx.command().qualified_name])
+                    .inc();
+
                 let author = ctx.author().tag();
                 let channel_name = ctx
                     .channel_id()
@@ -130,6 +211,16 @@ async fn app() -> Result<(), Error> {
             })
         },
         on_error: |error| Box::pin(on_error(error)),
+        event_handler: |ctx, event, _framework, data| {
+            Box::pin(async move {
+                // the buttons under tip announcements outlive the commands that posted them
+                if let poise::Event::InteractionCreate { interaction } = event {
+                    quick_tip::handle(ctx, data, interaction).await?;
+                }
+
+                Ok(())
+            })
+        },
         owners,
 
         ..Default::default()
@@ -153,7 +244,12 @@ async fn app() -> Result<(), Error> {
     debug!("connection string: {}", config.database.connection_string());
     info!("starting client");
 
-    poise::Framework::builder()
+    let shutdown = Arc::new(Shutdown::default());
+    let shutdown_clone = shutdown.clone();
+    let shutdown_pool = database.clone();
+    let shutdown_translations = translations.clone();
+
+    let framework = poise::Framework::builder()
         .token(config.application.discord.expose_secret())
         .setup(move |ctx, bot, _framework| {
             let http = ctx.http.clone();
@@ -161,24 +257,48 @@ async fn app() -> Result<(), Error> {
             let config_clone = config.clone();
             let deposits_enabled = Arc::new(RwLock::new(true));
             let deposits_enabled_clone = deposits_enabled.clone();
+            let shutdown = shutdown_clone;
 
             Box::pin(async move {
                 tokio::spawn({
                     let ctx = ctx.clone();
                     let pool = pool.clone();
+                    let translations = translations.clone();
+                    let shutdown = shutdown.clone();
 
                     info!("starting reactdrop loop");
 
                     async move {
+                        // reactdrops that were being paid out when the bot stopped
+                        if let Some(_in_flight) = shutdown.begin() {
+                            if let Err(e) =
+                                reactdrop::resume_payouts(&ctx, &pool, &translations).await
+                            {
+                                error!("could not resume reactdrop payouts: {e:?}");
+                            }
+                        }
+
                         let mut interval = interval(Duration::from_secs(20));
 
                         loop {
-                            interval.tick().await;
-
-                            if let Err(e) = reactdrop::check_running_reactdrops(&ctx, &pool).await {
+                            tokio::select! {
+                                _ = interval.tick() => {}
+                                _ = shutdown.stopped() => break,
+                            }
+                            // a payout that started is finished before the bot shuts down
+                            let Some(_in_flight) = shutdown.begin() else {
+                                break;
+                            };
+
+                            if let Err(e) =
+                                reactdrop::check_running_reactdrops(&ctx, &pool, &translations)
+                                    .await
+                            {
                                 error!("{:?}", e);
                             }
                         }
+
+                        info!("reactdrop loop stopped");
                     }
                 });
 
@@ -186,10 +306,33 @@ async fn app() -> Result<(), Error> {
                     http.clone(),
                     pool.clone(),
                     config_clone,
+                    translations.clone(),
                     Arc::new(RwLock::new(false)),
                     deposits_enabled_clone,
+                    shutdown.clone(),
                 ));
 
+                // deposits that were waiting for confirmations when the bot stopped
+                if let Err(e) = tx_proc.process_stored_transactions().await {
+                    error!("could not process stored transactions: {e:?}");
+                }
+
+                // withdrawals and pot withdrawals that were being sent when the bot stopped
+                let withdrawal_client = Arc::new(vrsc_rpc::Client::vrsc(
+                    config.application.testnet,
+                    vrsc_rpc::Auth::UserPass(
+                        format!("http://127.0.0.1:{}", config.application.rpc_port),
+                        config.application.rpc_user.clone(),
+                        config.application.rpc_password.clone(),
+                    ),
+                )?);
+                if let Err(e) = wallet::resolve_withdrawals(&pool, &withdrawal_client).await {
+                    error!("could not resolve withdrawals: {e:?}");
+                }
+                if let Err(e) = pot::resolve_withdrawals(&pool, &withdrawal_client).await {
+                    error!("could not resolve pot withdrawals: {e:?}");
+                }
+
                 let tx_proc_clone = tx_proc.clone();
                 tokio::spawn(async move {
                     tx_proc_clone.clone().listen_wallet_notifications().await;
@@ -202,9 +345,117 @@ async fn app() -> Result<(), Error> {
 
                 info!("listening for daemon notifications");
 
+                let degraded = Arc::new(RwLock::new(false));
+                let sync = Arc::new(RwLock::new(None));
+                let health_client = vrsc_rpc::Client::vrsc(
+                    config.application.testnet,
+                    vrsc_rpc::Auth::UserPass(
+                        format!("http://127.0.0.1:{}", config.application.rpc_port),
+                        config.application.rpc_user.clone(),
+                        config.application.rpc_password.clone(),
+                    ),
+                )?;
+                tokio::spawn(health::monitor(
+                    health_client,
+                    degraded.clone(),
+                    sync.clone(),
+                ));
+
+                info!("monitoring daemon health");
+
+                tokio::spawn(balance_history::snapshot_nightly(pool.clone()));
+                tokio::spawn(ledger::check_periodically(pool.clone()));
+                tokio::spawn(competition::finish_periodically(
+                    http.clone(),
+                    pool.clone(),
+                    translations.clone(),
+                    shutdown.clone(),
+                ));
+                tokio::spawn(delivery::flush_batches(
+                    http.clone(),
+                    pool.clone(),
+                    translations.clone(),
+                ));
+                tokio::spawn(delivery::deliver_outbox(
+                    http.clone(),
+                    pool.clone(),
+                    translations.clone(),
+                ));
+                tokio::spawn(delivery::retry_failed_notifications(
+                    http.clone(),
+                    pool.clone(),
+                    translations.clone(),
+                ));
+                tokio::spawn(mod_log::post_periodically(
+                    http.clone(),
+                    pool.clone(),
+                    translations.clone(),
+                ));
+
+                let sampler_client = vrsc_rpc::Client::vrsc(
+                    config.application.testnet,
+                    vrsc_rpc::Auth::UserPass(
+                        format!("http://127.0.0.1:{}", config.application.rpc_port),
+                        config.application.rpc_user.clone(),
+                        config.application.rpc_password.clone(),
+                    ),
+                )?;
+                let digest_client = vrsc_rpc::Client::vrsc(
+                    config.application.testnet,
+                    vrsc_rpc::Auth::UserPass(
+                        format!("http://127.0.0.1:{}", config.application.rpc_port),
+                        config.application.rpc_user.clone(),
+                        config.application.rpc_password.clone(),
+                    ),
+                )?;
+                tokio::spawn(network_digest::post_periodically(
+                    digest_client,
+                    http.clone(),
+                    pool.clone(),
+                    translations.clone(),
+                ));
+
+                tokio::spawn(news::relay_periodically(
+                    http.clone(),
+                    pool.clone(),
+                    config.application.news_feeds.clone(),
+                ));
+                tokio::spawn(liability_alert::check_periodically(
+                    http.clone(),
+                    pool.clone(),
+                    config.application.liability_alerts.clone(),
+                    owners_clone.iter().copied().collect(),
+                ));
+                tokio::spawn(currency_history::sample_periodically(
+                    sampler_client,
+                    pool.clone(),
+                    config.application.basket_currencies.clone(),
+                ));
+
                 let withdrawal_fee =
                     Arc::new(RwLock::new(config.application.global_withdrawal_fee));
 
+                let user_languages = database::get_user_languages(&pool)
+                    .await?
+                    .into_iter()
+                    .collect::<HashMap<UserId, String>>();
+
+                let withdrawals_frozen =
+                    Arc::new(RwLock::new(database::get_withdrawals_frozen(&pool).await?));
+
+                let mut tip_channels: HashMap<GuildId, HashSet<ChannelId>> = HashMap::new();
+                for (guild_id, channel_id) in database::get_tip_channels(&pool).await? {
+                    tip_channels.entry(guild_id).or_default().insert(channel_id);
+                }
+
+                let prices =
+                    TtlCache::new(Duration::from_secs(config.application.price_cache_secs));
+                let chain_info =
+                    TtlCache::new(Duration::from_secs(config.application.chaininfo_cache_secs));
+                let currency_names = TtlCache::new(Duration::from_secs(
+                    config.application.currency_name_cache_secs,
+                ));
+
                 Ok(Data {
                     // maintenance: Arc::new(RwLock::new(false)),
                     _verus: client.unwrap(),
@@ -214,11 +465,20 @@ async fn app() -> Result<(), Error> {
                     database,
                     withdrawal_fee,
                     withdrawals_enabled: Arc::new(RwLock::new(true)),
+                    withdrawals_frozen,
                     deposits_enabled,
+                    degraded,
+                    sync,
                     blacklist: std::sync::Mutex::new(HashSet::new()),
                     tx_processor: tx_proc,
                     owners: owners_clone,
-                    currency_names: HashMap::new(),
+                    prices,
+                    chain_info,
+                    currency_names,
+                    translations,
+                    user_languages: std::sync::RwLock::new(user_languages),
+                    tip_channels: std::sync::RwLock::new(tip_channels),
+                    shutdown,
                 })
             })
         })
@@ -229,9 +489,35 @@ async fn app() -> Result<(), Error> {
                 | serenity::GatewayIntents::MESSAGE_CONTENT
                 | serenity::GatewayIntents::GUILD_PRESENCES,
         )
-        .run()
+        .build()
         .await?;
 
+    let shard_manager = framework.shard_manager().clone();
+    let shutdown_http = framework.client().cache_and_http.http.clone();
+    let flush_pool = shutdown_pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = shutdown::signal_received().await {
+            error!("could not listen for signals: {e:?}");
+
+            return;
+        }
+
+        info!("shutting down");
+        if !shutdown.drain(shutdown::DRAIN_TIMEOUT).await {
+            warn!(
+                "operations were still in flight after {:?}",
+                shutdown::DRAIN_TIMEOUT
+            );
+        }
+        delivery::flush_all_batches(&shutdown_http, &flush_pool, &shutdown_translations).await;
+        mod_log::post_queued(&shutdown_http, &flush_pool, &shutdown_translations).await;
+        shard_manager.lock().await.shutdown_all().await;
+    });
+
+    framework.start().await?;
+    shutdown_pool.close().await;
+    info!("shut down");
+
     Ok(())
 }
 
@@ -239,11 +525,39 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     info!("Encountered error: {:?}", error);
 
     match error {
+        // a re-delivered or retried interaction: the first one did the work, so there is nothing to report
+        poise::FrameworkError::Command { ctx, error } if database::is_already_processed(&error) => {
+            if let Err(e) = ctx
+                .send(|reply| {
+                    reply
+                        .ephemeral(true)
+                        .content(tr!(ctx, "error-already-processed"))
+                })
+                .await
+            {
+                debug!("could not respond to an interaction that was already processed: {e:?}");
+            }
+        }
+        // the daemon could not be reached, which the admins learn about from the logs and the health monitor
+        poise::FrameworkError::Command { ctx, error }
+            if rpc_guard::is_chain_unavailable(&error) =>
+        {
+            if let Err(e) = ctx
+                .send(|reply| {
+                    reply
+                        .ephemeral(true)
+                        .content(tr!(ctx, "error-chain-unavailable"))
+                })
+                .await
+            {
+                debug!("could not respond that the chain is unavailable: {e:?}");
+            }
+        }
         poise::FrameworkError::Command { ctx, error } => {
             let owners = &ctx.data().owners;
             let s = owners
-                .into_iter()
-                .map(|id| format!("<@{}>", id.0.to_string()))
+                .iter()
+                .map(|id| format!("<@{}>", id.0))
                 .collect::<Vec<_>>()
                 .join(", ");
 
@@ -301,15 +615,29 @@ pub struct Data {
     database: sqlx::PgPool,
     withdrawal_fee: Arc<RwLock<Amount>>,
     withdrawals_enabled: Arc<RwLock<bool>>,
+    /// Set by the owners with `/wallet freeze`, and kept across restarts
+    withdrawals_frozen: Arc<RwLock<bool>>,
     deposits_enabled: Arc<RwLock<bool>>,
+    /// Set by the health monitor when the Verus daemon can not be reached
+    degraded: Arc<RwLock<bool>>,
+    /// Set by the health monitor while the Verus daemon is reindexing or syncing
+    sync: Arc<RwLock<Option<health::SyncProgress>>>,
     blacklist: std::sync::Mutex<HashSet<UserId>>,
     tx_processor: Arc<TransactionProcessor>,
     owners: HashSet<UserId>,
-    currency_names: HashMap<Address, String>,
+    /// The price of VRSC from all sources, for `/price` and `/currency`
+    prices: TtlCache<(), price::Prices>,
+    chain_info: TtlCache<(), chain::ChainInfo>,
+    currency_names: TtlCache<Address, String>,
+    translations: Arc<Translations>,
+    user_languages: std::sync::RwLock<HashMap<UserId, String>>,
+    /// The channels that allow tipping, for the guilds that restrict it, see `/admin channels`
+    tip_channels: std::sync::RwLock<HashMap<GuildId, HashSet<ChannelId>>>,
+    shutdown: Arc<Shutdown>,
 }
 
 impl Data {
-    pub fn verus(&self) -> Result<VerusClient, Error> {
+    pub fn verus(&self) -> Result<Arc<VerusClient>, Error> {
         vrsc_rpc::Client::vrsc(
             self.settings.application.testnet,
             vrsc_rpc::Auth::UserPass(
@@ -318,24 +646,30 @@ impl Data {
                 self.settings.application.rpc_password.clone(),
             ),
         )
+        .map(Arc::new)
         .map_err(|e| e.into())
     }
 
-    // TODO: cow?
-    pub fn to_currency_name(&self, address: &Address) -> Result<String, Error> {
+    pub async fn to_currency_name(&self, address: &Address) -> Result<String, Error> {
         if let Some(name) = self.currency_names.get(address) {
             trace!("name is known in currency_names");
-            return Ok(name.to_owned());
+            Ok(name)
         } else {
             trace!("name is unknown in currency_names");
             let client = self.verus()?;
             trace!("got client");
 
             debug!("address: {:?}", &address);
-            let currency = client.get_currency(&address.to_string()).unwrap();
+            let currency_address = address.to_string();
+            let currency = rpc_guard::call(&client, "getcurrency", move |client| {
+                client.get_currency(&currency_address)
+            })
+            .await?;
             debug!("{currency:?}");
             let currency_name = currency.fullyqualifiedname;
-            return Ok(currency_name);
+            self.currency_names
+                .insert(address.clone(), currency_name.clone());
+            Ok(currency_name)
         }
     }
 }
@@ -0,0 +1,196 @@
+//! Prometheus metrics.
+//!
+//! When `metrics_bind_address` is set in the config, a small HTTP server is started that serves all metrics on
+//! `/metrics`. Without it the metrics are still collected, but nobody can scrape them.
+
+use std::net::SocketAddr;
+
+use axum::{http::header, response::IntoResponse, routing::get, Router};
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+use tracing::{error, info};
+use vrsc::Amount;
+
+use crate::Error;
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+pub static COMMANDS_EXECUTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new("verusbot_commands_total", "Number of commands executed"),
+        &["command"],
+    )
+    .unwrap()
+});
+
+pub static TIPS_SENT: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new("verusbot_tips_total", "Number of tips sent, per tip kind"),
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub static TIPPED_SATS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "verusbot_tipped_sats_total",
+            "Total amount tipped in sats, per tip kind",
+        ),
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub static RPC_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "verusbot_rpc_duration_seconds",
+            "Latency of RPC calls to the Verus daemon",
+        ),
+        &["method"],
+    )
+    .unwrap()
+});
+
+pub static DB_QUERY_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "verusbot_db_query_duration_seconds",
+            "Latency of database queries",
+        ),
+        &["query"],
+    )
+    .unwrap()
+});
+
+pub static DEPOSITS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new("verusbot_deposits_total", "Number of processed deposits").unwrap()
+});
+
+pub static DEPOSITED_SATS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "verusbot_deposited_sats_total",
+        "Total amount deposited in sats",
+    )
+    .unwrap()
+});
+
+pub static WITHDRAWALS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "verusbot_withdrawals_total",
+        "Number of processed withdrawals",
+    )
+    .unwrap()
+});
+
+pub static WITHDRAWN_SATS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "verusbot_withdrawn_sats_total",
+        "Total amount withdrawn in sats, excluding fees",
+    )
+    .unwrap()
+});
+
+pub static ACTIVE_REACTDROPS: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "verusbot_active_reactdrops",
+        "Number of reactdrops that have not been paid out yet",
+    )
+    .unwrap()
+});
+
+pub static LEDGER_MISMATCHES: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "verusbot_ledger_mismatches",
+        "Number of balances and ledger events that did not match the ledger at the last check",
+    )
+    .unwrap()
+});
+
+pub static RPC_CIRCUIT_OPEN: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "verusbot_rpc_circuit_open",
+        "1 while calls to the Verus daemon fail right away because it could not be reached",
+    )
+    .unwrap()
+});
+
+/// Registers all metrics. Needs to be called once at startup.
+pub fn register() -> Result<(), Error> {
+    REGISTRY.register(Box::new(COMMANDS_EXECUTED.clone()))?;
+    REGISTRY.register(Box::new(TIPS_SENT.clone()))?;
+    REGISTRY.register(Box::new(TIPPED_SATS.clone()))?;
+    REGISTRY.register(Box::new(RPC_DURATION.clone()))?;
+    REGISTRY.register(Box::new(DB_QUERY_DURATION.clone()))?;
+    REGISTRY.register(Box::new(DEPOSITS.clone()))?;
+    REGISTRY.register(Box::new(DEPOSITED_SATS.clone()))?;
+    REGISTRY.register(Box::new(WITHDRAWALS.clone()))?;
+    REGISTRY.register(Box::new(WITHDRAWN_SATS.clone()))?;
+    REGISTRY.register(Box::new(ACTIVE_REACTDROPS.clone()))?;
+    REGISTRY.register(Box::new(LEDGER_MISMATCHES.clone()))?;
+    REGISTRY.register(Box::new(RPC_CIRCUIT_OPEN.clone()))?;
+
+    Ok(())
+}
+
+/// Calls the daemon and records how long the call took.
+pub fn observe_rpc<T>(method: &str, call: impl FnOnce() -> T) -> T {
+    let _timer = RPC_DURATION.with_label_values(&[method]).start_timer();
+
+    call()
+}
+
+/// Starts a timer for a database query. The duration is recorded when the returned timer is dropped.
+pub fn db_timer(query: &str) -> HistogramTimer {
+    DB_QUERY_DURATION.with_label_values(&[query]).start_timer()
+}
+
+pub fn record_tip(kind: &str, number_of_tips: usize, total: Amount) {
+    TIPS_SENT
+        .with_label_values(&[kind])
+        .inc_by(number_of_tips as u64);
+    TIPPED_SATS
+        .with_label_values(&[kind])
+        .inc_by(total.as_sat());
+}
+
+pub fn record_deposit(amount: Amount) {
+    DEPOSITS.inc();
+    DEPOSITED_SATS.inc_by(amount.as_sat());
+}
+
+pub fn record_withdrawal(amount: Amount) {
+    WITHDRAWALS.inc();
+    WITHDRAWN_SATS.inc_by(amount.as_sat());
+}
+
+pub async fn serve(address: SocketAddr) {
+    let app = Router::new().route("/metrics", get(metrics));
+
+    info!("serving metrics on {address}");
+
+    if let Err(e) = axum::Server::bind(&address)
+        .serve(app.into_make_service())
+        .await
+    {
+        error!("metrics server stopped: {e:?}");
+    }
+}
+
+async fn metrics() -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+
+    if let Err(e) = encoder.encode(&REGISTRY.gather(), &mut buffer) {
+        error!("could not encode metrics: {e:?}");
+    }
+
+    (
+        [(header::CONTENT_TYPE, encoder.format_type().to_owned())],
+        buffer,
+    )
+}
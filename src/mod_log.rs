@@ -0,0 +1,305 @@
+//! The mod-log: a channel where a guild sees the events that affect balances, for its moderators.
+//!
+//! Guilds turn it on with `/config modlog`. Withdrawals, sweeps of the hot wallet, tips of at least the threshold of
+//! the guild, blacklist changes and balances that don't match the ledger are posted there as embeds.
+//!
+//! The functions that store such an event, like [`database::tip_transactionally`] and [`database::settle_withdrawal`],
+//! queue it with [`dispatch`] in the same database transaction, so every event that happened is posted and an event
+//! is never posted for a change that was rolled back. [`post_periodically`] posts the queue, and it is posted one last
+//! time on shutdown with [`post_queued`]. An event that happened in a guild goes to the mod-log of that guild. An
+//! event without a guild, like a withdrawal in DM or a blacklist change by a bot admin, goes to the mod-logs of all
+//! guilds the user is a member of.
+
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{ChannelId, Colour, GuildId, Http, UserId};
+use sqlx::{PgConnection, PgPool};
+use tokio::time::interval;
+use tracing::{error, warn};
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    i18n::{tr_locale, Translations},
+    util::{database, format},
+    Error,
+};
+
+const POST_INTERVAL: Duration = Duration::from_secs(5);
+/// The most events that are taken off the queue at once.
+const BATCH_SIZE: i64 = 100;
+
+/// Tips of at least this amount (in satoshis) are posted in the mod-log of guilds that did not set their own threshold.
+pub const DEFAULT_TIP_THRESHOLD: u64 = 100 * 100_000_000;
+
+/// An event that is posted in the mod-log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModLogEvent {
+    Withdrawal {
+        user_id: UserId,
+        amount: Amount,
+        fees: Amount,
+        txid: Option<String>,
+    },
+    /// A sweep of the hot wallet to cold storage with `/wallet sweep`, by `user_id`
+    Sweep {
+        user_id: UserId,
+        amount: Amount,
+        fees: Amount,
+        opid: String,
+        destination: String,
+    },
+    /// A tip, which is only posted when it is at least the threshold of the guild
+    Tip {
+        tipper: UserId,
+        recipients: usize,
+        amount: Amount,
+        tip_event_id: Uuid,
+    },
+    Blacklist {
+        user_id: UserId,
+        blacklisted: bool,
+        actor: UserId,
+    },
+    /// A balance that does not match the ledger, posted every hour until it is resolved
+    BalanceMismatch {
+        user_id: UserId,
+        balance: i64,
+        ledger: i64,
+    },
+}
+
+impl ModLogEvent {
+    /// The user the event is about.
+    fn user_id(&self) -> UserId {
+        match self {
+            Self::Withdrawal { user_id, .. } => *user_id,
+            Self::Sweep { user_id, .. } => *user_id,
+            Self::Tip { tipper, .. } => *tipper,
+            Self::Blacklist { user_id, .. } => *user_id,
+            Self::BalanceMismatch { user_id, .. } => *user_id,
+        }
+    }
+
+    /// Whether a guild with the tip threshold `threshold` wants this event in its mod-log.
+    fn is_logged(&self, threshold: Option<Amount>) -> bool {
+        match self {
+            Self::Tip { amount, .. } => {
+                *amount >= threshold.unwrap_or(Amount::from_sat(DEFAULT_TIP_THRESHOLD))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Queues `event` to be posted in the mod-log of `guild_id`, or of all guilds of the user when it has no guild. When
+/// `conn` is in a transaction, the event is only queued when the transaction is committed.
+pub async fn dispatch(
+    conn: &mut PgConnection,
+    guild_id: Option<GuildId>,
+    event: ModLogEvent,
+) -> Result<(), Error> {
+    database::store_mod_log_event(conn, guild_id, &event).await
+}
+
+/// Posts the queued events, every [`POST_INTERVAL`].
+pub async fn post_periodically(http: Arc<Http>, pool: PgPool, translations: Arc<Translations>) {
+    let mut interval = interval(POST_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        post_queued(&http, &pool, &translations).await;
+    }
+}
+
+/// Posts every queued event. An event that could not be posted is logged and dropped.
+pub async fn post_queued(http: &Http, pool: &PgPool, translations: &Translations) {
+    loop {
+        let events = match database::take_mod_log_events(pool, BATCH_SIZE).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!("could not take the queued mod-log events: {e:?}");
+                return;
+            }
+        };
+        let done = (events.len() as i64) < BATCH_SIZE;
+
+        for (guild_id, event) in events {
+            if let Err(e) = post(http, pool, translations, guild_id, &event).await {
+                error!("could not post {event:?} in the mod-log: {e:?}");
+            }
+        }
+
+        if done {
+            return;
+        }
+    }
+}
+
+async fn post(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    guild_id: Option<GuildId>,
+    event: &ModLogEvent,
+) -> Result<(), Error> {
+    let channels = match guild_id {
+        Some(guild_id) => match database::get_mod_log(pool, guild_id).await? {
+            Some((channel_id, threshold)) if event.is_logged(threshold) => vec![channel_id],
+            _ => vec![],
+        },
+        None => {
+            let mut channels = vec![];
+            for (guild_id, channel_id, threshold) in database::get_mod_logs(pool).await? {
+                if event.is_logged(threshold)
+                    && guild_id.member(http, event.user_id()).await.is_ok()
+                {
+                    channels.push(channel_id);
+                }
+            }
+
+            channels
+        }
+    };
+
+    for channel_id in channels {
+        if let Err(e) = send(http, translations, channel_id, event).await {
+            warn!("could not post in mod-log {channel_id}: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn send(
+    http: &Http,
+    translations: &Translations,
+    channel_id: ChannelId,
+    event: &ModLogEvent,
+) -> Result<(), Error> {
+    let tr = |key: &str| tr_locale!(translations, None, key);
+    let user = |user_id: &UserId| format!("<@{user_id}>");
+
+    let (title, colour, fields) = match event {
+        ModLogEvent::Withdrawal {
+            user_id,
+            amount,
+            fees,
+            txid,
+        } => {
+            let mut fields = vec![
+                (tr("mod-log-user"), user(user_id)),
+                (tr("mod-log-amount"), format::amount(*amount)),
+                (tr("mod-log-fees"), format::amount(*fees)),
+            ];
+            if let Some(txid) = txid {
+                fields.push((
+                    tr("mod-log-transaction"),
+                    format!("[{txid}](https://insight.verus.io/tx/{txid})"),
+                ));
+            }
+
+            (tr("mod-log-withdrawal"), Colour::ORANGE, fields)
+        }
+        ModLogEvent::Sweep {
+            user_id,
+            amount,
+            fees,
+            opid,
+            destination,
+        } => (
+            tr("mod-log-sweep"),
+            Colour::PURPLE,
+            vec![
+                (tr("mod-log-actor"), user(user_id)),
+                (tr("mod-log-amount"), format::amount(*amount)),
+                (tr("mod-log-fees"), format::amount(*fees)),
+                (tr("mod-log-destination"), format!("`{destination}`")),
+                (tr("mod-log-operation"), format!("`{opid}`")),
+            ],
+        ),
+        ModLogEvent::Tip {
+            tipper,
+            recipients,
+            amount,
+            tip_event_id,
+        } => (
+            tr("mod-log-tip"),
+            Colour::BLUE,
+            vec![
+                (tr("mod-log-user"), user(tipper)),
+                (tr("mod-log-amount"), format::amount(*amount)),
+                (tr("mod-log-recipients"), recipients.to_string()),
+                (tr("mod-log-tip-id"), format!("`{tip_event_id}`")),
+            ],
+        ),
+        ModLogEvent::Blacklist {
+            user_id,
+            blacklisted,
+            actor,
+        } => (
+            match blacklisted {
+                true => tr("mod-log-blacklisted"),
+                false => tr("mod-log-unblacklisted"),
+            },
+            Colour::RED,
+            vec![
+                (tr("mod-log-user"), user(user_id)),
+                (tr("mod-log-actor"), user(actor)),
+            ],
+        ),
+        ModLogEvent::BalanceMismatch {
+            user_id,
+            balance,
+            ledger,
+        } => (
+            tr("mod-log-mismatch"),
+            Colour::RED,
+            vec![
+                (tr("mod-log-user"), user(user_id)),
+                // in sats, as a broken balance can be negative
+                (tr("mod-log-balance"), format!("{balance} sats")),
+                (tr("mod-log-ledger"), format!("{ledger} sats")),
+            ],
+        ),
+    };
+
+    channel_id
+        .send_message(http, |message| {
+            message.embed(|embed| {
+                embed
+                    .title(title)
+                    .colour(colour)
+                    .fields(fields.into_iter().map(|(name, value)| (name, value, true)))
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_tips_above_the_threshold_are_logged() {
+        let tip = |sats| ModLogEvent::Tip {
+            tipper: UserId(1),
+            recipients: 1,
+            amount: Amount::from_sat(sats),
+            tip_event_id: Uuid::nil(),
+        };
+
+        assert!(tip(DEFAULT_TIP_THRESHOLD).is_logged(None));
+        assert!(!tip(DEFAULT_TIP_THRESHOLD - 1).is_logged(None));
+        assert!(tip(500).is_logged(Some(Amount::from_sat(500))));
+        assert!(!tip(499).is_logged(Some(Amount::from_sat(500))));
+        assert!(ModLogEvent::Blacklist {
+            user_id: UserId(1),
+            blacklisted: true,
+            actor: UserId(2)
+        }
+        .is_logged(Some(Amount::from_sat(u64::MAX))));
+    }
+}
@@ -0,0 +1,277 @@
+//! Periodic digests of the Verus network, posted in the channels guilds subscribed with `/admin announce-blocks`.
+//!
+//! Every [`DIGEST_INTERVAL`] the daemon is polled for the new blocks since the previous digest. A digest shows the
+//! block milestones that were passed, how the difficulty and the staking supply changed, and the transactions in the
+//! new blocks that moved more than the threshold of the guild. The value of a transaction is the sum of its outputs,
+//! change included.
+
+use std::{cmp::Reverse, sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{ChannelId, Http};
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{debug, error, warn};
+use vrsc::Amount;
+use vrsc_rpc::{bitcoin::Txid, Client as VerusClient, RpcApi};
+
+use crate::{
+    i18n::{tr_locale, Translations},
+    metrics,
+    util::{database, format},
+    Error,
+};
+
+pub const DIGEST_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Block heights that are a multiple of this are milestones.
+const MILESTONE_EVERY: u64 = 100_000;
+/// The most blocks scanned for large transactions per digest, to bound the number of RPC calls after a long downtime.
+const MAX_SCANNED_BLOCKS: u64 = 240;
+/// The most large transactions listed in a digest.
+const MAX_LISTED_TRANSACTIONS: usize = 10;
+/// The threshold for large transactions (in satoshis) when a guild did not set one.
+pub const DEFAULT_LARGE_TX_THRESHOLD: u64 = 10_000 * 100_000_000;
+
+#[derive(Debug)]
+struct NetworkState {
+    height: u64,
+    difficulty: f64,
+    staking_supply: f64,
+}
+
+/// The block heights that are milestones in `(from, to]`.
+fn milestones(from: u64, to: u64) -> Vec<u64> {
+    (from / MILESTONE_EVERY + 1..=to / MILESTONE_EVERY)
+        .map(|milestone| milestone * MILESTONE_EVERY)
+        .collect()
+}
+
+/// The change from `old` to `new` in percent.
+fn percent_change(old: f64, new: f64) -> f64 {
+    if old == 0.0 {
+        return 0.0;
+    }
+
+    (new - old) / old * 100.0
+}
+
+fn network_state(client: &VerusClient) -> Result<NetworkState, Error> {
+    let blockchain_info =
+        metrics::observe_rpc("getblockchaininfo", || client.get_blockchain_info())?;
+    let mining_info = metrics::observe_rpc("getmininginfo", || client.get_mining_info())?;
+
+    Ok(NetworkState {
+        height: blockchain_info.blocks,
+        difficulty: blockchain_info.difficulty,
+        staking_supply: mining_info.stakingsupply,
+    })
+}
+
+/// The transactions in the blocks `(from, to]` that moved at least `threshold`, largest first.
+fn large_transactions(
+    client: &VerusClient,
+    from: u64,
+    to: u64,
+    threshold: Amount,
+) -> Result<Vec<(Txid, Amount)>, Error> {
+    let mut transactions = vec![];
+
+    for height in to.saturating_sub(MAX_SCANNED_BLOCKS).max(from) + 1..=to {
+        // verbosity 2 includes the inputs and outputs of the transactions
+        let block = metrics::observe_rpc("getblock", || client.get_block_by_height(height, 2))?;
+
+        for tx in block.tx {
+            // coinbase and staking rewards have no previous output
+            if tx.vin.iter().all(|vin| vin.txid.is_none()) {
+                continue;
+            }
+
+            let value = tx
+                .vout
+                .iter()
+                .fold(Amount::ZERO, |total, vout| total + vout.value_sat);
+            if value >= threshold {
+                transactions.push((tx.txid, value));
+            }
+        }
+    }
+
+    transactions.sort_by_key(|(_, amount)| Reverse(*amount));
+
+    Ok(transactions)
+}
+
+/// Posts a digest in every subscribed channel every [`DIGEST_INTERVAL`].
+pub async fn post_periodically(
+    client: VerusClient,
+    http: Arc<Http>,
+    pool: PgPool,
+    translations: Arc<Translations>,
+) {
+    let mut interval = interval(DIGEST_INTERVAL);
+    // the first tick completes immediately, which gives the state the first digest is compared to.
+    interval.tick().await;
+    let mut previous = match network_state(&client) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            warn!("could not get the network state: {e:?}");
+            None
+        }
+    };
+
+    loop {
+        interval.tick().await;
+
+        let current = match network_state(&client) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("could not get the network state: {e:?}");
+                continue;
+            }
+        };
+
+        if let Some(previous) = previous.as_ref() {
+            if let Err(e) =
+                post_digest(&client, &http, &pool, &translations, previous, &current).await
+            {
+                error!("could not post the network digest: {e:?}");
+            }
+        }
+
+        previous = Some(current);
+    }
+}
+
+async fn post_digest(
+    client: &VerusClient,
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    previous: &NetworkState,
+    current: &NetworkState,
+) -> Result<(), Error> {
+    let subscriptions = database::get_network_subscriptions(pool).await?;
+    let Some(lowest_threshold) = subscriptions
+        .iter()
+        .map(|(_, _, threshold)| *threshold)
+        .min()
+    else {
+        debug!("no guilds subscribed to the network digest");
+        return Ok(());
+    };
+
+    let transactions =
+        large_transactions(client, previous.height, current.height, lowest_threshold)?;
+
+    let title = tr_locale!(translations, None, "digest-title");
+    let height = tr_locale!(
+        translations,
+        None,
+        "digest-height",
+        height: current.height,
+        blocks: current.height.saturating_sub(previous.height)
+    );
+    let milestones = milestones(previous.height, current.height)
+        .into_iter()
+        .map(|milestone| tr_locale!(translations, None, "digest-milestone", height: milestone))
+        .collect::<Vec<_>>();
+    let difficulty = tr_locale!(
+        translations,
+        None,
+        "digest-difficulty",
+        difficulty: format::number(current.difficulty, 0),
+        change: format::number(percent_change(previous.difficulty, current.difficulty), 2)
+    );
+    let staking = tr_locale!(
+        translations,
+        None,
+        "digest-staking",
+        supply: format::amount(Amount::from_vrsc(current.staking_supply).unwrap_or(Amount::ZERO)),
+        change: format::number(
+            percent_change(previous.staking_supply, current.staking_supply),
+            2
+        )
+    );
+
+    for (guild_id, channel_id, threshold) in subscriptions {
+        let large = transactions
+            .iter()
+            .filter(|(_, value)| *value >= threshold)
+            .take(MAX_LISTED_TRANSACTIONS)
+            .map(|(txid, value)| format!("`{txid}`: {}", format::amount(*value)))
+            .collect::<Vec<_>>();
+        let large = match large.is_empty() {
+            true => tr_locale!(
+                translations,
+                None,
+                "digest-no-large-transactions",
+                threshold: format::amount(threshold)
+            ),
+            false => large.join("\n"),
+        };
+
+        let mut description = vec![height.clone()];
+        description.extend(milestones.iter().cloned());
+        description.push(difficulty.clone());
+        description.push(staking.clone());
+
+        if let Err(e) = post(
+            http,
+            channel_id,
+            &title,
+            &description.join("\n"),
+            &large,
+            translations,
+        )
+        .await
+        {
+            warn!("could not post the network digest for {guild_id} in {channel_id}: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn post(
+    http: &Http,
+    channel_id: ChannelId,
+    title: &str,
+    description: &str,
+    large_transactions: &str,
+    translations: &Translations,
+) -> Result<(), Error> {
+    channel_id
+        .send_message(http, |message| {
+            message.embed(|embed| {
+                embed.title(title).description(description).field(
+                    tr_locale!(translations, None, "digest-large-transactions"),
+                    large_transactions,
+                    false,
+                )
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn milestones_between_two_heights() {
+        assert_eq!(milestones(2_699_990, 2_700_010), vec![2_700_000]);
+        assert!(milestones(2_700_000, 2_700_010).is_empty());
+        assert_eq!(
+            milestones(2_599_999, 2_800_000),
+            vec![2_600_000, 2_700_000, 2_800_000]
+        );
+        assert!(milestones(10, 10).is_empty());
+    }
+
+    #[test]
+    fn percent_change_of_zero_is_zero() {
+        assert_eq!(percent_change(200.0, 250.0), 25.0);
+        assert_eq!(percent_change(0.0, 250.0), 0.0);
+    }
+}
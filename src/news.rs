@@ -0,0 +1,307 @@
+//! Verus news, relayed to the channels guilds subscribed with `/config news`.
+//!
+//! The operators of the bot pick the feeds in the `news_feeds` setting: RSS or Atom feeds, like the Verus blog, and
+//! the releases of GitHub repositories. Every feed has a topic, and guilds can choose the topics they want. Every
+//! [`POLL_INTERVAL`] the feeds are fetched and the items that were not seen before are relayed. The items a feed has
+//! the first time it is fetched are only marked as seen, so adding a feed does not flood the channels with its
+//! history. Operators can relay news of their own with `/postnews`.
+
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude::Http;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::time::interval;
+use tracing::{debug, error, warn};
+
+use crate::{
+    configuration::{NewsFeed, NewsFormat},
+    util::database,
+    Error,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// The most items of a single feed that are relayed per poll, the newest ones.
+const MAX_ITEMS_PER_POLL: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewsItem {
+    /// Identifies the item within its feed
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    id: u64,
+    name: Option<String>,
+    tag_name: String,
+    html_url: String,
+    draft: bool,
+}
+
+/// Fetches the feeds and relays their new items, every [`POLL_INTERVAL`].
+pub async fn relay_periodically(http: Arc<Http>, pool: PgPool, feeds: Vec<NewsFeed>) {
+    if feeds.is_empty() {
+        debug!("no news feeds configured");
+        return;
+    }
+
+    let client = reqwest::Client::new();
+    let mut interval = interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        for feed in &feeds {
+            if let Err(e) = relay_feed(&http, &pool, &client, feed).await {
+                warn!("could not relay the news feed {}: {e:?}", feed.url);
+            }
+        }
+    }
+}
+
+async fn relay_feed(
+    http: &Http,
+    pool: &PgPool,
+    client: &reqwest::Client,
+    feed: &NewsFeed,
+) -> Result<(), Error> {
+    let items = fetch(client, feed).await?;
+    let ids = items.iter().map(|item| item.id.clone()).collect::<Vec<_>>();
+    let new = database::store_news_items(pool, &feed.url, &ids).await?;
+
+    // feeds list their newest items first, which are posted last so they end up at the bottom of the channel.
+    let mut new_items = items
+        .into_iter()
+        .filter(|item| new.contains(&item.id))
+        .take(MAX_ITEMS_PER_POLL)
+        .collect::<Vec<_>>();
+    new_items.reverse();
+
+    for item in new_items {
+        relay(http, pool, &feed.topic, &item).await?;
+    }
+
+    Ok(())
+}
+
+/// Posts `item` in the channels of the guilds that want the news of `topic`. Returns the number of channels it was
+/// posted in.
+pub async fn relay(
+    http: &Http,
+    pool: &PgPool,
+    topic: &str,
+    item: &NewsItem,
+) -> Result<usize, Error> {
+    let channels = database::get_news_channels(pool, topic).await?;
+    let mut posted = 0;
+
+    for channel_id in channels {
+        match channel_id
+            .send_message(http, |message| {
+                message.embed(|embed| {
+                    embed.title(&item.title).footer(|footer| footer.text(topic));
+                    if let Some(link) = &item.link {
+                        embed.url(link);
+                    }
+
+                    embed
+                })
+            })
+            .await
+        {
+            Ok(_) => posted += 1,
+            Err(e) => error!(
+                "could not post news item {} in {channel_id}: {e:?}",
+                item.id
+            ),
+        }
+    }
+
+    Ok(posted)
+}
+
+async fn fetch(client: &reqwest::Client, feed: &NewsFeed) -> Result<Vec<NewsItem>, Error> {
+    let response = client
+        .get(&feed.url)
+        // the GitHub API rejects requests without a user agent
+        .header(reqwest::header::USER_AGENT, "verusbot")
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    match feed.format {
+        NewsFormat::Rss => Ok(parse_feed(&response.text().await?)),
+        NewsFormat::GithubReleases => {
+            let releases: Vec<GithubRelease> = response.json().await?;
+
+            Ok(releases
+                .into_iter()
+                .filter(|release| !release.draft)
+                .map(|release| NewsItem {
+                    id: release.id.to_string(),
+                    title: release
+                        .name
+                        .filter(|name| !name.trim().is_empty())
+                        .unwrap_or(release.tag_name),
+                    link: Some(release.html_url),
+                })
+                .collect())
+        }
+    }
+}
+
+/// The items of an RSS (`<item>`) or Atom (`<entry>`) feed, in the order of the feed. Items without a title are left
+/// out.
+fn parse_feed(xml: &str) -> Vec<NewsItem> {
+    let tag = match xml.contains("<entry") {
+        true => "entry",
+        false => "item",
+    };
+
+    elements(xml, tag)
+        .into_iter()
+        .filter_map(|item| {
+            let title = text_of(item, "title")?;
+            // RSS has the link as text, Atom in the href attribute
+            let link = text_of(item, "link")
+                .filter(|link| !link.is_empty())
+                .or_else(|| attribute_of(item, "link", "href"));
+            let id = text_of(item, "guid")
+                .or_else(|| text_of(item, "id"))
+                .or_else(|| link.clone())
+                .unwrap_or_else(|| title.clone());
+
+            Some(NewsItem { id, title, link })
+        })
+        .collect()
+}
+
+/// The start of an element named `tag`, e.g. `<link>` or `<link href="..">` but not `<linkx>`.
+fn find_start(xml: &str, tag: &str) -> Option<usize> {
+    let open = format!("<{tag}");
+    let mut from = 0;
+
+    while let Some(found) = xml[from..].find(&open) {
+        let start = from + found;
+        let next = xml[start + open.len()..].chars().next();
+        if matches!(next, Some('>' | '/') | Some(' ' | '\t' | '\r' | '\n')) {
+            return Some(start);
+        }
+        from = start + open.len();
+    }
+
+    None
+}
+
+/// The contents of all elements named `tag`.
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let close = format!("</{tag}>");
+    let mut elements = vec![];
+    let mut rest = xml;
+
+    while let Some(start) = find_start(rest, tag) {
+        let Some(content_start) = rest[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        let Some(content_end) = rest[content_start..]
+            .find(&close)
+            .map(|end| content_start + end)
+        else {
+            break;
+        };
+        elements.push(&rest[content_start..content_end]);
+        rest = &rest[content_end + close.len()..];
+    }
+
+    elements
+}
+
+/// The text of the first element named `tag`, without CDATA and with the common entities decoded.
+fn text_of(xml: &str, tag: &str) -> Option<String> {
+    let text = elements(xml, tag).into_iter().next()?.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+        .map(|text| text.to_string())
+        .unwrap_or_else(|| unescape(text));
+
+    Some(text.trim().to_string())
+}
+
+/// The value of `attribute` of the first element named `tag`.
+fn attribute_of(xml: &str, tag: &str, attribute: &str) -> Option<String> {
+    let start = find_start(xml, tag)?;
+    let element = &xml[start..start + xml[start..].find('>')?];
+    let value_start = element.find(&format!("{attribute}=\""))? + attribute.len() + 2;
+    let value_end = value_start + element[value_start..].find('"')?;
+
+    Some(unescape(&element[value_start..value_end]))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rss_items() {
+        let xml = r#"<?xml version="1.0"?>
+            <rss><channel><title>Verus</title><link>https://verus.io</link>
+            <item>
+                <title><![CDATA[Verus v1.2 & more]]></title>
+                <link>https://medium.com/verus/v12</link>
+                <guid isPermaLink="false">post-12</guid>
+            </item>
+            <item><title>Staking &amp; you</title><link>https://medium.com/verus/staking</link></item>
+            </channel></rss>"#;
+
+        assert_eq!(
+            parse_feed(xml),
+            vec![
+                NewsItem {
+                    id: "post-12".to_string(),
+                    title: "Verus v1.2 & more".to_string(),
+                    link: Some("https://medium.com/verus/v12".to_string()),
+                },
+                NewsItem {
+                    id: "https://medium.com/verus/staking".to_string(),
+                    title: "Staking & you".to_string(),
+                    link: Some("https://medium.com/verus/staking".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_atom_entries() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Releases</title>
+            <entry>
+                <id>tag:github.com,2008:Repository/1/v1.2.0</id>
+                <title>v1.2.0</title>
+                <link rel="alternate" href="https://github.com/VerusCoin/VerusCoin/releases/tag/v1.2.0"/>
+            </entry></feed>"#;
+
+        assert_eq!(
+            parse_feed(xml),
+            vec![NewsItem {
+                id: "tag:github.com,2008:Repository/1/v1.2.0".to_string(),
+                title: "v1.2.0".to_string(),
+                link: Some(
+                    "https://github.com/VerusCoin/VerusCoin/releases/tag/v1.2.0".to_string()
+                ),
+            }]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! The price of VRSC, from several sources.
+//!
+//! `/price` and `/currency` ask CoinPaprika, CoinGecko and, on mainnet, the [`BRIDGE`] basket on chain, where the
+//! price of VRSC in DAI follows from the prices of the basket in both reserves. The price that is shown is the median
+//! of the sources that answered, so a single source that is down or off does not break or skew it.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::warn;
+use vrsc_rpc::RpcApi;
+
+use crate::{currency_history, util::rpc_guard, Data, Error};
+
+/// The basket that the on-chain price comes from.
+pub const BRIDGE: &str = "Bridge.vETH";
+/// The reserve of [`BRIDGE`] that is used as USD.
+const DAI: &str = "DAI.vETH";
+
+const COINPAPRIKA_URL: &str =
+    "https://api.coinpaprika.com/v1/tickers/vrsc-verus-coin?quotes=USD,BTC";
+const COINGECKO_URL: &str =
+    "https://api.coingecko.com/api/v3/simple/price?ids=verus-coin&vs_currencies=usd,btc";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    CoinPaprika,
+    CoinGecko,
+    /// The on-chain price in the [`BRIDGE`] basket
+    Bridge,
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CoinPaprika => "CoinPaprika",
+            Self::CoinGecko => "CoinGecko",
+            Self::Bridge => BRIDGE,
+        }
+    }
+}
+
+/// The price of VRSC according to a single source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub source: Source,
+    pub usd: f64,
+    /// `None` for the on-chain price, which has no BTC reserve
+    pub btc: Option<f64>,
+}
+
+/// The quotes of the sources that answered.
+#[derive(Debug, Clone, Default)]
+pub struct Prices {
+    pub quotes: Vec<Quote>,
+    /// The full CoinPaprika ticker, for the details only it has, like the volume
+    pub ticker: Option<CoinPaprika>,
+}
+
+impl Prices {
+    pub fn usd(&self) -> Option<f64> {
+        median(self.quotes.iter().map(|quote| quote.usd))
+    }
+
+    pub fn btc(&self) -> Option<f64> {
+        median(self.quotes.iter().filter_map(|quote| quote.btc))
+    }
+}
+
+/// Asks all sources for the price. Only fails when none of them answered.
+pub async fn fetch(data: &Data) -> Result<Prices, Error> {
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+    let (ticker, coingecko) = futures::join!(fetch_coinpaprika(&client), fetch_coingecko(&client));
+
+    let mut prices = Prices::default();
+
+    match ticker {
+        Ok(ticker) => {
+            let quote = |currency: &str| ticker.quotes.get(currency).map(|quote| quote.price);
+            if let Some(usd) = quote("USD") {
+                prices.quotes.push(Quote {
+                    source: Source::CoinPaprika,
+                    usd,
+                    btc: quote("BTC"),
+                });
+            }
+            prices.ticker = Some(ticker);
+        }
+        Err(e) => warn!("could not get the price from CoinPaprika: {e:?}"),
+    }
+
+    match coingecko {
+        Ok(quote) => prices.quotes.push(quote),
+        Err(e) => warn!("could not get the price from CoinGecko: {e:?}"),
+    }
+
+    // there is no DAI on testnet
+    if !data.settings.application.testnet {
+        match bridge_price(data).await {
+            Ok(usd) => prices.quotes.push(Quote {
+                source: Source::Bridge,
+                usd,
+                btc: None,
+            }),
+            Err(e) => warn!("could not get the price from {BRIDGE}: {e:?}"),
+        }
+    }
+
+    if prices.quotes.is_empty() {
+        return Err("none of the price sources answered".into());
+    }
+
+    Ok(prices)
+}
+
+async fn fetch_coinpaprika(client: &reqwest::Client) -> Result<CoinPaprika, Error> {
+    Ok(client
+        .get(COINPAPRIKA_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+async fn fetch_coingecko(client: &reqwest::Client) -> Result<Quote, Error> {
+    let response: HashMap<String, HashMap<String, f64>> = client
+        .get(COINGECKO_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let prices = response
+        .get("verus-coin")
+        .ok_or("no price for verus-coin")?;
+
+    Ok(Quote {
+        source: Source::CoinGecko,
+        usd: *prices.get("usd").ok_or("no USD price")?,
+        btc: prices.get("btc").copied(),
+    })
+}
+
+/// The price of VRSC in DAI in the [`BRIDGE`] basket.
+async fn bridge_price(data: &Data) -> Result<f64, Error> {
+    let client = data.verus()?;
+    let bridge =
+        rpc_guard::call(&client, "getcurrency", |client| client.get_currency(BRIDGE)).await?;
+    let (native_id, _) = currency_history::native_currency(data.settings.application.testnet);
+
+    let mut native_price = None;
+    let mut dai_price = None;
+    for reserve in bridge
+        .bestcurrencystate
+        .reservecurrencies
+        .unwrap_or_default()
+    {
+        if reserve.currencyid.to_string() == native_id {
+            native_price = Some(reserve.priceinreserve.as_vrsc());
+        } else if data.to_currency_name(&reserve.currencyid).await? == DAI {
+            dai_price = Some(reserve.priceinreserve.as_vrsc());
+        }
+    }
+
+    match (native_price, dai_price) {
+        (Some(native_price), Some(dai_price)) if native_price > 0.0 => {
+            Ok(usd_from_reserve_prices(native_price, dai_price))
+        }
+        _ => Err(format!("{BRIDGE} has no VRSC and {DAI} reserves").into()),
+    }
+}
+
+/// One unit of a basket is worth `native_price` VRSC and `dai_price` DAI, so one VRSC is worth
+/// `dai_price / native_price` DAI.
+fn usd_from_reserve_prices(native_price: f64, dai_price: f64) -> f64 {
+    dai_price / native_price
+}
+
+pub fn median(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let mut values = values.collect::<Vec<_>>();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+
+    let middle = values.len() / 2;
+    match values.len() % 2 {
+        0 => Some((values[middle - 1] + values[middle]) / 2.0),
+        _ => Some(values[middle]),
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CoinPaprika {
+    #[serde(rename = "id")]
+    pub guid: String,
+    pub symbol: String,
+    pub circulating_supply: u64,
+    pub last_updated: DateTime<Utc>,
+    pub quotes: HashMap<String, CoinPaprikaQuoteCoin>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CoinPaprikaQuoteCoin {
+    pub price: f64,
+    pub volume_24h: f64,
+    pub percent_change_24h: f64,
+    pub percent_from_price_ath: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(source: Source, usd: f64, btc: Option<f64>) -> Quote {
+        Quote { source, usd, btc }
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median([3.0, 1.0, 2.0].into_iter()), Some(2.0));
+        assert_eq!(median([4.0, 1.0, 2.0, 3.0].into_iter()), Some(2.5));
+        assert_eq!(median(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn a_single_source_that_is_off_does_not_skew_the_price() {
+        let prices = Prices {
+            quotes: vec![
+                quote(Source::CoinPaprika, 0.50, Some(0.000010)),
+                quote(Source::CoinGecko, 0.52, Some(0.000011)),
+                quote(Source::Bridge, 5.0, None),
+            ],
+            ticker: None,
+        };
+
+        assert_eq!(prices.usd(), Some(0.52));
+        assert!((prices.btc().unwrap() - 0.0000105).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bridge_price_is_dai_per_vrsc() {
+        // a basket unit is worth 2 VRSC and 1 DAI, so a VRSC is worth half a DAI
+        assert_eq!(usd_from_reserve_prices(2.0, 1.0), 0.5);
+    }
+}
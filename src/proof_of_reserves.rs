@@ -0,0 +1,216 @@
+//! Proof-of-reserves reports, which let the community check that the bot holds the funds it owes its users.
+//!
+//! A report lists the liabilities of the bot (user balances, funds frozen in disputes, guild treasuries and unclaimed
+//! legacy balances) and the addresses of the wallet with their balances. The SHA256 hash of the JSON report is
+//! signed by the daemon with an address of the wallet, so anyone can check it with
+//! `verus verifymessage <address> <signature> <hash>`.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use vrsc::Amount;
+use vrsc_rpc::{
+    bitcoin::hashes::{sha256, Hash},
+    Client as VerusClient, RpcApi,
+};
+
+use crate::{util::rpc_guard, Error};
+
+/// All amounts are in sats.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub generated_at: String,
+    pub height: u64,
+    pub liabilities: Liabilities,
+    pub addresses: Vec<AddressBalance>,
+    pub total_reserves: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Liabilities {
+    pub user_balances: u64,
+    pub frozen_in_disputes: u64,
+    pub treasuries: u64,
+    pub unclaimed_legacy_balances: u64,
+    pub referral_pool: u64,
+    pub daily_pools: u64,
+    pub pots: u64,
+    pub pending_withdrawals: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressBalance {
+    pub address: String,
+    pub balance: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Unspent {
+    address: Option<String>,
+    amount: f64,
+}
+
+impl Liabilities {
+    pub fn new(
+        user_balances: Amount,
+        frozen_in_disputes: Amount,
+        treasuries: Amount,
+        unclaimed_legacy_balances: Amount,
+        referral_pool: Amount,
+        daily_pools: Amount,
+        pots: Amount,
+        pending_withdrawals: Amount,
+    ) -> Self {
+        let total = user_balances
+            + frozen_in_disputes
+            + treasuries
+            + unclaimed_legacy_balances
+            + referral_pool
+            + daily_pools
+            + pots
+            + pending_withdrawals;
+
+        Liabilities {
+            user_balances: user_balances.as_sat(),
+            frozen_in_disputes: frozen_in_disputes.as_sat(),
+            treasuries: treasuries.as_sat(),
+            unclaimed_legacy_balances: unclaimed_legacy_balances.as_sat(),
+            referral_pool: referral_pool.as_sat(),
+            daily_pools: daily_pools.as_sat(),
+            pots: pots.as_sat(),
+            pending_withdrawals: pending_withdrawals.as_sat(),
+            total: total.as_sat(),
+        }
+    }
+}
+
+impl Report {
+    pub fn new(height: u64, liabilities: Liabilities, mut addresses: Vec<AddressBalance>) -> Self {
+        addresses.sort_by(|a, b| b.balance.cmp(&a.balance).then(a.address.cmp(&b.address)));
+        let total_reserves = addresses.iter().map(|address| address.balance).sum();
+
+        Report {
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            height,
+            liabilities,
+            addresses,
+            total_reserves,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// The reserves as a fraction of the liabilities, where 1.0 means fully backed.
+    pub fn reserve_ratio(&self) -> f64 {
+        match self.liabilities.total {
+            0 => 1.0,
+            total => self.total_reserves as f64 / total as f64,
+        }
+    }
+}
+
+/// The hex SHA256 hash of a report, as it is signed.
+pub fn hash(json: &[u8]) -> String {
+    sha256::Hash::hash(json).to_string()
+}
+
+/// The balances of the addresses of the wallet, from its unspent outputs.
+pub async fn address_balances(client: &Arc<VerusClient>) -> Result<Vec<AddressBalance>, Error> {
+    let unspent: Vec<Unspent> = rpc_guard::call(client, "listunspent", |client| {
+        client.call("listunspent", &[])
+    })
+    .await?;
+
+    let mut balances: Vec<AddressBalance> = vec![];
+    for output in unspent {
+        let Some(address) = output.address else {
+            continue;
+        };
+        let amount = Amount::from_vrsc(output.amount)?.as_sat();
+
+        match balances
+            .iter_mut()
+            .find(|balance| balance.address == address)
+        {
+            Some(balance) => balance.balance += amount,
+            None => balances.push(AddressBalance {
+                address,
+                balance: amount,
+            }),
+        }
+    }
+
+    Ok(balances)
+}
+
+/// Signs `message` with `address`, which must be an address or identity of the wallet.
+pub async fn sign(
+    client: &Arc<VerusClient>,
+    address: &str,
+    message: &str,
+) -> Result<String, Error> {
+    let params = [address.into(), message.into()];
+
+    rpc_guard::call(client, "signmessage", move |client| {
+        client.call("signmessage", &params)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(address: &str, balance: u64) -> AddressBalance {
+        AddressBalance {
+            address: address.to_string(),
+            balance,
+        }
+    }
+
+    #[test]
+    fn liabilities_add_up() {
+        let liabilities = Liabilities::new(
+            Amount::from_sat(1_000),
+            Amount::from_sat(200),
+            Amount::from_sat(30),
+            Amount::from_sat(4),
+            Amount::from_sat(50_000),
+            Amount::from_sat(600_000),
+            Amount::from_sat(7_000_000),
+            Amount::from_sat(80_000_000),
+        );
+
+        assert_eq!(liabilities.total, 87_651_234);
+    }
+
+    #[test]
+    fn report_sums_reserves_and_sorts_addresses() {
+        let liabilities = Liabilities::new(
+            Amount::from_sat(1_000),
+            Amount::ZERO,
+            Amount::ZERO,
+            Amount::ZERO,
+            Amount::ZERO,
+            Amount::ZERO,
+            Amount::ZERO,
+            Amount::ZERO,
+        );
+        let report = Report::new(1, liabilities, vec![address("RA", 300), address("RB", 900)]);
+
+        assert_eq!(report.total_reserves, 1_200);
+        assert_eq!(report.addresses[0].address, "RB");
+        assert_eq!(report.reserve_ratio(), 1.2);
+    }
+
+    #[test]
+    fn hash_is_hex_sha256() {
+        assert_eq!(
+            hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
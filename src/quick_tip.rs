@@ -0,0 +1,431 @@
+//! Buttons under tip announcements that let anyone tip the same recipients with a single click.
+//!
+//! Guilds turn the buttons on with `/config tipbuttons`. A button under the announcement of a tip to a single user
+//! tips that user, a button under the announcement of a role tip, soak or reactdrop splits the tip among the same
+//! recipients. "Custom" opens a modal to enter the amount.
+//!
+//! The buttons outlive the command that posted them, so their clicks are handled by the event handler of the bot
+//! instead of a collector. Everything the handler needs is in the custom id of the button:
+//! `quicktip:u:<user_id>:<sats>` or `quicktip:e:<tip_uuid>:<sats>`, with `custom` or `modal` instead of an amount
+//! for the button that opens the modal and for the modal itself.
+
+use poise::serenity_prelude::{
+    self as serenity, ActionRowComponent, ButtonStyle, CreateComponents, GuildId, InputTextStyle,
+    Interaction, InteractionResponseType, UserId,
+};
+use tracing::{debug, instrument};
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{
+    authorization,
+    commands::{
+        tipping::{self, TipSplit},
+        wallet,
+    },
+    i18n::{tr_locale, Translations},
+    util::{database, format},
+    Data, Error,
+};
+
+const PREFIX: &str = "quicktip";
+const AMOUNT_INPUT: &str = "amount";
+/// The amounts of the buttons, in sats: 0.1 and 1 VRSC.
+const QUICK_TIP_AMOUNTS: [u64; 2] = [10_000_000, 100_000_000];
+
+/// Who a quick tip goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    User(UserId),
+    /// The recipients of a tip event, who split the quick tip
+    Event(Uuid),
+}
+
+/// What a click on a quick tip component asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Choice {
+    Amount(Amount),
+    /// Open the modal to enter an amount
+    Custom,
+    /// The modal was submitted
+    Modal,
+}
+
+fn custom_id(target: Target, choice: Choice) -> String {
+    let target = match target {
+        Target::User(user_id) => format!("u:{}", user_id.0),
+        Target::Event(uuid) => format!("e:{uuid}"),
+    };
+    let choice = match choice {
+        Choice::Amount(amount) => amount.as_sat().to_string(),
+        Choice::Custom => "custom".to_string(),
+        Choice::Modal => "modal".to_string(),
+    };
+
+    format!("{PREFIX}:{target}:{choice}")
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<(Target, Choice)> {
+    let mut parts = custom_id.split(':');
+    if parts.next()? != PREFIX {
+        return None;
+    }
+
+    let target = match (parts.next()?, parts.next()?) {
+        ("u", user_id) => Target::User(UserId(user_id.parse().ok()?)),
+        ("e", uuid) => Target::Event(uuid.parse().ok()?),
+        _ => return None,
+    };
+    let choice = match parts.next()? {
+        "custom" => Choice::Custom,
+        "modal" => Choice::Modal,
+        sats => Choice::Amount(Amount::from_sat(sats.parse().ok()?)),
+    };
+
+    match parts.next() {
+        None => Some((target, choice)),
+        Some(_) => None,
+    }
+}
+
+/// Whether quick tip buttons should be put under tip announcements in this guild.
+pub async fn enabled(pool: &sqlx::PgPool, guild_id: Option<GuildId>) -> Result<bool, Error> {
+    match guild_id {
+        Some(guild_id) => database::get_tip_buttons(pool, guild_id).await,
+        None => Ok(false),
+    }
+}
+
+/// Adds a row of quick tip buttons for `target`. Announcements are public, so the labels are in English.
+pub fn buttons<'a>(
+    components: &'a mut CreateComponents,
+    target: Target,
+    translations: &Translations,
+) -> &'a mut CreateComponents {
+    components.create_action_row(|row| {
+        for sats in QUICK_TIP_AMOUNTS {
+            let amount = Amount::from_sat(sats);
+            row.create_button(|button| {
+                button
+                    .custom_id(custom_id(target, Choice::Amount(amount)))
+                    .label(tr_locale!(
+                        translations,
+                        None,
+                        "quicktip-button",
+                        amount: format::amount(amount)
+                    ))
+                    .style(ButtonStyle::Primary)
+            });
+        }
+
+        row.create_button(|button| {
+            button
+                .custom_id(custom_id(target, Choice::Custom))
+                .label(tr_locale!(translations, None, "quicktip-custom"))
+                .style(ButtonStyle::Secondary)
+        })
+    })
+}
+
+/// Adds the quick tip buttons for `target` to a reply, if there is a target.
+pub fn with_buttons<'a, 'b>(
+    reply: &'b mut poise::CreateReply<'a>,
+    target: Option<Target>,
+    translations: &Translations,
+) -> &'b mut poise::CreateReply<'a> {
+    if let Some(target) = target {
+        reply.components(|components| buttons(components, target, translations));
+    }
+
+    reply
+}
+
+/// Handles clicks on quick tip buttons and submits of the quick tip modal. Other interactions are ignored.
+#[instrument(skip(ctx, data, interaction))]
+pub async fn handle(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &Interaction,
+) -> Result<(), Error> {
+    match interaction {
+        Interaction::MessageComponent(component) => {
+            let Some((target, choice)) = parse_custom_id(&component.data.custom_id) else {
+                return Ok(());
+            };
+            let locale = locale_of(data, component.user.id, &component.locale);
+
+            let amount = match choice {
+                Choice::Amount(amount) => amount,
+                Choice::Custom => {
+                    component
+                        .create_interaction_response(ctx, |response| {
+                            response
+                                .kind(InteractionResponseType::Modal)
+                                .interaction_response_data(|modal| {
+                                    modal
+                                        .custom_id(custom_id(target, Choice::Modal))
+                                        .title(tr_locale!(
+                                            data.translations,
+                                            locale.as_deref(),
+                                            "quicktip-modal-title"
+                                        ))
+                                        .components(|components| {
+                                            components.create_action_row(|row| {
+                                                row.create_input_text(|input| {
+                                                    input
+                                                        .custom_id(AMOUNT_INPUT)
+                                                        .label(tr_locale!(
+                                                            data.translations,
+                                                            locale.as_deref(),
+                                                            "quicktip-modal-amount"
+                                                        ))
+                                                        .style(InputTextStyle::Short)
+                                                        .placeholder("2.5")
+                                                        .required(true)
+                                                })
+                                            })
+                                        })
+                                })
+                        })
+                        .await?;
+
+                    return Ok(());
+                }
+                Choice::Modal => return Ok(()),
+            };
+
+            let refusal = tip(
+                ctx,
+                data,
+                component.id.0,
+                component.user.id,
+                component.channel_id,
+                component.guild_id,
+                target,
+                amount,
+                locale.as_deref(),
+            )
+            .await?;
+
+            component
+                .create_interaction_response(ctx, |response| match refusal {
+                    // the announcement of the tip is posted as a new message, the clicked one stays as it is
+                    None => response.kind(InteractionResponseType::DeferredUpdateMessage),
+                    Some(refusal) => response
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|data| data.ephemeral(true).content(refusal)),
+                })
+                .await?;
+        }
+        Interaction::ModalSubmit(modal) => {
+            let Some((target, Choice::Modal)) = parse_custom_id(&modal.data.custom_id) else {
+                return Ok(());
+            };
+            let locale = locale_of(data, modal.user.id, &modal.locale);
+
+            let input = modal
+                .data
+                .components
+                .iter()
+                .flat_map(|row| row.components.iter())
+                .find_map(|component| match component {
+                    ActionRowComponent::InputText(input) if input.custom_id == AMOUNT_INPUT => {
+                        Some(input.value.clone())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default();
+
+            let refusal = match parse_amount(&input) {
+                Some(amount) => {
+                    tip(
+                        ctx,
+                        data,
+                        modal.id.0,
+                        modal.user.id,
+                        modal.channel_id,
+                        modal.guild_id,
+                        target,
+                        amount,
+                        locale.as_deref(),
+                    )
+                    .await?
+                }
+                None => Some(tr_locale!(
+                    data.translations,
+                    locale.as_deref(),
+                    "quicktip-invalid-amount",
+                    amount: format::user_text(&input, 20)
+                )),
+            };
+
+            modal
+                .create_interaction_response(ctx, |response| match refusal {
+                    None => response.kind(InteractionResponseType::DeferredUpdateMessage),
+                    Some(refusal) => response
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|data| data.ephemeral(true).content(refusal)),
+                })
+                .await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// The locale for responses to `user_id`, like [`crate::i18n::locale_of`] for interactions outside of commands.
+fn locale_of(data: &Data, user_id: UserId, discord_locale: &str) -> Option<String> {
+    let user_language = data.user_languages.read().unwrap().get(&user_id).cloned();
+
+    user_language.or_else(|| Some(discord_locale.to_owned()))
+}
+
+/// An amount as typed in the modal, e.g. `2.5`. Amounts that are not positive are not valid.
+fn parse_amount(input: &str) -> Option<Amount> {
+    let amount = Amount::from_vrsc(input.trim().replace(',', ".").parse().ok()?).ok()?;
+
+    (amount > Amount::ZERO).then_some(amount)
+}
+
+/// Tips `amount` from `tipper` to `target`, which is announced in `channel_id` like a role tip. Returns why the tip was
+/// refused, if it was. A click or submit that Discord delivers again is not tipped again.
+async fn tip(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction_id: u64,
+    tipper: UserId,
+    channel_id: serenity::ChannelId,
+    guild_id: Option<GuildId>,
+    target: Target,
+    amount: Amount,
+    locale: Option<&str>,
+) -> Result<Option<String>, Error> {
+    let pool = &data.database;
+    let translations = &data.translations;
+
+    if *data.tx_processor.maintenance.read().await && !authorization::is_operator(data, &tipper) {
+        return Ok(Some(
+            ":tools: The bot is in maintenance mode, we'll be right back :tools:".to_owned(),
+        ));
+    }
+    if *data.degraded.read().await {
+        return Ok(Some(tr_locale!(translations, locale, "error-degraded")));
+    }
+    let sync = data.sync.read().await.clone();
+    if let Some(sync) = sync {
+        return Ok(Some(tr_locale!(
+            translations,
+            locale,
+            "error-syncing",
+            progress: format!("{:.0}", sync.verification_progress * 100.0)
+        )));
+    }
+    if data.blacklist.lock().unwrap().contains(&tipper) {
+        return Ok(Some(tr_locale!(translations, locale, "user-suspended")));
+    }
+    let Some(_in_flight) = data.shutdown.begin() else {
+        return Ok(Some(tr_locale!(
+            translations,
+            locale,
+            "error-shutting-down"
+        )));
+    };
+
+    let mut recipients = match target {
+        Target::User(user_id) => vec![user_id],
+        Target::Event(uuid) => database::get_tip_recipients(pool, &uuid.to_string()).await?,
+    };
+    recipients.retain(|recipient| *recipient != tipper);
+    if recipients.is_empty() {
+        return Ok(Some(tr_locale!(translations, locale, "quicktip-self")));
+    }
+
+    let tipped = match TipSplit::new(amount, recipients.len()) {
+        Some(split) if split.share > Amount::ZERO => split.tipped(recipients.len()),
+        _ => {
+            return Ok(Some(tr_locale!(
+                translations,
+                locale,
+                "quicktip-invalid-amount",
+                amount: format::amount(amount)
+            )))
+        }
+    };
+
+    if wallet::check_balance(&mut *pool.acquire().await?, tipper, tipped, Amount::ZERO)
+        .await?
+        .is_none()
+    {
+        return Ok(Some(tr_locale!(
+            translations,
+            locale,
+            "tip-insufficient-balance"
+        )));
+    }
+
+    debug!(
+        "{tipper} quick tips {amount} to {} user(s)",
+        recipients.len()
+    );
+    database::insert_discord_user(pool, &tipper).await?;
+    match tipping::tip_multiple_users(
+        pool,
+        translations,
+        tipper,
+        &ctx.http,
+        &channel_id,
+        guild_id,
+        &recipients,
+        &amount,
+        "quick",
+        None,
+        Some(interaction_id),
+    )
+    .await
+    {
+        Ok(_) => Ok(None),
+        // the first delivery tipped, this one only needs to be acknowledged
+        Err(e) if database::is_already_processed(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_ids_round_trip() {
+        let uuid = Uuid::new_v4();
+        for (target, choice) in [
+            (
+                Target::User(UserId(123)),
+                Choice::Amount(Amount::from_sat(10_000_000)),
+            ),
+            (Target::Event(uuid), Choice::Custom),
+            (Target::Event(uuid), Choice::Modal),
+        ] {
+            let id = custom_id(target, choice);
+            // Discord allows at most 100 characters
+            assert!(id.len() <= 100);
+            assert_eq!(parse_custom_id(&id), Some((target, choice)));
+        }
+    }
+
+    #[test]
+    fn other_custom_ids_are_ignored() {
+        assert_eq!(parse_custom_id("123-confirm"), None);
+        assert_eq!(parse_custom_id("quicktip:x:1:custom"), None);
+        assert_eq!(parse_custom_id("quicktip:u:1:custom:extra"), None);
+    }
+
+    #[test]
+    fn modal_amounts() {
+        assert_eq!(parse_amount(" 2.5 "), Some(Amount::from_sat(250_000_000)));
+        assert_eq!(parse_amount("0,1"), Some(Amount::from_sat(10_000_000)));
+        assert_eq!(parse_amount("0"), None);
+        assert_eq!(parse_amount("-1"), None);
+        assert_eq!(parse_amount("lots"), None);
+    }
+}
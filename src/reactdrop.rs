@@ -1,8 +1,10 @@
 use std::{fmt::Display, str::FromStr};
 
 use poise::serenity_prelude::{
-    ArgumentConvert, ChannelId, Context, Message, MessageId, ReactionType, UserId,
+    ArgumentConvert, ChannelId, Context, GuildId, Message, MessageId, ReactionType, RoleId, User,
+    UserId,
 };
+use rand::{seq::SliceRandom, Rng};
 use sqlx::{
     types::chrono::{self, DateTime, Utc},
     PgPool,
@@ -10,19 +12,33 @@ use sqlx::{
 use tracing::{debug, error, info, trace};
 use vrsc::Amount;
 
-use crate::{commands, util::database, Error};
+use crate::{
+    commands::{self, misc::Notification, tipping::TipSplit},
+    i18n::{tr_locale, Translations},
+    metrics,
+    util::{
+        database,
+        delivery::{self, Destination},
+    },
+    Error,
+};
 
 #[derive(Debug)]
 pub enum ReactdropState {
     Pending,
+    /// Ended, but the payout is not stored yet. The payout is resumed until it is.
+    Paying,
     Processed,
+    Cancelled,
 }
 
 impl Display for ReactdropState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Pending => write!(f, "pending"),
+            Self::Paying => write!(f, "paying"),
             Self::Processed => write!(f, "processed"),
+            Self::Cancelled => write!(f, "cancelled"),
         }
     }
 }
@@ -31,7 +47,9 @@ impl From<String> for ReactdropState {
     fn from(value: String) -> Self {
         match value.as_ref() {
             "pending" => ReactdropState::Pending,
+            "paying" => ReactdropState::Paying,
             "processed" => ReactdropState::Processed,
+            "cancelled" => ReactdropState::Cancelled,
             _ => unreachable!(),
         }
     }
@@ -43,13 +61,65 @@ pub struct Reactdrop {
     pub status: ReactdropState,
     pub emoji: String,
     pub tip_amount: Amount,
+    pub guild_id: Option<GuildId>,
     pub channel_id: ChannelId,
     pub message_id: MessageId,
     pub finish_time: DateTime<Utc>,
+    /// The number of random participants that split the amount, or `None` to split it among all participants.
+    pub winners: Option<u32>,
+    /// Only participants with this role get a share
+    pub required_role: Option<RoleId>,
+}
+
+/// The marker in the message of a reactdrop after which the remaining time is shown. The remaining time is updated by
+/// [`check_running_reactdrops`], so the marker is always in English.
+const TIME_REMAINING: &str = "Time remaining: ";
+
+/// Replaces the remaining time in the message of a reactdrop with `status`, for reactdrops that ended early.
+pub fn with_status(content: &str, status: &str) -> String {
+    match content.find(TIME_REMAINING) {
+        Some(split) => format!("{}{status}", &content[..split]),
+        None => format!("{content}\n\n{status}"),
+    }
+}
+
+/// Resumes the payouts of reactdrops that a previous run of the bot left in [`ReactdropState::Paying`], like when it
+/// stopped while paying them out. This runs once at startup: while the bot runs, a paying reactdrop is being paid out
+/// by whoever marked it as paying.
+pub async fn resume_payouts(
+    ctx: &Context,
+    pool: &PgPool,
+    translations: &Translations,
+) -> Result<(), Error> {
+    for reactdrop in database::get_pending_reactdrops(pool).await? {
+        if !matches!(reactdrop.status, ReactdropState::Paying) {
+            continue;
+        }
+
+        info!("resuming the payout of reactdrop: {reactdrop:?}");
+        let resumed = async {
+            let message = reactdrop_message(ctx, &reactdrop).await?;
+            pay_out(ctx, pool, translations, &reactdrop, message).await
+        };
+        if let Err(e) = resumed.await {
+            error!(
+                "could not resume the payout of reactdrop {}: {e:?}",
+                reactdrop.message_id
+            );
+        }
+    }
+
+    Ok(())
 }
 
-pub async fn check_running_reactdrops(ctx: &Context, pool: &PgPool) -> Result<(), Error> {
-    let pending_reactdrops = database::get_pending_reactdrops(&pool).await?;
+pub async fn check_running_reactdrops(
+    ctx: &Context,
+    pool: &PgPool,
+    translations: &Translations,
+) -> Result<(), Error> {
+    let pending_reactdrops = database::get_pending_reactdrops(pool).await?;
+
+    metrics::ACTIVE_REACTDROPS.set(pending_reactdrops.len() as i64);
 
     let now = chrono::Utc::now();
     debug!(
@@ -59,117 +129,537 @@ pub async fn check_running_reactdrops(ctx: &Context, pool: &PgPool) -> Result<()
     );
 
     for reactdrop in pending_reactdrops {
-        let mut message: Message = ArgumentConvert::convert(
-            &ctx,
-            None,
-            Some(reactdrop.channel_id),
-            reactdrop.message_id.to_string().as_ref(),
-        )
-        .await?;
-
-        let diff = reactdrop.finish_time.signed_duration_since(now);
-        let diff_fmt = || -> String {
-            match diff.num_seconds() {
-                t @ 0..=3600 => format!("{} minute(s)", t / 60),
-                t @ _ => {
-                    format!("{} hour(s) and {} minute(s)", t / (60 * 60), (t / 60) % 60)
-                }
+        // a paying reactdrop is paid out by whoever marked it as paying, or by `resume_payouts` at startup
+        if let ReactdropState::Paying = reactdrop.status {
+            continue;
+        }
+
+        if let Err(e) = check_reactdrop(ctx, pool, translations, &reactdrop, now).await {
+            error!("could not check reactdrop {}: {e:?}", reactdrop.message_id);
+        }
+    }
+
+    Ok(())
+}
+
+async fn reactdrop_message(ctx: &Context, reactdrop: &Reactdrop) -> Result<Message, Error> {
+    let message = ArgumentConvert::convert(
+        ctx,
+        None,
+        Some(reactdrop.channel_id),
+        reactdrop.message_id.to_string().as_ref(),
+    )
+    .await?;
+
+    Ok(message)
+}
+
+/// Updates the time remaining of a pending reactdrop, and pays it out when it is finished.
+async fn check_reactdrop(
+    ctx: &Context,
+    pool: &PgPool,
+    translations: &Translations,
+    reactdrop: &Reactdrop,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    let mut message = reactdrop_message(ctx, reactdrop).await?;
+
+    let diff = reactdrop.finish_time.signed_duration_since(now);
+    let diff_fmt = || -> String {
+        match diff.num_seconds() {
+            t @ 0..=3600 => format!("{} minute(s)", t / 60),
+            t => {
+                format!("{} hour(s) and {} minute(s)", t / (60 * 60), (t / 60) % 60)
             }
-        };
-        debug!("{diff:?}");
+        }
+    };
+    debug!("{diff:?}");
 
-        let content: &str = message.content.as_ref();
-        let split = content.find("Time remaining: ").unwrap();
-        let new_content = format!("{}Time remaining: {}", &content[..split], diff_fmt());
+    // the marker is gone when the reactdrop was ended or cancelled after it was fetched, and then the message already
+    // shows its status
+    let content: &str = message.content.as_ref();
+    if let Some(split) = content.find(TIME_REMAINING) {
+        let new_content = format!("{}{TIME_REMAINING}{}", &content[..split], diff_fmt());
 
         message.edit(&ctx, |edit| edit.content(new_content)).await?;
+    }
+
+    // the reactdrop is marked as paying before it is paid out, so it can't be paid out twice when it is ended with
+    // `/reactdrop end` at the same time.
+    if reactdrop.finish_time <= now
+        && database::update_reactdrop(
+            pool,
+            reactdrop.channel_id.0 as i64,
+            reactdrop.message_id.0 as i64,
+            ReactdropState::Paying,
+            None,
+        )
+        .await?
+    {
+        pay_out(ctx, pool, translations, reactdrop, message).await?;
 
-        if reactdrop.finish_time <= now {
-            let mut last_user = None;
-            let mut reaction_users = vec![];
+        info!("processed reactdrop: {reactdrop:#?}");
+    }
 
-            while let Ok(users) = message
-                .reaction_users(
-                    &ctx,
-                    ReactionType::from_str(&reactdrop.emoji)?,
-                    Some(50),
-                    last_user,
-                )
-                .await
-            {
-                debug!("appending {} users", users.len());
-                reaction_users.extend(users.clone());
+    Ok(())
+}
 
-                debug!("{users:?}");
+/// Some clients add this to an emoji, others leave it out, which makes them different reactions.
+const VARIATION_SELECTOR: char = '\u{fe0f}';
 
-                last_user = users.last().map(|user| user.id);
-                if last_user.is_none() {
-                    break;
-                }
-            }
+/// The reactions that count for a reactdrop: its emoji and, for a unicode emoji, the same emoji in the other skin
+/// tones and with and without the [`VARIATION_SELECTOR`].
+pub fn accepted_reactions(emoji: &str) -> Result<Vec<ReactionType>, Error> {
+    let reaction_type = ReactionType::from_str(emoji)?;
+    let ReactionType::Unicode(unicode) = &reaction_type else {
+        return Ok(vec![reaction_type]);
+    };
+
+    let mut variants = vec![unicode.clone()];
+    if let Some(emoji) =
+        emojis::get(unicode).or_else(|| emojis::get(&format!("{unicode}{VARIATION_SELECTOR}")))
+    {
+        variants.push(emoji.as_str().to_owned());
+        if let Some(skin_tones) = emoji.skin_tones() {
+            variants.extend(skin_tones.map(|emoji| emoji.as_str().to_owned()));
+        }
+    }
+    let without_selector = variants
+        .iter()
+        .map(|variant| variant.replace(VARIATION_SELECTOR, ""))
+        .collect::<Vec<_>>();
+    variants.extend(without_selector);
+
+    let mut accepted: Vec<ReactionType> = vec![];
+    for variant in variants {
+        let variant = ReactionType::Unicode(variant);
+        if !accepted
+            .iter()
+            .any(|accepted| same_reaction(accepted, &variant))
+        {
+            accepted.push(variant);
+        }
+    }
+
+    Ok(accepted)
+}
+
+fn same_reaction(a: &ReactionType, b: &ReactionType) -> bool {
+    match (a, b) {
+        (ReactionType::Unicode(a), ReactionType::Unicode(b)) => a == b,
+        (ReactionType::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a == b,
+        _ => false,
+    }
+}
 
+/// The accepted reactions of the reactdrop that are on its message.
+fn present_reactions(reactdrop: &Reactdrop, message: &Message) -> Result<Vec<ReactionType>, Error> {
+    Ok(accepted_reactions(&reactdrop.emoji)?
+        .into_iter()
+        .filter(|accepted| {
+            message
+                .reactions
+                .iter()
+                .any(|reaction| same_reaction(&reaction.reaction_type, accepted))
+        })
+        .collect())
+}
+
+/// Everyone that reacted to the message of the reactdrop with one of its accepted reactions, once.
+async fn reaction_users(
+    ctx: &Context,
+    reactdrop: &Reactdrop,
+    message: &Message,
+) -> Result<Vec<User>, Error> {
+    let mut reaction_users: Vec<User> = vec![];
+
+    for reaction_type in present_reactions(reactdrop, message)? {
+        let mut last_user = None;
+
+        while let Ok(users) = message
+            .reaction_users(&ctx, reaction_type.clone(), Some(50), last_user)
+            .await
+        {
             debug!(
-                "retrieved {} users who reacted on reactdrop tip\n{:#?}",
-                reaction_users.len(),
-                reaction_users
+                "appending {} users who reacted with {reaction_type}",
+                users.len()
             );
+            last_user = users.last().map(|user| user.id);
 
-            let reaction_users = reaction_users
-                .iter()
-                .filter(|user| !user.bot)
-                .map(|u| u.id)
-                .collect::<Vec<_>>();
+            for user in users {
+                if !reaction_users.iter().any(|known| known.id == user.id) {
+                    reaction_users.push(user);
+                }
+            }
+
+            if last_user.is_none() {
+                break;
+            }
+        }
+    }
 
-            if reaction_users.len() == 0 {
-                trace!("no users to tip, abort");
-            } else {
-                trace!("tipping {} users in reactdrop", reaction_users.len());
+    debug!(
+        "retrieved {} users who reacted on reactdrop tip\n{:#?}",
+        reaction_users.len(),
+        reaction_users
+    );
 
-                if let Err(e) = commands::tipping::tip_multiple_users(
-                    &pool,
-                    reactdrop.author,
-                    &ctx.http,
-                    &reactdrop.channel_id,
-                    &reaction_users,
-                    &reactdrop.tip_amount,
-                    "reactdrop",
-                )
-                .await
-                {
-                    error!("{e:?}");
-
-                    reactdrop
-                        .channel_id
-                        .send_message(&ctx.http, |msg| {
-                            msg.content(format!(
-                                "<@{}> didn't have enough funds, reactdrop failed",
-                                &message.author.id,
-                            ))
-                        })
-                        .await?;
+    Ok(reaction_users)
+}
+
+/// The participants that have the role the reactdrop requires, or all of them when it requires none. Participants
+/// that left the guild don't have the role.
+async fn with_required_role(
+    ctx: &Context,
+    reactdrop: &Reactdrop,
+    participants: Vec<User>,
+) -> Vec<User> {
+    let (Some(role_id), Some(guild_id)) = (reactdrop.required_role, reactdrop.guild_id) else {
+        return participants;
+    };
+
+    let mut eligible = vec![];
+    for user in participants {
+        match guild_id.member(ctx, user.id).await {
+            Ok(member) if member.roles.contains(&role_id) => eligible.push(user),
+            Ok(_) => trace!("{} does not have the required role {role_id}", user.id),
+            Err(e) => debug!("{} is not a member of {guild_id}: {e:?}", user.id),
+        }
+    }
+
+    eligible
+}
+
+/// The users that get a share of the reactdrop: all participants, or the randomly picked winners of a raffle.
+fn recipients(reactdrop: &Reactdrop, participants: &[User]) -> Vec<User> {
+    match reactdrop.winners {
+        Some(winners) => pick_winners(participants, winners as usize, &mut rand::thread_rng()),
+        None => participants.to_vec(),
+    }
+}
+
+/// Tips everyone that reacted to the reactdrop, stores the results and marks it as processed. The reactdrop must already
+/// be marked as paying.
+///
+/// A payout that fails is left paying, to be resumed by [`check_running_reactdrops`]. The tip claims the message of the
+/// reactdrop like an interaction, so a resumed payout does not tip again when the tip already went through.
+pub async fn pay_out(
+    ctx: &Context,
+    pool: &PgPool,
+    translations: &Translations,
+    reactdrop: &Reactdrop,
+    message: Message,
+) -> Result<(), Error> {
+    let participants = reaction_users(ctx, reactdrop, &message)
+        .await?
+        .into_iter()
+        .filter(|user| !user.bot)
+        .collect::<Vec<_>>();
+    let participants = with_required_role(ctx, reactdrop, participants).await;
+    let winners = recipients(reactdrop, &participants);
+    let reaction_users = winners.iter().map(|u| u.id).collect::<Vec<_>>();
+
+    let mut tip_uuid = None;
+
+    if reaction_users.is_empty() {
+        trace!("no users to tip, abort");
+    } else {
+        trace!("tipping {} users in reactdrop", reaction_users.len());
+
+        match commands::tipping::tip_multiple_users(
+            pool,
+            translations,
+            reactdrop.author,
+            &ctx.http,
+            &reactdrop.channel_id,
+            reactdrop.guild_id,
+            &reaction_users,
+            &reactdrop.tip_amount,
+            "reactdrop",
+            None,
+            Some(reactdrop.message_id.0),
+        )
+        .await
+        {
+            Ok(uuid) => {
+                tip_uuid = uuid;
+
+                if reactdrop.winners.is_some() && tip_uuid.is_some() {
+                    announce_winners(ctx, pool, translations, reactdrop, &winners).await?;
                 }
             }
+            Err(e) if database::is_already_processed(&e) => {
+                info!("reactdrop {} was already paid out", reactdrop.message_id);
+            }
+            Err(e) if database::is_insufficient_balance(&e) => {
+                error!("{e:?}");
 
-            reactdrop
-                .channel_id
-                .delete_reaction_emoji(
+                delivery::post_with_fallback(
                     &ctx.http,
-                    message,
-                    ReactionType::from_str(&reactdrop.emoji)?,
+                    pool,
+                    reactdrop.channel_id,
+                    reactdrop.guild_id,
+                    reactdrop.author,
+                    &tr_locale!(
+                        translations,
+                        None,
+                        "reactdrop-failed-insufficient-funds",
+                        author: format!("<@{}>", reactdrop.author)
+                    ),
                 )
                 .await?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // the number of members at the time the reactdrop finished, to calculate the participation rate.
+    let eligible = ctx
+        .cache
+        .guild_channel(reactdrop.channel_id)
+        .and_then(|channel| ctx.cache.guild_field(channel.guild_id, |g| g.member_count));
+
+    database::store_reactdrop_results(
+        pool,
+        reactdrop.channel_id.0 as i64,
+        reactdrop.message_id.0 as i64,
+        participants.len() as i32,
+        eligible.map(|e| e as i32),
+        tip_uuid,
+    )
+    .await?;
 
-            database::update_reactdrop(
-                &pool,
-                reactdrop.channel_id.0 as i64,
-                reactdrop.message_id.0 as i64,
-                ReactdropState::Processed,
-            )
+    for reaction_type in present_reactions(reactdrop, &message)? {
+        reactdrop
+            .channel_id
+            .delete_reaction_emoji(&ctx.http, message.id, reaction_type)
             .await?;
+    }
 
-            info!("processed reactdrop: {reactdrop:#?}");
-        }
+    Ok(())
+}
+
+/// What paying out a reactdrop would do at this moment, see [`simulate`].
+#[derive(Debug)]
+pub struct Simulation {
+    pub reactions: usize,
+    /// Reactions of bots, which don't count
+    pub bots: usize,
+    pub participants: usize,
+    /// Participants without the role the reactdrop requires, who don't count
+    pub without_role: usize,
+    /// The users that would be tipped. For a raffle, this is one random draw of the winners.
+    pub recipients: Vec<UserId>,
+    /// `None` when nobody would be tipped
+    pub split: Option<TipSplit>,
+    pub author_balance: Amount,
+}
+
+impl Simulation {
+    /// Whether the author can pay the reactdrop. A reactdrop the author can't pay fails when it ends.
+    pub fn affordable(&self) -> bool {
+        self.split.as_ref().is_none_or(|split| {
+            split.tipped(self.recipients.len()) <= self.author_balance
+        })
     }
+}
+
+/// Runs the payout calculation of a reactdrop without paying out or changing the reactdrop.
+pub async fn simulate(
+    ctx: &Context,
+    pool: &PgPool,
+    reactdrop: &Reactdrop,
+    message: &Message,
+) -> Result<Simulation, Error> {
+    let reaction_users = reaction_users(ctx, reactdrop, message).await?;
+    let reactions = reaction_users.len();
+    let participants = reaction_users
+        .into_iter()
+        .filter(|user| !user.bot)
+        .collect::<Vec<_>>();
+    let humans = participants.len();
+    let participants = with_required_role(ctx, reactdrop, participants).await;
+    let recipients = recipients(reactdrop, &participants)
+        .iter()
+        .map(|user| user.id)
+        .collect::<Vec<_>>();
+    let author_balance = database::get_balance_for_user(pool, &reactdrop.author)
+        .await?
+        .map_or(Amount::ZERO, Amount::from_sat);
+
+    Ok(Simulation {
+        reactions,
+        bots: reactions - humans,
+        participants: participants.len(),
+        without_role: humans - participants.len(),
+        split: TipSplit::new(reactdrop.tip_amount, recipients.len()),
+        recipients,
+        author_balance,
+    })
+}
+
+/// The channel and message of a message link, like `https://discord.com/channels/<guild>/<channel>/<message>`.
+pub fn parse_message_link(link: &str) -> Option<(ChannelId, MessageId)> {
+    let mut parts = link.trim().trim_end_matches('/').rsplit('/');
+    let message_id = parts.next()?.parse().ok()?;
+    let channel_id = parts.next()?.parse().ok()?;
+    // the guild, or `@me` for DMs
+    parts.next()?;
+
+    (parts.next()? == "channels").then_some((ChannelId(channel_id), MessageId(message_id)))
+}
+
+/// Picks `winners` distinct participants at random, each participant having the same chance to win. Everyone wins
+/// when there are no more participants than winners.
+pub fn pick_winners<R: Rng + ?Sized>(
+    participants: &[User],
+    winners: usize,
+    rng: &mut R,
+) -> Vec<User> {
+    participants
+        .choose_multiple(rng, winners)
+        .cloned()
+        .collect()
+}
+
+/// Announces the winners of a raffle. Winners are only mentioned when their notification settings allow pings in a
+/// channel, the others are named without a ping.
+async fn announce_winners(
+    ctx: &Context,
+    pool: &PgPool,
+    translations: &Translations,
+    reactdrop: &Reactdrop,
+    winners: &[User],
+) -> Result<(), Error> {
+    let user_ids = winners.iter().map(|user| user.id).collect::<Vec<_>>();
+    let notification_settings = database::get_notification_settings(pool, &user_ids).await?;
+
+    let names = winners
+        .iter()
+        .map(|user| {
+            let notification = notification_settings
+                .iter()
+                .find(|(user_id, _)| *user_id as u64 == user.id.0)
+                .map(|(_, notification)| notification);
+
+            match notification {
+                Some(Notification::DMOnly) | Some(Notification::Off) => {
+                    format!("**{}**", user.name)
+                }
+                // users without a setting get the default, which is ChannelOnly.
+                _ => format!("<@{}>", user.id),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    delivery::deliver(
+        &ctx.http,
+        pool,
+        translations,
+        Destination::Channel {
+            channel_id: reactdrop.channel_id,
+            guild_id: reactdrop.guild_id,
+            initiator: reactdrop.author,
+        },
+        &tr_locale!(translations, None, "reactdrop-winners", winners: names),
+        None,
+    )
+    .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn users(count: u64) -> Vec<User> {
+        (1..=count)
+            .map(|id| {
+                let mut user = User::default();
+                user.id = UserId(id);
+                user
+            })
+            .collect()
+    }
+
+    #[test]
+    fn message_links() {
+        assert_eq!(
+            parse_message_link("https://discord.com/channels/1/22/333"),
+            Some((ChannelId(22), MessageId(333)))
+        );
+        assert_eq!(
+            parse_message_link("https://ptb.discord.com/channels/@me/22/333/"),
+            Some((ChannelId(22), MessageId(333)))
+        );
+        assert_eq!(parse_message_link("https://discord.com/22/333"), None);
+        assert_eq!(parse_message_link("22:333"), None);
+    }
+
+    fn unicode(reactions: &[ReactionType]) -> Vec<&str> {
+        reactions
+            .iter()
+            .map(|reaction| match reaction {
+                ReactionType::Unicode(unicode) => unicode.as_str(),
+                _ => panic!("{reaction:?} is not a unicode emoji"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accepts_other_skin_tones_and_variation_selectors() {
+        let thumbs_up = accepted_reactions("👍🏽").unwrap();
+        assert_eq!(thumbs_up.len(), 6);
+        assert!(unicode(&thumbs_up).contains(&"👍"));
+        assert!(unicode(&thumbs_up).contains(&"👍🏿"));
+
+        assert_eq!(
+            unicode(&accepted_reactions("❤").unwrap()),
+            ["❤", "❤\u{fe0f}"]
+        );
+        assert_eq!(unicode(&accepted_reactions("🎉").unwrap()), ["🎉"]);
+    }
+
+    #[test]
+    fn picks_distinct_winners() {
+        let participants = users(10);
+        let winners = pick_winners(&participants, 3, &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(winners.len(), 3);
+        let mut ids = winners.iter().map(|user| user.id).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn everyone_wins_when_there_are_fewer_participants_than_winners() {
+        let participants = users(2);
+        let winners = pick_winners(&participants, 5, &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(winners.len(), 2);
+    }
+
+    #[test]
+    fn every_participant_can_win() {
+        let participants = users(4);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut wins = [0; 4];
+
+        for _ in 0..4000 {
+            for winner in pick_winners(&participants, 1, &mut rng) {
+                wins[winner.id.0 as usize - 1] += 1;
+            }
+        }
+
+        // each participant should win roughly 1000 times.
+        assert!(
+            wins.iter().all(|wins| (800..1200).contains(wins)),
+            "{wins:?}"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! The database schema. The migrations in `migrations/` are embedded in the binary and applied at startup, so a new
+//! version of the bot brings its own schema changes.
+//!
+//! A database with a migration that is newer than the newest one in the binary was migrated by a newer version of the
+//! bot. An older version could write data that the newer schema does not expect, so the bot refuses to start against
+//! it, see [`SchemaTooNew`].
+
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    PgPool,
+};
+use tracing::info;
+
+use crate::Error;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// The database has a migration that this version of the bot does not know about.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SchemaTooNew {
+    /// The newest migration that was applied to the database
+    pub database: i64,
+    /// The newest migration in the binary
+    pub binary: i64,
+}
+
+impl std::fmt::Display for SchemaTooNew {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "the database schema is at migration {}, which is newer than {}, the newest migration of this version of \
+            the bot. Run a newer version of the bot, or restore the database from before the upgrade",
+            self.database, self.binary
+        )
+    }
+}
+
+impl std::error::Error for SchemaTooNew {}
+
+/// Applies the migrations that were not applied yet, after checking that the schema is not newer than the binary.
+pub async fn migrate(pool: &PgPool) -> Result<(), Error> {
+    let applied = applied_versions(pool).await?;
+    let embedded = embedded_versions();
+    check_version(&applied, &embedded)?;
+
+    let pending = embedded
+        .iter()
+        .filter(|version| !applied.contains(version))
+        .count();
+    MIGRATOR.run(pool).await?;
+    info!(
+        "database schema is at migration {}, applied {pending} migration(s)",
+        embedded.last().copied().unwrap_or_default()
+    );
+
+    Ok(())
+}
+
+/// The versions of the migrations that were applied to the database, oldest first.
+pub async fn applied_versions(pool: &PgPool) -> Result<Vec<i64>, Error> {
+    let mut connection = pool.acquire().await?;
+    connection.ensure_migrations_table().await?;
+    let mut versions = connection
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect::<Vec<_>>();
+    versions.sort();
+
+    Ok(versions)
+}
+
+/// The versions of the migrations in the binary, oldest first.
+pub fn embedded_versions() -> Vec<i64> {
+    let mut versions = MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .collect::<Vec<_>>();
+    versions.sort();
+
+    versions
+}
+
+/// The description of a migration in the binary, which is its file name without the version.
+pub fn description(version: i64) -> Option<String> {
+    MIGRATOR
+        .iter()
+        .find(|migration| migration.version == version)
+        .map(|migration| migration.description.to_string())
+}
+
+fn check_version(applied: &[i64], embedded: &[i64]) -> Result<(), SchemaTooNew> {
+    match (applied.iter().max(), embedded.iter().max()) {
+        (Some(&database), Some(&binary)) if database > binary => {
+            Err(SchemaTooNew { database, binary })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_a_schema_newer_than_the_binary() {
+        let embedded = [20231105090000, 20231106090000];
+
+        assert_eq!(check_version(&[], &embedded), Ok(()));
+        assert_eq!(check_version(&[20231105090000], &embedded), Ok(()));
+        assert_eq!(check_version(&embedded, &embedded), Ok(()));
+        assert_eq!(
+            check_version(&[20231105090000, 20231107090000], &embedded),
+            Err(SchemaTooNew {
+                database: 20231107090000,
+                binary: 20231106090000
+            })
+        );
+    }
+
+    #[test]
+    fn embedded_migrations_are_sorted() {
+        let versions = embedded_versions();
+
+        assert!(!versions.is_empty());
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            description(20231104090000).as_deref(),
+            Some("compact output")
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Graceful shutdown.
+//!
+//! On SIGTERM or SIGINT the bot stops accepting commands that change balances, waits until the ones that are running
+//! are done and the background tasks have stored their state, and then disconnects from Discord. Without this, a
+//! restart in the middle of a withdrawal could send the coins without storing the withdrawal.
+//!
+//! Everything that changes balances holds an [`InFlight`] from [`Shutdown::begin`] while it runs. Background tasks
+//! hold one for as long as they run, and drop it after they stored their state when [`Shutdown::stopped`] resolves.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+use tracing::info;
+
+/// How long a shutdown waits for operations in flight, which is long enough for a withdrawal to be sent.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct Shutdown {
+    stopping: watch::Sender<bool>,
+    in_flight: watch::Sender<usize>,
+}
+
+/// An operation that a shutdown waits for, until this is dropped.
+#[derive(Debug)]
+pub struct InFlight(Arc<Shutdown>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.send_modify(|in_flight| *in_flight -= 1);
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            stopping: watch::channel(false).0,
+            in_flight: watch::channel(0).0,
+        }
+    }
+}
+
+impl Shutdown {
+    /// Registers an operation, or returns `None` when the bot is shutting down and no new operations should start.
+    pub fn begin(self: &Arc<Self>) -> Option<InFlight> {
+        // checked while the counter is locked, so a drain can't start between the check and the increment
+        let started = self.in_flight.send_if_modified(|in_flight| {
+            if self.is_stopping() {
+                return false;
+            }
+            *in_flight += 1;
+
+            true
+        });
+
+        started.then(|| InFlight(self.clone()))
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.stopping.borrow()
+    }
+
+    /// Resolves when the bot starts shutting down.
+    pub async fn stopped(&self) {
+        let mut stopping = self.stopping.subscribe();
+        // the sender lives in `self`, so this can't fail
+        let _ = stopping.wait_for(|stopping| *stopping).await;
+    }
+
+    /// Stops new operations and waits until the operations in flight are done. Returns false when they were not done
+    /// within `timeout`.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        // set while the counter is locked, see `begin`
+        self.in_flight.send_if_modified(|_| {
+            self.stopping.send_replace(true);
+
+            false
+        });
+
+        let mut in_flight = self.in_flight.subscribe();
+        info!("waiting for {} operation(s) in flight", *in_flight.borrow());
+
+        tokio::time::timeout(timeout, async move {
+            // the sender lives in `self`, so this can't fail
+            let _ = in_flight.wait_for(|in_flight| *in_flight == 0).await;
+        })
+        .await
+        .is_ok()
+    }
+}
+
+/// Resolves on the first SIGTERM or SIGINT.
+pub async fn signal_received() -> Result<(), std::io::Error> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+
+    tokio::select! {
+        _ = terminate.recv() => info!("received SIGTERM"),
+        _ = interrupt.recv() => info!("received SIGINT"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn no_new_operations_while_stopping() {
+        let shutdown = Arc::new(Shutdown::default());
+        assert!(shutdown.begin().is_some());
+
+        assert!(shutdown.drain(Duration::from_millis(10)).await);
+
+        assert!(shutdown.is_stopping());
+        assert!(shutdown.begin().is_none());
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_operations_in_flight() {
+        let shutdown = Arc::new(Shutdown::default());
+        let in_flight = shutdown.begin().unwrap();
+
+        assert!(!shutdown.drain(Duration::from_millis(10)).await);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(in_flight);
+        });
+        assert!(shutdown.drain(Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn stopped_resolves_on_drain() {
+        let shutdown = Arc::new(Shutdown::default());
+        let task = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.stopped().await }
+        });
+
+        shutdown.drain(Duration::from_millis(10)).await;
+
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+//! The balances of users, as seen by the code that moves funds between them.
+//!
+//! Tips and balance checks go through [`BalanceStore`] instead of the database directly, so their logic can be tested
+//! with [`mock::MockStore`]. The database itself is tested with `sqlx::test` in [`super::database`].
+//!
+//! The store of the database is a connection, so a tip can be done in a transaction that also stores the messages
+//! about it.
+
+use std::future::Future;
+
+use poise::serenity_prelude::{GuildId, UserId};
+use sqlx::PgConnection;
+use uuid::Uuid;
+use vrsc::Amount;
+
+use crate::{util::database, Error};
+
+pub trait BalanceStore {
+    /// The balance of a user, or `None` if they never had one.
+    fn balance(
+        &mut self,
+        user_id: UserId,
+    ) -> impl Future<Output = Result<Option<Amount>, Error>> + Send;
+
+    /// Moves `share` from the tipper to every recipient and returns the id of the tip event. Fails without changing any
+    /// balance with [`database::InsufficientBalance`] when the tipper can not afford all shares, or with
+    /// [`database::AlreadyProcessed`] when the interaction that started the tip was already processed.
+    fn tip(
+        &mut self,
+        tipper: UserId,
+        recipients: &[UserId],
+        share: Amount,
+        kind: &str,
+        guild_id: Option<GuildId>,
+        note: Option<&str>,
+        interaction_id: Option<u64>,
+    ) -> impl Future<Output = Result<Uuid, Error>> + Send;
+}
+
+impl BalanceStore for PgConnection {
+    async fn balance(&mut self, user_id: UserId) -> Result<Option<Amount>, Error> {
+        database::balance_of(self, &user_id).await
+    }
+
+    async fn tip(
+        &mut self,
+        tipper: UserId,
+        recipients: &[UserId],
+        share: Amount,
+        kind: &str,
+        guild_id: Option<GuildId>,
+        note: Option<&str>,
+        interaction_id: Option<u64>,
+    ) -> Result<Uuid, Error> {
+        database::tip_transactionally(
+            self,
+            &tipper,
+            recipients,
+            &share,
+            kind,
+            guild_id,
+            note,
+            interaction_id,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Mutex,
+    };
+
+    use super::*;
+
+    /// A tip that went through, as (tipper, recipients, share, kind)
+    pub type MockTip = (UserId, Vec<UserId>, Amount, String);
+
+    /// Balances in memory. Tips are all-or-nothing, like in the database.
+    #[derive(Default)]
+    pub struct MockStore {
+        pub balances: Mutex<HashMap<UserId, Amount>>,
+        /// Every tip that went through
+        pub tips: Mutex<Vec<MockTip>>,
+        pub interactions: Mutex<HashSet<u64>>,
+    }
+
+    impl MockStore {
+        pub fn with_balances(balances: impl IntoIterator<Item = (u64, u64)>) -> Self {
+            Self {
+                balances: Mutex::new(
+                    balances
+                        .into_iter()
+                        .map(|(user_id, sats)| (UserId(user_id), Amount::from_sat(sats)))
+                        .collect(),
+                ),
+                ..Default::default()
+            }
+        }
+
+        pub fn balance_of(&self, user_id: u64) -> Option<Amount> {
+            self.balances.lock().unwrap().get(&UserId(user_id)).copied()
+        }
+    }
+
+    impl BalanceStore for MockStore {
+        async fn balance(&mut self, user_id: UserId) -> Result<Option<Amount>, Error> {
+            Ok(self.balances.lock().unwrap().get(&user_id).copied())
+        }
+
+        async fn tip(
+            &mut self,
+            tipper: UserId,
+            recipients: &[UserId],
+            share: Amount,
+            kind: &str,
+            _guild_id: Option<GuildId>,
+            _note: Option<&str>,
+            interaction_id: Option<u64>,
+        ) -> Result<Uuid, Error> {
+            let mut balances = self.balances.lock().unwrap();
+            if let Some(interaction_id) = interaction_id {
+                if self.interactions.lock().unwrap().contains(&interaction_id) {
+                    return Err(database::AlreadyProcessed(interaction_id).into());
+                }
+            }
+            let total = share
+                .checked_mul(recipients.len() as u64)
+                .ok_or("tip overflows")?;
+            let remaining = balances
+                .get(&tipper)
+                .and_then(|balance| balance.checked_sub(total))
+                .ok_or(database::InsufficientBalance(tipper))?;
+
+            balances.insert(tipper, remaining);
+            for recipient in recipients {
+                let balance = balances.entry(*recipient).or_insert(Amount::ZERO);
+                *balance = balance.checked_add(share).ok_or("balance overflows")?;
+            }
+            self.tips
+                .lock()
+                .unwrap()
+                .push((tipper, recipients.to_vec(), share, kind.to_owned()));
+            self.interactions.lock().unwrap().extend(interaction_id);
+
+            Ok(Uuid::new_v4())
+        }
+    }
+}
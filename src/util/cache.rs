@@ -0,0 +1,82 @@
+//! Short-lived caches of lookups that informational commands do on every use, like the price sources for `/price`
+//! and `/currency`. Under load the price APIs rate-limit the bot, and the answers hardly change within a minute.
+//!
+//! The TTLs are configured with `price_cache_secs`, `chaininfo_cache_secs` and `currency_name_cache_secs`. Owners
+//! can skip the cache with the `fresh` option of the commands that use it.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Values that are forgotten `ttl` after they were inserted.
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The value of `key`, unless it is older than the TTL.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_at(key, Instant::now())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_at(key, value, Instant::now())
+    }
+
+    fn get_at(&self, key: &K, now: Instant) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        let (inserted_at, value) = entries.get(key)?;
+
+        (now.duration_since(*inserted_at) < self.ttl).then(|| value.clone())
+    }
+
+    fn insert_at(&self, key: K, value: V, now: Instant) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (inserted_at, _)| now.duration_since(*inserted_at) < self.ttl);
+        entries.insert(key, (now, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_expire_after_the_ttl() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        cache.insert_at("VRSC", 1, start);
+        assert_eq!(
+            cache.get_at(&"VRSC", start + Duration::from_secs(59)),
+            Some(1)
+        );
+        assert_eq!(cache.get_at(&"VRSC", start + Duration::from_secs(60)), None);
+        assert_eq!(cache.get_at(&"Bridge.vETH", start), None);
+
+        // expired values are dropped when something else is inserted
+        cache.insert_at("Bridge.vETH", 2, start + Duration::from_secs(61));
+        assert_eq!(cache.entries.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn a_ttl_of_zero_disables_the_cache() {
+        let cache = TtlCache::new(Duration::ZERO);
+        let start = Instant::now();
+
+        cache.insert_at("VRSC", 1, start);
+        assert_eq!(cache.get_at(&"VRSC", start), None);
+    }
+}
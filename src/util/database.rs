@@ -1,15 +1,43 @@
-use std::str::FromStr;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    time::Duration,
+};
 
 use crate::{
-    commands::misc::Notification,
+    announcement::AnnouncementTarget,
+    audit::AuditEntry,
+    authorization::BotRole,
+    commands::{
+        competition::{Competition, CompetitionMetric, CompetitionResult},
+        daily::{self, DailyClaim, DailyStreak},
+        dispute::{Dispute, DisputeOpening, DisputeOutcome},
+        misc::Notification,
+        pot::{Pot, PotContribution, PotPayout, PotRecipient, WithdrawingPot},
+        referral::ReferralClaim,
+        stats::{DropResult, HourStats},
+        tipping::{Reversal, Tip},
+        treasury::{TreasuryEntry, TreasuryKind},
+        trivia::TriviaQuestion,
+        wallet::PendingWithdrawal,
+    },
+    configuration::BotFee,
+    currency_history::CurrencySnapshot,
+    guild_export::{GuildExport, GuildSettingsExport, ReactdropExport, TreasuryEntryExport},
+    ledger::{self, Account, EntryKind, Mismatch},
+    metrics,
+    mod_log::{self, ModLogEvent},
+    proof_of_reserves::Liabilities,
+    quick_tip,
     reactdrop::{Reactdrop, ReactdropState},
+    util::delivery::{self, BatchKey, Destination, OutboxMessage, PendingBatch},
     Error,
 };
 use num_traits::cast::ToPrimitive;
-use poise::serenity_prelude::UserId;
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, RoleId, UserId};
 use sqlx::{
-    types::chrono::{DateTime, Utc},
-    PgPool, Postgres, QueryBuilder,
+    types::chrono::{DateTime, NaiveDate, Utc},
+    Connection, PgConnection, PgPool, Postgres, QueryBuilder,
 };
 use tracing::*;
 use uuid::Uuid;
@@ -17,6 +45,7 @@ use vrsc::{Address, Amount};
 use vrsc_rpc::bitcoin::Txid;
 
 pub async fn insert_discord_user(pool: &PgPool, user_id: &UserId) -> Result<(), Error> {
+    let _timer = metrics::db_timer("insert_discord_user");
     sqlx::query!(
         "INSERT INTO discord_users(discord_id) 
         VALUES ($1) 
@@ -30,111 +59,182 @@ pub async fn insert_discord_user(pool: &PgPool, user_id: &UserId) -> Result<(),
     Ok(())
 }
 
-// to store multiple tip transactions at once. Usually when a group tip needs to be processed.
-pub async fn store_tip_transactions(
-    pool: &PgPool,
-    uuid: &Uuid,
-    user_ids: &Vec<UserId>,
-    kind: &str,
-    amount: &Amount,
-    counterparty: UserId, // this is always a user
-) -> Result<(), Error> {
-    let mut query_builder: QueryBuilder<Postgres> =
-        QueryBuilder::new("INSERT INTO tips_vrsc(uuid, discord_id, kind, amount, counterparty) ");
-
-    let tuples = user_ids.iter().map(|user| {
-        (
-            uuid.to_string(),
-            user.0 as i64,
-            kind,
-            amount.as_sat() as i64,
-            counterparty.0 as i64,
-        )
-    });
-
-    query_builder.push_values(tuples, |mut b, tuple| {
-        b.push_bind(tuple.0)
-            .push_bind(tuple.1)
-            .push_bind(tuple.2)
-            .push_bind(tuple.3)
-            .push_bind(tuple.4);
-    });
-
-    query_builder.build().execute(pool).await?;
-
-    Ok(())
-}
-
 /// Queries the database and retrieves the balance for the user, if it exists.
 /// If there is no row for this user, None will be returned.
 ///
 /// The database has a constraint that balances can not go below 0.
 pub async fn get_balance_for_user(pool: &PgPool, user_id: &UserId) -> Result<Option<u64>, Error> {
+    Ok(balance_of(&mut *pool.acquire().await?, user_id)
+        .await?
+        .map(|balance| balance.as_sat()))
+}
+
+/// Like [`get_balance_for_user`], on a connection that can be in a transaction.
+pub async fn balance_of(
+    conn: &mut PgConnection,
+    user_id: &UserId,
+) -> Result<Option<Amount>, Error> {
+    let _timer = metrics::db_timer("get_balance_for_user");
     if let Some(row) = sqlx::query!(
         "SELECT balance FROM balance_vrsc WHERE discord_id = $1",
         user_id.0 as i64
     )
-    .fetch_optional(pool)
+    .fetch_optional(conn)
     .await?
     {
         let balance = row.balance;
         debug!("i64 balance: {balance}");
 
-        Ok(Some(balance as u64))
+        Ok(Some(Amount::from_sat(balance as u64)))
     } else {
         Ok(None)
     }
 }
 
-// process a tip from 1 user to 1 or more users.
-// The tipper can tip himself.
-// This function both increases the balances for the tip receivers and decreases the balance of the tipper.
-// If one of these 2 actions fail, the database is not updated.
-pub async fn process_a_tip(
-    pool: &PgPool,
-    from_user: &UserId,
-    to_users: &Vec<UserId>,
-    tip_amount: &Amount,
-) -> Result<(), Error> {
-    let mut tx = pool.begin().await?;
+/// An interaction that already moved funds, so the command that fails with this has nothing left to do.
+#[derive(Debug)]
+pub struct AlreadyProcessed(pub u64);
 
-    let mut query_builder: QueryBuilder<Postgres> =
-        QueryBuilder::new("INSERT INTO balance_vrsc (discord_id, balance) ");
+impl std::fmt::Display for AlreadyProcessed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "interaction {} was already processed", self.0)
+    }
+}
 
-    let tuples = to_users
-        .iter()
-        .map(|user| (user.0 as i64, tip_amount.as_sat() as i64));
+impl std::error::Error for AlreadyProcessed {}
 
-    query_builder.push_values(tuples, |mut b, tuple| {
-        b.push_bind(tuple.0).push_bind(tuple.1);
-    });
+pub fn is_already_processed(error: &Error) -> bool {
+    error.downcast_ref::<AlreadyProcessed>().is_some()
+}
 
-    query_builder
-        .push(" ON CONFLICT (discord_id) DO UPDATE SET balance = balance_vrsc.balance + $2");
+/// A tip the tipper can't afford. Nothing was changed.
+#[derive(Debug)]
+pub struct InsufficientBalance(pub UserId);
 
-    query_builder.build().execute(&mut *tx).await?;
+impl std::fmt::Display for InsufficientBalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "balance of {} is insufficient", self.0)
+    }
+}
 
-    debug!("updated balances");
+impl std::error::Error for InsufficientBalance {}
 
-    if let Some(mul) = tip_amount.checked_mul(to_users.len() as u64) {
-        sqlx::query!(
-            "UPDATE balance_vrsc SET balance = balance - $1 WHERE discord_id = $2",
-            mul.as_sat() as i64,
-            from_user.0 as i64
-        )
-        .execute(&mut *tx)
-        .await?;
+pub fn is_insufficient_balance(error: &Error) -> bool {
+    error.downcast_ref::<InsufficientBalance>().is_some()
+}
 
-        tx.commit().await?;
+/// Claims `interaction_id` for `operation`. Returns false when it was claimed before, in which case the operation must
+/// not be done again. When `conn` is in a transaction, the claim is undone with it.
+pub async fn claim_interaction(
+    conn: &mut PgConnection,
+    interaction_id: u64,
+    operation: &str,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("claim_interaction");
+    let result = sqlx::query!(
+        "INSERT INTO processed_interactions (interaction_id, operation)
+        VALUES ($1, $2)
+        ON CONFLICT (interaction_id) DO NOTHING",
+        interaction_id as i64,
+        operation
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Processes a tip from 1 user to 1 or more users, who each get `share`. The tipper can tip themselves.
+///
+/// Everything happens in a single database transaction: the shares are moved from the tipper to the recipients in the
+/// ledger and the tip is stored in `tips_vrsc`. The tipper is only charged for the shares, so dust that remained after
+/// splitting a tip stays with the tipper. If any of these fail (e.g. because the tipper's balance would go below 0),
+/// nothing is changed.
+///
+/// When the tip is done for an interaction, `interaction_id` is claimed in the same transaction, and a tip for an
+/// interaction that was already processed fails with [`AlreadyProcessed`].
+///
+/// When `conn` is in a transaction, the tip is only committed with it. That way the messages about the tip can be
+/// stored in the outbox in the same transaction, see [`delivery::enqueue`]. The tip is queued for the mod-log of
+/// `guild_id` with it.
+///
+/// Returns the id of the tip, which is shared by all recipients.
+pub async fn tip_transactionally(
+    conn: &mut PgConnection,
+    tipper: &UserId,
+    recipients: &[UserId],
+    share: &Amount,
+    kind: &str,
+    guild_id: Option<GuildId>,
+    memo: Option<&str>,
+    interaction_id: Option<u64>,
+) -> Result<Uuid, Error> {
+    let _timer = metrics::db_timer("tip_transactionally");
+
+    let total = share
+        .checked_mul(recipients.len() as u64)
+        .ok_or("tip amount overflows")?;
+
+    let uuid = Uuid::new_v4();
+    let mut tx = conn.begin().await?;
+
+    if let Some(interaction_id) = interaction_id {
+        if !claim_interaction(&mut tx, interaction_id, "tip").await? {
+            tx.rollback().await?;
+            info!("interaction {interaction_id} was already processed, not tipping again");
+
+            return Err(AlreadyProcessed(interaction_id).into());
+        }
+    }
+
+    let mut entries = vec![(Account::User(*tipper), -(total.as_sat() as i64))];
+    entries.extend(
+        recipients
+            .iter()
+            .map(|recipient| (Account::User(*recipient), share.as_sat() as i64)),
+    );
+
+    if !post_to_ledger(&mut tx, &uuid.to_string(), EntryKind::Tip, &entries).await? {
+        tx.rollback().await?;
+        debug!("balance of {tipper} is insufficient to tip {total}");
 
-        debug!("decreased balances");
-        return Ok(());
+        return Err(InsufficientBalance(*tipper).into());
     }
 
-    error!("something went wrong while processing a tip to multiple users");
-    tx.rollback().await?;
+    // tips are only stored one way: counterparty is the sender of the tip.
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "INSERT INTO tips_vrsc(uuid, discord_id, kind, amount, counterparty, memo, guild_id) ",
+    );
+    query_builder.push_values(recipients, |mut b, recipient| {
+        b.push_bind(uuid.to_string())
+            .push_bind(recipient.0 as i64)
+            .push_bind(kind)
+            .push_bind(share.as_sat() as i64)
+            .push_bind(tipper.0 as i64)
+            .push_bind(memo)
+            .push_bind(guild_id.map(|guild_id| guild_id.0 as i64));
+    });
+    query_builder.build().execute(&mut *tx).await?;
 
-    Ok(())
+    mod_log::dispatch(
+        &mut tx,
+        guild_id,
+        ModLogEvent::Tip {
+            tipper: *tipper,
+            recipients: recipients.len(),
+            amount: total,
+            tip_event_id: uuid,
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    debug!(
+        "tip {uuid} of {share} to {} user(s) processed",
+        recipients.len()
+    );
+
+    Ok(uuid)
 }
 
 pub async fn store_new_address_for_user(
@@ -195,6 +295,7 @@ pub async fn get_user_from_address(
 }
 
 pub async fn transaction_processed(pool: &PgPool, txid: &Txid) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("transaction_processed");
     let transaction_query = sqlx::query!(
         "SELECT * FROM transactions_vrsc WHERE transaction_id = $1 AND transaction_action = 'deposit'",
         &txid.to_string()
@@ -208,88 +309,254 @@ pub async fn transaction_processed(pool: &PgPool, txid: &Txid) -> Result<bool, E
     }
 }
 
+/// Credits a deposit to a user and stores it in `transactions_vrsc` with `uuid`, in one transaction, so a deposit is
+/// never credited without being stored as processed or the other way around.
 pub async fn increase_balance(
     pool: &PgPool,
     user_id: &UserId,
     amount: Amount,
+    uuid: &Uuid,
+    tx_hash: &Txid,
 ) -> Result<(), Error> {
+    let _timer = metrics::db_timer("increase_balance");
     debug!(
         "going to increase balance for {user_id} with {} VRSC",
         amount.as_vrsc()
     );
-    let result = sqlx::query!(
-        "INSERT INTO balance_vrsc (discord_id, balance)
-        VALUES ($1, $2)
-        ON CONFLICT (discord_id)
-        DO UPDATE SET balance = balance_vrsc.balance + $2",
-        user_id.0 as i64,
-        amount.as_sat() as i64
+    let mut tx = pool.begin().await?;
+    if !post_to_ledger(
+        &mut tx,
+        &uuid.to_string(),
+        EntryKind::Deposit,
+        &[
+            (Account::Deposits, -(amount.as_sat() as i64)),
+            (Account::User(*user_id), amount.as_sat() as i64),
+        ],
     )
-    .execute(pool)
-    .await;
+    .await?
+    {
+        return Err(format!("the deposit {tx_hash} of {user_id} could not be posted").into());
+    }
+    sqlx::query!(
+        "INSERT INTO transactions_vrsc (uuid, discord_id, transaction_id, transaction_action) VALUES ($1, $2, $3, $4)",
+        uuid.to_string(),
+        user_id.0 as i64,
+        tx_hash.to_string(),
+        "deposit"
+        )
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    info!("increasing the balance went ok!");
+
+    Ok(())
+}
+
+/// Claims `interaction_id` and reserves a withdrawal, its network fee and the fee of the bot from a user, in one
+/// transaction, before the withdrawal is sent. The reserved funds are held in [`Account::PendingWithdrawals`] until
+/// the withdrawal is settled with [`settle_withdrawal`] or refunded with [`refund_withdrawal`]. `uuid` is the id of the
+/// withdrawal in `transactions_vrsc`. Until then the withdrawal is stored as pending, so it can be resolved when its
+/// outcome was never known, see [`get_pending_withdrawals`].
+///
+/// Returns false when the balance of the user is insufficient, in which case nothing is changed. Fails with
+/// [`AlreadyProcessed`] when the interaction was claimed before.
+pub async fn reserve_withdrawal(
+    pool: &PgPool,
+    interaction_id: u64,
+    user_id: &UserId,
+    amount: Amount,
+    tx_fee: Amount,
+    bot_fee: Amount,
+    uuid: &Uuid,
+    guild_id: Option<GuildId>,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("reserve_withdrawal");
+    let total = withdrawal_total(amount, tx_fee, bot_fee)?;
+    let mut tx = pool.begin().await?;
 
-    match result {
-        Ok(result) => info!("increasing the balance went ok! {:?}", result),
-        Err(e) => return Err(e.into()),
+    if !claim_interaction(&mut tx, interaction_id, "withdrawal").await? {
+        return Err(AlreadyProcessed(interaction_id).into());
+    }
+    if !post_to_ledger(
+        &mut tx,
+        &uuid.to_string(),
+        EntryKind::WithdrawalReserve,
+        &[
+            (Account::User(*user_id), -total),
+            (Account::PendingWithdrawals, total),
+        ],
+    )
+    .await?
+    {
+        debug!("balance of {user_id} is insufficient to withdraw {amount}");
+        return Ok(false);
     }
+    sqlx::query!(
+        "INSERT INTO pending_withdrawals (uuid, discord_id, guild_id, amount, tx_fee, bot_fee)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+        uuid.to_string(),
+        user_id.0 as i64,
+        guild_id.map(|guild_id| guild_id.0 as i64),
+        amount.as_sat() as i64,
+        tx_fee.as_sat() as i64,
+        bot_fee.as_sat() as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Stores the operation of a reserved withdrawal as soon as it was sent, see [`reserve_withdrawal`].
+pub async fn store_withdrawal_opid(pool: &PgPool, uuid: &Uuid, opid: &str) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_withdrawal_opid");
+    sqlx::query!(
+        "UPDATE pending_withdrawals SET opid = $2 WHERE uuid = $1",
+        uuid.to_string(),
+        opid
+    )
+    .execute(pool)
+    .await?;
 
     Ok(())
 }
 
-pub async fn decrease_balance(
+/// The withdrawals that are reserved but not settled or refunded, like when the bot stopped while sending them or
+/// their outcome could not be checked.
+pub async fn get_pending_withdrawals(pool: &PgPool) -> Result<Vec<PendingWithdrawal>, Error> {
+    let _timer = metrics::db_timer("get_pending_withdrawals");
+    let rows = sqlx::query!(
+        "SELECT uuid, discord_id, guild_id, amount, tx_fee, bot_fee, opid
+        FROM pending_withdrawals ORDER BY created_at"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| -> Result<_, Error> {
+            Ok(PendingWithdrawal {
+                uuid: Uuid::parse_str(&row.uuid)?,
+                user_id: UserId(row.discord_id as u64),
+                guild_id: row.guild_id.map(|guild_id| GuildId(guild_id as u64)),
+                amount: Amount::from_sat(row.amount as u64),
+                tx_fee: Amount::from_sat(row.tx_fee as u64),
+                bot_fee: Amount::from_sat(row.bot_fee as u64),
+                opid: row.opid,
+            })
+        })
+        .collect()
+}
+
+/// Removes a reserved withdrawal from the pending ones when it is settled or refunded. Returns false when it was not
+/// pending, like when it was settled or refunded before.
+async fn end_pending_withdrawal(conn: &mut PgConnection, uuid: &Uuid) -> Result<bool, Error> {
+    let ended = sqlx::query!(
+        "DELETE FROM pending_withdrawals WHERE uuid = $1",
+        uuid.to_string()
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(ended.rows_affected() > 0)
+}
+
+/// Settles a reserved withdrawal that was sent, see [`reserve_withdrawal`]: the withdrawal and its fees leave the bot.
+/// `txid` is the transaction that paid it out. The withdrawal is queued for the mod-log of `guild_id` with it.
+pub async fn settle_withdrawal(
     pool: &PgPool,
     user_id: &UserId,
-    amount: &Amount,
-    tx_fee: &Amount,
+    amount: Amount,
+    tx_fee: Amount,
+    bot_fee: Amount,
+    uuid: &Uuid,
+    guild_id: Option<GuildId>,
+    txid: Option<&Txid>,
 ) -> Result<(), Error> {
-    if let Some(to_decrease) = amount.checked_add(*tx_fee) {
-        debug!(
-            "going to decrease balance for {user_id} with {} VRSC",
-            to_decrease.as_vrsc()
-        );
-        let result = sqlx::query!(
-            "UPDATE balance_vrsc SET balance = balance - $1 WHERE discord_id = $2",
-            to_decrease.as_sat() as i64,
-            user_id.0 as i64
-        )
-        .execute(pool)
-        .await;
+    let _timer = metrics::db_timer("settle_withdrawal");
+    let total = withdrawal_total(amount, tx_fee, bot_fee)?;
+    let mut tx = pool.begin().await?;
 
-        match result {
-            Ok(result) => info!("decreasing the balance went ok! {:?}", result),
-            Err(e) => {
-                error!("something went wrong while decreasing the balance: {:?}", e);
-                return Err(e.into());
-            }
-        }
-    } else {
-        // summing the 2 balances went wrong. This is an edge case that only happens when someone is withdrawing more than 184,467,440,737.09551615 VRSC,
-        // which is more than the supply of VRSC will ever be.
-        unreachable!()
-        // TODO: It could be that a PBaaS chain will have such a supply, in which case we need to catch the error and inform the user. But not needed right now.
+    if !end_pending_withdrawal(&mut tx, uuid).await? {
+        return Err(format!("withdrawal {uuid} of {user_id} is not pending").into());
+    }
+    if !post_to_ledger(
+        &mut tx,
+        &uuid.to_string(),
+        EntryKind::Withdrawal,
+        &[
+            (Account::PendingWithdrawals, -total),
+            (Account::Withdrawals, amount.as_sat() as i64),
+            (Account::NetworkFees, tx_fee.as_sat() as i64),
+            (Account::BotFees, bot_fee.as_sat() as i64),
+        ],
+    )
+    .await?
+    {
+        return Err(format!("withdrawal {uuid} of {user_id} could not be settled").into());
     }
+    mod_log::dispatch(
+        &mut tx,
+        guild_id,
+        ModLogEvent::Withdrawal {
+            user_id: *user_id,
+            amount,
+            fees: tx_fee.checked_add(bot_fee).unwrap_or(tx_fee),
+            txid: txid.map(|txid| txid.to_string()),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+    info!("withdrawal {uuid} of {user_id} settled");
+
     Ok(())
 }
 
-pub async fn store_deposit_transaction(
+/// Gives a reserved withdrawal back to the user when nothing was sent, see [`reserve_withdrawal`].
+pub async fn refund_withdrawal(
     pool: &PgPool,
-    uuid: &Uuid,
     user_id: &UserId,
-    tx_hash: &Txid,
+    amount: Amount,
+    tx_fee: Amount,
+    bot_fee: Amount,
+    uuid: &Uuid,
 ) -> Result<(), Error> {
-    sqlx::query!(
-        "INSERT INTO transactions_vrsc (uuid, discord_id, transaction_id, transaction_action) VALUES ($1, $2, $3, $4)",
-        uuid.to_string(),
-        user_id.0 as i64,
-        tx_hash.to_string(),
-        "deposit"
-        )
-        .execute(pool)
-        .await?;
+    let _timer = metrics::db_timer("refund_withdrawal");
+    let total = withdrawal_total(amount, tx_fee, bot_fee)?;
+    let mut tx = pool.begin().await?;
+
+    if !end_pending_withdrawal(&mut tx, uuid).await? {
+        return Err(format!("withdrawal {uuid} of {user_id} is not pending").into());
+    }
+    if !post_to_ledger(
+        &mut tx,
+        &uuid.to_string(),
+        EntryKind::WithdrawalRefund,
+        &[
+            (Account::PendingWithdrawals, -total),
+            (Account::User(*user_id), total),
+        ],
+    )
+    .await?
+    {
+        return Err(format!("withdrawal {uuid} of {user_id} could not be refunded").into());
+    }
+    tx.commit().await?;
+    info!("withdrawal {uuid} of {user_id} refunded");
 
     Ok(())
 }
 
+/// What a withdrawal takes from a balance, in sats.
+fn withdrawal_total(amount: Amount, tx_fee: Amount, bot_fee: Amount) -> Result<i64, Error> {
+    let total = amount
+        .checked_add(tx_fee)
+        .and_then(|total| total.checked_add(bot_fee))
+        .ok_or("withdrawal and fees overflow")?;
+
+    Ok(total.as_sat() as i64)
+}
+
 pub async fn store_withdraw_transaction(
     pool: &PgPool,
     uuid: &Uuid,
@@ -298,6 +565,7 @@ pub async fn store_withdraw_transaction(
     opid: &str,
     tx_fee: &Amount,
 ) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_withdraw_transaction");
     let tx_hash = if let Some(tx) = tx_hash {
         tx.to_string()
     } else {
@@ -361,16 +629,74 @@ pub async fn update_notifications(
     Ok(())
 }
 
-pub async fn get_notification_settings(
+/// Sets whether a user wants to receive group tips (role tips and soaks).
+pub async fn update_tippable(pool: &PgPool, user_id: &UserId, tippable: bool) -> Result<(), Error> {
+    // pre_command takes care of having a db row at this point for this user.
+    sqlx::query!(
+        "UPDATE discord_users SET tippable = $1 WHERE discord_id = $2",
+        tippable,
+        user_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the balance below which a user gets a reminder to top up, or turns the reminder off with `None`.
+pub async fn update_low_balance_threshold(
     pool: &PgPool,
-    user_ids: &Vec<UserId>,
-) -> Result<Vec<(i64, Notification)>, Error> {
+    user_id: &UserId,
+    threshold: Option<Amount>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("update_low_balance_threshold");
+    // pre_command takes care of having a db row at this point for this user.
+    sqlx::query!(
+        "UPDATE discord_users SET low_balance_threshold = $1 WHERE discord_id = $2",
+        threshold.map(|threshold| threshold.as_sat() as i64),
+        user_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the balance of a user and the threshold of their low balance reminder, if they turned it on.
+pub async fn get_low_balance_reminder(
+    pool: &PgPool,
+    user_id: &UserId,
+) -> Result<Option<(Amount, Amount)>, Error> {
+    let _timer = metrics::db_timer("get_low_balance_reminder");
+    let row = sqlx::query!(
+        r#"SELECT COALESCE(b.balance, 0) AS "balance!", u.low_balance_threshold AS "threshold!"
+        FROM discord_users u LEFT JOIN balance_vrsc b ON b.discord_id = u.discord_id
+        WHERE u.discord_id = $1 AND u.low_balance_threshold IS NOT NULL"#,
+        user_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        (
+            Amount::from_sat(row.balance as u64),
+            Amount::from_sat(row.threshold as u64),
+        )
+    }))
+}
+
+/// Returns the users of `user_ids` that opted out of group tips.
+pub async fn get_untippable_users(
+    pool: &PgPool,
+    user_ids: &[UserId],
+) -> Result<HashSet<UserId>, Error> {
+    let _timer = metrics::db_timer("get_untippable_users");
     let users = user_ids
         .iter()
         .map(|user| user.0 as i64)
         .collect::<Vec<_>>();
     let rows = sqlx::query!(
-        "SELECT discord_id, notifications FROM discord_users WHERE discord_id IN (SELECT * FROM UNNEST($1::bigint[]))",
+        "SELECT discord_id FROM discord_users WHERE NOT tippable AND discord_id IN (SELECT * FROM UNNEST($1::bigint[]))",
         &users
     )
     .fetch_all(pool)
@@ -378,47 +704,54 @@ pub async fn get_notification_settings(
 
     Ok(rows
         .into_iter()
-        .filter(|row| row.notifications.is_some())
-        .map(|row| (row.discord_id, row.notifications.unwrap().into()))
+        .map(|row| UserId(row.discord_id as u64))
         .collect())
 }
 
-pub async fn get_blacklist_status(pool: &PgPool, user_id: UserId) -> Result<Option<bool>, Error> {
-    if let Some(row) = sqlx::query!(
-        "SELECT blacklisted FROM discord_users WHERE discord_id = $1",
+pub async fn update_private(pool: &PgPool, user_id: &UserId, private: bool) -> Result<(), Error> {
+    let _timer = metrics::db_timer("update_private");
+    // pre_command takes care of having a db row at this point for this user.
+    sqlx::query!(
+        "UPDATE discord_users SET private = $1 WHERE discord_id = $2",
+        private,
         user_id.0 as i64
     )
-    .fetch_optional(pool)
-    .await?
-    {
-        return Ok(row.blacklisted);
-    } else {
-        Ok(None)
-    }
+    .execute(pool)
+    .await?;
+
+    Ok(())
 }
 
-// TODO user might not exist?
-pub async fn set_blacklist_status(
+/// Returns the users of `user_ids` that keep their balance and the amounts of their tips private.
+pub async fn get_private_users(
     pool: &PgPool,
-    user_id: UserId,
-    blacklist: bool,
-) -> Result<(), Error> {
-    sqlx::query!(
-        "UPDATE discord_users SET blacklisted = $1 WHERE discord_id = $2",
-        blacklist,
-        user_id.0 as i64
+    user_ids: &[UserId],
+) -> Result<HashSet<UserId>, Error> {
+    let _timer = metrics::db_timer("get_private_users");
+    let users = user_ids
+        .iter()
+        .map(|user| user.0 as i64)
+        .collect::<Vec<_>>();
+    let rows = sqlx::query!(
+        "SELECT discord_id FROM discord_users WHERE private AND discord_id IN (SELECT * FROM UNNEST($1::bigint[]))",
+        &users
     )
-    .execute(pool)
+    .fetch_all(pool)
     .await?;
 
-    Ok(())
+    Ok(rows
+        .into_iter()
+        .map(|row| UserId(row.discord_id as u64))
+        .collect())
 }
 
-pub async fn store_unprocessed_transaction(pool: &PgPool, txid: &Txid) -> Result<(), Error> {
+pub async fn update_compact(pool: &PgPool, user_id: &UserId, compact: bool) -> Result<(), Error> {
+    let _timer = metrics::db_timer("update_compact");
+    // pre_command takes care of having a db row at this point for this user.
     sqlx::query!(
-        "INSERT INTO unprocessed_transactions (txid, status) VALUES ($1, $2)",
-        &txid.to_string(),
-        "unprocessed"
+        "UPDATE discord_users SET compact = $1 WHERE discord_id = $2",
+        compact,
+        user_id.0 as i64
     )
     .execute(pool)
     .await?;
@@ -426,16 +759,110 @@ pub async fn store_unprocessed_transaction(pool: &PgPool, txid: &Txid) -> Result
     Ok(())
 }
 
-pub async fn get_stored_txids(pool: &PgPool) -> Result<Vec<Txid>, Error> {
-    let rows =
-        sqlx::query!("SELECT txid FROM unprocessed_transactions WHERE status = 'unprocessed'")
+/// Whether `user_id` prefers informational embeds without their details.
+pub async fn get_compact(pool: &PgPool, user_id: &UserId) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("get_compact");
+    let row = sqlx::query!(
+        "SELECT compact FROM discord_users WHERE discord_id = $1",
+        user_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some_and(|row| row.compact))
+}
+
+pub async fn get_notification_settings(
+    pool: &PgPool,
+    user_ids: &[UserId],
+) -> Result<Vec<(i64, Notification)>, Error> {
+    let _timer = metrics::db_timer("get_notification_settings");
+    let users = user_ids
+        .iter()
+        .map(|user| user.0 as i64)
+        .collect::<Vec<_>>();
+    let rows = sqlx::query!(
+        "SELECT discord_id, notifications FROM discord_users WHERE discord_id IN (SELECT * FROM UNNEST($1::bigint[]))",
+        &users
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| row.notifications.is_some())
+        .map(|row| (row.discord_id, row.notifications.unwrap().into()))
+        .collect())
+}
+
+pub async fn get_blacklist_status(pool: &PgPool, user_id: UserId) -> Result<Option<bool>, Error> {
+    if let Some(row) = sqlx::query!(
+        "SELECT blacklisted FROM discord_users WHERE discord_id = $1",
+        user_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?
+    {
+        Ok(row.blacklisted)
+    } else {
+        Ok(None)
+    }
+}
+
+// TODO user might not exist?
+/// Blacklists or unblacklists a user, on behalf of `actor`, and queues the change for the mod-logs.
+pub async fn set_blacklist_status(
+    pool: &PgPool,
+    user_id: UserId,
+    blacklist: bool,
+    actor: UserId,
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        "UPDATE discord_users SET blacklisted = $1 WHERE discord_id = $2",
+        blacklist,
+        user_id.0 as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+    mod_log::dispatch(
+        &mut tx,
+        None,
+        ModLogEvent::Blacklist {
+            user_id,
+            blacklisted: blacklist,
+            actor,
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn store_unprocessed_transaction(pool: &PgPool, txid: &Txid) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO unprocessed_transactions (txid, status) VALUES ($1, $2)
+        ON CONFLICT (txid) DO UPDATE SET status = $2",
+        &txid.to_string(),
+        "unprocessed"
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_stored_txids(pool: &PgPool) -> Result<Vec<Txid>, Error> {
+    let rows =
+        sqlx::query!("SELECT txid FROM unprocessed_transactions WHERE status = 'unprocessed'")
             .fetch_all(pool)
             .await?;
 
-    return Ok(rows
+    Ok(rows
         .into_iter()
         .map(|row| Txid::from_str(&row.txid).unwrap())
-        .collect::<Vec<_>>());
+        .collect::<Vec<_>>())
 }
 
 pub async fn set_stored_txid_to_processed(pool: &PgPool, txid: &Txid) -> Result<(), Error> {
@@ -494,10 +921,10 @@ pub async fn get_all_txids(pool: &PgPool, transaction_action: &str) -> Result<Ve
     .fetch_all(pool)
     .await?;
 
-    return Ok(rows
+    Ok(rows
         .into_iter()
         .map(|row| Txid::from_str(&row.transaction_id).unwrap())
-        .collect::<Vec<_>>());
+        .collect::<Vec<_>>())
 }
 
 pub async fn insert_reactdrop(
@@ -505,21 +932,27 @@ pub async fn insert_reactdrop(
     author: i64,
     emoji: String,
     amount: i64,
+    guild_id: Option<GuildId>,
     channel_id: i64,
     message_id: i64,
     finish_time: DateTime<Utc>,
+    winners: Option<u32>,
+    required_role: Option<RoleId>,
 ) -> Result<(), Error> {
     sqlx::query!(
-        "INSERT INTO reactdrops(author, channel_id, message_id, finish_time, emojistr, amount, status) \
-    VALUES ($1, $2, $3, $4, $5, $6, 'pending') \
+        "INSERT INTO reactdrops(author, guild_id, channel_id, message_id, finish_time, emojistr, amount, winners, required_role, status) \
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'pending') \
     ON CONFLICT (channel_id, message_id) \
     DO NOTHING",
         author,
+        guild_id.map(|id| id.0 as i64),
         channel_id,
         message_id,
         finish_time,
         emoji,
         amount,
+        winners.map(|winners| winners as i32),
+        required_role.map(|role_id| role_id.0 as i64),
     )
     .execute(pool)
     .await?;
@@ -527,12 +960,13 @@ pub async fn insert_reactdrop(
     Ok(())
 }
 
-/// Returns pending reactdrops, or an emtpy Vec if no pending reactdrops present
+/// Returns pending reactdrops and reactdrops that are still paying, or an emtpy Vec if there are none
 pub async fn get_pending_reactdrops(pool: &PgPool) -> Result<Vec<Reactdrop>, Error> {
+    let _timer = metrics::db_timer("get_pending_reactdrops");
     let rows = sqlx::query!(
         "SELECT * \
 FROM reactdrops \
-WHERE status = 'pending'"
+WHERE status IN ('pending', 'paying')"
     )
     .fetch_all(pool)
     .await?;
@@ -540,33 +974,5224 @@ WHERE status = 'pending'"
     let vec: Vec<Reactdrop> = rows
         .into_iter()
         .map(|row| Reactdrop {
-            status: crate::reactdrop::ReactdropState::Pending,
+            status: row.status.into(),
             author: (row.author as u64).into(),
             emoji: row.emojistr,
             tip_amount: Amount::from_sat(row.amount as u64),
+            guild_id: row.guild_id.map(|id| GuildId(id as u64)),
             channel_id: (row.channel_id as u64).into(),
             message_id: (row.message_id as u64).into(),
             finish_time: row.finish_time,
+            winners: row.winners.map(|winners| winners as u32),
+            required_role: row.required_role.map(|role_id| RoleId(role_id as u64)),
         })
         .collect();
 
     Ok(vec)
 }
 
-pub async fn update_reactdrop(
+/// Stores the outcome of a paying reactdrop and marks it as processed. A `tip_uuid` that was stored before is kept.
+pub async fn store_reactdrop_results(
     pool: &PgPool,
     channel_id: i64,
     message_id: i64,
-    status: ReactdropState,
+    participants: i32,
+    eligible: Option<i32>,
+    tip_uuid: Option<Uuid>,
 ) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_reactdrop_results");
     sqlx::query!(
-        "UPDATE reactdrops SET status = $3 WHERE channel_id = $1 AND message_id = $2",
+        "UPDATE reactdrops SET status = 'processed', participants = $3, eligible = $4, tip_uuid = COALESCE($5, tip_uuid)
+        WHERE channel_id = $1 AND message_id = $2 AND status = 'paying'",
+        channel_id,
+        message_id,
+        participants,
+        eligible,
+        tip_uuid.map(|uuid| uuid.to_string())
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves a pending reactdrop to `status`. `ended_by` is the user that ended or cancelled the reactdrop before its
+/// finish time. Returns false when the reactdrop was no longer pending.
+pub async fn update_reactdrop(
+    pool: &PgPool,
+    channel_id: i64,
+    message_id: i64,
+    status: ReactdropState,
+    ended_by: Option<UserId>,
+) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        "UPDATE reactdrops SET status = $3, ended_by = $4
+        WHERE channel_id = $1 AND message_id = $2 AND status = 'pending'",
         channel_id,
         message_id,
-        status.to_string()
+        status.to_string(),
+        ended_by.map(|user_id| user_id.0 as i64)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+pub async fn get_reactdrop(
+    pool: &PgPool,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<Option<Reactdrop>, Error> {
+    let _timer = metrics::db_timer("get_reactdrop");
+    let row = sqlx::query!(
+        "SELECT author, status, emojistr, amount, guild_id, channel_id, message_id, finish_time, winners, required_role
+        FROM reactdrops WHERE channel_id = $1 AND message_id = $2",
+        channel_id.0 as i64,
+        message_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| Reactdrop {
+        status: row.status.into(),
+        author: (row.author as u64).into(),
+        emoji: row.emojistr,
+        tip_amount: Amount::from_sat(row.amount as u64),
+        guild_id: row.guild_id.map(|id| GuildId(id as u64)),
+        channel_id: (row.channel_id as u64).into(),
+        message_id: (row.message_id as u64).into(),
+        finish_time: row.finish_time,
+        winners: row.winners.map(|winners| winners as u32),
+        required_role: row.required_role.map(|role_id| RoleId(role_id as u64)),
+    }))
+}
+
+/// Sets the language a user wants the bot to respond in. `None` resets it to the locale of the Discord client.
+pub async fn update_language(
+    pool: &PgPool,
+    user_id: &UserId,
+    language: Option<&str>,
+) -> Result<(), Error> {
+    // pre_command takes care of having a db row at this point for this user.
+    sqlx::query!(
+        "UPDATE discord_users SET language = $1 WHERE discord_id = $2",
+        language,
+        user_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the language of the DMs the bot sends a user. `None` resets it to the language set with `/language`.
+pub async fn update_dm_language(
+    pool: &PgPool,
+    user_id: &UserId,
+    language: Option<&str>,
+) -> Result<(), Error> {
+    // pre_command takes care of having a db row at this point for this user.
+    sqlx::query!(
+        "UPDATE discord_users SET dm_language = $1 WHERE discord_id = $2",
+        language,
+        user_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The language to send DMs to this user in: their DM language, or else the language they set with `/language`.
+pub async fn get_dm_language(pool: &PgPool, user_id: &UserId) -> Result<Option<String>, Error> {
+    let _timer = metrics::db_timer("get_dm_language");
+    let row = sqlx::query!(
+        "SELECT COALESCE(dm_language, language) AS language FROM discord_users WHERE discord_id = $1",
+        user_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(|row| row.language))
+}
+
+/// Returns all users that have set a language.
+pub async fn get_user_languages(pool: &PgPool) -> Result<Vec<(UserId, String)>, Error> {
+    let rows =
+        sqlx::query!("SELECT discord_id, language FROM discord_users WHERE language IS NOT NULL")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| Some((UserId(row.discord_id as u64), row.language?)))
+        .collect())
+}
+
+/// Allows tipping in a channel of a guild. Returns false when it was already allowed.
+pub async fn allow_tip_channel(
+    pool: &PgPool,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("allow_tip_channel");
+    let result = sqlx::query!(
+        "INSERT INTO tip_channels (guild_id, channel_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        guild_id.0 as i64,
+        channel_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns false when tipping was not allowed in the channel.
+pub async fn disallow_tip_channel(
+    pool: &PgPool,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("disallow_tip_channel");
+    let result = sqlx::query!(
+        "DELETE FROM tip_channels WHERE guild_id = $1 AND channel_id = $2",
+        guild_id.0 as i64,
+        channel_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns the channels that allow tipping, of all guilds.
+pub async fn get_tip_channels(pool: &PgPool) -> Result<Vec<(GuildId, ChannelId)>, Error> {
+    let rows = sqlx::query!("SELECT guild_id, channel_id FROM tip_channels")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                GuildId(row.guild_id as u64),
+                ChannelId(row.channel_id as u64),
+            )
+        })
+        .collect())
+}
+
+pub async fn get_bot_role(
+    pool: &PgPool,
+    user_id: &UserId,
+    guild_id: GuildId,
+) -> Result<Option<BotRole>, Error> {
+    if let Some(row) = sqlx::query!(
+        "SELECT role FROM bot_roles WHERE discord_id = $1 AND guild_id = $2",
+        user_id.0 as i64,
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?
+    {
+        // a role that can't be read gives no access
+        match BotRole::try_from(row.role) {
+            Ok(role) => Ok(Some(role)),
+            Err(e) => {
+                warn!("ignoring the bot role of {user_id} in {guild_id}: {e}");
+                Ok(None)
+            }
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn set_bot_role(
+    pool: &PgPool,
+    user_id: &UserId,
+    guild_id: GuildId,
+    role: BotRole,
+    granted_by: &UserId,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO bot_roles (discord_id, guild_id, role, granted_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (discord_id, guild_id)
+        DO UPDATE SET role = $3, granted_by = $4",
+        user_id.0 as i64,
+        guild_id.0 as i64,
+        role.as_str(),
+        granted_by.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_bot_role(
+    pool: &PgPool,
+    user_id: &UserId,
+    guild_id: GuildId,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "DELETE FROM bot_roles WHERE discord_id = $1 AND guild_id = $2",
+        user_id.0 as i64,
+        guild_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_bot_roles(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Vec<(UserId, BotRole)>, Error> {
+    let rows = sqlx::query!(
+        "SELECT discord_id, role FROM bot_roles WHERE guild_id = $1 ORDER BY role, discord_id",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| match BotRole::try_from(row.role) {
+            Ok(role) => Some((UserId(row.discord_id as u64), role)),
+            Err(e) => {
+                warn!(
+                    "ignoring the bot role of {} in {guild_id}: {e}",
+                    row.discord_id
+                );
+                None
+            }
+        })
+        .collect())
+}
+
+pub async fn insert_audit_entry(
+    pool: &PgPool,
+    guild_id: GuildId,
+    actor: UserId,
+    action: &str,
+    target: Option<String>,
+    details: Option<String>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO audit_log (guild_id, actor, action, target, details) VALUES ($1, $2, $3, $4, $5)",
+        guild_id.0 as i64,
+        actor.0 as i64,
+        action,
+        target,
+        details
     )
     .execute(pool)
     .await?;
 
     Ok(())
 }
+
+/// Returns the most recent audit entries of a guild, newest first.
+pub async fn get_audit_entries(
+    pool: &PgPool,
+    guild_id: GuildId,
+    limit: i64,
+) -> Result<Vec<AuditEntry>, Error> {
+    let rows = sqlx::query!(
+        "SELECT * FROM audit_log WHERE guild_id = $1 ORDER BY created_at DESC LIMIT $2",
+        guild_id.0 as i64,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| AuditEntry {
+            id: row.id,
+            guild_id: GuildId(row.guild_id as u64),
+            actor: UserId(row.actor as u64),
+            action: row.action,
+            target: row.target,
+            details: row.details,
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+/// Returns the most recent tips that `user_id` sent or received, excluding tips to oneself.
+pub async fn get_recent_tips(
+    pool: &PgPool,
+    user_id: &UserId,
+    limit: i64,
+) -> Result<Vec<Tip>, Error> {
+    let _timer = metrics::db_timer("get_recent_tips");
+    let rows = sqlx::query!(
+        "SELECT uuid, discord_id, kind, amount, counterparty, memo, guild_id, created_at FROM tips_vrsc
+        WHERE (discord_id = $1 OR counterparty = $2) AND discord_id::text <> counterparty
+        ORDER BY created_at DESC LIMIT $3",
+        user_id.0 as i64,
+        user_id.0.to_string(),
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Tip {
+            uuid: row.uuid,
+            recipient: UserId(row.discord_id as u64),
+            tipper: UserId(row.counterparty.parse::<u64>().unwrap_or_default()),
+            kind: row.kind,
+            amount: Amount::from_sat(row.amount as u64),
+            memo: row.memo,
+            guild_id: row.guild_id.map(|guild_id| GuildId(guild_id as u64)),
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+pub async fn get_tip(pool: &PgPool, uuid: &str, recipient: &UserId) -> Result<Option<Tip>, Error> {
+    let _timer = metrics::db_timer("get_tip");
+    let row = sqlx::query!(
+        "SELECT uuid, discord_id, kind, amount, counterparty, memo, guild_id, created_at FROM tips_vrsc
+        WHERE uuid = $1 AND discord_id = $2",
+        uuid,
+        recipient.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| Tip {
+        uuid: row.uuid,
+        recipient: UserId(row.discord_id as u64),
+        tipper: UserId(row.counterparty.parse::<u64>().unwrap_or_default()),
+        kind: row.kind,
+        amount: Amount::from_sat(row.amount as u64),
+        memo: row.memo,
+        guild_id: row.guild_id.map(|guild_id| GuildId(guild_id as u64)),
+        created_at: row.created_at,
+    }))
+}
+
+/// The recipients of the tips of a tip event.
+pub async fn get_tip_recipients(pool: &PgPool, uuid: &str) -> Result<Vec<UserId>, Error> {
+    let _timer = metrics::db_timer("get_tip_recipients");
+    let rows = sqlx::query!(
+        "SELECT DISTINCT discord_id FROM tips_vrsc WHERE uuid = $1",
+        uuid
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| UserId(row.discord_id as u64))
+        .collect())
+}
+
+/// Stores the announcement of a tip event in a channel, see [`get_tip_message_links`].
+pub async fn store_tip_message(
+    pool: &PgPool,
+    tip_event_id: &Uuid,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_tip_message");
+    sqlx::query!(
+        "INSERT INTO tip_messages (tip_event_id, guild_id, channel_id, message_id) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (tip_event_id) DO NOTHING",
+        tip_event_id.to_string(),
+        guild_id.map(|guild_id| guild_id.0 as i64),
+        channel_id.0 as i64,
+        message_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The links to the announcements of the tip events that have one, by tip event id.
+pub async fn get_tip_message_links(
+    pool: &PgPool,
+    tip_event_ids: &[String],
+) -> Result<HashMap<String, String>, Error> {
+    let _timer = metrics::db_timer("get_tip_message_links");
+    let rows = sqlx::query!(
+        "SELECT tip_event_id, guild_id, channel_id, message_id FROM tip_messages
+        WHERE tip_event_id IN (SELECT * FROM UNNEST($1::text[]))",
+        tip_event_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let link = MessageId(row.message_id as u64).link(
+                ChannelId(row.channel_id as u64),
+                row.guild_id.map(|guild_id| GuildId(guild_id as u64)),
+            );
+
+            (row.tip_event_id, link)
+        })
+        .collect())
+}
+
+/// Takes the tips of a tip event back from its recipients and returns them to the tipper, if the event is less than 24
+/// hours old. A recipient is only clawed back from when they still have their whole tip and it is not disputed.
+pub async fn reverse_tip_event(
+    pool: &PgPool,
+    uuid: &str,
+    reversed_by: &UserId,
+) -> Result<Reversal, Error> {
+    let _timer = metrics::db_timer("reverse_tip_event");
+    let mut tx = pool.begin().await?;
+
+    // see `open_dispute`
+    let tips = sqlx::query!(
+        "SELECT discord_id, amount, counterparty FROM tips_vrsc
+        WHERE uuid = $1 AND created_at > NOW() - INTERVAL '24 hours'
+        FOR UPDATE",
+        uuid
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let Some(tipper) = tips
+        .first()
+        .and_then(|tip| tip.counterparty.parse::<u64>().ok())
+        .map(UserId)
+    else {
+        tx.rollback().await?;
+
+        return Ok(Reversal::NotFound);
+    };
+
+    let mut recovered = vec![];
+    let mut unrecovered = vec![];
+    let event_id = Uuid::new_v4().to_string();
+
+    for tip in tips {
+        let recipient = UserId(tip.discord_id as u64);
+        let amount = Amount::from_sat(tip.amount as u64);
+
+        // a disputed tip is already frozen and is settled by resolving the dispute
+        let disputed = sqlx::query!(
+            r#"SELECT EXISTS(SELECT 1 FROM disputes WHERE tip_uuid = $1 AND recipient = $2) AS "disputed!""#,
+            uuid,
+            tip.discord_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .disputed;
+
+        let clawed_back = !disputed
+            && post_to_ledger(
+                &mut tx,
+                &event_id,
+                EntryKind::Reversal,
+                &[
+                    (Account::User(recipient), -tip.amount),
+                    (Account::User(tipper), tip.amount),
+                ],
+            )
+            .await?;
+
+        match clawed_back {
+            false => unrecovered.push((recipient, amount)),
+            true => recovered.push((recipient, amount)),
+        }
+    }
+
+    let total = |tips: &Vec<(UserId, Amount)>| {
+        tips.iter()
+            .map(|(_, amount)| amount.as_sat() as i64)
+            .sum::<i64>()
+    };
+
+    let inserted = sqlx::query!(
+        "INSERT INTO tip_reversals (tip_uuid, reversed_by, tipper, recovered, unrecovered)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (tip_uuid) DO NOTHING",
+        uuid,
+        reversed_by.0 as i64,
+        tipper.0 as i64,
+        total(&recovered),
+        total(&unrecovered)
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if inserted.rows_affected() == 0 {
+        tx.rollback().await?;
+
+        return Ok(Reversal::AlreadyReversed);
+    }
+
+    tx.commit().await?;
+
+    Ok(Reversal::Reversed {
+        tipper,
+        recovered,
+        unrecovered,
+    })
+}
+
+pub async fn dispute_exists(pool: &PgPool, uuid: &str, recipient: &UserId) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("dispute_exists");
+    let row = sqlx::query!(
+        "SELECT id FROM disputes WHERE tip_uuid = $1 AND recipient = $2",
+        uuid,
+        recipient.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some())
+}
+
+// Opening a dispute freezes the disputed amount: it is taken from the balance of the recipient and held by the dispute
+// until it is resolved. Nothing is changed when the tip was reversed with `/reverse`, as the tipper already got it back,
+// or when the recipient no longer has enough balance to cover the tip.
+pub async fn open_dispute(
+    pool: &PgPool,
+    tip: &Tip,
+    guild_id: GuildId,
+    opened_by: &UserId,
+    reason: &str,
+) -> Result<DisputeOpening, Error> {
+    let _timer = metrics::db_timer("open_dispute");
+    let mut tx = pool.begin().await?;
+
+    // locking the tip makes a dispute wait for a reversal of its tip event that is in progress, and the other way around
+    sqlx::query!(
+        "SELECT uuid FROM tips_vrsc WHERE uuid = $1 AND discord_id = $2 FOR UPDATE",
+        tip.uuid,
+        tip.recipient.0 as i64
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let reversed = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM tip_reversals WHERE tip_uuid = $1) AS "reversed!""#,
+        tip.uuid
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .reversed;
+    if reversed {
+        debug!("tip {} was reversed, it can't be disputed", tip.uuid);
+
+        return Ok(DisputeOpening::Reversed);
+    }
+
+    let row = sqlx::query!(
+        "INSERT INTO disputes (tip_uuid, guild_id, opened_by, tipper, recipient, amount, reason, status)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, 'open')
+        RETURNING id",
+        tip.uuid,
+        guild_id.0 as i64,
+        opened_by.0 as i64,
+        tip.tipper.0 as i64,
+        tip.recipient.0 as i64,
+        tip.amount.as_sat() as i64,
+        reason
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let frozen = post_to_ledger(
+        &mut tx,
+        &format!("dispute:{}", row.id),
+        EntryKind::Dispute,
+        &[
+            (Account::User(tip.recipient), -(tip.amount.as_sat() as i64)),
+            (Account::Escrow, tip.amount.as_sat() as i64),
+        ],
+    )
+    .await?;
+
+    if !frozen {
+        debug!(
+            "recipient does not have enough balance to freeze {}",
+            tip.amount
+        );
+        tx.rollback().await?;
+
+        return Ok(DisputeOpening::InsufficientBalance);
+    }
+
+    tx.commit().await?;
+
+    Ok(DisputeOpening::Opened(row.id))
+}
+
+pub async fn set_dispute_thread(
+    pool: &PgPool,
+    dispute_id: i64,
+    thread_id: ChannelId,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_dispute_thread");
+    sqlx::query!(
+        "UPDATE disputes SET thread_id = $2 WHERE id = $1",
+        dispute_id,
+        thread_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Resolving a dispute pays the frozen amount to either the recipient (released) or the tipper (refunded).
+// Returns None if there is no open dispute with this id, so a dispute can only be resolved once.
+pub async fn resolve_dispute(
+    pool: &PgPool,
+    dispute_id: i64,
+    guild_id: GuildId,
+    outcome: DisputeOutcome,
+    resolved_by: &UserId,
+) -> Result<Option<Dispute>, Error> {
+    let _timer = metrics::db_timer("resolve_dispute");
+    let mut tx = pool.begin().await?;
+
+    let Some(row) = sqlx::query!(
+        "UPDATE disputes SET status = $3, resolved_by = $4
+        WHERE id = $1 AND guild_id = $2 AND status = 'open'
+        RETURNING *",
+        dispute_id,
+        guild_id.0 as i64,
+        outcome.as_str(),
+        resolved_by.0 as i64
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        tx.rollback().await?;
+
+        return Ok(None);
+    };
+
+    let dispute = Dispute {
+        id: row.id,
+        tip_uuid: row.tip_uuid,
+        opened_by: UserId(row.opened_by as u64),
+        tipper: UserId(row.tipper as u64),
+        recipient: UserId(row.recipient as u64),
+        amount: Amount::from_sat(row.amount as u64),
+        reason: row.reason,
+        thread_id: row.thread_id.map(|id| ChannelId(id as u64)),
+        created_at: row.created_at,
+    };
+
+    let beneficiary = match outcome {
+        DisputeOutcome::Release => dispute.recipient,
+        DisputeOutcome::Refund => dispute.tipper,
+    };
+
+    post_to_ledger(
+        &mut tx,
+        &format!("dispute:{}", dispute.id),
+        EntryKind::Dispute,
+        &[
+            (Account::Escrow, -(dispute.amount.as_sat() as i64)),
+            (Account::User(beneficiary), dispute.amount.as_sat() as i64),
+        ],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(dispute))
+}
+
+pub async fn get_open_disputes(pool: &PgPool, guild_id: GuildId) -> Result<Vec<Dispute>, Error> {
+    let _timer = metrics::db_timer("get_open_disputes");
+    let rows = sqlx::query!(
+        "SELECT * FROM disputes WHERE guild_id = $1 AND status = 'open' ORDER BY created_at",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Dispute {
+            id: row.id,
+            tip_uuid: row.tip_uuid,
+            opened_by: UserId(row.opened_by as u64),
+            tipper: UserId(row.tipper as u64),
+            recipient: UserId(row.recipient as u64),
+            amount: Amount::from_sat(row.amount as u64),
+            reason: row.reason,
+            thread_id: row.thread_id.map(|id| ChannelId(id as u64)),
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+/// Returns the number of finished reactdrops in a guild and their average pot size.
+pub async fn get_reactdrop_totals(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<(i64, Amount), Error> {
+    let _timer = metrics::db_timer("get_reactdrop_totals");
+    let row = sqlx::query!(
+        "SELECT COUNT(*) AS drops, AVG(amount)::float8 AS average_pot FROM reactdrops
+        WHERE guild_id = $1 AND status = 'processed'",
+        guild_id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((
+        row.drops.unwrap_or(0),
+        Amount::from_sat(row.average_pot.unwrap_or(0.0) as u64),
+    ))
+}
+
+/// Returns the most recent finished reactdrops of a guild, newest first.
+pub async fn get_recent_reactdrop_results(
+    pool: &PgPool,
+    guild_id: GuildId,
+    limit: i64,
+) -> Result<Vec<DropResult>, Error> {
+    let _timer = metrics::db_timer("get_recent_reactdrop_results");
+    let rows = sqlx::query!(
+        "SELECT created_at, amount, participants, eligible FROM reactdrops
+        WHERE guild_id = $1 AND status = 'processed'
+        ORDER BY created_at DESC LIMIT $2",
+        guild_id.0 as i64,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DropResult {
+            started_at: row.created_at,
+            pot: Amount::from_sat(row.amount as u64),
+            participants: row.participants,
+            eligible: row.eligible,
+        })
+        .collect())
+}
+
+/// Returns how often every user has won a reactdrop in a guild, most wins first.
+pub async fn get_reactdrop_winners(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Vec<(UserId, i64)>, Error> {
+    let _timer = metrics::db_timer("get_reactdrop_winners");
+    let rows = sqlx::query!(
+        "SELECT t.discord_id, COUNT(*) AS wins FROM tips_vrsc t
+        JOIN reactdrops r ON r.tip_uuid = t.uuid
+        WHERE r.guild_id = $1
+        GROUP BY t.discord_id
+        ORDER BY wins DESC",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (UserId(row.discord_id as u64), row.wins.unwrap_or(0)))
+        .collect())
+}
+
+/// Groups the finished reactdrops of a guild by the hour (UTC) they were started in.
+pub async fn get_reactdrop_hours(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Vec<HourStats>, Error> {
+    let _timer = metrics::db_timer("get_reactdrop_hours");
+    let rows = sqlx::query!(
+        "SELECT EXTRACT(HOUR FROM created_at AT TIME ZONE 'UTC')::int AS hour,
+            COUNT(*) AS drops,
+            AVG(participants)::float8 AS average_participants,
+            AVG(participants::float8 / NULLIF(eligible, 0)) AS participation_rate
+        FROM reactdrops
+        WHERE guild_id = $1 AND status = 'processed' AND participants IS NOT NULL
+        GROUP BY hour
+        ORDER BY hour",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| HourStats {
+            hour: row.hour.unwrap_or(0),
+            drops: row.drops.unwrap_or(0),
+            average_participants: row.average_participants.unwrap_or(0.0),
+            participation_rate: row.participation_rate,
+        })
+        .collect())
+}
+
+/// The number of rows of the main tables, for `/admin dbinfo`.
+pub async fn get_table_row_counts(pool: &PgPool) -> Result<Vec<(&'static str, i64)>, Error> {
+    let _timer = metrics::db_timer("get_table_row_counts");
+    let row = sqlx::query!(
+        r#"SELECT
+            (SELECT COUNT(*) FROM discord_users) AS "discord_users!",
+            (SELECT COUNT(*) FROM tips_vrsc) AS "tips_vrsc!",
+            (SELECT COUNT(*) FROM transactions_vrsc) AS "transactions_vrsc!",
+            (SELECT COUNT(*) FROM ledger_entries) AS "ledger_entries!",
+            (SELECT COUNT(*) FROM reactdrops) AS "reactdrops!",
+            (SELECT COUNT(*) FROM outbox) AS "outbox!""#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(vec![
+        ("discord_users", row.discord_users),
+        ("tips_vrsc", row.tips_vrsc),
+        ("transactions_vrsc", row.transactions_vrsc),
+        ("ledger_entries", row.ledger_entries),
+        ("reactdrops", row.reactdrops),
+        ("outbox", row.outbox),
+    ])
+}
+
+pub async fn ping(pool: &PgPool) -> Result<(), Error> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+
+    Ok(())
+}
+
+// Moves funds from the balance of a user to the treasury of a guild.
+// Returns false when the user does not have enough balance, in which case nothing is changed.
+pub async fn donate_to_treasury(
+    pool: &PgPool,
+    guild_id: GuildId,
+    user_id: &UserId,
+    amount: Amount,
+    memo: Option<&str>,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("donate_to_treasury");
+    let mut tx = pool.begin().await?;
+
+    let donated = post_to_ledger(
+        &mut tx,
+        &Uuid::new_v4().to_string(),
+        EntryKind::Donation,
+        &[
+            (Account::User(*user_id), -(amount.as_sat() as i64)),
+            (Account::Treasury(guild_id), amount.as_sat() as i64),
+        ],
+    )
+    .await?;
+
+    if !donated {
+        tx.rollback().await?;
+
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "INSERT INTO treasury_ledger (guild_id, kind, amount, counterparty, memo) VALUES ($1, $2, $3, $4, $5)",
+        guild_id.0 as i64,
+        TreasuryKind::Donation.to_string(),
+        amount.as_sat() as i64,
+        user_id.0 as i64,
+        memo
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Returns the treasury entries of a guild in the given period, oldest first.
+pub async fn get_treasury_entries(
+    pool: &PgPool,
+    guild_id: GuildId,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<TreasuryEntry>, Error> {
+    let _timer = metrics::db_timer("get_treasury_entries");
+    let rows = sqlx::query!(
+        "SELECT * FROM treasury_ledger
+        WHERE guild_id = $1 AND created_at >= $2 AND created_at < $3
+        ORDER BY created_at, id",
+        guild_id.0 as i64,
+        from,
+        until
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TreasuryEntry {
+            id: row.id,
+            kind: row.kind.into(),
+            amount: Amount::from_sat(row.amount as u64),
+            counterparty: row.counterparty.map(|id| UserId(id as u64)),
+            memo: row.memo,
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+/// Returns the referral code of a user, after storing `code` as their code if they did not have one yet.
+pub async fn get_or_store_referral_code(
+    pool: &PgPool,
+    user_id: &UserId,
+    code: &str,
+) -> Result<String, Error> {
+    let _timer = metrics::db_timer("get_or_store_referral_code");
+    sqlx::query!(
+        "INSERT INTO referral_codes (discord_id, code)
+        VALUES ($1, $2)
+        ON CONFLICT (discord_id) DO NOTHING",
+        user_id.0 as i64,
+        code
+    )
+    .execute(pool)
+    .await?;
+
+    let row = sqlx::query!(
+        "SELECT code FROM referral_codes WHERE discord_id = $1",
+        user_id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.code)
+}
+
+/// Makes `referee` a referral of the owner of `code`, unless that is not allowed: a user can only be referred once,
+/// not by themselves, not by someone they referred, and not after they already sent a tip.
+pub async fn claim_referral(
+    pool: &PgPool,
+    referee: &UserId,
+    code: &str,
+) -> Result<ReferralClaim, Error> {
+    let _timer = metrics::db_timer("claim_referral");
+    let mut tx = pool.begin().await?;
+
+    let Some(referrer) = sqlx::query!(
+        "SELECT discord_id FROM referral_codes WHERE code = $1",
+        code
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|row| UserId(row.discord_id as u64)) else {
+        return Ok(ReferralClaim::UnknownCode);
+    };
+
+    if referrer == *referee {
+        return Ok(ReferralClaim::OwnCode);
+    }
+
+    let row = sqlx::query!(
+        r#"SELECT
+            EXISTS (SELECT 1 FROM referrals WHERE referee = $1) AS "referred!",
+            EXISTS (SELECT 1 FROM referrals WHERE referee = $2 AND referrer = $1) AS "mutual!",
+            EXISTS (SELECT 1 FROM tips_vrsc WHERE counterparty = $1::bigint::text) AS "tipped!""#,
+        referee.0 as i64,
+        referrer.0 as i64
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if row.referred {
+        return Ok(ReferralClaim::AlreadyReferred);
+    }
+    if row.mutual {
+        return Ok(ReferralClaim::Mutual);
+    }
+    if row.tipped {
+        return Ok(ReferralClaim::NotNew);
+    }
+
+    let inserted = sqlx::query!(
+        "INSERT INTO referrals (referee, referrer)
+        VALUES ($1, $2)
+        ON CONFLICT (referee) DO NOTHING",
+        referee.0 as i64,
+        referrer.0 as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(match inserted.rows_affected() {
+        1 => ReferralClaim::Claimed(referrer),
+        _ => ReferralClaim::AlreadyReferred,
+    })
+}
+
+/// The number of users a user referred, and how many of them were rewarded.
+pub async fn get_referral_counts(pool: &PgPool, referrer: &UserId) -> Result<(i64, i64), Error> {
+    let _timer = metrics::db_timer("get_referral_counts");
+    let row = sqlx::query!(
+        r#"SELECT COUNT(*) AS "referred!", COUNT(rewarded_at) AS "rewarded!"
+        FROM referrals WHERE referrer = $1"#,
+        referrer.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((row.referred, row.rewarded))
+}
+
+/// Pays the referral rewards of `referee` from the referral pool, if they were referred and not rewarded yet. Returns
+/// the referrer and the rewards of the referrer and the referee when they were paid. The rewards stay pending while
+/// they are 0, or while the pool can not pay them.
+pub async fn pay_referral_rewards(
+    pool: &PgPool,
+    referee: &UserId,
+) -> Result<Option<(UserId, Amount, Amount)>, Error> {
+    let _timer = metrics::db_timer("pay_referral_rewards");
+    let mut tx = pool.begin().await?;
+
+    let Some(referrer) = sqlx::query!(
+        "SELECT referrer FROM referrals WHERE referee = $1 AND rewarded_at IS NULL FOR UPDATE",
+        referee.0 as i64
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|row| UserId(row.referrer as u64)) else {
+        return Ok(None);
+    };
+
+    // locking the settings makes rewards wait for each other, so two rewards can't both spend the last of the pool
+    let rewards =
+        sqlx::query!("SELECT referrer_reward, referee_reward FROM referral_settings FOR UPDATE")
+            .fetch_one(&mut *tx)
+            .await?;
+    let total = rewards.referrer_reward + rewards.referee_reward;
+    if total == 0 {
+        return Ok(None);
+    }
+
+    let balance = referral_pool_balance(&mut tx).await?;
+    if balance < total {
+        warn!("referral pool has {balance} sats, which is not enough to reward {referee}");
+        return Ok(None);
+    }
+
+    post_to_ledger(
+        &mut tx,
+        &Uuid::new_v4().to_string(),
+        EntryKind::ReferralReward,
+        &[
+            (Account::Referrals, -total),
+            (Account::User(referrer), rewards.referrer_reward),
+            (Account::User(*referee), rewards.referee_reward),
+        ],
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE referrals SET rewarded_at = NOW(), referrer_reward = $2, referee_reward = $3
+        WHERE referee = $1",
+        referee.0 as i64,
+        rewards.referrer_reward,
+        rewards.referee_reward
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some((
+        referrer,
+        Amount::from_sat(rewards.referrer_reward as u64),
+        Amount::from_sat(rewards.referee_reward as u64),
+    )))
+}
+
+async fn referral_pool_balance(conn: &mut PgConnection) -> Result<i64, Error> {
+    let row = sqlx::query!(
+        r#"SELECT COALESCE(SUM(amount), 0)::bigint AS "balance!" FROM ledger_entries WHERE account = $1"#,
+        Account::Referrals.to_string()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(row.balance)
+}
+
+/// What is left in the referral pool to pay rewards from.
+pub async fn get_referral_pool(pool: &PgPool) -> Result<Amount, Error> {
+    let _timer = metrics::db_timer("get_referral_pool");
+    let balance = referral_pool_balance(&mut *pool.acquire().await?).await?;
+
+    Ok(Amount::from_sat(balance.max(0) as u64))
+}
+
+/// Moves `amount` from the balance of `user_id` into the referral pool. Returns false when their balance is too low.
+pub async fn fund_referral_pool(
+    pool: &PgPool,
+    user_id: &UserId,
+    amount: Amount,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("fund_referral_pool");
+    let mut conn = pool.acquire().await?;
+
+    post_to_ledger(
+        &mut conn,
+        &Uuid::new_v4().to_string(),
+        EntryKind::ReferralFunding,
+        &[
+            (Account::User(*user_id), -(amount.as_sat() as i64)),
+            (Account::Referrals, amount.as_sat() as i64),
+        ],
+    )
+    .await
+}
+
+/// The rewards of the referrer and the referee of a referral.
+pub async fn get_referral_rewards(pool: &PgPool) -> Result<(Amount, Amount), Error> {
+    let _timer = metrics::db_timer("get_referral_rewards");
+    let row = sqlx::query!("SELECT referrer_reward, referee_reward FROM referral_settings")
+        .fetch_one(pool)
+        .await?;
+
+    Ok((
+        Amount::from_sat(row.referrer_reward as u64),
+        Amount::from_sat(row.referee_reward as u64),
+    ))
+}
+
+pub async fn set_referral_rewards(
+    pool: &PgPool,
+    referrer_reward: Amount,
+    referee_reward: Amount,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_referral_rewards");
+    sqlx::query!(
+        "UPDATE referral_settings SET referrer_reward = $1, referee_reward = $2",
+        referrer_reward.as_sat() as i64,
+        referee_reward.as_sat() as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_withdrawals_frozen(pool: &PgPool) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("get_withdrawals_frozen");
+    let row = sqlx::query!("SELECT withdrawals_frozen FROM hot_wallet_settings")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.withdrawals_frozen)
+}
+
+pub async fn set_withdrawals_frozen(pool: &PgPool, frozen: bool) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_withdrawals_frozen");
+    sqlx::query!(
+        "UPDATE hot_wallet_settings SET withdrawals_frozen = $1",
+        frozen
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Pays the daily reward of a guild to `user_id` when their cooldown is over, see [`daily`]. The cooldown is checked
+/// against the clock of the database, never against anything the user sent.
+pub async fn claim_daily(
+    pool: &PgPool,
+    guild_id: GuildId,
+    user_id: &UserId,
+) -> Result<DailyClaim, Error> {
+    let _timer = metrics::db_timer("claim_daily");
+    let mut tx = pool.begin().await?;
+
+    // locking the settings makes the claims in a guild wait for each other, so a user can't claim twice at the same
+    // time and two claims can't both spend the last of the pool
+    let base = sqlx::query!(
+        "SELECT daily_reward FROM guild_settings WHERE guild_id = $1 FOR UPDATE",
+        guild_id.0 as i64
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .map_or(0, |row| row.daily_reward);
+    if base == 0 {
+        return Ok(DailyClaim::Off);
+    }
+
+    let now = sqlx::query!(r#"SELECT NOW() AS "now!""#)
+        .fetch_one(&mut *tx)
+        .await?
+        .now;
+    let last = sqlx::query!(
+        "SELECT streak, last_claim_at FROM daily_streaks WHERE guild_id = $1 AND discord_id = $2",
+        guild_id.0 as i64,
+        user_id.0 as i64
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|row| (row.streak, row.last_claim_at));
+
+    let streak = match daily::next_streak(last, now) {
+        Ok(streak) => streak,
+        Err(next_claim_at) => return Ok(DailyClaim::Cooldown(next_claim_at)),
+    };
+    let amount = daily::reward(Amount::from_sat(base as u64), streak);
+
+    let balance = daily_pool_balance(&mut tx, guild_id).await?;
+    if balance < amount.as_sat() as i64 {
+        debug!("daily pool of {guild_id} has {balance} sats, which is not enough for {amount}");
+        return Ok(DailyClaim::PoolEmpty);
+    }
+
+    post_to_ledger(
+        &mut tx,
+        &Uuid::new_v4().to_string(),
+        EntryKind::DailyReward,
+        &[
+            (Account::Daily(guild_id), -(amount.as_sat() as i64)),
+            (Account::User(*user_id), amount.as_sat() as i64),
+        ],
+    )
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO daily_streaks (guild_id, discord_id, streak, best_streak, last_claim_at, total_claimed)
+        VALUES ($1, $2, $3, $3, $4, $5)
+        ON CONFLICT (guild_id, discord_id)
+        DO UPDATE SET streak = $3, best_streak = GREATEST(daily_streaks.best_streak, $3), last_claim_at = $4,
+            total_claimed = daily_streaks.total_claimed + $5",
+        guild_id.0 as i64,
+        user_id.0 as i64,
+        streak,
+        now,
+        amount.as_sat() as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO daily_claims (guild_id, discord_id, streak, amount) VALUES ($1, $2, $3, $4)",
+        guild_id.0 as i64,
+        user_id.0 as i64,
+        streak,
+        amount.as_sat() as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(DailyClaim::Claimed { amount, streak })
+}
+
+async fn daily_pool_balance(conn: &mut PgConnection, guild_id: GuildId) -> Result<i64, Error> {
+    let row = sqlx::query!(
+        r#"SELECT COALESCE(SUM(amount), 0)::bigint AS "balance!" FROM ledger_entries WHERE account = $1"#,
+        Account::Daily(guild_id).to_string()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(row.balance)
+}
+
+/// What is left in the pool that the daily rewards of a guild are paid from.
+pub async fn get_daily_pool(pool: &PgPool, guild_id: GuildId) -> Result<Amount, Error> {
+    let _timer = metrics::db_timer("get_daily_pool");
+    let balance = daily_pool_balance(&mut *pool.acquire().await?, guild_id).await?;
+
+    Ok(Amount::from_sat(balance.max(0) as u64))
+}
+
+/// Moves `amount` from the balance of `user_id` into the daily pool of a guild. Returns false when their balance is
+/// too low.
+pub async fn fund_daily_pool(
+    pool: &PgPool,
+    guild_id: GuildId,
+    user_id: &UserId,
+    amount: Amount,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("fund_daily_pool");
+    let mut conn = pool.acquire().await?;
+
+    post_to_ledger(
+        &mut conn,
+        &Uuid::new_v4().to_string(),
+        EntryKind::DailyFunding,
+        &[
+            (Account::User(*user_id), -(amount.as_sat() as i64)),
+            (Account::Daily(guild_id), amount.as_sat() as i64),
+        ],
+    )
+    .await
+}
+
+/// The reward of the first day of a daily streak in a guild. Zero means `/daily` is off, which is the default.
+pub async fn get_daily_reward(pool: &PgPool, guild_id: GuildId) -> Result<Amount, Error> {
+    let _timer = metrics::db_timer("get_daily_reward");
+    let row = sqlx::query!(
+        "SELECT daily_reward FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(Amount::from_sat(
+        row.map_or(0, |row| row.daily_reward).max(0) as u64,
+    ))
+}
+
+pub async fn set_daily_reward(
+    pool: &PgPool,
+    guild_id: GuildId,
+    reward: Amount,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_daily_reward");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, daily_reward)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET daily_reward = $2",
+        guild_id.0 as i64,
+        reward.as_sat() as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The users of a guild with the longest streaks that are still going on, and their best streaks.
+pub async fn get_daily_leaderboard(
+    pool: &PgPool,
+    guild_id: GuildId,
+    limit: i64,
+) -> Result<Vec<DailyStreak>, Error> {
+    let _timer = metrics::db_timer("get_daily_leaderboard");
+    let rows = sqlx::query!(
+        r#"SELECT discord_id, best_streak,
+            (CASE WHEN last_claim_at > NOW() - make_interval(hours => $2) THEN streak ELSE 0 END) AS "streak!"
+        FROM daily_streaks
+        WHERE guild_id = $1
+        ORDER BY 3 DESC, best_streak DESC, discord_id
+        LIMIT $3"#,
+        guild_id.0 as i64,
+        daily::STREAK_HOURS as i32,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DailyStreak {
+            user_id: UserId(row.discord_id as u64),
+            streak: row.streak,
+            best_streak: row.best_streak,
+        })
+        .collect())
+}
+
+/// Stores a new competition. Returns its id, or `None` when the guild already has a running competition.
+pub async fn start_competition(
+    pool: &PgPool,
+    competition: &Competition,
+) -> Result<Option<i64>, Error> {
+    let _timer = metrics::db_timer("start_competition");
+    let row = sqlx::query!(
+        "INSERT INTO competitions (guild_id, channel_id, started_by, metric, prize, min_tip, ends_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (guild_id) WHERE status = 'running' DO NOTHING
+        RETURNING id",
+        competition.guild_id.0 as i64,
+        competition.channel_id.0 as i64,
+        competition.started_by.0 as i64,
+        competition.metric.as_str(),
+        competition.prize.as_sat() as i64,
+        competition.min_tip.as_sat() as i64,
+        competition.ends_at
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.id))
+}
+
+pub async fn get_running_competition(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Option<Competition>, Error> {
+    let _timer = metrics::db_timer("get_running_competition");
+    let row = sqlx::query!(
+        "SELECT id, guild_id, channel_id, started_by, metric, prize, min_tip, ends_at FROM competitions
+        WHERE guild_id = $1 AND status = 'running'",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| Competition {
+        id: row.id,
+        guild_id: GuildId(row.guild_id as u64),
+        channel_id: ChannelId(row.channel_id as u64),
+        started_by: UserId(row.started_by as u64),
+        metric: CompetitionMetric::from(row.metric),
+        prize: Amount::from_sat(row.prize as u64),
+        min_tip: Amount::from_sat(row.min_tip as u64),
+        ends_at: row.ends_at,
+    }))
+}
+
+/// The running competitions whose time is up.
+pub async fn get_ended_competitions(pool: &PgPool) -> Result<Vec<Competition>, Error> {
+    let _timer = metrics::db_timer("get_ended_competitions");
+    let rows = sqlx::query!(
+        "SELECT id, guild_id, channel_id, started_by, metric, prize, min_tip, ends_at FROM competitions
+        WHERE status = 'running' AND ends_at <= NOW()"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Competition {
+            id: row.id,
+            guild_id: GuildId(row.guild_id as u64),
+            channel_id: ChannelId(row.channel_id as u64),
+            started_by: UserId(row.started_by as u64),
+            metric: CompetitionMetric::from(row.metric),
+            prize: Amount::from_sat(row.prize as u64),
+            min_tip: Amount::from_sat(row.min_tip as u64),
+            ends_at: row.ends_at,
+        })
+        .collect())
+}
+
+/// Cancels the running competition of a guild. Returns its id, or `None` when there was none.
+pub async fn cancel_competition(pool: &PgPool, guild_id: GuildId) -> Result<Option<i64>, Error> {
+    let _timer = metrics::db_timer("cancel_competition");
+    let row = sqlx::query!(
+        "UPDATE competitions SET status = 'cancelled' WHERE guild_id = $1 AND status = 'running' RETURNING id",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.id))
+}
+
+/// Counts a tip event for the running competition of a guild, if there is one and the share of every recipient is at
+/// least the minimum tip of the competition. Tips to the tipper themselves never count.
+pub async fn record_competition_tips(
+    pool: &PgPool,
+    guild_id: GuildId,
+    tip_event_id: Uuid,
+    tipper: &UserId,
+    recipients: &[UserId],
+    share: Amount,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("record_competition_tips");
+    let recipients = recipients
+        .iter()
+        .map(|user| user.0 as i64)
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        "INSERT INTO competition_tips (competition_id, tip_uuid, tipper, recipient, amount)
+        SELECT competitions.id, $2, $3, recipient, $5
+        FROM competitions, UNNEST($4::bigint[]) AS recipient
+        WHERE competitions.guild_id = $1 AND competitions.status = 'running' AND competitions.ends_at > NOW()
+            AND $5 >= competitions.min_tip AND recipient <> $3
+        ON CONFLICT DO NOTHING",
+        guild_id.0 as i64,
+        tip_event_id.to_string(),
+        tipper.0 as i64,
+        &recipients,
+        share.as_sat() as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The best scores of a competition, the best first. Ties go to whoever started tipping first.
+pub async fn get_competition_scores(
+    pool: &PgPool,
+    competition: &Competition,
+    limit: i64,
+) -> Result<Vec<(UserId, i64)>, Error> {
+    let _timer = metrics::db_timer("get_competition_scores");
+
+    competition_scores(&mut *pool.acquire().await?, competition, limit).await
+}
+
+async fn competition_scores(
+    conn: &mut PgConnection,
+    competition: &Competition,
+    limit: i64,
+) -> Result<Vec<(UserId, i64)>, Error> {
+    // per pair of tipper and recipient what was sent, minus what the recipient sent back for the amount metric
+    let rows = sqlx::query!(
+        r#"WITH pairs AS (
+            SELECT tipper, recipient, SUM(amount) AS sent, MIN(created_at) AS first_at
+            FROM competition_tips
+            WHERE competition_id = $1
+                AND NOT EXISTS (SELECT 1 FROM tip_reversals WHERE tip_reversals.tip_uuid = competition_tips.tip_uuid)
+            GROUP BY tipper, recipient
+        ), scores AS (
+            SELECT pairs.tipper,
+                (CASE WHEN $2 = 'amount' THEN SUM(GREATEST(pairs.sent - COALESCE(back.sent, 0), 0)) ELSE COUNT(*) END)::bigint
+                    AS score,
+                MIN(pairs.first_at) AS first_at
+            FROM pairs
+            LEFT JOIN pairs AS back ON back.tipper = pairs.recipient AND back.recipient = pairs.tipper
+            GROUP BY pairs.tipper
+        )
+        SELECT tipper AS "tipper!", score AS "score!" FROM scores
+        WHERE score > 0
+        ORDER BY score DESC, first_at
+        LIMIT $3"#,
+        competition.id,
+        competition.metric.as_str(),
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (UserId(row.tipper as u64), row.score))
+        .collect())
+}
+
+/// Ends a competition and pays its prize from the treasury of the guild to the winner. Returns `None` when the
+/// competition is no longer running, e.g. because it was cancelled.
+pub async fn finish_competition(
+    pool: &PgPool,
+    competition: &Competition,
+) -> Result<Option<CompetitionResult>, Error> {
+    let _timer = metrics::db_timer("finish_competition");
+    let mut tx = pool.begin().await?;
+
+    let running = sqlx::query!(
+        "SELECT id FROM competitions WHERE id = $1 AND status = 'running' FOR UPDATE",
+        competition.id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if running.is_none() {
+        return Ok(None);
+    }
+
+    let winner = competition_scores(&mut tx, competition, 1)
+        .await?
+        .first()
+        .copied();
+    let result = match winner {
+        None => CompetitionResult::NoWinner,
+        Some((winner, score)) => {
+            let treasury = sqlx::query!(
+                r#"SELECT COALESCE(SUM(CASE WHEN kind IN ('fee', 'donation') THEN amount ELSE -amount END), 0)::bigint
+                    AS "balance!"
+                FROM treasury_ledger WHERE guild_id = $1"#,
+                competition.guild_id.0 as i64
+            )
+            .fetch_one(&mut *tx)
+            .await?
+            .balance;
+
+            if treasury < competition.prize.as_sat() as i64 {
+                warn!(
+                    "treasury of {} has {treasury} sats, which is not enough for the prize of competition {}",
+                    competition.guild_id, competition.id
+                );
+                CompetitionResult::Unpaid { winner, score }
+            } else {
+                post_to_ledger(
+                    &mut tx,
+                    &Uuid::new_v4().to_string(),
+                    EntryKind::Award,
+                    &[
+                        (
+                            Account::Treasury(competition.guild_id),
+                            -(competition.prize.as_sat() as i64),
+                        ),
+                        (Account::User(winner), competition.prize.as_sat() as i64),
+                    ],
+                )
+                .await?;
+                sqlx::query!(
+                    "INSERT INTO treasury_ledger (guild_id, kind, amount, counterparty, memo) VALUES ($1, $2, $3, $4, $5)",
+                    competition.guild_id.0 as i64,
+                    TreasuryKind::Award.to_string(),
+                    competition.prize.as_sat() as i64,
+                    winner.0 as i64,
+                    format!("Competition {}", competition.id)
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                CompetitionResult::Won { winner, score }
+            }
+        }
+    };
+
+    let (winner, paid) = match result {
+        CompetitionResult::Won { winner, .. } => (Some(winner), true),
+        CompetitionResult::Unpaid { winner, .. } => (Some(winner), false),
+        CompetitionResult::NoWinner => (None, false),
+    };
+    sqlx::query!(
+        "UPDATE competitions SET status = 'finished', winner = $2, prize_paid = $3 WHERE id = $1",
+        competition.id,
+        winner.map(|winner| winner.0 as i64),
+        paid
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some(result))
+}
+
+/// Opens a crowdfunding pot. Returns `None` when the guild already has an open pot with the same name.
+pub async fn create_pot(pool: &PgPool, pot: &Pot) -> Result<Option<i64>, Error> {
+    let _timer = metrics::db_timer("create_pot");
+    let (recipient_user, recipient_address) = match &pot.recipient {
+        PotRecipient::User(user_id) => (Some(user_id.0 as i64), None),
+        PotRecipient::Address(address) => (None, Some(address.as_str())),
+    };
+    let row = sqlx::query!(
+        "INSERT INTO pots (guild_id, channel_id, name, created_by, goal, recipient_user, recipient_address)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (guild_id, lower(name)) WHERE status = 'open' DO NOTHING
+        RETURNING id",
+        pot.guild_id.0 as i64,
+        pot.channel_id.0 as i64,
+        pot.name,
+        pot.created_by.0 as i64,
+        pot.goal.as_sat() as i64,
+        recipient_user,
+        recipient_address
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.id))
+}
+
+/// Stores the message that shows the progress of a pot.
+pub async fn set_pot_message(
+    pool: &PgPool,
+    pot_id: i64,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_pot_message");
+    sqlx::query!(
+        "UPDATE pots SET message_id = $2 WHERE id = $1",
+        pot_id,
+        message_id.0 as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The open pots of a guild, oldest first.
+pub async fn get_open_pots(pool: &PgPool, guild_id: GuildId) -> Result<Vec<Pot>, Error> {
+    let _timer = metrics::db_timer("get_open_pots");
+    get_pots(pool, guild_id, false).await
+}
+
+/// The pots of a guild that can be cancelled: the open ones, and the closed ones that were never paid out. The open
+/// pots come first.
+pub async fn get_cancellable_pots(pool: &PgPool, guild_id: GuildId) -> Result<Vec<Pot>, Error> {
+    let _timer = metrics::db_timer("get_cancellable_pots");
+    get_pots(pool, guild_id, true).await
+}
+
+async fn get_pots(pool: &PgPool, guild_id: GuildId, unpaid: bool) -> Result<Vec<Pot>, Error> {
+    let rows = sqlx::query!(
+        "SELECT id, guild_id, channel_id, message_id, name, created_by, goal, recipient_user, recipient_address
+        FROM pots
+        WHERE guild_id = $1 AND (status = 'open' OR ($2 AND status = 'closed' AND (
+            SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account = 'pot:' || pots.id
+        ) > 0))
+        ORDER BY status <> 'open', id",
+        guild_id.0 as i64,
+        unpaid
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Pot {
+            id: row.id,
+            guild_id: GuildId(row.guild_id as u64),
+            channel_id: ChannelId(row.channel_id as u64),
+            message_id: row.message_id.map(|id| MessageId(id as u64)),
+            name: row.name,
+            created_by: UserId(row.created_by as u64),
+            goal: Amount::from_sat(row.goal as u64),
+            recipient: match (row.recipient_user, row.recipient_address) {
+                (Some(user_id), _) => PotRecipient::User(UserId(user_id as u64)),
+                (None, address) => PotRecipient::Address(address.unwrap_or_default()),
+            },
+        })
+        .collect())
+}
+
+/// The open pot of a guild with this name, ignoring case.
+pub async fn get_open_pot(
+    pool: &PgPool,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<Option<Pot>, Error> {
+    Ok(get_open_pots(pool, guild_id)
+        .await?
+        .into_iter()
+        .find(|pot| pot.name.to_lowercase() == name.trim().to_lowercase()))
+}
+
+/// The pot of a guild with this name that can be cancelled, ignoring case, see [`get_cancellable_pots`]. An open pot
+/// goes before a closed pot with the same name.
+pub async fn get_cancellable_pot(
+    pool: &PgPool,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<Option<Pot>, Error> {
+    Ok(get_cancellable_pots(pool, guild_id)
+        .await?
+        .into_iter()
+        .find(|pot| pot.name.to_lowercase() == name.trim().to_lowercase()))
+}
+
+async fn pot_balance(conn: &mut PgConnection, pot_id: i64) -> Result<i64, Error> {
+    let row = sqlx::query!(
+        r#"SELECT COALESCE(SUM(amount), 0)::bigint AS "balance!" FROM ledger_entries WHERE account = $1"#,
+        Account::Pot(pot_id).to_string()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(row.balance)
+}
+
+/// What a pot holds: the contributions, until it is paid out or cancelled.
+pub async fn get_pot_raised(pool: &PgPool, pot_id: i64) -> Result<Amount, Error> {
+    let _timer = metrics::db_timer("get_pot_raised");
+    let balance = pot_balance(&mut *pool.acquire().await?, pot_id).await?;
+
+    Ok(Amount::from_sat(balance.max(0) as u64))
+}
+
+/// Moves `amount` from the balance of `user_id` into an open pot.
+pub async fn contribute_to_pot(
+    pool: &PgPool,
+    pot_id: i64,
+    user_id: &UserId,
+    amount: Amount,
+) -> Result<PotContribution, Error> {
+    let _timer = metrics::db_timer("contribute_to_pot");
+    let mut tx = pool.begin().await?;
+
+    // locking the pot makes a contribution wait for a close or cancel that is in progress
+    let open = sqlx::query!(
+        "SELECT id FROM pots WHERE id = $1 AND status = 'open' FOR UPDATE",
+        pot_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if open.is_none() {
+        return Ok(PotContribution::NotOpen);
+    }
+
+    if !post_to_ledger(
+        &mut tx,
+        &Uuid::new_v4().to_string(),
+        EntryKind::PotContribution,
+        &[
+            (Account::User(*user_id), -(amount.as_sat() as i64)),
+            (Account::Pot(pot_id), amount.as_sat() as i64),
+        ],
+    )
+    .await?
+    {
+        return Ok(PotContribution::InsufficientBalance);
+    }
+
+    sqlx::query!(
+        "INSERT INTO pot_contributions (pot_id, discord_id, amount) VALUES ($1, $2, $3)",
+        pot_id,
+        user_id.0 as i64,
+        amount.as_sat() as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+    let raised = pot_balance(&mut tx, pot_id).await?;
+    tx.commit().await?;
+
+    Ok(PotContribution::Added {
+        raised: Amount::from_sat(raised.max(0) as u64),
+    })
+}
+
+/// Closes an open pot, so nobody can contribute to it anymore, and returns what it raised, or `None` when the pot was
+/// not open. When `recipient` is given, the pot is paid out to them in the same transaction. Otherwise what it raised
+/// stays in the pot until it is withdrawn, see [`start_pot_withdrawal`].
+pub async fn close_pot(
+    pool: &PgPool,
+    pot_id: i64,
+    recipient: Option<UserId>,
+) -> Result<Option<Amount>, Error> {
+    let _timer = metrics::db_timer("close_pot");
+    let mut tx = pool.begin().await?;
+
+    let closed = sqlx::query!(
+        "UPDATE pots SET status = 'closed' WHERE id = $1 AND status = 'open' RETURNING id",
+        pot_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if closed.is_none() {
+        return Ok(None);
+    }
+
+    let raised = pot_balance(&mut tx, pot_id).await?;
+    if let Some(user_id) = recipient {
+        pay_out(
+            &mut tx,
+            pot_id,
+            raised,
+            &PotPayout::User(user_id),
+            &Uuid::new_v4(),
+            None,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Some(Amount::from_sat(raised.max(0) as u64)))
+}
+
+/// Marks a closed pot as withdrawing, before its withdrawal is sent, and returns what it holds. Returns `None` when
+/// the pot is not closed, like when it was cancelled or its withdrawal was already started. The withdrawal ends with
+/// [`pay_out_pot`], or with [`revert_pot_withdrawal`] when nothing was sent.
+pub async fn start_pot_withdrawal(pool: &PgPool, pot_id: i64) -> Result<Option<Amount>, Error> {
+    let _timer = metrics::db_timer("start_pot_withdrawal");
+    let mut tx = pool.begin().await?;
+
+    let started = sqlx::query!(
+        "UPDATE pots SET status = 'withdrawing' WHERE id = $1 AND status = 'closed' RETURNING id",
+        pot_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if started.is_none() {
+        return Ok(None);
+    }
+
+    let balance = pot_balance(&mut tx, pot_id).await?;
+    tx.commit().await?;
+
+    Ok(Some(Amount::from_sat(balance.max(0) as u64)))
+}
+
+/// Stores the withdrawal of a withdrawing pot with what it pays, before it is sent, and again with its operation as
+/// soon as it was sent, so the withdrawal can be resolved when the bot stops before the pot is paid out, see
+/// [`get_withdrawing_pots`]. Without an operation, the withdrawal might have been sent.
+pub async fn store_pot_withdrawal(
+    pool: &PgPool,
+    pot_id: i64,
+    opid: Option<&str>,
+    amount: Amount,
+    tx_fee: Amount,
+    bot_fee: Amount,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_pot_withdrawal");
+    sqlx::query!(
+        "UPDATE pots SET withdrawal_opid = $2, withdrawal_amount = $3, withdrawal_tx_fee = $4, withdrawal_bot_fee = $5
+        WHERE id = $1 AND status = 'withdrawing'",
+        pot_id,
+        opid,
+        amount.as_sat() as i64,
+        tx_fee.as_sat() as i64,
+        bot_fee.as_sat() as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The pots that are still withdrawing, like when the bot stopped after their withdrawal was started.
+pub async fn get_withdrawing_pots(pool: &PgPool) -> Result<Vec<WithdrawingPot>, Error> {
+    let _timer = metrics::db_timer("get_withdrawing_pots");
+    let rows = sqlx::query!(
+        "SELECT id, created_by, withdrawal_opid, withdrawal_amount, withdrawal_tx_fee, withdrawal_bot_fee
+        FROM pots WHERE status = 'withdrawing' ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| WithdrawingPot {
+            id: row.id,
+            created_by: UserId(row.created_by as u64),
+            sent: row.withdrawal_amount.map(|amount| {
+                let sats = |amount: Option<i64>| Amount::from_sat(amount.unwrap_or(0) as u64);
+                (
+                    row.withdrawal_opid,
+                    PotPayout::Withdrawal {
+                        amount: Amount::from_sat(amount as u64),
+                        tx_fee: sats(row.withdrawal_tx_fee),
+                        bot_fee: sats(row.withdrawal_bot_fee),
+                    },
+                )
+            }),
+        })
+        .collect())
+}
+
+/// Closes a withdrawing pot again when nothing was sent, so it can be withdrawn again or cancelled. Returns whether
+/// the pot was withdrawing.
+pub async fn revert_pot_withdrawal(pool: &PgPool, pot_id: i64) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("revert_pot_withdrawal");
+    let reverted = sqlx::query!(
+        "UPDATE pots SET status = 'closed', withdrawal_opid = NULL, withdrawal_amount = NULL,
+            withdrawal_tx_fee = NULL, withdrawal_bot_fee = NULL
+        WHERE id = $1 AND status = 'withdrawing'",
+        pot_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(reverted.rows_affected() > 0)
+}
+
+/// Pays out everything a closed or withdrawing pot holds, after which the pot is closed. A withdrawal is queued for
+/// the mod-log of the guild of the pot.
+pub async fn pay_out_pot(
+    pool: &PgPool,
+    pot_id: i64,
+    payout: &PotPayout,
+    uuid: &Uuid,
+    txid: Option<&str>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("pay_out_pot");
+    let mut tx = pool.begin().await?;
+
+    let closed = sqlx::query!(
+        "UPDATE pots SET status = 'closed' WHERE id = $1 AND status IN ('closed', 'withdrawing') RETURNING id",
+        pot_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if closed.is_none() {
+        return Err(format!("pot {pot_id} is not closed, it can not be paid out").into());
+    }
+
+    let balance = pot_balance(&mut tx, pot_id).await?;
+    pay_out(&mut tx, pot_id, balance, payout, uuid, txid).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn pay_out(
+    conn: &mut PgConnection,
+    pot_id: i64,
+    balance: i64,
+    payout: &PotPayout,
+    uuid: &Uuid,
+    txid: Option<&str>,
+) -> Result<(), Error> {
+    let mut entries = vec![(Account::Pot(pot_id), -balance)];
+    match payout {
+        PotPayout::User(user_id) => entries.push((Account::User(*user_id), balance)),
+        PotPayout::Withdrawal {
+            amount,
+            tx_fee,
+            bot_fee,
+        } => {
+            entries.push((Account::Withdrawals, amount.as_sat() as i64));
+            entries.push((Account::NetworkFees, tx_fee.as_sat() as i64));
+            entries.push((Account::BotFees, bot_fee.as_sat() as i64));
+        }
+    }
+
+    // a withdrawal must be exactly what the pot holds, which post_to_ledger checks
+    if !post_to_ledger(conn, &uuid.to_string(), EntryKind::PotPayout, &entries).await? {
+        return Err(format!("pot {pot_id} could not be paid out").into());
+    }
+
+    if let PotPayout::Withdrawal {
+        amount,
+        tx_fee,
+        bot_fee,
+    } = payout
+    {
+        // it is withdrawn on behalf of the creator
+        let pot = sqlx::query!(
+            "SELECT guild_id, created_by FROM pots WHERE id = $1",
+            pot_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        mod_log::dispatch(
+            conn,
+            Some(GuildId(pot.guild_id as u64)),
+            ModLogEvent::Withdrawal {
+                user_id: UserId(pot.created_by as u64),
+                amount: *amount,
+                fees: tx_fee.checked_add(*bot_fee).unwrap_or(*tx_fee),
+                txid: txid.map(str::to_owned),
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Cancels a pot and refunds every contributor. Open pots can be cancelled, and closed pots that were never paid out,
+/// like when the bot stopped before their withdrawal was started. Returns the refunds, or `None` when the pot can not
+/// be cancelled.
+pub async fn cancel_pot(
+    pool: &PgPool,
+    pot_id: i64,
+) -> Result<Option<Vec<(UserId, Amount)>>, Error> {
+    let _timer = metrics::db_timer("cancel_pot");
+    let mut tx = pool.begin().await?;
+
+    let status = sqlx::query!("SELECT status FROM pots WHERE id = $1 FOR UPDATE", pot_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|row| row.status);
+    let cancellable = match status.as_deref() {
+        Some("open") => true,
+        Some("closed") => pot_balance(&mut tx, pot_id).await? > 0,
+        _ => false,
+    };
+    if !cancellable {
+        return Ok(None);
+    }
+    sqlx::query!("UPDATE pots SET status = 'cancelled' WHERE id = $1", pot_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let refunds = sqlx::query!(
+        r#"SELECT discord_id, SUM(amount)::bigint AS "amount!" FROM pot_contributions
+        WHERE pot_id = $1 GROUP BY discord_id ORDER BY discord_id"#,
+        pot_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|row| (UserId(row.discord_id as u64), row.amount))
+    .collect::<Vec<_>>();
+
+    let total = refunds.iter().map(|(_, amount)| amount).sum::<i64>();
+    let mut entries = vec![(Account::Pot(pot_id), -total)];
+    entries.extend(
+        refunds
+            .iter()
+            .map(|(user_id, amount)| (Account::User(*user_id), *amount)),
+    );
+    post_to_ledger(
+        &mut tx,
+        &Uuid::new_v4().to_string(),
+        EntryKind::PotRefund,
+        &entries,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some(
+        refunds
+            .into_iter()
+            .map(|(user_id, amount)| (user_id, Amount::from_sat(amount.max(0) as u64)))
+            .collect(),
+    ))
+}
+
+/// Returns the number of tips and the amount tipped, over all guilds, in the last `hours`.
+pub async fn get_tip_volume(pool: &PgPool, hours: i32) -> Result<(i64, Amount), Error> {
+    let _timer = metrics::db_timer("get_tip_volume");
+    let row = sqlx::query!(
+        r#"SELECT COUNT(DISTINCT uuid) AS "tips!", COALESCE(SUM(amount), 0)::BIGINT AS "amount!"
+        FROM tips_vrsc WHERE created_at > NOW() - make_interval(hours => $1)"#,
+        hours
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((row.tips, Amount::from_sat(row.amount as u64)))
+}
+
+/// Everything the bot owes: user balances, amounts frozen in open disputes, the balances of all guild treasuries,
+/// unclaimed legacy balances, the referral pool, the daily reward pools of the guilds and the contributions held in
+/// crowdfunding pots.
+pub async fn get_liabilities(pool: &PgPool) -> Result<Liabilities, Error> {
+    let _timer = metrics::db_timer("get_liabilities");
+    let row = sqlx::query!(
+        r#"SELECT
+            (SELECT COALESCE(SUM(balance), 0) FROM balance_vrsc)::bigint AS "balances!",
+            (SELECT COALESCE(SUM(amount), 0) FROM disputes WHERE status = 'open')::bigint AS "frozen!",
+            (SELECT COALESCE(SUM(CASE WHEN kind IN ('fee', 'donation') THEN amount ELSE -amount END), 0)
+                FROM treasury_ledger)::bigint AS "treasuries!",
+            (SELECT COALESCE(SUM(amount), 0) FROM legacy_balances WHERE claimed_at IS NULL)::bigint AS "legacy!",
+            (SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account = 'referrals')::bigint AS "referrals!",
+            (SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account LIKE 'daily:%')::bigint AS "daily!",
+            (SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account LIKE 'pot:%')::bigint AS "pots!",
+            (SELECT COALESCE(SUM(amount), 0) FROM ledger_entries WHERE account = 'pending_withdrawals')::bigint
+                AS "pending_withdrawals!""#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Liabilities::new(
+        Amount::from_sat(row.balances.max(0) as u64),
+        Amount::from_sat(row.frozen.max(0) as u64),
+        Amount::from_sat(row.treasuries.max(0) as u64),
+        Amount::from_sat(row.legacy.max(0) as u64),
+        Amount::from_sat(row.referrals.max(0) as u64),
+        Amount::from_sat(row.daily.max(0) as u64),
+        Amount::from_sat(row.pots.max(0) as u64),
+        Amount::from_sat(row.pending_withdrawals.max(0) as u64),
+    ))
+}
+
+/// The balance of the treasury of a guild: all inflows minus all outflows.
+pub async fn get_treasury_balance(pool: &PgPool, guild_id: GuildId) -> Result<Amount, Error> {
+    let _timer = metrics::db_timer("get_treasury_balance");
+    let row = sqlx::query!(
+        "SELECT COALESCE(SUM(CASE WHEN kind IN ('fee', 'donation') THEN amount ELSE -amount END), 0)::bigint AS balance
+        FROM treasury_ledger WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Amount::from_sat(row.balance.unwrap_or(0).max(0) as u64))
+}
+
+pub async fn set_announcement_channel(
+    pool: &PgPool,
+    guild_id: GuildId,
+    channel_id: Option<ChannelId>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_announcement_channel");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, announcement_channel_id)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET announcement_channel_id = $2",
+        guild_id.0 as i64,
+        channel_id.map(|id| id.0 as i64)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_fallback_channel(
+    pool: &PgPool,
+    guild_id: GuildId,
+    channel_id: Option<ChannelId>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_fallback_channel");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, fallback_channel_id)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET fallback_channel_id = $2",
+        guild_id.0 as i64,
+        channel_id.map(|id| id.0 as i64)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the mod-log channel of a guild and the amount from which tips are posted in it. `None` turns the mod-log off
+/// or resets the threshold to the default.
+pub async fn set_mod_log(
+    pool: &PgPool,
+    guild_id: GuildId,
+    channel_id: Option<ChannelId>,
+    tip_threshold: Option<Amount>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_mod_log");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, mod_log_channel_id, mod_log_tip_threshold)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET mod_log_channel_id = $2, mod_log_tip_threshold = $3",
+        guild_id.0 as i64,
+        channel_id.map(|id| id.0 as i64),
+        tip_threshold.map(|threshold| threshold.as_sat() as i64)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the batching thresholds of a guild, `None` resets a threshold to the default.
+pub async fn set_batch_settings(
+    pool: &PgPool,
+    guild_id: GuildId,
+    threshold: Option<u32>,
+    window_seconds: Option<u32>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_batch_settings");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, batch_threshold, batch_window_seconds)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET batch_threshold = $2, batch_window_seconds = $3",
+        guild_id.0 as i64,
+        threshold.map(|threshold| threshold as i32),
+        window_seconds.map(|window| window as i32)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the batching threshold and window (in seconds) of a guild, see [`crate::util::delivery`].
+pub async fn get_batch_settings(pool: &PgPool, guild_id: GuildId) -> Result<(u32, u32), Error> {
+    let _timer = metrics::db_timer("get_batch_settings");
+    let row = sqlx::query!(
+        "SELECT batch_threshold, batch_window_seconds FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let (threshold, window) = row
+        .map(|row| (row.batch_threshold, row.batch_window_seconds))
+        .unwrap_or_default();
+
+    Ok((
+        threshold.map_or(delivery::DEFAULT_BATCH_THRESHOLD, |threshold| {
+            threshold as u32
+        }),
+        window.map_or(delivery::DEFAULT_BATCH_WINDOW_SECONDS, |window| {
+            window as u32
+        }),
+    ))
+}
+
+/// Overrides the bot withdrawal fee for withdrawals from a guild, or removes the override when `fee` is `None`.
+pub async fn set_guild_withdrawal_fee(
+    pool: &PgPool,
+    guild_id: GuildId,
+    fee: Option<BotFee>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_guild_withdrawal_fee");
+    let (sats, percentage) = match fee {
+        Some(BotFee::Flat(fee)) => (Some(fee.as_sat() as i64), None),
+        Some(BotFee::Percentage(percentage)) => (None, Some(percentage)),
+        None => (None, None),
+    };
+
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, withdrawal_fee_sats, withdrawal_fee_percentage)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET withdrawal_fee_sats = $2, withdrawal_fee_percentage = $3",
+        guild_id.0 as i64,
+        sats,
+        percentage
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The bot withdrawal fee a guild overrides the fee of the settings with, if any.
+pub async fn get_guild_withdrawal_fee(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Option<BotFee>, Error> {
+    let _timer = metrics::db_timer("get_guild_withdrawal_fee");
+    let row = sqlx::query!(
+        "SELECT withdrawal_fee_sats, withdrawal_fee_percentage FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(
+        |row| match (row.withdrawal_fee_sats, row.withdrawal_fee_percentage) {
+            (Some(sats), _) => Some(BotFee::Flat(Amount::from_sat(sats as u64))),
+            (_, Some(percentage)) => Some(BotFee::Percentage(percentage)),
+            _ => None,
+        },
+    ))
+}
+
+/// Turns the quick tip buttons under tip announcements in a guild on or off.
+pub async fn set_tip_buttons(pool: &PgPool, guild_id: GuildId, enabled: bool) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_tip_buttons");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, tip_buttons)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET tip_buttons = $2",
+        guild_id.0 as i64,
+        enabled
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether a guild has quick tip buttons under its tip announcements. They are off by default.
+pub async fn get_tip_buttons(pool: &PgPool, guild_id: GuildId) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("get_tip_buttons");
+    let row = sqlx::query!(
+        "SELECT tip_buttons FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some_and(|row| row.tip_buttons))
+}
+
+/// Blocks `word` in the notes of tips in a guild, or unblocks it when `blocked` is false.
+pub async fn set_filter_word(
+    pool: &PgPool,
+    guild_id: GuildId,
+    word: &str,
+    blocked: bool,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_filter_word");
+    match blocked {
+        true => {
+            sqlx::query!(
+                "INSERT INTO guild_filter_words (guild_id, word)
+                VALUES ($1, $2)
+                ON CONFLICT (guild_id, word) DO NOTHING",
+                guild_id.0 as i64,
+                word
+            )
+            .execute(pool)
+            .await?;
+        }
+        false => {
+            sqlx::query!(
+                "DELETE FROM guild_filter_words WHERE guild_id = $1 AND word = $2",
+                guild_id.0 as i64,
+                word
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The words a guild blocked itself, in alphabetical order.
+pub async fn get_filter_words(pool: &PgPool, guild_id: GuildId) -> Result<Vec<String>, Error> {
+    let _timer = metrics::db_timer("get_filter_words");
+    let rows = sqlx::query!(
+        "SELECT word FROM guild_filter_words WHERE guild_id = $1 ORDER BY word",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| row.word).collect())
+}
+
+pub async fn set_default_filter(
+    pool: &PgPool,
+    guild_id: GuildId,
+    enabled: bool,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_default_filter");
+    sqlx::query!(
+        "INSERT INTO guild_settings (guild_id, default_filter)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id)
+        DO UPDATE SET default_filter = $2",
+        guild_id.0 as i64,
+        enabled
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether the default list of blocked words applies in a guild. It does by default.
+pub async fn get_default_filter(pool: &PgPool, guild_id: GuildId) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("get_default_filter");
+    let row = sqlx::query!(
+        "SELECT default_filter FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_none_or(|row| row.default_filter))
+}
+
+/// Subscribes a guild to the network digest in `channel_id`, or unsubscribes it when `subscription` is `None`.
+pub async fn set_network_subscription(
+    pool: &PgPool,
+    guild_id: GuildId,
+    subscription: Option<(ChannelId, Amount)>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_network_subscription");
+    match subscription {
+        Some((channel_id, threshold)) => {
+            sqlx::query!(
+                "INSERT INTO network_subscriptions (guild_id, channel_id, large_tx_threshold)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (guild_id)
+                DO UPDATE SET channel_id = $2, large_tx_threshold = $3",
+                guild_id.0 as i64,
+                channel_id.0 as i64,
+                threshold.as_sat() as i64
+            )
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                "DELETE FROM network_subscriptions WHERE guild_id = $1",
+                guild_id.0 as i64
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the guilds subscribed to the network digest, with their channel and large transaction threshold.
+pub async fn get_network_subscriptions(
+    pool: &PgPool,
+) -> Result<Vec<(GuildId, ChannelId, Amount)>, Error> {
+    let _timer = metrics::db_timer("get_network_subscriptions");
+    let rows =
+        sqlx::query!("SELECT guild_id, channel_id, large_tx_threshold FROM network_subscriptions")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                GuildId(row.guild_id as u64),
+                ChannelId(row.channel_id as u64),
+                Amount::from_sat(row.large_tx_threshold as u64),
+            )
+        })
+        .collect())
+}
+
+/// Subscribes a guild to the news feeds of `topics` (all topics when empty) in a channel, or unsubscribes it with
+/// `None`.
+pub async fn set_news_subscription(
+    pool: &PgPool,
+    guild_id: GuildId,
+    subscription: Option<(ChannelId, &[String])>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_news_subscription");
+    match subscription {
+        Some((channel_id, topics)) => {
+            sqlx::query!(
+                "INSERT INTO news_subscriptions (guild_id, channel_id, topics)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (guild_id)
+                DO UPDATE SET channel_id = $2, topics = $3",
+                guild_id.0 as i64,
+                channel_id.0 as i64,
+                topics
+            )
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                "DELETE FROM news_subscriptions WHERE guild_id = $1",
+                guild_id.0 as i64
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the channels of the guilds that want the news of `topic`.
+pub async fn get_news_channels(pool: &PgPool, topic: &str) -> Result<Vec<ChannelId>, Error> {
+    let _timer = metrics::db_timer("get_news_channels");
+    let rows = sqlx::query!(
+        "SELECT channel_id FROM news_subscriptions WHERE cardinality(topics) = 0 OR $1 = ANY(topics)",
+        topic
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ChannelId(row.channel_id as u64))
+        .collect())
+}
+
+/// Marks the items of a news feed as seen and returns the ids of the items that were not seen before. The first time
+/// a feed has items, they are all marked as seen but none are returned, so a new feed does not relay its history.
+pub async fn store_news_items(
+    pool: &PgPool,
+    feed: &str,
+    item_ids: &[String],
+) -> Result<Vec<String>, Error> {
+    let _timer = metrics::db_timer("store_news_items");
+    let mut tx = pool.begin().await?;
+
+    let known = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM news_items WHERE feed = $1) AS "known!""#,
+        feed
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .known;
+
+    let new = sqlx::query!(
+        "INSERT INTO news_items (feed, item_id)
+        SELECT $1::text, * FROM UNNEST($2::text[])
+        ON CONFLICT (feed, item_id) DO NOTHING
+        RETURNING item_id",
+        feed,
+        item_ids
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(match known {
+        true => new.into_iter().map(|row| row.item_id).collect(),
+        false => vec![],
+    })
+}
+
+pub async fn get_fallback_channel(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Option<ChannelId>, Error> {
+    let _timer = metrics::db_timer("get_fallback_channel");
+    let row = sqlx::query!(
+        "SELECT fallback_channel_id FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row
+        .and_then(|row| row.fallback_channel_id)
+        .map(|id| ChannelId(id as u64)))
+}
+
+/// Returns the mod-log channel of a guild and its tip threshold, if the guild has a mod-log.
+pub async fn get_mod_log(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Option<(ChannelId, Option<Amount>)>, Error> {
+    let _timer = metrics::db_timer("get_mod_log");
+    let row = sqlx::query!(
+        "SELECT mod_log_channel_id, mod_log_tip_threshold FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(|row| {
+        row.mod_log_channel_id.map(|id| {
+            (
+                ChannelId(id as u64),
+                row.mod_log_tip_threshold
+                    .map(|threshold| Amount::from_sat(threshold as u64)),
+            )
+        })
+    }))
+}
+
+/// Returns all guilds that have a mod-log, with its channel and tip threshold.
+pub async fn get_mod_logs(
+    pool: &PgPool,
+) -> Result<Vec<(GuildId, ChannelId, Option<Amount>)>, Error> {
+    let _timer = metrics::db_timer("get_mod_logs");
+    let rows = sqlx::query!(
+        r#"SELECT guild_id, mod_log_channel_id AS "channel_id!", mod_log_tip_threshold FROM guild_settings
+        WHERE mod_log_channel_id IS NOT NULL"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                GuildId(row.guild_id as u64),
+                ChannelId(row.channel_id as u64),
+                row.mod_log_tip_threshold
+                    .map(|threshold| Amount::from_sat(threshold as u64)),
+            )
+        })
+        .collect())
+}
+
+/// Queues a mod-log event, see [`mod_log::dispatch`].
+pub async fn store_mod_log_event(
+    conn: &mut PgConnection,
+    guild_id: Option<GuildId>,
+    event: &ModLogEvent,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_mod_log_event");
+    let sats = |amount: &Amount| Some(amount.as_sat() as i64);
+    let (kind, user_id) = match event {
+        ModLogEvent::Withdrawal { user_id, .. } => ("withdrawal", user_id),
+        ModLogEvent::Sweep { user_id, .. } => ("sweep", user_id),
+        ModLogEvent::Tip { tipper, .. } => ("tip", tipper),
+        ModLogEvent::Blacklist { user_id, .. } => ("blacklist", user_id),
+        ModLogEvent::BalanceMismatch { user_id, .. } => ("balance_mismatch", user_id),
+    };
+    let (amount, fees, txid) = match event {
+        ModLogEvent::Withdrawal {
+            amount, fees, txid, ..
+        } => (sats(amount), sats(fees), txid.as_deref()),
+        ModLogEvent::Sweep { amount, fees, .. } => (sats(amount), sats(fees), None),
+        ModLogEvent::Tip { amount, .. } => (sats(amount), None, None),
+        _ => (None, None, None),
+    };
+    let (recipients, tip_event_id) = match event {
+        ModLogEvent::Tip {
+            recipients,
+            tip_event_id,
+            ..
+        } => (Some(*recipients as i32), Some(*tip_event_id)),
+        _ => (None, None),
+    };
+    let (blacklisted, actor) = match event {
+        ModLogEvent::Blacklist {
+            blacklisted, actor, ..
+        } => (Some(*blacklisted), Some(actor.0 as i64)),
+        _ => (None, None),
+    };
+    let (balance, ledger) = match event {
+        ModLogEvent::BalanceMismatch {
+            balance, ledger, ..
+        } => (Some(*balance), Some(*ledger)),
+        _ => (None, None),
+    };
+    let (opid, destination) = match event {
+        ModLogEvent::Sweep {
+            opid, destination, ..
+        } => (Some(opid.as_str()), Some(destination.as_str())),
+        _ => (None, None),
+    };
+
+    sqlx::query!(
+        "INSERT INTO mod_log_queue (guild_id, kind, user_id, amount, fees, txid, recipients, tip_event_id, blacklisted, actor, balance, ledger, opid, destination)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+        guild_id.map(|guild_id| guild_id.0 as i64),
+        kind,
+        user_id.0 as i64,
+        amount,
+        fees,
+        txid,
+        recipients,
+        tip_event_id,
+        blacklisted,
+        actor,
+        balance,
+        ledger,
+        opid,
+        destination
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Takes at most `limit` queued mod-log events off the queue, oldest first.
+pub async fn take_mod_log_events(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<(Option<GuildId>, ModLogEvent)>, Error> {
+    let _timer = metrics::db_timer("take_mod_log_events");
+    let mut rows = sqlx::query!(
+        "DELETE FROM mod_log_queue WHERE id IN (
+            SELECT id FROM mod_log_queue ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, guild_id, kind, user_id, amount, fees, txid, recipients, tip_event_id, blacklisted, actor, balance, ledger, opid, destination",
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    rows.sort_by_key(|row| row.id);
+
+    let amount = |sats: Option<i64>| Amount::from_sat(sats.unwrap_or_default().max(0) as u64);
+    let mut events = Vec::with_capacity(rows.len());
+    for row in rows {
+        let user_id = UserId(row.user_id as u64);
+        let event = match row.kind.as_str() {
+            "withdrawal" => ModLogEvent::Withdrawal {
+                user_id,
+                amount: amount(row.amount),
+                fees: amount(row.fees),
+                txid: row.txid,
+            },
+            "sweep" => ModLogEvent::Sweep {
+                user_id,
+                amount: amount(row.amount),
+                fees: amount(row.fees),
+                opid: row.opid.unwrap_or_default(),
+                destination: row.destination.unwrap_or_default(),
+            },
+            "tip" => ModLogEvent::Tip {
+                tipper: user_id,
+                recipients: row.recipients.unwrap_or_default() as usize,
+                amount: amount(row.amount),
+                tip_event_id: row.tip_event_id.unwrap_or_default(),
+            },
+            "blacklist" => ModLogEvent::Blacklist {
+                user_id,
+                blacklisted: row.blacklisted.unwrap_or_default(),
+                actor: UserId(row.actor.unwrap_or_default() as u64),
+            },
+            "balance_mismatch" => ModLogEvent::BalanceMismatch {
+                user_id,
+                balance: row.balance.unwrap_or_default(),
+                ledger: row.ledger.unwrap_or_default(),
+            },
+            kind => {
+                error!("mod-log event {} has kind {kind}", row.id);
+                continue;
+            }
+        };
+
+        events.push((row.guild_id.map(|guild_id| GuildId(guild_id as u64)), event));
+    }
+
+    Ok(events)
+}
+
+/// Maps `emoji` to `amount` in a guild, or removes the mapping when `amount` is `None`.
+pub async fn set_emoji_amount(
+    pool: &PgPool,
+    guild_id: GuildId,
+    emoji: &str,
+    amount: Option<Amount>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_emoji_amount");
+    match amount {
+        Some(amount) => {
+            sqlx::query!(
+                "INSERT INTO guild_emoji_amounts (guild_id, emoji, amount)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (guild_id, emoji)
+                DO UPDATE SET amount = $3",
+                guild_id.0 as i64,
+                emoji,
+                amount.as_sat() as i64
+            )
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                "DELETE FROM guild_emoji_amounts WHERE guild_id = $1 AND emoji = $2",
+                guild_id.0 as i64,
+                emoji
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the emoji amounts of a guild, from the smallest to the largest amount.
+pub async fn get_emoji_amounts(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Vec<(String, Amount)>, Error> {
+    let _timer = metrics::db_timer("get_emoji_amounts");
+    let rows = sqlx::query!(
+        "SELECT emoji, amount FROM guild_emoji_amounts WHERE guild_id = $1 ORDER BY amount, emoji",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.emoji, Amount::from_sat(row.amount as u64)))
+        .collect())
+}
+
+pub async fn get_announcement_channels(pool: &PgPool) -> Result<Vec<ChannelId>, Error> {
+    let _timer = metrics::db_timer("get_announcement_channels");
+    let rows = sqlx::query!(
+        "SELECT announcement_channel_id FROM guild_settings WHERE announcement_channel_id IS NOT NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| row.announcement_channel_id)
+        .map(|id| ChannelId(id as u64))
+        .collect())
+}
+
+/// All users that can receive an announcement in their DMs: everyone that did not turn off notifications and is not
+/// blacklisted.
+pub async fn get_announcement_recipients(pool: &PgPool) -> Result<Vec<UserId>, Error> {
+    let _timer = metrics::db_timer("get_announcement_recipients");
+    let rows = sqlx::query!(
+        "SELECT discord_id FROM discord_users
+        WHERE notifications IS DISTINCT FROM 'Off' AND blacklisted IS NOT TRUE"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| UserId(row.discord_id as u64))
+        .collect())
+}
+
+pub async fn insert_announcement(
+    pool: &PgPool,
+    author: &UserId,
+    message: &str,
+    target: AnnouncementTarget,
+) -> Result<i64, Error> {
+    let _timer = metrics::db_timer("insert_announcement");
+    let row = sqlx::query!(
+        "INSERT INTO announcements (author, message, target) VALUES ($1, $2, $3) RETURNING id",
+        author.0 as i64,
+        message,
+        target.as_str()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.id)
+}
+
+pub async fn insert_announcement_delivery(
+    pool: &PgPool,
+    announcement_id: i64,
+    recipient: u64,
+    error: Option<String>,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("insert_announcement_delivery");
+    sqlx::query!(
+        "INSERT INTO announcement_deliveries (announcement_id, recipient, delivered, error)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (announcement_id, recipient) DO NOTHING",
+        announcement_id,
+        recipient as i64,
+        error.is_none(),
+        error
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Copies every balance into `balance_snapshots` for today. Returns the number of snapshots taken.
+pub async fn snapshot_balances(pool: &PgPool) -> Result<u64, Error> {
+    let _timer = metrics::db_timer("snapshot_balances");
+    let result = sqlx::query!(
+        "INSERT INTO balance_snapshots (discord_id, balance, snapshot_date)
+        SELECT discord_id, balance, CURRENT_DATE FROM balance_vrsc WHERE discord_id IS NOT NULL
+        ON CONFLICT (discord_id, snapshot_date) DO NOTHING"
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// The daily balances of a user since `since`, oldest first.
+pub async fn get_balance_history(
+    pool: &PgPool,
+    user_id: &UserId,
+    since: NaiveDate,
+) -> Result<Vec<(NaiveDate, Amount)>, Error> {
+    let _timer = metrics::db_timer("get_balance_history");
+    let rows = sqlx::query!(
+        "SELECT snapshot_date, balance FROM balance_snapshots
+        WHERE discord_id = $1 AND snapshot_date >= $2
+        ORDER BY snapshot_date",
+        user_id.0 as i64,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.snapshot_date, Amount::from_sat(row.balance as u64)))
+        .collect())
+}
+
+/// Stores the state of a basket currency. `reserves` holds (reserve currency id, reserves, price in reserve).
+pub async fn store_currency_snapshot(
+    pool: &PgPool,
+    currency: &str,
+    supply: Amount,
+    reserves: &[(String, Amount, Amount)],
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("store_currency_snapshot");
+    let mut tx = pool.begin().await?;
+
+    let snapshot_id = sqlx::query!(
+        "INSERT INTO currency_snapshots (currency, supply) VALUES ($1, $2) RETURNING id",
+        currency,
+        supply.as_sat() as i64
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    if !reserves.is_empty() {
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO currency_snapshot_reserves (snapshot_id, reserve_currency_id, reserves, price_in_reserve) ",
+        );
+        query_builder.push_values(reserves, |mut b, (reserve_currency_id, reserves, price)| {
+            b.push_bind(snapshot_id)
+                .push_bind(reserve_currency_id)
+                .push_bind(reserves.as_sat() as i64)
+                .push_bind(price.as_sat() as i64);
+        });
+        query_builder.build().execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Returns the snapshots of a basket currency since `since`, oldest first, with the price in `price_currency_id`.
+pub async fn get_currency_history(
+    pool: &PgPool,
+    currency: &str,
+    price_currency_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<CurrencySnapshot>, Error> {
+    let _timer = metrics::db_timer("get_currency_history");
+    let rows = sqlx::query!(
+        "SELECT s.sampled_at, s.supply, r.price_in_reserve AS \"price?\" FROM currency_snapshots s
+        LEFT JOIN currency_snapshot_reserves r ON r.snapshot_id = s.id AND r.reserve_currency_id = $2
+        WHERE s.currency = $1 AND s.sampled_at >= $3
+        ORDER BY s.sampled_at",
+        currency,
+        price_currency_id,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| CurrencySnapshot {
+            sampled_at: row.sampled_at,
+            supply: Amount::from_sat(row.supply as u64),
+            price: row.price.map(|price| Amount::from_sat(price as u64)),
+        })
+        .collect())
+}
+
+/// Stores balances imported from a legacy tipbot. Users that already have an imported balance are skipped, so an import
+/// can be loaded again without crediting anyone twice. Returns the number of balances that were stored.
+pub async fn import_legacy_balances(
+    pool: &PgPool,
+    source: &str,
+    balances: &[(UserId, Amount)],
+) -> Result<u64, Error> {
+    let _timer = metrics::db_timer("import_legacy_balances");
+    if balances.is_empty() {
+        return Ok(0);
+    }
+
+    let mut query_builder: QueryBuilder<Postgres> =
+        QueryBuilder::new("INSERT INTO legacy_balances (discord_id, amount, source) ");
+    query_builder.push_values(balances, |mut b, (user_id, amount)| {
+        b.push_bind(user_id.0 as i64)
+            .push_bind(amount.as_sat() as i64)
+            .push_bind(source);
+    });
+    query_builder.push(" ON CONFLICT (discord_id) DO NOTHING");
+    let result = query_builder.build().execute(pool).await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Credits the unclaimed legacy balance of a user and marks it as claimed. Returns the claimed amount, or None when
+/// there is nothing to claim.
+pub async fn claim_legacy_balance(
+    pool: &PgPool,
+    user_id: &UserId,
+) -> Result<Option<(Amount, String)>, Error> {
+    let _timer = metrics::db_timer("claim_legacy_balance");
+    let mut tx = pool.begin().await?;
+
+    let Some(row) = sqlx::query!(
+        "UPDATE legacy_balances SET claimed_at = NOW()
+        WHERE discord_id = $1 AND claimed_at IS NULL
+        RETURNING amount, source",
+        user_id.0 as i64
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        tx.rollback().await?;
+
+        return Ok(None);
+    };
+
+    post_to_ledger(
+        &mut tx,
+        &Uuid::new_v4().to_string(),
+        EntryKind::LegacyClaim,
+        &[
+            (Account::Legacy, -row.amount),
+            (Account::User(*user_id), row.amount),
+        ],
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some((Amount::from_sat(row.amount as u64), row.source)))
+}
+
+/// Stores the entries of a ledger event and applies them to the balances of the users in it, see [`crate::ledger`].
+///
+/// Returns false when a user does not have enough balance for their debit, in which case nothing is changed. The
+/// entries must sum to zero.
+pub async fn post_to_ledger(
+    conn: &mut PgConnection,
+    event_id: &str,
+    kind: EntryKind,
+    entries: &[(Account, i64)],
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("post_to_ledger");
+    if !ledger::is_balanced(entries) {
+        return Err(format!("entries of ledger event {event_id} do not sum to zero").into());
+    }
+
+    // a savepoint when the caller is in a transaction, so a failed debit only undoes this event
+    let mut tx = conn.begin().await?;
+
+    for (user_id, change) in ledger::balance_changes(entries) {
+        if change < 0 {
+            let debited = sqlx::query!(
+                "UPDATE balance_vrsc SET balance = balance + $1 WHERE discord_id = $2 AND balance + $1 >= 0",
+                change,
+                user_id.0 as i64
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if debited.rows_affected() == 0 {
+                debug!("balance of {user_id} is insufficient for ledger event {event_id}");
+                tx.rollback().await?;
+
+                return Ok(false);
+            }
+        } else {
+            sqlx::query!(
+                "INSERT INTO balance_vrsc (discord_id, balance)
+                VALUES ($1, $2)
+                ON CONFLICT (discord_id)
+                DO UPDATE SET balance = balance_vrsc.balance + $2",
+                user_id.0 as i64,
+                change
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    let entries = entries
+        .iter()
+        .filter(|(_, amount)| *amount != 0)
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        tx.commit().await?;
+
+        return Ok(true);
+    }
+
+    let mut query_builder: QueryBuilder<Postgres> =
+        QueryBuilder::new("INSERT INTO ledger_entries (event_id, kind, account, amount) ");
+    query_builder.push_values(entries, |mut b, (account, amount)| {
+        b.push_bind(event_id)
+            .push_bind(kind.to_string())
+            .push_bind(account.to_string())
+            .push_bind(*amount);
+    });
+    query_builder.build().execute(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Users whose balance is not the sum of their ledger entries.
+pub async fn get_ledger_mismatches(pool: &PgPool) -> Result<Vec<Mismatch>, Error> {
+    let _timer = metrics::db_timer("get_ledger_mismatches");
+    let rows = sqlx::query!(
+        r#"SELECT balance_vrsc.discord_id AS "discord_id!", balance_vrsc.balance,
+            COALESCE(ledger.total, 0)::bigint AS "ledger!"
+        FROM balance_vrsc
+        LEFT JOIN (
+            SELECT account, SUM(amount) AS total FROM ledger_entries WHERE account LIKE 'user:%' GROUP BY account
+        ) ledger ON ledger.account = 'user:' || balance_vrsc.discord_id
+        WHERE balance_vrsc.discord_id IS NOT NULL AND balance_vrsc.balance <> COALESCE(ledger.total, 0)"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Mismatch {
+            user_id: UserId(row.discord_id as u64),
+            balance: row.balance,
+            ledger: row.ledger,
+        })
+        .collect())
+}
+
+/// The bot fees collected since `since` (or ever), and the number of withdrawals they were collected from.
+pub async fn get_collected_bot_fees(
+    pool: &PgPool,
+    since: Option<DateTime<Utc>>,
+) -> Result<(Amount, i64), Error> {
+    let _timer = metrics::db_timer("get_collected_bot_fees");
+    let row = sqlx::query!(
+        r#"SELECT COALESCE(SUM(amount), 0)::bigint AS "collected!", COUNT(*) AS "withdrawals!"
+        FROM ledger_entries
+        WHERE account = $1 AND amount > 0 AND ($2::timestamptz IS NULL OR created_at >= $2)"#,
+        Account::BotFees.to_string(),
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((
+        Amount::from_sat(row.collected.max(0) as u64),
+        row.withdrawals,
+    ))
+}
+
+/// Ledger events whose entries do not sum to zero.
+pub async fn get_unbalanced_ledger_events(pool: &PgPool) -> Result<Vec<String>, Error> {
+    let _timer = metrics::db_timer("get_unbalanced_ledger_events");
+    let rows = sqlx::query!(
+        "SELECT event_id FROM ledger_entries GROUP BY event_id HAVING SUM(amount) <> 0 ORDER BY event_id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| row.event_id).collect())
+}
+
+/// Collects everything the bot stores about a guild, see [`crate::guild_export`].
+pub async fn export_guild(pool: &PgPool, guild_id: GuildId) -> Result<GuildExport, Error> {
+    let _timer = metrics::db_timer("export_guild");
+    let settings = sqlx::query!(
+        "SELECT announcement_channel_id, fallback_channel_id, batch_threshold, batch_window_seconds
+        FROM guild_settings WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|row| GuildSettingsExport {
+        announcement_channel_id: row.announcement_channel_id.map(|id| id as u64),
+        fallback_channel_id: row.fallback_channel_id.map(|id| id as u64),
+        batch_threshold: row.batch_threshold.map(|threshold| threshold as u32),
+        batch_window_seconds: row.batch_window_seconds.map(|window| window as u32),
+    });
+
+    let treasury = sqlx::query!(
+        "SELECT kind, amount, counterparty, memo, created_at FROM treasury_ledger
+        WHERE guild_id = $1 ORDER BY id",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| TreasuryEntryExport {
+        kind: row.kind,
+        amount: row.amount as u64,
+        counterparty: row.counterparty.map(|id| id as u64),
+        memo: row.memo,
+        created_at: row.created_at.to_rfc3339(),
+    })
+    .collect();
+
+    let reactdrops = sqlx::query!(
+        "SELECT author, emojistr, amount, channel_id, message_id, status, finish_time, participants, eligible, tip_uuid, winners
+        FROM reactdrops WHERE guild_id = $1 AND status IN ('processed', 'cancelled') ORDER BY finish_time",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| ReactdropExport {
+        author: row.author as u64,
+        emoji: row.emojistr,
+        amount: row.amount as u64,
+        channel_id: row.channel_id as u64,
+        message_id: row.message_id as u64,
+        status: row.status,
+        finish_time: row.finish_time.to_rfc3339(),
+        participants: row.participants,
+        eligible: row.eligible,
+        tip_uuid: row.tip_uuid,
+        winners: row.winners.map(|winners| winners as u32),
+    })
+    .collect();
+
+    Ok(GuildExport {
+        version: crate::guild_export::EXPORT_VERSION,
+        guild_id: guild_id.0,
+        settings,
+        treasury,
+        reactdrops,
+    })
+}
+
+/// Whether there is a treasury or reactdrop history for this guild. An export should only be imported into a
+/// deployment where the guild has no history yet, otherwise entries would be duplicated.
+pub async fn guild_has_history(pool: &PgPool, guild_id: GuildId) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("guild_has_history");
+    let row = sqlx::query!(
+        r#"SELECT
+            EXISTS(SELECT 1 FROM treasury_ledger WHERE guild_id = $1)
+            OR EXISTS(SELECT 1 FROM reactdrops WHERE guild_id = $1) AS "history!""#,
+        guild_id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.history)
+}
+
+/// Imports an export in a single transaction: either all of it is imported, or nothing.
+pub async fn import_guild(pool: &PgPool, export: &GuildExport) -> Result<(), Error> {
+    let _timer = metrics::db_timer("import_guild");
+    let guild_id = export.guild_id as i64;
+    let mut tx = pool.begin().await?;
+
+    if let Some(settings) = &export.settings {
+        sqlx::query!(
+            "INSERT INTO guild_settings (guild_id, announcement_channel_id, fallback_channel_id, batch_threshold, batch_window_seconds)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET announcement_channel_id = $2, fallback_channel_id = $3, batch_threshold = $4, batch_window_seconds = $5",
+            guild_id,
+            settings.announcement_channel_id.map(|id| id as i64),
+            settings.fallback_channel_id.map(|id| id as i64),
+            settings.batch_threshold.map(|threshold| threshold as i32),
+            settings.batch_window_seconds.map(|window| window as i32)
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for entry in &export.treasury {
+        sqlx::query!(
+            "INSERT INTO treasury_ledger (guild_id, kind, amount, counterparty, memo, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+            guild_id,
+            entry.kind,
+            entry.amount as i64,
+            entry.counterparty.map(|id| id as i64),
+            entry.memo,
+            DateTime::parse_from_rfc3339(&entry.created_at)?.with_timezone(&Utc)
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for reactdrop in &export.reactdrops {
+        sqlx::query!(
+            "INSERT INTO reactdrops (guild_id, author, emojistr, amount, channel_id, message_id, status, finish_time, participants, eligible, tip_uuid, winners)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (channel_id, message_id) DO NOTHING",
+            guild_id,
+            reactdrop.author as i64,
+            reactdrop.emoji,
+            reactdrop.amount as i64,
+            reactdrop.channel_id as i64,
+            reactdrop.message_id as i64,
+            reactdrop.status,
+            DateTime::parse_from_rfc3339(&reactdrop.finish_time)?.with_timezone(&Utc),
+            reactdrop.participants,
+            reactdrop.eligible,
+            reactdrop.tip_uuid,
+            reactdrop.winners.map(|winners| winners as i32)
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn add_trivia_question(
+    pool: &PgPool,
+    guild_id: GuildId,
+    question: &str,
+    answer: &str,
+    created_by: &UserId,
+) -> Result<i64, Error> {
+    let _timer = metrics::db_timer("add_trivia_question");
+    let row = sqlx::query!(
+        "INSERT INTO trivia_questions (guild_id, question, answer, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id",
+        guild_id.0 as i64,
+        question,
+        answer,
+        created_by.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.id)
+}
+
+/// Returns false when the guild has no question with this id.
+pub async fn remove_trivia_question(
+    pool: &PgPool,
+    guild_id: GuildId,
+    id: i64,
+) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("remove_trivia_question");
+    let result = sqlx::query!(
+        "DELETE FROM trivia_questions WHERE guild_id = $1 AND id = $2",
+        guild_id.0 as i64,
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The trivia questions of a guild, the oldest first.
+pub async fn get_trivia_questions(
+    pool: &PgPool,
+    guild_id: GuildId,
+) -> Result<Vec<TriviaQuestion>, Error> {
+    let _timer = metrics::db_timer("get_trivia_questions");
+    let questions = sqlx::query_as!(
+        TriviaQuestion,
+        "SELECT id, question, answer FROM trivia_questions WHERE guild_id = $1 ORDER BY id",
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(questions)
+}
+
+/// Up to `count` different trivia questions of a guild, in random order.
+pub async fn get_random_trivia_questions(
+    pool: &PgPool,
+    guild_id: GuildId,
+    count: i64,
+) -> Result<Vec<TriviaQuestion>, Error> {
+    let _timer = metrics::db_timer("get_random_trivia_questions");
+    let questions = sqlx::query_as!(
+        TriviaQuestion,
+        "SELECT id, question, answer FROM trivia_questions WHERE guild_id = $1 ORDER BY random() LIMIT $2",
+        guild_id.0 as i64,
+        count
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(questions)
+}
+
+/// Stores a message in the outbox as attempted once, so it is retried after [`delivery::RETRY_DELAY`] unless it is
+/// delivered and deleted before that. When `conn` is in a transaction, the message is only stored when it commits.
+pub async fn store_outbox_message(
+    conn: &mut PgConnection,
+    destination: Destination,
+    content: &str,
+    buttons: Option<quick_tip::Target>,
+) -> Result<i64, Error> {
+    let _timer = metrics::db_timer("store_outbox_message");
+    let (kind, channel_id, guild_id, user_id) = match destination {
+        Destination::Channel {
+            channel_id,
+            guild_id,
+            initiator,
+        } => (
+            "channel",
+            Some(channel_id.0 as i64),
+            guild_id.map(|guild_id| guild_id.0 as i64),
+            initiator,
+        ),
+        Destination::Dm(user_id) => ("dm", None, None, user_id),
+    };
+    let (button_user, button_event) = match buttons {
+        Some(quick_tip::Target::User(user_id)) => (Some(user_id.0 as i64), None),
+        Some(quick_tip::Target::Event(uuid)) => (None, Some(uuid)),
+        None => (None, None),
+    };
+
+    let row = sqlx::query!(
+        "INSERT INTO outbox (kind, channel_id, guild_id, user_id, content, button_user, button_event, attempts, next_attempt_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, 1, NOW() + make_interval(secs => $8))
+        RETURNING id",
+        kind,
+        channel_id,
+        guild_id,
+        user_id.0 as i64,
+        content,
+        button_user,
+        button_event,
+        delivery::RETRY_DELAY.as_secs_f64()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(row.id)
+}
+
+/// Replaces the content of an outbox message that was not delivered yet.
+pub async fn set_outbox_content(pool: &PgPool, id: i64, content: &str) -> Result<(), Error> {
+    let _timer = metrics::db_timer("set_outbox_content");
+    sqlx::query!("UPDATE outbox SET content = $2 WHERE id = $1", id, content)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Takes the outbox messages that are due for a retry. Their next retry is scheduled right away, with exponential
+/// backoff, so a message is not picked up again while it is being delivered.
+pub async fn claim_due_outbox_messages(pool: &PgPool) -> Result<Vec<OutboxMessage>, Error> {
+    let _timer = metrics::db_timer("claim_due_outbox_messages");
+    let rows = sqlx::query!(
+        "UPDATE outbox
+        SET attempts = attempts + 1,
+            next_attempt_at = NOW() + LEAST(make_interval(secs => $1 * power(2, attempts)), make_interval(secs => $2))
+        WHERE id IN (
+            SELECT id FROM outbox
+            WHERE next_attempt_at <= NOW() AND attempts < $3
+            ORDER BY id
+            LIMIT 50
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, kind, channel_id, guild_id, user_id, content, button_user, button_event, attempts",
+        delivery::RETRY_DELAY.as_secs_f64(),
+        delivery::MAX_RETRY_DELAY.as_secs_f64(),
+        delivery::MAX_ATTEMPTS
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut messages = rows
+        .into_iter()
+        .map(|row| {
+            Ok(OutboxMessage {
+                id: row.id,
+                destination: outbox_destination(
+                    row.id,
+                    &row.kind,
+                    row.channel_id,
+                    row.guild_id,
+                    row.user_id,
+                )?,
+                content: row.content,
+                buttons: outbox_buttons(row.button_user, row.button_event),
+                attempts: row.attempts,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    messages.sort_by_key(|message| message.id);
+
+    Ok(messages)
+}
+
+/// The destination of an outbox message, from the columns it is stored in.
+fn outbox_destination(
+    id: i64,
+    kind: &str,
+    channel_id: Option<i64>,
+    guild_id: Option<i64>,
+    user_id: i64,
+) -> Result<Destination, String> {
+    match (kind, channel_id) {
+        ("channel", Some(channel_id)) => Ok(Destination::Channel {
+            channel_id: ChannelId(channel_id as u64),
+            guild_id: guild_id.map(|guild_id| GuildId(guild_id as u64)),
+            initiator: UserId(user_id as u64),
+        }),
+        ("dm", _) => Ok(Destination::Dm(UserId(user_id as u64))),
+        (kind, _) => Err(format!("outbox message {id} has kind {kind}")),
+    }
+}
+
+fn outbox_buttons(
+    button_user: Option<i64>,
+    button_event: Option<Uuid>,
+) -> Option<quick_tip::Target> {
+    match (button_user, button_event) {
+        (Some(user_id), _) => Some(quick_tip::Target::User(UserId(user_id as u64))),
+        (None, Some(uuid)) => Some(quick_tip::Target::Event(uuid)),
+        (None, None) => None,
+    }
+}
+
+pub async fn delete_outbox_message(pool: &PgPool, id: i64) -> Result<(), Error> {
+    let _timer = metrics::db_timer("delete_outbox_message");
+    sqlx::query!("DELETE FROM outbox WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Stores why an outbox message could not be delivered, it is retried later.
+pub async fn record_outbox_failure(pool: &PgPool, id: i64, error: &str) -> Result<(), Error> {
+    let _timer = metrics::db_timer("record_outbox_failure");
+    sqlx::query!("UPDATE outbox SET last_error = $2 WHERE id = $1", id, error)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Moves an outbox message that is given up on to the failed notifications, where it is retried after
+/// [`delivery::FAILED_RETRY_DELAY`].
+pub async fn fail_outbox_message(pool: &PgPool, id: i64, error: &str) -> Result<(), Error> {
+    let _timer = metrics::db_timer("fail_outbox_message");
+    sqlx::query!(
+        "WITH failed AS (
+            DELETE FROM outbox WHERE id = $1
+            RETURNING kind, channel_id, guild_id, user_id, content, button_user, button_event, created_at
+        )
+        INSERT INTO failed_notifications
+            (kind, channel_id, guild_id, user_id, content, button_user, button_event, last_error, next_retry_at, created_at)
+        SELECT kind, channel_id, guild_id, user_id, content, button_user, button_event, $2,
+            NOW() + make_interval(secs => $3), created_at
+        FROM failed",
+        id,
+        error,
+        delivery::FAILED_RETRY_DELAY.as_secs_f64()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Takes the failed notifications that are due for a retry, and schedules their next retry right away. The returned
+/// messages have their number of retries as attempts.
+pub async fn claim_due_failed_notifications(pool: &PgPool) -> Result<Vec<OutboxMessage>, Error> {
+    let _timer = metrics::db_timer("claim_due_failed_notifications");
+    let rows = sqlx::query!(
+        "UPDATE failed_notifications
+        SET retries = retries + 1, next_retry_at = NOW() + make_interval(secs => $1)
+        WHERE id IN (
+            SELECT id FROM failed_notifications
+            WHERE next_retry_at <= NOW() AND retries < $2
+            ORDER BY id
+            LIMIT 50
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, kind, channel_id, guild_id, user_id, content, button_user, button_event, retries",
+        delivery::FAILED_RETRY_DELAY.as_secs_f64(),
+        delivery::MAX_FAILED_RETRIES
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut messages = rows
+        .into_iter()
+        .map(|row| {
+            Ok(OutboxMessage {
+                id: row.id,
+                destination: outbox_destination(
+                    row.id,
+                    &row.kind,
+                    row.channel_id,
+                    row.guild_id,
+                    row.user_id,
+                )?,
+                content: row.content,
+                buttons: outbox_buttons(row.button_user, row.button_event),
+                attempts: row.retries,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    messages.sort_by_key(|message| message.id);
+
+    Ok(messages)
+}
+
+pub async fn delete_failed_notification(pool: &PgPool, id: i64) -> Result<(), Error> {
+    let _timer = metrics::db_timer("delete_failed_notification");
+    sqlx::query!("DELETE FROM failed_notifications WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn record_failed_notification_error(
+    pool: &PgPool,
+    id: i64,
+    error: &str,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("record_failed_notification_error");
+    sqlx::query!(
+        "UPDATE failed_notifications SET last_error = $2 WHERE id = $1",
+        id,
+        error
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether a DM to `user_id` failed and is still being retried, which means they most likely don't accept DMs from
+/// the bot.
+pub async fn has_failed_dm(pool: &PgPool, user_id: UserId) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("has_failed_dm");
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM failed_notifications WHERE kind = 'dm' AND user_id = $1 AND retries < $2
+        ) AS "failed!""#,
+        user_id.0 as i64,
+        delivery::MAX_FAILED_RETRIES
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.failed)
+}
+
+fn batch_columns(key: BatchKey) -> (&'static str, i64) {
+    match key {
+        BatchKey::Channel(channel_id) => ("channel", channel_id.0 as i64),
+        BatchKey::Dm(user_id) => ("dm", user_id.0 as i64),
+    }
+}
+
+/// Adds a tip to the batch of `key`, which is posted once the window of its first tip has passed. When `conn` is in
+/// the transaction that pays the tip, the tip is only added when it is paid.
+pub async fn store_batch_entry(
+    conn: &mut PgConnection,
+    key: BatchKey,
+    guild_id: Option<GuildId>,
+    initiator: UserId,
+    amount: Amount,
+    window: Duration,
+) -> Result<i64, Error> {
+    let _timer = metrics::db_timer("store_batch_entry");
+    let (kind, key) = batch_columns(key);
+    let row = sqlx::query!(
+        "INSERT INTO tip_batch_entries (kind, key, guild_id, initiator, amount, window_seconds, flush_at)
+        VALUES ($1, $2, $3, $4, $5, $6, COALESCE(
+            (SELECT MIN(flush_at) FROM tip_batch_entries WHERE kind = $1 AND key = $2),
+            NOW() + make_interval(secs => $6::integer)
+        ))
+        RETURNING id",
+        kind,
+        key,
+        guild_id.map(|guild_id| guild_id.0 as i64),
+        initiator.0 as i64,
+        amount.as_sat() as i64,
+        window.as_secs() as i32
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(row.id)
+}
+
+/// Counts recipients of a batched tip that could not be DMed, so the summary of its batch mentions them.
+pub async fn add_batch_unnotified(pool: &PgPool, id: i64, unnotified: usize) -> Result<(), Error> {
+    let _timer = metrics::db_timer("add_batch_unnotified");
+    sqlx::query!(
+        "UPDATE tip_batch_entries SET unnotified = unnotified + $2 WHERE id = $1",
+        id,
+        unnotified as i32
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether tips are waiting in the batch of `key`, in which case new tips join them.
+pub async fn has_pending_batch(pool: &PgPool, key: BatchKey) -> Result<bool, Error> {
+    let _timer = metrics::db_timer("has_pending_batch");
+    let (kind, key) = batch_columns(key);
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM tip_batch_entries WHERE kind = $1 AND key = $2
+        ) AS "pending!""#,
+        kind,
+        key
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.pending)
+}
+
+/// The batches whose window has passed, or all batches with tips when `all` is set.
+pub async fn get_due_batches(pool: &PgPool, all: bool) -> Result<Vec<BatchKey>, Error> {
+    let _timer = metrics::db_timer("get_due_batches");
+    let rows = sqlx::query!(
+        "SELECT kind, key FROM tip_batch_entries
+        GROUP BY kind, key
+        HAVING $1 OR MIN(flush_at) <= NOW()
+        ORDER BY MIN(flush_at)",
+        all
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| match row.kind.as_str() {
+            "channel" => Ok(BatchKey::Channel(ChannelId(row.key as u64))),
+            "dm" => Ok(BatchKey::Dm(UserId(row.key as u64))),
+            kind => Err(format!("batch {} has kind {kind}", row.key).into()),
+        })
+        .collect()
+}
+
+/// Takes the tips of the batch of `key` out of the batches, coalesced into the summary to post. Returns None when
+/// the batch was already taken.
+pub async fn take_batch(
+    conn: &mut PgConnection,
+    key: BatchKey,
+) -> Result<Option<PendingBatch>, Error> {
+    let _timer = metrics::db_timer("take_batch");
+    let (kind, column) = batch_columns(key);
+    let mut rows = sqlx::query!(
+        "DELETE FROM tip_batch_entries WHERE kind = $1 AND key = $2
+        RETURNING id, guild_id, initiator, amount, unnotified, window_seconds",
+        kind,
+        column
+    )
+    .fetch_all(conn)
+    .await?;
+    rows.sort_by_key(|row| row.id);
+
+    let Some(first) = rows.first() else {
+        return Ok(None);
+    };
+    let mut pending = PendingBatch {
+        key,
+        guild_id: first.guild_id.map(|guild_id| GuildId(guild_id as u64)),
+        initiator: UserId(first.initiator as u64),
+        tips: 0,
+        total: Amount::ZERO,
+        unnotified: 0,
+        window: Duration::from_secs(first.window_seconds as u64),
+    };
+    for row in &rows {
+        pending.tips += 1;
+        pending.total += Amount::from_sat(row.amount as u64);
+        pending.unnotified += row.unnotified as usize;
+    }
+
+    Ok(Some(pending))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn balance(pool: &PgPool, user_id: u64) -> Option<u64> {
+        get_balance_for_user(pool, &UserId(user_id)).await.unwrap()
+    }
+
+    async fn withdraw(
+        pool: &PgPool,
+        interaction_id: u64,
+        user_id: u64,
+        sats: u64,
+        tx_fee: u64,
+        bot_fee: u64,
+    ) {
+        let uuid = Uuid::new_v4();
+        let (amount, tx_fee, bot_fee) = (
+            Amount::from_sat(sats),
+            Amount::from_sat(tx_fee),
+            Amount::from_sat(bot_fee),
+        );
+        assert!(reserve_withdrawal(
+            pool,
+            interaction_id,
+            &UserId(user_id),
+            amount,
+            tx_fee,
+            bot_fee,
+            &uuid,
+            None,
+        )
+        .await
+        .unwrap());
+        settle_withdrawal(
+            pool,
+            &UserId(user_id),
+            amount,
+            tx_fee,
+            bot_fee,
+            &uuid,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn fund(pool: &PgPool, user_id: u64, sats: u64) {
+        insert_discord_user(pool, &UserId(user_id)).await.unwrap();
+        increase_balance(
+            pool,
+            &UserId(user_id),
+            Amount::from_sat(sats),
+            &Uuid::new_v4(),
+            &"00".repeat(32).parse::<Txid>().unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn a_deposit_is_stored_with_its_credit(pool: PgPool) {
+        let txid = "ab".repeat(32).parse::<Txid>().unwrap();
+        insert_discord_user(&pool, &UserId(1)).await.unwrap();
+
+        increase_balance(
+            &pool,
+            &UserId(1),
+            Amount::from_sat(500),
+            &Uuid::new_v4(),
+            &txid,
+        )
+        .await
+        .unwrap();
+        assert_eq!(balance(&pool, 1).await, Some(500));
+        assert!(transaction_processed(&pool, &txid).await.unwrap());
+
+        // a deposit to a user that does not exist can't be stored, so it isn't credited either
+        assert!(increase_balance(
+            &pool,
+            &UserId(2),
+            Amount::from_sat(500),
+            &Uuid::new_v4(),
+            &txid
+        )
+        .await
+        .is_err());
+        assert_eq!(balance(&pool, 2).await, None);
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn tip_moves_balances_and_stores_the_tip(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+
+        let uuid = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2), UserId(3)],
+            &Amount::from_sat(300),
+            "role",
+            Some(GuildId(7)),
+            Some("thanks"),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(balance(&pool, 1).await, Some(400));
+        assert_eq!(balance(&pool, 2).await, Some(300));
+        assert_eq!(balance(&pool, 3).await, Some(300));
+
+        let tip = get_tip(&pool, &uuid.to_string(), &UserId(2))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tip.tipper, UserId(1));
+        assert_eq!(tip.amount, Amount::from_sat(300));
+        assert_eq!(tip.memo.as_deref(), Some("thanks"));
+        assert_eq!(tip.guild_id, Some(GuildId(7)));
+    }
+
+    #[sqlx::test]
+    async fn mod_log_events_are_queued_with_their_change(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+        insert_discord_user(&pool, &UserId(3)).await.unwrap();
+
+        let uuid = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2), UserId(3)],
+            &Amount::from_sat(300),
+            "role",
+            Some(GuildId(7)),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        // a tip that is rolled back is not logged
+        let result = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2)],
+            &Amount::from_sat(5_000),
+            "direct",
+            Some(GuildId(7)),
+            None,
+            None,
+        )
+        .await;
+        assert!(result.is_err());
+        set_blacklist_status(&pool, UserId(3), true, UserId(9))
+            .await
+            .unwrap();
+
+        let events = take_mod_log_events(&pool, 1).await.unwrap();
+        assert_eq!(
+            events,
+            vec![(
+                Some(GuildId(7)),
+                ModLogEvent::Tip {
+                    tipper: UserId(1),
+                    recipients: 2,
+                    amount: Amount::from_sat(600),
+                    tip_event_id: uuid,
+                }
+            )]
+        );
+        let events = take_mod_log_events(&pool, 100).await.unwrap();
+        assert_eq!(
+            events,
+            vec![(
+                None,
+                ModLogEvent::Blacklist {
+                    user_id: UserId(3),
+                    blacklisted: true,
+                    actor: UserId(9),
+                }
+            )]
+        );
+        assert!(take_mod_log_events(&pool, 100).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn a_sweep_is_queued_with_its_operation_and_destination(pool: PgPool) {
+        let sweep = ModLogEvent::Sweep {
+            user_id: UserId(9),
+            amount: Amount::from_sat(5_000),
+            fees: Amount::from_sat(10),
+            opid: "opid-1".to_string(),
+            destination: "cold@".to_string(),
+        };
+        mod_log::dispatch(
+            &mut pool.acquire().await.unwrap(),
+            Some(GuildId(7)),
+            sweep.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            take_mod_log_events(&pool, 100).await.unwrap(),
+            vec![(Some(GuildId(7)), sweep)]
+        );
+    }
+
+    #[sqlx::test]
+    async fn failed_tip_changes_nothing(pool: PgPool) {
+        fund(&pool, 1, 500).await;
+
+        let result = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2), UserId(3)],
+            &Amount::from_sat(300),
+            "role",
+            None,
+            None,
+            None,
+        )
+        .await;
+
+        assert!(result.is_err_and(|e| is_insufficient_balance(&e)));
+        assert_eq!(balance(&pool, 1).await, Some(500));
+        assert_eq!(balance(&pool, 2).await, None);
+        assert_eq!(balance(&pool, 3).await, None);
+
+        let tips = sqlx::query!("SELECT COUNT(*) AS count FROM tips_vrsc")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tips.count, Some(0));
+    }
+
+    #[sqlx::test]
+    async fn an_interaction_only_tips_once(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+        let (tipper, recipients, share) = (UserId(1), vec![UserId(2)], Amount::from_sat(300));
+
+        let tip = || async {
+            tip_transactionally(
+                &mut pool.acquire().await.unwrap(),
+                &tipper,
+                &recipients,
+                &share,
+                "direct",
+                None,
+                None,
+                Some(42),
+            )
+            .await
+        };
+        tip().await.unwrap();
+        let again = tip().await.unwrap_err();
+
+        assert!(is_already_processed(&again));
+        assert_eq!(balance(&pool, 1).await, Some(700));
+        assert_eq!(balance(&pool, 2).await, Some(300));
+    }
+
+    #[sqlx::test]
+    async fn failed_tip_does_not_claim_the_interaction(pool: PgPool) {
+        fund(&pool, 1, 100).await;
+
+        let result = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2)],
+            &Amount::from_sat(300),
+            "direct",
+            None,
+            None,
+            Some(42),
+        )
+        .await;
+
+        assert!(result.is_err());
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(claim_interaction(&mut conn, 42, "withdrawal")
+            .await
+            .unwrap());
+        assert!(!claim_interaction(&mut conn, 42, "withdrawal")
+            .await
+            .unwrap());
+    }
+
+    #[sqlx::test]
+    async fn paying_reactdrops_are_resumed_until_their_results_are_stored(pool: PgPool) {
+        insert_reactdrop(
+            &pool,
+            1,
+            "🎉".to_owned(),
+            1_000,
+            None,
+            10,
+            20,
+            Utc::now(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            update_reactdrop(&pool, 10, 20, ReactdropState::Paying, None)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !update_reactdrop(&pool, 10, 20, ReactdropState::Paying, None)
+                .await
+                .unwrap()
+        );
+        let resumed = get_pending_reactdrops(&pool).await.unwrap();
+        assert_eq!(resumed.len(), 1);
+        assert!(matches!(resumed[0].status, ReactdropState::Paying));
+
+        store_reactdrop_results(&pool, 10, 20, 3, None, None)
+            .await
+            .unwrap();
+
+        assert!(get_pending_reactdrops(&pool).await.unwrap().is_empty());
+        let reactdrop = get_reactdrop(&pool, ChannelId(10), MessageId(20))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(reactdrop.status, ReactdropState::Processed));
+    }
+
+    #[sqlx::test]
+    async fn reversal_only_claws_back_from_recipients_that_still_have_the_tip(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+
+        let uuid = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2), UserId(3)],
+            &Amount::from_sat(300),
+            "role",
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .to_string();
+
+        // recipient 3 spent part of the tip
+        withdraw(&pool, 1, 3, 190, 10, 0).await;
+
+        let Reversal::Reversed {
+            tipper,
+            recovered,
+            unrecovered,
+        } = reverse_tip_event(&pool, &uuid, &UserId(9)).await.unwrap()
+        else {
+            panic!("tip event was not reversed");
+        };
+
+        assert_eq!(tipper, UserId(1));
+        assert_eq!(recovered, vec![(UserId(2), Amount::from_sat(300))]);
+        assert_eq!(unrecovered, vec![(UserId(3), Amount::from_sat(300))]);
+        assert_eq!(balance(&pool, 1).await, Some(700));
+        assert_eq!(balance(&pool, 2).await, Some(0));
+        assert_eq!(balance(&pool, 3).await, Some(100));
+
+        assert!(matches!(
+            reverse_tip_event(&pool, &uuid, &UserId(9)).await.unwrap(),
+            Reversal::AlreadyReversed
+        ));
+        assert_eq!(balance(&pool, 1).await, Some(700));
+    }
+
+    #[sqlx::test]
+    async fn reversed_tips_can_not_be_disputed(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+        fund(&pool, 2, 500).await;
+
+        let uuid = tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(2)],
+            &Amount::from_sat(300),
+            "user",
+            Some(GuildId(7)),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .to_string();
+        assert!(matches!(
+            reverse_tip_event(&pool, &uuid, &UserId(9)).await.unwrap(),
+            Reversal::Reversed { .. }
+        ));
+        assert_eq!(balance(&pool, 1).await, Some(1_000));
+
+        // the tipper already got the tip back, so a refund of a dispute would pay it twice
+        let tip = get_tip(&pool, &uuid, &UserId(2)).await.unwrap().unwrap();
+        assert!(matches!(
+            open_dispute(&pool, &tip, GuildId(7), &UserId(1), "wrong user")
+                .await
+                .unwrap(),
+            DisputeOpening::Reversed
+        ));
+        assert!(!dispute_exists(&pool, &uuid, &UserId(2)).await.unwrap());
+        assert_eq!(balance(&pool, 2).await, Some(500));
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn legacy_balance_can_be_claimed_once(pool: PgPool) {
+        fund(&pool, 1, 100).await;
+
+        let balances = vec![(UserId(1), Amount::from_sat(500))];
+        assert_eq!(
+            import_legacy_balances(&pool, "oldbot", &balances)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let (amount, source) = claim_legacy_balance(&pool, &UserId(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(amount, Amount::from_sat(500));
+        assert_eq!(source, "oldbot");
+        assert_eq!(balance(&pool, 1).await, Some(600));
+
+        assert!(claim_legacy_balance(&pool, &UserId(1))
+            .await
+            .unwrap()
+            .is_none());
+
+        // importing the same file again does not make the balance claimable again
+        assert_eq!(
+            import_legacy_balances(&pool, "oldbot", &balances)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(claim_legacy_balance(&pool, &UserId(1))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(balance(&pool, 1).await, Some(600));
+    }
+
+    #[sqlx::test]
+    async fn a_reserved_withdrawal_is_held_until_it_is_refunded(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+        let uuid = Uuid::new_v4();
+
+        assert!(reserve_withdrawal(
+            &pool,
+            7,
+            &UserId(1),
+            Amount::from_sat(600),
+            Amount::from_sat(10),
+            Amount::ZERO,
+            &uuid,
+            None,
+        )
+        .await
+        .unwrap());
+        assert_eq!(balance(&pool, 1).await, Some(390));
+        assert_eq!(
+            get_liabilities(&pool).await.unwrap().pending_withdrawals,
+            610
+        );
+        store_withdrawal_opid(&pool, &uuid, "opid-1").await.unwrap();
+        assert_eq!(
+            get_pending_withdrawals(&pool).await.unwrap(),
+            vec![PendingWithdrawal {
+                uuid,
+                user_id: UserId(1),
+                guild_id: None,
+                amount: Amount::from_sat(600),
+                tx_fee: Amount::from_sat(10),
+                bot_fee: Amount::ZERO,
+                opid: Some(String::from("opid-1")),
+            }]
+        );
+
+        // the same interaction can not reserve a second withdrawal
+        assert!(reserve_withdrawal(
+            &pool,
+            7,
+            &UserId(1),
+            Amount::from_sat(100),
+            Amount::ZERO,
+            Amount::ZERO,
+            &Uuid::new_v4(),
+            None,
+        )
+        .await
+        .is_err());
+
+        refund_withdrawal(
+            &pool,
+            &UserId(1),
+            Amount::from_sat(600),
+            Amount::from_sat(10),
+            Amount::ZERO,
+            &uuid,
+        )
+        .await
+        .unwrap();
+        assert_eq!(balance(&pool, 1).await, Some(1_000));
+        assert_eq!(get_liabilities(&pool).await.unwrap().pending_withdrawals, 0);
+        assert!(get_pending_withdrawals(&pool).await.unwrap().is_empty());
+
+        // a withdrawal that is no longer pending can't be refunded twice
+        assert!(refund_withdrawal(
+            &pool,
+            &UserId(1),
+            Amount::from_sat(600),
+            Amount::from_sat(10),
+            Amount::ZERO,
+            &uuid,
+        )
+        .await
+        .is_err());
+        assert_eq!(balance(&pool, 1).await, Some(1_000));
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn ledger_matches_balances_after_every_event(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+        tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(1),
+            &[UserId(1), UserId(2)],
+            &Amount::from_sat(200),
+            "role",
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        donate_to_treasury(&pool, GuildId(5), &UserId(2), Amount::from_sat(50), None)
+            .await
+            .unwrap();
+        withdraw(&pool, 1, 1, 90, 5, 5).await;
+
+        // a withdrawal the user can not afford is not posted at all
+        assert!(!reserve_withdrawal(
+            &pool,
+            2,
+            &UserId(2),
+            Amount::from_sat(1_000),
+            Amount::ZERO,
+            Amount::ZERO,
+            &Uuid::new_v4(),
+            None,
+        )
+        .await
+        .unwrap());
+
+        assert_eq!(balance(&pool, 1).await, Some(700));
+        assert_eq!(balance(&pool, 2).await, Some(150));
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+        assert!(get_unbalanced_ledger_events(&pool)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            get_collected_bot_fees(&pool, None).await.unwrap(),
+            (Amount::from_sat(5), 1)
+        );
+
+        sqlx::query!("UPDATE balance_vrsc SET balance = 999 WHERE discord_id = 2")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_ledger_mismatches(&pool).await.unwrap(),
+            vec![Mismatch {
+                user_id: UserId(2),
+                balance: 999,
+                ledger: 150,
+            }]
+        );
+    }
+
+    #[sqlx::test]
+    async fn referrals_can_only_be_claimed_by_new_users(pool: PgPool) {
+        let code = get_or_store_referral_code(&pool, &UserId(1), "AAAA2222")
+            .await
+            .unwrap();
+        // a second link keeps the first code
+        assert_eq!(
+            get_or_store_referral_code(&pool, &UserId(1), "BBBB3333")
+                .await
+                .unwrap(),
+            code
+        );
+
+        let claim = |user_id: u64, code: &'static str| {
+            let pool = pool.clone();
+            async move { claim_referral(&pool, &UserId(user_id), code).await.unwrap() }
+        };
+        assert_eq!(claim(2, "ZZZZ9999").await, ReferralClaim::UnknownCode);
+        assert_eq!(claim(1, "AAAA2222").await, ReferralClaim::OwnCode);
+        assert_eq!(
+            claim(2, "AAAA2222").await,
+            ReferralClaim::Claimed(UserId(1))
+        );
+        assert_eq!(claim(2, "AAAA2222").await, ReferralClaim::AlreadyReferred);
+
+        get_or_store_referral_code(&pool, &UserId(2), "CCCC4444")
+            .await
+            .unwrap();
+        assert_eq!(claim(1, "CCCC4444").await, ReferralClaim::Mutual);
+
+        fund(&pool, 3, 1_000).await;
+        tip_transactionally(
+            &mut pool.acquire().await.unwrap(),
+            &UserId(3),
+            &[UserId(4)],
+            &Amount::from_sat(100),
+            "direct",
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(claim(3, "AAAA2222").await, ReferralClaim::NotNew);
+    }
+
+    #[sqlx::test]
+    async fn referral_rewards_are_paid_once_from_the_pool(pool: PgPool) {
+        fund(&pool, 9, 1_000).await;
+        get_or_store_referral_code(&pool, &UserId(1), "AAAA2222")
+            .await
+            .unwrap();
+        claim_referral(&pool, &UserId(2), "AAAA2222").await.unwrap();
+        set_referral_rewards(&pool, Amount::from_sat(300), Amount::from_sat(200))
+            .await
+            .unwrap();
+
+        // the pool is empty, so the rewards stay pending
+        assert_eq!(pay_referral_rewards(&pool, &UserId(2)).await.unwrap(), None);
+
+        assert!(fund_referral_pool(&pool, &UserId(9), Amount::from_sat(600))
+            .await
+            .unwrap());
+        assert_eq!(
+            pay_referral_rewards(&pool, &UserId(2)).await.unwrap(),
+            Some((UserId(1), Amount::from_sat(300), Amount::from_sat(200)))
+        );
+        assert_eq!(pay_referral_rewards(&pool, &UserId(2)).await.unwrap(), None);
+
+        assert_eq!(balance(&pool, 1).await, Some(300));
+        assert_eq!(balance(&pool, 2).await, Some(200));
+        assert_eq!(
+            get_referral_pool(&pool).await.unwrap(),
+            Amount::from_sat(100)
+        );
+        assert_eq!(
+            get_referral_counts(&pool, &UserId(1)).await.unwrap(),
+            (1, 1)
+        );
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn daily_rewards_are_claimed_once_per_cooldown(pool: PgPool) {
+        let guild_id = GuildId(7);
+        let user_id = UserId(1);
+        let claim = || claim_daily(&pool, guild_id, &user_id);
+
+        assert_eq!(claim().await.unwrap(), DailyClaim::Off);
+
+        set_daily_reward(&pool, guild_id, Amount::from_sat(100))
+            .await
+            .unwrap();
+        assert_eq!(claim().await.unwrap(), DailyClaim::PoolEmpty);
+
+        fund(&pool, 9, 1_000).await;
+        assert!(
+            fund_daily_pool(&pool, guild_id, &UserId(9), Amount::from_sat(500))
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            claim().await.unwrap(),
+            DailyClaim::Claimed {
+                amount: Amount::from_sat(100),
+                streak: 1
+            }
+        );
+        assert!(matches!(claim().await.unwrap(), DailyClaim::Cooldown(_)));
+
+        // a day later the streak goes on, with a higher reward
+        sqlx::query!("UPDATE daily_streaks SET last_claim_at = NOW() - INTERVAL '25 hours'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            claim().await.unwrap(),
+            DailyClaim::Claimed {
+                amount: Amount::from_sat(110),
+                streak: 2
+            }
+        );
+
+        assert_eq!(balance(&pool, 1).await, Some(210));
+        assert_eq!(
+            get_daily_pool(&pool, guild_id).await.unwrap(),
+            Amount::from_sat(290)
+        );
+        let leaderboard = get_daily_leaderboard(&pool, guild_id, 10).await.unwrap();
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].streak, 2);
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn competitions_only_count_fair_tips(pool: PgPool) {
+        let guild_id = GuildId(5);
+        let mut competition = Competition {
+            id: 0,
+            guild_id,
+            channel_id: ChannelId(6),
+            started_by: UserId(9),
+            metric: CompetitionMetric::Amount,
+            prize: Amount::from_sat(100),
+            min_tip: Amount::from_sat(10),
+            ends_at: Utc::now() + chrono::Duration::hours(1),
+        };
+        competition.id = start_competition(&pool, &competition)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(start_competition(&pool, &competition).await.unwrap(), None);
+
+        let tip = |tipper: u64, recipients: Vec<u64>, share: u64| {
+            let pool = pool.clone();
+            async move {
+                let uuid = Uuid::new_v4();
+                let recipients = recipients.into_iter().map(UserId).collect::<Vec<_>>();
+                record_competition_tips(
+                    &pool,
+                    guild_id,
+                    uuid,
+                    &UserId(tipper),
+                    &recipients,
+                    Amount::from_sat(share),
+                )
+                .await
+                .unwrap();
+                uuid
+            }
+        };
+        // 1 tips 2, who tips most of it back
+        tip(1, vec![2], 50).await;
+        tip(2, vec![1], 40).await;
+        // 3 tips to themselves and below the minimum, which don't count
+        tip(3, vec![3, 4], 500).await;
+        tip(3, vec![4], 5).await;
+        // a reversed tip of 4 doesn't count
+        let reversed = tip(4, vec![1], 1_000).await;
+        sqlx::query!(
+            "INSERT INTO tip_reversals (tip_uuid, reversed_by, tipper, recovered, unrecovered) VALUES ($1, 9, 4, 1000, 0)",
+            reversed.to_string()
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            get_competition_scores(&pool, &competition, 10)
+                .await
+                .unwrap(),
+            vec![(UserId(3), 500), (UserId(1), 10)]
+        );
+
+        fund(&pool, 9, 1_000).await;
+        donate_to_treasury(&pool, guild_id, &UserId(9), Amount::from_sat(100), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            finish_competition(&pool, &competition).await.unwrap(),
+            Some(CompetitionResult::Won {
+                winner: UserId(3),
+                score: 500
+            })
+        );
+        assert_eq!(finish_competition(&pool, &competition).await.unwrap(), None);
+        assert_eq!(balance(&pool, 3).await, Some(100));
+        assert_eq!(
+            get_treasury_balance(&pool, guild_id).await.unwrap(),
+            Amount::ZERO
+        );
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn pots_hold_contributions_until_they_are_paid_out_or_refunded(pool: PgPool) {
+        let pot = |name: &str| Pot {
+            id: 0,
+            guild_id: GuildId(5),
+            channel_id: ChannelId(6),
+            message_id: None,
+            name: name.to_string(),
+            created_by: UserId(1),
+            goal: Amount::from_sat(1_000),
+            recipient: PotRecipient::User(UserId(3)),
+        };
+        fund(&pool, 1, 500).await;
+        fund(&pool, 2, 500).await;
+
+        let paid = create_pot(&pool, &pot("Prize")).await.unwrap().unwrap();
+        // the name of an open pot is taken, ignoring case
+        assert_eq!(create_pot(&pool, &pot("prize")).await.unwrap(), None);
+        let refunded = create_pot(&pool, &pot("Server")).await.unwrap().unwrap();
+
+        assert_eq!(
+            contribute_to_pot(&pool, paid, &UserId(1), Amount::from_sat(200))
+                .await
+                .unwrap(),
+            PotContribution::Added {
+                raised: Amount::from_sat(200)
+            }
+        );
+        assert_eq!(
+            contribute_to_pot(&pool, paid, &UserId(2), Amount::from_sat(300))
+                .await
+                .unwrap(),
+            PotContribution::Added {
+                raised: Amount::from_sat(500)
+            }
+        );
+        assert_eq!(
+            contribute_to_pot(&pool, paid, &UserId(2), Amount::from_sat(300))
+                .await
+                .unwrap(),
+            PotContribution::InsufficientBalance
+        );
+        contribute_to_pot(&pool, refunded, &UserId(1), Amount::from_sat(100))
+            .await
+            .unwrap();
+        contribute_to_pot(&pool, refunded, &UserId(1), Amount::from_sat(50))
+            .await
+            .unwrap();
+        assert_eq!(get_liabilities(&pool).await.unwrap().pots, 650);
+
+        assert_eq!(
+            close_pot(&pool, paid, Some(UserId(3))).await.unwrap(),
+            Some(Amount::from_sat(500))
+        );
+        assert_eq!(close_pot(&pool, paid, Some(UserId(3))).await.unwrap(), None);
+        assert_eq!(
+            contribute_to_pot(&pool, paid, &UserId(1), Amount::from_sat(10))
+                .await
+                .unwrap(),
+            PotContribution::NotOpen
+        );
+        // a pot that was paid out can't be cancelled anymore
+        assert_eq!(cancel_pot(&pool, paid).await.unwrap(), None);
+
+        assert_eq!(
+            cancel_pot(&pool, refunded).await.unwrap(),
+            Some(vec![(UserId(1), Amount::from_sat(150))])
+        );
+        assert_eq!(cancel_pot(&pool, refunded).await.unwrap(), None);
+
+        assert_eq!(balance(&pool, 1).await, Some(300));
+        assert_eq!(balance(&pool, 2).await, Some(200));
+        assert_eq!(balance(&pool, 3).await, Some(500));
+        assert_eq!(get_liabilities(&pool).await.unwrap().pots, 0);
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    fn address_pot(name: &str) -> Pot {
+        Pot {
+            id: 0,
+            guild_id: GuildId(5),
+            channel_id: ChannelId(6),
+            message_id: None,
+            name: name.to_string(),
+            created_by: UserId(1),
+            goal: Amount::from_sat(1_000),
+            recipient: PotRecipient::Address("RAddress".to_string()),
+        }
+    }
+
+    #[sqlx::test]
+    async fn pots_are_withdrawn_with_the_fees(pool: PgPool) {
+        fund(&pool, 2, 1_000).await;
+        let pot = create_pot(&pool, &address_pot("Prize"))
+            .await
+            .unwrap()
+            .unwrap();
+        contribute_to_pot(&pool, pot, &UserId(2), Amount::from_sat(1_000))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            close_pot(&pool, pot, None).await.unwrap(),
+            Some(Amount::from_sat(1_000))
+        );
+        assert_eq!(
+            start_pot_withdrawal(&pool, pot).await.unwrap(),
+            Some(Amount::from_sat(1_000))
+        );
+        // a withdrawal that was started can't be started again or cancelled, as it may have been sent
+        assert_eq!(start_pot_withdrawal(&pool, pot).await.unwrap(), None);
+        assert_eq!(cancel_pot(&pool, pot).await.unwrap(), None);
+
+        // the payout must be everything the pot holds
+        assert!(pay_out_pot(
+            &pool,
+            pot,
+            &PotPayout::Withdrawal {
+                amount: Amount::from_sat(890),
+                tx_fee: Amount::from_sat(100),
+                bot_fee: Amount::from_sat(9),
+            },
+            &Uuid::new_v4(),
+            Some("txid"),
+        )
+        .await
+        .is_err());
+        pay_out_pot(
+            &pool,
+            pot,
+            &PotPayout::Withdrawal {
+                amount: Amount::from_sat(890),
+                tx_fee: Amount::from_sat(100),
+                bot_fee: Amount::from_sat(10),
+            },
+            &Uuid::new_v4(),
+            Some("txid"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(get_pot_raised(&pool, pot).await.unwrap(), Amount::ZERO);
+        assert_eq!(
+            get_collected_bot_fees(&pool, None).await.unwrap(),
+            (Amount::from_sat(10), 1)
+        );
+        assert!(get_cancellable_pots(&pool, GuildId(5))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            take_mod_log_events(&pool, 100).await.unwrap(),
+            vec![(
+                Some(GuildId(5)),
+                ModLogEvent::Withdrawal {
+                    user_id: UserId(1),
+                    amount: Amount::from_sat(890),
+                    fees: Amount::from_sat(110),
+                    txid: Some("txid".to_string()),
+                }
+            )]
+        );
+    }
+
+    #[sqlx::test]
+    async fn interrupted_pot_withdrawals_are_found_with_their_operation(pool: PgPool) {
+        fund(&pool, 2, 1_000).await;
+        let unsent = create_pot(&pool, &address_pot("Unsent"))
+            .await
+            .unwrap()
+            .unwrap();
+        let sent = create_pot(&pool, &address_pot("Sent"))
+            .await
+            .unwrap()
+            .unwrap();
+        let timed_out = create_pot(&pool, &address_pot("Timed out"))
+            .await
+            .unwrap()
+            .unwrap();
+        for pot in [unsent, sent, timed_out] {
+            contribute_to_pot(&pool, pot, &UserId(2), Amount::from_sat(500))
+                .await
+                .unwrap();
+            close_pot(&pool, pot, None).await.unwrap();
+            start_pot_withdrawal(&pool, pot).await.unwrap();
+        }
+        let (amount, tx_fee, bot_fee) = (
+            Amount::from_sat(395),
+            Amount::from_sat(100),
+            Amount::from_sat(5),
+        );
+        store_pot_withdrawal(&pool, sent, Some("opid-1"), amount, tx_fee, bot_fee)
+            .await
+            .unwrap();
+        store_pot_withdrawal(&pool, timed_out, None, amount, tx_fee, bot_fee)
+            .await
+            .unwrap();
+
+        let payout = || PotPayout::Withdrawal {
+            amount,
+            tx_fee,
+            bot_fee,
+        };
+        assert_eq!(
+            get_withdrawing_pots(&pool).await.unwrap(),
+            vec![
+                WithdrawingPot {
+                    id: unsent,
+                    created_by: UserId(1),
+                    sent: None,
+                },
+                WithdrawingPot {
+                    id: sent,
+                    created_by: UserId(1),
+                    sent: Some((Some("opid-1".to_string()), payout())),
+                },
+                WithdrawingPot {
+                    id: timed_out,
+                    created_by: UserId(1),
+                    sent: Some((None, payout())),
+                },
+            ]
+        );
+
+        // a withdrawal that was never sent closes the pot again, after which it can be cancelled
+        assert!(revert_pot_withdrawal(&pool, unsent).await.unwrap());
+        assert!(!revert_pot_withdrawal(&pool, unsent).await.unwrap());
+        assert_eq!(
+            cancel_pot(&pool, unsent).await.unwrap(),
+            Some(vec![(UserId(2), Amount::from_sat(500))])
+        );
+        assert_eq!(get_withdrawing_pots(&pool).await.unwrap().len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn unpaid_pots_can_be_cancelled(pool: PgPool) {
+        fund(&pool, 2, 1_000).await;
+        let failed = create_pot(&pool, &address_pot("Failed"))
+            .await
+            .unwrap()
+            .unwrap();
+        let stopped = create_pot(&pool, &address_pot("Stopped"))
+            .await
+            .unwrap()
+            .unwrap();
+        for pot in [failed, stopped] {
+            contribute_to_pot(&pool, pot, &UserId(2), Amount::from_sat(400))
+                .await
+                .unwrap();
+            close_pot(&pool, pot, None).await.unwrap();
+        }
+
+        // a withdrawal that failed closes the pot again, so its contributors can be refunded
+        start_pot_withdrawal(&pool, failed).await.unwrap();
+        assert!(revert_pot_withdrawal(&pool, failed).await.unwrap());
+        assert_eq!(
+            cancel_pot(&pool, failed).await.unwrap(),
+            Some(vec![(UserId(2), Amount::from_sat(400))])
+        );
+
+        // a pot whose withdrawal never started, like when the bot stopped right after closing it, can be cancelled
+        let cancellable = get_cancellable_pot(&pool, GuildId(5), "stopped")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cancellable.id, stopped);
+        assert!(get_open_pot(&pool, GuildId(5), "stopped")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            cancel_pot(&pool, stopped).await.unwrap(),
+            Some(vec![(UserId(2), Amount::from_sat(400))])
+        );
+        assert_eq!(start_pot_withdrawal(&pool, stopped).await.unwrap(), None);
+        assert!(pay_out_pot(
+            &pool,
+            stopped,
+            &PotPayout::User(UserId(1)),
+            &Uuid::new_v4(),
+            None
+        )
+        .await
+        .is_err());
+
+        assert_eq!(balance(&pool, 2).await, Some(1_000));
+        assert_eq!(get_liabilities(&pool).await.unwrap().pots, 0);
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn contributions_racing_a_close_are_paid_out_or_rejected(pool: PgPool) {
+        fund(&pool, 2, 10_000).await;
+        let mut pot = address_pot("Race");
+        pot.recipient = PotRecipient::User(UserId(3));
+
+        let mut contributed = 0;
+        for round in 0..20 {
+            pot.name = format!("Race {round}");
+            let pot_id = create_pot(&pool, &pot).await.unwrap().unwrap();
+            contribute_to_pot(&pool, pot_id, &UserId(2), Amount::from_sat(10))
+                .await
+                .unwrap();
+
+            let (contribution, raised) = tokio::join!(
+                contribute_to_pot(&pool, pot_id, &UserId(2), Amount::from_sat(5)),
+                close_pot(&pool, pot_id, Some(UserId(3)))
+            );
+            let raised = raised.unwrap().unwrap();
+            match contribution.unwrap() {
+                PotContribution::Added { .. } => assert_eq!(raised, Amount::from_sat(15)),
+                PotContribution::NotOpen => assert_eq!(raised, Amount::from_sat(10)),
+                PotContribution::InsufficientBalance => unreachable!(),
+            }
+            contributed += raised.as_sat();
+        }
+
+        assert_eq!(balance(&pool, 3).await, Some(contributed));
+        assert_eq!(balance(&pool, 2).await, Some(10_000 - contributed));
+        assert_eq!(get_liabilities(&pool).await.unwrap().pots, 0);
+        assert!(get_ledger_mismatches(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn outbox_messages_are_only_stored_with_their_tip(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+
+        for commit in [false, true] {
+            let mut tx = pool.begin().await.unwrap();
+            tip_transactionally(
+                &mut tx,
+                &UserId(1),
+                &[UserId(2)],
+                &Amount::from_sat(100),
+                "direct",
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+            store_outbox_message(&mut tx, Destination::Dm(UserId(2)), "you got a tip", None)
+                .await
+                .unwrap();
+
+            if commit {
+                tx.commit().await.unwrap();
+            }
+        }
+
+        assert_eq!(balance(&pool, 2).await, Some(100));
+        let outbox = sqlx::query!("SELECT COUNT(*) AS count FROM outbox")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(outbox.count, Some(1));
+    }
+
+    #[sqlx::test]
+    async fn batched_tips_are_only_stored_with_their_tip(pool: PgPool) {
+        fund(&pool, 1, 1_000).await;
+        let key = BatchKey::Channel(ChannelId(1));
+
+        for commit in [false, true] {
+            let mut tx = pool.begin().await.unwrap();
+            tip_transactionally(
+                &mut tx,
+                &UserId(1),
+                &[UserId(2)],
+                &Amount::from_sat(100),
+                "direct",
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+            store_batch_entry(
+                &mut tx,
+                key,
+                Some(GuildId(1)),
+                UserId(1),
+                Amount::from_sat(100),
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+
+            if commit {
+                tx.commit().await.unwrap();
+            }
+        }
+
+        assert_eq!(balance(&pool, 2).await, Some(100));
+        let pending = take_batch(&mut pool.acquire().await.unwrap(), key)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.tips, 1);
+    }
+
+    #[sqlx::test]
+    async fn batches_are_coalesced_once_their_window_passed(pool: PgPool) {
+        let channel = BatchKey::Channel(ChannelId(1));
+        let dm = BatchKey::Dm(UserId(3));
+        let mut conn = pool.acquire().await.unwrap();
+        for (key, initiator, amount) in [(channel, 1, 100), (dm, 3, 100), (channel, 2, 50)] {
+            store_batch_entry(
+                &mut conn,
+                key,
+                Some(GuildId(1)),
+                UserId(initiator),
+                Amount::from_sat(amount),
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        }
+        let last = store_batch_entry(
+            &mut conn,
+            channel,
+            Some(GuildId(1)),
+            UserId(2),
+            Amount::from_sat(50),
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+        add_batch_unnotified(&pool, last, 2).await.unwrap();
+
+        assert!(has_pending_batch(&pool, channel).await.unwrap());
+        assert!(get_due_batches(&pool, false).await.unwrap().is_empty());
+        assert_eq!(get_due_batches(&pool, true).await.unwrap().len(), 2);
+
+        // the batch is due when the window of its first tip passed
+        sqlx::query!(
+            "UPDATE tip_batch_entries SET flush_at = NOW() WHERE id < $1 AND kind = 'channel'",
+            last
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(get_due_batches(&pool, false).await.unwrap(), vec![channel]);
+
+        let pending = take_batch(&mut conn, channel).await.unwrap().unwrap();
+        assert_eq!(pending.initiator, UserId(1));
+        assert_eq!(pending.guild_id, Some(GuildId(1)));
+        assert_eq!(pending.tips, 3);
+        assert_eq!(pending.total, Amount::from_sat(200));
+        assert_eq!(pending.unnotified, 2);
+        assert_eq!(pending.window, Duration::from_secs(60));
+
+        assert!(take_batch(&mut conn, channel).await.unwrap().is_none());
+        assert!(!has_pending_batch(&pool, channel).await.unwrap());
+        assert!(has_pending_batch(&pool, dm).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn outbox_messages_are_retried_until_they_fail(pool: PgPool) {
+        let id = store_outbox_message(
+            &mut pool.acquire().await.unwrap(),
+            Destination::Dm(UserId(1)),
+            "you got a tip",
+            Some(quick_tip::Target::User(UserId(2))),
+        )
+        .await
+        .unwrap();
+
+        // the first attempt is still going on, so the message is not due yet
+        assert!(claim_due_outbox_messages(&pool).await.unwrap().is_empty());
+
+        sqlx::query!(
+            "UPDATE outbox SET next_attempt_at = NOW() WHERE id = $1",
+            id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let due = claim_due_outbox_messages(&pool).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].destination, Destination::Dm(UserId(1)));
+        assert_eq!(due[0].buttons, Some(quick_tip::Target::User(UserId(2))));
+        assert_eq!(due[0].attempts, 2);
+        assert!(claim_due_outbox_messages(&pool).await.unwrap().is_empty());
+
+        // a message that is given up on moves to the failed notifications, which are retried much later
+        assert!(!has_failed_dm(&pool, UserId(1)).await.unwrap());
+        fail_outbox_message(&pool, id, "forbidden").await.unwrap();
+        assert!(has_failed_dm(&pool, UserId(1)).await.unwrap());
+        sqlx::query!(
+            "UPDATE outbox SET next_attempt_at = NOW() WHERE id = $1",
+            id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(claim_due_outbox_messages(&pool).await.unwrap().is_empty());
+        assert!(claim_due_failed_notifications(&pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        sqlx::query!("UPDATE failed_notifications SET next_retry_at = NOW()")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let failed = claim_due_failed_notifications(&pool).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].destination, Destination::Dm(UserId(1)));
+        assert_eq!(failed[0].content, "you got a tip");
+        assert_eq!(failed[0].attempts, 1);
+
+        delete_failed_notification(&pool, failed[0].id)
+            .await
+            .unwrap();
+        assert!(!has_failed_dm(&pool, UserId(1)).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn news_items_are_relayed_once_and_not_on_the_first_fetch(pool: PgPool) {
+        let feed = "https://example.com/feed";
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        assert!(store_news_items(&pool, feed, &ids(&["a", "b"]))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store_news_items(&pool, feed, &ids(&["a", "b", "c"]))
+                .await
+                .unwrap(),
+            ids(&["c"])
+        );
+        assert!(store_news_items(&pool, feed, &ids(&["b", "c"]))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
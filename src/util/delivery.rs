@@ -0,0 +1,890 @@
+//! Delivery of messages that must not get lost.
+//!
+//! Announcements of tips and reactdrops are posted after balances have already moved, so failing to post them would
+//! leave users wondering where their money went. When the original channel can not be used (it was deleted, or the
+//! bot lost its permissions), the message is posted in the fallback channel of the guild (see `/config fallback`),
+//! and when that fails too, it is sent to the user that started the tip.
+//!
+//! When a channel gets more tip announcements than its guild allows (see `/config batching`), or has slow mode, the
+//! announcements are batched instead: a single summary of the tips is posted through the outbox when the batching
+//! window has passed. DMs to a user that receives many tips in a short time are batched the same way, into a single
+//! summary DM, which goes through the outbox like any other tip DM. Batched tips are stored in the transaction that
+//! pays them, so a restart does not lose them, and are coalesced into the summary when their batch is flushed.
+//! Batches that are still pending when the bot shuts down are flushed right away (see [`flush_all_batches`]).
+//!
+//! Messages about balances that already changed go through the outbox (see [`deliver`]): they are stored before they
+//! are sent, and when Discord fails to take them, they are retried with backoff until they arrive.
+//!
+//! Messages the outbox gives up on, mostly DMs to users that do not accept DMs from the bot, are moved to the failed
+//! notifications. Those are retried once a day for a week (see [`retry_failed_notifications`]), in case the user
+//! opened their DMs, and are kept after that so an operator can look into them.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{
+    self as serenity, Channel, ChannelId, CreateMessage, GuildId, Http, HttpError, Message, UserId,
+};
+use sqlx::{PgConnection, PgPool};
+use tokio::time::interval;
+use tracing::*;
+use vrsc::Amount;
+
+use crate::{
+    i18n::{tr_locale, Translations},
+    quick_tip,
+    util::{database, format},
+    Error,
+};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const OUTBOX_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The delay before the first retry of an outbox message. Every next retry waits twice as long, up to
+/// [`MAX_RETRY_DELAY`].
+pub const RETRY_DELAY: Duration = Duration::from_secs(30);
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+/// The number of times an outbox message is tried before it is given up on.
+pub const MAX_ATTEMPTS: i32 = 10;
+
+const FAILED_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// The time between the retries of a failed notification.
+pub const FAILED_RETRY_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+/// The number of times a failed notification is retried before it is kept for good.
+pub const MAX_FAILED_RETRIES: i32 = 7;
+
+/// The number of tip announcements in a channel within the window after which tips are batched, for guilds that did
+/// not set their own.
+pub const DEFAULT_BATCH_THRESHOLD: u32 = 5;
+pub const DEFAULT_BATCH_WINDOW_SECONDS: u32 = 60;
+
+/// The number of tip DMs a user gets within [`DM_BATCH_WINDOW`] after which their tips are summarized.
+const DM_BATCH_THRESHOLD: usize = 3;
+const DM_BATCH_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Posts `content` in `channel_id`, falling back on the fallback channel of the guild and then on a DM to `initiator`.
+pub async fn post_with_fallback(
+    http: &Http,
+    pool: &PgPool,
+    channel_id: ChannelId,
+    guild_id: Option<GuildId>,
+    initiator: UserId,
+    content: &str,
+) -> Result<(), Error> {
+    send_with_fallback(http, pool, channel_id, guild_id, initiator, |message| {
+        message.content(content)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Like [`post_with_fallback`], for messages that are more than text. Returns the message that was posted.
+pub async fn send_with_fallback(
+    http: &Http,
+    pool: &PgPool,
+    channel_id: ChannelId,
+    guild_id: Option<GuildId>,
+    initiator: UserId,
+    build: impl for<'a, 'b> Fn(&'b mut CreateMessage<'a>) -> &'b mut CreateMessage<'a>,
+) -> Result<Message, Error> {
+    let error = match channel_id.send_message(http, |m| build(m)).await {
+        Ok(message) => return Ok(message),
+        Err(e) => e,
+    };
+    warn!("could not post in {channel_id}, trying the fallback channel: {error:?}");
+
+    let fallback_channel = match guild_id {
+        Some(guild_id) => database::get_fallback_channel(pool, guild_id).await?,
+        None => None,
+    };
+
+    if let Some(fallback_channel) = fallback_channel.filter(|id| *id != channel_id) {
+        match fallback_channel.send_message(http, |m| build(m)).await {
+            Ok(message) => return Ok(message),
+            Err(e) => warn!("could not post in fallback channel {fallback_channel}: {e:?}"),
+        }
+    }
+
+    debug!("sending the message to {initiator} instead");
+    let message = initiator
+        .create_dm_channel(http)
+        .await?
+        .send_message(http, |m| build(m))
+        .await?;
+
+    Ok(message)
+}
+
+/// Where a message in the outbox goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    /// A channel, see [`send_with_fallback`] for when it can not be used
+    Channel {
+        channel_id: ChannelId,
+        guild_id: Option<GuildId>,
+        initiator: UserId,
+    },
+    Dm(UserId),
+}
+
+/// A message in the outbox, or in the failed notifications.
+#[derive(Debug)]
+pub struct OutboxMessage {
+    pub id: i64,
+    pub destination: Destination,
+    pub content: String,
+    /// The quick tip buttons of the message, if it has them
+    pub buttons: Option<quick_tip::Target>,
+    pub attempts: i32,
+}
+
+/// What happened to a message when it was tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Delivered,
+    /// Discord did not take the message for now, it is retried with backoff
+    Retrying,
+    /// Discord refused the message, like a DM to a user that does not accept DMs from the bot, or it was tried too
+    /// often. It was moved to the failed notifications.
+    Failed,
+}
+
+/// Stores a message in the outbox and tries to deliver it right away. When that fails, [`deliver_outbox`] retries
+/// it later, so this only returns an error when the message could not be stored.
+pub async fn deliver(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    destination: Destination,
+    content: &str,
+    buttons: Option<quick_tip::Target>,
+) -> Result<Delivery, Error> {
+    let message = enqueue(&mut *pool.acquire().await?, destination, content, buttons).await?;
+
+    attempt(http, pool, translations, &message).await
+}
+
+/// Stores a message in the outbox without trying to deliver it. When `conn` is in the transaction that changed the
+/// balances the message is about, the message is stored if and only if the balances changed; it should be tried
+/// with [`attempt`] after the commit.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    destination: Destination,
+    content: &str,
+    buttons: Option<quick_tip::Target>,
+) -> Result<OutboxMessage, Error> {
+    // the first attempt is the caller's, the worker only picks the message up when it is still there after the retry
+    // delay
+    Ok(OutboxMessage {
+        id: database::store_outbox_message(conn, destination, content, buttons).await?,
+        destination,
+        content: content.to_owned(),
+        buttons,
+        attempts: 1,
+    })
+}
+
+/// Tries to deliver an outbox message once, and removes it from the outbox when it arrived.
+pub async fn attempt(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    message: &OutboxMessage,
+) -> Result<Delivery, Error> {
+    Ok(attempt_returning(http, pool, translations, message)
+        .await?
+        .0)
+}
+
+/// Like [`attempt`], also returning the message that was posted when it arrived, e.g. to link to it.
+pub async fn attempt_returning(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    message: &OutboxMessage,
+) -> Result<(Delivery, Option<Message>), Error> {
+    match send(http, pool, translations, message).await {
+        Ok(posted) => {
+            database::delete_outbox_message(pool, message.id).await?;
+
+            Ok((Delivery::Delivered, Some(posted)))
+        }
+        Err(e) => {
+            let give_up = message.attempts >= MAX_ATTEMPTS || !is_transient(&e);
+            if give_up {
+                warn!(
+                    "giving up on outbox message {} after {} attempt(s): {e:?}",
+                    message.id, message.attempts
+                );
+                database::fail_outbox_message(pool, message.id, &e.to_string()).await?;
+
+                return Ok((Delivery::Failed, None));
+            }
+
+            warn!(
+                "could not deliver outbox message {}, retrying later: {e:?}",
+                message.id
+            );
+            database::record_outbox_failure(pool, message.id, &e.to_string()).await?;
+
+            Ok((Delivery::Retrying, None))
+        }
+    }
+}
+
+async fn send(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    message: &OutboxMessage,
+) -> Result<Message, Error> {
+    match message.destination {
+        Destination::Channel {
+            channel_id,
+            guild_id,
+            initiator,
+        } => {
+            send_with_fallback(http, pool, channel_id, guild_id, initiator, |m| {
+                build(m, message, translations)
+            })
+            .await
+        }
+        Destination::Dm(user_id) => match user_id.create_dm_channel(http).await {
+            Ok(channel) => channel
+                .send_message(http, |m| build(m, message, translations))
+                .await
+                .map_err(Error::from),
+            Err(e) => Err(e.into()),
+        },
+    }
+}
+
+fn build<'a, 'b>(
+    m: &'b mut CreateMessage<'a>,
+    message: &OutboxMessage,
+    translations: &Translations,
+) -> &'b mut CreateMessage<'a> {
+    m.content(&message.content);
+    if let Some(target) = message.buttons {
+        m.components(|c| quick_tip::buttons(c, target, translations));
+    }
+
+    m
+}
+
+/// Whether retrying could help. Discord refusing the message itself, like a DM to a user that does not accept DMs
+/// from the bot, is not transient, but rate limits and server errors are.
+fn is_transient(error: &Error) -> bool {
+    match error.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(e)) => match e.as_ref() {
+            HttpError::UnsuccessfulRequest(response) => {
+                !response.status_code.is_client_error() || response.status_code.as_u16() == 429
+            }
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// Retries the outbox messages that are due, every [`OUTBOX_INTERVAL`].
+pub async fn deliver_outbox(http: Arc<Http>, pool: PgPool, translations: Arc<Translations>) {
+    let mut interval = interval(OUTBOX_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let due = match database::claim_due_outbox_messages(&pool).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("could not get the due outbox messages: {e:?}");
+                continue;
+            }
+        };
+
+        for message in due {
+            debug!(
+                "retrying outbox message {} (attempt {})",
+                message.id, message.attempts
+            );
+            if let Err(e) = attempt(&http, &pool, &translations, &message).await {
+                error!("could not update outbox message {}: {e:?}", message.id);
+            }
+        }
+    }
+}
+
+/// Retries the failed notifications that are due, every [`FAILED_RETRY_INTERVAL`].
+pub async fn retry_failed_notifications(
+    http: Arc<Http>,
+    pool: PgPool,
+    translations: Arc<Translations>,
+) {
+    let mut interval = interval(FAILED_RETRY_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let due = match database::claim_due_failed_notifications(&pool).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("could not get the due failed notifications: {e:?}");
+                continue;
+            }
+        };
+
+        for message in due {
+            let result = match send(&http, &pool, &translations, &message).await {
+                Ok(_) => {
+                    info!(
+                        "delivered failed notification {} on retry {}",
+                        message.id, message.attempts
+                    );
+                    database::delete_failed_notification(&pool, message.id).await
+                }
+                Err(e) => {
+                    debug!(
+                        "could not deliver failed notification {} on retry {}: {e:?}",
+                        message.id, message.attempts
+                    );
+                    database::record_failed_notification_error(&pool, message.id, &e.to_string())
+                        .await
+                }
+            };
+
+            if let Err(e) = result {
+                error!("could not update failed notification {}: {e:?}", message.id);
+            }
+        }
+    }
+}
+
+/// What tips are batched for: their announcements in a channel, or their DMs to a recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BatchKey {
+    Channel(ChannelId),
+    Dm(UserId),
+}
+
+/// Tips waiting to be announced in a single summary, coalesced from the entries of their batch.
+#[derive(Debug)]
+pub struct PendingBatch {
+    pub key: BatchKey,
+    pub guild_id: Option<GuildId>,
+    /// The tipper of the first tip in the batch, who gets the summary in DM when it can not be posted anywhere else.
+    /// For DMs this is the recipient.
+    pub initiator: UserId,
+    pub tips: usize,
+    pub total: Amount,
+    /// The recipients of the batched tips that could not be DMed
+    pub unnotified: usize,
+    pub window: Duration,
+}
+
+/// The tips in a channel, or tip DMs to a user, that were recently announced on their own, to decide whether new ones
+/// should be batched.
+#[derive(Debug, Default)]
+struct Recent {
+    /// How long the announcements count, after that they are forgotten
+    window: Duration,
+    posted: VecDeque<Instant>,
+}
+
+impl Recent {
+    /// Whether `threshold` tips were announced in the last `window`, or, in channels with slow mode, any tip was. A
+    /// `threshold` of 0 turns batching off.
+    fn busy(&self, now: Instant, threshold: usize, window: Duration, slow_mode: bool) -> bool {
+        let recent = self
+            .posted
+            .iter()
+            .filter(|posted| now.duration_since(**posted) < window)
+            .count();
+
+        threshold > 0 && (recent >= threshold || (slow_mode && recent > 0))
+    }
+
+    /// Records an announcement that was posted at `now`.
+    fn record(&mut self, now: Instant, window: Duration) {
+        self.window = window;
+        while self
+            .posted
+            .front()
+            .is_some_and(|posted| now.duration_since(*posted) >= window)
+        {
+            self.posted.pop_front();
+        }
+        self.posted.push_back(now);
+    }
+
+    /// Whether none of the announcements count anymore.
+    fn idle(&self, now: Instant) -> bool {
+        self.posted
+            .back()
+            .is_none_or(|posted| now.duration_since(*posted) >= self.window)
+    }
+}
+
+/// The number of channels and users whose recent announcements are kept. Only the pending batches have to survive a
+/// restart, they are in the database.
+const MAX_RECENT: usize = 10_000;
+
+static RECENT: Lazy<Mutex<HashMap<BatchKey, Recent>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Records an announcement for `key`. When `capacity` keys are known already, the idle ones are forgotten, and when
+/// none are idle, the one with the oldest announcement is.
+fn record_announcement(
+    recent: &mut HashMap<BatchKey, Recent>,
+    capacity: usize,
+    key: BatchKey,
+    now: Instant,
+    window: Duration,
+) {
+    if !recent.contains_key(&key) && recent.len() >= capacity {
+        recent.retain(|_, recent| !recent.idle(now));
+
+        if recent.len() >= capacity {
+            let oldest = recent
+                .iter()
+                .min_by_key(|(_, recent)| recent.posted.back().copied())
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                recent.remove(&oldest);
+            }
+        }
+    }
+
+    recent.entry(key).or_default().record(now, window);
+}
+
+/// Whether the announcement of a tip in a channel is batched, see `/config batching`. It is decided before the tip
+/// is paid, and the tip is added to the batch with [`batch_tip`], or recorded with [`record_tip`] once it is
+/// announced on its own.
+#[derive(Debug, Clone, Copy)]
+pub struct Batching {
+    channel_id: ChannelId,
+    guild_id: Option<GuildId>,
+    /// The batching window of the guild, None when it does not batch
+    window: Option<Duration>,
+    batched: bool,
+}
+
+impl Batching {
+    /// Whether the tip is added to a batch, instead of being announced on its own.
+    pub fn batched(&self) -> bool {
+        self.batched
+    }
+}
+
+/// Decides whether a tip in `channel_id` is batched: when tips are waiting in its batch already, or when the channel
+/// is busy.
+pub async fn batching(
+    http: &Http,
+    pool: &PgPool,
+    channel_id: ChannelId,
+    guild_id: Option<GuildId>,
+) -> Result<Batching, Error> {
+    let mut batching = Batching {
+        channel_id,
+        guild_id,
+        window: None,
+        batched: false,
+    };
+    let (threshold, window) = match guild_id {
+        Some(guild_id) => database::get_batch_settings(pool, guild_id).await?,
+        None => return Ok(batching),
+    };
+    if threshold == 0 {
+        return Ok(batching);
+    }
+
+    let slow_mode = match channel_id.to_channel(http).await {
+        Ok(Channel::Guild(channel)) => channel.rate_limit_per_user.unwrap_or(0) > 0,
+        _ => false,
+    };
+    let pending = database::has_pending_batch(pool, BatchKey::Channel(channel_id)).await?;
+
+    let window = Duration::from_secs(window as u64);
+    batching.window = Some(window);
+    batching.batched = pending
+        || RECENT
+            .lock()
+            .unwrap()
+            .get(&BatchKey::Channel(channel_id))
+            .is_some_and(|recent| {
+                recent.busy(Instant::now(), threshold as usize, window, slow_mode)
+            });
+
+    Ok(batching)
+}
+
+/// Adds a tip to the batch of its channel when its announcement is batched, as decided by [`batching`]. When `conn`
+/// is the transaction that pays the tip, the tip is only added when it is paid. Returns the id of the entry, to
+/// count the recipients that could not be DMed with [`database::add_batch_unnotified`].
+pub async fn batch_tip(
+    conn: &mut PgConnection,
+    batching: &Batching,
+    initiator: UserId,
+    amount: Amount,
+) -> Result<Option<i64>, Error> {
+    let (true, Some(window)) = (batching.batched, batching.window) else {
+        return Ok(None);
+    };
+
+    let entry = database::store_batch_entry(
+        conn,
+        BatchKey::Channel(batching.channel_id),
+        batching.guild_id,
+        initiator,
+        amount,
+        window,
+    )
+    .await?;
+
+    Ok(Some(entry))
+}
+
+/// Records a tip that was announced on its own, as decided by [`batching`].
+pub fn record_tip(batching: &Batching) {
+    if let (false, Some(window)) = (batching.batched, batching.window) {
+        record_announcement(
+            &mut RECENT.lock().unwrap(),
+            MAX_RECENT,
+            BatchKey::Channel(batching.channel_id),
+            Instant::now(),
+            window,
+        );
+    }
+}
+
+/// Whether the DM of a tip to `recipient` is batched, because they got too many tip DMs recently. It is decided
+/// before the tip is paid, and the tip is added to the batch with [`batch_dm`], or recorded with [`record_dm`] once
+/// it is DMed on its own.
+pub async fn dm_batched(pool: &PgPool, recipient: UserId) -> Result<bool, Error> {
+    if database::has_pending_batch(pool, BatchKey::Dm(recipient)).await? {
+        return Ok(true);
+    }
+
+    Ok(RECENT
+        .lock()
+        .unwrap()
+        .get(&BatchKey::Dm(recipient))
+        .is_some_and(|recent| {
+            recent.busy(Instant::now(), DM_BATCH_THRESHOLD, DM_BATCH_WINDOW, false)
+        }))
+}
+
+/// Adds a tip to the DM batch of `recipient`, as decided by [`dm_batched`]. When `conn` is the transaction that pays
+/// the tip, the tip is only added when it is paid.
+pub async fn batch_dm(
+    conn: &mut PgConnection,
+    recipient: UserId,
+    amount: Amount,
+) -> Result<(), Error> {
+    database::store_batch_entry(
+        conn,
+        BatchKey::Dm(recipient),
+        None,
+        recipient,
+        amount,
+        DM_BATCH_WINDOW,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Records a tip DM that was sent to `recipient` on its own, as decided by [`dm_batched`].
+pub fn record_dm(recipient: UserId) {
+    record_announcement(
+        &mut RECENT.lock().unwrap(),
+        MAX_RECENT,
+        BatchKey::Dm(recipient),
+        Instant::now(),
+        DM_BATCH_WINDOW,
+    );
+}
+
+/// Posts the summaries of batches whose window has passed, every [`FLUSH_INTERVAL`].
+pub async fn flush_batches(http: Arc<Http>, pool: PgPool, translations: Arc<Translations>) {
+    let mut interval = interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        post_summaries(&http, &pool, &translations, false).await;
+    }
+}
+
+/// Posts the summaries of all pending batches right away. The batches are stored, so they would be posted after a
+/// restart too, but this is done on shutdown, after the operations in flight are done, so the summaries are not held
+/// up by the downtime.
+pub async fn flush_all_batches(http: &Http, pool: &PgPool, translations: &Translations) {
+    let posted = post_summaries(http, pool, translations, true).await;
+    info!("flushed {posted} tip summaries");
+}
+
+/// Posts the summaries of the due batches, or of all of them. Returns the number of summaries that were posted.
+async fn post_summaries(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    all: bool,
+) -> usize {
+    let due = match database::get_due_batches(pool, all).await {
+        Ok(due) => due,
+        Err(e) => {
+            error!("could not get the due tip batches: {e:?}");
+            return 0;
+        }
+    };
+
+    let mut posted = 0;
+    for key in due {
+        match post_summary(http, pool, translations, key).await {
+            Ok(true) => posted += 1,
+            Ok(false) => {}
+            Err(e) => error!("could not post the tip summary of {key:?}: {e:?}"),
+        }
+    }
+
+    posted
+}
+
+/// Coalesces the tips of a batch into a summary, which goes through the outbox like the announcements and DMs of tips
+/// do. Returns false when the batch was already taken.
+async fn post_summary(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    key: BatchKey,
+) -> Result<bool, Error> {
+    let locale = match key {
+        BatchKey::Channel(_) => None,
+        BatchKey::Dm(user_id) => database::get_dm_language(pool, &user_id).await?,
+    };
+
+    // the entries are replaced by the summary in one transaction, so a restart in between posts them once
+    let mut tx = pool.begin().await?;
+    let Some(pending) = database::take_batch(&mut tx, key).await? else {
+        return Ok(false);
+    };
+    let (destination, content) = summary(translations, locale.as_deref(), &pending);
+    let message = enqueue(&mut tx, destination, &content, None).await?;
+    tx.commit().await?;
+
+    // a summary counts as an announcement, so a flood that goes on keeps being batched
+    record_announcement(
+        &mut RECENT.lock().unwrap(),
+        MAX_RECENT,
+        key,
+        Instant::now(),
+        pending.window,
+    );
+
+    // a summary the recipient does not accept ends up in the failed notifications like any other tip DM
+    attempt(http, pool, translations, &message).await?;
+
+    Ok(true)
+}
+
+/// Where the summary of a batch goes, and what it says.
+fn summary(
+    translations: &Translations,
+    locale: Option<&str>,
+    pending: &PendingBatch,
+) -> (Destination, String) {
+    let minutes = (pending.window.as_secs() / 60).max(1);
+
+    match pending.key {
+        BatchKey::Channel(channel_id) => {
+            let title = tr_locale!(translations, None, "tip-batch-title");
+            let mut summary = tr_locale!(
+                translations,
+                None,
+                "tip-batch-summary",
+                count: pending.tips,
+                total: format::amount(pending.total),
+                minutes: minutes
+            );
+            if pending.unnotified > 0 {
+                summary.push('\n');
+                summary.push_str(&tr_locale!(
+                    translations,
+                    None,
+                    "tip-dm-failed",
+                    count: pending.unnotified
+                ));
+            }
+
+            let destination = Destination::Channel {
+                channel_id,
+                guild_id: pending.guild_id,
+                initiator: pending.initiator,
+            };
+
+            (destination, format!("**{title}**\n{summary}"))
+        }
+        BatchKey::Dm(user_id) => {
+            let summary = tr_locale!(
+                translations,
+                locale,
+                "tip-dm-batch-summary",
+                count: pending.tips,
+                total: format::amount(pending.total),
+                minutes: minutes
+            );
+
+            (Destination::Dm(user_id), summary)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    /// Decides whether a tip is batched and records it when it is announced on its own, like the tip commands do.
+    fn add(
+        recent: &mut Recent,
+        now: Instant,
+        threshold: usize,
+        window: Duration,
+        slow_mode: bool,
+    ) -> bool {
+        let batched = recent.busy(now, threshold, window, slow_mode);
+        if !batched {
+            recent.record(now, window);
+        }
+
+        batched
+    }
+
+    #[test]
+    fn batches_once_the_threshold_is_reached() {
+        let mut recent = Recent::default();
+        let now = Instant::now();
+
+        assert!(!add(&mut recent, now, 2, WINDOW, false));
+        assert!(!add(&mut recent, now, 2, WINDOW, false));
+        assert!(add(&mut recent, now, 2, WINDOW, false));
+        assert!(add(&mut recent, now, 2, WINDOW, false));
+    }
+
+    #[test]
+    fn announcements_outside_the_window_do_not_count() {
+        let mut recent = Recent::default();
+        let now = Instant::now();
+
+        assert!(!add(&mut recent, now, 1, WINDOW, false));
+        assert!(!add(&mut recent, now + WINDOW, 1, WINDOW, false));
+    }
+
+    #[test]
+    fn slow_mode_batches_from_the_second_announcement() {
+        let mut recent = Recent::default();
+        let now = Instant::now();
+
+        assert!(!add(&mut recent, now, 5, WINDOW, true));
+        assert!(add(&mut recent, now, 5, WINDOW, true));
+    }
+
+    #[test]
+    fn deciding_does_not_change_the_recent_announcements() {
+        let mut recent = Recent::default();
+        let now = Instant::now();
+
+        assert!(!recent.busy(now, 1, WINDOW, false));
+        assert!(!recent.busy(now, 1, WINDOW, false));
+        recent.record(now, WINDOW);
+        assert!(recent.busy(now, 1, WINDOW, false));
+    }
+
+    #[test]
+    fn a_threshold_of_zero_never_batches() {
+        let mut recent = Recent::default();
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(!add(&mut recent, now, 0, WINDOW, true));
+        }
+    }
+
+    #[test]
+    fn recent_announcements_are_bounded() {
+        let mut recent = HashMap::new();
+        let now = Instant::now();
+
+        record_announcement(&mut recent, 2, BatchKey::Channel(ChannelId(1)), now, WINDOW);
+        record_announcement(
+            &mut recent,
+            2,
+            BatchKey::Dm(UserId(1)),
+            now + WINDOW,
+            WINDOW,
+        );
+        // the channel is idle by now, so it is forgotten first
+        let later = now + WINDOW + Duration::from_secs(1);
+        record_announcement(
+            &mut recent,
+            2,
+            BatchKey::Channel(ChannelId(2)),
+            later,
+            WINDOW,
+        );
+        assert_eq!(recent.len(), 2);
+        assert!(!recent.contains_key(&BatchKey::Channel(ChannelId(1))));
+
+        // none are idle, so the one with the oldest announcement is
+        record_announcement(
+            &mut recent,
+            2,
+            BatchKey::Channel(ChannelId(3)),
+            later,
+            WINDOW,
+        );
+        assert_eq!(recent.len(), 2);
+        assert!(!recent.contains_key(&BatchKey::Dm(UserId(1))));
+
+        // known keys are recorded without forgetting others
+        record_announcement(
+            &mut recent,
+            2,
+            BatchKey::Channel(ChannelId(3)),
+            later,
+            WINDOW,
+        );
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[&BatchKey::Channel(ChannelId(3))].posted.len(), 2);
+    }
+
+    #[sqlx::test]
+    async fn dms_are_batched_after_the_threshold(pool: PgPool) {
+        // a user that no other test uses, the recent announcements are shared
+        let recipient = UserId(4_242);
+
+        for _ in 0..DM_BATCH_THRESHOLD {
+            assert!(!dm_batched(&pool, recipient).await.unwrap());
+            record_dm(recipient);
+        }
+        assert!(dm_batched(&pool, recipient).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn tips_join_a_pending_batch(pool: PgPool) {
+        // a user that no other test uses, the recent announcements are shared
+        let recipient = UserId(4_243);
+        assert!(!dm_batched(&pool, recipient).await.unwrap());
+
+        batch_dm(
+            &mut pool.acquire().await.unwrap(),
+            recipient,
+            Amount::from_sat(100),
+        )
+        .await
+        .unwrap();
+        assert!(dm_batched(&pool, recipient).await.unwrap());
+    }
+}
@@ -0,0 +1,77 @@
+//! The embeds of informational commands, like `/price` and `/chaininfo`, in the output users pick with
+//! `/settings output`.
+//!
+//! Commands add the fields everyone sees with [`InfoEmbed::field`] and the rest with [`InfoEmbed::detail`]. Users who
+//! prefer compact output only get the first kind.
+
+use poise::serenity_prelude::CreateEmbed;
+
+use crate::{util::database, Context, Error};
+
+/// The fields of an informational embed, in the output the author of the command prefers.
+#[derive(Debug)]
+pub struct InfoEmbed {
+    compact: bool,
+    fields: Vec<(String, String, bool)>,
+}
+
+impl InfoEmbed {
+    /// Starts the fields of an embed for the author of `ctx`.
+    pub async fn new(ctx: Context<'_>) -> Result<Self, Error> {
+        let compact = database::get_compact(&ctx.data().database, &ctx.author().id).await?;
+
+        Ok(Self::with_output(compact))
+    }
+
+    fn with_output(compact: bool) -> Self {
+        Self {
+            compact,
+            fields: vec![],
+        }
+    }
+
+    /// Adds a field that is always shown.
+    pub fn field(&mut self, name: impl ToString, value: impl ToString, inline: bool) -> &mut Self {
+        self.fields
+            .push((name.to_string(), value.to_string(), inline));
+        self
+    }
+
+    /// Adds a field that is left out of compact output.
+    pub fn detail(&mut self, name: impl ToString, value: impl ToString, inline: bool) -> &mut Self {
+        if !self.compact {
+            self.field(name, value, inline);
+        }
+        self
+    }
+
+    /// Adds the fields to `embed`.
+    pub fn fill<'a>(&self, embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+        embed.fields(self.fields.iter().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_output_leaves_out_the_details() {
+        let build = |compact| {
+            let mut embed = InfoEmbed::with_output(compact);
+            embed
+                .field("USD", "$0.50", true)
+                .detail("Volume", "$10,000", false)
+                .field("BTC", "0.00001 BTC", true);
+
+            embed
+                .fields
+                .into_iter()
+                .map(|(name, _, _)| name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(build(true), vec!["USD", "BTC"]);
+        assert_eq!(build(false), vec!["USD", "Volume", "BTC"]);
+    }
+}
@@ -0,0 +1,170 @@
+//! Formatting of amounts in bot responses.
+//!
+//! Amounts are shown with thousands separators and without trailing zeros, e.g. `1,234.5 VRSC` instead of
+//! `1234.50000000 VRSC`. Fiat amounts use 2 decimals, unless the value is so small that 2 decimals would hide it.
+//!
+//! Text written by users (like tip notes) is passed through [`user_text`] before it is put in a message.
+
+use vrsc::Amount;
+
+pub const VRSC: &str = "VRSC";
+
+const SATS_PER_COIN: u64 = 100_000_000;
+
+/// `1,234.5 VRSC`
+pub fn amount(amount: Amount) -> String {
+    amount_in(amount, VRSC)
+}
+
+/// Like [`amount`], for other currencies on the Verus network (which all have 8 decimals), e.g. `1,234.5 vETH`.
+pub fn amount_in(amount: Amount, currency: &str) -> String {
+    format!("{} {currency}", coins(amount))
+}
+
+/// `1.2k VRSC`, `3.45M VRSC`. Amounts below 1,000 are shown in full.
+pub fn compact(amount: Amount) -> String {
+    let value = amount.as_vrsc();
+
+    let (value, suffix) = match value {
+        v if v >= 1_000_000_000.0 => (v / 1_000_000_000.0, "B"),
+        v if v >= 1_000_000.0 => (v / 1_000_000.0, "M"),
+        v if v >= 1_000.0 => (v / 1_000.0, "k"),
+        _ => return self::amount(amount),
+    };
+
+    format!("{}{suffix} {VRSC}", trim_zeros(&format!("{value:.2}")))
+}
+
+/// `$ 1,234.56`
+pub fn usd(value: f64) -> String {
+    format!("$ {}", fiat(value))
+}
+
+/// `₿ 0.00001234`
+pub fn btc(value: f64) -> String {
+    format!("₿ {}", number(value, 8))
+}
+
+/// A number with thousands separators and a fixed number of decimals.
+pub fn number(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = formatted
+        .split_once('.')
+        .map_or((formatted.as_str(), None), |(w, f)| (w, Some(f)));
+
+    let mut result = String::new();
+    if value < 0.0 {
+        result.push('-');
+    }
+    result.push_str(&group_thousands(whole));
+    if let Some(fraction) = fraction {
+        result.push('.');
+        result.push_str(fraction);
+    }
+
+    result
+}
+
+/// Makes text from a user safe to put in a message: mentions are broken up so nobody gets pinged, markdown is
+/// escaped, newlines are removed and the text is cut off at `max_length` characters.
+pub fn user_text(text: &str, max_length: usize) -> String {
+    let mut safe = String::with_capacity(text.len());
+
+    for c in text.chars().take(max_length) {
+        match c {
+            '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '#' | '[' | ']' => {
+                safe.push('\\');
+                safe.push(c);
+            }
+            // a zero width space after the @ prevents @everyone, @here and <@user> pings.
+            '@' => safe.push_str("@\u{200B}"),
+            '\n' | '\r' => safe.push(' '),
+            _ => safe.push(c),
+        }
+    }
+
+    safe.trim().to_string()
+}
+
+/// The amount in whole coins, without currency.
+fn coins(amount: Amount) -> String {
+    let sats = amount.as_sat();
+    let whole = group_thousands(&(sats / SATS_PER_COIN).to_string());
+    let fraction = format!("{:08}", sats % SATS_PER_COIN);
+    let fraction = fraction.trim_end_matches('0');
+
+    match fraction.is_empty() {
+        true => whole,
+        false => format!("{whole}.{fraction}"),
+    }
+}
+
+fn fiat(value: f64) -> String {
+    match value.abs() {
+        v if v > 0.0 && v < 1.0 => number(value, 4),
+        _ => number(value, 2),
+    }
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    grouped
+}
+
+fn trim_zeros(number: &str) -> &str {
+    match number.contains('.') {
+        true => number.trim_end_matches('0').trim_end_matches('.'),
+        false => number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts() {
+        assert_eq!(amount(Amount::ZERO), "0 VRSC");
+        assert_eq!(amount(Amount::from_sat(1)), "0.00000001 VRSC");
+        assert_eq!(amount(Amount::from_sat(150_000_000)), "1.5 VRSC");
+        assert_eq!(
+            amount(Amount::from_sat(123_456_789_000_000)),
+            "1,234,567.89 VRSC"
+        );
+        assert_eq!(amount_in(Amount::from_sat(100_000_000), "vETH"), "1 vETH");
+    }
+
+    #[test]
+    fn compact_amounts() {
+        assert_eq!(compact(Amount::from_sat(99_950_000_000)), "999.5 VRSC");
+        assert_eq!(compact(Amount::from_sat(120_000_000_000)), "1.2k VRSC");
+        assert_eq!(compact(Amount::from_sat(345_000_000_000_000)), "3.45M VRSC");
+    }
+
+    #[test]
+    fn user_text_is_made_safe() {
+        assert_eq!(user_text("thanks!", 200), "thanks!");
+        assert_eq!(user_text("@everyone", 200), "@\u{200B}everyone");
+        assert_eq!(user_text("<@1234>", 200), "<@\u{200B}1234\\>");
+        assert_eq!(user_text("**bold**", 200), "\\*\\*bold\\*\\*");
+        assert_eq!(user_text("line\nbreak", 200), "line break");
+        assert_eq!(user_text("abcdef", 3), "abc");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(number(-1234.0, 0), "-1,234");
+        assert_eq!(usd(0.4567), "$ 0.4567");
+        assert_eq!(usd(1234.5), "$ 1,234.50");
+        assert_eq!(btc(0.00001234), "₿ 0.00001234");
+    }
+}
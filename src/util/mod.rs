@@ -1 +1,8 @@
+pub mod balance_store;
+pub mod cache;
 pub mod database;
+pub mod delivery;
+pub mod embed;
+pub mod format;
+pub mod rpc;
+pub mod rpc_guard;
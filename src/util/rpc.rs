@@ -0,0 +1,174 @@
+//! The calls to the Verus daemon that move funds out of the bot.
+//!
+//! Withdrawals go through [`VerusRpc`] instead of the client directly, so their logic can be tested with
+//! [`mock::MockRpc`] instead of a running daemon.
+
+use std::{future::Future, sync::Arc};
+
+use vrsc::Amount;
+use vrsc_rpc::{bitcoin::Txid, jsonrpc, Client, RpcApi, SendCurrencyOutput};
+
+use crate::{util::rpc_guard, Error};
+
+/// The daemon answers `getidentity` with `RPC_INVALID_ADDRESS_OR_KEY` or `RPC_INVALID_PARAMETER` when the name is not
+/// an identity, or no identity has that name.
+const IDENTITY_NOT_FOUND: [i32; 2] = [-5, -8];
+
+pub trait VerusRpc {
+    /// Whether `name` is an existing VerusID. Fails when the daemon could not tell.
+    fn identity_exists(&self, name: &str) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// Sends `amount` to `destination` from the wallet of the daemon and returns the id of the operation.
+    /// `currency` is `None` for the native currency of the chain.
+    fn send_to(
+        &self,
+        currency: Option<&str>,
+        amount: Amount,
+        destination: &str,
+    ) -> impl Future<Output = Result<String, Error>> + Send;
+
+    /// The status of an operation started with [`VerusRpc::send_to`], if the daemon knows it yet.
+    fn operation_status(
+        &self,
+        opid: &str,
+    ) -> impl Future<Output = Result<Option<Operation>, Error>> + Send;
+}
+
+/// The status of a `sendcurrency` operation, as returned by `z_getoperationstatus`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    /// One of queued, executing, failed or success
+    pub status: String,
+    pub creation_time: i64,
+    /// The transaction of the operation, once it succeeded
+    pub txid: Option<Txid>,
+    pub address: String,
+    pub amount: f64,
+    pub currency: Option<String>,
+}
+
+impl Operation {
+    pub fn is_pending(&self) -> bool {
+        ["queued", "executing"].contains(&self.status.as_str())
+    }
+}
+
+impl VerusRpc for Arc<Client> {
+    async fn identity_exists(&self, name: &str) -> Result<bool, Error> {
+        let name = name.to_owned();
+
+        match rpc_guard::call(self, "getidentity", move |client| {
+            client.get_identity(&name)
+        })
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) if is_identity_not_found(&error) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn send_to(
+        &self,
+        currency: Option<&str>,
+        amount: Amount,
+        destination: &str,
+    ) -> Result<String, Error> {
+        let (currency, destination) = (currency.map(str::to_owned), destination.to_owned());
+
+        let opid = rpc_guard::call_once(self, "sendcurrency", move |client| {
+            let sco = SendCurrencyOutput::new(currency.as_deref(), &amount, &destination);
+            RpcApi::send_currency(client, "*", vec![sco], None, None)
+        })
+        .await?;
+
+        Ok(opid.to_string())
+    }
+
+    async fn operation_status(&self, opid: &str) -> Result<Option<Operation>, Error> {
+        let opid = opid.to_owned();
+
+        let operation_status = rpc_guard::call(self, "z_getoperationstatus", move |client| {
+            client.z_get_operation_status(vec![&opid])
+        })
+        .await?;
+
+        let Some(Some(status)) = operation_status.into_iter().next() else {
+            return Ok(None);
+        };
+        let params = status
+            .params
+            .into_iter()
+            .flatten()
+            .next()
+            .ok_or("operation without parameters")?;
+
+        Ok(Some(Operation {
+            status: status.status,
+            creation_time: status.creation_time as i64,
+            txid: status.result.map(|result| result.txid),
+            address: params.address,
+            amount: params.amount,
+            currency: params.currency,
+        }))
+    }
+}
+
+/// Whether the daemon answered that there is no such identity.
+fn is_identity_not_found(error: &Error) -> bool {
+    matches!(
+        error.downcast_ref::<vrsc_rpc::Error>(),
+        Some(vrsc_rpc::Error::JsonRPC(jsonrpc::Error::Rpc(error))) if IDENTITY_NOT_FOUND.contains(&error.code)
+    )
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::{collections::VecDeque, sync::Mutex};
+
+    use super::*;
+
+    /// A daemon that knows the given identities and answers status requests with the queued statuses, in order.
+    #[derive(Default)]
+    pub struct MockRpc {
+        pub identities: Vec<String>,
+        pub statuses: Mutex<VecDeque<Option<Operation>>>,
+        /// Every `sendcurrency` call, as (currency, amount, destination)
+        pub sent: Mutex<Vec<(Option<String>, Amount, String)>>,
+    }
+
+    impl MockRpc {
+        pub fn with_statuses(statuses: impl IntoIterator<Item = Option<Operation>>) -> Self {
+            Self {
+                statuses: Mutex::new(statuses.into_iter().collect()),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl VerusRpc for MockRpc {
+        async fn identity_exists(&self, name: &str) -> Result<bool, Error> {
+            Ok(self.identities.iter().any(|identity| identity == name))
+        }
+
+        async fn send_to(
+            &self,
+            currency: Option<&str>,
+            amount: Amount,
+            destination: &str,
+        ) -> Result<String, Error> {
+            let mut sent = self.sent.lock().unwrap();
+            sent.push((currency.map(str::to_owned), amount, destination.to_owned()));
+
+            Ok(format!("opid-{}", sent.len()))
+        }
+
+        async fn operation_status(&self, _opid: &str) -> Result<Option<Operation>, Error> {
+            self.statuses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| "no more statuses".into())
+        }
+    }
+}
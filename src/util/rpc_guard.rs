@@ -0,0 +1,355 @@
+//! Calls to the Verus daemon that survive hiccups, and stop early when the daemon is down.
+//!
+//! The client blocks while it waits for the daemon, so every attempt runs on the blocking pool of tokio and is given
+//! up on after [`CALL_TIMEOUT`]. The attempt itself can not be interrupted and finishes in the background.
+//!
+//! [`call`] retries a call that failed to reach the daemon or timed out, waiting twice as long before every next
+//! attempt. Errors that the daemon itself returned, like an unknown identity, are not retried.
+//!
+//! After [`FAILURE_THRESHOLD`] calls in a row could not reach the daemon, the circuit opens: for [`OPEN_FOR`], every
+//! call fails right away with [`ChainUnavailable`] instead of waiting for the daemon to time out again. After that a
+//! single call is let through, which closes the circuit when it succeeds. Commands that fail with
+//! [`ChainUnavailable`] tell the user to try again later instead of showing the RPC error.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use tracing::*;
+use vrsc_rpc::jsonrpc;
+
+use crate::{metrics, Error};
+
+/// The number of attempts of a call that could not reach the daemon.
+const MAX_ATTEMPTS: u32 = 3;
+/// The delay before the first retry. Every next retry waits twice as long.
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+/// How long a single attempt may take before it counts as not reaching the daemon.
+pub const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+/// The number of calls in a row that could not reach the daemon, after which the circuit opens.
+pub const FAILURE_THRESHOLD: u32 = 5;
+/// How long the circuit stays open before a call is let through again.
+pub const OPEN_FOR: Duration = Duration::from_secs(30);
+
+/// The daemon is `RPC_IN_WARMUP` while it loads its index after a restart.
+const RPC_IN_WARMUP: i32 = -28;
+
+static BREAKER: Lazy<Mutex<Breaker>> = Lazy::new(|| Mutex::new(Breaker::default()));
+
+/// A call that was not done, because the daemon could not be reached.
+#[derive(Debug)]
+pub struct ChainUnavailable {
+    pub method: String,
+}
+
+impl std::fmt::Display for ChainUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} failed: the Verus daemon can not be reached",
+            self.method
+        )
+    }
+}
+
+impl std::error::Error for ChainUnavailable {}
+
+pub fn is_chain_unavailable(error: &Error) -> bool {
+    error.downcast_ref::<ChainUnavailable>().is_some()
+}
+
+/// Whether a call that failed with `error` was certainly not done: it never went to the daemon, or the daemon answered
+/// it with an error. A call of [`call_once`] that timed out or lost its connection is not known to be done or not.
+pub fn not_done(error: &Error) -> bool {
+    is_chain_unavailable(error)
+        || matches!(
+            error.downcast_ref::<vrsc_rpc::Error>(),
+            Some(vrsc_rpc::Error::JsonRPC(jsonrpc::Error::Rpc(_)))
+        )
+}
+
+/// Calls the daemon with `client`, retrying when the call did not reach it.
+pub async fn call<C, T>(
+    client: &Arc<C>,
+    method: &str,
+    call: impl Fn(&C) -> Result<T, vrsc_rpc::Error> + Send + Sync + 'static,
+) -> Result<T, Error>
+where
+    C: Send + Sync + 'static,
+    T: Send + 'static,
+{
+    call_with(&BREAKER, client, method, MAX_ATTEMPTS, call).await
+}
+
+/// Calls the daemon without retrying, for calls that must not be done twice, like sending funds. A call that did not
+/// reach the daemon or timed out might still have been done, so it fails with the original error instead of
+/// [`ChainUnavailable`].
+pub async fn call_once<C, T>(
+    client: &Arc<C>,
+    method: &str,
+    call: impl Fn(&C) -> Result<T, vrsc_rpc::Error> + Send + Sync + 'static,
+) -> Result<T, Error>
+where
+    C: Send + Sync + 'static,
+    T: Send + 'static,
+{
+    call_with(&BREAKER, client, method, 1, call).await
+}
+
+/// Why an attempt did not reach the daemon.
+enum Unreached {
+    Transport(vrsc_rpc::Error),
+    TimedOut,
+}
+
+async fn call_with<C, T>(
+    breaker: &Mutex<Breaker>,
+    client: &Arc<C>,
+    method: &str,
+    max_attempts: u32,
+    call: impl Fn(&C) -> Result<T, vrsc_rpc::Error> + Send + Sync + 'static,
+) -> Result<T, Error>
+where
+    C: Send + Sync + 'static,
+    T: Send + 'static,
+{
+    let call = Arc::new(call);
+    let mut backoff = BASE_BACKOFF;
+
+    for attempt in 1.. {
+        if !breaker.lock().unwrap().allows(Instant::now()) {
+            return Err(ChainUnavailable {
+                method: method.to_owned(),
+            }
+            .into());
+        }
+
+        let task = tokio::task::spawn_blocking({
+            let (client, call, method) = (Arc::clone(client), Arc::clone(&call), method.to_owned());
+            move || metrics::observe_rpc(&method, || call(&client))
+        });
+
+        let unreached = match tokio::time::timeout(CALL_TIMEOUT, task).await {
+            Ok(Ok(Ok(result))) => {
+                breaker.lock().unwrap().succeeded();
+
+                return Ok(result);
+            }
+            Ok(Ok(Err(error))) if !is_transient(&error) => {
+                // the daemon answered, so it is up
+                breaker.lock().unwrap().succeeded();
+
+                return Err(error.into());
+            }
+            Ok(Ok(Err(error))) => Unreached::Transport(error),
+            Ok(Err(join_error)) => return Err(join_error.into()),
+            Err(_) => Unreached::TimedOut,
+        };
+
+        match &unreached {
+            Unreached::Transport(error) => {
+                warn!("{method} did not reach the daemon (attempt {attempt}): {error:?}")
+            }
+            Unreached::TimedOut => {
+                warn!("{method} timed out after {CALL_TIMEOUT:?} (attempt {attempt})")
+            }
+        }
+        breaker.lock().unwrap().failed(Instant::now());
+
+        if max_attempts == 1 {
+            return Err(match unreached {
+                Unreached::Transport(error) => error.into(),
+                Unreached::TimedOut => format!("{method} timed out after {CALL_TIMEOUT:?}").into(),
+            });
+        }
+        if attempt >= max_attempts {
+            break;
+        }
+
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+
+    Err(ChainUnavailable {
+        method: method.to_owned(),
+    }
+    .into())
+}
+
+/// Whether the call failed before the daemon could answer it, or the daemon is still starting.
+fn is_transient(error: &vrsc_rpc::Error) -> bool {
+    match error {
+        vrsc_rpc::Error::JsonRPC(jsonrpc::Error::Transport(_)) => true,
+        vrsc_rpc::Error::JsonRPC(jsonrpc::Error::Rpc(error)) => error.code == RPC_IN_WARMUP,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    /// Set while the single call after [`OPEN_FOR`] is in progress
+    probing: bool,
+}
+
+impl Breaker {
+    fn allows(&mut self, now: Instant) -> bool {
+        match self.opened_at {
+            None => true,
+            Some(opened_at) if !self.probing && now.duration_since(opened_at) >= OPEN_FOR => {
+                self.probing = true;
+
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn succeeded(&mut self) {
+        if self.opened_at.is_some() {
+            info!("the daemon is reachable again, closing the circuit");
+            metrics::RPC_CIRCUIT_OPEN.set(0);
+        }
+
+        *self = Self::default();
+    }
+
+    fn failed(&mut self, now: Instant) {
+        self.failures += 1;
+        self.probing = false;
+
+        if self.opened_at.is_some() || self.failures >= FAILURE_THRESHOLD {
+            if self.opened_at.is_none() {
+                error!(
+                    "the daemon could not be reached {} times in a row, opening the circuit",
+                    self.failures
+                );
+                metrics::RPC_CIRCUIT_OPEN.set(1);
+            }
+
+            self.opened_at = Some(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn unreachable() -> vrsc_rpc::Error {
+        vrsc_rpc::Error::JsonRPC(jsonrpc::Error::Transport("connection refused".into()))
+    }
+
+    fn rpc_error(code: i32) -> vrsc_rpc::Error {
+        vrsc_rpc::Error::JsonRPC(jsonrpc::Error::Rpc(jsonrpc::error::RpcError {
+            code,
+            message: String::from("error"),
+            data: None,
+        }))
+    }
+
+    /// A client that counts the attempts made with it.
+    fn attempts() -> Arc<AtomicU32> {
+        Arc::new(AtomicU32::new(0))
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_daemon_is_reached() {
+        let breaker = Mutex::new(Breaker::default());
+        let attempts = attempts();
+
+        let result = call_with(&breaker, &attempts, "ping", 3, |attempts| {
+            match attempts.fetch_add(1, Ordering::SeqCst) + 1 {
+                1 => Err(unreachable()),
+                attempt => Ok(attempt),
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(breaker.lock().unwrap().failures, 0);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_errors_of_the_daemon() {
+        let breaker = Mutex::new(Breaker::default());
+        let attempts = attempts();
+
+        let result: Result<(), _> = call_with(&breaker, &attempts, "getidentity", 3, |attempts| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(rpc_error(-5))
+        })
+        .await;
+
+        assert!(!is_chain_unavailable(&result.unwrap_err()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_with_chain_unavailable() {
+        let breaker = Mutex::new(Breaker::default());
+        let attempts = attempts();
+
+        let result: Result<(), _> = call_with(&breaker, &attempts, "ping", 2, |attempts| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(rpc_error(RPC_IN_WARMUP))
+        })
+        .await;
+
+        assert!(is_chain_unavailable(&result.unwrap_err()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn only_answered_or_unsent_calls_are_not_done() {
+        let breaker = Mutex::new(Breaker::default());
+
+        let answered: Result<(), _> = call_with(&breaker, &attempts(), "sendcurrency", 1, |_| {
+            Err(rpc_error(-6))
+        })
+        .await;
+        assert!(not_done(&answered.unwrap_err()));
+
+        let unreached: Result<(), _> = call_with(&breaker, &attempts(), "sendcurrency", 1, |_| {
+            Err(unreachable())
+        })
+        .await;
+        assert!(!not_done(&unreached.unwrap_err()));
+
+        let unavailable: Error = ChainUnavailable {
+            method: String::from("sendcurrency"),
+        }
+        .into();
+        assert!(not_done(&unavailable));
+    }
+
+    #[test]
+    fn open_circuit_lets_a_single_call_through_after_a_while() {
+        let mut breaker = Breaker::default();
+        let start = Instant::now();
+
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(breaker.allows(start));
+            breaker.failed(start);
+        }
+        assert!(!breaker.allows(start));
+
+        let later = start + OPEN_FOR;
+        assert!(breaker.allows(later));
+        assert!(!breaker.allows(later));
+
+        // the probe failed, so the circuit stays open for another while
+        breaker.failed(later);
+        assert!(!breaker.allows(later + OPEN_FOR / 2));
+        assert!(breaker.allows(later + OPEN_FOR));
+
+        breaker.succeeded();
+        assert!(breaker.allows(later + OPEN_FOR));
+        assert_eq!(breaker.failures, 0);
+    }
+}
@@ -15,8 +15,14 @@ use vrsc_rpc::bitcoin::Txid;
 use vrsc_rpc::json::GetRawTransactionResultVerbose;
 use vrsc_rpc::{Auth, Client, RpcApi};
 
+use crate::commands::misc::Notification;
 use crate::configuration::Settings;
+use crate::i18n::{tr_locale, Translations};
+use crate::metrics;
+use crate::shutdown::Shutdown;
 use crate::util::database::{self, *};
+use crate::util::delivery::{self, Destination};
+use crate::util::format;
 use crate::Error;
 
 /// Listens for wallet transactions and processes them.
@@ -31,6 +37,9 @@ use crate::Error;
 /// The bot can be in maintenance mode, in which case processing will be postponed by putting the yet-to-be-processed
 /// txids in a database table. When maintenance mode is disabled, the transactions will be processed.
 ///
+/// The same table holds the transactions that were waiting for confirmations when the bot shut down. They are
+/// processed when the bot starts again.
+///
 ///
 
 #[derive(Debug)]
@@ -38,10 +47,12 @@ pub struct TransactionProcessor {
     http: Arc<Http>,
     pool: PgPool,
     config: Settings,
+    translations: Arc<Translations>,
     pub maintenance: Arc<RwLock<bool>>,
     pub deposits_enabled: Arc<RwLock<bool>>,
     queue_small_txns: Arc<RwLock<VecDeque<(Txid, Amount)>>>,
     queue_large_txns: Arc<RwLock<VecDeque<(Txid, Amount)>>>,
+    shutdown: Arc<Shutdown>,
 }
 
 impl TransactionProcessor {
@@ -49,27 +60,31 @@ impl TransactionProcessor {
         http: Arc<Http>,
         pool: PgPool,
         config: Settings,
+        translations: Arc<Translations>,
         maintenance: Arc<RwLock<bool>>,
         deposits_enabled: Arc<RwLock<bool>>,
+        shutdown: Arc<Shutdown>,
     ) -> Self {
         TransactionProcessor {
             http,
             pool,
             config,
+            translations,
             maintenance,
             deposits_enabled,
             queue_small_txns: Arc::new(RwLock::new(VecDeque::new())),
             queue_large_txns: Arc::new(RwLock::new(VecDeque::new())),
+            shutdown,
         }
     }
 
     pub async fn listen_wallet_notifications(&self) {
         let wallet_notify_socket_path = &self.config.application.vrsc_wallet_notify_socket_path;
-        let wallet_listener = UnixListener::bind(&wallet_notify_socket_path).unwrap_or_else(|_| {
-            std::fs::remove_file(&wallet_notify_socket_path).unwrap();
-            let bind = UnixListener::bind(&wallet_notify_socket_path).unwrap();
+        let wallet_listener = UnixListener::bind(wallet_notify_socket_path).unwrap_or_else(|_| {
+            std::fs::remove_file(wallet_notify_socket_path).unwrap();
+            let bind = UnixListener::bind(wallet_notify_socket_path).unwrap();
 
-            std::fs::set_permissions(&wallet_notify_socket_path, Permissions::from_mode(0o777))
+            std::fs::set_permissions(wallet_notify_socket_path, Permissions::from_mode(0o777))
                 .unwrap();
 
             bind
@@ -83,9 +98,12 @@ impl TransactionProcessor {
                     let parsed_str = parse_bytes(&stream).await.expect("valid string");
                     let txid = Txid::from_str(&parsed_str).expect("valid txid");
 
-                    // at this point, the bot could be in maintenance mode, so we should check for that.
-                    // if it is in maintenance mode, we should store all the transactions in a database for later check
-                    if *self.maintenance.read().await || !*self.deposits_enabled.read().await {
+                    // at this point, the bot could be in maintenance mode or shutting down, so we should check for that.
+                    // if it is, we should store all the transactions in a database for later check
+                    if *self.maintenance.read().await
+                        || !*self.deposits_enabled.read().await
+                        || self.shutdown.is_stopping()
+                    {
                         trace!("store {txid} in unprocessed_transactions");
                         if let Err(e) =
                             database::store_unprocessed_transaction(&self.pool, &txid).await
@@ -93,7 +111,7 @@ impl TransactionProcessor {
                             error!("Something went wrong while storing an unprocessed transaction: {:?}", e);
                         }
 
-                        return;
+                        continue;
                     }
 
                     if let Err(e) = self.check_tx(txid).await {
@@ -111,33 +129,40 @@ impl TransactionProcessor {
 
     pub async fn listen_block_notifications(&self) {
         let block_notify_socket_path = &self.config.application.vrsc_block_notify_socket_path;
-        let block_listener = UnixListener::bind(&block_notify_socket_path).unwrap_or_else(|_| {
-            std::fs::remove_file(&block_notify_socket_path).unwrap();
-            let bind = UnixListener::bind(&block_notify_socket_path).unwrap();
+        let block_listener = UnixListener::bind(block_notify_socket_path).unwrap_or_else(|_| {
+            std::fs::remove_file(block_notify_socket_path).unwrap();
+            let bind = UnixListener::bind(block_notify_socket_path).unwrap();
 
-            std::fs::set_permissions(&block_notify_socket_path, Permissions::from_mode(0o777))
+            std::fs::set_permissions(block_notify_socket_path, Permissions::from_mode(0o777))
                 .unwrap();
 
             bind
         });
 
-        let deposits_enabled = self.deposits_enabled.read().await.clone();
+        let deposits_enabled = *self.deposits_enabled.read().await;
+        // held until the queues are stored, so a shutdown waits for deposits that are being processed
+        let Some(in_flight) = self.shutdown.begin() else {
+            return;
+        };
 
         info!("blocknotify listening");
         loop {
-            match block_listener.accept().await {
-                Ok((_stream, _address)) => loop {
-                    if deposits_enabled == false {
+            let accepted = tokio::select! {
+                accepted = block_listener.accept() => accepted,
+                _ = self.shutdown.stopped() => break,
+            };
+
+            match accepted {
+                Ok((_stream, _address)) => {
+                    if !deposits_enabled {
                         // deposits are disabled, let's return
                         info!("deposits are disabled");
-                        break;
+                        continue;
                     }
 
                     self.process_short_queue().await.unwrap();
                     self.process_long_queue().await.unwrap();
-
-                    break;
-                },
+                }
                 Err(e) => {
                     error!("connection to socket listener failed: {}", e);
 
@@ -145,6 +170,45 @@ impl TransactionProcessor {
                 }
             }
         }
+
+        self.checkpoint().await;
+        drop(in_flight);
+        info!("blocknotify stopped");
+    }
+
+    /// Stores the transactions that are waiting for confirmations in the database, so they are processed after a
+    /// restart instead of being lost with the queues.
+    pub async fn checkpoint(&self) {
+        for queue in [&self.queue_small_txns, &self.queue_large_txns] {
+            let mut queue = queue.write().await;
+
+            for (txid, _) in queue.drain(..) {
+                trace!("store {txid} in unprocessed_transactions");
+                if let Err(e) = database::store_unprocessed_transaction(&self.pool, &txid).await {
+                    error!("could not store queued transaction {txid}: {e:?}");
+                }
+            }
+        }
+    }
+
+    /// Checks the transactions that were stored during maintenance or a shutdown and processes the ones that have
+    /// enough confirmations. The others are queued again.
+    pub async fn process_stored_transactions(&self) -> Result<(), Error> {
+        let stored_txids = database::get_stored_txids(&self.pool).await?;
+
+        for txid in stored_txids {
+            trace!("processing {txid}");
+            // checks tx and puts them in a queue
+            self.check_tx(txid).await?;
+
+            // process the queue immediately
+            self.process_long_queue().await?;
+            self.process_short_queue().await?;
+
+            database::set_stored_txid_to_processed(&self.pool, &txid).await?;
+        }
+
+        Ok(())
     }
 
     pub async fn check_tx(&self, txid: Txid) -> Result<(), Error> {
@@ -158,7 +222,9 @@ impl TransactionProcessor {
         )?;
 
         trace!("getting raw_transaction {txid}");
-        let raw_tx = client.get_raw_transaction_verbose(&txid)?;
+        let raw_tx = metrics::observe_rpc("getrawtransaction", || {
+            client.get_raw_transaction_verbose(&txid)
+        })?;
 
         for vout in raw_tx.vout.iter() {
             if let Some(addresses) = &vout.script_pubkey.addresses {
@@ -174,10 +240,10 @@ impl TransactionProcessor {
                             .gt(&self.config.application.min_deposit_threshold)
                         {
                             trace!("{txid} put in long queue");
-                            long_write.push_back((txid.clone(), vout.value))
+                            long_write.push_back((txid, vout.value))
                         } else {
                             trace!("{txid} put in short queue");
-                            write.push_back((txid.clone(), vout.value))
+                            write.push_back((txid, vout.value))
                         }
                     }
                 }
@@ -217,7 +283,9 @@ impl TransactionProcessor {
                 )
                 .unwrap();
 
-                let raw_tx = client.get_raw_transaction_verbose(&front.0)?;
+                let raw_tx = metrics::observe_rpc("getrawtransaction", || {
+                    client.get_raw_transaction_verbose(&front.0)
+                })?;
 
                 if let Some(confs) = raw_tx.confirmations {
                     let min_confs = self.config.application.min_deposit_confirmations_small;
@@ -227,7 +295,15 @@ impl TransactionProcessor {
                         break;
                     } else {
                         trace!("tx has at least {} confs: {}", min_confs, front.0);
-                        if let Err(e) = process_txid(Arc::clone(&http), &pool, &raw_tx).await {
+                        if let Err(e) = process_txid(
+                            Arc::clone(&http),
+                            &pool,
+                            &client,
+                            &self.translations,
+                            &raw_tx,
+                        )
+                        .await
+                        {
                             error!(
                                 "something went wrong while handling a new wallet tx: {:?}\n{:?}",
                                 e, &front
@@ -272,7 +348,9 @@ impl TransactionProcessor {
                 )
                 .unwrap();
 
-                let raw_tx = client.get_raw_transaction_verbose(&front.0)?;
+                let raw_tx = metrics::observe_rpc("getrawtransaction", || {
+                    client.get_raw_transaction_verbose(&front.0)
+                })?;
 
                 if let Some(confs) = raw_tx.confirmations {
                     let min_confs = self.config.application.min_deposit_confirmations_large;
@@ -282,7 +360,15 @@ impl TransactionProcessor {
                         break;
                     } else {
                         trace!("tx has at least {} confs: {}", min_confs, front.0);
-                        if let Err(e) = process_txid(Arc::clone(&http), &pool, &raw_tx).await {
+                        if let Err(e) = process_txid(
+                            Arc::clone(&http),
+                            &pool,
+                            &client,
+                            &self.translations,
+                            &raw_tx,
+                        )
+                        .await
+                        {
                             error!(
                                 "something went wrong while handling a new wallet tx: {:?}\n{:?}",
                                 e, &front
@@ -314,25 +400,38 @@ impl TransactionProcessor {
 pub async fn process_txid(
     http: Arc<Http>,
     pool: &PgPool,
+    client: &Client,
+    translations: &Translations,
     raw_tx: &GetRawTransactionResultVerbose,
     // _config: ?Settings,
 ) -> Result<(), Error> {
-    if !transaction_processed(&pool, &raw_tx.txid).await? {
+    if !transaction_processed(pool, &raw_tx.txid).await? {
         for vout in raw_tx.vout.iter() {
             if let Some(addresses) = &vout.script_pubkey.addresses {
                 for address in addresses {
-                    if let Some(user_id) = get_user_from_address(&pool, address).await? {
+                    if let Some(user_id) = get_user_from_address(pool, address).await? {
                         let uuid = Uuid::new_v4();
-                        if let Err(e) = increase_balance(&pool, &user_id, vout.value_sat).await {
+                        if let Err(e) =
+                            increase_balance(pool, &user_id, vout.value_sat, &uuid, &raw_tx.txid)
+                                .await
+                        {
                             error!("something went wrong while increasing a user's balance\nuser: {user_id} txid: {} vout: {} \nerror: {:?}", &raw_tx.txid, vout.n, e)
                         } else {
+                            metrics::record_deposit(vout.value_sat);
+                            let deposit = Deposit {
+                                amount: vout.value,
+                                txid: raw_tx.txid,
+                                sender: sender_of(client, raw_tx),
+                                confirmations: raw_tx.confirmations,
+                            };
+                            // the deposit is credited, so a DM that can't be sent doesn't stop the other outputs
                             if let Err(e) =
-                                store_deposit_transaction(&pool, &uuid, &user_id, &raw_tx.txid)
-                                    .await
+                                send_deposit_dm(&http, pool, translations, user_id, &deposit).await
                             {
-                                error!("something went wrong while storing a transaction to the database: {:?}", e)
-                            } else {
-                                send_deposit_dm(http.clone(), user_id, vout.value).await?;
+                                error!(
+                                    "could not notify {user_id} of deposit {}: {e:?}",
+                                    raw_tx.txid
+                                )
                             }
                         }
                     }
@@ -355,19 +454,110 @@ async fn parse_bytes(stream: &UnixStream) -> Result<String, Report> {
     match stream.try_read(&mut data) {
         Ok(_) => {
             let tx_hash = String::from_utf8(data)?;
-            return Ok(tx_hash);
+            Ok(tx_hash)
         }
         Err(e) => {
-            return Err(e.into());
+            Err(e.into())
         }
     }
 }
 
-async fn send_deposit_dm(http: Arc<Http>, user_id: UserId, amount: Amount) -> Result<(), Error> {
-    let user = http.get_user(user_id.0).await?;
-    user.direct_message(http, |message| {
-        message.content(format!("Your deposit of {} has been processed.", amount))
+/// A deposit that was just credited to a user.
+struct Deposit {
+    amount: Amount,
+    txid: Txid,
+    sender: Option<String>,
+    /// The confirmations of the transaction at the time it was credited
+    confirmations: Option<u32>,
+}
+
+// the sender of a transaction is the address (or identity) that owned the output spent by the first input.
+fn sender_of(client: &Client, raw_tx: &GetRawTransactionResultVerbose) -> Option<String> {
+    let vin = raw_tx.vin.first()?;
+    let (txid, n) = (vin.txid?, vin.vout?);
+
+    let previous_tx = metrics::observe_rpc("getrawtransaction", || {
+        client.get_raw_transaction_verbose(&txid)
     })
+    .ok()?;
+
+    previous_tx
+        .vout
+        .into_iter()
+        .find(|vout| vout.n == n)?
+        .script_pubkey
+        .addresses?
+        .into_iter()
+        .next()
+        .map(|address| address.to_string())
+}
+
+// deposits are only ever announced in a DM, so users that turned off DMs don't get a notification. The DM goes
+// through the outbox, so it is retried when Discord doesn't take it.
+async fn send_deposit_dm(
+    http: &Http,
+    pool: &PgPool,
+    translations: &Translations,
+    user_id: UserId,
+    deposit: &Deposit,
+) -> Result<(), Error> {
+    if let Some((_, Notification::ChannelOnly | Notification::Off)) =
+        get_notification_settings(pool, &[user_id])
+            .await?
+            .first()
+    {
+        trace!("{user_id} does not want to be notified of deposits");
+        return Ok(());
+    }
+
+    let locale = get_dm_language(pool, &user_id).await?;
+    let locale = locale.as_deref();
+    let new_balance = get_balance_for_user(pool, &user_id).await?;
+
+    let mut lines = vec![
+        format!("**{}**", tr_locale!(translations, locale, "deposit-title")),
+        tr_locale!(
+            translations,
+            locale,
+            "deposit-processed",
+            amount: format::amount(deposit.amount)
+        ),
+        format!(
+            "{}: {}",
+            tr_locale!(translations, locale, "deposit-sender"),
+            deposit.sender.clone().unwrap_or_else(|| tr_locale!(
+                translations,
+                locale,
+                "deposit-unknown-sender"
+            ))
+        ),
+        format!(
+            "{}: {}",
+            tr_locale!(translations, locale, "deposit-confirmations"),
+            deposit.confirmations.unwrap_or_default()
+        ),
+        format!(
+            "{}: [link](https://insight.verus.io/tx/{})",
+            tr_locale!(translations, locale, "withdraw-explorer"),
+            deposit.txid
+        ),
+    ];
+    if let Some(new_balance) = new_balance {
+        lines.push(format!(
+            "{}: {}",
+            tr_locale!(translations, locale, "withdraw-new-balance"),
+            format::amount(Amount::from_sat(new_balance))
+        ));
+    }
+
+    delivery::deliver(
+        http,
+        pool,
+        translations,
+        Destination::Dm(user_id),
+        &lines.join("\n"),
+        None,
+    )
     .await?;
 
     Ok(())
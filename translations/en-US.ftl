@@ -0,0 +1,349 @@
+# General
+error-guild-only = You need to be in a Discord server to use this command.
+user-suspended = You have been temporarily suspended
+
+# Language
+language-set = You successfully set your language to: { $language }
+language-unknown = `{ $language }` is not a supported language. Available languages: { $available }
+language-dm-set = You will receive DMs in: { $language }
+settings-tippable-on = You will receive role tips and soaks again.
+settings-tippable-off = You will no longer receive role tips and soaks.
+settings-low-balance-on = You will get a DM when your balance drops below { $threshold }.
+settings-low-balance-off = You will no longer get a DM when your balance is low.
+settings-private-on = The amounts of tips to you are no longer shown in channels, and others can't look up your balance.
+settings-private-off = The amounts of tips to you are shown in channels again, and others can look up your balance.
+settings-output-compact = Informational commands now only show the essentials.
+settings-output-detailed = Informational commands now show all details.
+low-balance-reminder = Your balance dropped to { $balance }, below the { $threshold } you asked to be reminded of. Top up by depositing to the address below.
+low-balance-reminder-no-address = Your balance dropped to { $balance }, below the { $threshold } you asked to be reminded of. Use `/deposit` to get an address to top up.
+
+# Tipping
+tip-insufficient-balance = Your balance is insufficient to tip that amount!
+tip-user-announcement = { $tipper } just tipped { $recipient } { $amount }!
+tip-user-announcement-private = { $tipper } just tipped { $recipient }!
+tip-received-dm = You just got tipped { $amount } from { $tipper }!
+tip-multiple-announcement = { $tipper } just tipped { $amount } to { $count } users ({ $share } each)!
+tip-multiple-announcement-private = { $tipper } just tipped { $count } users!
+tip-dust-refunded = { $dust } could not be divided evenly and was returned to { $tipper }.
+tip-dm-failed = Couldn't DM { $count } user(s), they can find the tip in their balance.
+tip-confirm-group = You are about to tip { $amount } to { $count } users, which is { $share } each. Are you sure?
+tip-note = Note: { $note }
+tip-receipt-title = Tip receipt
+tip-receipt-recipient = Recipient
+tip-receipt-amount = Amount
+tip-receipt-balance = Your new balance
+tip-receipt-id = Tip id
+tip-batch-title = Tips
+tip-batch-summary = { $count } more tips totaling { $total } in the last { $minutes } minute(s)
+tip-dm-batch-summary = You received { $count } more tips totaling { $total } in the last { $minutes } minute(s).
+soak-no-recipients = There is nobody online to soak.
+soak-share-too-small = Split among { $count } online members, everyone would get less than { $minimum }. Please soak a larger amount.
+soak-done = Soak sent!
+quicktip-button = Tip { $amount }
+quicktip-custom = Custom
+quicktip-modal-title = Tip the same
+quicktip-modal-amount = Amount in VRSC
+quicktip-invalid-amount = `{ $amount }` is not an amount that can be tipped.
+quicktip-self = You can not tip yourself.
+
+# Reactdrop
+reactdrop-emoji-not-in-guild = This emoji is not found in this Discord server, so it can't be used. Please pick another one
+reactdrop-emoji-invalid = This is not a valid emoji. Please pick an emoji to start a Reactdrop
+reactdrop-started =
+    >>> **A reactdrop of { $amount } was started!**
+
+    React with the { $emoji } emoji to participate
+reactdrop-failed-insufficient-funds = { $author } didn't have enough funds, reactdrop failed
+reactdrop-started-raffle = { $winners } random participant(s) will split the amount.
+reactdrop-started-role = Only members with the { $role } role can participate.
+reactdrop-winners = The winners of the reactdrop are: { $winners }
+reactdrop-not-found = This reactdrop does not exist or has already ended.
+reactdrop-not-allowed = Only the creator of a reactdrop or a server admin can end or cancel it.
+reactdrop-not-pending = This reactdrop has already ended.
+reactdrop-ended = The reactdrop has ended and is paid out.
+reactdrop-cancelled = The reactdrop is cancelled. Nothing was taken from the balance of its creator.
+reactdrop-status-ended = **This reactdrop was ended early.**
+reactdrop-status-cancelled = **This reactdrop was cancelled.**
+
+# Wallet
+balance-of-user = { $user }'s balance is: { $balance }
+balance-private = This user keeps their balance private.
+balance-history-empty = There is not enough balance history yet, check again in a few days.
+balance-history-title = Balance history
+balance-history-period = Period
+balance-history-balance = Balance
+balance-history-net-flow = Net flow
+balance-history-recent-tips = Recent tips
+balance-history-tip-sent = { $amount } to { $user } on { $date }
+balance-history-tip-received = { $amount } from { $user } on { $date }
+deposit-address = Address
+deposit-processed = Your deposit of { $amount } has been processed.
+deposit-title = Deposit
+deposit-sender = From
+deposit-unknown-sender = Unknown
+deposit-confirmations = Confirmations
+withdraw-disabled = Withdrawals are temporarily disabled.
+withdraw-invalid-destination = Error: The destination you entered cannot be used: { $destination }
+withdraw-amount-not-positive = Error: Withdrawal amount should be more than 0.0
+withdraw-title = Withdraw
+withdraw-amount = Amount
+withdraw-fees = Network fee
+withdraw-bot-fee = Bot fee
+withdraw-explorer = Explorer
+withdraw-new-balance = New balance
+withdraw-unknown-status = Something went wrong trying to process your withdrawal. Please contact support with withdrawal ID: { $withdrawal_id }
+withdraw-failed = Your withdrawal could not be sent. Nothing was taken from your balance.
+withdraw-insufficient-balance =
+    Your balance is insufficient to withdraw { $amount }.
+    Max available balance for withdraw: { $max_amount }
+withdraw-all-insufficient-balance =
+    Your balance is insufficient to withdraw everything.
+    Max available balance for withdraw: { $max_amount }
+withdraw-no-balance = Your balance is insufficient to withdraw
+claim-legacy-done = Your balance of { $amount } from { $source } has been added to your balance.
+claim-legacy-nothing = There is no balance from a previous tipbot for you to claim.
+
+# Chain
+chaininfo-title = Verus info
+chaininfo-title-testnet = Verus (testnet) info
+chaininfo-height = height
+chaininfo-difficulty = difficulty
+chaininfo-staking = amount staking
+chaininfo-block-fees = average block fees
+peerinfo-peers = Publicly available peers:```{ $peers }```
+price-title = VRSC price information
+price-usd = USD price (median)
+price-btc = BTC price
+price-from-ath = % from ATH (USD)
+price-volume = Volume 24h (USD)
+price-circulating-supply = Circulating supply (VRSC)
+price-onchain = { $basket } (on-chain)
+price-data-source = Data from { $source }
+currency-supply = Supply
+currency-baskets = Baskets
+currency-value-usd = est. currency value (USD)
+currency-tvl-usd = est. reserves value (USD)
+digest-title = Network digest
+digest-height = Height { $height } ({ $blocks } new blocks)
+digest-milestone = :tada: Block { $height } was reached!
+digest-difficulty = Difficulty: { $difficulty } ({ $change }%)
+digest-staking = Staking supply: { $supply } ({ $change }%)
+digest-large-transactions = Large transactions
+digest-no-large-transactions = No transactions of { $threshold } or more.
+identity-not-found = `{ $identity }` is not a known identity.
+identity-revoked = :warning: **This identity is revoked.** Do not send funds to it.
+identity-address = Identity address
+identity-primary-addresses = Primary addresses
+identity-minimum-signatures = Minimum signatures: { $count }
+identity-revocation = Revocation authority
+identity-recovery = Recovery authority
+identity-self = Itself
+identity-timelock = Timelock
+identity-unlocked = Not locked
+identity-locked-delay = Locked, spending is delayed by { $blocks } blocks after unlocking
+identity-locked-until = Locked until block { $height }
+identity-content-map = Content map
+identity-none = None
+currencychart-unknown = `{ $currency }` is not tracked. Tracked currencies: { $available }
+currencychart-empty = There is not enough history of { $currency } yet, check again later.
+currencychart-period = Period
+currencychart-price = Price
+calc-title = Conversion calculator
+calc-unavailable = `{ $from }` can not be converted to `{ $to }` directly.
+calc-footer = Estimate without conversion fees, updated at most every minute.
+
+# Disputes
+dispute-unknown-tip = This is not a tip you sent or received. Select one of your tips from the list.
+dispute-too-old = This tip is too old to dispute. Tips can only be disputed within { $hours } hours.
+dispute-already-open = This tip has already been disputed.
+dispute-insufficient-balance = The recipient no longer has the funds of this tip, so it can not be frozen. Please contact a moderator.
+dispute-reversed = This tip was reversed and the tipper already got it back, so it can not be disputed.
+dispute-opened = Dispute #{ $id } is opened and the tip is frozen. Follow it in { $thread }.
+dispute-thread-opened =
+    >>> **Dispute #{ $id }** opened by { $opened_by }
+    A tip of { $amount } from { $tipper } to { $recipient } is frozen until a moderator resolves this dispute.
+    Reason: { $reason }
+dispute-not-open = There is no open dispute #{ $id } in this server.
+dispute-released = Dispute #{ $id } is resolved by { $moderator }: { $amount } is released to { $recipient }.
+dispute-refunded = Dispute #{ $id } is resolved by { $moderator }: { $amount } is refunded to { $tipper }.
+dispute-none-open = There are no open disputes.
+
+# Status
+error-degraded = The bot can not reach the Verus daemon right now, so balances can not be changed. Please try again later.
+error-syncing = The Verus node is syncing ({ $progress }%), so balances can not be changed. Please try again later.
+error-shutting-down = The bot is restarting, so balances can not be changed. Please try again in a minute.
+error-text-blocked = Your message contains a word that is not allowed in this server, so nothing was sent.
+error-chain-unavailable = The Verus chain is temporarily unavailable. Please try again in a minute.
+error-already-processed = This was already done, so it was not done again. Check your balance to see the result.
+error-tipping-channel = Tipping is not allowed in this channel. You can tip in { $channels }.
+confirm-yes = Confirm
+confirm-no = Cancel
+confirm-confirmed = Confirmed.
+confirm-cancelled = Cancelled.
+status-title = Bot status
+status-daemon = Verus daemon
+status-database = Database
+status-wallet = Wallet
+status-daemon-height = Daemon height
+status-explorer-height = Explorer height
+status-degraded = Balance changes
+status-degraded-yes = :warning: Paused until the daemon is reachable again
+status-degraded-no = Available
+status-ok = Reachable
+status-unreachable = Unreachable
+status-unknown = Unknown
+status-blocks = { $blocks } blocks ({ $headers } headers)
+status-wallet-unencrypted = Not encrypted
+status-wallet-locked = Locked
+status-wallet-unlocked = Unlocked
+
+# Treasury
+treasury-donated = { $donor } donated { $amount } to the treasury of this server!
+treasury-invalid-month = `{ $month }` is not a valid month. Use the format YYYY-MM, e.g. 2023-09.
+treasury-report =
+    **Treasury statement for { $month }**
+    { $entries } movement(s): { $inflows } in, { $outflows } out.
+    Current treasury balance: { $balance }
+
+# Config
+config-announcements-set = Announcements will be posted in { $channel }.
+config-announcements-off = Announcements will no longer be posted in this server.
+config-fallback-set = Tip announcements that can not be posted in their channel will be posted in { $channel }.
+config-fallback-off = Tip announcements that can not be posted in their channel will be sent to the tipper in DM.
+config-batching-set = Tip announcements will be combined into a summary when a channel gets more than { $threshold } within { $window } seconds, or has slow mode.
+config-batching-off = Tip announcements will never be combined.
+config-digest-set = The network digest will be posted in { $channel } every hour, listing transactions of at least { $threshold }.
+config-digest-off = The network digest will no longer be posted in this server.
+config-withdrawal-fee-set = Withdrawals from this server are charged a bot fee of { $fee } on top of the network fee.
+config-withdrawal-fee-default = Withdrawals from this server are charged the default bot fee of { $fee } on top of the network fee.
+config-tip-buttons-on = Tip announcements will have buttons to tip the same recipients.
+config-tip-buttons-off = Tip announcements will no longer have tip buttons.
+config-filter-none = This server has not blocked any words itself.
+config-filter-default-on = The default list of blocked words of the bot applies in this server.
+config-filter-default-off = The default list of blocked words of the bot no longer applies in this server.
+config-filter-added = { $word } is now blocked in notes.
+config-filter-removed = { $word } is no longer blocked in notes.
+config-filter-invalid = A blocked word must be a single word of at most { $max } characters.
+config-emoji-invalid = `{ $emoji }` is not a standard emoji or an emoji of this server.
+config-emoji-amount-set = { $emoji } now stands for { $amount }.
+config-emoji-amount-removed = { $emoji } no longer stands for an amount.
+config-emoji-amounts-none = This server has no emoji amounts yet.
+config-emoji-amounts-full = This server already has { $max } emoji amounts. Remove one before adding another.
+config-daily-on = Members can now claim { $reward } a day with `/daily`, more on a streak. The pool has { $pool } left, add to it with `/dailyfund`.
+config-daily-off = Daily rewards are now off in this server.
+config-mod-log-set = Withdrawals, blacklist changes, balance problems and tips of { $threshold } or more will be posted in { $channel }.
+config-mod-log-off = The mod-log is now off in this server.
+config-news-set = Verus news about { $topics } will be posted in { $channel }.
+config-news-off = Verus news will no longer be posted in this server.
+config-news-unknown-topic = `{ $topic }` is not a news topic. Available topics: { $available }
+
+# Referrals
+referral-link = Your referral code is **{ $code }**. New users can claim it with `/referral claim`.
+referral-rewards = When they send their first tip, you get { $referrer_reward } and they get { $referee_reward }.
+referral-counts = You referred { $referred } user(s), { $rewarded } of them sent their first tip.
+referral-claimed = You were referred by { $referrer }. You both get a reward when you send your first tip.
+referral-unknown-code = There is no referral code { $code }.
+referral-own-code = You can't claim your own referral code.
+referral-already-referred = You already claimed a referral code.
+referral-mutual = You can't claim the code of a user you referred yourself.
+referral-not-new = Referral codes are for new users, and you already sent a tip.
+referral-rewarded-referrer = { $user } sent their first tip, so you got a referral reward of { $amount }!
+referral-rewarded-referee = You sent your first tip, so you got a referral reward of { $amount }! { $user } got one too.
+
+# Trivia
+trivia-no-questions = This server has no trivia questions yet. Add them with `/trivia add`.
+trivia-already-running = A game of trivia is already going on in this channel.
+trivia-started = { $starter } started a game of trivia with { $rounds } question(s)! The first correct answer to every question wins { $prize }.
+trivia-question = **Question { $round }/{ $rounds }**: { $question } ({ $seconds } seconds)
+trivia-no-answer = Time's up! The answer was **{ $answer }**.
+trivia-correct = { $winner } got it, the answer was **{ $answer }**!
+trivia-prize-failed = The prize could not be paid from the balance of { $starter }, so the game is over.
+trivia-over = The game is over! Winners: { $winners }
+trivia-over-no-winners = The game is over, and nobody won a prize.
+trivia-invalid-question = A question needs a text of at most 300 characters, and answers of at most 100 characters with at least one letter or digit.
+trivia-question-added = Added trivia question { $id }.
+trivia-question-removed = Removed trivia question { $id }.
+trivia-question-not-found = This server has no trivia question { $id }.
+
+# Daily rewards
+daily-claimed = { $user } claimed { $amount } on day { $streak } of their streak!
+daily-off = This server has no daily rewards.
+daily-cooldown = You already claimed your daily reward. You can claim again { $time }.
+daily-pool-empty = The daily pool of this server is empty. Anyone can add to it with `/dailyfund`.
+daily-funded = { $user } added { $amount } to the daily pool of this server!
+daily-no-streaks = Nobody claimed a daily reward yet.
+daily-leaderboard-title = Daily streaks
+daily-leaderboard-line = { $rank }. { $user }: { $streak } day(s), best { $best }
+daily-reward = Daily reward
+daily-reward-off = Off
+daily-pool = Pool
+
+# Competitions
+competition-started = A tipping competition started! Whoever has the most { $metric } { $ends } wins { $prize } from the treasury. Tips below { $min_tip } per recipient, tips to yourself and reversed tips don't count. See the scores with `/competition scoreboard`.
+competition-treasury-short = The treasury only has { $treasury }, which is not enough for this prize.
+competition-already-running = This server already has a running competition.
+competition-none = This server has no running competition.
+competition-cancelled = The competition was cancelled, nobody wins the prize.
+competition-no-scores = Nobody scored yet.
+competition-scoreboard-title = Competition scoreboard
+competition-metric = Competing on
+competition-metric-unique-recipients = members tipped
+competition-metric-amount = VRSC tipped
+competition-prize = Prize
+competition-min-tip = Minimum tip
+competition-ends = Ends
+competition-won = The tipping competition is over! { $winner } won { $prize } with a score of { $score }.
+competition-won-unpaid = The tipping competition is over! { $winner } won with a score of { $score }, but the treasury no longer has { $prize } to pay the prize.
+competition-no-winner = The tipping competition is over, and nobody scored.
+
+# Pots
+pot-created = { $user } opened the pot **{ $name }** to raise { $goal }! Contribute with `/pot contribute { $name }`.
+pot-title = Pot: { $name }
+pot-raised = Raised
+pot-goal = Goal
+pot-recipient = Paid to
+pot-status-closed = This pot is closed.
+pot-status-cancelled = This pot was cancelled, the contributions were refunded.
+pot-two-recipients = Choose either a member or an address to pay the pot to, not both.
+pot-name-taken = This server already has an open pot named **{ $name }**.
+pot-not-found = This server has no open pot named **{ $name }**.
+pot-close-not-allowed = Only the creator of a pot can close it.
+pot-cancel-not-allowed = Only the creator of a pot and server admins can cancel it.
+pot-contributed = { $user } contributed { $amount } to **{ $name }**, which raised { $raised } of { $goal }!
+pot-confirm-close = Close **{ $name }** and pay the { $raised } it raised to { $recipient }?
+pot-confirm-close-fees = { $amount } is sent after a network fee of { $tx_fee } and a bot fee of { $bot_fee }.
+pot-confirm-cancel = Cancel **{ $name }** and refund the { $raised } it raised to its contributors?
+pot-paid = The pot **{ $name }** is closed, { $recipient } received { $amount }!
+pot-withdrawn = The pot **{ $name }** is closed, { $amount } was sent to `{ $address }`!
+pot-withdraw-failed = The pot **{ $name }** is closed, but { $amount } could not be sent to `{ $address }`. { $user } can cancel the pot to refund its contributors.
+pot-withdraw-unknown = The pot **{ $name }** is closed, but it is not known yet whether it was sent. An operator will check it.
+pot-cancelled = The pot **{ $name }** was cancelled, { $amount } was refunded to { $contributors } contributor(s).
+
+# Mod log
+mod-log-withdrawal = Withdrawal
+mod-log-sweep = Hot wallet swept
+mod-log-tip = Large tip
+mod-log-blacklisted = User blacklisted
+mod-log-unblacklisted = User removed from the blacklist
+mod-log-mismatch = Balance does not match the ledger
+mod-log-user = User
+mod-log-actor = By
+mod-log-amount = Amount
+mod-log-fees = Fees
+mod-log-transaction = Transaction
+mod-log-destination = Destination
+mod-log-operation = Operation
+mod-log-recipients = Recipients
+mod-log-tip-id = Tip id
+mod-log-balance = Balance
+mod-log-ledger = Ledger
+
+# Help
+help-title = Help: { $category }
+help-footer = Page { $page } of { $pages }. Type `/help <command>` for more info on a command.
+help-previous = Previous
+help-next = Next
+help-example = Example
+help-required = required
+help-optional = optional
+help-unknown-command = There is no command `{ $command }`.
@@ -0,0 +1,349 @@
+# General
+error-guild-only = Je moet in een Discord server zijn om dit commando te gebruiken.
+user-suspended = Je bent tijdelijk geschorst
+
+# Language
+language-set = Je taal is ingesteld op: { $language }
+language-unknown = `{ $language }` is geen ondersteunde taal. Beschikbare talen: { $available }
+language-dm-set = Je ontvangt DM's in: { $language }
+settings-tippable-on = Je ontvangt weer tips van rollen en soaks.
+settings-tippable-off = Je ontvangt geen tips van rollen en soaks meer.
+settings-low-balance-on = Je krijgt een DM als je saldo onder { $threshold } zakt.
+settings-low-balance-off = Je krijgt geen DM meer als je saldo laag is.
+settings-private-on = De bedragen van tips aan jou worden niet meer getoond in kanalen, en anderen kunnen je saldo niet meer opvragen.
+settings-private-off = De bedragen van tips aan jou worden weer getoond in kanalen, en anderen kunnen je saldo weer opvragen.
+settings-output-compact = Informatieve commando's tonen nu alleen het belangrijkste.
+settings-output-detailed = Informatieve commando's tonen nu alle details.
+low-balance-reminder = Je saldo is gezakt naar { $balance }, onder de { $threshold } waarvoor je een herinnering wilde. Vul het aan door op het adres hieronder te storten.
+low-balance-reminder-no-address = Je saldo is gezakt naar { $balance }, onder de { $threshold } waarvoor je een herinnering wilde. Gebruik `/deposit` voor een adres om het aan te vullen.
+
+# Tipping
+tip-insufficient-balance = Je saldo is onvoldoende om dit bedrag te tippen!
+tip-user-announcement = { $tipper } heeft { $recipient } zojuist { $amount } getipt!
+tip-user-announcement-private = { $tipper } heeft { $recipient } zojuist een tip gegeven!
+tip-received-dm = Je hebt zojuist een tip van { $amount } ontvangen van { $tipper }!
+tip-multiple-announcement = { $tipper } heeft zojuist { $amount } getipt aan { $count } gebruikers ({ $share } per persoon)!
+tip-multiple-announcement-private = { $tipper } heeft zojuist { $count } gebruikers een tip gegeven!
+tip-dust-refunded = { $dust } kon niet eerlijk verdeeld worden en is teruggegeven aan { $tipper }.
+tip-dm-failed = { $count } gebruiker(s) konden geen DM krijgen, ze vinden de tip in hun saldo.
+tip-confirm-group = Je staat op het punt om { $amount } te tippen aan { $count } gebruikers, dat is { $share } per persoon. Weet je het zeker?
+tip-note = Bericht: { $note }
+tip-receipt-title = Tipbewijs
+tip-receipt-recipient = Ontvanger
+tip-receipt-amount = Bedrag
+tip-receipt-balance = Je nieuwe saldo
+tip-receipt-id = Tip-id
+tip-batch-title = Tips
+tip-batch-summary = Nog { $count } tips, samen { $total }, in de afgelopen { $minutes } minuut/minuten
+tip-dm-batch-summary = Je hebt in de afgelopen { $minutes } minuut/minuten nog { $count } tips ontvangen, samen { $total }.
+soak-no-recipients = Er is niemand online om te soaken.
+soak-share-too-small = Verdeeld over { $count } online leden zou iedereen minder dan { $minimum } krijgen. Soak een groter bedrag.
+soak-done = Soak verstuurd!
+quicktip-button = Tip { $amount }
+quicktip-custom = Ander bedrag
+quicktip-modal-title = Tip hetzelfde
+quicktip-modal-amount = Bedrag in VRSC
+quicktip-invalid-amount = `{ $amount }` is geen bedrag dat getipt kan worden.
+quicktip-self = Je kunt jezelf niet tippen.
+
+# Reactdrop
+reactdrop-emoji-not-in-guild = Deze emoji bestaat niet in deze Discord server en kan dus niet gebruikt worden. Kies een andere
+reactdrop-emoji-invalid = Dit is geen geldige emoji. Kies een emoji om een Reactdrop te starten
+reactdrop-started =
+    >>> **Er is een reactdrop van { $amount } gestart!**
+
+    Reageer met de { $emoji } emoji om mee te doen
+reactdrop-failed-insufficient-funds = { $author } had onvoldoende saldo, de reactdrop is mislukt
+reactdrop-started-raffle = { $winners } willekeurige deelnemer(s) verdelen het bedrag.
+reactdrop-started-role = Alleen leden met de rol { $role } kunnen meedoen.
+reactdrop-winners = De winnaars van de reactdrop zijn: { $winners }
+reactdrop-not-found = Deze reactdrop bestaat niet of is al afgelopen.
+reactdrop-not-allowed = Alleen de maker van een reactdrop of een serverbeheerder kan deze beëindigen of annuleren.
+reactdrop-not-pending = Deze reactdrop is al afgelopen.
+reactdrop-ended = De reactdrop is beëindigd en uitbetaald.
+reactdrop-cancelled = De reactdrop is geannuleerd. Er is niets van het saldo van de maker afgehaald.
+reactdrop-status-ended = **Deze reactdrop is vroegtijdig beëindigd.**
+reactdrop-status-cancelled = **Deze reactdrop is geannuleerd.**
+
+# Wallet
+balance-of-user = Het saldo van { $user } is: { $balance }
+balance-private = Deze gebruiker houdt het saldo privé.
+balance-history-empty = Er is nog niet genoeg saldogeschiedenis, kijk over een paar dagen nog eens.
+balance-history-title = Saldogeschiedenis
+balance-history-period = Periode
+balance-history-balance = Saldo
+balance-history-net-flow = Netto verandering
+balance-history-recent-tips = Recente tips
+balance-history-tip-sent = { $amount } aan { $user } op { $date }
+balance-history-tip-received = { $amount } van { $user } op { $date }
+deposit-address = Adres
+deposit-processed = Je storting van { $amount } is verwerkt.
+deposit-title = Storting
+deposit-sender = Van
+deposit-unknown-sender = Onbekend
+deposit-confirmations = Bevestigingen
+withdraw-disabled = Opnames zijn tijdelijk uitgeschakeld.
+withdraw-invalid-destination = Fout: De bestemming die je hebt opgegeven kan niet gebruikt worden: { $destination }
+withdraw-amount-not-positive = Fout: Het op te nemen bedrag moet meer dan 0.0 zijn
+withdraw-title = Opname
+withdraw-amount = Bedrag
+withdraw-fees = Netwerkkosten
+withdraw-bot-fee = Botkosten
+withdraw-explorer = Explorer
+withdraw-new-balance = Nieuw saldo
+withdraw-unknown-status = Er ging iets mis bij het verwerken van je opname. Neem contact op met support met opname-ID: { $withdrawal_id }
+withdraw-failed = Je opname kon niet worden verstuurd. Er is niets van je saldo afgehaald.
+withdraw-insufficient-balance =
+    Je saldo is onvoldoende om { $amount } op te nemen.
+    Maximaal beschikbaar saldo voor opname: { $max_amount }
+withdraw-all-insufficient-balance =
+    Je saldo is onvoldoende om alles op te nemen.
+    Maximaal beschikbaar saldo voor opname: { $max_amount }
+withdraw-no-balance = Je saldo is onvoldoende om op te nemen
+claim-legacy-done = Je saldo van { $amount } bij { $source } is aan je saldo toegevoegd.
+claim-legacy-nothing = Er is geen saldo van een vorige tipbot dat je kunt claimen.
+
+# Chain
+chaininfo-title = Verus info
+chaininfo-title-testnet = Verus (testnet) info
+chaininfo-height = hoogte
+chaininfo-difficulty = moeilijkheid
+chaininfo-staking = hoeveelheid aan het staken
+chaininfo-block-fees = gemiddelde blokkosten
+peerinfo-peers = Publiek beschikbare peers:```{ $peers }```
+price-title = VRSC prijsinformatie
+price-usd = USD prijs (mediaan)
+price-btc = BTC prijs
+price-from-ath = % van ATH (USD)
+price-volume = Volume 24u (USD)
+price-circulating-supply = Circulerende voorraad (VRSC)
+price-onchain = { $basket } (on-chain)
+price-data-source = Data van { $source }
+currency-supply = Voorraad
+currency-baskets = Baskets
+currency-value-usd = geschatte waarde valuta (USD)
+currency-tvl-usd = geschatte waarde reserves (USD)
+digest-title = Netwerkoverzicht
+digest-height = Hoogte { $height } ({ $blocks } nieuwe blokken)
+digest-milestone = :tada: Blok { $height } is bereikt!
+digest-difficulty = Moeilijkheid: { $difficulty } ({ $change }%)
+digest-staking = Staking supply: { $supply } ({ $change }%)
+digest-large-transactions = Grote transacties
+digest-no-large-transactions = Geen transacties van { $threshold } of meer.
+identity-not-found = `{ $identity }` is geen bekende identiteit.
+identity-revoked = :warning: **Deze identiteit is ingetrokken.** Stuur er geen geld naartoe.
+identity-address = Identiteitsadres
+identity-primary-addresses = Primaire adressen
+identity-minimum-signatures = Minimaal aantal handtekeningen: { $count }
+identity-revocation = Intrekkingsautoriteit
+identity-recovery = Herstelautoriteit
+identity-self = Zichzelf
+identity-timelock = Tijdslot
+identity-unlocked = Niet vergrendeld
+identity-locked-delay = Vergrendeld, uitgeven wordt na ontgrendelen { $blocks } blokken vertraagd
+identity-locked-until = Vergrendeld tot blok { $height }
+identity-content-map = Content map
+identity-none = Geen
+currencychart-unknown = `{ $currency }` wordt niet bijgehouden. Bijgehouden valuta: { $available }
+currencychart-empty = Er is nog niet genoeg geschiedenis van { $currency }, kijk later nog eens.
+currencychart-period = Periode
+currencychart-price = Prijs
+calc-title = Conversiecalculator
+calc-unavailable = `{ $from }` kan niet direct worden omgezet naar `{ $to }`.
+calc-footer = Schatting zonder conversiekosten, maximaal elke minuut bijgewerkt.
+
+# Disputes
+dispute-unknown-tip = Dit is geen tip die je verstuurd of ontvangen hebt. Kies een van je tips uit de lijst.
+dispute-too-old = Deze tip is te oud voor een geschil. Een geschil kan alleen binnen { $hours } uur geopend worden.
+dispute-already-open = Voor deze tip is al een geschil geopend.
+dispute-insufficient-balance = De ontvanger heeft het bedrag van deze tip niet meer, dus het kan niet bevroren worden. Neem contact op met een moderator.
+dispute-reversed = Deze tip is teruggedraaid en de tipper heeft hem al terug, dus hij kan niet betwist worden.
+dispute-opened = Geschil #{ $id } is geopend en de tip is bevroren. Volg het in { $thread }.
+dispute-thread-opened =
+    >>> **Geschil #{ $id }** geopend door { $opened_by }
+    Een tip van { $amount } van { $tipper } aan { $recipient } is bevroren totdat een moderator dit geschil oplost.
+    Reden: { $reason }
+dispute-not-open = Er is geen open geschil #{ $id } in deze server.
+dispute-released = Geschil #{ $id } is opgelost door { $moderator }: { $amount } gaat naar { $recipient }.
+dispute-refunded = Geschil #{ $id } is opgelost door { $moderator }: { $amount } is teruggestort aan { $tipper }.
+dispute-none-open = Er zijn geen open geschillen.
+
+# Status
+error-degraded = De bot kan de Verus daemon op dit moment niet bereiken, dus saldo's kunnen niet gewijzigd worden. Probeer het later opnieuw.
+error-syncing = De Verus node is aan het synchroniseren ({ $progress }%), dus saldo's kunnen niet gewijzigd worden. Probeer het later opnieuw.
+error-shutting-down = De bot wordt herstart, dus saldo's kunnen niet gewijzigd worden. Probeer het over een minuut opnieuw.
+error-text-blocked = Je bericht bevat een woord dat niet is toegestaan in deze server, dus er is niets verstuurd.
+error-chain-unavailable = De Verus-chain is tijdelijk niet bereikbaar. Probeer het over een minuut opnieuw.
+error-already-processed = Dit is al gedaan, dus het is niet nog een keer gedaan. Bekijk je saldo voor het resultaat.
+error-tipping-channel = Tippen is niet toegestaan in dit kanaal. Je kunt tippen in { $channels }.
+confirm-yes = Bevestigen
+confirm-no = Annuleren
+confirm-confirmed = Bevestigd.
+confirm-cancelled = Geannuleerd.
+status-title = Status van de bot
+status-daemon = Verus-daemon
+status-database = Database
+status-wallet = Wallet
+status-daemon-height = Hoogte daemon
+status-explorer-height = Hoogte explorer
+status-degraded = Saldowijzigingen
+status-degraded-yes = :warning: Gepauzeerd totdat de daemon weer bereikbaar is
+status-degraded-no = Beschikbaar
+status-ok = Bereikbaar
+status-unreachable = Onbereikbaar
+status-unknown = Onbekend
+status-blocks = { $blocks } blokken ({ $headers } headers)
+status-wallet-unencrypted = Niet versleuteld
+status-wallet-locked = Vergrendeld
+status-wallet-unlocked = Ontgrendeld
+
+# Treasury
+treasury-donated = { $donor } heeft { $amount } gedoneerd aan de schatkist van deze server!
+treasury-invalid-month = `{ $month }` is geen geldige maand. Gebruik het formaat JJJJ-MM, bijvoorbeeld 2023-09.
+treasury-report =
+    **Overzicht van de schatkist voor { $month }**
+    { $entries } mutatie(s): { $inflows } in, { $outflows } uit.
+    Huidig saldo van de schatkist: { $balance }
+
+# Config
+config-announcements-set = Aankondigingen worden geplaatst in { $channel }.
+config-announcements-off = Aankondigingen worden niet meer geplaatst in deze server.
+config-fallback-set = Tip-aankondigingen die niet in hun kanaal geplaatst kunnen worden, worden geplaatst in { $channel }.
+config-fallback-off = Tip-aankondigingen die niet in hun kanaal geplaatst kunnen worden, worden als DM naar de tipper gestuurd.
+config-batching-set = Tip-aankondigingen worden samengevat wanneer een kanaal er meer dan { $threshold } binnen { $window } seconden krijgt, of slow mode heeft.
+config-batching-off = Tip-aankondigingen worden nooit samengevat.
+config-digest-set = Het netwerkoverzicht wordt elk uur in { $channel } geplaatst, met transacties van minstens { $threshold }.
+config-digest-off = Het netwerkoverzicht wordt niet langer in deze server geplaatst.
+config-withdrawal-fee-set = Voor opnames vanuit deze server rekent de bot { $fee } bovenop de netwerkkosten.
+config-withdrawal-fee-default = Voor opnames vanuit deze server rekent de bot de standaard botkosten van { $fee } bovenop de netwerkkosten.
+config-tip-buttons-on = Tipaankondigingen krijgen knoppen om dezelfde ontvangers te tippen.
+config-tip-buttons-off = Tipaankondigingen krijgen geen tipknoppen meer.
+config-filter-none = Deze server heeft zelf geen woorden geblokkeerd.
+config-filter-default-on = De standaardlijst met geblokkeerde woorden van de bot geldt in deze server.
+config-filter-default-off = De standaardlijst met geblokkeerde woorden van de bot geldt niet meer in deze server.
+config-filter-added = { $word } is nu geblokkeerd in berichten.
+config-filter-removed = { $word } is niet meer geblokkeerd in berichten.
+config-filter-invalid = Een geblokkeerd woord moet een enkel woord van maximaal { $max } tekens zijn.
+config-emoji-invalid = `{ $emoji }` is geen standaard emoji of emoji van deze server.
+config-emoji-amount-set = { $emoji } staat nu voor { $amount }.
+config-emoji-amount-removed = { $emoji } staat niet langer voor een bedrag.
+config-emoji-amounts-none = Deze server heeft nog geen emoji-bedragen.
+config-emoji-amounts-full = Deze server heeft al { $max } emoji-bedragen. Verwijder er een voordat je een nieuwe toevoegt.
+config-daily-on = Leden kunnen nu elke dag { $reward } ophalen met `/daily`, meer met een reeks. De pot heeft nog { $pool }, vul hem aan met `/dailyfund`.
+config-daily-off = Dagelijkse beloningen staan nu uit in deze server.
+config-mod-log-set = Opnames, wijzigingen van de blacklist, saldoproblemen en tips van { $threshold } of meer worden gepost in { $channel }.
+config-mod-log-off = De mod-log staat nu uit in deze server.
+config-news-set = Verus-nieuws over { $topics } wordt gepost in { $channel }.
+config-news-off = Verus-nieuws wordt niet meer gepost in deze server.
+config-news-unknown-topic = `{ $topic }` is geen nieuwsonderwerp. Beschikbare onderwerpen: { $available }
+
+# Referrals
+referral-link = Je verwijzingscode is **{ $code }**. Nieuwe gebruikers kunnen hem gebruiken met `/referral claim`.
+referral-rewards = Als ze hun eerste tip sturen, krijg jij { $referrer_reward } en krijgen zij { $referee_reward }.
+referral-counts = Je hebt { $referred } gebruiker(s) doorverwezen, { $rewarded } daarvan hebben hun eerste tip gestuurd.
+referral-claimed = Je bent doorverwezen door { $referrer }. Jullie krijgen allebei een beloning als je je eerste tip stuurt.
+referral-unknown-code = Er bestaat geen verwijzingscode { $code }.
+referral-own-code = Je kunt je eigen verwijzingscode niet gebruiken.
+referral-already-referred = Je hebt al een verwijzingscode gebruikt.
+referral-mutual = Je kunt de code van een gebruiker die je zelf hebt doorverwezen niet gebruiken.
+referral-not-new = Verwijzingscodes zijn voor nieuwe gebruikers, en je hebt al een tip gestuurd.
+referral-rewarded-referrer = { $user } heeft een eerste tip gestuurd, dus je hebt een verwijzingsbeloning van { $amount } gekregen!
+referral-rewarded-referee = Je hebt je eerste tip gestuurd, dus je hebt een verwijzingsbeloning van { $amount } gekregen! { $user } heeft er ook een gekregen.
+
+# Trivia
+trivia-no-questions = Deze server heeft nog geen triviavragen. Voeg ze toe met `/trivia add`.
+trivia-already-running = Er is al een triviaspel bezig in dit kanaal.
+trivia-started = { $starter } is een triviaspel met { $rounds } vraag/vragen begonnen! Het eerste goede antwoord op elke vraag wint { $prize }.
+trivia-question = **Vraag { $round }/{ $rounds }**: { $question } ({ $seconds } seconden)
+trivia-no-answer = De tijd is om! Het antwoord was **{ $answer }**.
+trivia-correct = { $winner } heeft het goed, het antwoord was **{ $answer }**!
+trivia-prize-failed = De prijs kon niet betaald worden van het saldo van { $starter }, dus het spel is voorbij.
+trivia-over = Het spel is voorbij! Winnaars: { $winners }
+trivia-over-no-winners = Het spel is voorbij, en niemand heeft een prijs gewonnen.
+trivia-invalid-question = Een vraag heeft een tekst van maximaal 300 tekens nodig, en antwoorden van maximaal 100 tekens met minstens één letter of cijfer.
+trivia-question-added = Triviavraag { $id } toegevoegd.
+trivia-question-removed = Triviavraag { $id } verwijderd.
+trivia-question-not-found = Deze server heeft geen triviavraag { $id }.
+
+# Daily rewards
+daily-claimed = { $user } heeft { $amount } opgehaald op dag { $streak } van een reeks!
+daily-off = Deze server heeft geen dagelijkse beloningen.
+daily-cooldown = Je hebt je dagelijkse beloning al opgehaald. Je kunt weer ophalen { $time }.
+daily-pool-empty = De dagelijkse pot van deze server is leeg. Iedereen kan hem aanvullen met `/dailyfund`.
+daily-funded = { $user } heeft { $amount } aan de dagelijkse pot van deze server toegevoegd!
+daily-no-streaks = Niemand heeft nog een dagelijkse beloning opgehaald.
+daily-leaderboard-title = Dagelijkse reeksen
+daily-leaderboard-line = { $rank }. { $user }: { $streak } dag(en), beste { $best }
+daily-reward = Dagelijkse beloning
+daily-reward-off = Uit
+daily-pool = Pot
+
+# Competitions
+competition-started = Een tipwedstrijd is begonnen! Wie { $ends } de meeste { $metric } heeft, wint { $prize } uit de schatkist. Tips onder { $min_tip } per ontvanger, tips aan jezelf en teruggedraaide tips tellen niet. Bekijk de scores met `/competition scoreboard`.
+competition-treasury-short = De schatkist heeft maar { $treasury }, wat niet genoeg is voor deze prijs.
+competition-already-running = Deze server heeft al een lopende wedstrijd.
+competition-none = Deze server heeft geen lopende wedstrijd.
+competition-cancelled = De wedstrijd is geannuleerd, niemand wint de prijs.
+competition-no-scores = Nog niemand heeft gescoord.
+competition-scoreboard-title = Scorebord van de wedstrijd
+competition-metric = Wedstrijd om
+competition-metric-unique-recipients = getipte leden
+competition-metric-amount = getipte VRSC
+competition-prize = Prijs
+competition-min-tip = Minimale tip
+competition-ends = Eindigt
+competition-won = De tipwedstrijd is voorbij! { $winner } heeft { $prize } gewonnen met een score van { $score }.
+competition-won-unpaid = De tipwedstrijd is voorbij! { $winner } heeft gewonnen met een score van { $score }, maar de schatkist heeft geen { $prize } meer om de prijs te betalen.
+competition-no-winner = De tipwedstrijd is voorbij, en niemand heeft gescoord.
+
+# Pots
+pot-created = { $user } heeft de pot **{ $name }** geopend om { $goal } op te halen! Draag bij met `/pot contribute { $name }`.
+pot-title = Pot: { $name }
+pot-raised = Opgehaald
+pot-goal = Doel
+pot-recipient = Uitbetaald aan
+pot-status-closed = Deze pot is gesloten.
+pot-status-cancelled = Deze pot is geannuleerd, de bijdragen zijn terugbetaald.
+pot-two-recipients = Kies een lid of een adres om de pot aan uit te betalen, niet allebei.
+pot-name-taken = Deze server heeft al een open pot met de naam **{ $name }**.
+pot-not-found = Deze server heeft geen open pot met de naam **{ $name }**.
+pot-close-not-allowed = Alleen de maker van een pot kan hem sluiten.
+pot-cancel-not-allowed = Alleen de maker van een pot en serverbeheerders kunnen hem annuleren.
+pot-contributed = { $user } heeft { $amount } bijgedragen aan **{ $name }**, die { $raised } van { $goal } heeft opgehaald!
+pot-confirm-close = **{ $name }** sluiten en de opgehaalde { $raised } uitbetalen aan { $recipient }?
+pot-confirm-close-fees = Er wordt { $amount } verstuurd, na { $tx_fee } netwerkkosten en { $bot_fee } botkosten.
+pot-confirm-cancel = **{ $name }** annuleren en de opgehaalde { $raised } terugbetalen aan de bijdragers?
+pot-paid = De pot **{ $name }** is gesloten, { $recipient } heeft { $amount } ontvangen!
+pot-withdrawn = De pot **{ $name }** is gesloten, { $amount } is verstuurd naar `{ $address }`!
+pot-withdraw-failed = De pot **{ $name }** is gesloten, maar { $amount } kon niet verstuurd worden naar `{ $address }`. { $user } kan de pot annuleren om de bijdragers terug te betalen.
+pot-withdraw-unknown = De pot **{ $name }** is gesloten, maar het is nog niet bekend of hij verstuurd is. Een beheerder gaat het controleren.
+pot-cancelled = De pot **{ $name }** is geannuleerd, { $amount } is terugbetaald aan { $contributors } bijdrager(s).
+
+# Mod log
+mod-log-withdrawal = Opname
+mod-log-sweep = Hot wallet leeggehaald
+mod-log-tip = Grote tip
+mod-log-blacklisted = Gebruiker op de blacklist gezet
+mod-log-unblacklisted = Gebruiker van de blacklist gehaald
+mod-log-mismatch = Saldo komt niet overeen met het grootboek
+mod-log-user = Gebruiker
+mod-log-actor = Door
+mod-log-amount = Bedrag
+mod-log-fees = Kosten
+mod-log-transaction = Transactie
+mod-log-destination = Bestemming
+mod-log-operation = Operatie
+mod-log-recipients = Ontvangers
+mod-log-tip-id = Tip-id
+mod-log-balance = Saldo
+mod-log-ledger = Grootboek
+
+# Help
+help-title = Help: { $category }
+help-footer = Pagina { $page } van { $pages }. Typ `/help <commando>` voor meer informatie over een commando.
+help-previous = Vorige
+help-next = Volgende
+help-example = Voorbeeld
+help-required = verplicht
+help-optional = optioneel
+help-unknown-command = Er is geen commando `{ $command }`.